@@ -0,0 +1,101 @@
+/// Archive canister for the orderbook - keeps finished orders and trades that the
+/// orderbook canister offloads once they pass its retention window
+/// Records are stored as the orderbook's candid-encoded bytes so this canister never
+/// needs to track the orderbook's record types
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk::{init, query, update};
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell};
+use std::cell::RefCell;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RecordKind {
+    Order,
+    Trade,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchiveStats {
+    pub owner: Principal,
+    pub orders: u64,
+    pub trades: u64,
+}
+
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+
+    // Orderbook canister allowed to append records
+    static OWNER: RefCell<StableCell<Principal, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0))),
+            Principal::anonymous(),
+        ).expect("Failed to init owner cell")
+    );
+
+    // Archived orders (with their chunks), keyed by order ID
+    static ORDERS: RefCell<StableBTreeMap<u64, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1))),
+        )
+    );
+
+    // Archived trades, keyed by trade ID
+    static TRADES: RefCell<StableBTreeMap<u64, Vec<u8>, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2))),
+        )
+    );
+}
+
+fn with_records<R>(kind: RecordKind, f: impl FnOnce(&mut StableBTreeMap<u64, Vec<u8>, Memory>) -> R) -> R {
+    match kind {
+        RecordKind::Order => ORDERS.with(|m| f(&mut m.borrow_mut())),
+        RecordKind::Trade => TRADES.with(|m| f(&mut m.borrow_mut())),
+    }
+}
+
+#[init]
+fn init(owner: Principal) {
+    OWNER.with(|cell| {
+        cell.borrow_mut().set(owner).expect("Failed to set owner");
+    });
+}
+
+/// Store (id, bytes) records - re-appending an ID overwrites it, so the owner can safely
+/// retry a batch whose reply it never saw
+/// Returns the number of records stored
+#[update]
+fn append_records(kind: RecordKind, records: Vec<(u64, Vec<u8>)>) -> Result<u64, String> {
+    let owner = OWNER.with(|cell| *cell.borrow().get());
+    if ic_cdk::api::msg_caller() != owner {
+        return Err("Only the orderbook canister can append records".to_string());
+    }
+
+    let count = records.len() as u64;
+    with_records(kind, |map| {
+        for (id, bytes) in records {
+            map.insert(id, bytes);
+        }
+    });
+
+    Ok(count)
+}
+
+#[query]
+fn get_record(kind: RecordKind, id: u64) -> Option<Vec<u8>> {
+    with_records(kind, |map| map.get(&id))
+}
+
+#[query]
+fn get_stats() -> ArchiveStats {
+    ArchiveStats {
+        owner: OWNER.with(|cell| *cell.borrow().get()),
+        orders: ORDERS.with(|m| m.borrow().len()),
+        trades: TRADES.with(|m| m.borrow().len()),
+    }
+}
+
+ic_cdk::export_candid!();
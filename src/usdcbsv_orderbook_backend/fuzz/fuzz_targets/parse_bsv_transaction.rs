@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use usdcbsv_orderbook_backend::fuzzing;
+
+// Raw bytes are hex-encoded first so the fuzzer explores transaction structure, not hex decoding
+fuzz_target!(|data: &[u8]| {
+    let _ = fuzzing::parse_bsv_transaction(&hex::encode(data));
+});
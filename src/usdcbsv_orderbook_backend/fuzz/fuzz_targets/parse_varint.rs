@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use usdcbsv_orderbook_backend::fuzzing;
+
+fuzz_target!(|data: &[u8]| {
+    // First byte picks the start offset, the rest is the buffer
+    let Some((&start, bytes)) = data.split_first() else {
+        return;
+    };
+    if let Ok((_, next)) = fuzzing::parse_varint(bytes, start as usize) {
+        assert!(next <= bytes.len());
+    }
+});
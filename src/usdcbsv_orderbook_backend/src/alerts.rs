@@ -0,0 +1,285 @@
+/// Operator alerts - critical events are pushed to an admin-configured sink (a canister method
+/// or an HTTPS webhook) instead of waiting for someone to poll get_admin_events
+/// Raising an alert only queues it; the scheduler tick delivers the outbox within
+/// SCHEDULER_TICK_SECONDS, retrying a failed delivery up to ALERT_MAX_ATTEMPTS times. The outbox
+/// lives on the heap, so alerts still queued at an upgrade are lost - the admin events behind them are kept
+use candid::Principal;
+use ic_cdk::api::call::CallResult;
+use ic_cdk::api::management_canister::http_request::{
+    CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs, TransformContext,
+};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use crate::config::{
+    ALERT_LOW_CYCLES, ALERT_MAX_ATTEMPTS, ALERT_PENALTY_SPIKE_COUNT, ALERT_PENALTY_SPIKE_WINDOW_SECONDS,
+    ALERT_SYNC_FAILURES, MAX_ALERT_METHOD_LENGTH, MAX_ALERT_URL_LENGTH, MAX_PENDING_ALERTS,
+};
+use crate::runtime::AlertSender;
+use crate::state::*;
+use crate::types::*;
+
+struct PendingAlert {
+    alert: Alert,
+    attempts: u32,
+}
+
+#[derive(Default)]
+struct AlertState {
+    outbox: VecDeque<PendingAlert>,
+    next_alert_id: u64,
+    low_cycles_alerted: bool,   // Set until the balance recovers, so a low balance alerts once
+    delivered: u64,
+    dropped: u64,
+    last_error: Option<String>,
+}
+
+thread_local! {
+    static ALERTS: RefCell<AlertState> = RefCell::new(AlertState::default());
+}
+
+pub fn set_alert_sink(sink: Option<AlertSink>) -> Result<(), EasySwapError> {
+    match &sink {
+        Some(AlertSink::Canister { canister_id, method }) => {
+            if *canister_id == Principal::anonymous() || *canister_id == crate::runtime::canister_id() {
+                return Err(EasySwapError::invalid_input("Alert canister must be another canister"));
+            }
+            if method.is_empty() || method.len() > MAX_ALERT_METHOD_LENGTH {
+                return Err(EasySwapError::invalid_input(format!(
+                    "Alert method must be 1 to {} characters", MAX_ALERT_METHOD_LENGTH
+                )));
+            }
+        }
+        Some(AlertSink::Webhook { url }) if !url.starts_with("https://") || url.len() > MAX_ALERT_URL_LENGTH => {
+            return Err(EasySwapError::invalid_input(format!(
+                "Alert webhook must be an https:// URL of at most {} characters", MAX_ALERT_URL_LENGTH
+            )));
+        }
+        Some(AlertSink::Webhook { .. }) | None => {}
+    }
+    crate::state::set_alert_sink(sink);
+    Ok(())
+}
+
+pub fn get_alert_status() -> AlertStatus {
+    ALERTS.with(|alerts| {
+        let alerts = alerts.borrow();
+        AlertStatus {
+            sink: get_alert_sink(),
+            pending: alerts.outbox.len() as u64,
+            delivered: alerts.delivered,
+            dropped: alerts.dropped,
+            last_error: alerts.last_error.clone(),
+        }
+    })
+}
+
+/// Queue an alert for the sink - nothing happens while no sink is set
+pub fn raise(kind: AlertKind, admin_event_id: Option<u64>) {
+    if get_alert_sink().is_none() {
+        return;
+    }
+    ic_cdk::println!("🚨 Alert: {:?}", kind);
+    ALERTS.with(|alerts| {
+        let mut alerts = alerts.borrow_mut();
+        let alert = Alert { id: alerts.next_alert_id, kind, admin_event_id, raised_at: get_time() };
+        alerts.next_alert_id += 1;
+        if alerts.outbox.len() >= MAX_PENDING_ALERTS {
+            alerts.outbox.pop_front();
+            alerts.dropped += 1;
+        }
+        alerts.outbox.push_back(PendingAlert { alert, attempts: 0 });
+    });
+}
+
+/// Called for every new admin event - header insertion failures alert at once, timeout
+/// penalties when ALERT_PENALTY_SPIKE_COUNT land within the window
+pub fn on_admin_event(event_id: u64, event_type: &AdminEventType) {
+    match event_type {
+        AdminEventType::BlockInsertionError { block_height, error_message } => raise(
+            AlertKind::BlockInsertionError { block_height: *block_height, error_message: error_message.clone() },
+            Some(event_id),
+        ),
+        AdminEventType::PenaltyApplied { .. } => {
+            let window_ns = ALERT_PENALTY_SPIKE_WINDOW_SECONDS * 1_000_000_000;
+            let recent = AdminEventFilter {
+                kinds: Some(vec![AdminEventKind::PenaltyApplied]),
+                from_time: Some(get_time().saturating_sub(window_ns)),
+                to_time: None,
+            };
+            // Alert as the count reaches the threshold, not on every penalty after it
+            let (penalties, _) = query_admin_events(&recent, None, ALERT_PENALTY_SPIKE_COUNT as usize + 1);
+            if penalties.len() as u64 == ALERT_PENALTY_SPIKE_COUNT {
+                raise(
+                    AlertKind::PenaltySpike { penalties: ALERT_PENALTY_SPIKE_COUNT, window_seconds: ALERT_PENALTY_SPIKE_WINDOW_SECONDS },
+                    Some(event_id),
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Called by the scheduler after each failed block sync
+pub fn on_block_sync_failed(consecutive_failures: u32, error: &str) {
+    if consecutive_failures == ALERT_SYNC_FAILURES {
+        raise(AlertKind::BlockSyncFailing { consecutive_failures, last_error: error.to_string() }, None);
+    }
+}
+
+/// Called every scheduler tick - alerts once as the balance drops below ALERT_LOW_CYCLES
+pub fn check_cycles(balance: u128) {
+    let low = balance < ALERT_LOW_CYCLES;
+    let newly_low = ALERTS.with(|alerts| {
+        let mut alerts = alerts.borrow_mut();
+        let newly_low = low && !alerts.low_cycles_alerted;
+        alerts.low_cycles_alerted = low;
+        newly_low
+    });
+    if newly_low {
+        raise(AlertKind::LowCycles { balance, threshold: ALERT_LOW_CYCLES }, None);
+    }
+}
+
+pub fn has_pending() -> bool {
+    ALERTS.with(|alerts| !alerts.borrow().outbox.is_empty())
+}
+
+/// Send everything in the outbox - failures go back in until they run out of attempts
+/// The outbox is taken up front, so overlapping runs never send an alert twice
+pub async fn deliver_pending() {
+    let pending: Vec<PendingAlert> = ALERTS.with(|alerts| alerts.borrow_mut().outbox.drain(..).collect());
+    let Some(sink) = get_alert_sink() else {
+        return;
+    };
+
+    for mut pending in pending {
+        let result = crate::runtime::alert_sender().send_alert(&sink, &pending.alert).await;
+        pending.attempts += 1;
+        ALERTS.with(|alerts| {
+            let mut alerts = alerts.borrow_mut();
+            match result {
+                Ok(()) => alerts.delivered += 1,
+                Err(e) => {
+                    ic_cdk::println!("⚠️ Alert {} delivery failed (attempt {}): {}", pending.alert.id, pending.attempts, e);
+                    alerts.last_error = Some(e);
+                    if pending.attempts < ALERT_MAX_ATTEMPTS {
+                        alerts.outbox.push_back(pending);
+                    } else {
+                        alerts.dropped += 1;
+                    }
+                }
+            }
+        });
+    }
+}
+
+// ===== DELIVERY =====
+
+/// The sink method takes the Alert - whatever it replies is ignored
+pub async fn call_canister(canister_id: Principal, method: &str, alert: &Alert) -> Result<(), String> {
+    let result: CallResult<()> = ic_cdk::call(canister_id, method, (alert,)).await;
+    result.map_err(|(code, msg)| format!("Alert call to {} failed: {:?}: {}", canister_id, code, msg))
+}
+
+/// Every replica sends the request, so receivers should dedupe on the Idempotency-Key header
+pub async fn post_webhook(url: &str, alert: &Alert) -> Result<(), String> {
+    let body = serde_json::json!({
+        "canister_id": crate::runtime::canister_id().to_text(),
+        "alert": alert,
+    });
+    let request = CanisterHttpRequestArgument {
+        url: url.to_string(),
+        method: HttpMethod::POST,
+        body: Some(body.to_string().into_bytes()),
+        max_response_bytes: Some(2_000),
+        transform: Some(TransformContext::from_name("transform_alert_response".to_string(), vec![])),
+        headers: vec![
+            HttpHeader { name: "Content-Type".to_string(), value: "application/json".to_string() },
+            HttpHeader { name: "Idempotency-Key".to_string(), value: format!("{}-{}", alert.raised_at, alert.id) },
+        ],
+    };
+
+    match crate::outcall_retry::http_request_with_backoff(request, 25_000_000_000).await {
+        Ok((response,)) if response.status >= 200u64 && response.status < 300u64 => Ok(()),
+        Ok((response,)) => Err(format!("Alert webhook answered status {}", response.status)),
+        Err((code, msg)) => Err(format!("Alert webhook failed: {:?} - {}", code, msg)),
+    }
+}
+
+/// Only the status matters - webhook bodies and headers differ between replicas
+#[ic_cdk::query]
+fn transform_alert_response(args: TransformArgs) -> HttpResponse {
+    HttpResponse {
+        status: args.response.status,
+        headers: vec![],
+        body: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+
+    fn filler() -> Principal {
+        Principal::from_slice(&[2, 2, 2])
+    }
+
+
+    #[test]
+    fn critical_events_alert_the_configured_sink_once() {
+        let bad_header = AdminEventType::BlockInsertionError { block_height: 7, error_message: "bad header".to_string() };
+        let penalty = AdminEventType::PenaltyApplied {
+            trade_id: 1,
+            order_id: None,
+            filler: filler(),
+            order_maker: None,
+            penalty_amount: 0.5,
+            bsv_txid: None,
+            reason: "timeout".to_string(),
+        };
+
+        // No sink, no alerts
+        create_admin_event(bad_header.clone());
+        assert!(!has_pending());
+
+        let webhook = AlertSink::Webhook { url: "http://alerts.example".to_string() };
+        assert!(set_alert_sink(Some(webhook)).is_err());
+        let sink = AlertSink::Webhook { url: "https://alerts.example/easyswap".to_string() };
+        set_alert_sink(Some(sink.clone())).unwrap();
+
+        let header_event = create_admin_event(bad_header);
+        for _ in 0..ALERT_PENALTY_SPIKE_COUNT + 2 {
+            create_admin_event(penalty.clone());
+        }
+        for failures in 1..=ALERT_SYNC_FAILURES + 1 {
+            on_block_sync_failed(failures, "providers disagree");
+        }
+        check_cycles(ALERT_LOW_CYCLES - 1);
+        check_cycles(ALERT_LOW_CYCLES - 1);
+        block_on(deliver_pending());
+
+        let sent = mock::sent_alerts();
+        assert!(sent.iter().all(|(to, _)| *to == sink));
+        let kinds: Vec<&AlertKind> = sent.iter().map(|(_, alert)| &alert.kind).collect();
+        assert!(matches!(kinds[..], [
+            AlertKind::BlockInsertionError { block_height: 7, .. },
+            AlertKind::PenaltySpike { penalties: ALERT_PENALTY_SPIKE_COUNT, .. },
+            AlertKind::BlockSyncFailing { consecutive_failures: ALERT_SYNC_FAILURES, .. },
+            AlertKind::LowCycles { .. },
+        ]));
+        assert_eq!(sent[0].1.admin_event_id, Some(header_event));
+
+        // A recovered balance re-arms the cycles alert; a down sink is retried, then dropped
+        check_cycles(ALERT_LOW_CYCLES);
+        check_cycles(0);
+        mock::set_alert_sink_down(true);
+        for _ in 0..3 {
+            block_on(deliver_pending());
+        }
+        let status = get_alert_status();
+        assert_eq!((status.pending, status.delivered, status.dropped), (0, 4, 1));
+        assert!(status.last_error.is_some());
+        assert_eq!(mock::sent_alerts().len(), 4);
+    }
+}
@@ -0,0 +1,133 @@
+/// Public API versioning - every endpoint in the .did without a v2_ prefix is v1. v1 endpoints
+/// return the records pinned in v1.rs (USD as float64), whatever storage holds, and
+/// candid_interface_tests fails if one drifts from the shape integrators decode. A changed
+/// signature ships as a v2_ endpoint next to the v1 one, the v1 endpoint is marked Deprecated
+/// here, and get_api_versions lets integrators plan the migration
+use crate::types::*;
+use crate::config::{API_VERSION, FILLER_INCENTIVE_PERCENT};
+
+struct VersionedEndpoint {
+    name: &'static str,
+    version: u32,
+    deprecated_in: Option<u32>,
+    replaced_by: Option<&'static str>,
+    note: Option<&'static str>,
+}
+
+const VERSIONED_ENDPOINTS: &[VersionedEndpoint] = &[
+    VersionedEndpoint {
+        name: "get_orderbook_stats",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_orderbook_stats"),
+        note: Some("USD as float64 - v2 returns micro-USD and the price in cents"),
+    },
+    VersionedEndpoint {
+        name: "get_available_orderbook",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_available_orderbook"),
+        note: Some("USD as float64 - v2 returns micro-USD"),
+    },
+    VersionedEndpoint {
+        name: "get_bsv_price",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_bsv_price"),
+        note: Some("USD per BSV as float64 - v2 returns cents"),
+    },
+    VersionedEndpoint {
+        name: "get_filler_incentive_percent",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_filler_incentive_bps"),
+        note: Some("Percent as float64 - v2 returns basis points"),
+    },
+    VersionedEndpoint {
+        name: "create_order",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_create_order"),
+        note: Some("Always offers the default filler incentive - v2 takes filler_incentive_bps"),
+    },
+    VersionedEndpoint {
+        name: "get_order",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_order"),
+        note: Some("USD as float64 - v2 returns the stored micro-USD amounts"),
+    },
+    VersionedEndpoint {
+        name: "get_my_orders",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_my_orders"),
+        note: Some("USD as float64 - v2 returns the stored micro-USD amounts"),
+    },
+    VersionedEndpoint {
+        name: "get_trade",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_trade"),
+        note: Some("USD as float64 - v2 returns the stored micro-USD amounts"),
+    },
+    VersionedEndpoint {
+        name: "get_my_trades",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_my_trades"),
+        note: Some("USD as float64 - v2 returns the stored micro-USD amounts"),
+    },
+    VersionedEndpoint {
+        name: "get_active_chunks",
+        version: 1,
+        deprecated_in: Some(2),
+        replaced_by: Some("v2_get_active_chunks"),
+        note: Some("USD as float64 - v2 returns micro-USD"),
+    },
+    VersionedEndpoint { name: "v2_get_orderbook_stats", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_available_orderbook", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_bsv_price", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_filler_incentive_bps", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_create_order", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_order", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_my_orders", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_trade", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_my_trades", version: 2, deprecated_in: None, replaced_by: None, note: None },
+    VersionedEndpoint { name: "v2_get_active_chunks", version: 2, deprecated_in: None, replaced_by: None, note: None },
+];
+
+pub fn get_api_versions() -> ApiVersionInfo {
+    ApiVersionInfo {
+        current_version: API_VERSION,
+        supported_versions: (1..=API_VERSION).collect(),
+        endpoints: VERSIONED_ENDPOINTS.iter().map(|endpoint| ApiEndpointInfo {
+            name: endpoint.name.to_string(),
+            version: endpoint.version,
+            status: if endpoint.deprecated_in.is_some() { ApiEndpointStatus::Deprecated } else { ApiEndpointStatus::Stable },
+            deprecated_in: endpoint.deprecated_in,
+            replaced_by: endpoint.replaced_by.map(str::to_string),
+            note: endpoint.note.map(str::to_string),
+        }).collect(),
+    }
+}
+
+// ===== V2 =====
+
+pub fn get_orderbook_stats() -> OrderbookStatsV2 {
+    crate::chunk_allocation::get_orderbook_stats_v2()
+}
+
+pub fn get_available_orderbook() -> UsdE6 {
+    crate::chunk_allocation::get_listed_orderbook_e6()
+}
+
+/// BSV/USD in cents (same oracle and cache as v1 get_bsv_price)
+pub async fn get_bsv_price() -> Result<u64, EasySwapError> {
+    let price = crate::price_oracle::get_bsv_price().await.map_err(EasySwapError::unavailable)?;
+    Ok((price * 100.0).round() as u64)
+}
+
+pub fn get_filler_incentive_bps() -> u64 {
+    FILLER_INCENTIVE_PERCENT
+}
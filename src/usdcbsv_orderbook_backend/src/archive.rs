@@ -0,0 +1,159 @@
+/// Archive offloading - finished orders and trades past their retention window are pushed
+/// to the companion archive canister (src/usdcbsv_orderbook_archive) and only removed here
+/// once the archive has acknowledged them
+/// With no archive canister configured, data_cleanup deletes them as before
+use crate::types::*;
+use crate::state::*;
+use crate::config::{ARCHIVE_BATCH_SIZE, ARCHIVE_BATCH_MAX_BYTES};
+use crate::data_cleanup::{expired_orders, expired_trades};
+use candid::{Encode, Decode, Principal};
+use ic_cdk::api::call::CallResult;
+use ic_stable_structures::Storable;
+
+async fn append_records(archive: Principal, kind: ArchiveRecordKind, records: Vec<(u64, Vec<u8>)>) -> Result<u64, String> {
+    let result: CallResult<(Result<u64, String>,)> =
+        ic_cdk::call(archive, "append_records", (kind, records)).await;
+    match result {
+        Ok((Ok(count),)) => Ok(count),
+        Ok((Err(e),)) => Err(e),
+        Err((code, msg)) => Err(format!("{:?}: {}", code, msg)),
+    }
+}
+
+async fn fetch_record(archive: Principal, kind: ArchiveRecordKind, id: u64) -> Result<Option<Vec<u8>>, EasySwapError> {
+    let result: CallResult<(Option<Vec<u8>>,)> =
+        ic_cdk::call(archive, "get_record", (kind, id)).await;
+    result
+        .map(|(bytes,)| bytes)
+        .map_err(|(code, msg)| EasySwapError::unavailable(format!("Archive canister call failed: {:?}: {}", code, msg)))
+}
+
+/// Orders data_cleanup would drop under the current policy, encoded for the archive
+fn collect_order_batch(now: u64) -> Vec<(u64, Vec<u8>)> {
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0usize;
+    for (order_id, chunk_ids) in expired_orders(now, &get_retention_policy()) {
+        let Some(order) = get_order(order_id) else { continue };
+        let chunks: Vec<Chunk> = chunk_ids.iter().filter_map(|chunk_id| get_chunk(*chunk_id)).collect();
+
+        let bytes = Encode!(&ArchivedOrder { order, chunks, archived_at: Some(now) }).unwrap();
+        if !batch.is_empty() && batch_bytes + bytes.len() > ARCHIVE_BATCH_MAX_BYTES {
+            break;
+        }
+        batch_bytes += bytes.len();
+        batch.push((order_id, bytes));
+        if batch.len() >= ARCHIVE_BATCH_SIZE {
+            break;
+        }
+    }
+    batch
+}
+
+/// Trades data_cleanup would drop under the current policy, encoded for the archive
+fn collect_trade_batch(now: u64) -> Vec<(u64, Vec<u8>)> {
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0usize;
+    for trade_id in expired_trades(now, &get_retention_policy()) {
+        let Some(trade) = get_trade(trade_id) else { continue };
+
+        let bytes = trade.to_bytes().into_owned();
+        if !batch.is_empty() && batch_bytes + bytes.len() > ARCHIVE_BATCH_MAX_BYTES {
+            break;
+        }
+        batch_bytes += bytes.len();
+        batch.push((trade_id, bytes));
+        if batch.len() >= ARCHIVE_BATCH_SIZE {
+            break;
+        }
+    }
+    batch
+}
+
+/// Push one batch of finished orders and one of finished trades to the archive canister,
+/// deleting each batch locally once the archive accepts it
+/// Returns (orders_archived, trades_archived) - (0, 0) when no archive canister is configured
+pub async fn run_archive() -> (u64, u64) {
+    let Some(archive) = get_archive_canister() else {
+        return (0, 0);
+    };
+    let now = get_time();
+
+    let mut orders_archived = 0u64;
+    let order_batch = collect_order_batch(now);
+    if !order_batch.is_empty() {
+        let order_ids: Vec<OrderId> = order_batch.iter().map(|(id, _)| *id).collect();
+        match append_records(archive, ArchiveRecordKind::Order, order_batch).await {
+            Ok(_) => {
+                for order_id in order_ids {
+                    // Chunks and status can't have moved on a finished order, but re-read after the await anyway
+                    let Some(order) = get_order(order_id) else { continue };
+                    ORDERS.with(|orders| {
+                        orders.borrow_mut().remove(&order_id);
+                    });
+                    CHUNKS.with(|chunks| {
+                        let mut chunks_mut = chunks.borrow_mut();
+                        for chunk_id in &order.chunks {
+                            chunks_mut.remove(chunk_id);
+                        }
+                    });
+                    orders_archived += 1;
+                }
+            }
+            Err(e) => ic_cdk::println!("⚠️ Archiving orders failed, keeping them for the next run: {}", e),
+        }
+    }
+
+    let mut trades_archived = 0u64;
+    let trade_batch = collect_trade_batch(now);
+    if !trade_batch.is_empty() {
+        let trade_ids: Vec<TradeId> = trade_batch.iter().map(|(id, _)| *id).collect();
+        match append_records(archive, ArchiveRecordKind::Trade, trade_batch).await {
+            Ok(_) => {
+                for trade_id in &trade_ids {
+                    if remove_trade(*trade_id).is_some() {
+                        trades_archived += 1;
+                    }
+                }
+            }
+            Err(e) => ic_cdk::println!("⚠️ Archiving trades failed, keeping them for the next run: {}", e),
+        }
+    }
+
+    if orders_archived > 0 || trades_archived > 0 {
+        ic_cdk::println!("📦 Archived {} orders and {} trades to {}", orders_archived, trades_archived, archive);
+    }
+
+    (orders_archived, trades_archived)
+}
+
+/// Look an order up here first and fall back to the archive canister
+pub async fn get_archived_order(order_id: OrderId) -> Result<Option<ArchivedOrder>, EasySwapError> {
+    if let Some(order) = get_order(order_id) {
+        let chunks = order.chunks.iter().filter_map(|chunk_id| get_chunk(*chunk_id)).collect();
+        return Ok(Some(ArchivedOrder { order, chunks, archived_at: None }));
+    }
+
+    let Some(archive) = get_archive_canister() else {
+        return Ok(None);
+    };
+    let Some(bytes) = fetch_record(archive, ArchiveRecordKind::Order, order_id).await? else {
+        return Ok(None);
+    };
+    let archived = Decode!(&bytes, ArchivedOrder)
+        .map_err(|e| format!("Failed to decode archived order {}: {}", order_id, e))?;
+    Ok(Some(archived))
+}
+
+/// Look a trade up here first and fall back to the archive canister
+pub async fn get_archived_trade(trade_id: TradeId) -> Result<Option<Trade>, EasySwapError> {
+    if let Some(trade) = get_trade(trade_id) {
+        return Ok(Some(trade));
+    }
+
+    let Some(archive) = get_archive_canister() else {
+        return Ok(None);
+    };
+    let bytes = fetch_record(archive, ArchiveRecordKind::Trade, trade_id).await?;
+    // Trade's Storable decoder also handles records written before later schema changes
+    Ok(bytes.map(|bytes| Trade::from_bytes(bytes.into())))
+}
@@ -0,0 +1,318 @@
+/// Full-state backup and restore for disaster recovery
+/// A backup is a candid-encoded snapshot of every stable map's raw key/value bytes, held on
+/// the heap until the next backup/upgrade and downloaded chunk by chunk. Restore only runs
+/// on a fresh canister: chunks are uploaded (each checked against the manifest), then the
+/// whole snapshot hash is verified before anything is written
+use candid::{CandidType, Deserialize, Encode, Decode, Principal};
+use ic_stable_structures::memory_manager::VirtualMemory;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, Storable};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use crate::types::*;
+use crate::state::*;
+use crate::block_headers::{CompactHeader, BLOCK_HEADERS, TESTNET_BLOCK_HEADERS};
+use crate::config::{BACKUP_CHUNK_SIZE_BYTES, BACKUP_FORMAT_VERSION};
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+#[derive(CandidType, Deserialize)]
+struct BackupSection {
+    name: String,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,  // Storable key bytes, Storable value bytes
+}
+
+#[derive(CandidType, Deserialize)]
+struct Snapshot {
+    format_version: u32,
+    schema_version: u32,
+    app_state: Vec<u8>,
+    sections: Vec<BackupSection>,
+}
+
+struct PendingRestore {
+    manifest: BackupManifest,
+    chunks: BTreeMap<u64, Vec<u8>>,
+}
+
+thread_local! {
+    static LATEST_BACKUP: RefCell<Option<(BackupManifest, Vec<u8>)>> = RefCell::new(None);
+    static PENDING_RESTORE: RefCell<Option<PendingRestore>> = RefCell::new(None);
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn dump_map<K, V>(name: &str, map: &StableBTreeMap<K, V, Memory>) -> BackupSection
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+{
+    BackupSection {
+        name: name.to_string(),
+        entries: map.iter()
+            .map(|(k, v)| (k.to_bytes().into_owned(), v.to_bytes().into_owned()))
+            .collect(),
+    }
+}
+
+fn load_map<K, V>(map: &mut StableBTreeMap<K, V, Memory>, entries: Vec<(Vec<u8>, Vec<u8>)>) -> u64
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+{
+    let count = entries.len() as u64;
+    for (k, v) in entries {
+        map.insert(K::from_bytes(Cow::Owned(k)), V::from_bytes(Cow::Owned(v)));
+    }
+    count
+}
+
+/// Snapshot all stable maps and keep it for download via get_backup_chunk
+/// Replaces any previous backup held in memory
+pub fn create_backup() -> BackupManifest {
+    let sections = vec![
+        ORDERS.with(|m| dump_map("orders", &m.borrow())),
+        CHUNKS.with(|m| dump_map("chunks", &m.borrow())),
+        TRADES.with(|m| dump_map("trades", &m.borrow())),
+        FILLER_ACCOUNTS.with(|m| dump_map("filler_accounts", &m.borrow())),
+        USED_BSV_TXIDS.with(|m| dump_map("used_bsv_txids", &m.borrow())),
+        ADMIN_EVENTS.with(|m| dump_map("admin_events", &m.borrow())),
+        BLOCK_HEADERS.with(|m| dump_map("block_headers", &m.borrow())),
+        DEPOSIT_RECOVERIES.with(|m| dump_map("deposit_recoveries", &m.borrow())),
+        DISPUTES.with(|m| dump_map("disputes", &m.borrow())),
+        PENALTY_APPEALS.with(|m| dump_map("penalty_appeals", &m.borrow())),
+        LATE_CLAIMS.with(|m| dump_map("late_claims", &m.borrow())),
+        MAKER_QUIET_HOURS.with(|m| dump_map("maker_quiet_hours", &m.borrow())),
+        SETTLEMENT_REPORTS.with(|m| dump_map("settlement_reports", &m.borrow())),
+        MARKET_STATS.with(|m| dump_map("market_stats", &m.borrow())),
+        PRICE_CANDLES.with(|m| dump_map("price_candles", &m.borrow())),
+        RECONCILIATION_ISSUES.with(|m| dump_map("reconciliation_issues", &m.borrow())),
+        ETH_WITHDRAWALS.with(|m| dump_map("eth_withdrawals", &m.borrow())),
+        WITHDRAWAL_ROUTES.with(|m| dump_map("withdrawal_routes", &m.borrow())),
+        QUOTES.with(|m| dump_map("quotes", &m.borrow())),
+        SCHEDULED_TASKS.with(|m| dump_map("scheduled_tasks", &m.borrow())),
+        PENDING_TRANSFERS.with(|m| dump_map("pending_transfers", &m.borrow())),
+        TRANSFER_DEDUP.with(|m| dump_map("transfer_dedup", &m.borrow())),
+        APPROVED_FILLERS.with(|m| dump_map("approved_fillers", &m.borrow())),
+        MAKER_FILLER_BLOCKLISTS.with(|m| dump_map("maker_filler_blocklists", &m.borrow())),
+        ORDERBOOK_CHANGES.with(|m| dump_map("orderbook_changes", &m.borrow())),
+        CORRUPT_RECORDS.with(|m| dump_map("corrupt_records", &m.borrow())),
+        TX_BLOBS.with(|m| dump_map("tx_blobs", &m.borrow())),
+        DELEGATIONS.with(|m| dump_map("delegations", &m.borrow())),
+        RELEASE_BONDS.with(|m| dump_map("release_bonds", &m.borrow())),
+        VERIFIED_INCLUSIONS.with(|m| dump_map("verified_inclusions", &m.borrow())),
+        MAKER_AUTO_TOP_UPS.with(|m| dump_map("maker_auto_top_ups", &m.borrow())),
+        REBATE_EPOCHS.with(|m| dump_map("rebate_epochs", &m.borrow())),
+        MAKER_REBATES.with(|m| dump_map("maker_rebates", &m.borrow())),
+        BOOKS.with(|m| dump_map("books", &m.borrow())),
+        TESTNET_BLOCK_HEADERS.with(|m| dump_map("testnet_block_headers", &m.borrow())),
+    ];
+
+    let section_counts: Vec<BackupSectionCount> = sections.iter()
+        .map(|s| BackupSectionCount { section: s.name.clone(), entries: s.entries.len() as u64 })
+        .collect();
+
+    let snapshot = Snapshot {
+        format_version: BACKUP_FORMAT_VERSION,
+        schema_version: get_schema_state().version,
+        app_state: APP_STATE.with(|cell| cell.borrow().get().to_bytes().into_owned()),
+        sections,
+    };
+    let data = Encode!(&snapshot).expect("Failed to encode backup snapshot");
+
+    let now = get_time();
+    let chunk_hashes: Vec<String> = data.chunks(BACKUP_CHUNK_SIZE_BYTES).map(sha256_hex).collect();
+    let manifest = BackupManifest {
+        backup_id: now,
+        format_version: BACKUP_FORMAT_VERSION,
+        schema_version: snapshot.schema_version,
+        created_at: now,
+        total_bytes: data.len() as u64,
+        chunk_size: BACKUP_CHUNK_SIZE_BYTES as u64,
+        chunk_count: chunk_hashes.len() as u64,
+        sha256: sha256_hex(&data),
+        chunk_hashes,
+        sections: section_counts,
+    };
+
+    ic_cdk::println!(
+        "💾 Backup {} created: {} bytes in {} chunks",
+        manifest.backup_id, manifest.total_bytes, manifest.chunk_count
+    );
+
+    LATEST_BACKUP.with(|b| *b.borrow_mut() = Some((manifest.clone(), data)));
+    manifest
+}
+
+/// One chunk of the backup currently held in memory
+pub fn get_backup_chunk(backup_id: u64, index: u64) -> Result<BackupChunk, String> {
+    LATEST_BACKUP.with(|b| {
+        let backup = b.borrow();
+        let (manifest, data) = backup.as_ref()
+            .filter(|(m, _)| m.backup_id == backup_id)
+            .ok_or_else(|| format!("Backup {} is not available - create a new backup", backup_id))?;
+
+        if index >= manifest.chunk_count {
+            return Err(format!("Chunk index {} out of range (backup has {} chunks)", index, manifest.chunk_count));
+        }
+
+        let start = index as usize * BACKUP_CHUNK_SIZE_BYTES;
+        let end = (start + BACKUP_CHUNK_SIZE_BYTES).min(data.len());
+        Ok(BackupChunk {
+            backup_id,
+            index,
+            data: data[start..end].to_vec(),
+        })
+    })
+}
+
+/// A restore would overwrite live data unless the canister has never held any
+fn is_fresh_canister() -> bool {
+    let next_order_id = APP_STATE.with(|cell| cell.borrow().get().next_order_id);
+    next_order_id == 0
+        && ORDERS.with(|m| m.borrow().is_empty())
+        && TRADES.with(|m| m.borrow().is_empty())
+        && FILLER_ACCOUNTS.with(|m| m.borrow().is_empty())
+}
+
+/// Upload backup chunks; once all chunks of the manifest are in, verify and apply the snapshot
+/// Can be called repeatedly with the same manifest and further chunks
+pub fn restore_backup(manifest: BackupManifest, chunks: Vec<BackupChunk>) -> Result<RestoreStatus, String> {
+    if !is_fresh_canister() {
+        return Err("Restore is only allowed into a fresh canister with no orders, trades or filler accounts".to_string());
+    }
+    if manifest.format_version != BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported backup format v{} (this build reads v{})",
+            manifest.format_version, BACKUP_FORMAT_VERSION
+        ));
+    }
+    if manifest.chunk_hashes.len() as u64 != manifest.chunk_count {
+        return Err("Manifest chunk hashes don't match chunk count".to_string());
+    }
+
+    PENDING_RESTORE.with(|p| {
+        let mut pending = p.borrow_mut();
+        // A different manifest starts over
+        if pending.as_ref().is_none_or(|r| r.manifest != manifest) {
+            *pending = Some(PendingRestore { manifest: manifest.clone(), chunks: BTreeMap::new() });
+        }
+        let restore = pending.as_mut().unwrap();
+
+        for chunk in chunks {
+            if chunk.backup_id != manifest.backup_id {
+                return Err(format!("Chunk belongs to backup {}, not {}", chunk.backup_id, manifest.backup_id));
+            }
+            let expected = manifest.chunk_hashes.get(chunk.index as usize)
+                .ok_or_else(|| format!("Chunk index {} out of range", chunk.index))?;
+            if &sha256_hex(&chunk.data) != expected {
+                return Err(format!("Chunk {} failed integrity check", chunk.index));
+            }
+            restore.chunks.insert(chunk.index, chunk.data);
+        }
+        Ok(())
+    })?;
+
+    let received = PENDING_RESTORE.with(|p| p.borrow().as_ref().map_or(0, |r| r.chunks.len() as u64));
+    if received < manifest.chunk_count {
+        return Ok(RestoreStatus {
+            backup_id: manifest.backup_id,
+            chunks_received: received,
+            chunk_count: manifest.chunk_count,
+            completed: false,
+            entries_restored: 0,
+        });
+    }
+
+    let data: Vec<u8> = PENDING_RESTORE.with(|p| {
+        p.borrow_mut().take()
+            .map(|r| r.chunks.into_values().flatten().collect())
+            .unwrap_or_default()
+    });
+
+    if data.len() as u64 != manifest.total_bytes || sha256_hex(&data) != manifest.sha256 {
+        return Err("Snapshot failed integrity check - upload all chunks again".to_string());
+    }
+
+    let snapshot = Decode!(&data, Snapshot)
+        .map_err(|e| format!("Failed to decode snapshot: {}", e))?;
+
+    let entries_restored = apply_snapshot(snapshot);
+
+    ic_cdk::println!("♻️  Backup {} restored: {} entries", manifest.backup_id, entries_restored);
+    create_admin_event(AdminEventType::BackupRestored {
+        backup_id: manifest.backup_id,
+        entries: entries_restored,
+    });
+
+    Ok(RestoreStatus {
+        backup_id: manifest.backup_id,
+        chunks_received: received,
+        chunk_count: manifest.chunk_count,
+        completed: true,
+        entries_restored,
+    })
+}
+
+fn apply_snapshot(snapshot: Snapshot) -> u64 {
+    let mut restored = 0;
+
+    for section in snapshot.sections {
+        let entries = section.entries;
+        restored += match section.name.as_str() {
+            "orders" => ORDERS.with(|m| load_map::<OrderId, Order>(&mut m.borrow_mut(), entries)),
+            "chunks" => CHUNKS.with(|m| load_map::<ChunkId, Chunk>(&mut m.borrow_mut(), entries)),
+            "trades" => TRADES.with(|m| load_map::<TradeId, Trade>(&mut m.borrow_mut(), entries)),
+            "filler_accounts" => FILLER_ACCOUNTS.with(|m| load_map::<Principal, FillerAccount>(&mut m.borrow_mut(), entries)),
+            "used_bsv_txids" => USED_BSV_TXIDS.with(|m| load_map::<TxidKey, TradeIdValue>(&mut m.borrow_mut(), entries)),
+            "admin_events" => ADMIN_EVENTS.with(|m| load_map::<u64, AdminEvent>(&mut m.borrow_mut(), entries)),
+            "block_headers" => BLOCK_HEADERS.with(|m| load_map::<u64, CompactHeader>(&mut m.borrow_mut(), entries)),
+            "deposit_recoveries" => DEPOSIT_RECOVERIES.with(|m| load_map::<u64, DepositRecovery>(&mut m.borrow_mut(), entries)),
+            "disputes" => DISPUTES.with(|m| load_map::<TradeId, TradeDispute>(&mut m.borrow_mut(), entries)),
+            "penalty_appeals" => PENALTY_APPEALS.with(|m| load_map::<TradeId, PenaltyAppeal>(&mut m.borrow_mut(), entries)),
+            "late_claims" => LATE_CLAIMS.with(|m| load_map::<TradeId, LateClaim>(&mut m.borrow_mut(), entries)),
+            "maker_quiet_hours" => MAKER_QUIET_HOURS.with(|m| load_map::<Principal, MakerQuietHours>(&mut m.borrow_mut(), entries)),
+            "settlement_reports" => SETTLEMENT_REPORTS.with(|m| load_map::<u64, DailySettlementReport>(&mut m.borrow_mut(), entries)),
+            "market_stats" => MARKET_STATS.with(|m| load_map::<u64, HourlyMarketStats>(&mut m.borrow_mut(), entries)),
+            "price_candles" => PRICE_CANDLES.with(|m| load_map::<u64, PriceCandle>(&mut m.borrow_mut(), entries)),
+            "reconciliation_issues" => RECONCILIATION_ISSUES.with(|m| load_map::<OrderId, ReconciliationIssue>(&mut m.borrow_mut(), entries)),
+            "eth_withdrawals" => ETH_WITHDRAWALS.with(|m| load_map::<u64, EthWithdrawal>(&mut m.borrow_mut(), entries)),
+            "withdrawal_routes" => WITHDRAWAL_ROUTES.with(|m| load_map::<String, WithdrawalRoute>(&mut m.borrow_mut(), entries)),
+            "quotes" => QUOTES.with(|m| load_map::<QuoteId, Quote>(&mut m.borrow_mut(), entries)),
+            "scheduled_tasks" => SCHEDULED_TASKS.with(|m| load_map::<String, ScheduledTask>(&mut m.borrow_mut(), entries)),
+            "pending_transfers" => PENDING_TRANSFERS.with(|m| load_map::<u64, PendingTransfer>(&mut m.borrow_mut(), entries)),
+            "transfer_dedup" => TRANSFER_DEDUP.with(|m| load_map::<Vec<u8>, u64>(&mut m.borrow_mut(), entries)),
+            "approved_fillers" => APPROVED_FILLERS.with(|m| load_map::<Principal, u64>(&mut m.borrow_mut(), entries)),
+            "maker_filler_blocklists" => MAKER_FILLER_BLOCKLISTS.with(|m| load_map::<Principal, MakerFillerBlocklist>(&mut m.borrow_mut(), entries)),
+            "orderbook_changes" => ORDERBOOK_CHANGES.with(|m| load_map::<u64, OrderbookChange>(&mut m.borrow_mut(), entries)),
+            "corrupt_records" => CORRUPT_RECORDS.with(|m| load_map::<u64, CorruptRecord>(&mut m.borrow_mut(), entries)),
+            "tx_blobs" => TX_BLOBS.with(|m| load_map::<TxidKey, TxBlob>(&mut m.borrow_mut(), entries)),
+            "delegations" => DELEGATIONS.with(|m| load_map::<Principal, OperatorDelegation>(&mut m.borrow_mut(), entries)),
+            "release_bonds" => RELEASE_BONDS.with(|m| load_map::<TradeId, ReleaseBond>(&mut m.borrow_mut(), entries)),
+            "verified_inclusions" => VERIFIED_INCLUSIONS.with(|m| load_map::<TxidKey, VerifiedInclusion>(&mut m.borrow_mut(), entries)),
+            "maker_auto_top_ups" => MAKER_AUTO_TOP_UPS.with(|m| load_map::<Principal, MakerAutoTopUp>(&mut m.borrow_mut(), entries)),
+            "rebate_epochs" => REBATE_EPOCHS.with(|m| load_map::<u64, RebateEpoch>(&mut m.borrow_mut(), entries)),
+            "maker_rebates" => MAKER_REBATES.with(|m| load_map::<Principal, MakerRebates>(&mut m.borrow_mut(), entries)),
+            "books" => BOOKS.with(|m| load_map::<BookId, OrderBook>(&mut m.borrow_mut(), entries)),
+            "testnet_block_headers" => TESTNET_BLOCK_HEADERS.with(|m| load_map::<u64, CompactHeader>(&mut m.borrow_mut(), entries)),
+            other => {
+                ic_cdk::println!("⚠️  Skipping unknown backup section '{}'", other);
+                0
+            }
+        };
+    }
+
+    rebuild_admin_event_index();
+
+    let app_state = AppState::from_bytes(Cow::Owned(snapshot.app_state));
+    APP_STATE.with(|cell| cell.borrow_mut().set(app_state).expect("Failed to restore app state"));
+
+    // Records are in the backup's schema - bring them up to this build's version
+    update_schema_state(|s| s.version = snapshot.schema_version);
+    crate::migrations::run_pending_migrations();
+
+    restored
+}
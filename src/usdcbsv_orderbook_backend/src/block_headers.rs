@@ -1,10 +1,11 @@
-use candid::{CandidType, Deserialize, Encode, Decode};
+use candid::{CandidType, Deserialize};
 use ic_cdk::api::management_canister::http_request::{
     http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
     TransformContext,
 };
 use crate::state::{create_admin_event, MEMORY_MANAGER};
-use crate::types::AdminEventType;
+use crate::types::{AdminEventType, BsvNetwork};
+use crate::record_envelope::{encode_record, decode_stored, VersionedRecord};
 use serde::{Serialize};
 use serde_json;
 use std::cell::RefCell;
@@ -40,24 +41,116 @@ pub struct BlocksWithMetadata {
     pub total_count: u64,
 }
 
-// Implement Storable for BlockHeader to use in stable storage
+// Storable for the legacy header map (MemoryId 7) and headers in older backups
 impl Storable for BlockHeader {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).expect("Failed to encode BlockHeader"))
+        Cow::Owned(encode_record(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).expect("Failed to decode BlockHeader")
+        decode_stored(&bytes)
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
+impl VersionedRecord for BlockHeader {}
+
+/// A stored block header - just the 80 serialized bytes, keyed by height
+/// Every BlockHeader field is parsed back out of them and the hash recomputed, so a header
+/// costs 80 bytes instead of a candid record with four hex strings
+#[derive(Clone, Debug)]
+pub struct CompactHeader(pub [u8; 80]);
+
+/// 32 bytes of a display (reversed) hex hash in header byte order
+fn internal_hash_bytes(display_hex: &str) -> Result<[u8; 32], String> {
+    let mut bytes: [u8; 32] = hex::decode(display_hex)
+        .map_err(|e| format!("Invalid hash hex: {}", e))?
+        .try_into()
+        .map_err(|_| "Hash is not 32 bytes".to_string())?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+fn display_hex(bytes: &[u8]) -> String {
+    let mut bytes = bytes.to_vec();
+    bytes.reverse();
+    hex::encode(bytes)
+}
+
+impl CompactHeader {
+    /// The header's raw bytes when it has them, otherwise serialized from its fields
+    /// (WhatsOnChain headers come without raw bytes)
+    pub fn from_header(header: &BlockHeader) -> Result<Self, String> {
+        if let Ok(raw) = hex::decode(&header.raw_header) {
+            if let Ok(raw) = <[u8; 80]>::try_from(raw) {
+                return Ok(CompactHeader(raw));
+            }
+        }
+
+        let mut raw = [0u8; 80];
+        raw[0..4].copy_from_slice(&header.version.to_le_bytes());
+        raw[4..36].copy_from_slice(&internal_hash_bytes(&header.previous_hash)?);
+        raw[36..68].copy_from_slice(&internal_hash_bytes(&header.merkle_root)?);
+        raw[68..72].copy_from_slice(&(header.timestamp as u32).to_le_bytes());
+        raw[72..76].copy_from_slice(&header.bits.to_le_bytes());
+        raw[76..80].copy_from_slice(&header.nonce.to_le_bytes());
+        Ok(CompactHeader(raw))
+    }
+
+    pub fn to_header(&self, height: u64) -> BlockHeader {
+        let raw = &self.0;
+        let u32_at = |offset: usize| u32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap());
+        BlockHeader {
+            height,
+            hash: display_hex(&Sha256::digest(Sha256::digest(raw))),
+            previous_hash: display_hex(&raw[4..36]),
+            merkle_root: display_hex(&raw[36..68]),
+            timestamp: u32_at(68) as u64,
+            bits: u32_at(72),
+            nonce: u32_at(76),
+            version: u32_at(0) as i32,
+            raw_header: hex::encode(raw),
+        }
+    }
+}
+
+impl Storable for CompactHeader {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    /// Backups taken before compaction hold candid BlockHeader records
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        match <[u8; 80]>::try_from(bytes.as_ref()) {
+            Ok(raw) => CompactHeader(raw),
+            Err(_) => CompactHeader::from_header(&decode_stored::<BlockHeader>(&bytes))
+                .unwrap_or_else(|e| panic!("Stored block header failed to compact: {}", e)),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded { max_size: 80, is_fixed_size: true };
+}
+
 type Memory = ic_stable_structures::memory_manager::VirtualMemory<ic_stable_structures::DefaultMemoryImpl>;
 
 // Stable block storage - persists across upgrades
 thread_local! {
-    pub(crate) static BLOCK_HEADERS: RefCell<StableBTreeMap<u64, BlockHeader, Memory>> = RefCell::new(
+    pub(crate) static BLOCK_HEADERS: RefCell<StableBTreeMap<u64, CompactHeader, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(29))),
+        )
+    );
+    
+    // Testnet headers, for deployments following BSV testnet - never mixed with mainnet ones
+    pub(crate) static TESTNET_BLOCK_HEADERS: RefCell<StableBTreeMap<u64, CompactHeader, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(35))),
+        )
+    );
+    
+    // Headers as full candid records, from before compaction - emptied by schema migration 3
+    pub(crate) static LEGACY_BLOCK_HEADERS: RefCell<StableBTreeMap<u64, BlockHeader, Memory>> = RefCell::new(
         StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7))),
         )
@@ -67,6 +160,14 @@ thread_local! {
     static BLOCK_METADATA: RefCell<(u64, u64)> = RefCell::new((0, 0)); // (highest_block, last_sync_time)
 }
 
+/// Run `f` on the header store of the BSV network the canister follows
+pub(crate) fn with_headers<R>(f: impl FnOnce(&RefCell<StableBTreeMap<u64, CompactHeader, Memory>>) -> R) -> R {
+    match crate::state::get_bsv_network() {
+        BsvNetwork::Mainnet => BLOCK_HEADERS.with(f),
+        BsvNetwork::Testnet => TESTNET_BLOCK_HEADERS.with(f),
+    }
+}
+
 /// Get the most recent N blocks in descending order (newest first)
 /// Limited to max 100 blocks to prevent abuse
 /// Returns blocks with metadata about storage range
@@ -74,13 +175,13 @@ pub fn get_recent_blocks(count: u64) -> BlocksWithMetadata {
     // Limit maximum blocks returned to prevent DoS
     let safe_count = count.min(100);
     
-    BLOCK_HEADERS.with(|headers| {
+    with_headers(|headers| {
         let headers_map = headers.borrow();
         let blocks: Vec<BlockHeader> = headers_map
             .iter()
             .rev() // Start from highest blocks
             .take(safe_count as usize)
-            .map(|(_, block)| block)
+            .map(|(height, block)| block.to_header(height))
             .collect();
         
         // Get storage range
@@ -96,25 +197,39 @@ pub fn get_recent_blocks(count: u64) -> BlocksWithMetadata {
     })
 }
 
+/// Stored headers from from_height to to_height inclusive, ascending - at most 100
+pub fn get_block_headers_range(from_height: u64, to_height: u64) -> Vec<BlockHeader> {
+    if from_height > to_height {
+        return Vec::new();
+    }
+    with_headers(|headers| {
+        headers.borrow()
+            .range(from_height..=to_height)
+            .take(100)
+            .map(|(height, block)| block.to_header(height))
+            .collect()
+    })
+}
+
 /// Get block header by height
 pub fn get_block_by_height(height: u64) -> Option<BlockHeader> {
-    BLOCK_HEADERS.with(|headers| headers.borrow().get(&height))
+    with_headers(|headers| headers.borrow().get(&height).map(|block| block.to_header(height)))
 }
 
 /// Get block header by hash - O(n) scan through all blocks
 pub fn get_block_by_hash(hash: &str) -> Option<BlockHeader> {
-    BLOCK_HEADERS.with(|headers| {
+    with_headers(|headers| {
         headers.borrow()
             .iter()
-            .find(|(_, block)| block.hash == hash)
-            .map(|(_, block)| block)
+            .map(|(height, block)| block.to_header(height))
+            .find(|block| block.hash == hash)
     })
 }
 
 /// Get highest stored block height
 /// Scans the actual stable storage to find the highest block
 pub fn get_highest_block() -> u64 {
-    BLOCK_HEADERS.with(|headers| {
+    with_headers(|headers| {
         headers.borrow()
             .last_key_value()
             .map(|(height, _)| height)
@@ -131,11 +246,20 @@ fn set_highest_block(height: u64) {
 }
 
 /// Store a block header in stable storage
+/// A header that can't be serialized (malformed hash hex) is logged and not stored - chain
+/// validation then reports the gap
 pub fn store_block(header: BlockHeader) {
     let height = header.height;
+    let compact = match CompactHeader::from_header(&header) {
+        Ok(compact) => compact,
+        Err(e) => {
+            ic_cdk::println!("❌ Block {} not stored: {}", height, e);
+            return;
+        }
+    };
 
-    BLOCK_HEADERS.with(|headers| {
-        headers.borrow_mut().insert(height, header);
+    with_headers(|headers| {
+        headers.borrow_mut().insert(height, compact);
     });
 
     // Update highest block if needed
@@ -147,7 +271,7 @@ pub fn store_block(header: BlockHeader) {
 
 /// Remove blocks from a certain height onwards (for reorg handling)
 pub fn remove_blocks_from(height: u64) {
-    BLOCK_HEADERS.with(|headers| {
+    with_headers(|headers| {
         let mut headers_map = headers.borrow_mut();
         let heights_to_remove: Vec<u64> = headers_map
             .range(height..)
@@ -276,12 +400,12 @@ pub fn should_sync(current_time: u64) -> bool {
 
 /// Get block count
 pub fn get_block_count() -> usize {
-    BLOCK_HEADERS.with(|headers| headers.borrow().len() as usize)
+    with_headers(|headers| headers.borrow().len() as usize)
 }
 
 /// Get height range of stored blocks
 pub fn get_stored_range() -> (u64, u64) {
-    BLOCK_HEADERS.with(|headers| {
+    with_headers(|headers| {
         let map = headers.borrow();
         if map.is_empty() {
             (0, 0)
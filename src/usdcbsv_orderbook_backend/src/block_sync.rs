@@ -1,10 +1,59 @@
 use crate::block_headers::{BlockHeader, CONFIRMATION_DEPTH};
-use candid::{CandidType, Deserialize, Principal};
+use candid::{CandidType, Deserialize};
 use ic_cdk::api::management_canister::http_request::{
-    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
+    CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs,
     TransformContext,
 };
 use serde_json::{json, Value};
+use crate::outcall_retry::http_request_with_backoff;
+use crate::types::ApiProvider;
+
+/// Request headers for a provider outcall, including its API key if one is configured
+/// Keys only travel in the request - transform functions never see request headers
+pub fn provider_headers(provider: ApiProvider) -> Vec<HttpHeader> {
+    let header_name = match provider {
+        ApiProvider::WhatsOnChain => crate::config::WOC_API_KEY_HEADER,
+        ApiProvider::Bitails => crate::config::BITAILS_API_KEY_HEADER,
+    };
+    
+    match crate::state::get_provider_api_key(&provider) {
+        Some(key) => vec![HttpHeader {
+            name: header_name.to_string(),
+            value: key,
+        }],
+        None => vec![],
+    }
+}
+
+/// Validate and store a provider API key (None clears it and falls back to the free tier)
+/// Note: Admin check is enforced in lib.rs before calling this function
+pub fn set_provider_api_key(provider: ApiProvider, api_key: Option<String>) -> Result<(), String> {
+    let api_key = api_key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
+    
+    if let Some(ref key) = api_key {
+        if key.len() > crate::config::MAX_API_KEY_LENGTH {
+            return Err(format!("API key too long (max {} characters)", crate::config::MAX_API_KEY_LENGTH));
+        }
+        // Must be a valid HTTP header value
+        if !key.chars().all(|c| c.is_ascii_graphic()) {
+            return Err("API key must contain only printable ASCII characters".to_string());
+        }
+    }
+    
+    ic_cdk::println!("🔑 {:?} API key {}", provider, if api_key.is_some() { "set" } else { "cleared" });
+    crate::state::set_provider_api_key(&provider, api_key);
+    Ok(())
+}
+
+/// WoC endpoint on the network the canister follows
+fn woc_url(path: &str) -> String {
+    format!("{}{}", crate::state::get_bsv_network().woc_api(), path)
+}
+
+/// Bitails endpoint on the network the canister follows
+fn bitails_url(path: &str) -> String {
+    format!("{}{}", crate::state::get_bsv_network().bitails_api(), path)
+}
 
 // Response structures
 #[derive(Debug, Clone, CandidType, Deserialize)]
@@ -15,7 +64,7 @@ pub struct BlockInfo {
 
 /// Fetch tip height from WoC
 pub async fn fetch_woc_tip() -> Result<BlockInfo, String> {
-    let url = "https://api.whatsonchain.com/v1/bsv/main/chain/info";
+    let url = woc_url("/chain/info");
     
     let request = CanisterHttpRequestArgument {
         url: url.to_string(),
@@ -26,10 +75,10 @@ pub async fn fetch_woc_tip() -> Result<BlockInfo, String> {
             "transform_http_response".to_string(),
             vec![],
         )),
-        headers: vec![],
+        headers: provider_headers(ApiProvider::WhatsOnChain),
     };
 
-    match http_request(request, 25_000_000_000).await {
+    match http_request_with_backoff(request, 25_000_000_000).await {
         Ok((response,)) => {
             if response.status != 200u64 {
                 return Err(format!("WoC API error: status {}", response.status));
@@ -58,7 +107,7 @@ pub async fn fetch_woc_tip() -> Result<BlockInfo, String> {
 
 /// Fetch tip height from Bitails using block list endpoint
 pub async fn fetch_bitails_tip() -> Result<BlockInfo, String> {
-    let url = "https://api.bitails.io/block/list?skip=0&limit=1&sort=height&direction=desc";
+    let url = bitails_url("/block/list?skip=0&limit=1&sort=height&direction=desc");
     
     let request = CanisterHttpRequestArgument {
         url: url.to_string(),
@@ -69,10 +118,10 @@ pub async fn fetch_bitails_tip() -> Result<BlockInfo, String> {
             "transform_http_response".to_string(),
             vec![],
         )),
-        headers: vec![],
+        headers: provider_headers(ApiProvider::Bitails),
     };
 
-    match http_request(request, 25_000_000_000).await {
+    match http_request_with_backoff(request, 25_000_000_000).await {
         Ok((response,)) => {
             if response.status != 200u64 {
                 return Err(format!("Bitails API error: status {}", response.status));
@@ -106,10 +155,7 @@ pub async fn fetch_bitails_tip() -> Result<BlockInfo, String> {
 
 /// Fetch block header by hash from WoC
 pub async fn fetch_woc_block_header(block_hash: &str) -> Result<BlockHeader, String> {
-    let url = format!(
-        "https://api.whatsonchain.com/v1/bsv/main/block/hash/{}/header",
-        block_hash
-    );
+    let url = woc_url(&format!("/block/hash/{}/header", block_hash));
     
     let request = CanisterHttpRequestArgument {
         url: url.to_string(),
@@ -120,10 +166,10 @@ pub async fn fetch_woc_block_header(block_hash: &str) -> Result<BlockHeader, Str
             "transform_http_response".to_string(),
             vec![],
         )),
-        headers: vec![],
+        headers: provider_headers(ApiProvider::WhatsOnChain),
     };
 
-    match http_request(request, 25_000_000_000).await {
+    match http_request_with_backoff(request, 25_000_000_000).await {
         Ok((response,)) => {
             if response.status != 200u64 {
                 return Err(format!("WoC API error: status {}", response.status));
@@ -143,10 +189,7 @@ pub async fn fetch_woc_block_header(block_hash: &str) -> Result<BlockHeader, Str
 
 /// Fetch block header by height from WoC
 pub async fn fetch_woc_block_header_by_height(height: u64) -> Result<BlockHeader, String> {
-    let url = format!(
-        "https://api.whatsonchain.com/v1/bsv/main/block/height/{}",
-        height
-    );
+    let url = woc_url(&format!("/block/height/{}", height));
     
     let request = CanisterHttpRequestArgument {
         url: url.to_string(),
@@ -157,10 +200,10 @@ pub async fn fetch_woc_block_header_by_height(height: u64) -> Result<BlockHeader
             "transform_http_response".to_string(),
             vec![],
         )),
-        headers: vec![],
+        headers: provider_headers(ApiProvider::WhatsOnChain),
     };
 
-    match http_request(request, 25_000_000_000).await {
+    match http_request_with_backoff(request, 25_000_000_000).await {
         Ok((response,)) => {
             if response.status != 200u64 {
                 return Err(format!("WoC API error: status {}", response.status));
@@ -193,10 +236,7 @@ pub async fn fetch_bitails_block_header(height: u64) -> Result<BlockHeader, Stri
 /// skip: number of blocks to skip from tip (0 = latest block)
 /// count: number of blocks to fetch
 pub async fn fetch_bitails_blocks_batch(skip: u64, count: u64) -> Result<Vec<BlockHeader>, String> {
-    let url = format!(
-        "https://api.bitails.io/block/list?skip={}&limit={}&sort=height&direction=desc",
-        skip, count
-    );
+    let url = bitails_url(&format!("/block/list?skip={}&limit={}&sort=height&direction=desc", skip, count));
     
     let request = CanisterHttpRequestArgument {
         url: url.to_string(),
@@ -207,10 +247,10 @@ pub async fn fetch_bitails_blocks_batch(skip: u64, count: u64) -> Result<Vec<Blo
             "transform_http_response".to_string(),
             vec![],
         )),
-        headers: vec![],
+        headers: provider_headers(ApiProvider::Bitails),
     };
 
-    match http_request(request, 25_000_000_000).await {
+    match http_request_with_backoff(request, 25_000_000_000).await {
         Ok((response,)) => {
             if response.status != 200u64 {
                 return Err(format!("Bitails API error: status {}", response.status));
@@ -446,6 +486,47 @@ pub async fn find_consensus_tip() -> Result<ConsensusResult, String> {
     Err("WoC and Bitails disagree - waiting for them to sync up".to_string())
 }
 
+/// Fetch a raw transaction hex from WoC (used to look up the values of spent outputs)
+pub async fn fetch_woc_raw_tx(txid: &str) -> Result<String, String> {
+    let url = woc_url(&format!("/tx/{}/hex", txid));
+    
+    let request = CanisterHttpRequestArgument {
+        url,
+        method: HttpMethod::GET,
+        body: None,
+        max_response_bytes: Some(crate::config::MAX_PARENT_TX_RESPONSE_BYTES),
+        transform: Some(TransformContext::from_name(
+            "transform_raw_tx_response".to_string(),
+            vec![],
+        )),
+        headers: provider_headers(ApiProvider::WhatsOnChain),
+    };
+
+    match http_request_with_backoff(request, 25_000_000_000).await {
+        Ok((response,)) => {
+            if response.status != 200u64 {
+                return Err(format!("WoC API error: status {}", response.status));
+            }
+
+            let raw_hex = String::from_utf8(response.body)
+                .map_err(|e| format!("Failed to parse response body: {}", e))?;
+
+            Ok(raw_hex.trim().to_string())
+        }
+        Err((code, msg)) => Err(format!("HTTP request failed: {:?} - {}", code, msg)),
+    }
+}
+
+/// Transform for raw transaction hex - the body is immutable, only headers differ between replicas
+#[ic_cdk::query]
+fn transform_raw_tx_response(args: TransformArgs) -> HttpResponse {
+    HttpResponse {
+        status: args.response.status,
+        headers: vec![],
+        body: args.response.body,
+    }
+}
+
 /// Transform function for HTTP responses (required by ICP)
 /// CRITICAL: Must produce IDENTICAL output on all replicas for consensus
 /// Extracts only immutable blockchain fields in deterministic order
@@ -584,8 +665,7 @@ struct TxArchiveBlockInfo {
 
 /// Fetch a single block from TxArchive by height
 async fn fetch_block_from_txarchive(height: u64) -> Result<TxArchiveBlockInfo, String> {
-    let txarchive_canister = Principal::from_text("glgze-4qaaa-aaaac-a4m2a-cai")
-        .map_err(|e| format!("Invalid TxArchive principal: {}", e))?;
+    let txarchive_canister = crate::state::get_txarchive_policy().canister;
     
     let (response,): (TxArchiveBlockInfo,) = ic_cdk::call(
         txarchive_canister,
@@ -732,6 +812,10 @@ async fn find_consensus_tip_with_txarchive_fallback(
     woc_tip: Option<BlockInfo>,
     bitails_tip: Option<BlockInfo>,
 ) -> Result<BlockInfo, String> {
+    if !crate::state::get_txarchive_policy().enabled {
+        return Err("Header API unavailable and TxArchive fallback is disabled".to_string());
+    }
+
     // Determine which API is working (if any)
     let working_api_tip = woc_tip.as_ref().or(bitails_tip.as_ref());
     
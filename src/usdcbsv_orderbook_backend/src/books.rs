@@ -0,0 +1,175 @@
+/// Orderbooks - each book settles in one token against the canister's BSV network and has its own orders,
+/// matching, limits and stats. Book 0 is the original ckUSDC book on whichever network the
+/// canister follows; it exists without being stored until admin changes its limits. Orders
+/// carry their book, and their funds move on its settlement ledger (see order_ledger)
+use candid::Principal;
+use std::collections::HashSet;
+use crate::types::*;
+use crate::state::*;
+use crate::config::MAX_ORDERBOOK_USD_LIMIT_E6;
+use crate::ckusdc_integration::CKUSDC_LEDGER_CANISTER_ID;
+
+pub const DEFAULT_BOOK_ID: BookId = 0;
+
+pub fn ckusdc_ledger() -> Principal {
+    Principal::from_text(CKUSDC_LEDGER_CANISTER_ID).expect("valid ckUSDC ledger ID")
+}
+
+fn default_book() -> OrderBook {
+    OrderBook {
+        id: DEFAULT_BOOK_ID,
+        settlement_symbol: "ckUSDC".to_string(),
+        settlement_ledger: ckusdc_ledger(),
+        bsv_network: get_bsv_network(),
+        enabled: true,
+        max_orderbook_usd_e6: None,
+        trade_size_limits: None,
+        created_at: 0,
+    }
+}
+
+/// The default book follows the canister's network, even once its limits are stored
+fn with_network(mut book: OrderBook) -> OrderBook {
+    if book.id == DEFAULT_BOOK_ID {
+        book.bsv_network = get_bsv_network();
+    }
+    book
+}
+
+pub fn get_book(book_id: BookId) -> Option<OrderBook> {
+    get_stored_book(book_id).map(with_network).or_else(|| (book_id == DEFAULT_BOOK_ID).then(default_book))
+}
+
+/// Every book, by ID
+pub fn get_books() -> Vec<OrderBook> {
+    let mut books: Vec<OrderBook> = get_stored_books().into_iter().map(with_network).collect();
+    if books.first().is_none_or(|book| book.id != DEFAULT_BOOK_ID) {
+        books.insert(0, default_book());
+    }
+    books
+}
+
+/// The book an endpoint's optional book_id names - None = DEFAULT_BOOK_ID
+pub fn resolve(book_id: Option<BookId>) -> Result<OrderBook, EasySwapError> {
+    let book_id = book_id.unwrap_or(DEFAULT_BOOK_ID);
+    get_book(book_id).ok_or_else(|| EasySwapError::not_found(format!("Book {} not found", book_id)))
+}
+
+/// Ledger an order's funds move on, including while it is being created - ckUSDC for orders
+/// of the default book and for subaccounts no order owns
+pub fn order_ledger(order_id: OrderId) -> Principal {
+    get_order(order_id)
+        .map(|order| order.book_id())
+        .or_else(|| crate::order_management::book_in_creation(order_id))
+        .and_then(get_book)
+        .map(|book| book.settlement_ledger)
+        .unwrap_or_else(ckusdc_ledger)
+}
+
+/// Security deposits are ckUSDC, so only these books can top up orders from them
+pub fn settles_in_ckusdc(book: &OrderBook) -> bool {
+    book.settlement_ledger == ckusdc_ledger()
+}
+
+pub fn trade_size_limits(book: &OrderBook) -> TradeSizeLimits {
+    book.trade_size_limits.clone().unwrap_or_else(get_trade_size_limits)
+}
+
+pub fn max_orderbook_usd_e6(book: &OrderBook) -> UsdE6 {
+    book.max_orderbook_usd_e6.unwrap_or(MAX_ORDERBOOK_USD_LIMIT_E6)
+}
+
+/// USD in the book's Available chunks, hidden ones included
+pub fn available_e6(book_id: BookId) -> UsdE6 {
+    get_available_chunks().iter()
+        .filter(|chunk| chunk.book_id() == book_id)
+        .map(|chunk| chunk.amount_usd_e6)
+        .sum()
+}
+
+/// Open a book for another settlement token on the network the canister follows - no two
+/// books share a token. Returns the new book's ID
+pub fn add_book(settlement_symbol: String, settlement_ledger: Principal) -> Result<BookId, EasySwapError> {
+    if settlement_symbol.trim().is_empty() {
+        return Err(EasySwapError::invalid_input("Book needs a settlement token symbol"));
+    }
+    let books = get_books();
+    if let Some(existing) = books.iter().find(|book| book.settlement_ledger == settlement_ledger) {
+        return Err(EasySwapError::invalid_input(format!(
+            "Book {} already settles {}",
+            existing.id, existing.settlement_symbol
+        )));
+    }
+
+    let book_id = books.iter().map(|book| book.id).max().unwrap_or(DEFAULT_BOOK_ID) + 1;
+    insert_book(OrderBook {
+        id: book_id,
+        settlement_symbol,
+        settlement_ledger,
+        bsv_network: get_bsv_network(),
+        enabled: true,
+        max_orderbook_usd_e6: None,
+        trade_size_limits: None,
+        created_at: get_time(),
+    });
+    Ok(book_id)
+}
+
+/// Set a book's own limits, or close it to new orders - its token and network are fixed
+pub fn update_book(
+    book_id: BookId,
+    enabled: bool,
+    max_orderbook_usd_e6: Option<UsdE6>,
+    trade_size_limits: Option<TradeSizeLimits>,
+) -> Result<(), EasySwapError> {
+    let mut book = get_book(book_id).ok_or_else(|| EasySwapError::not_found(format!("Book {} not found", book_id)))?;
+    if let Some(limits) = &trade_size_limits {
+        crate::trade_lifecycle::validate_trade_size_limits(limits)?;
+    }
+
+    book.enabled = enabled;
+    book.max_orderbook_usd_e6 = max_orderbook_usd_e6;
+    book.trade_size_limits = trade_size_limits;
+    insert_book(book);
+    Ok(())
+}
+
+pub fn get_book_stats(book_id: Option<BookId>) -> Result<BookStats, EasySwapError> {
+    let book = resolve(book_id)?;
+    let mut stats = BookStats {
+        book_id: book.id,
+        settlement_symbol: book.settlement_symbol,
+        total_orders: 0,
+        open_orders: 0,
+        total_active_chunks: 0,
+        total_available_usd_e6: 0,
+        total_locked_usd_e6: 0,
+        total_filled_usd_e6: 0,
+        total_trades: 0,
+    };
+
+    let mut order_ids = HashSet::new();
+    for order in get_all_orders().into_iter().filter(|order| order.book_id() == book.id) {
+        stats.total_orders += 1;
+        if matches!(order.status, OrderStatus::Active | OrderStatus::Idle) {
+            stats.open_orders += 1;
+        }
+        stats.total_filled_usd_e6 += order.total_filled_usd_e6;
+        order_ids.insert(order.id);
+    }
+    CHUNKS.with(|chunks| {
+        for (_, chunk) in chunks.borrow().iter().filter(|(_, chunk)| chunk.book_id() == book.id) {
+            if chunk.is_listed() {
+                stats.total_active_chunks += 1;
+                stats.total_available_usd_e6 += chunk.amount_usd_e6;
+            } else if chunk.status == ChunkStatus::Locked {
+                stats.total_locked_usd_e6 += chunk.amount_usd_e6;
+            }
+        }
+    });
+    stats.total_trades = TRADES.with(|trades| {
+        trades.borrow().iter().filter(|(_, trade)| order_ids.contains(&trade.order_id)).count() as u64
+    });
+
+    Ok(stats)
+}
@@ -1,6 +1,15 @@
 use crate::types::*;
+use crate::config::MAX_BSV_TX_BYTES;
+
+// Smallest possible input (outpoint + empty script + sequence) and output (value + empty script)
+const MIN_INPUT_BYTES: u64 = 41;
+const MIN_OUTPUT_BYTES: u64 = 9;
 
 pub fn parse_bsv_transaction(raw_hex: &str) -> Result<ParsedBsvTx, String> {
+    if raw_hex.len() > MAX_BSV_TX_BYTES * 2 {
+        return Err(format!("Transaction too large (max {} bytes)", MAX_BSV_TX_BYTES));
+    }
+    
     let bytes = hex::decode(raw_hex)
         .map_err(|e| format!("Failed to decode hex: {}", e))?;
     
@@ -15,6 +24,7 @@ pub fn parse_bsv_transaction(raw_hex: &str) -> Result<ParsedBsvTx, String> {
     
     // Parse inputs
     let input_count = read_varint(&bytes, &mut cursor)?;
+    check_item_count(&bytes, cursor, input_count, MIN_INPUT_BYTES, "inputs")?;
     let mut inputs = Vec::new();
     
     for _ in 0..input_count {
@@ -24,6 +34,7 @@ pub fn parse_bsv_transaction(raw_hex: &str) -> Result<ParsedBsvTx, String> {
     
     // Parse outputs
     let output_count = read_varint(&bytes, &mut cursor)?;
+    check_item_count(&bytes, cursor, output_count, MIN_OUTPUT_BYTES, "outputs")?;
     let mut outputs = Vec::new();
     
     for _ in 0..output_count {
@@ -49,6 +60,7 @@ pub fn parse_bsv_transaction(raw_hex: &str) -> Result<ParsedBsvTx, String> {
         inputs,
         outputs,
         locktime,
+        size_bytes: bytes.len(),
     })
 }
 
@@ -62,7 +74,7 @@ fn parse_input(bytes: &[u8], cursor: &mut usize) -> Result<BsvInput, String> {
     
     // Script signature length
     let script_len = read_varint(bytes, cursor)?;
-    let script_sig = read_bytes(bytes, cursor, script_len as usize)?;
+    let script_sig = read_bytes(bytes, cursor, script_len)?;
     
     // Sequence (4 bytes, little-endian)
     let sequence = read_u32_le(bytes, cursor)?;
@@ -81,7 +93,9 @@ fn parse_output(bytes: &[u8], cursor: &mut usize) -> Result<BsvOutput, String> {
     
     // Script pub key length
     let script_len = read_varint(bytes, cursor)?;
-    let script_pubkey = read_bytes(bytes, cursor, script_len as usize)?;
+    let script_pubkey = read_bytes(bytes, cursor, script_len)?;
+    
+    let script_type = classify_output_script(&script_pubkey);
     
     // Extract address from script
     let address = extract_address_from_script(&script_pubkey)?;
@@ -89,24 +103,72 @@ fn parse_output(bytes: &[u8], cursor: &mut usize) -> Result<BsvOutput, String> {
     Ok(BsvOutput {
         address,
         satoshis,
+        script_type,
+        script_pubkey,
     })
 }
 
-fn extract_address_from_script(script: &[u8]) -> Result<String, String> {
-    if script.is_empty() {
-        return Err("Empty script".to_string());
+fn classify_output_script(script: &[u8]) -> OutputScriptType {
+    if script.len() == 25
+        && script[0] == 0x76
+        && script[1] == 0xa9
+        && script[2] == 0x14
+        && script[23] == 0x88
+        && script[24] == 0xac
+    {
+        return OutputScriptType::P2PKH;
+    }
+    
+    if script.len() == 23 && script[0] == 0xa9 && script[1] == 0x14 && script[22] == 0x87 {
+        return OutputScriptType::P2SH;
+    }
+    
+    if script.first() == Some(&0x6a) || script.starts_with(&[0x00, 0x6a]) {
+        return OutputScriptType::OpReturn;
+    }
+    
+    OutputScriptType::Other
+}
+
+/// Decode an address of the network the canister follows to the script type that pays it and
+/// its 20-byte hash. New orders only take P2PKH addresses, but orders created before P2SH ones
+/// were refused keep them, so their chunks are still paid with P2SH outputs
+fn address_to_hash160(address: &str) -> Result<(OutputScriptType, Vec<u8>), String> {
+    use sha2::{Sha256, Digest};
+    
+    let decoded = bs58::decode(address.trim())
+        .into_vec()
+        .map_err(|e| format!("Invalid address {}: {}", address, e))?;
+    
+    if decoded.len() != 25 {
+        return Err(format!("Invalid address {}: wrong length", address));
     }
+    let network = crate::state::get_bsv_network();
+    let script_type = if decoded[0] == network.p2pkh_version() {
+        OutputScriptType::P2PKH
+    } else if decoded[0] == network.p2sh_version() {
+        OutputScriptType::P2SH
+    } else {
+        return Err(format!("Address {} is not a BSV {:?} address", address, network));
+    };
     
-    // P2PKH: OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
-    if script.len() == 25 && script[0] == 0x76 && script[1] == 0xa9 && script[2] == 0x14 {
-        let pubkey_hash = &script[3..23];
-        return encode_base58_check(pubkey_hash, 0x00); // Mainnet P2PKH prefix
+    let checksum = &Sha256::digest(&Sha256::digest(&decoded[..21]))[..4];
+    if checksum != &decoded[21..] {
+        return Err(format!("Invalid address {}: bad checksum", address));
     }
     
-    // P2SH: OP_HASH160 <20 bytes> OP_EQUAL
-    if script.len() == 23 && script[0] == 0xa9 && script[1] == 0x14 {
-        let script_hash = &script[2..22];
-        return encode_base58_check(script_hash, 0x05); // Mainnet P2SH prefix
+    Ok((script_type, decoded[1..21].to_vec()))
+}
+
+fn extract_address_from_script(script: &[u8]) -> Result<String, String> {
+    let network = crate::state::get_bsv_network();
+    match classify_output_script(script) {
+        // P2PKH: OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+        OutputScriptType::P2PKH => return encode_base58_check(&script[3..23], network.p2pkh_version()),
+        // P2SH: OP_HASH160 <20 bytes> OP_EQUAL
+        OutputScriptType::P2SH => return encode_base58_check(&script[2..22], network.p2sh_version()),
+        OutputScriptType::OpReturn => return Ok("OP_RETURN".to_string()),
+        OutputScriptType::Other => {}
     }
     
     // Unknown script type - return hex representation (valid for non-standard scripts)
@@ -131,10 +193,23 @@ fn encode_base58_check(payload: &[u8], version: u8) -> Result<String, String> {
 
 // Helper functions for reading bytes
 
-fn read_bytes(bytes: &[u8], cursor: &mut usize, len: usize) -> Result<Vec<u8>, String> {
-    if *cursor + len > bytes.len() {
+/// Reject a count that couldn't fit in the remaining data even if every item were minimal,
+/// before looping over it
+fn check_item_count(bytes: &[u8], cursor: usize, count: u64, min_item_bytes: u64, what: &str) -> Result<(), String> {
+    let remaining = bytes.len().saturating_sub(cursor) as u64;
+    if count > remaining / min_item_bytes {
+        return Err(format!("Transaction claims {} {} but only {} bytes remain", count, what, remaining));
+    }
+    Ok(())
+}
+
+/// Lengths come from varints, so they're checked as u64 - a cast to usize could truncate on wasm32
+fn read_bytes(bytes: &[u8], cursor: &mut usize, len: u64) -> Result<Vec<u8>, String> {
+    let remaining = bytes.len().saturating_sub(*cursor) as u64;
+    if len > remaining {
         return Err("Unexpected end of data".to_string());
     }
+    let len = len as usize;
     let result = bytes[*cursor..*cursor + len].to_vec();
     *cursor += len;
     Ok(result)
@@ -183,6 +258,9 @@ fn read_varint(bytes: &[u8], cursor: &mut usize) -> Result<u64, String> {
     match first_byte {
         0..=0xfc => Ok(first_byte as u64),
         0xfd => {
+            if *cursor + 2 > bytes.len() {
+                return Err("Unexpected end of data reading varint".to_string());
+            }
             let value = u16::from_le_bytes([bytes[*cursor], bytes[*cursor + 1]]);
             *cursor += 2;
             Ok(value as u64)
@@ -197,13 +275,51 @@ fn read_varint(bytes: &[u8], cursor: &mut usize) -> Result<u64, String> {
     }
 }
 
-pub fn validate_transaction_outputs(
-    parsed_tx: &ParsedBsvTx,
-    expected_outputs: &[LockedChunk],
-) -> Result<(), String> {
+/// One maker output a payment must contain
+struct ExpectedOutput<'a> {
+    bsv_address: &'a str,
+    sats: u64,
+    allowed: u64,   // Deviation allowed by the trade's sats tolerance (exact when it has none)
+}
+
+/// One output per locked chunk of `trades`, in order
+fn per_chunk_outputs<'a>(trades: &[&'a Trade]) -> Vec<ExpectedOutput<'a>> {
+    trades.iter()
+        .flat_map(|trade| trade.locked_chunks.iter().map(move |chunk| ExpectedOutput {
+            bsv_address: &chunk.bsv_address,
+            sats: chunk.sats_amount,
+            allowed: trade.sats_tolerance.as_ref().map_or(0, |t| t.allowed_deviation(chunk.sats_amount)),
+        }))
+        .collect()
+}
+
+/// Chunk outputs merged per address, in order of first appearance - the template layout
+fn per_address_outputs<'a>(per_chunk: &[ExpectedOutput<'a>]) -> Vec<ExpectedOutput<'a>> {
+    let mut merged: Vec<ExpectedOutput<'a>> = Vec::new();
+    for output in per_chunk {
+        match merged.iter_mut().find(|m| m.bsv_address == output.bsv_address) {
+            Some(m) => {
+                m.sats += output.sats;
+                m.allowed += output.allowed;
+            }
+            None => merged.push(ExpectedOutput { ..*output }),
+        }
+    }
+    merged
+}
+
+/// Canonical maker outputs for paying `trades` in one transaction (see get_trade_payment_template)
+pub fn payment_template_outputs(trades: &[&Trade]) -> Vec<PaymentOutput> {
+    per_address_outputs(&per_chunk_outputs(trades)).into_iter()
+        .map(|output| PaymentOutput { bsv_address: output.bsv_address.to_string(), sats: output.sats })
+        .collect()
+}
+
+/// Check the leading outputs of the transaction against `expected`, in order
+fn check_maker_outputs(parsed_tx: &ParsedBsvTx, expected_outputs: &[ExpectedOutput]) -> Result<(), String> {
     ic_cdk::println!("🔍 BSV TX VALIDATION DEBUG:");
     ic_cdk::println!("  Transaction has {} outputs", parsed_tx.outputs.len());
-    ic_cdk::println!("  Expected {} outputs (locked chunks)", expected_outputs.len());
+    ic_cdk::println!("  Expected {} maker outputs", expected_outputs.len());
     
     // Must have at least as many outputs as expected
     if parsed_tx.outputs.len() < expected_outputs.len() {
@@ -219,30 +335,34 @@ pub fn validate_transaction_outputs(
         let actual = &parsed_tx.outputs[i];
         
         ic_cdk::println!("\n  ✅ Validating output #{}", i);
-        ic_cdk::println!("    Chunk ID: {}", expected.chunk_id);
         ic_cdk::println!("    Expected Address: {}", expected.bsv_address);
-        ic_cdk::println!("    Actual Address:   {}", actual.address);
-        ic_cdk::println!("    Expected Satoshis: {} sats", expected.sats_amount);
+        ic_cdk::println!("    Actual Address:   {} ({:?})", actual.address, actual.script_type);
+        ic_cdk::println!("    Expected Satoshis: {} sats", expected.sats);
         ic_cdk::println!("    Actual Satoshis:   {} sats", actual.satoshis);
         
-        // Check satoshi amount matches (LockedChunk has required sats_amount)
-        let expected_sats = expected.sats_amount;
-        if actual.satoshis != expected_sats {
-            ic_cdk::println!("    ❌ AMOUNT MISMATCH!");
+        let (expected_type, expected_hash160) = address_to_hash160(expected.bsv_address)?;
+        if actual.script_type != expected_type {
+            ic_cdk::println!("    ❌ SCRIPT TYPE MISMATCH!");
             return Err(format!(
-                "Output {} amount mismatch. Expected: {} sats, Got: {} sats",
-                i, expected_sats, actual.satoshis
+                "Output {} script type mismatch. Expected: {:?}, Got: {:?}",
+                i, expected_type, actual.script_type
             ));
         }
         
-        // Check address matches (normalize both addresses for comparison)
-        let actual_addr = actual.address.trim().to_lowercase();
-        let expected_addr = expected.bsv_address.trim().to_lowercase();
-        
-        ic_cdk::println!("    Normalized Expected: {}", expected_addr);
-        ic_cdk::println!("    Normalized Actual:   {}", actual_addr);
+        if actual.satoshis.abs_diff(expected.sats) > expected.allowed {
+            ic_cdk::println!("    ❌ AMOUNT MISMATCH!");
+            return Err(format!(
+                "Output {} amount mismatch. Expected: {} sats (±{}), Got: {} sats",
+                i, expected.sats, expected.allowed, actual.satoshis
+            ));
+        }
         
-        if actual_addr != expected_addr && !actual_addr.contains(&expected_addr) {
+        // Compare the hash in the script, not the rendered address string
+        let actual_hash160 = match actual.script_type {
+            OutputScriptType::P2SH => &actual.script_pubkey[2..22],
+            _ => &actual.script_pubkey[3..23],
+        };
+        if actual_hash160 != &expected_hash160[..] {
             ic_cdk::println!("    ❌ ADDRESS MISMATCH!");
             return Err(format!(
                 "Output {} address mismatch. Expected: {}, Got: {}",
@@ -253,7 +373,259 @@ pub fn validate_transaction_outputs(
         ic_cdk::println!("    ✅ Output #{} validated successfully", i);
     }
     
-    ic_cdk::println!("\n✅ All {} outputs validated successfully!", expected_outputs.len());
+    Ok(())
+}
+
+/// Validate that the transaction pays the locked chunks of `trades` (in order) within each
+/// trade's sats tolerance (exact amounts when a trade has no tolerance recorded)
+/// Maker outputs come first, either one per chunk or one per address totaling its chunks
+/// (the payment template layout), and must be standard P2PKH to the maker's pubkey hash (P2SH
+/// for orders that kept a legacy P2SH address); remaining outputs (change) may not be non-standard scripts or OP_RETURNs carrying value
+/// Returns the number of maker outputs
+pub fn validate_transaction_outputs(parsed_tx: &ParsedBsvTx, trades: &[&Trade]) -> Result<usize, String> {
+    let per_chunk = per_chunk_outputs(trades);
+    let per_address = per_address_outputs(&per_chunk);
+    
+    let maker_outputs = match check_maker_outputs(parsed_tx, &per_chunk) {
+        Ok(()) => per_chunk.len(),
+        // Layouts only differ when an address is paid for more than one chunk
+        Err(_) if per_address.len() < per_chunk.len() && check_maker_outputs(parsed_tx, &per_address).is_ok() => {
+            per_address.len()
+        }
+        Err(e) => return Err(e),
+    };
+    
+    // Remaining outputs are the filler's change - reject scripts that could hide or burn value
+    for (i, output) in parsed_tx.outputs.iter().enumerate().skip(maker_outputs) {
+        match output.script_type {
+            OutputScriptType::Other => {
+                return Err(format!("Output {} has a non-standard script", i));
+            }
+            OutputScriptType::OpReturn if output.satoshis > 0 => {
+                return Err(format!(
+                    "Output {} is an OP_RETURN carrying {} sats",
+                    i, output.satoshis
+                ));
+            }
+            _ => {}
+        }
+    }
+    
+    ic_cdk::println!("\n✅ All {} maker outputs validated successfully!", maker_outputs);
+    
+    Ok(maker_outputs)
+}
+
+/// Check the BSV paid to the maker outputs against the trades' prices, independent of the
+/// stored sats_amount values: the implied USD/BSV price must not exceed the trade's effective
+/// price (maker protection) nor fall below min_bsv_price (filler protection)
+/// Trades paid together are checked on their combined amounts
+/// `maker_outputs` is what validate_transaction_outputs returned for the same trades
+pub fn validate_price_paid(parsed_tx: &ParsedBsvTx, trades: &[&Trade], maker_outputs: usize) -> Result<(), String> {
+    let mut min_sats: u64 = 0;
+    let mut max_sats: u64 = 0;
+    let mut allowed: u64 = 0;
+    let mut total_usd = 0.0;
+    
+    for trade in trades {
+        let (agreed_bsv_price, min_bsv_price) = (trade.agreed_bsv_price, trade.min_bsv_price);
+        if agreed_bsv_price <= 0.0 || min_bsv_price <= 0.0 {
+            return Err(format!("Trade {} has no valid agreed/minimum BSV price", trade.id));
+        }
+        if agreed_bsv_price < min_bsv_price {
+            return Err(format!(
+                "Agreed BSV price ${} is below the filler's minimum ${}",
+                agreed_bsv_price, min_bsv_price
+            ));
+        }
+        
+        let trade_usd = e6_to_usd(trade.locked_chunks.iter().map(|c| c.amount_usd_e6).sum());
+        let sats_at = |price: f64| (trade_usd / price * crate::config::SATOSHIS_PER_BSV as f64) as u64;
+        let trade_min_sats = sats_at(trade.effective_bsv_price());
+        
+        // Per-chunk truncation can lose up to one sat per chunk on top of the trade's tolerance
+        allowed += trade.sats_tolerance.as_ref().map_or(0, |t| t.allowed_deviation(trade_min_sats))
+            + trade.locked_chunks.len() as u64;
+        min_sats += trade_min_sats;
+        max_sats += sats_at(min_bsv_price);
+        total_usd += trade_usd;
+    }
+    
+    // Output values are attacker-chosen - saturate rather than wrap
+    let paid_sats = parsed_tx.outputs.iter()
+        .take(maker_outputs)
+        .fold(0u64, |total, o| total.saturating_add(o.satoshis));
+    
+    if paid_sats.saturating_add(allowed) < min_sats {
+        return Err(format!(
+            "Outputs pay {} sats for ${:.2}, below the {} sats due at the trade price",
+            paid_sats, total_usd, min_sats
+        ));
+    }
+    if paid_sats > max_sats.saturating_add(allowed) {
+        return Err(format!(
+            "Outputs pay {} sats for ${:.2}, above the {} sats due at the filler's minimum price",
+            paid_sats, total_usd, max_sats
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Miner fee rate in sats per kB, given the values of the outputs spent by each input (in input order)
+pub fn calculate_fee_rate(parsed_tx: &ParsedBsvTx, input_values: &[u64]) -> Result<u64, String> {
+    if input_values.len() != parsed_tx.inputs.len() {
+        return Err(format!(
+            "Expected {} input values, got {}",
+            parsed_tx.inputs.len(),
+            input_values.len()
+        ));
+    }
+    
+    let total_in = input_values.iter()
+        .try_fold(0u64, |total, value| total.checked_add(*value))
+        .ok_or("Input values overflow")?;
+    let total_out = parsed_tx.outputs.iter()
+        .try_fold(0u64, |total, o| total.checked_add(o.satoshis))
+        .ok_or("Output values overflow")?;
+    
+    if total_in < total_out {
+        return Err(format!(
+            "Transaction spends more than its inputs. Inputs: {} sats, Outputs: {} sats",
+            total_in, total_out
+        ));
+    }
+    
+    let fee = total_in - total_out;
+    let fee_rate = fee.saturating_mul(1000) / (parsed_tx.size_bytes.max(1) as u64);
+    
+    ic_cdk::println!("⛏️ TX fee: {} sats for {} bytes ({} sats/kB)", fee, parsed_tx.size_bytes, fee_rate);
+    
+    Ok(fee_rate)
+}
+
+/// Reject transactions whose fee rate is too low to confirm before the claim window closes
+pub fn validate_fee_rate(parsed_tx: &ParsedBsvTx, input_values: &[u64], min_sats_per_kb: u64) -> Result<(), String> {
+    let fee_rate = calculate_fee_rate(parsed_tx, input_values)?;
+    
+    if fee_rate < min_sats_per_kb {
+        return Err(format!(
+            "Transaction fee rate too low: {} sats/kB (minimum {} sats/kB). It may not confirm before the claim window closes.",
+            fee_rate, min_sats_per_kb
+        ));
+    }
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use candid::Principal;
+
+    const MAKER_HASH160: [u8; 20] = [0x11; 20];
+
+    fn p2pkh_script(hash160: [u8; 20]) -> Vec<u8> {
+        [&[0x76, 0xa9, 0x14][..], &hash160, &[0x88, 0xac]].concat()
+    }
+
+    fn output(script_pubkey: Vec<u8>, satoshis: u64) -> BsvOutput {
+        BsvOutput {
+            address: extract_address_from_script(&script_pubkey).unwrap(),
+            satoshis,
+            script_type: classify_output_script(&script_pubkey),
+            script_pubkey,
+        }
+    }
+
+    fn parsed_tx(input_count: usize, outputs: Vec<BsvOutput>, size_bytes: usize) -> ParsedBsvTx {
+        let input = BsvInput { prev_tx_hash: vec![0; 32], prev_output_index: 0, script_sig: Vec::new(), sequence: u32::MAX };
+        ParsedBsvTx { version: 1, inputs: vec![input; input_count], outputs, locktime: 0, size_bytes }
+    }
+
+    #[test]
+    fn fee_rate_is_the_fee_per_kb_of_the_raw_tx() {
+        let tx = parsed_tx(2, vec![output(p2pkh_script([0x11; 20]), 9_000), output(p2pkh_script([0x22; 20]), 875)], 250);
+
+        assert_eq!(calculate_fee_rate(&tx, &[5_000, 5_000]), Ok(500));
+        assert!(calculate_fee_rate(&tx, &[10_000]).is_err(), "one value per input");
+        assert!(calculate_fee_rate(&tx, &[5_000, 4_000]).is_err(), "spends more than its inputs");
+        assert!(calculate_fee_rate(&tx, &[u64::MAX, 1]).is_err());
+
+        assert!(validate_fee_rate(&tx, &[5_000, 5_000], 500).is_ok());
+        assert!(validate_fee_rate(&tx, &[5_000, 5_000], 501).is_err());
+    }
+
+    /// $3 trade of one chunk paying MAKER_HASH160, matched at $50/BSV with a $40 minimum
+    fn trade() -> Trade {
+        let (maker, filler) = (Principal::from_slice(&[1, 1, 1]), Principal::from_slice(&[2, 2, 2]));
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker), 10_000_000);
+        mock::set_caller(maker);
+        block_on(crate::order_management::create_order(3.0, 50.0, crate::lifecycle_tests::p2pkh_address(&MAKER_HASH160), None)).unwrap();
+        mock::mint(crate::filler_accounts::get_deposit_account(filler), 1_000_000);
+        let trade_id = block_on(crate::trade_lifecycle::create_trades(filler, crate::trade_lifecycle::CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        })).unwrap()[0];
+        crate::state::get_trade(trade_id).unwrap()
+    }
+
+    #[test]
+    fn change_outputs_may_not_hide_or_burn_value() {
+        let trade = trade();
+        let due = trade.locked_chunks[0].sats_amount;
+        let paid = |change: BsvOutput| validate_transaction_outputs(&parsed_tx(1, vec![output(p2pkh_script(MAKER_HASH160), due), change], 250), &[&trade]);
+
+        assert_eq!(paid(output(p2pkh_script([0x22; 20]), 1_000)), Ok(1));
+        assert_eq!(paid(output(vec![0x00, 0x6a, 0x04, 1, 2, 3, 4], 0)), Ok(1), "zero-value data carrier");
+        assert!(paid(output(vec![0x6a, 0x04, 1, 2, 3, 4], 1)).is_err());
+        assert!(paid(output(vec![0x51], 1_000)).is_err(), "non-standard script");
+
+        // The maker's output must be P2PKH to their hash, not a P2SH of the same hash
+        let p2sh = [&[0xa9, 0x14][..], &MAKER_HASH160, &[0x87]].concat();
+        assert_eq!(classify_output_script(&p2sh), OutputScriptType::P2SH);
+        assert!(validate_transaction_outputs(&parsed_tx(1, vec![output(p2sh, due)], 250), &[&trade]).is_err());
+    }
+
+    #[test]
+    fn maker_output_may_deviate_by_the_trades_sats_tolerance_only() {
+        let mut trade = trade();
+        let due = trade.locked_chunks[0].sats_amount;
+        let allowed = trade.sats_tolerance.as_ref().unwrap().allowed_deviation(due);
+        assert!(allowed > 0);
+        let paying = |trade: &Trade, sats: u64| validate_transaction_outputs(&parsed_tx(1, vec![output(p2pkh_script(MAKER_HASH160), sats)], 250), &[trade]);
+
+        assert!(paying(&trade, due - allowed).is_ok());
+        assert!(paying(&trade, due + allowed).is_ok());
+        assert!(paying(&trade, due - allowed - 1).is_err());
+        assert!(paying(&trade, due + allowed + 1).is_err());
+
+        // Trades created before tolerances were recorded are paid exactly
+        trade.sats_tolerance = None;
+        assert!(paying(&trade, due).is_ok());
+        assert!(paying(&trade, due - 1).is_err());
+    }
+
+    #[test]
+    fn price_paid_is_checked_against_the_trade_prices_not_the_stored_sats() {
+        let mut trade = trade();
+        let paying = |trade: &Trade, sats: u64| validate_price_paid(&parsed_tx(1, vec![output(p2pkh_script(MAKER_HASH160), sats)], 250), &[trade], 1);
+
+        // $3 at $50/BSV, and at the filler's $40 minimum
+        assert!(paying(&trade, 6_000_000).is_ok());
+        assert!(paying(&trade, 7_500_000).is_ok());
+        assert!(paying(&trade, 7_600_000).is_err());
+
+        // A chunk whose stored sats were derived from a higher price doesn't lower what's due
+        trade.locked_chunks[0].sats_amount = 5_000_000;
+        assert!(paying(&trade, 5_000_000).is_err());
+
+        trade.min_bsv_price = 55.0;
+        assert!(paying(&trade, 6_000_000).is_err(), "agreed price below the filler's minimum");
+    }
+}
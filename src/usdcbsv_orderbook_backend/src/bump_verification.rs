@@ -1,9 +1,9 @@
 use crate::block_headers::{get_block_by_height, get_highest_block, CONFIRMATION_DEPTH, BlockHeader};
-use candid::{CandidType, Deserialize, Principal};
+use candid::{CandidType, Deserialize};
 use sha2::{Digest, Sha256};
-
-// TxArchive canister ID for fallback block lookups
-const TXARCHIVE_CANISTER_ID: &str = "glgze-4qaaa-aaaac-a4m2a-cai";
+use crate::runtime::HeaderProvider;
+use crate::config::{MAX_BSV_TX_BYTES, MAX_BUMP_BYTES, MAX_BUMP_TREE_HEIGHT};
+use crate::types::{TxArchiveTrust, VerifiedInclusion};
 
 // TxArchive response structure
 #[derive(CandidType, Deserialize)]
@@ -42,9 +42,8 @@ pub struct TxVerification {
 }
 
 /// Fetch block info from TxArchive canister (fallback)
-async fn fetch_block_from_txarchive(block_height: u64) -> Result<BlockHeader, String> {
-    let txarchive_principal = Principal::from_text(TXARCHIVE_CANISTER_ID)
-        .map_err(|e| format!("Invalid TxArchive canister ID: {}", e))?;
+pub(crate) async fn fetch_block_from_txarchive(block_height: u64) -> Result<BlockHeader, String> {
+    let txarchive_principal = crate::state::get_txarchive_policy().canister;
     
     ic_cdk::println!("📦 Fetching block {} from TxArchive canister as fallback", block_height);
     
@@ -87,17 +86,23 @@ async fn fetch_block_from_txarchive(block_height: u64) -> Result<BlockHeader, St
 }
 
 /// Verify a transaction using BUMP proof (async version with TxArchive fallback)
-pub async fn verify_tx_bump_async(txid: &str, bump_hex: &str) -> Result<TxVerification, String> {
+/// `required_confirmations` is the depth the trade being claimed needs (see ConfirmationDepthSchedule)
+pub async fn verify_tx_bump_async(txid: &str, bump_hex: &str, required_confirmations: u64) -> Result<TxVerification, String> {
     // Input validation: prevent DoS with oversized inputs
     if txid.len() != 64 {
         return Err("Invalid txid: must be 64 hex characters (32 bytes)".to_string());
     }
-    if bump_hex.len() > 10000 {
-        return Err("BUMP proof too large (max 10000 hex chars)".to_string());
+    if bump_hex.len() > MAX_BUMP_BYTES * 2 {
+        return Err(format!("BUMP proof too large (max {} hex chars)", MAX_BUMP_BYTES * 2));
     }
     if !txid.chars().all(|c| c.is_ascii_hexdigit()) {
         return Err("Invalid txid: must be valid hex string".to_string());
     }
+
+    // Already proven against a block we still hold - the BUMP needn't be parsed again
+    if let Some(verification) = verify_tx_cached(txid, required_confirmations) {
+        return Ok(verification);
+    }
     
     // Parse BUMP proof from hex
     ic_cdk::println!("📄 BUMP hex (first 100 chars): {}", &bump_hex.chars().take(100).collect::<String>());
@@ -106,16 +111,24 @@ pub async fn verify_tx_bump_async(txid: &str, bump_hex: &str) -> Result<TxVerifi
     let bump = parse_bump_hex(bump_hex)?;
     ic_cdk::println!("✅ BUMP parsed successfully: block_height={}, path_length={}", bump.block_height, bump.path.len());
     
+    let txarchive = crate::state::get_txarchive_policy();
+
     // Try to get block from local storage first
     let (block, used_fallback) = match get_block_by_height(bump.block_height) {
         Some(b) => {
             ic_cdk::println!("✓ Block {} found in local storage", bump.block_height);
             (b, false)
         }
+        None if !txarchive.enabled => {
+            return Err(format!(
+                "Block at height {} not found in local storage (TxArchive fallback is disabled)",
+                bump.block_height
+            ));
+        }
         None => {
             // Fallback to TxArchive canister
             ic_cdk::println!("⚠️ Block {} not in local storage, trying TxArchive fallback", bump.block_height);
-            match fetch_block_from_txarchive(bump.block_height).await {
+            match crate::runtime::headers().txarchive_block(bump.block_height).await {
                 Ok(b) => {
                     ic_cdk::println!("✅ Block {} retrieved from TxArchive", bump.block_height);
                     (b, true)
@@ -140,45 +153,77 @@ pub async fn verify_tx_bump_async(txid: &str, bump_hex: &str) -> Result<TxVerifi
         ));
     }
 
+    // TxArchive blocks aren't in local storage, so a cached proof for one could never be checked
+    if !used_fallback {
+        crate::state::insert_verified_inclusion(VerifiedInclusion {
+            txid: txid.to_string(),
+            block_height: bump.block_height,
+            block_hash: block.hash.clone(),
+            verified_at: crate::state::get_time(),
+        });
+    }
+
     // Check confirmations
-    // If we used TxArchive fallback, we can trust the block is sufficiently confirmed
+    // Under AssumeConfirmed a TxArchive block is trusted as sufficiently confirmed -
     // TxArchive only stores blocks that are already deep in the chain
-    let highest = if used_fallback {
+    let highest = if used_fallback && txarchive.trust == TxArchiveTrust::AssumeConfirmed {
         ic_cdk::println!("ℹ️ Using TxArchive fallback - assuming sufficient confirmations (TxArchive only has confirmed blocks)");
         // Assume TxArchive has blocks with at least CONFIRMATION_DEPTH confirmations
-        // Set highest to bump_height + CONFIRMATION_DEPTH to pass the check
-        bump.block_height + CONFIRMATION_DEPTH
+        // Set highest to bump_height + the larger of the two to pass the check
+        bump.block_height + CONFIRMATION_DEPTH.max(required_confirmations)
     } else {
         get_highest_block()
     };
-    
-    if highest < bump.block_height {
+
+    confirmation_verdict(bump.block_height, block.hash, highest, required_confirmations)
+}
+
+/// Fast path for a txid whose proof already verified - None when it isn't cached or its block
+/// is no longer the stored header at that height (reorged or pruned), so the caller verifies
+/// the BUMP in full
+pub fn verify_tx_cached(txid: &str, required_confirmations: u64) -> Option<TxVerification> {
+    let inclusion = crate::state::get_verified_inclusion(txid)?;
+    match get_block_by_height(inclusion.block_height) {
+        Some(block) if block.hash == inclusion.block_hash => {
+            ic_cdk::println!("✓ Merkle proof for {} already verified in block {}", txid, inclusion.block_height);
+            confirmation_verdict(inclusion.block_height, inclusion.block_hash, get_highest_block(), required_confirmations).ok()
+        }
+        Some(_) => {
+            crate::state::remove_verified_inclusion(txid);
+            None
+        }
+        None => None,
+    }
+}
+
+/// Confirmation count of a proven block against the chain tip
+fn confirmation_verdict(block_height: u64, block_hash: String, highest: u64, required_confirmations: u64) -> Result<TxVerification, String> {
+    if highest < block_height {
         return Err(format!(
             "Block height {} is ahead of our chain tip {}. Please wait for block sync to catch up.",
-            bump.block_height, highest
+            block_height, highest
         ));
     }
 
-    let confirmations = highest - bump.block_height + 1;
+    let confirmations = highest - block_height + 1;
 
-    // Require CONFIRMATION_DEPTH confirmations (18 blocks)
-    if confirmations < CONFIRMATION_DEPTH {
+    if confirmations < required_confirmations {
         return Ok(TxVerification {
             verified: false,
-            block_height: bump.block_height,
-            block_hash: block.hash.clone(),
+            block_height,
+            block_hash,
             confirmations,
             message: format!(
                 "Insufficient confirmations: {} (need {})",
-                confirmations, CONFIRMATION_DEPTH
+                confirmations, required_confirmations
             ),
         });
     }
 
     Ok(TxVerification {
         verified: true,
-        block_height: bump.block_height,
-        block_hash: block.hash,
+        block_height,
+        block_hash,
         confirmations,
         message: format!("Transaction verified with {} confirmations", confirmations),
     })
@@ -190,8 +235,8 @@ pub fn verify_tx_bump(txid: &str, bump_hex: &str) -> Result<TxVerification, Stri
     if txid.len() != 64 {
         return Err("Invalid txid: must be 64 hex characters (32 bytes)".to_string());
     }
-    if bump_hex.len() > 10000 {
-        return Err("BUMP proof too large (max 10000 hex chars)".to_string());
+    if bump_hex.len() > MAX_BUMP_BYTES * 2 {
+        return Err(format!("BUMP proof too large (max {} hex chars)", MAX_BUMP_BYTES * 2));
     }
     if !txid.chars().all(|c| c.is_ascii_hexdigit()) {
         return Err("Invalid txid: must be valid hex string".to_string());
@@ -255,7 +300,10 @@ pub fn verify_tx_bump(txid: &str, bump_hex: &str) -> Result<TxVerification, Stri
 ///     - Offset (varint) - position in this level
 ///     - Flags (1 byte): bit 0 = duplicate, bit 1 = txid
 ///     - Hash (32 bytes, reversed for display) - only if not a duplicate
-fn parse_bump_hex(bump_hex: &str) -> Result<BumpProof, String> {
+pub(crate) fn parse_bump_hex(bump_hex: &str) -> Result<BumpProof, String> {
+    if bump_hex.len() > MAX_BUMP_BYTES * 2 {
+        return Err(format!("BUMP proof too large (max {} hex chars)", MAX_BUMP_BYTES * 2));
+    }
     let bytes = hex::decode(bump_hex).map_err(|e| format!("Invalid BUMP hex: {}", e))?;
     ic_cdk::println!("🔍 Parsing BUMP: {} bytes decoded from {} hex chars", bytes.len(), bump_hex.len());
     
@@ -269,6 +317,9 @@ fn parse_bump_hex(bump_hex: &str) -> Result<BumpProof, String> {
     }
     let tree_height = bytes[offset] as u64;
     offset += 1;
+    if tree_height > MAX_BUMP_TREE_HEIGHT {
+        return Err(format!("Invalid BUMP: tree height {} exceeds {}", tree_height, MAX_BUMP_TREE_HEIGHT));
+    }
     ic_cdk::println!("  Tree height: {}, offset after: {}", tree_height, offset);
     
     let mut path = Vec::new();
@@ -280,6 +331,10 @@ fn parse_bump_hex(bump_hex: &str) -> Result<BumpProof, String> {
         // Number of leaves at this level
         let (n_leaves, new_offset) = parse_varint(&bytes, offset)?;
         offset = new_offset;
+        // Every leaf takes at least an offset byte and a flags byte
+        if n_leaves > (bytes.len() - offset) as u64 / 2 {
+            return Err(format!("Invalid BUMP: level {} claims {} leaves but only {} bytes remain", level, n_leaves, bytes.len() - offset));
+        }
         ic_cdk::println!("  Level {}: {} leaves, offset after count: {}", level, n_leaves, offset);
         
         // Parse each leaf in this level
@@ -341,7 +396,7 @@ fn parse_bump_hex(bump_hex: &str) -> Result<BumpProof, String> {
 /// - 0xFD: followed by 2 bytes little-endian (3 bytes total)
 /// - 0xFE: followed by 4 bytes little-endian (5 bytes total)
 /// - 0xFF: followed by 8 bytes little-endian (9 bytes total)
-fn parse_varint(bytes: &[u8], start: usize) -> Result<(u64, usize), String> {
+pub(crate) fn parse_varint(bytes: &[u8], start: usize) -> Result<(u64, usize), String> {
     if start >= bytes.len() {
         return Err("Insufficient data for varint".to_string());
     }
@@ -476,31 +531,31 @@ fn double_sha256(data: &[u8]) -> Vec<u8> {
 }
 
 /// Verify transaction with raw hex (compute txid and verify against block)
-pub async fn verify_tx_raw_async(tx_hex: &str, bump_hex: &str) -> Result<TxVerification, String> {
+pub async fn verify_tx_raw_async(tx_hex: &str, bump_hex: &str, required_confirmations: u64) -> Result<TxVerification, String> {
     // Input validation: prevent DoS with oversized inputs
     // Maximum BSV transaction size is 10MB, but for our use case (simple transfers) we limit to 100KB
-    if tx_hex.len() > 200000 {
-        return Err("Transaction too large (max 100KB)".to_string());
+    if tx_hex.len() > MAX_BSV_TX_BYTES * 2 {
+        return Err(format!("Transaction too large (max {} bytes)", MAX_BSV_TX_BYTES));
     }
-    if bump_hex.len() > 10000 {
-        return Err("BUMP proof too large (max 10000 hex chars)".to_string());
+    if bump_hex.len() > MAX_BUMP_BYTES * 2 {
+        return Err(format!("BUMP proof too large (max {} hex chars)", MAX_BUMP_BYTES * 2));
     }
     
     // Compute txid from raw transaction
     let txid = compute_txid(tx_hex)?;
     
     // Verify using BUMP (with TxArchive fallback)
-    verify_tx_bump_async(&txid, bump_hex).await
+    verify_tx_bump_async(&txid, bump_hex, required_confirmations).await
 }
 
 pub fn verify_tx_raw(tx_hex: &str, bump_hex: &str) -> Result<TxVerification, String> {
     // Input validation: prevent DoS with oversized inputs
     // Maximum BSV transaction size is 10MB, but for our use case (simple transfers) we limit to 100KB
-    if tx_hex.len() > 200000 {
-        return Err("Transaction too large (max 100KB)".to_string());
+    if tx_hex.len() > MAX_BSV_TX_BYTES * 2 {
+        return Err(format!("Transaction too large (max {} bytes)", MAX_BSV_TX_BYTES));
     }
-    if bump_hex.len() > 10000 {
-        return Err("BUMP proof too large (max 10000 hex chars)".to_string());
+    if bump_hex.len() > MAX_BUMP_BYTES * 2 {
+        return Err(format!("BUMP proof too large (max {} hex chars)", MAX_BUMP_BYTES * 2));
     }
     
     // Compute txid from raw transaction
@@ -0,0 +1,144 @@
+//! The committed usdcbsv_orderbook_backend.did is what integrators build against - the interface
+//! export_candid!() generates from the code has to stay compatible with it and list nothing it
+//! doesn't. When one of these fails, fix the signature or update the .did in the same change
+use candid_parser::utils::{service_compatible, CandidSource};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+const COMMITTED_DID: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/usdcbsv_orderbook_backend.did");
+
+// Exported by debug builds only, so never part of the deployed interface
+const DEBUG_ONLY_METHODS: [&str; 1] = ["debug_check_invariants"];
+
+fn method_names(source: CandidSource) -> BTreeSet<String> {
+    let (env, service) = source.load().expect("interface parses and type-checks");
+    let service = service.expect("interface has a service");
+    env.as_service(&service)
+        .expect("service type")
+        .iter()
+        .map(|(name, _)| name.clone())
+        .filter(|name| !DEBUG_ONLY_METHODS.contains(&name.as_str()))
+        .collect()
+}
+
+#[test]
+fn exported_interface_is_compatible_with_committed_did() {
+    let generated = crate::__export_service();
+    if let Err(e) = service_compatible(CandidSource::Text(&generated), CandidSource::File(Path::new(COMMITTED_DID))) {
+        panic!("Exported interface breaks clients of usdcbsv_orderbook_backend.did:\n{:?}", e);
+    }
+}
+
+#[test]
+fn committed_did_lists_exactly_the_exported_methods() {
+    let generated = crate::__export_service();
+    let exported = method_names(CandidSource::Text(&generated));
+    let committed = method_names(CandidSource::File(Path::new(COMMITTED_DID)));
+    assert!(
+        exported == committed,
+        "usdcbsv_orderbook_backend.did is out of date - exported only: {:?}, committed only: {:?}",
+        exported.difference(&committed).collect::<Vec<_>>(),
+        committed.difference(&exported).collect::<Vec<_>>(),
+    );
+}
+
+// The v1 records as integrators first decoded them - USD amounts are float64 under these names.
+// Fields added since are fine (they are extra to old clients); changing one of these is not,
+// and has to ship as a v2_ endpoint instead (see api_versions.rs)
+const PINNED_V1_RECORDS: &str = r#"
+type OrderStatus = variant { Active; Idle; PartiallyFilled; Filled; Cancelled; Refunded };
+type ChunkStatus = variant { Available; Locked; Filled; Idle; Pending; Refunding; Refunded };
+type TradeStatus = variant { ChunksLocked; TxSubmitted; ReadyForRelease; WithdrawalConfirmed; Cancelled; PenaltyApplied };
+type RefundStatus = variant { Pending; Sent; Confirmed; Failed };
+type RefundAttempt = record {
+  refund_id : nat64;
+  requested_at : nat64;
+  chunk_ids : vec nat64;
+  total_amount : float64;
+  recipient_address : text;
+  tx_hash : opt text;
+  tx_sent_at : opt nat64;
+  confirmed_at : opt nat64;
+  status : RefundStatus;
+};
+type Order = record {
+  id : nat64;
+  maker : principal;
+  amount_usd : float64;
+  total_deposited_usd : opt float64;
+  activation_fee_usd : opt float64;
+  filler_incentive_reserved : opt float64;
+  deposit_principal : text;
+  deposit_subaccount : text;
+  max_bsv_price : float64;
+  allow_partial_fill : bool;
+  bsv_address : text;
+  status : OrderStatus;
+  chunks : vec nat64;
+  created_at : nat64;
+  deposit_confirmed_at : opt nat64;
+  funded_at : opt nat64;
+  activation_fee_block_index : opt nat64;
+  activation_fee_confirmed_at : opt nat64;
+  total_filled_usd : float64;
+  total_locked_usd : float64;
+  total_idle_usd : float64;
+  total_refunded_usd : opt float64;
+  refund_attempts : vec RefundAttempt;
+};
+type LockedChunk = record {
+  chunk_id : nat64;
+  order_id : nat64;
+  amount_usd : float64;
+  bsv_address : text;
+  sats_amount : nat64;
+};
+type Trade = record {
+  id : nat64;
+  order_id : nat64;
+  filler : principal;
+  amount_usd : float64;
+  locked_chunks : vec LockedChunk;
+  agreed_bsv_price : float64;
+  min_bsv_price : float64;
+  status : TradeStatus;
+  created_at : nat64;
+  tx_submitted_at : opt nat64;
+  lock_expires_at : nat64;
+  release_available_at : opt nat64;
+  claim_expires_at : opt nat64;
+  withdrawal_initiated_at : opt nat64;
+  withdrawal_tx_hash : opt text;
+  withdrawal_confirmed_at : opt nat64;
+};
+type ChunkDetails = record {
+  id : nat64;
+  order_id : nat64;
+  amount_usd : float64;
+  status : ChunkStatus;
+  locked_by : opt nat64;
+  filled_at : opt nat64;
+};
+type OrderbookChunk = record {
+  order_id : nat64;
+  amount_usd : float64;
+  max_price_per_bsv_in_cents : nat64;
+};
+service : {
+  get_order : (nat64) -> (opt Order) query;
+  get_my_orders : () -> (vec Order) query;
+  get_my_active_orders : () -> (vec Order) query;
+  get_trade : (nat64) -> (opt Trade) query;
+  get_my_trades : () -> (vec Trade) query;
+  get_order_chunks : (nat64) -> (vec ChunkDetails) query;
+  get_active_chunks : () -> (vec OrderbookChunk) query;
+}
+"#;
+
+#[test]
+fn v1_endpoints_still_return_the_pinned_records() {
+    let generated = crate::__export_service();
+    if let Err(e) = service_compatible(CandidSource::Text(&generated), CandidSource::Text(PINNED_V1_RECORDS)) {
+        panic!("A v1 record changed shape - ship the change as a v2_ endpoint instead:\n{:?}", e);
+    }
+}
@@ -1,8 +1,9 @@
 use crate::block_headers::*;
 use crate::block_sync::*;
-use crate::state::create_admin_event;
-use crate::types::AdminEventType;
-use crate::config::MAX_BLOCKS_TO_KEEP;
+use crate::state::{create_admin_event, get_max_chain_tip_age_seconds};
+use crate::types::{AdminEventType, BsvNetwork, EasySwapError, OrderStatus, TradeStatus, TxArchivePolicy};
+use crate::config::{MAX_BLOCKS_TO_KEEP, BLOCK_SYNC_INTERVAL_SECONDS, MAX_CHAIN_TIP_AGE_LIMIT_SECONDS, ON_DEMAND_SYNC_MIN_AGE_SECONDS};
+use crate::runtime::{headers, HeaderProvider};
 use candid::{CandidType, Deserialize};
 use std::cell::RefCell;
 
@@ -39,14 +40,17 @@ pub async fn sync_blocks() -> Result<SyncResult, String> {
     }
     
     // Track cycles cost
-    let cycles_start = ic_cdk::api::canister_balance128();
+    let cycles_start = crate::runtime::cycles_balance();
     
     // Ensure flag is cleared on any exit path
     let result = sync_blocks_internal().await;
     SYNC_IN_PROGRESS.with(|flag| *flag.borrow_mut() = false);
+    if result.is_ok() {
+        update_sync_time(crate::runtime::time() / 1_000_000_000);
+    }
     
     // Log cycles consumed
-    let cycles_end = ic_cdk::api::canister_balance128();
+    let cycles_end = crate::runtime::cycles_balance();
     let cycles_consumed = cycles_start.saturating_sub(cycles_end);
     ic_cdk::println!(
         "💰 Block sync consumed {} cycles ({:.2} T cycles)",
@@ -62,7 +66,7 @@ async fn sync_blocks_internal() -> Result<SyncResult, String> {
     ic_cdk::println!("Starting block synchronization...");
     
     // Step 1: Find consensus tip between WoC and Bitails (with retry)
-    let consensus_result = match find_consensus_tip().await {
+    let consensus_result = match headers().consensus_tip().await {
         Ok(result) => result,
         Err(e) => {
             ic_cdk::println!("⚠️ Failed to find consensus tip: {}", e);
@@ -164,7 +168,7 @@ async fn sync_blocks_internal() -> Result<SyncResult, String> {
         };
         
         // Fetch blocks from TxArchive (handles chain validation automatically)
-        let txarchive_blocks = fetch_blocks_from_txarchive(consensus_tip.height, local_tip).await?;
+        let txarchive_blocks = headers().txarchive_blocks(consensus_tip.height, local_tip).await?;
         
         ic_cdk::println!("📥 Received {} blocks from TxArchive", txarchive_blocks.len());
         
@@ -206,7 +210,7 @@ async fn sync_blocks_internal() -> Result<SyncResult, String> {
             ic_cdk::println!("Fetching batch: skip={}, limit={}", skip, limit);
             
             // Fetch batch from Bitails using pagination (descending order from tip)
-            let batch = fetch_bitails_blocks_batch(skip, limit).await?;
+            let batch = headers().bitails_blocks_batch(skip, limit).await?;
             
             // Bitails returns in descending order, so reverse for storage
             let mut sorted_batch = batch;
@@ -309,7 +313,7 @@ async fn initial_sync(consensus_result: ConsensusResult) -> Result<SyncResult, S
     if use_txarchive {
         // TXARCHIVE FALLBACK MODE for initial sync
         ic_cdk::println!("� Using TxArchive for initial sync");
-        all_blocks = fetch_blocks_from_txarchive(consensus_tip.height, None).await?;
+        all_blocks = headers().txarchive_blocks(consensus_tip.height, None).await?;
         
         // Filter to only keep blocks from target_start_height to consensus_tip
         all_blocks.retain(|b| b.height >= target_start_height && b.height <= consensus_tip.height);
@@ -335,7 +339,7 @@ async fn initial_sync(consensus_result: ConsensusResult) -> Result<SyncResult, S
             
             ic_cdk::println!("Fetching batch: skip={}, limit={}", skip, BATCH_SIZE);
             
-            let batch = fetch_bitails_blocks_batch(skip, BATCH_SIZE).await?;
+            let batch = headers().bitails_blocks_batch(skip, BATCH_SIZE).await?;
             
             if batch.is_empty() {
                 let error_msg = "No more blocks returned from Bitails".to_string();
@@ -424,7 +428,7 @@ async fn check_and_handle_reorg(_consensus_tip: &BlockInfo) -> Result<ReorgResul
     let our_highest = get_highest_block();
     
     // Fetch the block at our highest from Bitails
-    let bitails_block = fetch_bitails_block_header(our_highest).await?;
+    let bitails_block = headers().bitails_block_header(our_highest).await?;
     
     // Get our stored block at that height
     let our_block = get_block_by_height(our_highest)
@@ -489,7 +493,7 @@ async fn check_and_handle_reorg(_consensus_tip: &BlockInfo) -> Result<ReorgResul
             ));
         }
 
-        let bitails_block = fetch_bitails_block_header(check_height).await?;
+        let bitails_block = headers().bitails_block_header(check_height).await?;
         let our_block = get_block_by_height(check_height)
             .ok_or_else(|| format!("Our block at height {} not found", check_height))?;
 
@@ -532,7 +536,7 @@ pub async fn admin_force_resync() -> Result<SyncResult, String> {
             );
 
             // Fetch the consensus tip to measure the gap
-            let consensus_result = find_consensus_tip().await.map_err(|e| {
+            let consensus_result = headers().consensus_tip().await.map_err(|e| {
                 format!(
                     "Normal sync failed: {}. Then tip fetch also failed: {}. Cannot resync.",
                     normal_err, e
@@ -576,6 +580,242 @@ pub async fn admin_force_resync() -> Result<SyncResult, String> {
     }
 }
 
+/// Result of a sync dry run - what a sync would do right now, without touching storage
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct SyncDryRunReport {
+    pub local_tip_height: u64,
+    pub local_tip_hash: Option<String>,
+    pub woc_tip: Option<BlockInfo>,
+    pub bitails_tip: Option<BlockInfo>,
+    pub provider_errors: Vec<String>,
+    pub consensus_tip: Option<BlockInfo>,
+    pub would_use_txarchive: bool,
+    pub divergence_detected: bool,
+    pub common_ancestor_height: Option<u64>, // Highest local block matching Bitails (None if not found in checked window)
+    pub blocks_to_remove: u64,
+    pub blocks_to_add: u64,
+    pub message: String,
+}
+
+/// Admin dry run of block sync
+/// Fetches provider tips and compares them against local storage, reporting how many
+/// blocks would be added/removed and whether local headers diverge from Bitails.
+/// Never mutates header storage.
+pub async fn sync_dry_run() -> Result<SyncDryRunReport, String> {
+    use crate::config::MAX_REORG_CHECK_PER_CALL;
+    
+    let local_tip_height = get_highest_block();
+    let local_tip_hash = get_block_by_height(local_tip_height).map(|b| b.hash);
+    let mut provider_errors = Vec::new();
+    
+    let woc_tip = match headers().woc_tip().await {
+        Ok(tip) => Some(tip),
+        Err(e) => {
+            provider_errors.push(format!("WoC: {}", e));
+            None
+        }
+    };
+    
+    let bitails_tip = match headers().bitails_tip().await {
+        Ok(tip) => Some(tip),
+        Err(e) => {
+            provider_errors.push(format!("Bitails: {}", e));
+            None
+        }
+    };
+    
+    let (consensus_tip, would_use_txarchive) = match headers().consensus_tip().await {
+        Ok(result) => (Some(result.tip), result.use_txarchive_fallback),
+        Err(e) => {
+            provider_errors.push(format!("Consensus: {}", e));
+            (None, false)
+        }
+    };
+    
+    let mut report = SyncDryRunReport {
+        local_tip_height,
+        local_tip_hash,
+        woc_tip,
+        bitails_tip: bitails_tip.clone(),
+        provider_errors,
+        consensus_tip: consensus_tip.clone(),
+        would_use_txarchive,
+        divergence_detected: false,
+        common_ancestor_height: None,
+        blocks_to_remove: 0,
+        blocks_to_add: 0,
+        message: String::new(),
+    };
+    
+    let consensus_tip = match consensus_tip {
+        Some(tip) => tip,
+        None => {
+            report.message = "No consensus tip - a sync would fail right now".to_string();
+            return Ok(report);
+        }
+    };
+    
+    // No local blocks: sync would do an initial sync of the retention window
+    if local_tip_height == 0 {
+        report.blocks_to_add = std::cmp::min(consensus_tip.height, MAX_BLOCKS_TO_KEEP);
+        report.message = format!("No local blocks - initial sync would fetch {} blocks", report.blocks_to_add);
+        return Ok(report);
+    }
+    
+    // Compare our most recent blocks against Bitails (one batch, newest first)
+    let bitails_tip = match bitails_tip {
+        Some(tip) => tip,
+        None => {
+            report.blocks_to_add = consensus_tip.height.saturating_sub(local_tip_height);
+            report.message = "Bitails unavailable - divergence not checked".to_string();
+            return Ok(report);
+        }
+    };
+    
+    if local_tip_height > bitails_tip.height {
+        report.divergence_detected = true;
+        report.message = format!(
+            "Local tip {} is ahead of Bitails tip {} - divergence not checked",
+            local_tip_height, bitails_tip.height
+        );
+        return Ok(report);
+    }
+    
+    let skip = bitails_tip.height - local_tip_height;
+    let provider_blocks = headers().bitails_blocks_batch(skip, MAX_REORG_CHECK_PER_CALL).await?;
+    
+    for provider_block in provider_blocks.iter() {
+        if let Some(local_block) = get_block_by_height(provider_block.height) {
+            if local_block.hash == provider_block.hash {
+                report.common_ancestor_height = Some(provider_block.height);
+                break;
+            }
+            report.divergence_detected = true;
+        }
+    }
+    
+    match report.common_ancestor_height {
+        Some(ancestor) => {
+            report.blocks_to_remove = local_tip_height - ancestor;
+            report.blocks_to_add = consensus_tip.height.saturating_sub(ancestor);
+            report.message = if report.divergence_detected {
+                format!(
+                    "Divergence detected - {} local blocks above height {} differ from Bitails",
+                    report.blocks_to_remove, ancestor
+                )
+            } else {
+                format!("Local chain matches Bitails - {} blocks to add", report.blocks_to_add)
+            };
+        }
+        None => {
+            report.divergence_detected = true;
+            report.blocks_to_add = consensus_tip.height.saturating_sub(local_tip_height);
+            report.message = format!(
+                "No common ancestor in the last {} local blocks - reorg deeper than one sync batch",
+                MAX_REORG_CHECK_PER_CALL
+            );
+        }
+    }
+    
+    Ok(report)
+}
+
+/// Make sure the local tip was synced within the max chain tip age, syncing on demand if not
+/// Confirmations counted against a stale tip can be short (missed blocks) or long (missed reorg)
+pub async fn ensure_fresh_tip() -> Result<(), EasySwapError> {
+    let max_age = get_max_chain_tip_age_seconds();
+    let now = crate::runtime::time() / 1_000_000_000;
+    if now.saturating_sub(get_last_sync_time()) <= max_age {
+        return Ok(());
+    }
+    
+    ic_cdk::println!("⏳ Chain tip last synced at {} - syncing before counting confirmations", get_last_sync_time());
+    sync_blocks().await.map(|_| ()).map_err(|e| EasySwapError::unavailable(format!(
+        "Block headers are more than {} minutes old and could not be refreshed ({}). Please retry shortly.",
+        max_age / 60, e
+    )))
+}
+
+/// Ask for a block sync ahead of the timer, e.g. while a claim waits for confirmations
+/// Only once headers are ON_DEMAND_SYNC_MIN_AGE_SECONDS old - returns when the sync will start
+pub fn request_block_sync() -> Result<u64, EasySwapError> {
+    if crate::state::get_caller() == candid::Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot request a block sync. Please authenticate first."));
+    }
+    if SYNC_IN_PROGRESS.with(|flag| *flag.borrow()) {
+        return Err(EasySwapError::invalid_state("A block sync is already running"));
+    }
+    
+    let age = (crate::runtime::time() / 1_000_000_000).saturating_sub(get_last_sync_time());
+    if age < ON_DEMAND_SYNC_MIN_AGE_SECONDS {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "Headers were synced {} seconds ago - a sync can be requested again in {} seconds",
+            age, ON_DEMAND_SYNC_MIN_AGE_SECONDS - age
+        )));
+    }
+    
+    crate::scheduler::run_soon(crate::types::BackgroundTask::BlockSync)
+}
+
+/// Validate and store the max chain tip age - no shorter than the scheduled sync interval,
+/// which would force a sync on nearly every claim
+pub fn set_max_chain_tip_age(seconds: u64) -> Result<(), EasySwapError> {
+    if !(BLOCK_SYNC_INTERVAL_SECONDS..=MAX_CHAIN_TIP_AGE_LIMIT_SECONDS).contains(&seconds) {
+        return Err(EasySwapError::invalid_input(format!(
+            "Max chain tip age must be between {} and {} seconds",
+            BLOCK_SYNC_INTERVAL_SECONDS, MAX_CHAIN_TIP_AGE_LIMIT_SECONDS
+        )));
+    }
+    crate::state::set_max_chain_tip_age_seconds(seconds);
+    Ok(())
+}
+
+/// Validate and store the TxArchive fallback policy
+pub fn set_txarchive_policy(policy: TxArchivePolicy) -> Result<(), EasySwapError> {
+    if policy.canister == candid::Principal::anonymous() {
+        return Err(EasySwapError::invalid_input("TxArchive canister cannot be the anonymous principal"));
+    }
+    ic_cdk::println!("📦 TxArchive fallback {} ({}, {:?})", if policy.enabled { "enabled" } else { "disabled" }, policy.canister, policy.trust);
+    crate::state::set_txarchive_policy(policy);
+    Ok(())
+}
+
+/// Turn the TxArchive fallback on or off, keeping its canister and trust
+pub fn set_txarchive_fallback_enabled(enabled: bool) -> Result<(), EasySwapError> {
+    set_txarchive_policy(TxArchivePolicy { enabled, ..crate::state::get_txarchive_policy() })
+}
+
+/// Point the canister at another BSV network - only while nothing is open on the current one
+/// Headers are kept per network, so switching back resumes from the headers already synced
+/// TxArchive indexes mainnet, so its fallback is switched off when moving to testnet
+pub fn set_bsv_network(network: BsvNetwork) -> Result<(), EasySwapError> {
+    let current = crate::state::get_bsv_network();
+    if network == current {
+        return Ok(());
+    }
+    let open_orders = crate::state::get_all_orders().iter()
+        .filter(|order| matches!(order.status, OrderStatus::Active | OrderStatus::Idle))
+        .count();
+    let open_trades = crate::state::get_all_trades().iter()
+        .filter(|trade| matches!(trade.status, TradeStatus::ChunksLocked | TradeStatus::TxSubmitted | TradeStatus::ReadyForRelease))
+        .count();
+    if open_orders > 0 || open_trades > 0 {
+        return Err(EasySwapError::invalid_state(format!(
+            "{} open orders and {} unsettled trades are on BSV {:?} - close them before switching networks",
+            open_orders, open_trades, current
+        )));
+    }
+
+    if network == BsvNetwork::Testnet {
+        set_txarchive_fallback_enabled(false)?;
+    }
+    crate::state::set_bsv_network(network);
+    // Sync the new network's headers on the next tick
+    update_sync_time(0);
+    ic_cdk::println!("🌐 BSV network switched from {:?} to {:?}", current, network);
+    Ok(())
+}
+
 /// Get sync status
 pub fn get_sync_status() -> SyncStatus {
     let (min, max) = get_stored_range();
@@ -1,7 +1,9 @@
 use crate::types::*;
 use crate::state::*;
+use crate::config::{FILLER_CHURN_FLAG_THRESHOLD, FILLER_INCENTIVE_PERCENT, MAX_PAGE_LIMIT};
+use candid::Principal;
 
-pub fn allocate_chunks_fifo(requested_usd: f64) -> Result<Vec<Chunk>, String> {
+pub fn allocate_chunks_fifo(requested_e6: UsdE6) -> Result<Vec<Chunk>, String> {
     let available_chunks = get_available_chunks();
     
     if available_chunks.is_empty() {
@@ -9,37 +11,147 @@ pub fn allocate_chunks_fifo(requested_usd: f64) -> Result<Vec<Chunk>, String> {
     }
     
     let mut selected_chunks = Vec::new();
-    let mut accumulated_usd = 0.0;
+    let mut accumulated_e6: UsdE6 = 0;
     
     for chunk in available_chunks {
         // Check if adding this chunk would exceed requested amount
-        if accumulated_usd + chunk.amount_usd > requested_usd {
+        if accumulated_e6 + chunk.amount_usd_e6 > requested_e6 {
             // Skip this chunk (too large)
             continue;
         }
         
         // Add this chunk
         selected_chunks.push(chunk.clone());
-        accumulated_usd += chunk.amount_usd;
+        accumulated_e6 += chunk.amount_usd_e6;
         
         // Check if we've reached the requested amount
-        if (accumulated_usd - requested_usd).abs() < 0.000001 {
+        if accumulated_e6 == requested_e6 {
             break;
         }
     }
     
     // Verify we matched exactly
-    if (accumulated_usd - requested_usd).abs() > 0.000001 {
+    if accumulated_e6 != requested_e6 {
         return Err(format!(
             "Could not match exact amount. Requested: ${}, Found: ${}",
-            requested_usd,
-            accumulated_usd
+            e6_to_usd(requested_e6),
+            e6_to_usd(accumulated_e6)
         ));
     }
     
     Ok(selected_chunks)
 }
 
+// ===== MATCHING POLICY =====
+
+/// Pick chunks for a fill of up to requested_e6 under `policy`, at most max_chunks per order
+/// (each order's picks become one trade)
+/// `candidates` are the orders the filler may match, oldest first, each with its Available chunks.
+/// Returns the picked chunks grouped by order, in the order trades should be created
+pub fn select_chunks(
+    policy: MatchingPolicy,
+    mut candidates: Vec<(Order, Vec<Chunk>)>,
+    requested_e6: UsdE6,
+    max_chunks: usize,
+) -> Vec<(OrderId, Vec<Chunk>)> {
+    for (_, chunks) in candidates.iter_mut() {
+        chunks.truncate(max_chunks);
+    }
+    
+    match policy {
+        MatchingPolicy::Fifo => select_in_sequence(candidates, requested_e6),
+        MatchingPolicy::BestIncentive => {
+            // Stable sort, so orders offering the same incentive stay FIFO
+            candidates.sort_by_key(|(order, _)| std::cmp::Reverse(order.filler_incentive_bps()));
+            select_in_sequence(candidates, requested_e6)
+        }
+        MatchingPolicy::ProRata => select_pro_rata(candidates, requested_e6),
+    }
+}
+
+/// Drain each order in turn before moving to the next
+fn select_in_sequence(candidates: Vec<(Order, Vec<Chunk>)>, requested_e6: UsdE6) -> Vec<(OrderId, Vec<Chunk>)> {
+    let mut remaining_e6 = requested_e6;
+    let mut picks = Vec::new();
+    
+    for (order, chunks) in candidates {
+        if remaining_e6 == 0 {
+            break;
+        }
+        
+        let mut taken = Vec::new();
+        for chunk in chunks {
+            // A chunk larger than what's left is skipped (chunks can't be split)
+            if chunk.amount_usd_e6 <= remaining_e6 {
+                remaining_e6 -= chunk.amount_usd_e6;
+                taken.push(chunk);
+            }
+        }
+        if !taken.is_empty() {
+            picks.push((order.id, taken));
+        }
+    }
+    
+    picks
+}
+
+/// Give each order its share of the fill in proportion to its available amount, rounded down to
+/// whole chunks, then hand out what rounding left over one chunk per order at a time, oldest first
+fn select_pro_rata(candidates: Vec<(Order, Vec<Chunk>)>, requested_e6: UsdE6) -> Vec<(OrderId, Vec<Chunk>)> {
+    let total_available_e6: UsdE6 = candidates.iter()
+        .flat_map(|(_, chunks)| chunks)
+        .map(|chunk| chunk.amount_usd_e6)
+        .sum();
+    if total_available_e6 == 0 {
+        return Vec::new();
+    }
+    
+    let fill_e6 = requested_e6.min(total_available_e6);
+    let mut remaining_e6 = fill_e6;
+    let mut picks: Vec<(OrderId, Vec<Chunk>)> = Vec::with_capacity(candidates.len());
+    let mut leftovers: Vec<Vec<Chunk>> = Vec::with_capacity(candidates.len());
+    
+    for (order, chunks) in candidates {
+        let available_e6: UsdE6 = chunks.iter().map(|chunk| chunk.amount_usd_e6).sum();
+        let share_e6 = (fill_e6 as u128 * available_e6 as u128 / total_available_e6 as u128) as UsdE6;
+        
+        let mut taken_e6: UsdE6 = 0;
+        let mut taken = Vec::new();
+        let mut rest = Vec::new();
+        for chunk in chunks {
+            if taken_e6 + chunk.amount_usd_e6 <= share_e6 {
+                taken_e6 += chunk.amount_usd_e6;
+                taken.push(chunk);
+            } else {
+                rest.push(chunk);
+            }
+        }
+        
+        // Shares sum to at most fill_e6, so this can't underflow
+        remaining_e6 -= taken_e6;
+        picks.push((order.id, taken));
+        leftovers.push(rest);
+    }
+    
+    while remaining_e6 > 0 {
+        let mut progressed = false;
+        for (pick, rest) in picks.iter_mut().zip(leftovers.iter_mut()) {
+            if let Some(pos) = rest.iter().position(|chunk| chunk.amount_usd_e6 <= remaining_e6) {
+                let chunk = rest.remove(pos);
+                remaining_e6 -= chunk.amount_usd_e6;
+                pick.1.push(chunk);
+                progressed = true;
+            }
+        }
+        if !progressed {
+            break;
+        }
+    }
+    
+    picks.retain(|(_, chunks)| !chunks.is_empty());
+    picks
+}
+
 pub fn lock_chunks_for_trade(chunk_ids: &[ChunkId], trade_id: TradeId) -> Result<(), String> {
     for chunk_id in chunk_ids {
         if let Some(chunk) = get_chunk(*chunk_id) {
@@ -51,11 +163,15 @@ pub fn lock_chunks_for_trade(chunk_ids: &[ChunkId], trade_id: TradeId) -> Result
             update_chunk(*chunk_id, |c| {
                 c.status = ChunkStatus::Locked;
                 c.locked_by = Some(trade_id);
+                c.lock_count = Some(c.lock_count.unwrap_or(0) + 1);
+                // A lock consumes any reservation on the chunk
+                c.reserved_by = None;
+                c.reserved_until = None;
             })?;
             
             // Update order's locked amount
             update_order(chunk.order_id, |o| {
-                o.total_locked_usd += chunk.amount_usd;
+                o.total_locked_usd_e6 += chunk.amount_usd_e6;
             })?;
             
             // Orderbook balance will be recalculated on next query
@@ -70,11 +186,14 @@ pub fn unlock_chunks(chunk_ids: &[ChunkId]) -> Result<(), String> {
     for chunk_id in chunk_ids {
         if let Some(chunk) = get_chunk(*chunk_id) {
             let order_id = chunk.order_id;
-            let amount = chunk.amount_usd;
+            let amount = chunk.amount_usd_e6;
             
             if let Some(order) = get_order(order_id) {
-                // Check if current BSV price exceeded max → Idle, else → Available
-                let new_status = if current_bsv_price > order.max_bsv_price {
+                // A cancelled order's chunks don't go back on the book - they wait for
+                // finalize_cancelled_orders to refund them. Otherwise price above max → Idle, else → Available
+                let new_status = if order.finalization_pending_since.is_some() {
+                    ChunkStatus::Refunding
+                } else if current_bsv_price > order.max_bsv_price {
                     ChunkStatus::Idle
                 } else {
                     ChunkStatus::Available
@@ -87,9 +206,9 @@ pub fn unlock_chunks(chunk_ids: &[ChunkId]) -> Result<(), String> {
                 
                 // Update order's locked amount
                 update_order(order_id, |o| {
-                    o.total_locked_usd -= amount;
+                    o.total_locked_usd_e6 = o.total_locked_usd_e6.saturating_sub(amount);
                     if new_status == ChunkStatus::Idle {
-                        o.total_idle_usd += amount;
+                        o.total_idle_usd_e6 += amount;
                     }
                 })?;
                 
@@ -100,13 +219,83 @@ pub fn unlock_chunks(chunk_ids: &[ChunkId]) -> Result<(), String> {
     Ok(())
 }
 
+/// Record that a trade's chunks were released because the filler let the lock time out
+/// Flags the filler to admin each time their timeout count reaches a multiple of the threshold
+pub fn record_timeout_release(chunk_ids: &[ChunkId], filler: Principal, trade_id: TradeId) -> Result<(), String> {
+    for chunk_id in chunk_ids {
+        update_chunk(*chunk_id, |c| {
+            c.timeout_release_count = Some(c.timeout_release_count.unwrap_or(0) + 1);
+            c.last_timeout_filler = Some(filler);
+        })?;
+    }
+    
+    let mut timeout_releases = 0;
+    update_filler_account(filler, |account| {
+        timeout_releases = account.timeout_releases.unwrap_or(0) + 1;
+        account.timeout_releases = Some(timeout_releases);
+    })?;
+    
+    if timeout_releases % FILLER_CHURN_FLAG_THRESHOLD == 0 {
+        ic_cdk::println!("🚩 Filler {} has let {} trades time out", filler, timeout_releases);
+        create_admin_event(AdminEventType::FillerChurnFlagged {
+            filler,
+            trade_id,
+            timeout_releases,
+        });
+    }
+    
+    Ok(())
+}
+
+// ===== RESERVATIONS =====
+
+/// Reserve chunks for `filler` until `expires_at` - they stay Available and on the book
+pub fn reserve_chunks_for(chunk_ids: &[ChunkId], filler: Principal, expires_at: u64) -> Result<(), String> {
+    for chunk_id in chunk_ids {
+        update_chunk(*chunk_id, |c| {
+            c.reserved_by = Some(filler);
+            c.reserved_until = Some(expires_at);
+        })?;
+    }
+    Ok(())
+}
+
+/// Clear the reservation of every chunk `should_release` selects - returns how many were cleared
+fn release_reservations(should_release: impl Fn(&Chunk) -> bool) -> u64 {
+    let reserved: Vec<ChunkId> = CHUNKS.with(|chunks| {
+        chunks.borrow().iter()
+            .filter(|(_, chunk)| chunk.reserved_by.is_some() && should_release(chunk))
+            .map(|(id, _)| id)
+            .collect()
+    });
+
+    for chunk_id in &reserved {
+        let _ = update_chunk(*chunk_id, |c| {
+            c.reserved_by = None;
+            c.reserved_until = None;
+        });
+    }
+    reserved.len() as u64
+}
+
+/// Drop all of a filler's reservations (a new reserve_chunks call replaces them)
+pub fn release_reservations_of(filler: Principal) -> u64 {
+    release_reservations(|chunk| chunk.reserved_by == Some(filler))
+}
+
+/// Called from the cleanup heartbeat - matching already ignores lapsed reservations,
+/// this just clears them from the chunks
+pub fn release_expired_reservations(now: u64) -> u64 {
+    release_reservations(|chunk| chunk.reservation_holder(now).is_none())
+}
+
 pub fn mark_chunks_filled(chunk_ids: &[ChunkId]) -> Result<(), String> {
     let now = get_time();
     
     for chunk_id in chunk_ids {
         if let Some(chunk) = get_chunk(*chunk_id) {
             let order_id = chunk.order_id;
-            let amount = chunk.amount_usd;
+            let amount = chunk.amount_usd_e6;
             
             update_chunk(*chunk_id, |c| {
                 c.status = ChunkStatus::Filled;
@@ -117,67 +306,95 @@ pub fn mark_chunks_filled(chunk_ids: &[ChunkId]) -> Result<(), String> {
             // Update order's filled and locked amounts
             // Note: Platform fees (2.9%) already collected upfront during order activation
             update_order(order_id, |o| {
-                o.total_filled_usd += amount;
+                o.total_filled_usd_e6 += amount;
                 // Decrease locked amount since chunk is now filled
-                o.total_locked_usd -= amount;
+                o.total_locked_usd_e6 = o.total_locked_usd_e6.saturating_sub(amount);
                 
                 // Check if order is fully filled
-                if (o.total_filled_usd - o.amount_usd).abs() < 0.000001 || o.total_filled_usd >= o.amount_usd {
+                if o.total_filled_usd_e6 >= o.amount_usd_e6 {
                     o.status = OrderStatus::Filled;
-                } else if o.total_filled_usd > 0.0 {
+                } else if o.total_filled_usd_e6 > 0 {
                     o.status = OrderStatus::PartiallyFilled;
                 }
             })?;
+            
+            if let Some(order) = get_order(order_id) {
+                crate::rebates::record_filled_volume(order.maker, amount);
+            }
         }
     }
     Ok(())
 }
 
+/// Listed chunks only - see Chunk::is_listed
 pub fn get_active_chunks() -> Vec<OrderbookChunk> {
     let available_chunks = get_available_chunks();
     
     available_chunks.iter()
+        .filter(|chunk| chunk.is_listed())
         .map(|chunk| {
             // Convert f64 max_bsv_price to cents (chunk has its own max_bsv_price)
             let max_price_cents = (chunk.max_bsv_price * 100.0).round() as u64;
             
             OrderbookChunk {
+                chunk_id: chunk.id,
                 order_id: chunk.order_id,
-                amount_usd: chunk.amount_usd,
+                amount_usd_e6: chunk.amount_usd_e6,
                 max_price_per_bsv_in_cents: max_price_cents,
+                filler_incentive_bps: chunk.filler_incentive_bps.unwrap_or(FILLER_INCENTIVE_PERCENT),
             }
         })
         .collect()
 }
 
-pub fn get_active_chunks_paginated(offset: u64, limit: u64) -> PaginatedChunks {
-    // Optimized: Calculate total and paginate at storage level instead of loading all chunks
+/// Listed chunks of one book
+pub fn get_book_active_chunks(book_id: BookId) -> Vec<OrderbookChunk> {
+    get_available_chunks().into_iter()
+        .filter(|chunk| chunk.is_listed() && chunk.book_id() == book_id)
+        .map(to_orderbook_chunk)
+        .collect()
+}
+
+pub(crate) fn to_orderbook_chunk(chunk: Chunk) -> OrderbookChunk {
+    // Convert f64 max_bsv_price to cents (chunk has its own max_bsv_price)
+    let max_price_cents = (chunk.max_bsv_price * 100.0).round() as u64;
+    
+    OrderbookChunk {
+        chunk_id: chunk.id,
+        order_id: chunk.order_id,
+        amount_usd_e6: chunk.amount_usd_e6,
+        max_price_per_bsv_in_cents: max_price_cents,
+        filler_incentive_bps: chunk.filler_incentive_bps.unwrap_or(FILLER_INCENTIVE_PERCENT),
+    }
+}
+
+/// Cursor page of a book's listed chunks in FIFO (ascending ID) order
+pub fn get_active_chunks_page(after_id: Option<ChunkId>, limit: u64, book_id: BookId) -> ChunkPage {
+    let limit = limit.min(MAX_PAGE_LIMIT) as usize;
+    
+    let (chunks, next_cursor) = CHUNKS.with(|chunks_map| {
+        scan_page(&chunks_map.borrow(), after_id, limit, false, |chunk| chunk.is_listed() && chunk.book_id() == book_id)
+    });
+    
+    ChunkPage {
+        chunks: chunks.into_iter().map(to_orderbook_chunk).collect(),
+        next_cursor,
+    }
+}
+
+/// Offset wrapper over the same FIFO scan as get_active_chunks_page
+pub fn get_active_chunks_paginated(offset: u64, limit: u64, book_id: BookId) -> PaginatedChunks {
+    let keep = |chunk: &Chunk| chunk.is_listed() && chunk.book_id() == book_id;
+    
     let (chunks, total) = CHUNKS.with(|chunks_map| {
-        let available: Vec<Chunk> = chunks_map.borrow().iter()
-            .filter(|(_, chunk)| chunk.status == ChunkStatus::Available)
-            .map(|(_, chunk)| chunk)
-            .collect();
-        
-        let total = available.len() as u64;
-        
-        // Convert to OrderbookChunk and paginate
-        let start = offset as usize;
-        let paginated_chunks: Vec<OrderbookChunk> = available.into_iter()
-            .skip(start)
-            .take(limit as usize)
-            .map(|chunk| {
-                // Convert f64 max_bsv_price to cents (chunk has its own max_bsv_price)
-                let max_price_cents = (chunk.max_bsv_price * 100.0).round() as u64;
-                
-                OrderbookChunk {
-                    order_id: chunk.order_id,
-                    amount_usd: chunk.amount_usd,
-                    max_price_per_bsv_in_cents: max_price_cents,
-                }
-            })
+        let map = chunks_map.borrow();
+        let total = map.iter().filter(|(_, chunk)| keep(chunk)).count() as u64;
+        let (page, _) = scan_page(&map, None, offset.saturating_add(limit) as usize, false, keep);
+        let chunks: Vec<OrderbookChunk> = page.into_iter()
+            .skip(offset as usize)
+            .map(to_orderbook_chunk)
             .collect();
-        
-        (paginated_chunks, total)
+        (chunks, total)
     });
     
     PaginatedChunks {
@@ -188,21 +405,66 @@ pub fn get_active_chunks_paginated(offset: u64, limit: u64) -> PaginatedChunks {
     }
 }
 
-pub fn get_orderbook_stats() -> OrderbookStats {
+/// Public orderbook size - the listed chunks (state::get_available_orderbook counts hidden ones too)
+pub fn get_listed_orderbook_e6() -> UsdE6 {
+    CHUNKS.with(|chunks| {
+        chunks.borrow().iter()
+            .filter(|(_, chunk)| chunk.is_listed())
+            .map(|(_, chunk)| chunk.amount_usd_e6)
+            .sum()
+    })
+}
+
+/// Listed size of one book
+pub fn get_listed_book_e6(book_id: BookId) -> UsdE6 {
+    CHUNKS.with(|chunks| {
+        chunks.borrow().iter()
+            .filter(|(_, chunk)| chunk.is_listed() && chunk.book_id() == book_id)
+            .map(|(_, chunk)| chunk.amount_usd_e6)
+            .sum()
+    })
+}
+
+/// USD held in Locked chunks across all open trades
+pub fn get_total_locked_e6() -> UsdE6 {
+    CHUNKS.with(|chunks| {
+        chunks.borrow().iter()
+            .filter(|(_, chunk)| chunk.status == ChunkStatus::Locked)
+            .map(|(_, chunk)| chunk.amount_usd_e6)
+            .sum()
+    })
+}
+
+/// Open interest per filler - same trade statuses as calculate_pending_trades_for_filler
+pub fn get_open_interest_by_filler() -> std::collections::BTreeMap<Principal, UsdE6> {
+    TRADES.with(|trades| {
+        let mut by_filler = std::collections::BTreeMap::new();
+        for (_, trade) in trades.borrow().iter() {
+            if matches!(trade.status, TradeStatus::ChunksLocked | TradeStatus::TxSubmitted | TradeStatus::ReadyForRelease) {
+                *by_filler.entry(trade.filler).or_insert(0) += trade.amount_usd_e6;
+            }
+        }
+        by_filler
+    })
+}
+
+/// Orderbook stats in fixed point - get_orderbook_stats and the v2 API are both derived from this
+pub fn get_orderbook_stats_v2() -> OrderbookStatsV2 {
     // Optimized: Calculate stats in a single pass instead of loading all chunks then filtering multiple times
-    let (total_active_chunks, total_available_usd, total_locked_usd) = CHUNKS.with(|chunks| {
+    let (total_active_chunks, total_available_usd_e6, total_locked_usd_e6) = CHUNKS.with(|chunks| {
         let mut active_count = 0u64;
-        let mut available_sum = 0.0;
-        let mut locked_sum = 0.0;
+        let mut available_sum: UsdE6 = 0;
+        let mut locked_sum: UsdE6 = 0;
         
         for (_, chunk) in chunks.borrow().iter() {
             match chunk.status {
-                ChunkStatus::Available => {
+                // Hidden chunks are left out of the public totals
+                ChunkStatus::Available if chunk.hidden != Some(true) => {
                     active_count += 1;
-                    available_sum += chunk.amount_usd;
+                    available_sum += chunk.amount_usd_e6;
                 }
                 ChunkStatus::Locked => {
-                    locked_sum += chunk.amount_usd;
+                    locked_sum += chunk.amount_usd_e6;
                 }
                 _ => {}
             }
@@ -221,14 +483,142 @@ pub fn get_orderbook_stats() -> OrderbookStats {
     
     let (current_bsv_price, _) = get_cached_bsv_price();
     
-    OrderbookStats {
+    let largest_filler_open_interest_e6 = get_open_interest_by_filler().into_values().max().unwrap_or(0);
+    
+    let limits = get_exposure_limits();
+    
+    OrderbookStatsV2 {
         total_active_chunks,
-        total_available_usd,
-        total_locked_usd,
+        total_available_usd_e6,
+        total_locked_usd_e6,
         total_orders,
         total_trades,
+        current_bsv_price_cents: (current_bsv_price * 100.0).round() as u64,
+        largest_filler_open_interest_usd_e6: largest_filler_open_interest_e6,
+        max_filler_open_interest_usd_e6: limits.max_filler_open_interest_e6,
+        max_total_locked_usd_e6: limits.max_total_locked_e6,
+    }
+}
+
+pub fn get_orderbook_stats() -> OrderbookStats {
+    let stats = get_orderbook_stats_v2();
+    let (current_bsv_price, _) = get_cached_bsv_price();
+    
+    OrderbookStats {
+        total_active_chunks: stats.total_active_chunks,
+        total_available_usd: e6_to_usd(stats.total_available_usd_e6),
+        total_locked_usd: e6_to_usd(stats.total_locked_usd_e6),
+        total_orders: stats.total_orders,
+        total_trades: stats.total_trades,
         current_bsv_price,
+        largest_filler_open_interest_usd: e6_to_usd(stats.largest_filler_open_interest_usd_e6),
+        max_filler_open_interest_usd: e6_to_usd(stats.max_filler_open_interest_usd_e6),
+        max_total_locked_usd: e6_to_usd(stats.max_total_locked_usd_e6),
     }
 }
 
 use crate::state::{CHUNKS, ORDERS, TRADES};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(id: OrderId, filler_incentive_bps: Option<u64>) -> Order {
+        Order {
+            id,
+            maker: Principal::from_slice(&[1, 1, 1]),
+            amount_usd_e6: 0,
+            total_deposited_usd_e6: None,
+            activation_fee_usd_e6: None,
+            filler_incentive_reserved_e6: None,
+            filler_incentive_bps,
+            deposit_principal: String::new(),
+            deposit_subaccount: String::new(),
+            max_bsv_price: 100.0,
+            allow_partial_fill: true,
+            bsv_address: String::new(),
+            status: OrderStatus::Active,
+            chunks: Vec::new(),
+            created_at: 0,
+            deposit_confirmed_at: None,
+            funded_at: None,
+            activation_fee_block_index: None,
+            activation_fee_confirmed_at: None,
+            total_filled_usd_e6: 0,
+            total_locked_usd_e6: 0,
+            total_idle_usd_e6: 0,
+            total_refunded_usd_e6: None,
+            refund_attempts: Vec::new(),
+            finalization_pending_since: None,
+            hidden: None,
+            idle_since: None,
+            last_idle_at: None,
+            idle_duration_total_ns: None,
+            auto_cancel_above_price: None,
+            post_only: None,
+            book_id: None,
+        }
+    }
+
+    /// Available chunks of order_id, IDs numbered from order_id * 100
+    fn chunks(order_id: OrderId, amounts_usd: &[u64]) -> Vec<Chunk> {
+        amounts_usd.iter().enumerate().map(|(i, usd)| Chunk {
+            id: order_id * 100 + i as u64,
+            order_id,
+            amount_usd_e6: usd * 1_000_000,
+            status: ChunkStatus::Available,
+            locked_by: None,
+            filled_at: None,
+            bsv_address: String::new(),
+            sats_amount: None,
+            max_bsv_price: 100.0,
+            filler_incentive_bps: None,
+            lock_count: None,
+            timeout_release_count: None,
+            last_timeout_filler: None,
+            reserved_by: None,
+            reserved_until: None,
+            hidden: None,
+            book_id: None,
+        }).collect()
+    }
+
+    /// (order, USD taken) per pick
+    fn filled(picks: &[(OrderId, Vec<Chunk>)]) -> Vec<(OrderId, u64)> {
+        picks.iter()
+            .map(|(order_id, chunks)| (*order_id, chunks.iter().map(|chunk| chunk.amount_usd_e6).sum::<UsdE6>() / 1_000_000))
+            .collect()
+    }
+
+    #[test]
+    fn fifo_drains_the_oldest_order_and_skips_chunks_too_large_for_the_rest() {
+        let candidates = vec![(order(1, None), chunks(1, &[3, 3])), (order(2, Some(800)), chunks(2, &[5, 1]))];
+        assert_eq!(filled(&select_chunks(MatchingPolicy::Fifo, candidates.clone(), 8_000_000, 10)), vec![(1, 6), (2, 1)]);
+        // One chunk per order leaves room for order 2's larger chunk
+        assert_eq!(filled(&select_chunks(MatchingPolicy::Fifo, candidates, 8_000_000, 1)), vec![(1, 3), (2, 5)]);
+    }
+
+    #[test]
+    fn best_incentive_takes_the_highest_offer_first_and_ties_stay_fifo() {
+        let candidates = vec![
+            (order(1, None), chunks(1, &[3])),
+            (order(2, Some(800)), chunks(2, &[3])),
+            (order(3, Some(FILLER_INCENTIVE_PERCENT)), chunks(3, &[3])),
+            (order(4, Some(800)), chunks(4, &[3])),
+        ];
+        let picks = select_chunks(MatchingPolicy::BestIncentive, candidates, 9_000_000, 10);
+        assert_eq!(filled(&picks), vec![(2, 3), (4, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn pro_rata_splits_by_available_amount_and_hands_out_the_remainder() {
+        let candidates = || vec![(order(1, None), chunks(1, &[3, 3, 3, 3])), (order(2, None), chunks(2, &[3, 3]))];
+        // $9 of $18 - half of each
+        assert_eq!(filled(&select_chunks(MatchingPolicy::ProRata, candidates(), 9_000_000, 10)), vec![(1, 6), (2, 3)]);
+        // $6 rounds order 2's $2 share down to nothing; the leftover chunk goes to the oldest order
+        assert_eq!(filled(&select_chunks(MatchingPolicy::ProRata, candidates(), 6_000_000, 10)), vec![(1, 6)]);
+        // More than the book holds fills everything
+        assert_eq!(filled(&select_chunks(MatchingPolicy::ProRata, candidates(), 30_000_000, 10)), vec![(1, 12), (2, 6)]);
+        assert!(select_chunks(MatchingPolicy::ProRata, Vec::new(), 9_000_000, 10).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+/// Circuit breaker on extreme BSV price moves
+/// Every adopted oracle price is compared against the others seen in the last
+/// CIRCUIT_BREAKER_WINDOW_NS. A high/low spread above CIRCUIT_BREAKER_MOVE_BPS halts new
+/// trades and idle chunk reactivation, so makers aren't filled at the peak of a flash spike.
+/// The halt lifts on the first sample after CIRCUIT_BREAKER_COOLDOWN_NS without a breach
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use crate::types::*;
+use crate::state::*;
+use crate::config::{CIRCUIT_BREAKER_MOVE_BPS, CIRCUIT_BREAKER_WINDOW_NS, CIRCUIT_BREAKER_COOLDOWN_NS};
+
+thread_local! {
+    // Recent (timestamp, price) samples - heap only, the window refills after an upgrade
+    static RECENT_PRICES: RefCell<VecDeque<(u64, f64)>> = RefCell::new(VecDeque::new());
+}
+
+/// Feed an adopted oracle price - trips or resets the breaker as needed
+pub fn record_price_sample(price: f64, now: u64) {
+    let (low_price, high_price) = RECENT_PRICES.with(|r| {
+        let mut recent = r.borrow_mut();
+        recent.push_back((now, price));
+        while recent.front().is_some_and(|(t, _)| now.saturating_sub(*t) > CIRCUIT_BREAKER_WINDOW_NS) {
+            recent.pop_front();
+        }
+        recent.iter().fold((price, price), |(low, high), (_, p)| (low.min(*p), high.max(*p)))
+    });
+
+    let move_bps = if low_price > 0.0 {
+        ((high_price - low_price) / low_price * 10_000.0) as u64
+    } else {
+        0
+    };
+
+    match get_circuit_breaker() {
+        Some(mut breaker) => {
+            if move_bps > CIRCUIT_BREAKER_MOVE_BPS {
+                breaker.last_breach_at = now;
+                set_circuit_breaker(Some(breaker));
+            } else if now.saturating_sub(breaker.last_breach_at) >= CIRCUIT_BREAKER_COOLDOWN_NS {
+                ic_cdk::println!("🟢 Circuit breaker reset - price stable at ${:.4}", price);
+                set_circuit_breaker(None);
+                create_admin_event(AdminEventType::CircuitBreakerReset {
+                    tripped_at: breaker.tripped_at,
+                });
+            }
+        }
+        None if move_bps > CIRCUIT_BREAKER_MOVE_BPS => {
+            ic_cdk::println!(
+                "🛑 Circuit breaker tripped - BSV moved {}bps (${:.4} - ${:.4}), trading halted",
+                move_bps, low_price, high_price
+            );
+            set_circuit_breaker(Some(CircuitBreakerState {
+                tripped_at: now,
+                last_breach_at: now,
+                low_price,
+                high_price,
+                move_bps,
+            }));
+            create_admin_event(AdminEventType::CircuitBreakerTripped {
+                low_price,
+                high_price,
+                move_bps,
+            });
+        }
+        None => {}
+    }
+}
+
+/// True while new trades and reactivations must wait
+/// Also false once the cooldown has passed but no sample has cleared the state yet
+pub fn is_trading_halted(now: u64) -> bool {
+    get_circuit_breaker()
+        .is_some_and(|b| now.saturating_sub(b.last_breach_at) < CIRCUIT_BREAKER_COOLDOWN_NS)
+}
+
+pub fn get_status() -> Option<CircuitBreakerState> {
+    get_circuit_breaker().filter(|_| is_trading_halted(get_time()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE_NS: u64 = 60 * 1_000_000_000;
+
+    #[test]
+    fn spike_within_the_window_trips_and_a_quiet_cooldown_resets() {
+        let start = 1_000 * MINUTE_NS;
+        record_price_sample(40.0, start);
+        record_price_sample(42.0, start + MINUTE_NS);
+        assert!(!is_trading_halted(start + MINUTE_NS));
+
+        // +25% on the window's low
+        record_price_sample(50.0, start + 2 * MINUTE_NS);
+        assert!(is_trading_halted(start + 2 * MINUTE_NS));
+        let breaker = get_circuit_breaker().unwrap();
+        assert_eq!((breaker.low_price, breaker.high_price, breaker.move_bps), (40.0, 50.0, 2500));
+
+        // The old samples age out, but the halt holds for the cooldown after the last breach
+        let calm = start + 2 * MINUTE_NS + CIRCUIT_BREAKER_WINDOW_NS + MINUTE_NS;
+        record_price_sample(50.0, calm);
+        assert!(is_trading_halted(calm));
+        assert!(get_circuit_breaker().is_some());
+
+        let after_cooldown = start + 2 * MINUTE_NS + CIRCUIT_BREAKER_COOLDOWN_NS;
+        assert!(!is_trading_halted(after_cooldown), "halt lapses even before a sample clears it");
+        record_price_sample(50.0, after_cooldown);
+        assert!(get_circuit_breaker().is_none());
+        assert!(matches!(
+            get_admin_events()[0].event_type,
+            AdminEventType::CircuitBreakerReset { tripped_at } if tripped_at == start + 2 * MINUTE_NS
+        ));
+    }
+
+    #[test]
+    fn slow_drift_outside_the_window_does_not_trip() {
+        let step = CIRCUIT_BREAKER_WINDOW_NS / 2 + MINUTE_NS;
+        let mut price = 50.0;
+        for i in 0..6 {
+            record_price_sample(price, i * step);
+            assert!(!is_trading_halted(i * step));
+            price *= 1.06;
+        }
+    }
+}
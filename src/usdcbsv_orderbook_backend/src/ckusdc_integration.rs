@@ -2,41 +2,13 @@ use candid::{CandidType, Deserialize, Principal, Nat};
 use serde::Serialize;
 use ic_cdk::api::management_canister::main::CanisterId;
 use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError, Memo};
 use sha2::{Sha256, Digest};
+use crate::runtime::LedgerClient;
 
 // ckUSDC Ledger Canister ID (Ethereum)
 pub const CKUSDC_LEDGER_CANISTER_ID: &str = "xevnm-gaaaa-aaaar-qafnq-cai";
 
-// ICRC-1 Transfer Arguments
-#[derive(CandidType, Deserialize)]
-pub struct TransferArg {
-    pub from_subaccount: Option<Vec<u8>>,
-    pub to: Account,
-    pub amount: Nat,
-    pub fee: Option<Nat>,
-    pub memo: Option<Vec<u8>>,
-    pub created_at_time: Option<u64>,
-}
-
-// ICRC-1 Transfer Result
-#[derive(CandidType, Deserialize)]
-pub enum TransferResult {
-    Ok(Nat),
-    Err(TransferError),
-}
-
-#[derive(CandidType, Deserialize, Debug)]
-pub enum TransferError {
-    BadFee { expected_fee: Nat },
-    BadBurn { min_burn_amount: Nat },
-    InsufficientFunds { balance: Nat },
-    TooOld,
-    CreatedInFuture { ledger_time: u64 },
-    Duplicate { duplicate_of: Nat },
-    TemporarilyUnavailable,
-    GenericError { error_code: Nat, message: String },
-}
-
 // Deposit information for frontend
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct DepositInfo {
@@ -67,7 +39,7 @@ pub fn order_subaccount(maker: Principal, order_id: u64) -> [u8; 32] {
 /// Get ICRC-1 Account for an order's deposit subaccount
 /// Account owner is this canister, subaccount is derived from maker+order_id
 pub fn get_order_deposit_account(maker: Principal, order_id: u64) -> Account {
-    let owner = ic_cdk::id(); // This canister
+    let owner = crate::runtime::canister_id(); // This canister
     let subaccount = order_subaccount(maker, order_id);
     Account {
         owner,
@@ -77,7 +49,7 @@ pub fn get_order_deposit_account(maker: Principal, order_id: u64) -> Account {
 
 /// Get full deposit information for frontend
 pub async fn get_deposit_info_for_order(maker: Principal, order_id: u64) -> Result<DepositInfo, String> {
-    let canister_id = ic_cdk::api::id();
+    let canister_id = crate::runtime::canister_id();
     let subaccount = order_subaccount(maker, order_id);
     let subaccount_hex = hex::encode(&subaccount);
     
@@ -98,21 +70,17 @@ pub async fn get_deposit_info_for_order(maker: Principal, order_id: u64) -> Resu
 /// Check the ckUSDC balance for a specific order's subaccount
 pub async fn get_order_ckusdc_balance(maker: Principal, order_id: u64) -> Result<u128, String> {
     let subaccount = order_subaccount(maker, order_id);
-    let this_canister = ic_cdk::api::id();
+    let this_canister = crate::runtime::canister_id();
     
-    let ledger_principal = Principal::from_text(CKUSDC_LEDGER_CANISTER_ID)
-        .map_err(|e| format!("Invalid ledger principal: {}", e))?;
+    // The order's book settlement token - ckUSDC unless the order belongs to another book
+    let ledger_principal = crate::books::order_ledger(order_id);
     
     let account = Account {
         owner: this_canister,
         subaccount: Some(subaccount),
     };
     
-    let result: Result<(Nat,), _> = ic_cdk::call(
-        ledger_principal,
-        "icrc1_balance_of",
-        (account,),
-    ).await;
+    let result = crate::runtime::ledger().icrc1_balance_of(ledger_principal, account).await;
     
     match result {
         Ok((balance,)) => {
@@ -135,32 +103,38 @@ async fn transfer_ckusdc_from_order_raw(
 ) -> Result<u64, String> {
     let from_subaccount = order_subaccount(maker, order_id);
     
-    let ledger_principal = Principal::from_text(CKUSDC_LEDGER_CANISTER_ID)
-        .map_err(|e| format!("Invalid ledger principal: {}", e))?;
+    // The order's book settlement token - ckUSDC unless the order belongs to another book
+    let ledger_principal = crate::books::order_ledger(order_id);
     
     let arg = TransferArg {
-        from_subaccount: Some(from_subaccount.to_vec()), // Convert [u8; 32] to Vec<u8>
+        from_subaccount: Some(from_subaccount),
         to: Account {
             owner: to_principal,
             subaccount: to_subaccount,
         },
         amount: Nat::from(amount_e6),
         fee: None, // Use default fee
-        memo,
-        created_at_time: None,
+        // Deterministic memo + first-attempt time, so a retried payout is deduplicated by the ledger
+        created_at_time: memo.as_deref().map(crate::transfer_dedup::created_at_time),
+        memo: memo.clone().map(Memo::from),
     };
     
-    let result: Result<(TransferResult,), _> = ic_cdk::call(
-        ledger_principal,
-        "icrc1_transfer",
-        (arg,),
-    ).await;
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_principal, arg).await;
+    
+    if let (Ok(_), Some(memo)) = (&result, &memo) {
+        crate::transfer_dedup::settle(memo);
+    }
     
     match result {
-        Ok((TransferResult::Ok(block_index),)) => {
+        Ok((Ok(block_index),)) => {
             nat_to_u64(&block_index)
         },
-        Ok((TransferResult::Err(err),)) => {
+        // An earlier attempt whose reply was lost already went through
+        Ok((Err(TransferError::Duplicate { duplicate_of }),)) => {
+            ic_cdk::println!("♻️ Transfer from order {} already executed at block {}", order_id, duplicate_of);
+            nat_to_u64(&duplicate_of)
+        },
+        Ok((Err(err),)) => {
             // Format error with human-readable USD values
             match err {
                 TransferError::InsufficientFunds { balance } => {
@@ -252,9 +226,9 @@ pub async fn transfer_activation_fee_to_treasury(
 }
 
 /// Convert USD amount to ckUSDC base units (6 decimals)
-/// Only rounds at the final conversion to u128 (blockchain requires integer)
+/// ckUSDC base units are micro-USD, so this matches `usd_to_e6`
 pub fn usd_to_ckusdc_e6(usd_amount: f64) -> u128 {
-    (usd_amount * 1_000_000.0) as u128
+    crate::types::usd_to_e6(usd_amount) as u128
 }
 
 /// Convert ckUSDC base units to USD with full precision
@@ -6,21 +6,21 @@
 pub const ADMIN_PRINCIPAL: &str = "dow63-puub5-ne7wq-knc6a-i3tqs-ur75n-ozxkz-ad22e-frcrk-vq5jo-jae";
 
 // ============== CHUNK SIZE CONFIGURATION ==============
-// Minimum chunk size in USD (floating point)
-pub const MIN_CHUNK_SIZE: f64 = 3.0;
+// Minimum chunk size in micro-USD (1 USD = 1_000_000)
+pub const MIN_CHUNK_SIZE_E6: u64 = 3_000_000; // $3
 
 // Maximum number of chunks allowed per order
 pub const MAX_CHUNKS_ALLOWED: usize = 30; // 30 chunks
 
 // ============== ORDERBOOK LIMITS ==============
-// Maximum total value of available orders in the orderbook (USD)
+// Maximum total value of available orders in the orderbook (micro-USD)
 // This prevents the orderbook from growing too large
 // When limit is reached, new orders are rejected until:
 //   - Fillers clear existing orders (chunks filled)
 //   - Prices move and orders go idle (delisted)
 //   - Makers cancel orders
 //
-pub const MAX_ORDERBOOK_USD_LIMIT: f64 = 2000.0; // $2,000
+pub const MAX_ORDERBOOK_USD_LIMIT_E6: u64 = 2_000_000_000; // $2,000
 
 // ============== BLOCK SYNC CONFIGURATION ==============
 // Number of block confirmations required before claiming USDC
@@ -31,11 +31,59 @@ pub const MAX_ORDERBOOK_USD_LIMIT: f64 = 2000.0; // $2,000
 // TO CHANGE: Adjust based on your security requirements
 pub const CONFIRMATION_DEPTH: u64 = 18; // Blocks required for safe confirmation (default: 18 = ~3 hours)
 
+// Confirmation depth by trade size (defaults - adjustable at runtime via ConfirmationDepthSchedule)
+// Small fills risk little in a reorg, so they release sooner; everything else waits CONFIRMATION_DEPTH
+pub const CONFIRMATION_DEPTH_TIERS: [(u64, u64); 1] = [
+    (100_000_000, 6), // Trades under $100: 6 blocks (~1 hour)
+];
+pub const MAX_CONFIRMATION_DEPTH: u64 = 36; // Upper bound for any tier
+
+// Instant release - fillers who opt in claim small trades after INSTANT_RELEASE_CONFIRMATIONS,
+// with the released amount bonded from their security deposit until CONFIRMATION_DEPTH
+pub const INSTANT_RELEASE_MAX_TRADE_USD_E6: u64 = 100_000_000; // Trades under $100
+pub const INSTANT_RELEASE_CONFIRMATIONS: u64 = 1;
+// A reorg that replaces a bond's block often re-mines the payment on the new branch - the
+// bond is only forfeited if the payment isn't proven there within this window
+pub const RELEASE_BOND_REPROOF_WINDOW_NS: u64 = 6 * 60 * 60 * 1_000_000_000; // 6 hours
+
+// Request headers carrying provider API keys (paid tiers with higher rate limits)
+// Keys are set at runtime by admin - see admin_set_provider_api_key
+pub const WOC_API_KEY_HEADER: &str = "Authorization";
+pub const BITAILS_API_KEY_HEADER: &str = "apikey";
+
+// Header/transaction API roots per BSV network - the canister follows the network set at
+// runtime (admin_set_bsv_network), so a staging deployment can run against testnet
+pub const WOC_MAINNET_API: &str = "https://api.whatsonchain.com/v1/bsv/main";
+pub const WOC_TESTNET_API: &str = "https://api.whatsonchain.com/v1/bsv/test";
+pub const BITAILS_MAINNET_API: &str = "https://api.bitails.io";
+pub const BITAILS_TESTNET_API: &str = "https://test-api.bitails.io";
+
+// Maximum accepted API key length
+pub const MAX_API_KEY_LENGTH: usize = 256;
+
+// HTTP outcall retry/backoff
+// A failing API host is skipped until its backoff expires, so retries happen on later
+// timer ticks instead of repeatedly inside one message
+// Delay = BASE * 2^(attempt-1), capped at MAX, plus up to 25% deterministic jitter
+pub const OUTCALL_BASE_BACKOFF_SECONDS: u64 = 60; // 1 minute
+pub const OUTCALL_MAX_BACKOFF_SECONDS: u64 = 60 * 60; // 1 hour
+// After this many failures in a row the host is retried every OUTCALL_MAX_BACKOFF_SECONDS
+pub const OUTCALL_MAX_ATTEMPTS: u32 = 8;
+
 // Sync interval for block headers (seconds)
 pub const SYNC_INTERVAL_SECONDS: u64 = 15 * 60; // 15 minutes
 
+// claim_usdc counts confirmations against the local tip - older than this and it syncs on demand
+// first (default - adjustable at runtime, between BLOCK_SYNC_INTERVAL_SECONDS and the max)
+pub const MAX_CHAIN_TIP_AGE_SECONDS: u64 = 60 * 60; // 1 hour
+pub const MAX_CHAIN_TIP_AGE_LIMIT_SECONDS: u64 = 24 * 60 * 60;
+
+// request_block_sync only brings the scheduled sync forward once headers are this old - which
+// also limits on-demand syncs to one per this interval, however many users ask
+pub const ON_DEMAND_SYNC_MIN_AGE_SECONDS: u64 = 2 * 60;
+
 // ============== MAKER LIMITS ==============
-// Maximum total value of active orders per maker (USD)
+// Maximum total value of active orders per maker (micro-USD)
 // This protects users from accidentally placing too many orders
 // and limits exposure in case of errors
 // 
@@ -48,18 +96,80 @@ pub const SYNC_INTERVAL_SECONDS: u64 = 15 * 60; // 15 minutes
 //   - Existing orders are filled (chunks completed)
 //   - Existing orders are cancelled/refunded
 // 
-pub const MAX_MAKER_TOTAL_ORDERS_USD: f64 = 270.0; 
+pub const MAX_MAKER_TOTAL_ORDERS_USD_E6: u64 = 270_000_000; // $270
+
+// Maximum orders per create_orders_batch call
+// Each order costs several ledger calls, so large batches risk running into message limits
+pub const MAX_ORDERS_PER_BATCH: usize = 10;
+
+// Maximum orders touched by one cancel_all_my_orders / update_max_bsv_price_bulk call
+pub const MAX_BULK_ORDER_OPERATIONS: usize = 50;
+
+// Maximum number of order IDs scanned per recover_orphaned_deposits call
+// Each scanned ID costs one ledger balance query, so keep this small
+pub const MAX_ORPHAN_SCAN_PER_CALL: u64 = 20;
+
+// Maximum fillers a maker can block from matching their chunks
+pub const MAX_BLOCKED_FILLERS_PER_MAKER: usize = 100;
+
+// Maximum operator principals (bots, session keys) one maker or filler can register
+pub const MAX_OPERATORS_PER_PRINCIPAL: usize = 5;
+
+// Longest expiry a session delegation may ask for - matches Internet Identity's session cap
+pub const MAX_DELEGATION_LIFETIME_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+// ============== QUOTES (RFQ) ==============
+// Fillers post quotes (amount, price, expiry) that makers accept against their own orders
+pub const MAX_QUOTE_LIFETIME_SECONDS: u64 = 24 * 60 * 60;
+pub const MAX_OPEN_QUOTES_PER_FILLER: usize = 20;
+
+// Accepted/cancelled/expired quotes are deleted by the daily cleanup after this long
+pub const QUOTE_RETENTION_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+// ============== CHUNK RESERVATIONS ==============
+// Fillers soft-reserve chunks between a UI quote and create_trades - at most this long
+pub const MAX_CHUNK_RESERVATION_SECONDS: u64 = 120;
+
+// ============== PAGINATION ==============
+// Upper bound on the limit of every paginated query - larger requests get LimitExceeded
+pub const MAX_PAGE_LIMIT: u64 = 200;
+// Offset pages walk every record before the offset - past this, page by cursor instead
+pub const MAX_PAGE_OFFSET: u64 = 100_000;
+
+// Orders + trades returned per export_my_data page (orders carry up to MAX_CHUNKS_ALLOWED chunks)
+pub const MAX_EXPORT_RECORDS_PER_PAGE: usize = 100;
+
+// Orderbook changes kept for get_orderbook_delta - clients further behind must take a new snapshot
+pub const ORDERBOOK_CHANGES_RETAINED: u64 = 10_000;
+
+// Changes returned per get_orderbook_delta call
+pub const MAX_ORDERBOOK_DELTA_CHANGES: usize = 500;
+
+// Public API version - v1 endpoint signatures are frozen, changed signatures ship as v2_* side by side
+pub const API_VERSION: u32 = 2;
+
+// Cache-Control max-age on certified http_request responses (they are re-certified every scheduler tick)
+pub const HTTP_CACHE_MAX_AGE_SECONDS: u64 = 10;
 
 // ============== OTHER CONSTANTS ==============
 pub const SATOSHIS_PER_BSV: u64 = 100_000_000;
 
+// Output amount tolerance recorded on each new trade (larger bound applies)
+// Absorbs floating-point rounding between the canister's sats_amount and wallet rounding
+pub const SATS_TOLERANCE_ABSOLUTE: u64 = 10;
+pub const SATS_TOLERANCE_BASIS_POINTS: u64 = 5; // 0.05%
+
 // Fee structure
-pub const MAKER_FEE_PERCENT: u64 = 700;  // 7.0% total fee shown to maker (in basis points: 700/10000)
 pub const ACTIVATION_FEE_PERCENT: u64 = 250; // 2.5% activation fee to treasury (in basis points: 250/10000)
-pub const FILLER_INCENTIVE_PERCENT: u64 = 450; // 4.5% reserved for filler bonus (in basis points: 450/10000)
-// Maker deposits order + 7.0% fee
+pub const FILLER_INCENTIVE_PERCENT: u64 = 450; // 4.5% default filler bonus (in basis points: 450/10000)
+// Maker deposits order + activation fee + the order's filler incentive (7.0% at the default)
 // Platform takes 2.5% upfront to treasury (ACTIVATION_FEE_PERCENT) non refundable
-// 4.5% stays in order address for filler bonus on filled chunks value (FILLER_INCENTIVE_PERCENT)
+// The incentive stays in order address for filler bonus on filled chunks value
+// Makers may offer their own incentive within admin-set bounds (see admin_set_filler_incentive_bounds)
+// Bounds must include FILLER_INCENTIVE_PERCENT, which orders created without one use
+pub const FILLER_INCENTIVE_MIN_BPS: u64 = 200;     // Default lower bound (2.0%)
+pub const FILLER_INCENTIVE_MAX_BPS: u64 = 1_000;   // Default upper bound (10.0%)
+pub const FILLER_INCENTIVE_CEILING_BPS: u64 = 2_000; // Highest upper bound admin can set (20.0%)
 
 // Security deposit percentage (10% of trade amount)
 pub const SECURITY_DEPOSIT_PERCENT: u64 = 10;
@@ -68,6 +178,28 @@ pub const SECURITY_DEPOSIT_PERCENT: u64 = 10;
 // With 10% security, allows locking 10x the deposit amount
 pub const MAX_LOCK_MULTIPLIER: u64 = 10;
 
+// Trusted filler tier - a reduced deposit (and so a higher lock multiplier) for fillers with a long
+// clean record. Any penalty demotes them until it is TRUSTED_FILLER_CLEAN_WINDOW_NS old
+pub const TRUSTED_FILLER_MIN_SUCCESSFUL_TRADES: u64 = 50;
+pub const TRUSTED_FILLER_CLEAN_WINDOW_NS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000; // 30 days
+pub const TRUSTED_FILLER_SECURITY_DEPOSIT_PERCENT: u64 = 5;
+pub const TRUSTED_FILLER_LOCK_MULTIPLIER: u64 = 20; // 100 / TRUSTED_FILLER_SECURITY_DEPOSIT_PERCENT
+
+// Collateral tokens besides ckUSDC (configured at runtime via admin_set_collateral_token)
+// Each counts toward MAX_LOCK_MULTIPLIER at its oracle USD value times its credit ratio
+pub const MAX_COLLATERAL_TOKENS: usize = 5;
+pub const COLLATERAL_PRICE_CACHE_NS: u64 = 5 * 60 * 1_000_000_000; // Reuse a collateral USD rate for 5 minutes
+
+// Exposure caps (defaults - adjustable at runtime via ExposureLimits)
+// Checked on top of MAX_LOCK_MULTIPLIER so one filler can't hold most of the book however large their deposit
+pub const MAX_FILLER_OPEN_INTEREST_E6: u64 = 500_000_000; // $500 locked per filler
+pub const MAX_TOTAL_LOCKED_E6: u64 = 2_000_000_000; // $2,000 locked across all fillers
+
+// Trade size limits (defaults - adjustable at runtime via TradeSizeLimits)
+// Dust requests are rejected, and a trade's chunk count is capped so the BSV payment stays small to build and verify
+pub const MIN_TRADE_SIZE_E6: u64 = MIN_CHUNK_SIZE_E6; // One chunk
+pub const MAX_CHUNKS_PER_TRADE: u64 = 20; // One payment output per chunk
+
 // ============== BLOCKCHAIN SYNC CONFIGURATION ==============
 // Maximum reorg depth to check AND maximum number of blocks to keep
 // 720 blocks = ~5 days at 10 minute average block time
@@ -82,25 +214,120 @@ pub const MAX_BLOCKS_TO_KEEP: u64 = 288;
 // Maximum blocks to check per reorg detection call (prevents instruction limit issues)
 pub const MAX_REORG_CHECK_PER_CALL: u64 = 50;
 
+// TxArchive canister - block source when a header API is down, and for BUMPs against blocks
+// no longer held locally (default - the canister, trust and on/off are runtime config)
+pub const TXARCHIVE_CANISTER_ID: &str = "glgze-4qaaa-aaaac-a4m2a-cai";
+
 // Trade timeout in nanoseconds (45 minutes)
 pub const TRADE_TIMEOUT_NS: u64 = 45 * 60 * 1_000_000_000;
 
+// Trade lock extension - a filler may push lock_expires_at back once for a fee paid to the maker
+// from their security deposit, instead of taking the timeout penalty
+pub const LOCK_EXTENSION_NS: u64 = 20 * 60 * 1_000_000_000; // 20 minutes
+pub const LOCK_EXTENSION_FEE_PERCENT: u64 = 50; // basis points: 50/10000 = 0.5% of trade amount
+
+// Timeout penalty schedule (defaults - adjustable at runtime via TimeoutPenaltySchedule)
+// The penalty on a trade that expired without a BSV payment grows with how long past lock_expires_at
+// it was unlocked, so a filler who only just missed the deadline loses less of their deposit
+pub const TIMEOUT_PENALTY_TIERS: [(u64, u64); 2] = [
+    (10 * 60 * 1_000_000_000, 100), // Expired under 10 minutes: 1% of trade amount
+    (30 * 60 * 1_000_000_000, 300), // Under 30 minutes: 3%
+];
+pub const TIMEOUT_PENALTY_FINAL_BPS: u64 = 500; // After that: 5% (must stay within SECURITY_DEPOSIT_PERCENT)
+
+// Fillers may appeal a trade's penalties this long after the last was logged (see disputes.rs)
+pub const PENALTY_APPEAL_WINDOW_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days
+
+// A verified claim on a trade the 24h reclaim sent to treasury is paid automatically this long
+// after the reclaim - later ones wait for the admin (see late_claims.rs)
+pub const LATE_CLAIM_AUTO_WINDOW_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days
+// ...and refused altogether this long after it - cleanup keeps reclaimed trades until then
+pub const LATE_CLAIM_WINDOW_NS: u64 = 30 * 24 * 60 * 60 * 1_000_000_000; // 30 days
+
+// Price improvement sharing (defaults - adjustable at runtime via PriceImprovementSharing)
+// When the oracle price at match time sits more than the threshold below an order's max_bsv_price,
+// the filler's share of that gap is added to the price the trade's sats are calculated at
+// The rest stays with the maker as extra sats. A zero share gives the maker the whole gap
+pub const PRICE_IMPROVEMENT_THRESHOLD_BPS: u64 = 100; // Gap over 1% of the order's max price
+pub const PRICE_IMPROVEMENT_FILLER_SHARE_BPS: u64 = 0;
+
+// Fillers whose trades time out this many times are flagged to admin for churning the orderbook
+pub const FILLER_CHURN_FLAG_THRESHOLD: u64 = 3;
+
 // USDC release wait time after BSV tx submission
 // 3 hours = 3 * 60 * 60 * 1_000_000_000 nanoseconds 
 pub const USDC_RELEASE_WAIT_NS: u64 = 3 * 60 * 60 * 1_000_000_000; 
 
 // Transaction resubmission penalty (2% of trade amount, deducted from security deposit)
 // This prevents traders from gaming the system by repeatedly resubmitting during market volatility
-pub const RESUBMISSION_PENALTY_PERCENT: f64 = 2.0;
+pub const RESUBMISSION_PENALTY_PERCENT: u64 = 200; // basis points: 200/10000
 
 // Resubmission window: Traders can only resubmit within 2 hours of INITIAL submission
 // After this window, resubmission is locked to prevent eternal claim delays
 pub const RESUBMISSION_WINDOW_NS: u64 = 2 * 60 * 60 * 1_000_000_000; // 2 hours
 
+// Maximum trades per claim_usdc_batch call (each distinct tx costs one SPV verification)
+pub const MAX_CLAIMS_PER_BATCH: usize = 20;
+
+// Maximum trades one BSV payment may cover (get_trade_payment_template / submit_bsv_transaction_for_trades)
+pub const MAX_TRADES_PER_PAYMENT: usize = 20;
+
 // Trade claim expiry - if no successful claim after 24 hours, funds go to treasury
 // 24 hours = 24 * 60 * 60 * 1_000_000_000 nanoseconds
 pub const TRADE_CLAIM_EXPIRY_NS: u64 = 24 * 60 * 60 * 1_000_000_000; 
 
+// Minimum miner fee rate for submitted BSV transactions (sats per kB)
+// Lower-fee transactions risk sitting in the mempool past the claim window
+pub const MIN_TX_FEE_RATE_SATS_PER_KB: u64 = 50;
+
+// Fee rate check fetches each distinct parent transaction - skipped above this many
+pub const MAX_FEE_CHECK_PARENT_TXS: usize = 5;
+
+// Largest parent transaction fetched for the fee rate check (hex bytes)
+pub const MAX_PARENT_TX_RESPONSE_BYTES: u64 = 200_000;
+
+// ============== PARSER LIMITS ==============
+// Raw transactions and BUMP proofs are caller-supplied hex - inputs past these limits are
+// rejected before parsing, and every length read from the data is checked against what's left
+pub const MAX_BSV_TX_BYTES: usize = 100_000; // Same as the 200_000 hex char cap on claims
+pub const MAX_BUMP_BYTES: usize = 5_000; // 10_000 hex chars
+// Levels in a BUMP path - a 64-level tree already covers any u64 leaf offset
+pub const MAX_BUMP_TREE_HEIGHT: u64 = 64;
+
+// Verified merkle proofs kept for retried and batched claims - oldest evicted first
+pub const MAX_VERIFIED_INCLUSIONS: u64 = 1_000;
+
+// ============== INPUT LIMITS ==============
+// Caller-supplied strings are checked against these in validation.rs before any endpoint
+// parses or stores them
+pub const MAX_BSV_ADDRESS_LENGTH: usize = 35; // Base58 P2PKH
+pub const MAX_PRINCIPAL_TEXT_LENGTH: usize = 63; // Textual form of a 29-byte principal
+pub const MAX_CHAIN_NAME_LENGTH: usize = 32;
+pub const MAX_DISPUTE_REASON_LENGTH: usize = 500;
+pub const MAX_APPEAL_EXPLANATION_LENGTH: usize = 1_000;
+
+// ============== INGRESS INSPECTION ==============
+// Candid-encoded argument size accepted by canister_inspect_message (see ingress_guard.rs)
+// Calls carrying a raw tx or BUMP get room for MAX_BSV_TX_BYTES / MAX_BUMP_BYTES of hex on top
+pub const MAX_INGRESS_ARG_BYTES: usize = 16 * 1024;
+
+// ============== PRICE ORACLE ==============
+// BSV/USD is the median of XRC, WhatsOnChain and Coinlore
+// Feeds further than this from the median are discarded as outliers
+pub const PRICE_MAX_DEVIATION_BPS: u64 = 300; // 3%
+
+// Feeds that must agree (after outlier rejection) before a price is adopted
+// With fewer, the cached price is kept and goes stale, which pauses matching
+pub const PRICE_MIN_AGREEING_SOURCES: usize = 2;
+
+// Circuit breaker: halt new trades and idle chunk reactivation when the oracle price
+// moves more than CIRCUIT_BREAKER_MOVE_BPS (high vs low) within CIRCUIT_BREAKER_WINDOW_NS
+// Trading resumes automatically once no move above the limit is seen for the cooldown
+// Existing trades (submission, claims, refunds) are not affected
+pub const CIRCUIT_BREAKER_MOVE_BPS: u64 = 1000; // 10%
+pub const CIRCUIT_BREAKER_WINDOW_NS: u64 = 30 * 60 * 1_000_000_000; // 30 minutes
+pub const CIRCUIT_BREAKER_COOLDOWN_NS: u64 = 60 * 60 * 1_000_000_000; // 1 hour
+
 // ============== LEDGER CONFIGURATION ==============
 // ckETH Ledger Canister ID (for paying Ethereum gas fees)
 pub const CK_ETH_LEDGER: &str = "ss2fx-dyaaa-aaaar-qacoq-cai";
@@ -111,9 +338,74 @@ pub const CK_USDC_LEDGER: &str = "xevnm-gaaaa-aaaar-qafnq-cai";
 // ckUSDC Minter Canister ID (for ERC-20 withdrawals)
 pub const CK_USDC_MINTER: &str = "sv3dd-oaaaa-aaaar-qacoa-cai";
 
+// Withdrawal route used when withdraw_ckusdc_to_eth is called without a chain
+// Built from the ledger/minter IDs above unless the admin stores a route under this name
+pub const DEFAULT_WITHDRAWAL_CHAIN: &str = "ethereum";
+
 // ckUSDC transfer fee (0.01 USDC = 10,000 e6s)
 pub const CKUSDC_TRANSFER_FEE: u128 = 10_000;
 
+// ============== ckETH GAS BUDGET ==============
+// The canister pays Ethereum gas for ckUSDC withdrawals from its ckETH balance.
+// Below the low-watermark an admin event is logged and, unless the admin turned it off,
+// withdraw_ckusdc_to_eth is rejected up front until the balance is refilled
+pub const DEFAULT_CKETH_LOW_WATERMARK_WEI: u64 = 10_000_000_000_000_000; // 0.01 ETH
+
+// How often the ckETH balance is checked against the watermark
+pub const CKETH_BALANCE_CHECK_INTERVAL_SECONDS: u64 = 15 * 60;
+
+// Withdrawal follow-up runs this often: withdrawals with an unknown minter outcome are looked
+// up, refunds of withdrawals that failed after pulling the user's ckUSDC are retried, and
+// submitted withdrawals are polled on the minter until finalized
+pub const WITHDRAWAL_FOLLOWUP_INTERVAL_SECONDS: u64 = 5 * 60;
+
+// Max submitted withdrawals polled on the minter per run (one call each)
+pub const MAX_WITHDRAWAL_STATUS_POLLS_PER_RUN: usize = 20;
+
+// ============== MAKER FEE REBATES ==============
+// Share of each activation fee set aside for makers - the pool is split by filled volume
+// when the epoch closes (cleanup heartbeat) and makers claim their share with claim_rebates
+pub const REBATE_SHARE_BPS: u64 = 1_000; // 10% of the fee
+pub const REBATE_EPOCH_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // Weekly
+
+// ============== DUST SWEEP ==============
+// Completed/cancelled orders can leave small residual balances in their subaccounts
+// (rounding, fee arithmetic). Admin can sweep these to treasury.
+// Balances above MAX_DUST_SWEEP_USD are never swept - they likely belong to the maker
+// (e.g. a failed refund) and need manual review instead
+pub const MAX_DUST_SWEEP_USD: f64 = 1.0;
+
+// Maximum number of orders checked per sweep call (one ledger balance query each)
+pub const MAX_DUST_SWEEP_ORDERS_PER_CALL: usize = 25;
+
+// ============== BACKGROUND SCHEDULER ==============
+// One timer ticks every SCHEDULER_TICK_SECONDS and starts whichever tasks are due
+// Task intervals (defaults below) can be changed at runtime within these bounds
+pub const SCHEDULER_TICK_SECONDS: u64 = 10;
+pub const MAX_TASK_INTERVAL_SECONDS: u64 = 7 * 24 * 60 * 60;
+// After consecutive failures a task waits interval * 2^failures, capped at the larger of
+// its interval and TASK_MAX_BACKOFF_SECONDS
+pub const TASK_MAX_BACKOFF_SECONDS: u64 = 60 * 60;
+pub const CONFIRMATIONS_INTERVAL_SECONDS: u64 = 60;
+pub const IDLE_REACTIVATION_INTERVAL_SECONDS: u64 = 60;
+pub const CLEANUP_TASKS_INTERVAL_SECONDS: u64 = 5 * 60;
+pub const BLOCK_SYNC_INTERVAL_SECONDS: u64 = 20 * 60;
+
+// ============== PENDING TRANSFER RETRIES ==============
+// Penalty and cancel-refund transfers that fail inline are queued and retried by the
+// cleanup task, waiting BASE * 2^(attempts-1) between tries (capped at MAX)
+pub const PENDING_TRANSFER_BASE_DELAY_SECONDS: u64 = 60;
+pub const PENDING_TRANSFER_MAX_DELAY_SECONDS: u64 = 6 * 60 * 60;
+// Attempts (including the original inline one) before a transfer is marked Failed
+pub const PENDING_TRANSFER_MAX_ATTEMPTS: u32 = 10;
+pub const MAX_PENDING_TRANSFERS_PER_RUN: usize = 20;
+
+// ============== TRANSFER DEDUPLICATION ==============
+// ICRC-1 ledgers deduplicate identical transfers (same created_at_time and memo) for 24 hours
+// A retry reuses its first attempt's created_at_time only within this window, leaving margin
+// for clock drift before the ledger would reject it as TooOld
+pub const TRANSFER_DEDUP_WINDOW_SECONDS: u64 = 23 * 60 * 60;
+
 // ============== DATA RETENTION & CLEANUP ==============
 // Automatic cleanup to prevent storage exhaustion
 
@@ -131,12 +423,79 @@ pub const TRADE_RETENTION_SECONDS: u64 = 7 * 24 * 60 * 60; // 7 days
 // Old admin events are automatically cleaned up to prevent storage bloat
 pub const ADMIN_EVENTS_RETENTION_SECONDS: u64 = 7 * 24 * 60 * 60; // 7 days
 
+// Admin events beyond this count are dropped oldest-first
+pub const MAX_ADMIN_EVENTS: u64 = 10_000;
+
+// The values above are defaults - admin can adjust them at runtime (see RetentionPolicy)
+// within these bounds
+pub const BLOCKS_PER_DAY: u64 = 144;
+// Claims need headers for the full claim window plus confirmation depth
+pub const MIN_BLOCKS_TO_KEEP: u64 = TRADE_CLAIM_EXPIRY_NS / (600 * 1_000_000_000) + CONFIRMATION_DEPTH;
+pub const MAX_BLOCKS_TO_KEEP_CONFIGURABLE: u64 = 14 * BLOCKS_PER_DAY;
+pub const MIN_ADMIN_EVENTS_KEPT: u64 = 100;
+pub const MAX_ADMIN_EVENTS_KEPT: u64 = 100_000;
+
+// Once an archive canister is configured, finished orders/trades past their retention window
+// are pushed there in batches instead of being deleted - see archive.rs
+pub const ARCHIVE_BATCH_SIZE: usize = 50;
+pub const ARCHIVE_BATCH_MAX_BYTES: usize = 1_500_000; // Stays under the 2MB inter-canister message limit
+
 // Cleanup interval (1 hour in seconds)
 pub const CLEANUP_INTERVAL_SECONDS: u64 = 1 * 60 * 60; // Run hourly
 
+// Settlement timer checks hourly whether yesterday's (UTC) report still needs compiling
+pub const SETTLEMENT_CHECK_INTERVAL_SECONDS: u64 = 60 * 60;
+
+// Max reports returned per admin query
+pub const MAX_SETTLEMENT_REPORTS_PER_QUERY: u64 = 90;
+
+// Order balance reconciliation (ledger balance vs. open chunks) runs hourly
+// Each run checks at most MAX_RECONCILIATION_ORDERS_PER_RUN orders (one ledger query each),
+// continuing where the previous run stopped
+pub const RECONCILIATION_INTERVAL_SECONDS: u64 = 60 * 60;
+pub const MAX_RECONCILIATION_ORDERS_PER_RUN: usize = 25;
+
+// Shortfalls up to this are ignored (one ckUSDC transfer fee)
+pub const RECONCILIATION_TOLERANCE_E6: u64 = 10_000;
+
+// Hourly market statistics buckets kept for get_market_stats (covers the 7d window)
+pub const MARKET_STATS_RETENTION_HOURS: u64 = 7 * 24;
+
+// Hourly BSV price candles kept for get_price_candles (30 days)
+pub const PRICE_CANDLE_RETENTION_HOURS: u64 = 30 * 24;
+
+// Max candles returned per get_price_candles query
+pub const MAX_PRICE_CANDLES_PER_QUERY: usize = 500;
+
+// ============== BACKUP & RESTORE ==============
+// Snapshots are served and uploaded in chunks to stay under the 2MB message limit
+pub const BACKUP_CHUNK_SIZE_BYTES: usize = 1_000_000;
+
+// Bump when the snapshot layout (not the record schema) changes
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
 // ============== CYCLES MANAGEMENT ==============
 // Minimum cycles balance required to accept new orders
 // If canister balance drops below this, new order creation is rejected
 // This prevents canister from running out of cycles and becoming unresponsive
 pub const MIN_CYCLES_FOR_NEW_ORDERS: u128 = 500_000_000_000; // 500 Billion Cycles
 
+
+// ============== OPERATOR ALERTS ==============
+// Critical events pushed to the admin's alert sink - see alerts.rs
+// Block syncs failing in a row before an alert
+pub const ALERT_SYNC_FAILURES: u32 = 3;
+
+// Timeout penalties within the window that count as a spike
+pub const ALERT_PENALTY_SPIKE_COUNT: u64 = 5;
+pub const ALERT_PENALTY_SPIKE_WINDOW_SECONDS: u64 = 60 * 60;
+
+// Cycles balance that raises an alert - well before new orders stop at MIN_CYCLES_FOR_NEW_ORDERS
+pub const ALERT_LOW_CYCLES: u128 = 2 * MIN_CYCLES_FOR_NEW_ORDERS;
+
+// Undelivered alerts kept for retry - the oldest are dropped beyond this
+pub const MAX_PENDING_ALERTS: usize = 100;
+pub const ALERT_MAX_ATTEMPTS: u32 = 3;
+
+pub const MAX_ALERT_URL_LENGTH: usize = 2048;
+pub const MAX_ALERT_METHOD_LENGTH: usize = 100;
@@ -0,0 +1,88 @@
+/// Dry run of limit changes - evaluates existing orders, makers and fillers against a proposed
+/// set of limits and reports what would no longer pass, before an upgrade or
+/// admin_set_exposure_limits commits them
+use crate::types::*;
+use crate::state::*;
+use crate::config::{MIN_CHUNK_SIZE_E6, MAX_CHUNKS_ALLOWED, MAX_ORDERBOOK_USD_LIMIT_E6, MAX_MAKER_TOTAL_ORDERS_USD_E6};
+use candid::Principal;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn simulate_config(proposal: ConfigProposal) -> ConfigImpactReport {
+    let min_chunk_size_e6 = proposal.min_chunk_size_e6.unwrap_or(MIN_CHUNK_SIZE_E6);
+    let max_chunks_per_order = proposal.max_chunks_per_order.unwrap_or(MAX_CHUNKS_ALLOWED as u64);
+    let max_orderbook_usd_e6 = proposal.max_orderbook_usd_e6.unwrap_or(MAX_ORDERBOOK_USD_LIMIT_E6);
+    let max_maker_total_orders_usd_e6 = proposal.max_maker_total_orders_usd_e6.unwrap_or(MAX_MAKER_TOTAL_ORDERS_USD_E6);
+    let exposure_limits = proposal.exposure_limits.unwrap_or_else(get_exposure_limits);
+    let exposure_limits_error = crate::trade_lifecycle::validate_exposure_limits(&exposure_limits)
+        .err()
+        .map(|e| e.to_string());
+
+    let active_orders: Vec<Order> = ORDERS.with(|orders| {
+        orders.borrow().iter()
+            .filter(|(_, order)| matches!(order.status, OrderStatus::Active | OrderStatus::Idle))
+            .map(|(_, order)| order)
+            .collect()
+    });
+
+    let mut open_chunks = 0u64;
+    let mut chunks_below_min_size = 0u64;
+    let mut orders_with_chunks_below_min_size = Vec::new();
+    let mut orders_over_max_chunks = Vec::new();
+    // Same per-maker total as create_order: unfilled remainder of Active/Idle orders
+    let mut maker_totals: BTreeMap<Principal, UsdE6> = BTreeMap::new();
+
+    for order in &active_orders {
+        let small_chunks = order.chunks.iter()
+            .filter_map(|chunk_id| get_chunk(*chunk_id))
+            .filter(|chunk| matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending))
+            .inspect(|_| open_chunks += 1)
+            .filter(|chunk| chunk.amount_usd_e6 < min_chunk_size_e6)
+            .count() as u64;
+        if small_chunks > 0 {
+            chunks_below_min_size += small_chunks;
+            orders_with_chunks_below_min_size.push(order.id);
+        }
+
+        if order.chunks.len() as u64 > max_chunks_per_order {
+            orders_over_max_chunks.push(order.id);
+        }
+
+        *maker_totals.entry(order.maker).or_insert(0) += order.amount_usd_e6.saturating_sub(order.total_filled_usd_e6);
+    }
+
+    let invalid_orders: BTreeSet<OrderId> = orders_with_chunks_below_min_size.iter()
+        .chain(orders_over_max_chunks.iter())
+        .copied()
+        .collect();
+
+    let orderbook_usd_e6 = get_available_orderbook();
+    let total_locked_usd_e6 = crate::chunk_allocation::get_total_locked_e6();
+
+    ConfigImpactReport {
+        min_chunk_size_e6,
+        max_chunks_per_order,
+        max_orderbook_usd_e6,
+        max_maker_total_orders_usd_e6,
+        exposure_limits_error,
+        active_orders: active_orders.len() as u64,
+        open_chunks,
+        invalid_orders: invalid_orders.into_iter().collect(),
+        chunks_below_min_size,
+        orders_with_chunks_below_min_size,
+        orders_over_max_chunks,
+        orderbook_usd_e6,
+        orderbook_over_limit: orderbook_usd_e6 > max_orderbook_usd_e6,
+        makers_over_total_limit: maker_totals.into_iter()
+            .filter(|(_, total)| *total > max_maker_total_orders_usd_e6)
+            .map(|(maker, _)| maker)
+            .collect(),
+        total_locked_usd_e6,
+        total_locked_over_limit: total_locked_usd_e6 > exposure_limits.max_total_locked_e6,
+        fillers_over_open_interest_limit: crate::chunk_allocation::get_open_interest_by_filler().into_iter()
+            .filter(|(_, open_interest)| *open_interest > exposure_limits.max_filler_open_interest_e6)
+            .map(|(filler, _)| filler)
+            .collect(),
+        exposure_limits,
+        generated_at: get_time(),
+    }
+}
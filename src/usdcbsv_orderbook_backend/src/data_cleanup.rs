@@ -1,16 +1,15 @@
 /// Automated data cleanup to prevent storage exhaustion
+/// Retention windows come from the runtime RetentionPolicy (admin-adjustable, defaults in config.rs)
 use crate::types::*;
 use crate::state::*;
-use crate::config::{TRADE_RETENTION_SECONDS, ORDER_RETENTION_SECONDS};
-use crate::block_headers::CONFIRMATION_DEPTH;
+use crate::config::{BLOCKS_PER_DAY, MIN_BLOCKS_TO_KEEP, MAX_BLOCKS_TO_KEEP_CONFIGURABLE, MIN_ADMIN_EVENTS_KEPT, MAX_ADMIN_EVENTS_KEPT};
 
-/// Clean up old orders where ALL chunks are in final states (Filled or Refunded)
-/// Does NOT delete orders with any active, idle, locked, or pending chunks
-pub fn cleanup_old_orders() -> u64 {
-    let now = get_time();
-    let retention_threshold = now.saturating_sub(ORDER_RETENTION_SECONDS * 1_000_000_000);
-    
-    let mut deleted_count = 0u64;
+const DAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Orders older than the policy window whose chunks are ALL in final states (Filled or Refunded)
+/// Returns (order_id, chunk_ids) - orders with any active, idle, locked, or pending chunks are never included
+pub(crate) fn expired_orders(now: u64, policy: &RetentionPolicy) -> Vec<(OrderId, Vec<ChunkId>)> {
+    let retention_threshold = now.saturating_sub(policy.order_retention_days.saturating_mul(DAY_NS));
     
     // Get all order IDs to check (just IDs, not full orders)
     let order_ids_to_check: Vec<OrderId> = ORDERS.with(|orders| {
@@ -20,36 +19,119 @@ pub fn cleanup_old_orders() -> u64 {
                 return None;
             }
             
-            Some(id.clone())
+            Some(id)
         }).collect()
     });
     
-    // Process each order one-by-one for fault tolerance
-    for order_id in order_ids_to_check {
-        // Get order details
-        let order = match ORDERS.with(|orders| orders.borrow().get(&order_id)) {
-            Some(o) => o,
-            None => continue, // Order already deleted
-        };
-        
+    order_ids_to_check.into_iter().filter_map(|order_id| {
         // Get all chunks for this specific order
-        let order_chunks = CHUNKS.with(|chunks| {
+        let order_chunks: Vec<Chunk> = CHUNKS.with(|chunks| {
             chunks.borrow()
                 .iter()
                 .filter(|(_, chunk)| chunk.order_id == order_id)
-                .map(|(id, chunk)| (id.clone(), chunk.clone()))
-                .collect::<Vec<_>>()
+                .map(|(_, chunk)| chunk)
+                .collect()
         });
         
         // Check if ALL chunks are in final states (Filled or Refunded)
-        let all_chunks_final = order_chunks.iter().all(|(_, chunk)| {
+        let all_chunks_final = order_chunks.iter().all(|chunk| {
             matches!(chunk.status, ChunkStatus::Filled | ChunkStatus::Refunded)
         });
         
-        // If any chunk is NOT in a final state, skip this order
-        if !all_chunks_final {
-            continue;
-        }
+        all_chunks_final.then(|| (order_id, order_chunks.iter().map(|c| c.id).collect()))
+    }).collect()
+}
+
+/// Trades older than the policy window in final states - except those a penalty appeal or a
+/// late claim may still reach (disputes::trades_open_to_appeal, late_claims::open_to_late_claim)
+pub(crate) fn expired_trades(now: u64, policy: &RetentionPolicy) -> Vec<TradeId> {
+    let retention_threshold = now.saturating_sub(policy.trade_retention_days.saturating_mul(DAY_NS));
+    let open_to_appeal = crate::disputes::trades_open_to_appeal(now);
+    
+    TRADES.with(|trades| {
+        trades.borrow().iter().filter_map(|(id, trade)| {
+            // Only consider trades in final states
+            let is_final_state = matches!(
+                trade.status,
+                TradeStatus::WithdrawalConfirmed | TradeStatus::Cancelled | TradeStatus::PenaltyApplied
+            );
+            
+            if !is_final_state {
+                return None;
+            }
+            
+            // Only cleanup old trades
+            if trade.created_at >= retention_threshold {
+                return None;
+            }
+            
+            if open_to_appeal.contains(&id) || crate::late_claims::open_to_late_claim(id, &trade, now) {
+                return None;
+            }
+            
+            Some(id)
+        }).collect()
+    })
+}
+
+/// Block heights below the last policy.blocks_to_keep from tip, and the lowest height kept
+fn expired_blocks(policy: &RetentionPolicy) -> (Vec<u64>, u64) {
+    // Get current tip
+    let highest_block = crate::block_headers::get_highest_block();
+    
+    // Calculate minimum height to keep
+    let min_height_to_keep = highest_block.saturating_sub(policy.blocks_to_keep.saturating_sub(1));
+    
+    let heights = crate::block_headers::with_headers(|headers| {
+        headers.borrow().iter().filter_map(|(height, _)| {
+            if height < min_height_to_keep {
+                Some(height)
+            } else {
+                None
+            }
+        }).collect()
+    });
+    
+    (heights, min_height_to_keep)
+}
+
+/// Admin events older than the policy window, plus the oldest beyond policy.max_admin_events
+fn expired_admin_events(now: u64, policy: &RetentionPolicy) -> Vec<u64> {
+    let retention_threshold_ns = now.saturating_sub(policy.admin_event_retention_days.saturating_mul(DAY_NS));
+    
+    crate::state::ADMIN_EVENTS.with(|events| {
+        let events = events.borrow();
+        let over_count = events.len().saturating_sub(policy.max_admin_events);
+        
+        // Event IDs are assigned in order, so ascending keys are oldest first
+        events.iter().enumerate().filter_map(|(index, (id, event))| {
+            if (index as u64) < over_count || event.timestamp < retention_threshold_ns {
+                Some(id)
+            } else {
+                None
+            }
+        }).collect()
+    })
+}
+
+/// Clean up old orders where ALL chunks are in final states (Filled or Refunded)
+/// Does NOT delete orders with any active, idle, locked, or pending chunks
+pub fn cleanup_old_orders() -> u64 {
+    // Archived instead of deleted once an archive canister is configured - see archive.rs
+    if get_archive_canister().is_some() {
+        return 0;
+    }
+    
+    let now = get_time();
+    let mut deleted_count = 0u64;
+    
+    // Process each order one-by-one for fault tolerance
+    for (order_id, chunk_ids) in expired_orders(now, &get_retention_policy()) {
+        // Get order details
+        let order = match ORDERS.with(|orders| orders.borrow().get(&order_id)) {
+            Some(o) => o,
+            None => continue, // Order already deleted
+        };
         
         // Safe to delete - all chunks are in final states
         // Delete order first
@@ -60,7 +142,7 @@ pub fn cleanup_old_orders() -> u64 {
         // Then delete chunks one by one
         CHUNKS.with(|chunks| {
             let mut chunks_mut = chunks.borrow_mut();
-            for (chunk_id, _) in order_chunks.iter() {
+            for chunk_id in chunk_ids.iter() {
                 chunks_mut.remove(chunk_id);
             }
         });
@@ -70,8 +152,8 @@ pub fn cleanup_old_orders() -> u64 {
         ic_cdk::println!(
             "🗑️  Deleted old order #{} with {} chunks (age: {} days)",
             order_id,
-            order_chunks.len(),
-            (now - order.created_at) / (24 * 60 * 60 * 1_000_000_000)
+            chunk_ids.len(),
+            (now - order.created_at) / DAY_NS
         );
     }
     
@@ -84,36 +166,16 @@ pub fn cleanup_old_orders() -> u64 {
 
 /// Clean up old trades that are in final states and older than retention period
 pub fn cleanup_old_trades() -> u64 {
-    let now = get_time();
-    let retention_threshold = now.saturating_sub(TRADE_RETENTION_SECONDS * 1_000_000_000);
+    // Archived instead of deleted once an archive canister is configured - see archive.rs
+    if get_archive_canister().is_some() {
+        return 0;
+    }
     
+    let now = get_time();
     let mut deleted_count = 0u64;
     
-    // Get trade IDs to check (only final states, older than threshold)
-    let trade_ids_to_check: Vec<TradeId> = TRADES.with(|trades| {
-        trades.borrow().iter().filter_map(|(id, trade)| {
-            // Only consider trades in final states
-            let is_final_state = matches!(
-                trade.status,
-                TradeStatus::WithdrawalConfirmed | TradeStatus::Cancelled | TradeStatus::PenaltyApplied
-            );
-            
-            if !is_final_state {
-                return None;
-            }
-            
-            // Only cleanup old trades
-            if trade.created_at >= retention_threshold {
-                return None;
-            }
-            
-            Some(id.clone())
-        }).collect()
-    });
-    
-    // Delete each trade one by one (fault tolerant)
     // Process each trade one-by-one for fault tolerance
-    for trade_id in trade_ids_to_check {
+    for trade_id in expired_trades(now, &get_retention_policy()) {
         // Get trade details for logging
         let trade = match TRADES.with(|trades| trades.borrow().get(&trade_id)) {
             Some(t) => t,
@@ -121,9 +183,7 @@ pub fn cleanup_old_trades() -> u64 {
         };
         
         // Delete the trade
-        TRADES.with(|trades| {
-            trades.borrow_mut().remove(&trade_id);
-        });
+        remove_trade(trade_id);
         
         deleted_count += 1;
         
@@ -131,7 +191,7 @@ pub fn cleanup_old_trades() -> u64 {
             "🗑️  Deleted old trade #{} (status: {:?}, age: {} days)",
             trade_id,
             trade.status,
-            (now - trade.created_at) / (24 * 60 * 60 * 1_000_000_000)
+            (now - trade.created_at) / DAY_NS
         );
     }
     
@@ -142,44 +202,28 @@ pub fn cleanup_old_trades() -> u64 {
     deleted_count
 }
 
-/// Clean up old block headers - keep only the last MAX_BLOCKS_TO_KEEP from tip
+/// Clean up old block headers - keep only the last policy.blocks_to_keep from tip
 pub fn cleanup_old_blocks() -> u64 {
-    use crate::config::MAX_BLOCKS_TO_KEEP;
-    
-    let mut deleted_count = 0u64;
-    
-    // Get current tip
-    let highest_block = crate::block_headers::get_highest_block();
-    
-    // Calculate minimum height to keep (last 720 blocks)
-    let min_height_to_keep = highest_block.saturating_sub(MAX_BLOCKS_TO_KEEP - 1);
+    let policy = get_retention_policy();
+    let (heights_to_delete, min_height_to_keep) = expired_blocks(&policy);
     
     ic_cdk::println!(
-        "Block cleanup: tip={}, min_to_keep={}, will delete blocks below {}",
-        highest_block, min_height_to_keep, min_height_to_keep
+        "Block cleanup: min_to_keep={}, will delete {} blocks below it",
+        min_height_to_keep, heights_to_delete.len()
     );
     
-    // Get block heights to delete (all blocks below min_height_to_keep)
-    let heights_to_delete: Vec<u64> = crate::block_headers::BLOCK_HEADERS.with(|headers| {
-        headers.borrow().iter().filter_map(|(height, _)| {
-            if height < min_height_to_keep {
-                Some(height)
-            } else {
-                None
-            }
-        }).collect()
-    });
+    let mut deleted_count = 0u64;
     
     // Delete old blocks
     for height in heights_to_delete {
-        crate::block_headers::BLOCK_HEADERS.with(|headers| {
+        crate::block_headers::with_headers(|headers| {
             headers.borrow_mut().remove(&height);
         });
         deleted_count += 1;
     }
     
     if deleted_count > 0 {
-        ic_cdk::println!("✅ Cleanup: Deleted {} old blocks (keeping last {} blocks)", deleted_count, MAX_BLOCKS_TO_KEEP);
+        ic_cdk::println!("✅ Cleanup: Deleted {} old blocks (keeping last {} blocks)", deleted_count, policy.blocks_to_keep);
     }
     
     deleted_count
@@ -187,27 +231,11 @@ pub fn cleanup_old_blocks() -> u64 {
 
 /// Clean up old admin events
 pub fn cleanup_old_admin_events() -> u64 {
-    let now = get_time();
-    let retention_threshold_ns = now.saturating_sub(crate::config::ADMIN_EVENTS_RETENTION_SECONDS * 1_000_000_000);
-    
     let mut deleted_count = 0u64;
     
-    // Get admin event IDs to check (only old enough events)
-    let event_ids_to_check: Vec<u64> = crate::state::ADMIN_EVENTS.with(|events| {
-        events.borrow().iter().filter_map(|(id, event)| {
-            if event.timestamp < retention_threshold_ns {
-                Some(id.clone())
-            } else {
-                None
-            }
-        }).collect()
-    });
-    
     // Process each event one-by-one for fault tolerance
-    for event_id in event_ids_to_check {
-        crate::state::ADMIN_EVENTS.with(|events| {
-            events.borrow_mut().remove(&event_id);
-        });
+    for event_id in expired_admin_events(get_time(), &get_retention_policy()) {
+        crate::state::remove_admin_event(event_id);
         deleted_count += 1;
     }
     
@@ -238,3 +266,58 @@ pub fn run_cleanup() -> (u64, u64, u64, u64) {
     
     (orders_deleted, trades_deleted, blocks_deleted, admin_events_deleted)
 }
+
+/// Dry run of run_cleanup under the current policy - nothing is deleted
+pub fn preview_cleanup() -> CleanupPreview {
+    let now = get_time();
+    let policy = get_retention_policy();
+    
+    let orders = expired_orders(now, &policy);
+    let (blocks, min_block_height_kept) = expired_blocks(&policy);
+    
+    CleanupPreview {
+        archive_enabled: get_archive_canister().is_some(),
+        chunk_count: orders.iter().map(|(_, chunk_ids)| chunk_ids.len() as u64).sum(),
+        order_ids: orders.into_iter().map(|(order_id, _)| order_id).collect(),
+        trade_ids: expired_trades(now, &policy),
+        block_count: blocks.len() as u64,
+        min_block_height_kept,
+        admin_event_count: expired_admin_events(now, &policy).len() as u64,
+        generated_at: now,
+        policy,
+    }
+}
+
+/// Validate and store a new retention policy
+/// Trades must outlive the block window - a txid is only rejected as a duplicate while its
+/// trade is still held, so trades dropping before their blocks would let a confirmed tx be reused
+pub fn set_retention_policy(policy: RetentionPolicy) -> Result<(), EasySwapError> {
+    if policy.order_retention_days == 0 || policy.trade_retention_days == 0 || policy.admin_event_retention_days == 0 {
+        return Err(EasySwapError::invalid_input("Retention periods must be at least 1 day"));
+    }
+    
+    if policy.blocks_to_keep < MIN_BLOCKS_TO_KEEP || policy.blocks_to_keep > MAX_BLOCKS_TO_KEEP_CONFIGURABLE {
+        return Err(EasySwapError::invalid_input(format!(
+            "blocks_to_keep must be between {} and {}",
+            MIN_BLOCKS_TO_KEEP, MAX_BLOCKS_TO_KEEP_CONFIGURABLE
+        )));
+    }
+    
+    if policy.trade_retention_days.saturating_mul(BLOCKS_PER_DAY) < policy.blocks_to_keep {
+        return Err(EasySwapError::invalid_input(format!(
+            "Trades must be kept at least as long as blocks ({} blocks ≈ {} days)",
+            policy.blocks_to_keep,
+            policy.blocks_to_keep.div_ceil(BLOCKS_PER_DAY)
+        )));
+    }
+    
+    if policy.max_admin_events < MIN_ADMIN_EVENTS_KEPT || policy.max_admin_events > MAX_ADMIN_EVENTS_KEPT {
+        return Err(EasySwapError::invalid_input(format!(
+            "max_admin_events must be between {} and {}",
+            MIN_ADMIN_EVENTS_KEPT, MAX_ADMIN_EVENTS_KEPT
+        )));
+    }
+    
+    crate::state::set_retention_policy(policy);
+    Ok(())
+}
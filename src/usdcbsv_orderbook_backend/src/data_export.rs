@@ -0,0 +1,100 @@
+/// Self-service data export - everything the canister holds about the caller, so users can
+/// audit their activity and keep their own records
+/// Orders and trades are paged with a cursor; account-level records come with the first page
+use crate::types::*;
+use crate::state::*;
+use crate::config::MAX_EXPORT_RECORDS_PER_PAGE;
+use candid::Principal;
+use std::collections::BTreeSet;
+
+fn account_section(caller: Principal) -> DataExportAccount {
+    let involves_caller = |kind: &PendingTransferKind| match kind {
+        PendingTransferKind::Penalty { filler, recipient } => *filler == caller || *recipient == Some(caller),
+        PendingTransferKind::OrderRefund { maker, .. } => *maker == caller,
+    };
+
+    DataExportAccount {
+        filler_account: get_filler_account(caller),
+        quiet_hours: get_maker_quiet_hours(caller),
+        filler_blocklist: get_maker_filler_blocklist(caller),
+        quotes: get_all_quotes().into_iter().filter(|q| q.filler == caller).collect(),
+        disputes: get_disputes(false).into_iter().filter(|d| d.maker == caller || d.filler == caller).collect(),
+        penalty_appeals: get_penalty_appeals(false).into_iter().filter(|a| a.filler == caller).collect(),
+        late_claims: get_late_claims(false).into_iter().filter(|c| c.filler == caller).collect(),
+        deposit_recoveries: get_deposit_recoveries_by_maker(caller),
+        eth_withdrawals: get_eth_withdrawals_by_user(caller),
+        pending_transfers: PENDING_TRANSFERS.with(|map| {
+            map.borrow().iter()
+                .map(|(_, t)| t)
+                .filter(|t| involves_caller(&t.kind))
+                .collect()
+        }),
+    }
+}
+
+/// One page of the caller's data - pass next_cursor back until it comes back None
+/// Records archived to the archive canister are not included (see get_archived_order / get_archived_trade)
+pub fn export_my_data(cursor: Option<DataExportCursor>) -> Result<DataExport, EasySwapError> {
+    let caller = get_caller();
+
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal has no data to export. Please authenticate first."));
+    }
+
+    let mut export = DataExport {
+        principal: caller,
+        generated_at: get_time(),
+        account: cursor.is_none().then(|| account_section(caller)),
+        orders: Vec::new(),
+        chunks: Vec::new(),
+        trades: Vec::new(),
+        next_cursor: None,
+    };
+
+    // Orders first - trades start once the orders are exhausted
+    let trades_after_id = match cursor {
+        None | Some(DataExportCursor::Orders { .. }) => {
+            let after_id = match cursor {
+                Some(DataExportCursor::Orders { after_id }) => Some(after_id),
+                _ => None,
+            };
+            let (orders, next_cursor) = ORDERS.with(|orders| {
+                scan_page(&orders.borrow(), after_id, MAX_EXPORT_RECORDS_PER_PAGE, false, |order| order.maker == caller)
+            });
+            export.chunks = orders.iter()
+                .flat_map(|order| order.chunks.iter().filter_map(|chunk_id| get_chunk(*chunk_id)))
+                .collect();
+            export.orders = orders;
+
+            if let Some(after_id) = next_cursor {
+                export.next_cursor = Some(DataExportCursor::Orders { after_id });
+                return Ok(export);
+            }
+            None
+        }
+        Some(DataExportCursor::Trades { after_id }) => after_id,
+    };
+
+    let remaining = MAX_EXPORT_RECORDS_PER_PAGE - export.orders.len();
+    if remaining == 0 {
+        export.next_cursor = Some(DataExportCursor::Trades { after_id: trades_after_id });
+        return Ok(export);
+    }
+
+    // Trades against the caller's orders count too, not only the caller's own fills
+    let my_order_ids: BTreeSet<OrderId> = ORDERS.with(|orders| {
+        orders.borrow().iter()
+            .filter(|(_, order)| order.maker == caller)
+            .map(|(id, _)| id)
+            .collect()
+    });
+    let (trades, next_cursor) = TRADES.with(|trades| {
+        scan_page(&trades.borrow(), trades_after_id, remaining, false, |trade| {
+            trade.filler == caller || my_order_ids.contains(&trade.order_id)
+        })
+    });
+    export.trades = trades;
+    export.next_cursor = next_cursor.map(|after_id| DataExportCursor::Trades { after_id: Some(after_id) });
+
+    Ok(export)
+}
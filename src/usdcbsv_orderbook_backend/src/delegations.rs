@@ -0,0 +1,165 @@
+/// Operator delegations - a maker or filler registers a secondary principal (a bot, or a web
+/// frontend's Internet Identity session principal) that may make a restricted set of calls on
+/// their behalf, optionally until an expiry. Never withdrawals - those endpoints only ever act
+/// for ic_cdk::caller()
+/// Delegations are two-step: the owner proposes, the operator accepts - a proposal never changes
+/// who the operator acts for, and an operator can resign at any time
+/// lib.rs resolves the acting principal with acting_principal before calling in
+use candid::Principal;
+use crate::types::*;
+use crate::state::*;
+use crate::config::{MAX_OPERATORS_PER_PRINCIPAL, MAX_DELEGATION_LIFETIME_SECONDS};
+
+fn is_live(delegation: &OperatorDelegation, now: u64) -> bool {
+    delegation.expires_at.is_none_or(|expires_at| expires_at > now)
+}
+
+/// Accepted by the operator and not expired - the only delegations that act
+fn is_active(delegation: &OperatorDelegation, now: u64) -> bool {
+    delegation.accepted_at.is_some() && is_live(delegation, now)
+}
+
+/// Propose that `operator` acts for the caller within `scopes`, for expires_in_seconds or until
+/// revoked (None) - it acts once the operator calls accept_delegation. Re-proposing replaces the
+/// scopes and expiry and needs accepting again
+pub fn register_operator(
+    operator: Principal,
+    scopes: Vec<OperatorScope>,
+    expires_in_seconds: Option<u64>,
+) -> Result<OperatorDelegation, EasySwapError> {
+    let caller = get_caller();
+    let now = get_time();
+
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot register operators. Please authenticate first."));
+    }
+    if operator == Principal::anonymous() || operator == caller {
+        return Err(EasySwapError::invalid_input("Operator must be another authenticated principal"));
+    }
+    // One hop only - an operator can't delegate onwards
+    if get_delegation(caller).is_some_and(|delegation| is_active(&delegation, now)) {
+        return Err(EasySwapError::invalid_state("Operators cannot register operators of their own"));
+    }
+
+    let mut scopes = scopes;
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(EasySwapError::invalid_input("Give at least one scope"));
+    }
+
+    if let Some(seconds) = expires_in_seconds {
+        if seconds == 0 || seconds > MAX_DELEGATION_LIFETIME_SECONDS {
+            return Err(EasySwapError::invalid_input(format!(
+                "Delegation expiry must be between 1 and {} seconds",
+                MAX_DELEGATION_LIFETIME_SECONDS
+            )));
+        }
+    }
+
+    // Expired delegations don't count against the limit
+    for expired in get_delegations_by_owner(caller).into_iter().filter(|d| !is_live(d, now)) {
+        remove_delegation(expired.operator);
+    }
+
+    // A pending proposal from another account doesn't hold the operator - the latest one stands
+    match get_delegation(operator).filter(|delegation| is_live(delegation, now)) {
+        Some(existing) if existing.owner != caller && existing.accepted_at.is_some() => {
+            return Err(EasySwapError::invalid_state("Principal is already an operator for another account"));
+        }
+        Some(existing) if existing.owner == caller => {}
+        _ => {
+            if get_delegations_by_owner(operator).iter().any(|delegation| is_active(delegation, now)) {
+                return Err(EasySwapError::invalid_state("Principal has operators of its own and cannot be one"));
+            }
+            if get_delegations_by_owner(caller).len() >= MAX_OPERATORS_PER_PRINCIPAL {
+                return Err(EasySwapError::limit_exceeded(format!(
+                    "At most {} operators per account", MAX_OPERATORS_PER_PRINCIPAL
+                )));
+            }
+        }
+    }
+
+    let delegation = OperatorDelegation {
+        operator,
+        owner: caller,
+        scopes,
+        created_at: now,
+        expires_at: expires_in_seconds.map(|seconds| now + seconds * 1_000_000_000),
+        accepted_at: None,
+    };
+    ic_cdk::println!("🤖 {} proposed operator {} for {:?} (expires {:?})", caller, operator, delegation.scopes, delegation.expires_at);
+    set_delegation(delegation.clone());
+
+    Ok(delegation)
+}
+
+/// Remove one of the caller's operators
+pub fn revoke_operator(operator: Principal) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    let delegation = get_delegation(operator)
+        .filter(|delegation| delegation.owner == caller)
+        .ok_or_else(|| EasySwapError::not_found("Operator not registered for this account"))?;
+
+    remove_delegation(delegation.operator);
+    ic_cdk::println!("🤖 {} revoked operator {}", caller, operator);
+
+    Ok(())
+}
+
+/// Operator accepts the delegation `owner` proposed to it
+pub fn accept_delegation(owner: Principal) -> Result<OperatorDelegation, EasySwapError> {
+    let caller = get_caller();
+    let now = get_time();
+
+    let mut delegation = get_delegation(caller)
+        .filter(|delegation| delegation.owner == owner && is_live(delegation, now))
+        .ok_or_else(|| EasySwapError::not_found("No pending delegation from this account"))?;
+    if delegation.accepted_at.is_some() {
+        return Err(EasySwapError::invalid_state("Delegation has already been accepted"));
+    }
+    if get_delegations_by_owner(caller).iter().any(|own| is_active(own, now)) {
+        return Err(EasySwapError::invalid_state("Principal has operators of its own and cannot be one"));
+    }
+    // The owner may have become an operator itself since proposing
+    if get_delegation(owner).is_some_and(|upstream| is_active(&upstream, now)) {
+        return Err(EasySwapError::invalid_state("Operators cannot register operators of their own"));
+    }
+
+    delegation.accepted_at = Some(now);
+    set_delegation(delegation.clone());
+    ic_cdk::println!("🤖 {} accepted acting for {} in {:?}", caller, owner, delegation.scopes);
+
+    Ok(delegation)
+}
+
+/// Operator declines a proposal or stops acting for its owner
+pub fn resign_delegation() -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    let delegation = get_delegation(caller)
+        .ok_or_else(|| EasySwapError::not_found("Principal is not an operator for any account"))?;
+
+    remove_delegation(caller);
+    ic_cdk::println!("🤖 {} resigned as operator for {}", caller, delegation.owner);
+
+    Ok(())
+}
+
+/// The caller's delegations, expired ones included until they are replaced or pruned
+pub fn get_my_operators() -> Vec<OperatorDelegation> {
+    get_delegations_by_owner(get_caller())
+}
+
+/// The delegation proposed to or accepted by the caller as operator
+pub fn get_my_delegation() -> Option<OperatorDelegation> {
+    get_delegation(get_caller())
+}
+
+/// The principal a call in `scope` acts for - the owner when `caller` holds an accepted, live
+/// delegation for that scope, otherwise the caller itself
+pub fn acting_principal(caller: Principal, scope: OperatorScope) -> Principal {
+    match get_delegation(caller) {
+        Some(delegation) if delegation.scopes.contains(&scope) && is_active(&delegation, get_time()) => delegation.owner,
+        _ => caller,
+    }
+}
@@ -0,0 +1,431 @@
+/// Trade disputes - maker can put a submitted trade on hold for admin review
+/// While a dispute is open the filler cannot claim or resubmit, and the
+/// 24h auto-reclaim to treasury is paused
+/// Penalty appeals - a filler can ask the admin to refund penalties taken on one of their
+/// trades; an upheld appeal is paid from the treasury's main account into their deposit -
+/// in ckUSDC whatever the trade's book, as penalties are always taken from ckUSDC deposits
+use candid::{Nat, Principal};
+use std::collections::HashSet;
+use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError};
+use crate::types::*;
+use crate::state::*;
+use crate::chunk_allocation;
+use crate::config::{CKUSDC_TRANSFER_FEE, CK_USDC_LEDGER, PENALTY_APPEAL_WINDOW_NS};
+use crate::runtime::LedgerClient;
+
+/// Maker flags a trade on one of their orders as disputed
+/// Only trades with a submitted BSV transaction that haven't been claimed can be disputed
+pub fn maker_flag_dispute(trade_id: TradeId, reason: String) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+
+    if caller == candid::Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot flag disputes. Please authenticate first."));
+    }
+
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(EasySwapError::invalid_input("Dispute reason is required"));
+    }
+
+    let trade = get_trade(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
+
+    let order = get_order(trade.order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+
+    if order.maker != caller {
+        return Err(EasySwapError::unauthorized("Only the order maker can dispute this trade"));
+    }
+
+    if trade.status != TradeStatus::TxSubmitted && trade.status != TradeStatus::ReadyForRelease {
+        return Err(EasySwapError::invalid_state(format!("Trade cannot be disputed in {:?} status", trade.status)));
+    }
+
+    if get_dispute(trade_id).is_some() {
+        return Err(EasySwapError::invalid_state("This trade has already been disputed"));
+    }
+
+    insert_dispute(TradeDispute {
+        trade_id,
+        order_id: trade.order_id,
+        maker: caller,
+        filler: trade.filler,
+        reason: reason.clone(),
+        status: DisputeStatus::Open,
+        flagged_at: get_time(),
+        resolution: None,
+        resolved_at: None,
+    });
+
+    create_admin_event(AdminEventType::TradeDisputed {
+        trade_id,
+        order_id: trade.order_id,
+        maker: caller,
+        reason,
+    });
+
+    ic_cdk::println!("⚠️ Trade {} put on hold - disputed by maker {}", trade_id, caller);
+    Ok(())
+}
+
+/// Resolve an open dispute
+/// Note: Admin check is enforced in lib.rs before calling this function
+pub fn admin_resolve_dispute(trade_id: TradeId, resolution: DisputeResolution) -> Result<(), EasySwapError> {
+    let mut dispute = get_dispute(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("No dispute found for this trade"))?;
+
+    if dispute.status != DisputeStatus::Open {
+        return Err(EasySwapError::invalid_state("Dispute is already resolved"));
+    }
+
+    let trade = get_trade(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
+
+    let now = get_time();
+
+    match resolution {
+        DisputeResolution::ReleaseToFiller => {
+            // Give back the claim time that was spent on hold
+            let hold_duration = now.saturating_sub(dispute.flagged_at);
+            update_trade(trade_id, |t| {
+                t.claim_expires_at = t.claim_expires_at.map(|expiry| expiry + hold_duration);
+            })?;
+            ic_cdk::println!("✅ Dispute on trade {} resolved - released to filler", trade_id);
+        }
+        DisputeResolution::ReturnToMaker => {
+            // Chunks go back to the order; the BSV txid stays marked as used
+            let chunk_ids: Vec<ChunkId> = trade.locked_chunks.iter()
+                .map(|lc| lc.chunk_id)
+                .collect();
+            chunk_allocation::unlock_chunks(&chunk_ids)?;
+
+            update_trade(trade_id, |t| {
+                t.status = TradeStatus::Cancelled;
+            })?;
+            ic_cdk::println!("✅ Dispute on trade {} resolved - chunks returned to maker", trade_id);
+        }
+    }
+
+    dispute.status = DisputeStatus::Resolved;
+    dispute.resolution = Some(resolution.clone());
+    dispute.resolved_at = Some(now);
+    insert_dispute(dispute);
+
+    create_admin_event(AdminEventType::DisputeResolved {
+        trade_id,
+        resolution,
+    });
+
+    Ok(())
+}
+
+/// Get dispute for a trade (visible to the maker, filler and admin)
+pub fn get_trade_dispute(trade_id: TradeId) -> Option<TradeDispute> {
+    let caller = get_caller();
+    let dispute = get_dispute(trade_id)?;
+
+    if caller == dispute.maker || caller == dispute.filler || caller == get_admin() {
+        Some(dispute)
+    } else {
+        None
+    }
+}
+
+// ===== PENALTY APPEALS =====
+
+/// PenaltyApplied events still inside the appeal window
+fn appealable_penalties(now: u64) -> Vec<AdminEvent> {
+    get_admin_events_matching(&AdminEventFilter {
+        kinds: Some(vec![AdminEventKind::PenaltyApplied]),
+        from_time: Some(now.saturating_sub(PENALTY_APPEAL_WINDOW_NS)),
+        to_time: None,
+    })
+}
+
+/// Trades an appeal may still reach - penalized within the window, or appealed and not yet
+/// resolved. data_cleanup keeps them until both are over
+pub(crate) fn trades_open_to_appeal(now: u64) -> HashSet<TradeId> {
+    let mut trade_ids: HashSet<TradeId> = appealable_penalties(now).iter()
+        .filter_map(|event| match &event.event_type {
+            AdminEventType::PenaltyApplied { trade_id, .. } => Some(*trade_id),
+            _ => None,
+        })
+        .collect();
+    PENALTY_APPEALS.with(|appeals| {
+        trade_ids.extend(appeals.borrow().iter()
+            .filter(|(_, appeal)| appeal.status != AppealStatus::Resolved)
+            .map(|(trade_id, _)| trade_id));
+    });
+    trade_ids
+}
+
+/// Filler appeals the penalties logged on one of their trades
+/// Only penalties with a PenaltyApplied event within PENALTY_APPEAL_WINDOW_NS can be appealed,
+/// and each trade only once
+pub fn appeal_penalty(trade_id: TradeId, explanation: String) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot appeal penalties. Please authenticate first."));
+    }
+
+    let explanation = explanation.trim().to_string();
+    if explanation.is_empty() {
+        return Err(EasySwapError::invalid_input("Appeal explanation is required"));
+    }
+
+    let trade = get_trade(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
+
+    if trade.filler != caller {
+        return Err(EasySwapError::unauthorized("Only the trade's filler can appeal its penalties"));
+    }
+
+    if get_penalty_appeal(trade_id).is_some() {
+        return Err(EasySwapError::invalid_state("Penalties on this trade have already been appealed"));
+    }
+
+    let now = get_time();
+    let penalty_amount: f64 = appealable_penalties(now).iter()
+        .filter_map(|event| match &event.event_type {
+            AdminEventType::PenaltyApplied { trade_id: penalized, filler, penalty_amount, .. }
+                if *penalized == trade_id && *filler == caller => Some(*penalty_amount),
+            _ => None,
+        })
+        .sum();
+    if penalty_amount <= 0.0 {
+        return Err(EasySwapError::invalid_state("No penalty on this trade within the appeal window"));
+    }
+
+    insert_penalty_appeal(PenaltyAppeal {
+        trade_id,
+        filler: caller,
+        explanation: explanation.clone(),
+        penalty_amount,
+        status: AppealStatus::Open,
+        appealed_at: now,
+        refund_amount: None,
+        refund_block_index: None,
+        resolved_at: None,
+    });
+
+    create_admin_event(AdminEventType::PenaltyAppealed {
+        trade_id,
+        filler: caller,
+        penalty_amount,
+        explanation,
+    });
+
+    ic_cdk::println!("⚖️ Filler {} appealed ${:.2} of penalties on trade {}", caller, penalty_amount, trade_id);
+    Ok(())
+}
+
+/// Resolve an appeal - refund_amount (up to the appealed penalty) goes from the treasury to the
+/// filler's deposit subaccount, 0 denies the appeal. Only treasury revenue the admin could
+/// withdraw is used. A refund whose outcome was lost stays Refunding and is resent unchanged
+/// when resolved again with the same amount
+/// Note: Admin check is enforced in lib.rs before calling this function
+pub async fn admin_resolve_appeal(trade_id: TradeId, refund_amount: f64) -> Result<PenaltyAppeal, EasySwapError> {
+    let mut appeal = get_penalty_appeal(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("No appeal found for this trade"))?;
+
+    match appeal.status {
+        AppealStatus::Resolved => return Err(EasySwapError::invalid_state("Appeal is already resolved")),
+        AppealStatus::Refunding if appeal.refund_amount != Some(refund_amount) => {
+            return Err(EasySwapError::invalid_state(format!(
+                "A refund of ${:.6} is in flight - resolve again with that amount to retry it",
+                appeal.refund_amount.unwrap_or(0.0)
+            )));
+        }
+        _ => {}
+    }
+
+    if !refund_amount.is_finite() || refund_amount < 0.0 || refund_amount > appeal.penalty_amount {
+        return Err(EasySwapError::invalid_input(format!(
+            "Refund must be between $0 and the ${:.6} penalised", appeal.penalty_amount
+        )));
+    }
+
+    if refund_amount == 0.0 {
+        appeal.refund_amount = Some(0.0);
+        appeal.status = AppealStatus::Resolved;
+        appeal.resolved_at = Some(get_time());
+        insert_penalty_appeal(appeal.clone());
+        create_admin_event(AdminEventType::AppealResolved {
+            trade_id,
+            filler: appeal.filler,
+            refund_amount: 0.0,
+            block_index: None,
+        });
+        ic_cdk::println!("⚖️ Appeal on trade {} denied", trade_id);
+        return Ok(appeal);
+    }
+
+    let amount_e6 = crate::ckusdc_integration::usd_to_ckusdc_e6(refund_amount);
+    let ledger_id = Principal::from_text(CK_USDC_LEDGER)
+        .map_err(|e| EasySwapError::ledger(format!("Invalid ledger canister ID: {:?}", e)))?;
+
+    // A resend skips the balance check - the first attempt may already have spent it
+    if appeal.status == AppealStatus::Open {
+        let treasury = icrc_ledger_types::icrc1::account::Account { owner: crate::runtime::canister_id(), subaccount: None };
+        let balance = match crate::runtime::ledger().icrc1_balance_of(ledger_id, treasury).await {
+            Ok((balance,)) => crate::ckusdc_integration::nat_to_u64(&balance).unwrap_or(u64::MAX),
+            Err((code, msg)) => return Err(EasySwapError::ledger(format!("Failed to get ckUSDC balance: {:?}: {}", code, msg))),
+        };
+        let withdrawable_e6 = crate::treasury::withdrawable_e6(balance);
+        if amount_e6 > withdrawable_e6 as u128 {
+            return Err(EasySwapError::insufficient_funds(format!(
+                "Treasury can refund up to {} e6, requested {}", withdrawable_e6, amount_e6
+            )));
+        }
+
+        appeal.status = AppealStatus::Refunding;
+        appeal.refund_amount = Some(refund_amount);
+        insert_penalty_appeal(appeal.clone());
+    }
+
+    // The memo names the trade, so a resend after a lost reply is deduplicated by the ledger
+    let memo = format!("Appeal refund T{}", trade_id).into_bytes();
+    let transfer_args = TransferArg {
+        from_subaccount: None,
+        to: crate::filler_accounts::get_deposit_account(appeal.filler),
+        fee: None,
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+        memo: Some(Memo::from(memo.clone())),
+        amount: Nat::from(amount_e6),
+    };
+
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_id, transfer_args).await;
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
+
+    match result {
+        Ok((Ok(block_index),)) | Ok((Err(TransferError::Duplicate { duplicate_of: block_index }),)) => {
+            let block_index = crate::ckusdc_integration::nat_to_u64(&block_index).ok();
+            crate::treasury::record_appeal_refund(amount_e6 as UsdE6 + CKUSDC_TRANSFER_FEE as UsdE6);
+            update_filler_account(appeal.filler, |account| {
+                account.penalties_paid = (account.penalties_paid - refund_amount).max(0.0);
+            }).ok();
+
+            appeal.status = AppealStatus::Resolved;
+            appeal.refund_block_index = block_index;
+            appeal.resolved_at = Some(get_time());
+            insert_penalty_appeal(appeal.clone());
+            create_admin_event(AdminEventType::AppealResolved {
+                trade_id,
+                filler: appeal.filler,
+                refund_amount,
+                block_index,
+            });
+            ic_cdk::println!("⚖️ Appeal on trade {} upheld - refunded ${:.6} to filler {}", trade_id, refund_amount, appeal.filler);
+            Ok(appeal)
+        }
+        // Rejected - nothing was paid, so the appeal is open again
+        Ok((Err(e),)) => {
+            appeal.status = AppealStatus::Open;
+            appeal.refund_amount = None;
+            insert_penalty_appeal(appeal);
+            Err(EasySwapError::ledger(format!("Appeal refund failed: {:?}", e)))
+        }
+        // Outcome unknown - stays Refunding for the next attempt
+        Err((code, msg)) => Err(EasySwapError::ledger(format!(
+            "Appeal refund call failed: {:?}: {}. Resolve again with the same amount to retry it.",
+            code, msg
+        ))),
+    }
+}
+
+/// Get the appeal for a trade (visible to its filler and the admin)
+pub fn get_trade_penalty_appeal(trade_id: TradeId) -> Option<PenaltyAppeal> {
+    let caller = get_caller();
+    let appeal = get_penalty_appeal(trade_id)?;
+
+    if caller == appeal.filler || caller == get_admin() {
+        Some(appeal)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use crate::trade_lifecycle::CreateTradesRequest;
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn filler() -> Principal {
+        Principal::from_slice(&[2, 2, 2])
+    }
+
+    /// Two $3 trades on one order of the maker, marked as paid without going through SPV
+    fn submitted_trades() -> Vec<TradeId> {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        block_on(crate::order_management::create_order(6.0, 100.0, crate::lifecycle_tests::p2pkh_address(&[0x11; 20]), None)).unwrap();
+
+        mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+        mock::set_caller(filler());
+        let trade_ids: Vec<TradeId> = (0..2)
+            .map(|_| block_on(crate::trade_lifecycle::create_trades(filler(), CreateTradesRequest {
+                requested_usd: 3.0,
+                allow_partial: true,
+                min_bsv_price: 40.0,
+                prefer_higher_incentive: None,
+                book_id: None,
+            })).unwrap()[0])
+            .collect();
+        let claim_expires_at = get_time() + 24 * 3_600 * 1_000_000_000;
+        for trade_id in &trade_ids {
+            update_trade(*trade_id, |t| {
+                t.status = TradeStatus::TxSubmitted;
+                t.claim_expires_at = Some(claim_expires_at);
+            }).unwrap();
+        }
+        trade_ids
+    }
+
+    #[test]
+    fn disputed_trade_is_held_until_released_with_its_claim_time_restored() {
+        let trade_id = submitted_trades()[0];
+        let claim_expires_at = get_trade(trade_id).unwrap().claim_expires_at.unwrap();
+
+        assert!(maker_flag_dispute(trade_id, "no payment seen".to_string()).is_err(), "only the maker can dispute");
+        mock::set_caller(maker());
+        assert!(maker_flag_dispute(trade_id, "  ".to_string()).is_err());
+        maker_flag_dispute(trade_id, "no payment seen".to_string()).unwrap();
+        assert!(maker_flag_dispute(trade_id, "again".to_string()).is_err());
+
+        assert!(is_trade_on_hold(trade_id));
+        let claim = block_on(crate::trade_lifecycle::claim_usdc(filler(), trade_id, String::new(), String::new()));
+        assert!(matches!(claim, Err(EasySwapError::InvalidState { .. })));
+
+        mock::advance_time(3_600 * 1_000_000_000);
+        admin_resolve_dispute(trade_id, DisputeResolution::ReleaseToFiller).unwrap();
+        assert!(!is_trade_on_hold(trade_id));
+        assert_eq!(get_trade(trade_id).unwrap().claim_expires_at, Some(claim_expires_at + 3_600 * 1_000_000_000));
+        assert!(admin_resolve_dispute(trade_id, DisputeResolution::ReturnToMaker).is_err(), "already resolved");
+    }
+
+    #[test]
+    fn dispute_returned_to_maker_cancels_the_trade_and_frees_its_chunks() {
+        let trade_ids = submitted_trades();
+        mock::set_caller(maker());
+        maker_flag_dispute(trade_ids[1], "wrong amount".to_string()).unwrap();
+
+        admin_resolve_dispute(trade_ids[1], DisputeResolution::ReturnToMaker).unwrap();
+        let trade = get_trade(trade_ids[1]).unwrap();
+        assert_eq!(trade.status, TradeStatus::Cancelled);
+        for locked in &trade.locked_chunks {
+            assert_eq!(get_chunk(locked.chunk_id).unwrap().status, ChunkStatus::Available);
+        }
+        // The other trade on the order is untouched
+        assert_eq!(get_trade(trade_ids[0]).unwrap().status, TradeStatus::TxSubmitted);
+        assert_eq!(get_trade_dispute(trade_ids[1]).unwrap().resolution, Some(DisputeResolution::ReturnToMaker));
+    }
+}
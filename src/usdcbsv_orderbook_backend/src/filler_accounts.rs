@@ -1,10 +1,17 @@
 use crate::types::*;
 use crate::state::*;
-use crate::config::{MAX_LOCK_MULTIPLIER};
+use crate::config::{
+    MAX_LOCK_MULTIPLIER, SECURITY_DEPOSIT_PERCENT, MAX_COLLATERAL_TOKENS, COLLATERAL_PRICE_CACHE_NS,
+    TRUSTED_FILLER_MIN_SUCCESSFUL_TRADES, TRUSTED_FILLER_CLEAN_WINDOW_NS,
+    TRUSTED_FILLER_SECURITY_DEPOSIT_PERCENT, TRUSTED_FILLER_LOCK_MULTIPLIER,
+};
 use candid::{Nat, Principal};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use icrc_ledger_types::icrc1::account::Account;
 use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError, Memo};
 use sha2::{Sha256, Digest};
+use crate::runtime::{LedgerClient, Oracle};
 
 // ckUSDC Ledger canister ID (mainnet)
 const CK_USDC_LEDGER_ID: &str = "xevnm-gaaaa-aaaar-qafnq-cai";
@@ -21,6 +28,20 @@ fn principal_to_subaccount(principal: Principal) -> Subaccount {
     subaccount.copy_from_slice(&result);
     subaccount
 }
+/// Whether a principal may trade - always true while the allowlist is off
+pub fn is_approved_filler(principal: Principal) -> bool {
+    !is_filler_allowlist_enabled() || is_filler_on_allowlist(principal)
+}
+
+/// Reject principals not on the allowlist while allowlist mode is enabled
+pub fn ensure_approved_filler(principal: Principal) -> Result<(), EasySwapError> {
+    if is_approved_filler(principal) {
+        Ok(())
+    } else {
+        Err(EasySwapError::unauthorized("Trading is limited to approved fillers. Contact the operator to be approved."))
+    }
+}
+
 pub fn create_account_if_needed(principal: Principal) {
     if get_filler_account(principal).is_none() {
         let now = get_time();
@@ -31,14 +52,39 @@ pub fn create_account_if_needed(principal: Principal) {
             successful_trades: 0,
             penalties_paid: 0.0,
             created_at: now,
+            timeout_releases: Some(0),
+            last_penalty_at: None,
+            instant_release: None,
         };
         insert_filler_account(account);
     }
 }
 
+// ===== TRUSTED FILLER TIER =====
+
+/// Enough completed trades and no penalty within TRUSTED_FILLER_CLEAN_WINDOW_NS - a penalty
+/// demotes the filler straight away, and they return once it has aged out of the window
+pub fn is_trusted_filler(account: &FillerAccount, now: u64) -> bool {
+    let recently_penalized = matches!(
+        account.last_penalty_at,
+        Some(at) if now.saturating_sub(at) < TRUSTED_FILLER_CLEAN_WINDOW_NS
+    );
+    account.successful_trades >= TRUSTED_FILLER_MIN_SUCCESSFUL_TRADES && !recently_penalized
+}
+
+/// (security deposit percent, lock multiplier) a filler trades under right now
+pub fn security_terms(filler: Principal) -> (u64, u64) {
+    match get_filler_account(filler) {
+        Some(account) if is_trusted_filler(&account, get_time()) => {
+            (TRUSTED_FILLER_SECURITY_DEPOSIT_PERCENT, TRUSTED_FILLER_LOCK_MULTIPLIER)
+        }
+        _ => (SECURITY_DEPOSIT_PERCENT, MAX_LOCK_MULTIPLIER),
+    }
+}
+
 // Get deposit account for a user
 pub fn get_deposit_account(user_principal: Principal) -> Account {
-    let owner = ic_cdk::id(); // Canister's principal
+    let owner = crate::runtime::canister_id(); // Canister's principal
     let subaccount = principal_to_subaccount(user_principal);
     Account {
         owner,
@@ -46,20 +92,20 @@ pub fn get_deposit_account(user_principal: Principal) -> Account {
     }
 }
 
-pub async fn deposit_security(amount: u64) -> Result<(), String> {
+pub async fn deposit_security(amount: u64) -> Result<(), EasySwapError> {
     let caller = get_caller();
     
     // Get the deposit account for this filler
     let account = get_deposit_account(caller);
     
     // Check ckUSDC balance of the filler's subaccount in our canister
-    let balance = check_ckusdc_balance(account).await?;
+    let balance = check_ckusdc_balance(account).await.map_err(EasySwapError::ledger)?;
     
     if balance < amount {
-        return Err(format!(
+        return Err(EasySwapError::insufficient_funds(format!(
             "Insufficient ckUSDC balance in subaccount. Required: {}, Available: {}",
             amount, balance
-        ));
+        )));
     }
     
     // Balance is live from ledger - no need to store it
@@ -88,8 +134,9 @@ pub async fn get_available_security_balance(principal: Principal) -> Result<f64,
     // Get filler account to check pending trades
     let filler_account = crate::state::get_filler_account(principal);
     let locked_in_trades = if let Some(account) = filler_account {
-        // Locked amount is 5% of pending trade total
-        account.pending_trades_total * 0.05
+        // Locked amount is the filler's deposit percent of pending trade total
+        let (security_deposit_percent, _) = security_terms(principal);
+        account.pending_trades_total * security_deposit_percent as f64 / 100.0
     } else {
         0.0
     };
@@ -100,14 +147,326 @@ pub async fn get_available_security_balance(principal: Principal) -> Result<f64,
     Ok(available.max(0.0)) // Never return negative
 }
 
+/// Caller's security balance split the way create_trades checks it, so fillers can size a new
+/// trade before asking for it
+pub async fn get_my_security_breakdown() -> Result<SecurityBreakdown, EasySwapError> {
+    let caller = get_caller();
+    let ckusdc_balance_e6: UsdE6 = get_security_balance_for_principal(caller).await.map_err(EasySwapError::ledger)?;
+    let collateral = get_collateral_balances(caller).await;
+    let total_balance_e6 = ckusdc_balance_e6 + collateral.iter().map(|c| c.credited_e6).sum::<UsdE6>();
+    
+    let (security_deposit_percent, lock_multiplier) = security_terms(caller);
+    let pending_trades_e6 = calculate_pending_trades_for_filler(caller);
+    let locked_e6 = pending_trades_e6 * security_deposit_percent / 100;
+    let release_bonds_e6 = crate::release_bonds::bonded_e6(caller);
+    
+    // The per-filler open interest cap applies however large the deposit is
+    let max_lock_capacity_e6 = (total_balance_e6.saturating_sub(release_bonds_e6) * lock_multiplier)
+        .min(get_exposure_limits().max_filler_open_interest_e6);
+    
+    Ok(SecurityBreakdown {
+        total_balance_e6,
+        ckusdc_balance_e6,
+        collateral,
+        trusted: get_filler_account(caller).is_some_and(|account| is_trusted_filler(&account, get_time())),
+        security_deposit_percent,
+        lock_multiplier,
+        pending_trades_e6,
+        locked_e6,
+        release_bonds_e6,
+        available_e6: total_balance_e6.saturating_sub(locked_e6 + release_bonds_e6),
+        max_lock_capacity_e6,
+        remaining_lock_capacity_e6: max_lock_capacity_e6.saturating_sub(pending_trades_e6),
+        checked_at: get_time(),
+    })
+}
+
+// ===== COLLATERAL =====
+
+thread_local! {
+    /// USD rate per price symbol and when it was fetched - heap only, refetched after an upgrade
+    static COLLATERAL_RATES: RefCell<BTreeMap<String, (f64, u64)>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Validate and add a collateral token, or replace the one with the same symbol
+pub fn set_collateral_token(token: CollateralToken) -> Result<(), EasySwapError> {
+    if token.symbol.trim().is_empty() {
+        return Err(EasySwapError::invalid_input("Collateral token needs a symbol"));
+    }
+    if token.ledger.to_text() == CK_USDC_LEDGER_ID {
+        return Err(EasySwapError::invalid_input("ckUSDC is the base security deposit, not a collateral token"));
+    }
+    if token.credit_bps == 0 || token.credit_bps > 10_000 {
+        return Err(EasySwapError::invalid_input("Collateral credit must be between 1 and 10000 bps"));
+    }
+    if token.decimals > 18 {
+        return Err(EasySwapError::invalid_input("Collateral tokens may have at most 18 decimals"));
+    }
+    
+    let mut tokens = get_collateral_tokens();
+    match tokens.iter().position(|t| t.symbol == token.symbol) {
+        Some(index) => tokens[index] = token,
+        None if tokens.len() >= MAX_COLLATERAL_TOKENS => {
+            return Err(EasySwapError::limit_exceeded(format!("At most {} collateral tokens", MAX_COLLATERAL_TOKENS)));
+        }
+        None => tokens.push(token),
+    }
+    set_collateral_tokens(tokens);
+    Ok(())
+}
+
+/// Stop crediting a collateral token - the token stays listed as disabled, so balances already
+/// deposited stay withdrawable, and setting it again re-enables it
+pub fn remove_collateral_token(symbol: &str) -> Result<(), EasySwapError> {
+    let mut tokens = get_collateral_tokens();
+    let token = tokens.iter_mut()
+        .find(|t| t.symbol == symbol && !t.is_disabled())
+        .ok_or_else(|| EasySwapError::not_found(format!("No collateral token {}", symbol)))?;
+    token.disabled = Some(true);
+    set_collateral_tokens(tokens);
+    Ok(())
+}
+
+fn collateral_token(symbol: &str) -> Result<CollateralToken, EasySwapError> {
+    get_collateral_tokens().into_iter()
+        .find(|t| t.symbol == symbol)
+        .ok_or_else(|| EasySwapError::not_found(format!("No collateral token {}", symbol)))
+}
+
+/// USD rate of a collateral token, cached for COLLATERAL_PRICE_CACHE_NS
+async fn collateral_usd_rate(token: &CollateralToken) -> Result<f64, String> {
+    let Some(symbol) = &token.price_symbol else {
+        return Ok(1.0);
+    };
+    
+    let now = get_time();
+    let cached = COLLATERAL_RATES.with(|rates| rates.borrow().get(symbol).copied());
+    if let Some((rate, fetched_at)) = cached {
+        if now.saturating_sub(fetched_at) < COLLATERAL_PRICE_CACHE_NS {
+            return Ok(rate);
+        }
+    }
+    
+    let rate = crate::runtime::oracle().fetch_usd_rate(symbol).await?;
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(format!("Invalid {}/USD rate: {}", symbol, rate));
+    }
+    COLLATERAL_RATES.with(|rates| rates.borrow_mut().insert(symbol.clone(), (rate, now)));
+    Ok(rate)
+}
+
+fn token_units_to_usd_e6(units: u64, decimals: u8, rate: f64) -> UsdE6 {
+    (units as f64 / 10f64.powi(decimals as i32) * rate * 1_000_000.0) as UsdE6
+}
+
+async fn check_token_balance(token: &CollateralToken, account: Account) -> Result<u64, String> {
+    match crate::runtime::ledger().icrc1_balance_of(token.ledger, account).await {
+        Ok((balance,)) => nat_to_u64(&balance),
+        Err((code, msg)) => Err(format!("Failed to check {} balance: {:?}: {}", token.symbol, code, msg)),
+    }
+}
+
+/// Collateral a filler holds, valued at the oracle rate and haircut
+/// Tokens whose balance or rate can't be read are left out, so an outage only ever lowers capacity
+pub async fn get_collateral_balances(principal: Principal) -> Vec<CollateralBalance> {
+    let account = get_deposit_account(principal);
+    let mut balances = Vec::new();
+    
+    for token in get_collateral_tokens() {
+        let balance = match check_token_balance(&token, account).await {
+            Ok(0) => continue,
+            Ok(balance) => balance,
+            Err(e) => {
+                ic_cdk::println!("⚠️ Skipping {} collateral of {}: {}", token.symbol, principal, e);
+                continue;
+            }
+        };
+        let rate = match collateral_usd_rate(&token).await {
+            Ok(rate) => rate,
+            Err(e) => {
+                ic_cdk::println!("⚠️ Skipping {} collateral of {}: {}", token.symbol, principal, e);
+                continue;
+            }
+        };
+        
+        let usd_value_e6 = token_units_to_usd_e6(balance, token.decimals, rate);
+        let credited_e6 = if token.is_disabled() { 0 } else { bps_of(usd_value_e6, token.credit_bps) };
+        balances.push(CollateralBalance {
+            symbol: token.symbol,
+            balance,
+            usd_value_e6,
+            credited_e6,
+        });
+    }
+    balances
+}
+
+/// Security counted toward MAX_LOCK_MULTIPLIER: ckUSDC at face value plus haircut collateral
+pub async fn get_security_value_e6(principal: Principal) -> Result<UsdE6, String> {
+    let ckusdc_e6 = get_security_balance_for_principal(principal).await?;
+    let collateral_e6: UsdE6 = get_collateral_balances(principal).await.iter().map(|c| c.credited_e6).sum();
+    Ok(ckusdc_e6 + collateral_e6)
+}
+
+/// Pay a penalty the ckUSDC deposit can't cover out of the first collateral token that holds
+/// enough, valued at the oracle rate without the haircut - returns the ledger block index
+async fn transfer_penalty_from_collateral(filler: Principal, to: Account, amount_e6: u128, memo: &[u8]) -> Result<u64, String> {
+    let from = get_deposit_account(filler);
+    
+    for token in get_collateral_tokens().into_iter().filter(|t| !t.is_disabled()) {
+        let Ok(rate) = collateral_usd_rate(&token).await else {
+            continue;
+        };
+        let units = (amount_e6 as f64 / 1_000_000.0 / rate * 10f64.powi(token.decimals as i32)).ceil() as u64;
+        let Ok(balance) = check_token_balance(&token, from).await else {
+            continue;
+        };
+        if balance < units.saturating_add(token.transfer_fee) {
+            continue;
+        }
+        
+        // Keyed per token, so the ckUSDC attempt and this one deduplicate separately
+        let dedup_key = [memo, token.symbol.as_bytes()].concat();
+        let transfer_args = TransferArg {
+            from_subaccount: from.subaccount,
+            to,
+            fee: Some(Nat::from(token.transfer_fee)),
+            created_at_time: Some(crate::transfer_dedup::created_at_time(&dedup_key)),
+            memo: Some(Memo::from(memo.to_vec())),
+            amount: Nat::from(units),
+        };
+        
+        let result = crate::runtime::ledger().icrc1_transfer(token.ledger, transfer_args).await;
+        if result.is_ok() {
+            crate::transfer_dedup::settle(&dedup_key);
+        }
+        match result {
+            Ok((Ok(block_index),)) | Ok((Err(TransferError::Duplicate { duplicate_of: block_index }),)) => {
+                ic_cdk::println!("✅ Penalty paid in {} {} collateral", units, token.symbol);
+                return nat_to_u64(&block_index);
+            }
+            Ok((Err(e),)) => ic_cdk::println!("⚠️ {} penalty transfer failed: {:?}", token.symbol, e),
+            Err((code, msg)) => ic_cdk::println!("⚠️ {} penalty transfer call failed: {:?}: {}", token.symbol, code, msg),
+        }
+    }
+    
+    Err("No collateral covers the penalty".to_string())
+}
+
+/// Withdraw a collateral token from the caller's deposit subaccount, as long as what remains
+/// still secures their open trades
+pub async fn withdraw_collateral(symbol: String, amount: u64, to_principal: Principal) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    let token = collateral_token(&symbol)?;
+    let from_account = get_deposit_account(caller);
+    
+    let balance = check_token_balance(&token, from_account).await.map_err(EasySwapError::ledger)?;
+    let total_needed = amount.checked_add(token.transfer_fee)
+        .ok_or_else(|| EasySwapError::invalid_input("Amount overflow"))?;
+    if balance < total_needed {
+        return Err(EasySwapError::insufficient_funds(format!(
+            "Insufficient {} balance. Available: {}, Requested: {} (including fee: {})",
+            symbol, balance, amount, token.transfer_fee
+        )));
+    }
+    
+    let pending_e6 = calculate_pending_trades_for_filler(caller);
+    let bonded_e6 = crate::release_bonds::bonded_e6(caller);
+    if pending_e6 > 0 || bonded_e6 > 0 {
+        // Credit lost with the withdrawn units (a token that can't be priced already counts for nothing)
+        let security_e6 = get_security_value_e6(caller).await.map_err(EasySwapError::ledger)?;
+        let withdrawn_e6 = match collateral_usd_rate(&token).await {
+            Ok(rate) => bps_of(token_units_to_usd_e6(total_needed, token.decimals, rate), token.credit_bps),
+            Err(_) => 0,
+        };
+        if security_e6.saturating_sub(withdrawn_e6) < bonded_e6 {
+            return Err(EasySwapError::insufficient_funds(format!(
+                "Cannot withdraw: ${:.6} of security is bonded against instantly released trades",
+                e6_to_usd(bonded_e6)
+            )));
+        }
+        let remaining_e6 = security_e6.saturating_sub(withdrawn_e6) - bonded_e6;
+        let (_, lock_multiplier) = security_terms(caller);
+        if remaining_e6 * lock_multiplier < pending_e6 {
+            return Err(EasySwapError::insufficient_funds(format!(
+                "Cannot withdraw: would violate security requirements. Need at least ${:.6} of security for pending locked chunks of ${:.6}",
+                e6_to_usd(pending_e6 / lock_multiplier),
+                e6_to_usd(pending_e6)
+            )));
+        }
+    }
+    
+    // Keyed per filler, token and recipient - a retry after a lost reply is deduplicated by the ledger
+    let memo = format!("Withdraw {}", amount).into_bytes();
+    let dedup_key = [memo.as_slice(), token.symbol.as_bytes(), caller.as_slice(), to_principal.as_slice()].concat();
+    let transfer_args = TransferArg {
+        from_subaccount: from_account.subaccount,
+        to: Account {
+            owner: to_principal,
+            subaccount: None,
+        },
+        amount: Nat::from(amount),
+        fee: Some(Nat::from(token.transfer_fee)),
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&dedup_key)),
+        memo: Some(Memo::from(memo)),
+    };
+    
+    let result = crate::runtime::ledger().icrc1_transfer(token.ledger, transfer_args).await;
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&dedup_key);
+    }
+    
+    match result {
+        Ok((Ok(_),)) | Ok((Err(TransferError::Duplicate { .. }),)) => Ok(()),
+        Ok((Err(e),)) => Err(EasySwapError::ledger(format!("Withdrawal transfer failed: {:?}", e))),
+        Err((code, msg)) => Err(EasySwapError::ledger(format!("Transfer call failed: {:?}: {}", code, msg))),
+    }
+}
+
 pub async fn deduct_penalty(filler: Principal, penalty_amount: f64, recipient: Option<Principal>, memo_hint: Option<String>) -> Result<(), String> {
     // Update penalty tracking
+    // Any penalty also drops the filler off the trusted tier
+    let now = get_time();
     update_filler_account(filler, |account| {
         account.penalties_paid += penalty_amount;
+        account.last_penalty_at = Some(now);
     })?;
     
+    ic_cdk::println!("💰 Deducting penalty ${:.6} from filler {}", penalty_amount, filler);
+    
+    transfer_penalty(filler, recipient, penalty_transfer_amount_e6(penalty_amount), penalty_memo(memo_hint))
+        .await
+        .map(|_| ())
+}
+
+/// Net amount moved for a penalty - the ledger fee comes out of the penalty itself
+pub(crate) fn penalty_transfer_amount_e6(penalty_amount: f64) -> u128 {
+    crate::ckusdc_integration::usd_to_ckusdc_e6(penalty_amount)
+        .saturating_sub(crate::config::CKUSDC_TRANSFER_FEE)
+}
+
+/// Prefer the provided hint, fallback to a generic message (max 80 bytes)
+pub(crate) fn penalty_memo(memo_hint: Option<String>) -> Vec<u8> {
+    memo_hint
+        .map(|s| {
+            let bytes = s.into_bytes();
+            if bytes.len() <= 80 {
+                bytes
+            } else {
+                // Truncate to 80 bytes if too long
+                bytes[..80].to_vec()
+            }
+        })
+        .unwrap_or_else(|| b"Penalty".to_vec())
+}
+
+/// Ledger side of a penalty: move amount_e6 from the filler's deposit subaccount to the
+/// maker (timeout penalties) or the treasury (recipient None)
+/// Penalty accounting is NOT touched here, so pending_transfers.rs can retry this alone
+/// Returns the ledger block index
+pub(crate) async fn transfer_penalty(filler: Principal, recipient: Option<Principal>, amount_e6: u128, memo: Vec<u8>) -> Result<u64, String> {
     // Determine recipient: Some(maker) for timeout penalties, None for treasury
-    let treasury_principal = ic_cdk::api::id(); // Treasury is the canister itself
+    let treasury_principal = crate::runtime::canister_id(); // Treasury is the canister itself
     let recipient_account = match recipient {
         Some(maker) => Account {
             owner: maker,
@@ -126,46 +485,41 @@ pub async fn deduct_penalty(filler: Principal, penalty_amount: f64, recipient: O
     
     // Transfer the penalty from filler subaccount to recipient
     let from_account = get_deposit_account(filler);
-    let amount_e6 = crate::ckusdc_integration::usd_to_ckusdc_e6(penalty_amount);
-    
-    // Deduct transfer fee
-    let amount_after_fee = amount_e6.saturating_sub(crate::config::CKUSDC_TRANSFER_FEE);
     
-    ic_cdk::println!("💰 Deducting penalty ${:.6} from filler {} to {}", penalty_amount, filler, recipient_name);
-    
-    // Determine memo: prefer provided hint, fallback to a generic message (max 80 bytes)
-    let memo_bytes = memo_hint
-        .map(|s| {
-            let bytes = s.into_bytes();
-            if bytes.len() <= 80 {
-                bytes
-            } else {
-                // Truncate to 80 bytes if too long
-                bytes[..80].to_vec()
-            }
-        })
-        .unwrap_or_else(|| b"Penalty".to_vec());
-
     // Use icrc1_transfer to send to recipient account
+    // The memo names the trade, so a retry after a lost reply is deduplicated by the ledger
     let transfer_args = TransferArg {
         from_subaccount: from_account.subaccount,
         to: recipient_account,
         fee: None,
-        created_at_time: None,
-        memo: Some(Memo::from(memo_bytes)),
-        amount: Nat::from(amount_after_fee),
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+        memo: Some(Memo::from(memo.clone())),
+        amount: Nat::from(amount_e6),
     };
     
     let ledger_id = Principal::from_text(CK_USDC_LEDGER_ID)
         .map_err(|e| format!("Invalid ledger canister ID: {:?}", e))?;
     
-    let result: Result<(Result<Nat, TransferError>,), _> = 
-        ic_cdk::call(ledger_id, "icrc1_transfer", (transfer_args,)).await;
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_id, transfer_args).await;
+    
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
     
     match result {
-        Ok((Ok(_block_index),)) => {
+        Ok((Ok(block_index),)) | Ok((Err(TransferError::Duplicate { duplicate_of: block_index }),)) => {
             ic_cdk::println!("✅ Penalty transferred to {}", recipient_name);
-            Ok(())
+            if recipient.is_none() {
+                crate::treasury::record_inflow(crate::types::TreasuryInflowKind::Penalty, amount_e6 as u64);
+            }
+            nat_to_u64(&block_index)
+        }
+        // Not enough ckUSDC left - fall back to the filler's collateral
+        Ok((Err(TransferError::InsufficientFunds { .. }),)) if !get_collateral_tokens().is_empty() => {
+            let block_index = transfer_penalty_from_collateral(filler, recipient_account, amount_e6, &memo).await
+                .map_err(|e| format!("Transfer failed: insufficient ckUSDC, {}", e))?;
+            ic_cdk::println!("✅ Penalty transferred to {} from collateral", recipient_name);
+            Ok(block_index)
         }
         Ok((Err(e),)) => Err(format!("Transfer failed: {:?}", e)),
         Err((code, msg)) => Err(format!("Call failed: {:?}: {}", code, msg)),
@@ -196,7 +550,8 @@ pub async fn migrate_old_penalty_funds() -> Result<u64, String> {
     }
     
     // Transfer to treasury
-    let treasury_principal = ic_cdk::api::id();
+    let treasury_principal = crate::runtime::canister_id();
+    let memo = b"Penalty migration to treasury".to_vec();
     let transfer_args = TransferArg {
         from_subaccount: old_penalty_account.subaccount,
         to: Account {
@@ -204,19 +559,22 @@ pub async fn migrate_old_penalty_funds() -> Result<u64, String> {
             subaccount: None, // Treasury default account
         },
         fee: None,
-        created_at_time: None,
-        memo: Some(Memo::from(b"Penalty migration to treasury".to_vec())),
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+        memo: Some(Memo::from(memo.clone())),
         amount: Nat::from(amount_after_fee),
     };
     
     let ledger_id = Principal::from_text(CK_USDC_LEDGER_ID)
         .map_err(|e| format!("Invalid ledger canister ID: {:?}", e))?;
     
-    let result: Result<(Result<Nat, TransferError>,), _> = 
-        ic_cdk::call(ledger_id, "icrc1_transfer", (transfer_args,)).await;
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_id, transfer_args).await;
+    
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
     
     match result {
-        Ok((Ok(_block_index),)) => {
+        Ok((Ok(_),)) | Ok((Err(TransferError::Duplicate { .. }),)) => {
             ic_cdk::println!("✅ Migrated {} e6 from old penalty account to treasury", amount_after_fee);
             Ok(amount_after_fee as u64)
         }
@@ -232,7 +590,7 @@ fn get_old_penalty_account() -> Account {
     subaccount[..admin_bytes.len()].copy_from_slice(admin_bytes);
     
     Account {
-        owner: ic_cdk::id(),
+        owner: crate::runtime::canister_id(),
         subaccount: Some(subaccount),
     }
 }
@@ -246,7 +604,7 @@ fn get_admin_account_deprecated() -> Account {
     subaccount[..admin_bytes.len()].copy_from_slice(admin_bytes);
     
     Account {
-        owner: ic_cdk::id(),
+        owner: crate::runtime::canister_id(),
         subaccount: Some(subaccount),
     }
 }
@@ -256,8 +614,7 @@ async fn check_ckusdc_balance(account: Account) -> Result<u64, String> {
     let ledger_id = Principal::from_text(CK_USDC_LEDGER_ID)
         .map_err(|e| format!("Invalid ledger canister ID: {:?}", e))?;
     
-    #[allow(deprecated)]
-    let result: Result<(Nat,), _> = ic_cdk::call(ledger_id, "icrc1_balance_of", (account,)).await;
+    let result = crate::runtime::ledger().icrc1_balance_of(ledger_id, account).await;
     
     match result {
         Ok((balance,)) => {
@@ -276,7 +633,7 @@ async fn transfer_ckusdc_internal(
     to_account: Account,
     amount: f64,
 ) -> Result<Nat, String> {
-    use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
+    use icrc_ledger_types::icrc1::transfer::TransferArg;
     
     let ledger_id = Principal::from_text(CK_USDC_LEDGER_ID)
         .map_err(|e| format!("Invalid ledger canister ID: {:?}", e))?;
@@ -293,9 +650,7 @@ async fn transfer_ckusdc_internal(
         created_at_time: Some(get_time()),
     };
     
-    #[allow(deprecated)]
-    let result: Result<(Result<Nat, TransferError>,), _> = 
-        ic_cdk::call(ledger_id, "icrc1_transfer", (transfer_args,)).await;
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_id, transfer_args).await;
     
     match result {
         Ok((Ok(block_index),)) => Ok(block_index),
@@ -328,25 +683,25 @@ pub fn get_filler_subaccount_address(principal: Principal) -> String {
     }
 }
 
-pub async fn withdraw_security(amount: u64, to_principal: Principal) -> Result<(), String> {
+pub async fn withdraw_security(amount: u64, to_principal: Principal) -> Result<(), EasySwapError> {
     let caller = get_caller();
     
     // Get live balance from ledger
     let from_account = get_deposit_account(caller);
-    let current_balance = check_ckusdc_balance(from_account.clone()).await?;
+    let current_balance = check_ckusdc_balance(from_account.clone()).await.map_err(EasySwapError::ledger)?;
     
     // ckUSDC transfer fee is 10,000 e6s (0.01 ckUSDC)
     const CKUSDC_FEE: u64 = 10_000;
     
     // Check if user has enough available balance (amount + fee)
     let total_needed = amount.checked_add(CKUSDC_FEE)
-        .ok_or_else(|| "Amount overflow".to_string())?;
+        .ok_or_else(|| EasySwapError::invalid_input("Amount overflow"))?;
         
     if current_balance < total_needed {
-        return Err(format!(
+        return Err(EasySwapError::insufficient_funds(format!(
             "Insufficient balance. Available: {}, Requested: {} (including fee: {})",
             current_balance, amount, CKUSDC_FEE
-        ));
+        )));
     }
     
     // If filler account exists, check security requirements for locked chunks
@@ -354,24 +709,33 @@ pub async fn withdraw_security(amount: u64, to_principal: Principal) -> Result<(
         // Check that withdrawal maintains 5% security for pending locked chunks
         // Remaining balance after withdrawal AND fee
         let remaining_balance = current_balance.checked_sub(total_needed)
-            .ok_or_else(|| "Insufficient balance for withdrawal".to_string())?;
+            .ok_or_else(|| EasySwapError::insufficient_funds("Insufficient balance for withdrawal"))?;
         
-        // Convert remaining balance to USD
-        let remaining_balance_usd = remaining_balance as f64 / 1_000_000.0;
-        let max_allowed_pending = remaining_balance_usd * (MAX_LOCK_MULTIPLIER as f64);
+        // Convert remaining balance to USD - collateral still counts toward it, release bonds don't
+        let collateral_e6: UsdE6 = get_collateral_balances(caller).await.iter().map(|c| c.credited_e6).sum();
+        let bonded_e6 = crate::release_bonds::bonded_e6(caller);
+        if remaining_balance + collateral_e6 < bonded_e6 {
+            return Err(EasySwapError::insufficient_funds(format!(
+                "Cannot withdraw: ${:.6} of security is bonded against instantly released trades",
+                e6_to_usd(bonded_e6)
+            )));
+        }
+        let remaining_balance_usd = (remaining_balance + collateral_e6 - bonded_e6) as f64 / 1_000_000.0;
+        let (_, lock_multiplier) = security_terms(caller);
+        let max_allowed_pending = remaining_balance_usd * (lock_multiplier as f64);
         
         if account.pending_trades_total > max_allowed_pending {
-            return Err(format!(
+            return Err(EasySwapError::insufficient_funds(format!(
                 "Cannot withdraw: would violate security requirements. Need at least ${:.6} for pending locked chunks of ${:.6}",
-                account.pending_trades_total / (MAX_LOCK_MULTIPLIER as f64),
+                account.pending_trades_total / (lock_multiplier as f64),
                 account.pending_trades_total
-            ));
+            )));
         }
     }
     // If no filler account, user can freely withdraw (no pending trades)
     
     // Transfer ckUSDC from filler's subaccount to their external account
-    use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
+    use icrc_ledger_types::icrc1::transfer::TransferArg;
     
     let ledger_id = Principal::from_text(CK_USDC_LEDGER_ID)
         .map_err(|e| format!("Invalid ledger canister ID: {:?}", e))?;
@@ -388,14 +752,97 @@ pub async fn withdraw_security(amount: u64, to_principal: Principal) -> Result<(
         created_at_time: Some(get_time()),
     };
     
-    #[allow(deprecated)]
-    let result: Result<(Result<Nat, TransferError>,), _> = 
-        ic_cdk::call(ledger_id, "icrc1_transfer", (transfer_args,)).await;
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_id, transfer_args).await;
     
     match result {
         Ok((Ok(_block_index),)) => Ok(()),
-        Ok((Err(e),)) => Err(format!("Withdrawal transfer failed: {:?}", e)),
-        Err((code, msg)) => Err(format!("Transfer call failed: {:?}: {}", code, msg)),
+        Ok((Err(e),)) => Err(EasySwapError::ledger(format!("Withdrawal transfer failed: {:?}", e))),
+        Err((code, msg)) => Err(EasySwapError::ledger(format!("Transfer call failed: {:?}: {}", code, msg))),
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+
+    fn filler() -> Principal {
+        Principal::from_slice(&[2, 2, 2])
+    }
+
+    #[test]
+    fn removed_collateral_token_is_no_longer_credited_but_stays_withdrawable() {
+        let ckbtc = Principal::from_slice(&[9, 9]);
+        set_collateral_token(CollateralToken {
+            symbol: "ckBTC".to_string(),
+            ledger: ckbtc,
+            decimals: 8,
+            transfer_fee: 10,
+            price_symbol: Some("BTC".to_string()),
+            credit_bps: 8_000,
+            disabled: None,
+        })
+        .unwrap();
+        mock::set_usd_rate("BTC", 50_000.0);
+        let deposit = get_deposit_account(filler());
+        mock::mint_token(ckbtc, deposit, 2_500);
+
+        assert_eq!(block_on(get_collateral_balances(filler()))[0].credited_e6, 1_000_000);
+        remove_collateral_token("ckBTC").unwrap();
+        assert!(remove_collateral_token("ckBTC").is_err(), "already removed");
+        assert!(get_collateral_tokens()[0].is_disabled());
+        let balances = block_on(get_collateral_balances(filler()));
+        assert_eq!((balances[0].usd_value_e6, balances[0].credited_e6), (1_250_000, 0));
+
+        // A withdrawal whose reply is lost is retried under the same created_at_time
+        mock::set_caller(filler());
+        mock::set_ledger_down(true);
+        let first_attempt = get_time();
+        assert!(block_on(withdraw_collateral("ckBTC".to_string(), 1_000, filler())).is_err());
+        mock::set_ledger_down(false);
+        mock::advance_time(1_000_000_000);
+        let dedup_key = [b"Withdraw 1000".as_slice(), b"ckBTC", filler().as_slice(), filler().as_slice()].concat();
+        assert_eq!(crate::transfer_dedup::created_at_time(&dedup_key), first_attempt);
+        block_on(withdraw_collateral("ckBTC".to_string(), 1_000, filler())).expect("withdrawn after removal");
+        assert_eq!(mock::token_balance(ckbtc, deposit), 2_500 - 1_000 - 10);
+        assert_eq!(mock::token_balance(ckbtc, Account { owner: filler(), subaccount: None }), 1_000);
+        // Settled - the next withdrawal of the same amount is a new transfer
+        assert_ne!(crate::transfer_dedup::created_at_time(&dedup_key), first_attempt);
+
+        // Setting it again re-enables it
+        let mut token = get_collateral_tokens()[0].clone();
+        token.disabled = None;
+        set_collateral_token(token).unwrap();
+        assert_eq!(block_on(get_collateral_balances(filler()))[0].credited_e6, bps_of(token_units_to_usd_e6(1_490, 8, 50_000.0), 8_000));
+    }
+
+    #[test]
+    fn only_approved_fillers_trade_while_the_allowlist_is_on() {
+        assert!(ensure_approved_filler(filler()).is_ok(), "anyone trades while it is off");
+
+        set_filler_allowlist_enabled(true);
+        assert!(matches!(ensure_approved_filler(filler()), Err(EasySwapError::Unauthorized { .. })));
+        let request = crate::trade_lifecycle::CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        };
+        assert!(matches!(
+            block_on(crate::trade_lifecycle::create_trades(filler(), request)),
+            Err(EasySwapError::Unauthorized { .. })
+        ));
+
+        insert_approved_filler(filler(), 5);
+        insert_approved_filler(filler(), 9);
+        assert!(ensure_approved_filler(filler()).is_ok());
+        assert_eq!(get_approved_fillers()[0].approved_at, 5, "re-approval keeps the original time");
+
+        assert!(remove_approved_filler(filler()));
+        assert!(!is_approved_filler(filler()));
+        set_filler_allowlist_enabled(false);
+        assert!(is_approved_filler(filler()));
+    }
+}
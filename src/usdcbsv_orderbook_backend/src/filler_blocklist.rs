@@ -0,0 +1,103 @@
+/// Maker filler blocklists - fillers a maker doesn't want to trade with (e.g. after a dispute)
+/// Their trades skip the maker's chunks during FIFO matching in create_trades_from_chunks
+use candid::Principal;
+use crate::types::*;
+use crate::state::*;
+use crate::config::MAX_BLOCKED_FILLERS_PER_MAKER;
+
+/// Replace the caller's blocklist; an empty list clears it
+/// Trades already open with a blocked filler are not affected
+pub fn set_my_filler_blocklist(fillers: Vec<Principal>) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot set a filler blocklist. Please authenticate first."));
+    }
+    
+    let mut blocked_fillers = fillers;
+    blocked_fillers.sort();
+    blocked_fillers.dedup();
+    
+    if blocked_fillers.len() > MAX_BLOCKED_FILLERS_PER_MAKER {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "Blocklist can hold at most {} fillers", MAX_BLOCKED_FILLERS_PER_MAKER
+        )));
+    }
+    
+    if blocked_fillers.contains(&caller) {
+        return Err(EasySwapError::invalid_input("You cannot block yourself"));
+    }
+    
+    if blocked_fillers.is_empty() {
+        remove_maker_filler_blocklist(caller);
+        ic_cdk::println!("🔓 Filler blocklist cleared for maker {}", caller);
+        return Ok(());
+    }
+    
+    ic_cdk::println!("🚫 Maker {} blocked {} fillers", caller, blocked_fillers.len());
+    set_maker_filler_blocklist(MakerFillerBlocklist {
+        maker: caller,
+        blocked_fillers,
+        updated_at: get_time(),
+    });
+    
+    Ok(())
+}
+
+pub fn get_my_filler_blocklist() -> Option<MakerFillerBlocklist> {
+    get_maker_filler_blocklist(get_caller())
+}
+
+/// Whether the maker has blocked this filler from their chunks
+pub fn is_filler_blocked(maker: Principal, filler: Principal) -> bool {
+    get_maker_filler_blocklist(maker)
+        .is_some_and(|blocklist| blocklist.blocked_fillers.binary_search(&filler).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use crate::trade_lifecycle::CreateTradesRequest;
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn filler(n: u8) -> Principal {
+        Principal::from_slice(&[2, 2, n])
+    }
+
+    fn fill(filler: Principal) -> Result<Vec<TradeId>, EasySwapError> {
+        mock::mint(crate::filler_accounts::get_deposit_account(filler), 1_000_000);
+        mock::set_caller(filler);
+        block_on(crate::trade_lifecycle::create_trades(filler, CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        }))
+    }
+
+    #[test]
+    fn blocked_filler_cannot_match_the_makers_chunks() {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        assert!(set_my_filler_blocklist(vec![maker()]).is_err(), "a maker can't block themselves");
+        assert!(set_my_filler_blocklist(vec![filler(9); MAX_BLOCKED_FILLERS_PER_MAKER + 1]).is_ok(), "duplicates count once");
+        set_my_filler_blocklist(vec![filler(1), filler(1)]).unwrap();
+        assert_eq!(get_my_filler_blocklist().unwrap().blocked_fillers, vec![filler(1)]);
+        block_on(crate::order_management::create_order(3.0, 100.0, crate::lifecycle_tests::p2pkh_address(&[0x11; 20]), None)).unwrap();
+
+        assert!(is_filler_blocked(maker(), filler(1)));
+        assert!(fill(filler(1)).is_err());
+        assert!(fill(filler(2)).is_ok());
+
+        mock::set_caller(maker());
+        set_my_filler_blocklist(Vec::new()).unwrap();
+        assert!(get_my_filler_blocklist().is_none());
+        assert!(!is_filler_blocked(maker(), filler(1)));
+    }
+}
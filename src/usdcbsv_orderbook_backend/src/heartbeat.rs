@@ -2,12 +2,47 @@
 use crate::types::*;
 use crate::state::*;
 use crate::chunk_allocation;
+use std::cell::RefCell;
+use std::thread::LocalKey;
+
+thread_local! {
+    // In-flight flags (like chain_sync's SYNC_IN_PROGRESS) - slow ledger calls can push a run past
+    // its timer interval, and a second run would mutate the same trades/chunks concurrently
+    static IDLE_REACTIVATION_IN_PROGRESS: RefCell<bool> = RefCell::new(false);
+    static CLEANUP_TASKS_IN_PROGRESS: RefCell<bool> = RefCell::new(false);
+    static RECLAIM_IN_PROGRESS: RefCell<bool> = RefCell::new(false);
+}
+
+/// Holds an in-flight flag and clears it when dropped - on every exit path, including a trap
+/// after an await (the pending future is dropped during cleanup)
+struct InFlightGuard(&'static LocalKey<RefCell<bool>>);
+
+impl InFlightGuard {
+    /// None if a run is already in progress
+    fn acquire(flag: &'static LocalKey<RefCell<bool>>) -> Option<Self> {
+        flag.with(|f| {
+            let mut f = f.borrow_mut();
+            if *f {
+                None
+            } else {
+                *f = true;
+                Some(InFlightGuard(flag))
+            }
+        })
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.with(|f| *f.borrow_mut() = false);
+    }
+}
 
 /// Process confirmations and withdrawals (called every 60 seconds by timer)
 pub async fn process_confirmations() -> Result<(), String> {
-    let cycles_start = ic_cdk::api::canister_balance128();
+    let cycles_start = crate::runtime::cycles_balance();
     
-    let cycles_end = ic_cdk::api::canister_balance128();
+    let cycles_end = crate::runtime::cycles_balance();
     let cycles_consumed = cycles_start.saturating_sub(cycles_end);
     
     ic_cdk::println!(
@@ -21,7 +56,12 @@ pub async fn process_confirmations() -> Result<(), String> {
 
 /// Cleanup tasks (called every 5 minutes by timer)
 pub async fn process_cleanup_tasks() -> Result<(), String> {
-    let cycles_start = ic_cdk::api::canister_balance128();
+    let Some(_guard) = InFlightGuard::acquire(&CLEANUP_TASKS_IN_PROGRESS) else {
+        ic_cdk::println!("⏭️  process_cleanup_tasks still running - skipping this run");
+        return Ok(());
+    };
+    
+    let cycles_start = crate::runtime::cycles_balance();
     
     // Check and unlock expired trades (with penalty)
     let _ = unlock_expired_trades().await;
@@ -29,7 +69,36 @@ pub async fn process_cleanup_tasks() -> Result<(), String> {
     // Check for expired unclaimed trades (24h after tx submission)
     let _ = reclaim_expired_trades().await;
     
-    let cycles_end = ic_cdk::api::canister_balance128();
+    // Refund and close orders cancelled while trades held some of their chunks
+    let finalized = crate::order_management::finalize_cancelled_orders().await;
+    if finalized > 0 {
+        ic_cdk::println!("🏁 Finalized {} cancelled orders", finalized);
+    }
+    
+    // Clear chunk reservations whose TTL has passed
+    let released = chunk_allocation::release_expired_reservations(get_time());
+    if released > 0 {
+        ic_cdk::println!("🔖 Released {} expired chunk reservations", released);
+    }
+    
+    // Return release bonds whose payment is buried, forfeit those a reorg undid
+    let settled = crate::release_bonds::settle_release_bonds().await;
+    if settled > 0 {
+        ic_cdk::println!("⚡ Settled {} release bonds", settled);
+    }
+    
+    // Split the rebate pool between makers once the epoch is over
+    if let Some(closed) = crate::rebates::close_epoch_if_due() {
+        ic_cdk::println!("🎁 Rebate epoch {} closed: ${:.6} to {} makers", closed.epoch, e6_to_usd(closed.distributed_e6), closed.makers_rebated);
+    }
+    
+    // Retry penalty/refund transfers that failed earlier
+    let retried = crate::pending_transfers::process_pending_transfers().await;
+    if retried > 0 {
+        ic_cdk::println!("🔁 Completed {} pending transfers", retried);
+    }
+    
+    let cycles_end = crate::runtime::cycles_balance();
     let cycles_consumed = cycles_start.saturating_sub(cycles_end);
     
     ic_cdk::println!(
@@ -52,8 +121,8 @@ async fn unlock_expired_trades() -> Result<(), String> {
             // Lock expired and NO BSV transaction submitted - apply penalty
             ic_cdk::println!("⚠️  Trade {} expired without BSV transaction. Applying penalty to filler.", trade.id);
             
-            // Apply penalty (5% of trade amount)
-            let penalty_amount = trade.amount_usd * (crate::config::SECURITY_DEPOSIT_PERCENT as f64 / 100.0);
+            // Penalty grows with how long past expiry the trade was found
+            let penalty_amount = crate::trade_lifecycle::timeout_penalty_usd(&trade, now);
             
             // Get order to find maker (recipient of timeout penalty)
             let order = crate::state::get_order(trade.order_id);
@@ -76,14 +145,21 @@ async fn unlock_expired_trades() -> Result<(), String> {
                             filler: trade.filler,
                             order_maker: Some(order.maker),
                             penalty_amount,
-                            bsv_tx_hex: trade.bsv_tx_hex.clone(),
+                            bsv_txid: trade.bsv_txid.clone(),
                             reason: format!("Trade expired without BSV transaction submission"),
                         });
                     }
                 }
                 Err(e) => {
                     ic_cdk::println!("❌ Failed to deduct penalty: {}", e);
-                    // Continue with unlock even if penalty fails
+                    // Continue with unlock - the transfer is retried from the pending queue
+                    crate::pending_transfers::queue_penalty(
+                        trade.filler,
+                        recipient,
+                        penalty_amount,
+                        format!("Timeout penalty T{}", trade.id),
+                        e,
+                    );
                 }
             }
             
@@ -93,6 +169,7 @@ async fn unlock_expired_trades() -> Result<(), String> {
                 .collect();
             
             chunk_allocation::unlock_chunks(&chunk_ids)?;
+            chunk_allocation::record_timeout_release(&chunk_ids, trade.filler, trade.id)?;
             
             // Update trade status to PenaltyApplied (not just Cancelled)
             update_trade(trade.id, |t| {
@@ -113,26 +190,26 @@ async fn unlock_expired_trades() -> Result<(), String> {
 /// Safety: Only reclaims trades with ID < last successfully claimed trade ID
 /// This avoids penalizing trades that couldn't be claimed due to blockchain/API issues
 async fn reclaim_expired_trades() -> Result<(), String> {
+    let Some(_guard) = InFlightGuard::acquire(&RECLAIM_IN_PROGRESS) else {
+        ic_cdk::println!("⏭️  reclaim_expired_trades still running - skipping this run");
+        return Ok(());
+    };
+    
     let now = get_time();
     
     let submitted_trades = get_trades_by_status(TradeStatus::TxSubmitted);
     
     for trade in submitted_trades {
+        // Disputed trades are on hold - never reclaim while admin review is pending
+        if is_trade_on_hold(trade.id) {
+            continue;
+        }
+        
         // Check if claim has expired (24 hours after submission)
         if let Some(claim_expiry) = trade.claim_expires_at {
             if now > claim_expiry {
                 ic_cdk::println!("⚠️  Trade {} expired without claim after 24 hours. Reclaiming funds to treasury.", trade.id);
                 
-                // Calculate amount to send to treasury (chunk amount + incentive)
-                // Use config value for filler incentive (4.5% = 450 basis points)
-                let incentive_multiplier = 1.0 + (crate::config::FILLER_INCENTIVE_PERCENT as f64 / 10000.0);
-                let total_amount = trade.amount_usd * incentive_multiplier;
-                let total_e6 = crate::ckusdc_integration::usd_to_ckusdc_e6(total_amount);
-                
-                ic_cdk::println!("💰 Reclaiming ${:.6} ({} e6) to treasury from order (transfer will deduct fee)", 
-                    crate::ckusdc_integration::ckusdc_e6_to_usd(total_e6), 
-                    total_e6);
-                
                 // Get order to extract maker for subaccount
                 let order = match crate::state::get_order(trade.order_id) {
                     Some(o) => o,
@@ -142,9 +219,16 @@ async fn reclaim_expired_trades() -> Result<(), String> {
                     }
                 };
                 
+                // Calculate amount to send to treasury (chunk amount + the order's filler incentive)
+                let total_e6 = (trade.amount_usd_e6 + bps_of(trade.amount_usd_e6, order.filler_incentive_bps())) as u128;
+                
+                ic_cdk::println!("💰 Reclaiming ${:.6} ({} e6) to treasury from order (transfer will deduct fee)", 
+                    crate::ckusdc_integration::ckusdc_e6_to_usd(total_e6), 
+                    total_e6);
+                
                 // Apply 5% penalty from filler's security deposit to treasury
                 // This prevents spam/fake transactions that match outputs but cannot be broadcast
-                let penalty_amount = trade.amount_usd * (crate::config::SECURITY_DEPOSIT_PERCENT as f64 / 100.0);
+                let penalty_amount = e6_to_usd(trade.amount_usd_e6 * crate::config::SECURITY_DEPOSIT_PERCENT / 100);
                 ic_cdk::println!("⚠️  Applying {}% penalty: ${:.2} from filler's security deposit", 
                     crate::config::SECURITY_DEPOSIT_PERCENT, penalty_amount);
                 
@@ -164,13 +248,20 @@ async fn reclaim_expired_trades() -> Result<(), String> {
                             filler: trade.filler,
                             order_maker: Some(order.maker),
                             penalty_amount,
-                            bsv_tx_hex: trade.bsv_tx_hex.clone(),
+                            bsv_txid: trade.bsv_txid.clone(),
                             reason: format!("Trade expired without claim after 24 hours - possible spam/fake transaction"),
                         });
                     }
                     Err(e) => {
                         ic_cdk::println!("❌ Failed to deduct penalty: {}", e);
-                        // Continue with transfer even if penalty fails
+                        // Continue with transfer - the penalty is retried from the pending queue
+                        crate::pending_transfers::queue_penalty(
+                            trade.filler,
+                            None,
+                            penalty_amount,
+                            format!("Unclaimed penalty T{}", trade.id),
+                            e,
+                        );
                     }
                 }
                 
@@ -185,6 +276,11 @@ async fn reclaim_expired_trades() -> Result<(), String> {
                 ).await {
                     Ok(block_index) => {
                         ic_cdk::println!("✅ Transferred to treasury at block {}", block_index);
+                        crate::treasury::record_order_inflow(
+                            trade.order_id,
+                            crate::types::TreasuryInflowKind::ExpiredTradeReclaim,
+                            total_e6.saturating_sub(crate::config::CKUSDC_TRANSFER_FEE) as u64,
+                        );
                         
                         // Calculate actual amount sent (before fee deduction)
                         let amount_sent_usd = crate::ckusdc_integration::ckusdc_e6_to_usd(total_e6);
@@ -229,12 +325,19 @@ async fn reclaim_expired_trades() -> Result<(), String> {
 }
 
 /// Check if any Idle chunks should become Available (price dropped below max)
+/// Also applies maker quiet hours: chunks of makers in a quiet window go Idle and
+/// are only reactivated once the window ends
 /// Called every 60 seconds by dedicated timer for faster reactivation
 pub async fn reactivate_idle_chunks() -> Result<(), String> {
-    let cycles_start = ic_cdk::api::canister_balance128();
+    let Some(_guard) = InFlightGuard::acquire(&IDLE_REACTIVATION_IN_PROGRESS) else {
+        ic_cdk::println!("⏭️  reactivate_idle_chunks still running - skipping this run");
+        return Ok(());
+    };
+    
+    let cycles_start = crate::runtime::cycles_balance();
     
     use crate::price_oracle;
-    use crate::config::MAX_ORDERBOOK_USD_LIMIT;
+    use crate::config::MAX_ORDERBOOK_USD_LIMIT_E6;
     
     // Get current BSV price
     let current_price = match price_oracle::get_bsv_price().await {
@@ -242,23 +345,59 @@ pub async fn reactivate_idle_chunks() -> Result<(), String> {
         Err(_) => return Ok(()),  // Skip on price fetch error
     };
     
+    // Orders whose maker would rather be refunded than sit Idle above this price
+    let auto_cancelled = crate::order_management::auto_cancel_orders_above_price(current_price).await;
+    if auto_cancelled > 0 {
+        ic_cdk::println!("🛑 Auto-cancelled {} orders at BSV ${:.2}", auto_cancelled, current_price);
+    }
+    
     // Get current orderbook available balance
-    let current_orderbook_usd = get_available_orderbook();
+    let current_orderbook_e6 = get_available_orderbook();
     
     // Get all active orders - optimized to filter at storage level
     let orders = crate::state::get_active_orders_fifo();
     
+    let now = get_time();
+    
+    // Circuit breaker tripped - keep idle chunks delisted until the price settles
+    let trading_halted = crate::circuit_breaker::is_trading_halted(now);
+    
     for order in orders {
+        // Post-only chunks have waited out a cycle - list them against the current price
+        if order.post_only == Some(true) {
+            crate::order_management::activate_pending_chunks(order.id, trading_halted || current_price > order.max_bsv_price)?;
+        }
+        
+        // Maker is in a quiet window - delist Available chunks instead of reactivating
+        if crate::quiet_hours::is_maker_quiet(order.maker, now) {
+            for chunk_id in &order.chunks {
+                if let Some(chunk) = get_chunk(*chunk_id) {
+                    if chunk.status == ChunkStatus::Available {
+                        update_chunk(chunk.id, |c| {
+                            c.status = ChunkStatus::Idle;
+                        })?;
+                        
+                        update_order(order.id, |o| {
+                            o.total_idle_usd_e6 += chunk.amount_usd_e6;
+                        })?;
+                        
+                        ic_cdk::println!("🔕 Chunk {} idled for maker quiet hours (${:.2})", chunk_id, e6_to_usd(chunk.amount_usd_e6));
+                    }
+                }
+            }
+            continue;
+        }
+        
         // Check if price dropped back below max
-        if current_price < order.max_bsv_price {
+        if !trading_halted && current_price < order.max_bsv_price {
             // Find Idle chunks in this order
             for chunk_id in &order.chunks {
                 if let Some(chunk) = get_chunk(*chunk_id) {
                     if chunk.status == ChunkStatus::Idle {
                         // Check if adding this chunk would exceed orderbook limit
-                        if current_orderbook_usd + chunk.amount_usd > MAX_ORDERBOOK_USD_LIMIT {
+                        if current_orderbook_e6 + chunk.amount_usd_e6 > MAX_ORDERBOOK_USD_LIMIT_E6 {
                             ic_cdk::println!("⚠️  Orderbook limit reached (${:.2}/${:.2}). Chunk {} stays Idle.", 
-                                current_orderbook_usd, MAX_ORDERBOOK_USD_LIMIT, chunk_id);
+                                e6_to_usd(current_orderbook_e6), e6_to_usd(MAX_ORDERBOOK_USD_LIMIT_E6), chunk_id);
                             continue; // Skip this chunk, keep it Idle
                         }
                         
@@ -269,17 +408,17 @@ pub async fn reactivate_idle_chunks() -> Result<(), String> {
                         
                         // Update order tracking
                         update_order(order.id, |o| {
-                            o.total_idle_usd -= chunk.amount_usd;
+                            o.total_idle_usd_e6 = o.total_idle_usd_e6.saturating_sub(chunk.amount_usd_e6);
                         })?;
                         
-                        ic_cdk::println!("✅ Chunk {} reactivated (${:.2})", chunk_id, chunk.amount_usd);
+                        ic_cdk::println!("✅ Chunk {} reactivated (${:.2})", chunk_id, e6_to_usd(chunk.amount_usd_e6));
                     }
                 }
             }
         }
     }
     
-    let cycles_end = ic_cdk::api::canister_balance128();
+    let cycles_end = crate::runtime::cycles_balance();
     let cycles_consumed = cycles_start.saturating_sub(cycles_end);
     
     // Log to admin events for tracking
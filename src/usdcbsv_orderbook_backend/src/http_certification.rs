@@ -0,0 +1,280 @@
+/// Certified HTTP endpoints - http_request serves JSON the IC HTTP gateway verifies against this
+/// canister's certified data, so frontends get cacheable responses without update-call latency
+/// Queries can't set certified data, so bodies are rebuilt from the scheduler tick whenever the
+/// book, chain tip, latest trade or cached price moved, and served as-is until the next rebuild
+/// Uses v1 response verification: the IC-Certificate header carries the certificate plus a
+/// witness for http_assets/<path> -> sha256(body)
+use crate::types::*;
+use crate::state::*;
+use crate::config::HTTP_CACHE_MAX_AGE_SECONDS;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+
+const ORDERBOOK_PATH: &str = "/orderbook";
+const STATS_PATH: &str = "/stats";
+
+#[derive(Serialize)]
+struct LastTrade {
+    id: TradeId,
+    order_id: OrderId,
+    amount_usd_e6: UsdE6,
+    agreed_bsv_price: f64,
+    status: TradeStatus,
+    created_at: u64,
+}
+
+/// Body of /stats
+#[derive(Serialize)]
+struct CertifiedStats {
+    orderbook_seq: u64,
+    total_active_chunks: u64,
+    total_available_usd: f64,
+    total_locked_usd: f64,
+    current_bsv_price: f64,
+    tip_height: u64,
+    last_trade: Option<LastTrade>,
+    certified_at: u64,
+}
+
+/// (orderbook seq, tip height, latest trade and its status, price update time) - bodies are
+/// only rebuilt when this changes
+type Fingerprint = (u64, u64, Option<(TradeId, TradeStatus)>, u64);
+
+struct CertifiedAssets {
+    fingerprint: Fingerprint,
+    assets: Vec<(&'static str, Vec<u8>)>,  // Sorted by path
+}
+
+thread_local! {
+    // Heap only - rebuilt by refresh() from init/post_upgrade
+    static CERTIFIED: RefCell<Option<CertifiedAssets>> = const { RefCell::new(None) };
+}
+
+// ===== HASH TREE =====
+
+enum HashTree {
+    Fork(Box<HashTree>, Box<HashTree>),
+    Labeled(Vec<u8>, Box<HashTree>),
+    Leaf(Vec<u8>),
+    Pruned([u8; 32]),
+}
+
+fn domain_hash(separator: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([separator.len() as u8]);
+    hasher.update(separator.as_bytes());
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        out.push(major | 24);
+        out.push(value as u8);
+    } else if value <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn cbor_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    cbor_head(out, 2, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+impl HashTree {
+    fn digest(&self) -> [u8; 32] {
+        match self {
+            HashTree::Fork(left, right) => domain_hash("ic-hashtree-fork", &[&left.digest(), &right.digest()]),
+            HashTree::Labeled(label, subtree) => domain_hash("ic-hashtree-labeled", &[label, &subtree.digest()]),
+            HashTree::Leaf(value) => domain_hash("ic-hashtree-leaf", &[value]),
+            HashTree::Pruned(digest) => *digest,
+        }
+    }
+
+    /// Interface spec encoding: [1, l, r] / [2, label, t] / [3, value] / [4, digest]
+    fn write_cbor(&self, out: &mut Vec<u8>) {
+        match self {
+            HashTree::Fork(left, right) => {
+                cbor_head(out, 4, 3);
+                cbor_head(out, 0, 1);
+                left.write_cbor(out);
+                right.write_cbor(out);
+            }
+            HashTree::Labeled(label, subtree) => {
+                cbor_head(out, 4, 3);
+                cbor_head(out, 0, 2);
+                cbor_bytes(out, label);
+                subtree.write_cbor(out);
+            }
+            HashTree::Leaf(value) => {
+                cbor_head(out, 4, 2);
+                cbor_head(out, 0, 3);
+                cbor_bytes(out, value);
+            }
+            HashTree::Pruned(digest) => {
+                cbor_head(out, 4, 2);
+                cbor_head(out, 0, 4);
+                cbor_bytes(out, digest);
+            }
+        }
+    }
+
+    /// Self-describing CBOR (tag 55799), as the gateway expects in the tree= field
+    fn to_cbor(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        cbor_head(&mut out, 6, 55799);
+        self.write_cbor(&mut out);
+        out
+    }
+}
+
+/// http_assets tree over every asset - with witness_for set, all other paths are pruned
+fn assets_tree(assets: &[(&'static str, Vec<u8>)], witness_for: Option<&str>) -> HashTree {
+    let nodes = assets.iter().map(|(path, body)| {
+        let body_hash: [u8; 32] = Sha256::digest(body).into();
+        let node = HashTree::Labeled(path.as_bytes().to_vec(), Box::new(HashTree::Leaf(body_hash.to_vec())));
+        match witness_for {
+            Some(witness_path) if witness_path != *path => HashTree::Pruned(node.digest()),
+            _ => node,
+        }
+    });
+    let assets_subtree = nodes
+        .rev()
+        .reduce(|right, left| HashTree::Fork(Box::new(left), Box::new(right)))
+        .expect("at least one certified asset");
+    HashTree::Labeled(b"http_assets".to_vec(), Box::new(assets_subtree))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = (group[0] as u32) << 16
+            | (*group.get(1).unwrap_or(&0) as u32) << 8
+            | *group.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// ===== CERTIFIED BODIES =====
+
+fn last_trade() -> Option<Trade> {
+    TRADES.with(|trades| trades.borrow().last_key_value().map(|(_, trade)| trade))
+}
+
+fn fingerprint() -> Fingerprint {
+    (
+        crate::orderbook_feed::current_seq(),
+        crate::block_headers::get_highest_block(),
+        last_trade().map(|trade| (trade.id, trade.status)),
+        get_cached_bsv_price().1,
+    )
+}
+
+fn build_assets() -> Vec<(&'static str, Vec<u8>)> {
+    let snapshot = crate::orderbook_feed::get_orderbook_snapshot();
+    let stats = CertifiedStats {
+        orderbook_seq: snapshot.seq,
+        total_active_chunks: snapshot.stats.total_active_chunks,
+        total_available_usd: snapshot.stats.total_available_usd,
+        total_locked_usd: snapshot.stats.total_locked_usd,
+        current_bsv_price: snapshot.stats.current_bsv_price,
+        tip_height: crate::block_headers::get_highest_block(),
+        last_trade: last_trade().map(|trade| LastTrade {
+            id: trade.id,
+            order_id: trade.order_id,
+            amount_usd_e6: trade.amount_usd_e6,
+            agreed_bsv_price: trade.agreed_bsv_price,
+            status: trade.status,
+            created_at: trade.created_at,
+        }),
+        certified_at: snapshot.generated_at,
+    };
+
+    vec![
+        (ORDERBOOK_PATH, serde_json::to_vec(&snapshot).expect("orderbook snapshot serializes")),
+        (STATS_PATH, serde_json::to_vec(&stats).expect("stats serialize")),
+    ]
+}
+
+/// Rebuild and re-certify the bodies if anything they show has changed
+/// Must run in an update context (init, post_upgrade, timers)
+pub fn refresh() {
+    let fingerprint = fingerprint();
+    let unchanged = CERTIFIED.with(|certified| {
+        certified.borrow().as_ref().is_some_and(|c| c.fingerprint == fingerprint)
+    });
+    if unchanged {
+        return;
+    }
+
+    let assets = build_assets();
+    ic_cdk::api::certified_data_set(assets_tree(&assets, None).digest());
+    CERTIFIED.with(|certified| {
+        *certified.borrow_mut() = Some(CertifiedAssets { fingerprint, assets });
+    });
+}
+
+fn plain_response(status_code: u16, message: &str) -> HttpGatewayResponse {
+    HttpGatewayResponse {
+        status_code,
+        headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+        body: message.as_bytes().to_vec(),
+    }
+}
+
+/// Serve a certified asset - GET /orderbook (snapshot, see get_orderbook_snapshot) or GET /stats
+pub fn http_request(request: HttpGatewayRequest) -> HttpGatewayResponse {
+    if request.method != "GET" {
+        return plain_response(405, "Method not allowed");
+    }
+    let path = request.url.split('?').next().unwrap_or_default();
+
+    CERTIFIED.with(|certified| {
+        let certified = certified.borrow();
+        let Some(certified) = certified.as_ref() else {
+            return plain_response(503, "Certified data not initialized yet");
+        };
+        let Some((_, body)) = certified.assets.iter().find(|(asset_path, _)| *asset_path == path) else {
+            return plain_response(404, "Not found");
+        };
+
+        let mut headers = vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Cache-Control".to_string(), format!("public, max-age={}", HTTP_CACHE_MAX_AGE_SECONDS)),
+        ];
+        if let Some(certificate) = ic_cdk::api::data_certificate() {
+            let witness = assets_tree(&certified.assets, Some(path)).to_cbor();
+            headers.push((
+                "IC-Certificate".to_string(),
+                format!("certificate=:{}:, tree=:{}:", base64(&certificate), base64(&witness)),
+            ));
+        }
+
+        HttpGatewayResponse {
+            status_code: 200,
+            headers,
+            body: body.clone(),
+        }
+    })
+}
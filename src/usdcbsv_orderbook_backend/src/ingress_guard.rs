@@ -0,0 +1,135 @@
+/// canister_inspect_message - ingress update calls are screened here before the subnet accepts
+/// them, so anonymous spam, oversized payloads and absurd page sizes never pay for execution
+/// Calls from other canisters skip inspection, so every endpoint still checks its own inputs
+use candid::de::IDLDeserialize;
+use candid::{Principal, Reserved};
+use crate::config::{MAX_BSV_TX_BYTES, MAX_BUMP_BYTES, MAX_CLAIMS_PER_BATCH, MAX_INGRESS_ARG_BYTES, MAX_PAGE_LIMIT};
+
+/// Updates anyone may call without signing in - the public price refreshes
+const ANONYMOUS_UPDATE_METHODS: [&str; 3] = ["get_bsv_price", "v2_get_bsv_price", "get_eth_usd_price"];
+
+/// Read endpoints, which anonymous callers may also send as update calls
+const READ_PREFIXES: [&str; 9] = ["get_", "v2_get_", "list_", "export_", "preview_", "is_", "are_", "am_", "http_request"];
+
+/// Paginated reads, by the position of their nat64 limit argument
+const PAGINATED_METHODS: [(&str, usize); 13] = [
+    ("get_my_orders_paginated", 1),
+    ("get_my_orders_page", 1),
+    ("get_my_active_orders_paginated", 1),
+    ("get_my_orders_by_status_paginated", 2),
+    ("get_my_deposit_recoveries", 1),
+    ("get_my_trades_paginated", 1),
+    ("get_my_trades_page", 1),
+    ("get_active_chunks_paginated", 1),
+    ("get_active_chunks_page", 1),
+    ("get_recent_trades_public", 0),
+    ("get_admin_events_paginated", 1),
+    ("get_admin_events_page", 1),
+    ("admin_get_corrupt_records", 1),
+];
+
+// Candid header, trade IDs and the other small arguments around a hex payload
+const ARG_OVERHEAD_BYTES: usize = 1024;
+
+fn anonymous_may_call(method: &str) -> bool {
+    ANONYMOUS_UPDATE_METHODS.contains(&method) || READ_PREFIXES.iter().any(|prefix| method.starts_with(prefix))
+}
+
+#[cfg(all(feature = "local-dev", not(test)))]
+fn is_dev_seeder(method: &str, caller: Principal) -> bool {
+    method.starts_with("admin_dev_") && ic_cdk::api::is_controller(&caller)
+}
+
+#[cfg(not(all(feature = "local-dev", not(test))))]
+fn is_dev_seeder(_method: &str, _caller: Principal) -> bool {
+    false
+}
+
+/// Largest argument the method takes - None for admin calls, which may restore backups
+fn max_arg_bytes(method: &str) -> Option<usize> {
+    let claim_bytes = (MAX_BSV_TX_BYTES + MAX_BUMP_BYTES) * 2;
+    match method {
+        _ if method.starts_with("admin_") => None,
+        "submit_bsv_transaction" | "submit_bsv_transaction_for_trades" | "resubmit_bsv_transaction" => {
+            Some(MAX_BSV_TX_BYTES * 2 + ARG_OVERHEAD_BYTES)
+        }
+        "claim_usdc" | "late_claim_review" => Some(claim_bytes + ARG_OVERHEAD_BYTES),
+        "claim_usdc_batch" => Some(MAX_CLAIMS_PER_BATCH * claim_bytes + ARG_OVERHEAD_BYTES),
+        "reprove_release_bond" => Some(MAX_BUMP_BYTES * 2 + ARG_OVERHEAD_BYTES),
+        _ => Some(MAX_INGRESS_ARG_BYTES),
+    }
+}
+
+/// The nat64 at `position` of a candid argument list - None if it doesn't decode as one
+fn nat64_arg(arg: &[u8], position: usize) -> Option<u64> {
+    let mut de = IDLDeserialize::new(arg).ok()?;
+    for _ in 0..position {
+        de.get_value::<Reserved>().ok()?;
+    }
+    de.get_value::<u64>().ok()
+}
+
+/// Whether an ingress call may go ahead - Err names why not. `arg` is only read for paginated
+/// methods, after the size check
+pub fn check(method: &str, caller: Principal, arg_size: usize, arg: impl FnOnce() -> Vec<u8>) -> Result<(), String> {
+    if caller == Principal::anonymous() && !anonymous_may_call(method) {
+        return Err(format!("{} requires an authenticated caller", method));
+    }
+    if method.starts_with("admin_") && caller != crate::state::get_admin() && !is_dev_seeder(method, caller) {
+        return Err(format!("{} is admin-only", method));
+    }
+    if let Some(max_bytes) = max_arg_bytes(method) {
+        if arg_size > max_bytes {
+            return Err(format!("{} arguments too large ({} bytes, max {})", method, arg_size, max_bytes));
+        }
+    }
+    if let Some((_, position)) = PAGINATED_METHODS.iter().find(|(name, _)| *name == method) {
+        match nat64_arg(&arg(), *position) {
+            Some(limit) if limit > MAX_PAGE_LIMIT => {
+                return Err(format!("{} limit {} exceeds the maximum of {}", method, limit, MAX_PAGE_LIMIT));
+            }
+            Some(_) => {}
+            None => return Err(format!("{} arguments don't decode", method)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn filler() -> Principal {
+        Principal::from_slice(&[2, 2, 2])
+    }
+
+
+    #[test]
+    fn inspect_message_turns_away_anonymous_and_malformed_calls() {
+        let anonymous = Principal::anonymous();
+        let no_arg = || -> Vec<u8> { unreachable!("argument read for a method that isn't paginated") };
+
+        assert!(check("create_order", anonymous, 64, no_arg).is_err());
+        assert!(check("get_bsv_price", anonymous, 8, no_arg).is_ok());
+        assert!(check("create_order", maker(), 64, no_arg).is_ok());
+        assert!(check("admin_get_disputes", maker(), 8, no_arg).is_err());
+        assert!(check("admin_get_disputes", crate::state::get_admin(), 8, no_arg).is_ok());
+
+        // Hex of a tx well past MAX_BSV_TX_BYTES - the endpoint itself rejects anything just over
+        let oversized = candid::encode_args((1u64, "00".repeat(MAX_BSV_TX_BYTES * 2))).unwrap();
+        assert!(check("submit_bsv_transaction", filler(), oversized.len(), no_arg).is_err());
+        let full_size = candid::encode_args((1u64, "00".repeat(MAX_BSV_TX_BYTES))).unwrap();
+        assert!(check("submit_bsv_transaction", filler(), full_size.len(), no_arg).is_ok());
+
+        let page = |limit: u64| candid::encode_args((0u64, limit, None::<Vec<crate::types::TradeStatus>>)).unwrap();
+        let absurd = page(10_000_000);
+        assert!(check("get_my_trades_paginated", filler(), absurd.len(), || absurd.clone()).is_err());
+        let sane = page(50);
+        assert!(check("get_my_trades_paginated", filler(), sane.len(), || sane.clone()).is_ok());
+        assert!(check("get_my_trades_paginated", filler(), 3, || b"DID".to_vec()).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Random sequences of order, trade, price and timeout steps against the runtime::mock services,
+//! with invariants::check_invariants asserted after every step
+use crate::config::TRADE_TIMEOUT_NS;
+use crate::invariants::check_invariants;
+use crate::lifecycle_tests::p2pkh_address;
+use crate::runtime::mock::{self, block_on};
+use crate::state::*;
+use crate::trade_lifecycle::CreateTradesRequest;
+use crate::types::*;
+use candid::Principal;
+use proptest::prelude::*;
+
+const MAKERS: u8 = 3;
+const FILLERS: u8 = 2;
+const PRICE_REFRESH_NS: u64 = 6 * 60 * 1_000_000_000;
+
+#[derive(Clone, Debug)]
+enum Step {
+    CreateOrder { maker: u8, chunks: u64, max_bsv_price: f64 },
+    CreateTrade { filler: u8, chunks: u64 },
+    CancelOrder { maker: u8 },
+    HideOrder { maker: u8, hidden: bool },
+    SetPrice(f64),
+    SetMatchingPolicy(MatchingPolicy),
+    ExpireLocks,
+}
+
+fn maker(index: u8) -> Principal {
+    Principal::from_slice(&[1, index])
+}
+
+fn filler(index: u8) -> Principal {
+    Principal::from_slice(&[2, index])
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        3 => (0..MAKERS, 1..=4u64, prop::sample::select(vec![48.0, 100.0]))
+            .prop_map(|(maker, chunks, max_bsv_price)| Step::CreateOrder { maker, chunks, max_bsv_price }),
+        3 => (0..FILLERS, 1..=3u64).prop_map(|(filler, chunks)| Step::CreateTrade { filler, chunks }),
+        1 => (0..MAKERS).prop_map(|maker| Step::CancelOrder { maker }),
+        1 => (0..MAKERS, any::<bool>()).prop_map(|(maker, hidden)| Step::HideOrder { maker, hidden }),
+        1 => prop::sample::select(vec![45.0, 50.0]).prop_map(Step::SetPrice),
+        1 => prop::sample::select(vec![MatchingPolicy::Fifo, MatchingPolicy::ProRata, MatchingPolicy::BestIncentive])
+            .prop_map(Step::SetMatchingPolicy),
+        1 => Just(Step::ExpireLocks),
+    ]
+}
+
+fn set_price(price: f64) {
+    mock::set_prices(&[price, price, price]);
+    // Past the price cache, so the next read picks up the new feeds
+    mock::advance_time(PRICE_REFRESH_NS);
+}
+
+fn oldest_open_order(index: u8) -> Option<OrderId> {
+    get_orders_by_maker(maker(index)).into_iter()
+        .filter(|order| matches!(order.status, OrderStatus::Active | OrderStatus::Idle))
+        .map(|order| order.id)
+        .min()
+}
+
+fn apply(step: &Step) {
+    // Outcomes are ignored - rejected steps must leave state as consistent as accepted ones
+    match step {
+        Step::CreateOrder { maker: index, chunks, max_bsv_price } => {
+            mock::set_caller(maker(*index));
+            let amount_usd = *chunks as f64 * e6_to_usd(crate::config::MIN_CHUNK_SIZE_E6);
+            let address = p2pkh_address(&[0x10 + index; 20]);
+            let _ = block_on(crate::order_management::create_order(amount_usd, *max_bsv_price, address, None));
+        }
+        Step::CreateTrade { filler: index, chunks } => {
+            mock::set_caller(filler(*index));
+            let _ = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+                requested_usd: *chunks as f64 * e6_to_usd(crate::config::MIN_CHUNK_SIZE_E6),
+                allow_partial: true,
+                min_bsv_price: 1.0,
+                prefer_higher_incentive: None,
+                book_id: None,
+            }));
+        }
+        Step::CancelOrder { maker: index } => {
+            if let Some(order_id) = oldest_open_order(*index) {
+                mock::set_caller(maker(*index));
+                let _ = block_on(crate::order_management::cancel_order(order_id));
+            }
+        }
+        Step::HideOrder { maker: index, hidden } => {
+            if let Some(order_id) = oldest_open_order(*index) {
+                mock::set_caller(maker(*index));
+                let _ = crate::order_management::set_order_hidden(order_id, *hidden);
+            }
+        }
+        Step::SetPrice(price) => {
+            set_price(*price);
+            let _ = block_on(crate::order_management::check_and_mark_idle_orders());
+            let _ = block_on(crate::heartbeat::reactivate_idle_chunks());
+        }
+        Step::SetMatchingPolicy(policy) => set_matching_policy(*policy),
+        Step::ExpireLocks => {
+            mock::advance_time(TRADE_TIMEOUT_NS + 1);
+            let _ = block_on(crate::heartbeat::process_cleanup_tasks());
+        }
+    }
+}
+
+/// Run on a new thread, so stable maps and the mock environment start empty
+fn isolated(test: impl FnOnce() + Send + 'static) {
+    if let Err(panic) = std::thread::spawn(move || {
+        set_price(50.0);
+        for index in 0..MAKERS {
+            mock::mint(crate::filler_accounts::get_deposit_account(maker(index)), 100_000_000);
+        }
+        for index in 0..FILLERS {
+            mock::mint(crate::filler_accounts::get_deposit_account(filler(index)), 10_000_000);
+        }
+        test();
+    })
+    .join()
+    {
+        std::panic::resume_unwind(panic);
+    }
+}
+
+fn run_steps(steps: &[Step]) {
+    for (i, step) in steps.iter().enumerate() {
+        apply(step);
+        let violations = check_invariants();
+        assert!(violations.is_empty(), "after step {} {:?}: {:#?}", i, step, violations);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn chunk_accounting_holds_across_random_steps(steps in prop::collection::vec(step(), 1..30)) {
+        isolated(move || run_steps(&steps));
+    }
+}
+
+#[test]
+fn timed_out_trade_on_cancelled_order_keeps_invariants() {
+    isolated(|| {
+        run_steps(&[
+            Step::CreateOrder { maker: 0, chunks: 3, max_bsv_price: 100.0 },
+            Step::CreateTrade { filler: 0, chunks: 2 },
+            Step::CancelOrder { maker: 0 },
+        ]);
+        let trade = get_trades_by_status(TradeStatus::ChunksLocked).pop().expect("trade locked two chunks");
+        assert_eq!(trade.amount_usd_e6, 2 * crate::config::MIN_CHUNK_SIZE_E6);
+
+        run_steps(&[Step::ExpireLocks]);
+        assert_eq!(get_trade(trade.id).unwrap().status, TradeStatus::PenaltyApplied);
+    });
+}
+
+#[test]
+fn detects_chunk_locked_without_trade() {
+    isolated(|| {
+        apply(&Step::CreateOrder { maker: 0, chunks: 2, max_bsv_price: 100.0 });
+        assert!(check_invariants().is_empty());
+
+        let chunk_id = get_available_chunks()[0].id;
+        update_chunk(chunk_id, |c| c.status = ChunkStatus::Locked).unwrap();
+
+        let violations = check_invariants();
+        assert!(violations.iter().any(|v| v.contains("Locked without locked_by")), "{:#?}", violations);
+        assert!(violations.iter().any(|v| v.contains("total_locked_usd_e6")), "{:#?}", violations);
+    });
+}
@@ -0,0 +1,159 @@
+/// Global chunk accounting invariants - every message returned names one property that no longer
+/// holds, so an empty result means orders, chunks, trades and the orderbook agree
+/// Exposed as debug_check_invariants in debug builds and asserted after every step of the
+/// property tests in invariant_tests
+use crate::types::*;
+use crate::state::*;
+use std::collections::BTreeMap;
+
+fn is_open_trade(trade: &Trade) -> bool {
+    matches!(trade.status, TradeStatus::ChunksLocked | TradeStatus::TxSubmitted | TradeStatus::ReadyForRelease)
+}
+
+pub fn check_invariants() -> Vec<String> {
+    let chunks: BTreeMap<ChunkId, Chunk> = CHUNKS.with(|chunks| chunks.borrow().iter().collect());
+    let trades: BTreeMap<TradeId, Trade> = TRADES.with(|trades| trades.borrow().iter().collect());
+    let orders: Vec<Order> = ORDERS.with(|orders| orders.borrow().iter().map(|(_, order)| order).collect());
+
+    let mut violations = Vec::new();
+    check_orders(&orders, &chunks, &mut violations);
+    check_locks(&chunks, &trades, &mut violations);
+    check_orderbook(&chunks, &mut violations);
+    check_tx_blobs(&trades, &mut violations);
+    violations
+}
+
+/// Chunks of a funded order add up to its amount, and its locked/filled totals match
+/// the statuses of those chunks
+fn check_orders(orders: &[Order], chunks: &BTreeMap<ChunkId, Chunk>, violations: &mut Vec<String>) {
+    for order in orders {
+        // Orders that never activated have no chunks
+        if order.chunks.is_empty() {
+            continue;
+        }
+
+        let mut total_e6: UsdE6 = 0;
+        let mut locked_e6: UsdE6 = 0;
+        let mut filled_e6: UsdE6 = 0;
+        for chunk_id in &order.chunks {
+            let Some(chunk) = chunks.get(chunk_id) else {
+                violations.push(format!("Order {} lists missing chunk {}", order.id, chunk_id));
+                continue;
+            };
+            if chunk.order_id != order.id {
+                violations.push(format!("Order {} lists chunk {} of order {}", order.id, chunk_id, chunk.order_id));
+            }
+            total_e6 += chunk.amount_usd_e6;
+            match chunk.status {
+                ChunkStatus::Locked => locked_e6 += chunk.amount_usd_e6,
+                ChunkStatus::Filled => filled_e6 += chunk.amount_usd_e6,
+                _ => {}
+            }
+        }
+
+        if total_e6 != order.amount_usd_e6 {
+            violations.push(format!("Order {} chunks sum to {} e6, order amount is {} e6", order.id, total_e6, order.amount_usd_e6));
+        }
+        if locked_e6 != order.total_locked_usd_e6 {
+            violations.push(format!("Order {} has {} e6 in Locked chunks, total_locked_usd_e6 is {}", order.id, locked_e6, order.total_locked_usd_e6));
+        }
+        if filled_e6 != order.total_filled_usd_e6 {
+            violations.push(format!("Order {} has {} e6 in Filled chunks, total_filled_usd_e6 is {}", order.id, filled_e6, order.total_filled_usd_e6));
+        }
+    }
+}
+
+/// A chunk is Locked exactly when an open trade holds it
+fn check_locks(chunks: &BTreeMap<ChunkId, Chunk>, trades: &BTreeMap<TradeId, Trade>, violations: &mut Vec<String>) {
+    for chunk in chunks.values().filter(|chunk| chunk.status == ChunkStatus::Locked) {
+        let Some(trade_id) = chunk.locked_by else {
+            violations.push(format!("Chunk {} is Locked without locked_by", chunk.id));
+            continue;
+        };
+        match trades.get(&trade_id) {
+            None => violations.push(format!("Chunk {} is locked by missing trade {}", chunk.id, trade_id)),
+            Some(trade) if !is_open_trade(trade) => {
+                violations.push(format!("Chunk {} is locked by trade {} in status {:?}", chunk.id, trade_id, trade.status));
+            }
+            Some(trade) if !trade.locked_chunks.iter().any(|locked| locked.chunk_id == chunk.id) => {
+                violations.push(format!("Chunk {} is locked by trade {} which doesn't list it", chunk.id, trade_id));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for trade in trades.values().filter(|trade| is_open_trade(trade)) {
+        let locked_sum: UsdE6 = trade.locked_chunks.iter().map(|locked| locked.amount_usd_e6).sum();
+        if locked_sum != trade.amount_usd_e6 {
+            violations.push(format!("Trade {} locks {} e6 of chunks for a {} e6 trade", trade.id, locked_sum, trade.amount_usd_e6));
+        }
+
+        for locked in &trade.locked_chunks {
+            match chunks.get(&locked.chunk_id) {
+                None => violations.push(format!("Open trade {} holds missing chunk {}", trade.id, locked.chunk_id)),
+                Some(chunk) if chunk.status != ChunkStatus::Locked || chunk.locked_by != Some(trade.id) => {
+                    violations.push(format!(
+                        "Open trade {} holds chunk {} which is {:?} (locked_by {:?})",
+                        trade.id, chunk.id, chunk.status, chunk.locked_by
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+/// Each stored transaction is referenced by exactly ref_count trades, and every trade's txid has one
+fn check_tx_blobs(trades: &BTreeMap<TradeId, Trade>, violations: &mut Vec<String>) {
+    let mut references: BTreeMap<&str, u32> = BTreeMap::new();
+    for txid in trades.values().filter_map(|trade| trade.bsv_txid.as_deref()) {
+        *references.entry(txid).or_default() += 1;
+    }
+
+    let blobs: BTreeMap<String, u32> = TX_BLOBS.with(|blobs| {
+        blobs.borrow().iter().map(|(txid, blob)| (txid.0, blob.ref_count)).collect()
+    });
+    for (txid, ref_count) in &blobs {
+        let referenced = references.get(txid.as_str()).copied().unwrap_or(0);
+        if referenced != *ref_count {
+            violations.push(format!("Tx blob {} has ref_count {}, {} trades reference it", txid, ref_count, referenced));
+        }
+    }
+    for txid in references.keys().filter(|txid| !blobs.contains_key(**txid)) {
+        violations.push(format!("Trade txid {} has no stored tx blob", txid));
+    }
+}
+
+/// The orderbook total covers every Available chunk, and every public view reports the listed ones
+/// (Available chunks their maker hasn't hidden)
+fn check_orderbook(chunks: &BTreeMap<ChunkId, Chunk>, violations: &mut Vec<String>) {
+    let available_e6: UsdE6 = chunks.values()
+        .filter(|chunk| chunk.status == ChunkStatus::Available)
+        .map(|chunk| chunk.amount_usd_e6)
+        .sum();
+
+    let orderbook_e6 = get_available_orderbook();
+    if orderbook_e6 != available_e6 {
+        violations.push(format!("Orderbook total is {} e6, Available chunks sum to {} e6", orderbook_e6, available_e6));
+    }
+
+    let listed: Vec<&Chunk> = chunks.values().filter(|chunk| chunk.is_listed()).collect();
+    let listed_e6: UsdE6 = listed.iter().map(|chunk| chunk.amount_usd_e6).sum();
+
+    let stats = crate::chunk_allocation::get_orderbook_stats_v2();
+    if stats.total_available_usd_e6 != listed_e6 || stats.total_active_chunks != listed.len() as u64 {
+        violations.push(format!(
+            "Orderbook stats show {} chunks / {} e6, expected {} / {} e6",
+            stats.total_active_chunks, stats.total_available_usd_e6, listed.len(), listed_e6
+        ));
+    }
+
+    let book = crate::chunk_allocation::get_active_chunks();
+    let book_e6: UsdE6 = book.iter().map(|chunk| chunk.amount_usd_e6).sum();
+    if book.len() != listed.len() || book_e6 != listed_e6 {
+        violations.push(format!(
+            "Orderbook listing has {} chunks / {} e6, expected {} / {} e6",
+            book.len(), book_e6, listed.len(), listed_e6
+        ));
+    }
+}
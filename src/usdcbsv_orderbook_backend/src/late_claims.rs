@@ -0,0 +1,225 @@
+/// Late claims - the 24h reclaim (heartbeat::reclaim_expired_trades) sends an unclaimed trade's
+/// ckUSDC to treasury, but the filler's payment may still have confirmed. A late claim is
+/// verified exactly like claim_usdc; within LATE_CLAIM_AUTO_WINDOW_NS of the reclaim the treasury
+/// pays the filler what an on-time claim would have, later ones wait for the admin, and none are
+/// taken past LATE_CLAIM_WINDOW_NS
+/// The unclaimed-trade penalty is not refunded here - fillers appeal it (disputes.rs)
+use candid::{Nat, Principal};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError};
+use crate::types::*;
+use crate::state::*;
+use crate::runtime::LedgerClient;
+use crate::config::{CKUSDC_TRANSFER_FEE, LATE_CLAIM_AUTO_WINDOW_NS, LATE_CLAIM_WINDOW_NS};
+use crate::{bump_verification, chain_sync, trade_lifecycle};
+
+/// When the reclaim sent the trade's funds to treasury - None if it never did
+fn reclaimed_at(trade: &Trade) -> Option<u64> {
+    let reclaimed = trade.status == TradeStatus::Cancelled
+        && trade.withdrawal_tx_hash.as_ref().is_some_and(|hash| hash.starts_with("treasury_reclaim_"));
+    if reclaimed { trade.withdrawal_confirmed_at } else { None }
+}
+
+/// Whether a late claim may still reach the trade - a new one inside LATE_CLAIM_WINDOW_NS, or one
+/// not yet paid. data_cleanup keeps these trades
+pub(crate) fn open_to_late_claim(trade_id: TradeId, trade: &Trade, now: u64) -> bool {
+    match get_late_claim(trade_id) {
+        Some(claim) => claim.status != LateClaimStatus::Paid,
+        None => reclaimed_at(trade).is_some_and(|reclaimed_at| now < reclaimed_at + LATE_CLAIM_WINDOW_NS),
+    }
+}
+
+/// Reclaims moved on the order's book ledger, so its late claim is paid back on that ledger
+fn claim_ledger(trade_id: TradeId) -> Principal {
+    get_trade(trade_id)
+        .map(|trade| crate::books::order_ledger(trade.order_id))
+        .unwrap_or_else(crate::books::ckusdc_ledger)
+}
+
+/// A payout may only spend revenue the admin could withdraw - on another book's ledger, only
+/// the reclaims held there
+async fn ensure_treasury_covers(ledger: Principal, amount_e6: UsdE6) -> Result<(), EasySwapError> {
+    let treasury = Account { owner: crate::runtime::canister_id(), subaccount: None };
+    let balance = match crate::runtime::ledger().icrc1_balance_of(ledger, treasury).await {
+        Ok((balance,)) => crate::ckusdc_integration::nat_to_u64(&balance).unwrap_or(u64::MAX),
+        Err((code, msg)) => return Err(EasySwapError::ledger(format!("Failed to get treasury balance: {:?}: {}", code, msg))),
+    };
+    // withdrawable_e6 already leaves room for the ledger fee, which the payout takes out of amount_e6
+    let withdrawable_e6 = if ledger == crate::books::ckusdc_ledger() {
+        crate::treasury::withdrawable_e6(balance) + CKUSDC_TRANSFER_FEE as UsdE6
+    } else {
+        crate::treasury::ledger_revenue(ledger).reclaims_held().min(balance)
+    };
+    if amount_e6 > withdrawable_e6 {
+        return Err(EasySwapError::insufficient_funds(format!(
+            "Treasury can pay up to {} e6 towards late claims right now, {} needed", withdrawable_e6, amount_e6
+        )));
+    }
+    Ok(())
+}
+
+/// Filler proves the payment of a trade that was reclaimed to treasury
+/// Reviewing an Approved claim again retries its payout without re-verifying
+pub async fn late_claim_review(caller: Principal, trade_id: TradeId, tx_hex: String, bump_hex: String) -> Result<LateClaim, EasySwapError> {
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot claim USDC. Please authenticate first."));
+    }
+
+    let trade = get_trade(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
+
+    if trade.filler != caller {
+        return Err(EasySwapError::unauthorized("Only the trade filler can claim USDC"));
+    }
+
+    let reclaimed_at = reclaimed_at(&trade)
+        .ok_or_else(|| EasySwapError::invalid_state("Trade was not reclaimed to treasury - claim it with claim_usdc"))?;
+
+    if let Some(claim) = get_late_claim(trade_id) {
+        return match claim.status {
+            LateClaimStatus::Approved => pay_late_claim(claim).await,
+            LateClaimStatus::AwaitingAdmin => Err(EasySwapError::invalid_state("Late claim is verified and awaiting admin approval")),
+            LateClaimStatus::Paid => Err(EasySwapError::invalid_state("Late claim has already been paid")),
+        };
+    }
+
+    if get_time() >= reclaimed_at + LATE_CLAIM_WINDOW_NS {
+        return Err(EasySwapError::invalid_state(format!(
+            "Late claims on this trade closed {} days after it was reclaimed",
+            LATE_CLAIM_WINDOW_NS / (24 * 60 * 60 * 1_000_000_000)
+        )));
+    }
+
+    // Same checks as claim_usdc, bar the status and the 24h expiry
+    let stored_txid = trade.bsv_txid.clone()
+        .ok_or_else(|| EasySwapError::invalid_state("No BSV transaction submitted for this trade"))?;
+    if compute_bsv_txid(&tx_hex).ok().as_ref() != Some(&stored_txid) {
+        return Err(EasySwapError::invalid_state("Transaction hex does not match the submitted transaction for this trade"));
+    }
+    trade_lifecycle::check_claim_outputs(&trade, &tx_hex)?;
+
+    chain_sync::ensure_fresh_tip().await?;
+    let required_confirmations = trade_lifecycle::claim_confirmations(&trade);
+    let verification = bump_verification::verify_tx_raw_async(&tx_hex, &bump_hex, required_confirmations).await;
+    let verification = trade_lifecycle::check_claim_verification(verification, required_confirmations)?;
+
+    let order = get_order(trade.order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+    let amount_e6 = trade.amount_usd_e6 + bps_of(trade.amount_usd_e6, order.filler_incentive_bps());
+
+    let now = get_time();
+    let automatic = now <= reclaimed_at + LATE_CLAIM_AUTO_WINDOW_NS;
+    if automatic {
+        ensure_treasury_covers(claim_ledger(trade_id), amount_e6).await?;
+    }
+
+    // Another review may have finished while verification was in flight
+    if get_late_claim(trade_id).is_some() {
+        return Err(EasySwapError::invalid_state("Late claim for this trade is already being processed"));
+    }
+
+    let claim = LateClaim {
+        trade_id,
+        filler: caller,
+        bsv_txid: stored_txid.clone(),
+        bsv_block_height: verification.block_height,
+        amount_e6,
+        reclaimed_at,
+        verified_at: now,
+        status: if automatic { LateClaimStatus::Approved } else { LateClaimStatus::AwaitingAdmin },
+        block_index: None,
+        paid_at: None,
+    };
+    insert_late_claim(claim.clone());
+
+    create_admin_event(AdminEventType::LateClaimVerified {
+        trade_id,
+        filler: caller,
+        bsv_txid: stored_txid,
+        awaiting_admin: !automatic,
+    });
+
+    if automatic {
+        pay_late_claim(claim).await
+    } else {
+        ic_cdk::println!("⏳ Late claim on trade {} verified - awaiting admin approval", trade_id);
+        Ok(claim)
+    }
+}
+
+/// Approve a verified late claim past the automatic window and pay it (or retry its payout)
+/// Note: Admin check is enforced in lib.rs before calling this function
+pub async fn admin_approve_late_claim(trade_id: TradeId) -> Result<LateClaim, EasySwapError> {
+    let mut claim = get_late_claim(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("No late claim found for this trade"))?;
+
+    match claim.status {
+        LateClaimStatus::Paid => Err(EasySwapError::invalid_state("Late claim has already been paid")),
+        LateClaimStatus::Approved => pay_late_claim(claim).await,
+        LateClaimStatus::AwaitingAdmin => {
+            ensure_treasury_covers(claim_ledger(trade_id), claim.amount_e6).await?;
+            claim.status = LateClaimStatus::Approved;
+            insert_late_claim(claim.clone());
+            pay_late_claim(claim).await
+        }
+    }
+}
+
+/// Pay an Approved claim from the treasury's main account to the filler's default account
+async fn pay_late_claim(mut claim: LateClaim) -> Result<LateClaim, EasySwapError> {
+    let ledger = claim_ledger(claim.trade_id);
+    // The memo names the trade, so a retry after a lost reply is deduplicated by the ledger
+    let memo = format!("Late claim T{}", claim.trade_id).into_bytes();
+    let transfer_args = TransferArg {
+        from_subaccount: None,
+        to: Account { owner: claim.filler, subaccount: None },
+        fee: None,
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+        memo: Some(Memo::from(memo.clone())),
+        amount: Nat::from(claim.amount_e6.saturating_sub(CKUSDC_TRANSFER_FEE as UsdE6)),
+    };
+
+    let result = crate::runtime::ledger().icrc1_transfer(ledger, transfer_args).await;
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
+
+    match result {
+        Ok((Ok(block_index),)) | Ok((Err(TransferError::Duplicate { duplicate_of: block_index }),)) => {
+            let block_index = crate::ckusdc_integration::nat_to_u64(&block_index).map_err(EasySwapError::ledger)?;
+            crate::treasury::record_late_claim_payout(ledger, claim.amount_e6);
+
+            claim.status = LateClaimStatus::Paid;
+            claim.block_index = Some(block_index);
+            claim.paid_at = Some(get_time());
+            insert_late_claim(claim.clone());
+
+            create_admin_event(AdminEventType::LateClaimPaid {
+                trade_id: claim.trade_id,
+                filler: claim.filler,
+                amount_paid: e6_to_usd(claim.amount_e6),
+                block_index,
+            });
+            ic_cdk::println!("✅ Late claim on trade {} paid from treasury at block {}", claim.trade_id, block_index);
+            Ok(claim)
+        }
+        // Nothing was paid - the claim stays Approved for the next attempt
+        Ok((Err(e),)) => Err(EasySwapError::ledger(format!("Late claim payout failed: {:?}", e))),
+        Err((code, msg)) => Err(EasySwapError::ledger(format!(
+            "Late claim payout call failed: {:?}: {}. Review the claim again to retry it.",
+            code, msg
+        ))),
+    }
+}
+
+/// Get the late claim on a trade (visible to its filler and the admin)
+pub fn get_trade_late_claim(trade_id: TradeId) -> Option<LateClaim> {
+    let caller = get_caller();
+    let claim = get_late_claim(trade_id)?;
+
+    if caller == claim.filler || caller == get_admin() {
+        Some(claim)
+    } else {
+        None
+    }
+}
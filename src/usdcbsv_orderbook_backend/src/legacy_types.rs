@@ -0,0 +1,217 @@
+/// Record layouts from before USD amounts were stored as micro-USD integers
+/// Only used to decode entries still held in stable memory in the old format -
+/// `state::migrate_usd_amounts_to_e6` rewrites them once after upgrade
+use candid::{CandidType, Deserialize, Principal};
+use crate::types::*;
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LegacyRefundAttempt {
+    pub refund_id: u64,
+    pub requested_at: u64,
+    pub chunk_ids: Vec<ChunkId>,
+    pub total_amount: f64,
+    pub recipient_address: String,
+    pub tx_hash: Option<String>,
+    pub tx_sent_at: Option<u64>,
+    pub confirmed_at: Option<u64>,
+    pub status: RefundStatus,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LegacyOrder {
+    pub id: OrderId,
+    pub maker: Principal,
+    pub amount_usd: f64,
+    pub total_deposited_usd: Option<f64>,
+    pub activation_fee_usd: Option<f64>,
+    pub filler_incentive_reserved: Option<f64>,
+    pub deposit_principal: String,
+    pub deposit_subaccount: String,
+    pub max_bsv_price: f64,
+    pub allow_partial_fill: bool,
+    pub bsv_address: String,
+    pub status: OrderStatus,
+    pub chunks: Vec<ChunkId>,
+    pub created_at: u64,
+    pub deposit_confirmed_at: Option<u64>,
+    pub funded_at: Option<u64>,
+    pub activation_fee_block_index: Option<u64>,
+    pub activation_fee_confirmed_at: Option<u64>,
+    pub total_filled_usd: f64,
+    pub total_locked_usd: f64,
+    pub total_idle_usd: f64,
+    pub total_refunded_usd: Option<f64>,
+    pub refund_attempts: Vec<LegacyRefundAttempt>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LegacyChunk {
+    pub id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd: f64,
+    pub status: ChunkStatus,
+    pub locked_by: Option<TradeId>,
+    pub filled_at: Option<u64>,
+    pub bsv_address: String,
+    pub sats_amount: Option<u64>,
+    pub max_bsv_price: f64,
+    pub lock_count: Option<u64>,
+    pub timeout_release_count: Option<u64>,
+    pub last_timeout_filler: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LegacyLockedChunk {
+    pub chunk_id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd: f64,
+    pub bsv_address: String,
+    pub sats_amount: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LegacyTrade {
+    pub id: TradeId,
+    pub order_id: OrderId,
+    pub filler: Principal,
+    pub amount_usd: f64,
+    pub locked_chunks: Vec<LegacyLockedChunk>,
+    pub agreed_bsv_price: f64,
+    pub min_bsv_price: f64,
+    pub status: TradeStatus,
+    pub bsv_tx_hex: Option<String>,
+    pub created_at: u64,
+    pub tx_submitted_at: Option<u64>,
+    pub lock_expires_at: u64,
+    pub release_available_at: Option<u64>,
+    pub claim_expires_at: Option<u64>,
+    pub required_confirmations: Option<u64>,
+    pub sats_tolerance: Option<SatsTolerance>,
+    pub withdrawal_initiated_at: Option<u64>,
+    pub withdrawal_tx_hash: Option<String>,
+    pub withdrawal_confirmed_at: Option<u64>,
+}
+
+impl From<LegacyRefundAttempt> for RefundAttempt {
+    fn from(r: LegacyRefundAttempt) -> Self {
+        Self {
+            refund_id: r.refund_id,
+            requested_at: r.requested_at,
+            chunk_ids: r.chunk_ids,
+            total_amount_e6: usd_to_e6(r.total_amount),
+            recipient_address: r.recipient_address,
+            tx_hash: r.tx_hash,
+            tx_sent_at: r.tx_sent_at,
+            confirmed_at: r.confirmed_at,
+            status: r.status,
+            block_index: None,
+            pending_transfer_id: None,
+            last_error: None,
+        }
+    }
+}
+
+impl From<LegacyOrder> for Order {
+    fn from(o: LegacyOrder) -> Self {
+        Self {
+            id: o.id,
+            maker: o.maker,
+            amount_usd_e6: usd_to_e6(o.amount_usd),
+            total_deposited_usd_e6: o.total_deposited_usd.map(usd_to_e6),
+            activation_fee_usd_e6: o.activation_fee_usd.map(usd_to_e6),
+            filler_incentive_reserved_e6: o.filler_incentive_reserved.map(usd_to_e6),
+            filler_incentive_bps: None,
+            deposit_principal: o.deposit_principal,
+            deposit_subaccount: o.deposit_subaccount,
+            max_bsv_price: o.max_bsv_price,
+            allow_partial_fill: o.allow_partial_fill,
+            bsv_address: o.bsv_address,
+            status: o.status,
+            chunks: o.chunks,
+            created_at: o.created_at,
+            deposit_confirmed_at: o.deposit_confirmed_at,
+            funded_at: o.funded_at,
+            activation_fee_block_index: o.activation_fee_block_index,
+            activation_fee_confirmed_at: o.activation_fee_confirmed_at,
+            total_filled_usd_e6: usd_to_e6(o.total_filled_usd),
+            total_locked_usd_e6: usd_to_e6(o.total_locked_usd),
+            total_idle_usd_e6: usd_to_e6(o.total_idle_usd),
+            total_refunded_usd_e6: o.total_refunded_usd.map(usd_to_e6),
+            refund_attempts: o.refund_attempts.into_iter().map(RefundAttempt::from).collect(),
+            finalization_pending_since: None,
+            hidden: None,
+            idle_since: None,
+            last_idle_at: None,
+            idle_duration_total_ns: None,
+            auto_cancel_above_price: None,
+            post_only: None,
+            book_id: None,
+        }
+    }
+}
+
+impl From<LegacyChunk> for Chunk {
+    fn from(c: LegacyChunk) -> Self {
+        Self {
+            id: c.id,
+            order_id: c.order_id,
+            amount_usd_e6: usd_to_e6(c.amount_usd),
+            status: c.status,
+            locked_by: c.locked_by,
+            filled_at: c.filled_at,
+            bsv_address: c.bsv_address,
+            sats_amount: c.sats_amount,
+            max_bsv_price: c.max_bsv_price,
+            filler_incentive_bps: None,
+            lock_count: c.lock_count,
+            timeout_release_count: c.timeout_release_count,
+            last_timeout_filler: c.last_timeout_filler,
+            reserved_by: None,
+            reserved_until: None,
+            hidden: None,
+            book_id: None,
+        }
+    }
+}
+
+impl From<LegacyLockedChunk> for LockedChunk {
+    fn from(lc: LegacyLockedChunk) -> Self {
+        Self {
+            chunk_id: lc.chunk_id,
+            order_id: lc.order_id,
+            amount_usd_e6: usd_to_e6(lc.amount_usd),
+            bsv_address: lc.bsv_address,
+            sats_amount: lc.sats_amount,
+        }
+    }
+}
+
+impl From<LegacyTrade> for Trade {
+    fn from(t: LegacyTrade) -> Self {
+        Self {
+            id: t.id,
+            order_id: t.order_id,
+            filler: t.filler,
+            amount_usd_e6: usd_to_e6(t.amount_usd),
+            locked_chunks: t.locked_chunks.into_iter().map(LockedChunk::from).collect(),
+            agreed_bsv_price: t.agreed_bsv_price,
+            min_bsv_price: t.min_bsv_price,
+            effective_bsv_price: None,
+            status: t.status,
+            bsv_txid: None, // Set by migration 2, which moves bsv_tx_hex into TX_BLOBS
+            payment_group: None,
+            bsv_block_height: None,
+            created_at: t.created_at,
+            tx_submitted_at: t.tx_submitted_at,
+            lock_expires_at: t.lock_expires_at,
+            lock_extended_at: None,
+            release_available_at: t.release_available_at,
+            claim_expires_at: t.claim_expires_at,
+            required_confirmations: t.required_confirmations,
+            sats_tolerance: t.sats_tolerance,
+            withdrawal_initiated_at: t.withdrawal_initiated_at,
+            withdrawal_tx_hash: t.withdrawal_tx_hash,
+            withdrawal_confirmed_at: t.withdrawal_confirmed_at,
+        }
+    }
+}
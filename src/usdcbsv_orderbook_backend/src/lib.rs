@@ -16,9 +16,72 @@ mod block_sync;
 mod chain_sync;
 mod bump_verification;
 mod data_cleanup;
+mod outcall_retry;
+mod disputes;
+mod quiet_hours;
+mod filler_blocklist;
+mod wallet_top_up;
+mod rebates;
+mod books;
+mod delegations;
+mod release_bonds;
+mod settlement;
+mod legacy_types;
+mod migrations;
+mod backup;
+mod market_data;
+mod market_stats;
+mod price_candles;
+mod circuit_breaker;
+mod reconciliation;
+mod treasury;
+mod quotes;
+mod archive;
+mod scheduler;
+mod pending_transfers;
+mod transfer_dedup;
+mod data_export;
+mod orderbook_feed;
+mod http_certification;
+mod api_versions;
+mod config_simulation;
+mod runtime;
+mod record_envelope;
+mod validation;
+mod ingress_guard;
+mod memory_stats;
+mod alerts;
+mod late_claims;
+mod v1;
+#[cfg(any(test, debug_assertions))]
+mod invariants;
+#[cfg(test)]
+mod lifecycle_tests;
+#[cfg(test)]
+mod invariant_tests;
+#[cfg(test)]
+mod candid_interface_tests;
+
+/// Parser entry points for the cargo-fuzz targets in fuzz/ - these take caller-supplied hex,
+/// so any input must come back as Ok or Err, never a panic
+#[cfg(fuzzing)]
+pub mod fuzzing {
+    pub fn parse_bsv_transaction(raw_hex: &str) -> Result<(), String> {
+        let parsed = crate::bsv_parser::parse_bsv_transaction(raw_hex)?;
+        let input_values = vec![u64::MAX; parsed.inputs.len()];
+        crate::bsv_parser::calculate_fee_rate(&parsed, &input_values).map(|_| ())
+    }
+
+    pub fn parse_bump_hex(bump_hex: &str) -> Result<(), String> {
+        crate::bump_verification::parse_bump_hex(bump_hex).map(|_| ())
+    }
+
+    pub fn parse_varint(bytes: &[u8], start: usize) -> Result<(u64, usize), String> {
+        crate::bump_verification::parse_varint(bytes, start)
+    }
+}
 
-use ic_cdk::{init, post_upgrade, query, update};
-use ic_cdk_timers::{set_timer, set_timer_interval};
+use ic_cdk::{init, inspect_message, post_upgrade, query, update};
 use std::time::Duration;
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
@@ -38,75 +101,9 @@ use ic_cdk::api::management_canister::http_request::{TransformArgs, HttpResponse
 fn start_timers() {
     ic_cdk::println!("🔄 Starting background timers...");
     
-    // Timer 1: Check pending orders and process confirmations (every 60 seconds)
-    set_timer_interval(Duration::from_secs(60), || {
-        ic_cdk::spawn(async {
-            let _ = heartbeat::process_confirmations().await;
-        });
-    });
-    
-    // Timer 2: Idle chunk reactivation (every 60 seconds)
-    set_timer_interval(Duration::from_secs(60), || {
-        ic_cdk::spawn(async {
-            let _ = heartbeat::reactivate_idle_chunks().await;
-        });
-    });
-    
-    // Timer 3: Cleanup tasks (every 5 minutes)
-    set_timer_interval(Duration::from_secs(5 * 60), || {
-        ic_cdk::spawn(async {
-            let _ = heartbeat::process_cleanup_tasks().await;
-        });
-    });
-    
-    // Timer 4: Sync BSV blocks (every 20 minutes)
-    set_timer_interval(Duration::from_secs(20 * 60), || {
-        ic_cdk::spawn(async {
-            let cycles_start = ic_cdk::api::canister_balance128();
-            
-            match chain_sync::sync_blocks().await {
-                Ok(result) => {
-                    ic_cdk::println!(
-                        "✅ Block sync successful: {} blocks added, {} removed",
-                        result.blocks_added,
-                        result.blocks_removed
-                    );
-                    block_headers::update_sync_time(ic_cdk::api::time() / 1_000_000_000);
-                }
-                Err(e) => {
-                    ic_cdk::println!("❌ Block sync failed: {}", e);
-                }
-            }
-            
-            let cycles_end = ic_cdk::api::canister_balance128();
-            let cycles_consumed = cycles_start.saturating_sub(cycles_end);
-            
-            ic_cdk::println!(
-                "⏱️  sync_blocks consumed {} cycles ({:.4} TC)",
-                cycles_consumed,
-                cycles_consumed as f64 / 1_000_000_000_000.0
-            );
-        });
-    });
-    
-    // Timer 5: Data cleanup (every 24 hours)
-    set_timer_interval(Duration::from_secs(config::CLEANUP_INTERVAL_SECONDS), || {
-        ic_cdk::spawn(async {
-            let cycles_start = ic_cdk::api::canister_balance128();
-            
-            let (orders, trades, blocks, admin_events) = data_cleanup::run_cleanup();
-            ic_cdk::println!("🧹 Cleanup: {} orders, {} trades, {} blocks, {} admin_events deleted", orders, trades, blocks, admin_events);
-            
-            let cycles_end = ic_cdk::api::canister_balance128();
-            let cycles_consumed = cycles_start.saturating_sub(cycles_end);
-            
-            ic_cdk::println!(
-                "⏱️  data_cleanup consumed {} cycles ({:.4} TC)",
-                cycles_consumed,
-                cycles_consumed as f64 / 1_000_000_000_000.0
-            );
-        });
-    });
+    // Confirmations, idle reactivation, cleanup, block sync, data cleanup, settlement,
+    // reconciliation, ckETH check and withdrawal follow-up all run from the scheduler
+    scheduler::start();
     
     ic_cdk::println!("✅ All timers started successfully");
 }
@@ -115,14 +112,28 @@ fn start_timers() {
 fn init_canister() {
     ic_cdk::println!("EasySwap initialized with admin: {}", ADMIN_PRINCIPAL);
     ic_cdk::println!("Block storage in stable memory - timer will sync last 720 blocks every 20 minutes");
+    migrations::run_pending_migrations();
     start_timers();
 }
 
+/// Screen ingress update calls before they are accepted - see ingress_guard.rs
+#[inspect_message]
+fn inspect_message() {
+    let method = ic_cdk::api::msg_method_name();
+    match ingress_guard::check(&method, ic_cdk::caller(), ic_cdk::api::call::arg_data_raw_size(), ic_cdk::api::msg_arg_data) {
+        Ok(()) => ic_cdk::api::accept_message(),
+        Err(reason) => ic_cdk::trap(&reason),
+    }
+}
+
 #[post_upgrade]
 fn post_upgrade_canister() {
     ic_cdk::println!("EasySwap upgraded - restarting timers");
     ic_cdk::println!("Block storage persisted in stable memory - timer will sync any missing blocks");
     
+    // Before anything reads the maps - undecodable records would otherwise trap every call touching them
+    record_envelope::quarantine_corrupt_records();
+    migrations::run_pending_migrations();
     start_timers();
 
     
@@ -138,7 +149,6 @@ fn post_upgrade_canister() {
                         result.blocks_added,
                         result.new_tip_height
                     );
-                    block_headers::update_sync_time(ic_cdk::api::time() / 1_000_000_000);
                 }
                 Err(e) => {
                     ic_cdk::println!("❌ Initial sync failed: {}", e);
@@ -150,43 +160,96 @@ fn post_upgrade_canister() {
 
 // ===== MAKER FUNCTIONS =====
 
+/// `post_only` holds the new chunks Pending until the next idle check or confirm_order
+/// `book_id` picks the book (see get_books) - None = the default ckUSDC book
 #[update]
 async fn create_order(
     amount_usd: f64,
     max_bsv_price: f64,
     bsv_address: String,
-) -> Result<OrderId, String> {
+    post_only: Option<bool>,
+    book_id: Option<types::BookId>,
+) -> Result<OrderId, EasySwapError> {
+    validation::bsv_address(&bsv_address)?;
     // Creates order with auto-activation if balance sufficient
-    order_management::create_order(amount_usd, max_bsv_price, bsv_address).await
+    if post_only == Some(true) {
+        return order_management::create_post_only_order(amount_usd, max_bsv_price, bsv_address, None, book_id).await;
+    }
+    order_management::create_order_in_book(amount_usd, max_bsv_price, bsv_address, None, book_id).await
+}
+
+/// List a post-only order's Pending chunks now - returns how many were activated
+#[update]
+async fn confirm_order(order_id: OrderId) -> Result<u64, EasySwapError> {
+    order_management::confirm_order(order_id).await
+}
+
+/// Create several orders in one call - returns one result per request, in order
+#[update]
+async fn create_orders_batch(requests: Vec<order_management::CreateOrderRequest>) -> Result<Vec<Result<OrderId, EasySwapError>>, EasySwapError> {
+    for request in &requests {
+        validation::bsv_address(&request.bsv_address)?;
+    }
+    order_management::create_orders_batch(requests).await
+}
+
+/// Create an order funded by an ICRC-2 approval instead of a prior deposit - the exact
+/// required deposit is pulled from the caller's default ckUSDC account
+#[update]
+async fn create_order_with_allowance(
+    amount_usd: f64,
+    max_bsv_price: f64,
+    bsv_address: String,
+    filler_incentive_bps: Option<u64>,
+    book_id: Option<types::BookId>,
+) -> Result<OrderId, EasySwapError> {
+    validation::bsv_address(&bsv_address)?;
+    order_management::create_order_with_allowance(amount_usd, max_bsv_price, bsv_address, filler_incentive_bps, book_id).await
+}
+
+#[query]
+fn get_my_orders() -> Vec<v1::Order> {
+    let maker = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::OrdersRead);
+    order_management::get_my_orders(maker).into_iter().map(v1::Order::from).collect()
 }
 
 #[query]
-fn get_my_orders() -> Vec<Order> {
-    order_management::get_my_orders()
+fn get_my_active_orders() -> Vec<v1::Order> {
+    let maker = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::OrdersRead);
+    order_management::get_my_active_orders(maker).into_iter().map(v1::Order::from).collect()
 }
 
 #[query]
-fn get_my_active_orders() -> Vec<Order> {
-    order_management::get_my_active_orders()
+fn get_my_orders_paginated(offset: u64, limit: u64, status_filter: Option<Vec<types::OrderStatus>>) -> Result<v1::PaginatedOrders, EasySwapError> {
+    validation::page(offset, limit)?;
+    Ok(order_management::get_my_orders_paginated(offset, limit, status_filter).into())
 }
 
+/// Cursor page of the caller's orders (newest first) - pass next_cursor back as after_id
 #[query]
-fn get_my_orders_paginated(offset: u64, limit: u64, status_filter: Option<Vec<types::OrderStatus>>) -> types::PaginatedOrders {
-    order_management::get_my_orders_paginated(offset, limit, status_filter)
+fn get_my_orders_page(after_id: Option<OrderId>, limit: u64, status_filter: Option<Vec<types::OrderStatus>>) -> Result<v1::OrderPage, EasySwapError> {
+    validation::page_limit(limit)?;
+    Ok(order_management::get_my_orders_page(after_id, limit, status_filter).into())
 }
 
 #[query]
-fn get_my_active_orders_paginated(offset: u64, limit: u64) -> types::PaginatedOrders {
-    order_management::get_my_active_orders_paginated(offset, limit)
+fn get_my_active_orders_paginated(offset: u64, limit: u64) -> Result<v1::PaginatedOrders, EasySwapError> {
+    validation::page(offset, limit)?;
+    Ok(order_management::get_my_active_orders_paginated(offset, limit).into())
 }
 
 #[query]
-fn get_my_orders_by_status_paginated(status: OrderStatus, offset: u64, limit: u64) -> types::PaginatedOrders {
-    order_management::get_my_orders_by_status_paginated(status, offset, limit)
+fn get_my_orders_by_status_paginated(status: OrderStatus, offset: u64, limit: u64) -> Result<v1::PaginatedOrders, EasySwapError> {
+    validation::page(offset, limit)?;
+    Ok(order_management::get_my_orders_by_status_paginated(status, offset, limit).into())
 }
 
 #[query]
-fn get_order(order_id: OrderId) -> Option<Order> {
+fn get_order(order_id: OrderId) -> Option<v1::Order> {
+    visible_order(order_id).map(v1::Order::from)
+}
+
+fn visible_order(order_id: OrderId) -> Option<Order> {
     let caller = ic_cdk::caller();
     let order = order_management::get_order(order_id)?;
     
@@ -199,25 +262,195 @@ fn get_order(order_id: OrderId) -> Option<Order> {
     }
 }
 
+/// Order with its chunks, fetched from the archive canister once it has been offloaded (maker or admin only)
+#[query(composite = true)]
+async fn get_archived_order(order_id: OrderId) -> Result<Option<v1::ArchivedOrder>, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let archived = archive::get_archived_order(order_id).await?;
+    
+    match archived {
+        Some(a) if caller == a.order.maker || caller == state::get_admin() => Ok(Some(a.into())),
+        Some(_) => Err(EasySwapError::unauthorized("Only the order maker or admin can view this order")),
+        None => Ok(None),
+    }
+}
+
+/// Trade fetched from the archive canister once it has been offloaded (filler or admin only)
+#[query(composite = true)]
+async fn get_archived_trade(trade_id: TradeId) -> Result<Option<v1::Trade>, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let trade = archive::get_archived_trade(trade_id).await?;
+    
+    match trade {
+        Some(t) if caller == t.filler || caller == state::get_admin() => Ok(Some(t.into())),
+        Some(_) => Err(EasySwapError::unauthorized("Only the filler or admin can view this trade")),
+        None => Ok(None),
+    }
+}
+
+#[query]
+fn get_order_chunks(order_id: OrderId) -> Vec<v1::ChunkDetails> {
+    order_management::get_order_chunks(order_id).into_iter().map(v1::ChunkDetails::from).collect()
+}
+
+/// BSV transactions fillers submitted to the caller's orders, with per-address sats and confirmations
+#[query]
+fn get_incoming_bsv_payments() -> Vec<v1::IncomingBsvPayment> {
+    order_management::get_incoming_bsv_payments().into_iter().map(v1::IncomingBsvPayment::from).collect()
+}
+
+/// Trades that locked chunks of the order, with BSV txids and timestamps (maker or admin only)
+#[query]
+fn get_order_trades(order_id: OrderId) -> Result<Vec<v1::OrderTradeActivity>, EasySwapError> {
+    Ok(order_management::get_order_trades(order_id)?.into_iter().map(v1::OrderTradeActivity::from).collect())
+}
+
+/// Set UTC hours (0-23) and weekdays (0-6, Monday = 0) during which the caller's chunks stay Idle
+/// Empty lists clear the schedule
+#[update]
+fn set_my_quiet_hours(quiet_hours: Vec<u8>, quiet_days: Vec<u8>) -> Result<(), EasySwapError> {
+    quiet_hours::set_my_quiet_hours(quiet_hours, quiet_days)
+}
+
+#[query]
+fn get_my_quiet_hours() -> Option<types::MakerQuietHours> {
+    quiet_hours::get_my_quiet_hours()
+}
+
+/// Replace the list of fillers whose trades skip the caller's chunks - an empty list clears it
+#[update]
+fn set_my_filler_blocklist(fillers: Vec<Principal>) -> Result<(), EasySwapError> {
+    filler_blocklist::set_my_filler_blocklist(fillers)
+}
+
+#[query]
+fn get_my_filler_blocklist() -> Option<types::MakerFillerBlocklist> {
+    filler_blocklist::get_my_filler_blocklist()
+}
+
+/// Let order activation pull a deposit shortfall of up to `max_per_order_usd` from the caller's
+/// wallet through their standing ICRC-2 approval - None turns it off
+#[update]
+fn set_my_auto_top_up(max_per_order_usd: Option<f64>) -> Result<(), EasySwapError> {
+    wallet_top_up::set_my_auto_top_up(max_per_order_usd)
+}
+
+#[query]
+fn get_my_auto_top_up() -> Option<types::MakerAutoTopUp> {
+    wallet_top_up::get_my_auto_top_up()
+}
+
+/// Caller's filled volume this rebate epoch and rebates owed and paid
+#[query]
+fn get_my_rebates() -> types::MakerRebates {
+    rebates::get_my_rebates()
+}
+
+/// Pay the caller's owed rebates to their wallet - returns the amount received
+#[update]
+async fn claim_rebates() -> Result<u64, EasySwapError> {
+    rebates::claim_rebates().await
+}
+
+#[update]
+async fn update_max_bsv_price(order_id: OrderId, new_max_bsv_price: f64) -> Result<(), EasySwapError> {
+    let maker = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::MakerPricing);
+    order_management::update_max_bsv_price(maker, order_id, new_max_bsv_price).await
+}
+
+/// Set one max BSV price on several orders - returns (order_id, result) per order
+#[update]
+async fn update_max_bsv_price_bulk(order_ids: Vec<OrderId>, new_max_bsv_price: f64) -> Result<Vec<(OrderId, Result<(), EasySwapError>)>, EasySwapError> {
+    let maker = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::MakerPricing);
+    order_management::update_max_bsv_price_bulk(maker, order_ids, new_max_bsv_price).await
+}
+
+// ===== OPERATOR DELEGATIONS =====
+
+/// Propose `operator` to act for the caller within `scopes` (never withdrawals) for
+/// expires_in_seconds (None = until revoked) - it acts once the operator calls accept_delegation
+#[update]
+fn register_operator(operator: Principal, scopes: Vec<types::OperatorScope>, expires_in_seconds: Option<u64>) -> Result<types::OperatorDelegation, EasySwapError> {
+    delegations::register_operator(operator, scopes, expires_in_seconds)
+}
+
+#[update]
+fn revoke_operator(operator: Principal) -> Result<(), EasySwapError> {
+    delegations::revoke_operator(operator)
+}
+
 #[query]
-fn get_order_chunks(order_id: OrderId) -> Vec<types::ChunkDetails> {
-    order_management::get_order_chunks(order_id)
+fn get_my_operators() -> Vec<types::OperatorDelegation> {
+    delegations::get_my_operators()
 }
 
+/// Operator side - accept the delegation `owner` proposed to the caller
 #[update]
-async fn update_max_bsv_price(order_id: OrderId, new_max_bsv_price: f64) -> Result<(), String> {
-    order_management::update_max_bsv_price(order_id, new_max_bsv_price).await
+fn accept_delegation(owner: Principal) -> Result<types::OperatorDelegation, EasySwapError> {
+    delegations::accept_delegation(owner)
 }
 
+/// Operator side - decline a proposal or stop acting for its owner
 #[update]
-async fn cancel_order(order_id: OrderId) -> Result<(), String> {
+fn resign_delegation() -> Result<(), EasySwapError> {
+    delegations::resign_delegation()
+}
+
+#[query]
+fn get_my_delegation() -> Option<types::OperatorDelegation> {
+    delegations::get_my_delegation()
+}
+
+/// Hide an order's chunks from public depth and stats (still matchable), or list them again
+#[update]
+fn set_order_hidden(order_id: OrderId, hidden: bool) -> Result<(), EasySwapError> {
+    order_management::set_order_hidden(order_id, hidden)
+}
+
+/// Cancel and refund the order automatically once BSV trades above `price` (None clears it)
+#[update]
+fn set_order_auto_cancel(order_id: OrderId, price: Option<f64>) -> Result<(), EasySwapError> {
+    order_management::set_order_auto_cancel(order_id, price)
+}
+
+#[update]
+fn update_order_bsv_address(order_id: OrderId, new_address: String) -> Result<(), EasySwapError> {
+    validation::bsv_address(&new_address)?;
+    order_management::update_order_bsv_address(order_id, new_address)
+}
+
+#[update]
+async fn cancel_order(order_id: OrderId) -> Result<(), EasySwapError> {
     order_management::cancel_order(order_id).await
 }
 
+/// Cancel all of the caller's Active/Idle orders - returns (order_id, result) per order
+#[update]
+async fn cancel_all_my_orders() -> Result<Vec<(OrderId, Result<(), EasySwapError>)>, EasySwapError> {
+    order_management::cancel_all_my_orders().await
+}
+
+#[update]
+async fn recover_orphaned_deposits(from_order_id: OrderId) -> Result<types::DepositRecoveryScan, EasySwapError> {
+    order_management::recover_orphaned_deposits(from_order_id).await
+}
+
+/// Refunds of the caller's cancelled orders and whether each has gone through
+#[query]
+fn get_my_refunds() -> Vec<v1::OrderRefund> {
+    order_management::get_my_refunds().into_iter().map(v1::OrderRefund::from).collect()
+}
+
+#[query]
+fn get_my_deposit_recoveries(offset: u64, limit: u64) -> Result<types::PaginatedDepositRecoveries, EasySwapError> {
+    validation::page(offset, limit)?;
+    Ok(order_management::get_my_deposit_recoveries(offset, limit))
+}
+
 // ===== FILLER FUNCTIONS =====
 
 #[update]
-async fn deposit_security(amount: u64) -> Result<(), String> {
+async fn deposit_security(amount: u64) -> Result<(), EasySwapError> {
     filler_accounts::deposit_security(amount).await
 }
 
@@ -226,13 +459,80 @@ fn get_my_filler_account() -> Option<FillerAccount> {
     filler_accounts::get_my_filler_account()
 }
 
+/// Caller's security balance: total, locked by open trades, available and the lock capacity left
+/// (update call - reads the ledger balance)
+#[update]
+async fn get_my_security_breakdown() -> Result<types::SecurityBreakdown, EasySwapError> {
+    filler_accounts::get_my_security_breakdown().await
+}
+
+/// Opt in to (or out of) instant release of small trades, bonded from the security deposit
+#[update]
+fn set_instant_release(enabled: bool) -> Result<(), EasySwapError> {
+    release_bonds::set_instant_release(enabled)
+}
+
+/// Caller's security held against instantly released trades until their payments are buried
+#[query]
+fn get_my_release_bonds() -> Vec<types::ReleaseBond> {
+    release_bonds::get_my_release_bonds()
+}
+
+/// Prove a bonded payment in the branch that replaced its block, so the bond isn't forfeited
 #[update]
-async fn withdraw_security(amount: u64, to_principal: String) -> Result<(), String> {
+async fn reprove_release_bond(trade_id: u64, bump_hex: String) -> Result<(), EasySwapError> {
+    release_bonds::reprove_release_bond(ic_cdk::caller(), trade_id, bump_hex).await
+}
+
+#[update]
+async fn withdraw_security(amount: u64, to_principal: String) -> Result<(), EasySwapError> {
+    validation::principal_text(&to_principal)?;
     let principal = Principal::from_text(to_principal)
-        .map_err(|e| format!("Invalid principal: {}", e))?;
+        .map_err(|e| EasySwapError::invalid_input(format!("Invalid principal: {}", e)))?;
     filler_accounts::withdraw_security(amount, principal).await
 }
 
+/// Withdraw a collateral token from the caller's deposit subaccount
+#[update]
+async fn withdraw_collateral(symbol: String, amount: u64, to_principal: String) -> Result<(), EasySwapError> {
+    validation::principal_text(&to_principal)?;
+    let principal = Principal::from_text(to_principal)
+        .map_err(|e| EasySwapError::invalid_input(format!("Invalid principal: {}", e)))?;
+    filler_accounts::withdraw_collateral(symbol, amount, principal).await
+}
+
+/// Tokens accepted as security besides ckUSDC, deposited to the same subaccount address
+#[query]
+fn get_collateral_tokens() -> Vec<types::CollateralToken> {
+    state::get_collateral_tokens()
+}
+
+/// Add a collateral token or change one's ledger, pricing or credit ratio
+#[update]
+fn admin_set_collateral_token(token: types::CollateralToken) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set collateral tokens"));
+    }
+    
+    filler_accounts::set_collateral_token(token)
+}
+
+/// Stop crediting a collateral token toward lock capacity
+#[update]
+fn admin_remove_collateral_token(symbol: String) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can remove collateral tokens"));
+    }
+    
+    filler_accounts::remove_collateral_token(&symbol)
+}
+
 #[query]
 fn get_filler_subaccount_address() -> String {
     let caller = ic_cdk::caller();
@@ -240,37 +540,146 @@ fn get_filler_subaccount_address() -> String {
 }
 
 #[update]
-async fn create_trades(request: trade_lifecycle::CreateTradesRequest) -> Result<Vec<TradeId>, String> {
-    trade_lifecycle::create_trades(request).await
+async fn create_trades(request: trade_lifecycle::CreateTradesRequest) -> Result<Vec<TradeId>, EasySwapError> {
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::TradeCreate);
+    trade_lifecycle::create_trades(filler, request).await
 }
 
+/// Soft-reserve up to amount_usd of chunks for ttl_secs ahead of create_trades
 #[update]
-async fn submit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) -> Result<(), String> {
-    trade_lifecycle::submit_bsv_transaction(trade_id, raw_tx_hex).await
+async fn reserve_chunks(amount_usd: f64, ttl_secs: u64, book_id: Option<types::BookId>) -> Result<types::ChunkReservation, EasySwapError> {
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::TradeCreate);
+    trade_lifecycle::reserve_chunks(filler, amount_usd, ttl_secs, book_id).await
+}
+
+// ===== QUOTES (RFQ) =====
+
+/// Post a quote to buy up to amount_usd at bsv_price, valid for expires_in_seconds
+#[update]
+async fn post_quote(amount_usd: f64, bsv_price: f64, expires_in_seconds: u64) -> Result<types::Quote, EasySwapError> {
+    quotes::post_quote(amount_usd, bsv_price, expires_in_seconds).await
 }
 
 #[update]
-async fn resubmit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) -> Result<(), String> {
-    trade_lifecycle::resubmit_bsv_transaction(trade_id, raw_tx_hex).await
+fn cancel_quote(quote_id: types::QuoteId) -> Result<(), EasySwapError> {
+    quotes::cancel_quote(quote_id)
 }
 
+/// Maker accepts a quote against one of their orders - creates a trade at the quoted price
 #[update]
-async fn claim_usdc(trade_id: TradeId, tx_hex: String, bump_hex: String) -> Result<(), String> {
-    trade_lifecycle::claim_usdc(trade_id, tx_hex, bump_hex).await
+async fn accept_quote(quote_id: types::QuoteId, order_id: OrderId) -> Result<TradeId, EasySwapError> {
+    quotes::accept_quote(quote_id, order_id).await
 }
 
 #[query]
-fn get_my_trades() -> Vec<Trade> {
-    trade_lifecycle::get_my_trades()
+fn get_open_quotes() -> Vec<types::Quote> {
+    quotes::get_open_quotes()
+}
+
+#[query]
+fn get_my_quotes() -> Vec<types::Quote> {
+    quotes::get_my_quotes()
+}
+
+#[update]
+async fn submit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) -> Result<(), EasySwapError> {
+    validation::tx_hex(&raw_tx_hex)?;
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::FillerSettlement);
+    trade_lifecycle::submit_bsv_transaction(filler, trade_id, raw_tx_hex).await
+}
+
+/// Submit one BSV transaction paying several of the caller's locked trades
+#[update]
+async fn submit_bsv_transaction_for_trades(trade_ids: Vec<TradeId>, raw_tx_hex: String) -> Result<(), EasySwapError> {
+    validation::tx_hex(&raw_tx_hex)?;
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::FillerSettlement);
+    trade_lifecycle::submit_bsv_transaction_for_trades(filler, trade_ids, raw_tx_hex).await
+}
+
+/// Exact outputs a transaction paying the given locked trades must start with
+#[query]
+fn get_trade_payment_template(trade_ids: Vec<TradeId>) -> Result<types::PaymentTemplate, EasySwapError> {
+    trade_lifecycle::get_payment_template(trade_ids)
+}
+
+#[update]
+async fn resubmit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) -> Result<(), EasySwapError> {
+    validation::tx_hex(&raw_tx_hex)?;
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::FillerSettlement);
+    trade_lifecycle::resubmit_bsv_transaction(filler, trade_id, raw_tx_hex).await
+}
+
+/// Extend a locked trade's expiry once, for a fee from the caller's security deposit - returns the new expiry
+#[update]
+async fn extend_trade_lock(trade_id: TradeId) -> Result<u64, EasySwapError> {
+    trade_lifecycle::extend_trade_lock(trade_id).await
+}
+
+#[update]
+async fn claim_usdc(trade_id: TradeId, tx_hex: String, bump_hex: String) -> Result<(), EasySwapError> {
+    validation::tx_hex(&tx_hex)?;
+    validation::bump_hex(&bump_hex)?;
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::FillerSettlement);
+    trade_lifecycle::claim_usdc(filler, trade_id, tx_hex, bump_hex).await
+}
+
+/// Claim a trade whose ckUSDC the 24h reclaim already sent to treasury - paid from treasury once
+/// verified, automatically within a week of the reclaim and after admin approval later
+#[update]
+async fn late_claim_review(trade_id: TradeId, tx_hex: String, bump_hex: String) -> Result<types::LateClaim, EasySwapError> {
+    validation::tx_hex(&tx_hex)?;
+    validation::bump_hex(&bump_hex)?;
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::FillerSettlement);
+    late_claims::late_claim_review(filler, trade_id, tx_hex, bump_hex).await
+}
+
+#[query]
+fn get_late_claim(trade_id: TradeId) -> Option<types::LateClaim> {
+    late_claims::get_trade_late_claim(trade_id)
 }
 
+/// Claim several trades in one call with (trade_id, tx_hex, bump_hex) per trade - returns one result per claim, in order
+#[update]
+async fn claim_usdc_batch(claims: Vec<(TradeId, String, String)>) -> Result<Vec<Result<(), EasySwapError>>, EasySwapError> {
+    for (_, tx_hex, bump_hex) in &claims {
+        validation::tx_hex(tx_hex)?;
+        validation::bump_hex(bump_hex)?;
+    }
+    let filler = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::FillerSettlement);
+    trade_lifecycle::claim_usdc_batch(filler, claims).await
+}
+
+#[query]
+fn get_my_trades() -> Vec<v1::Trade> {
+    trade_lifecycle::get_my_trades().into_iter().map(v1::Trade::from).collect()
+}
+
+#[query]
+fn get_my_trades_paginated(offset: u64, limit: u64, status_filter: Option<Vec<types::TradeStatus>>) -> Result<v1::PaginatedTrades, EasySwapError> {
+    validation::page(offset, limit)?;
+    Ok(trade_lifecycle::get_my_trades_paginated(offset, limit, status_filter).into())
+}
+
+/// Cursor page of the caller's trades (newest first) - pass next_cursor back as after_id
+#[query]
+fn get_my_trades_page(after_id: Option<TradeId>, limit: u64, status_filter: Option<Vec<types::TradeStatus>>) -> Result<v1::TradePage, EasySwapError> {
+    validation::page_limit(limit)?;
+    Ok(trade_lifecycle::get_my_trades_page(after_id, limit, status_filter).into())
+}
+
+/// Everything held about the caller (orders, chunks, trades, filler account, transfers) in cursor pages
+/// Account-level records come with the first page - pass next_cursor back until it is None
 #[query]
-fn get_my_trades_paginated(offset: u64, limit: u64, status_filter: Option<Vec<types::TradeStatus>>) -> types::PaginatedTrades {
-    trade_lifecycle::get_my_trades_paginated(offset, limit, status_filter)
+fn export_my_data(cursor: Option<types::DataExportCursor>) -> Result<v1::DataExport, EasySwapError> {
+    data_export::export_my_data(cursor).map(v1::DataExport::from)
 }
 
 #[query]
-fn get_trade(trade_id: TradeId) -> Option<Trade> {
+fn get_trade(trade_id: TradeId) -> Option<v1::Trade> {
+    visible_trade(trade_id).map(v1::Trade::from)
+}
+
+fn visible_trade(trade_id: TradeId) -> Option<Trade> {
     let caller = ic_cdk::caller();
     let trade = trade_lifecycle::get_trade(trade_id)?;
     
@@ -286,69 +695,832 @@ fn get_trade(trade_id: TradeId) -> Option<Trade> {
     }
 }
 
-// ===== ORDERBOOK FUNCTIONS =====
-
+/// Raw hex of the BSV transaction submitted for a trade - same access as get_trade
 #[query]
-fn get_active_chunks() -> Vec<OrderbookChunk> {
-    chunk_allocation::get_active_chunks()
+fn get_trade_tx_hex(trade_id: TradeId) -> Option<String> {
+    visible_trade(trade_id).and_then(|trade| state::get_trade_tx_hex(&trade))
 }
 
-#[query]
-fn get_active_chunks_paginated(offset: u64, limit: u64) -> types::PaginatedChunks {
-    chunk_allocation::get_active_chunks_paginated(offset, limit)
+// ===== DISPUTES =====
+
+#[update]
+fn maker_flag_dispute(trade_id: TradeId, reason: String) -> Result<(), EasySwapError> {
+    validation::dispute_reason(&reason)?;
+    disputes::maker_flag_dispute(trade_id, reason)
 }
 
 #[query]
-fn get_orderbook_stats() -> OrderbookStats {
-    chunk_allocation::get_orderbook_stats()
+fn get_trade_dispute(trade_id: TradeId) -> Option<types::TradeDispute> {
+    disputes::get_trade_dispute(trade_id)
 }
 
 #[query]
-fn get_available_orderbook() -> f64 {
-    state::get_available_orderbook()
+fn admin_get_disputes(open_only: bool) -> Vec<types::TradeDispute> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Vec::new();
+    }
+    
+    state::get_disputes(open_only)
 }
 
-// ===== TREASURY =====
+// ===== PENALTY APPEALS =====
+
+/// Ask the admin to refund the penalties taken on one of the caller's trades
+#[update]
+fn appeal_penalty(trade_id: TradeId, explanation: String) -> Result<(), EasySwapError> {
+    validation::appeal_explanation(&explanation)?;
+    disputes::appeal_penalty(trade_id, explanation)
+}
 
-// Get canister's cycles balance
 #[query]
-fn get_cycles_balance() -> u64 {
-    ic_cdk::api::canister_balance()
+fn get_penalty_appeal(trade_id: TradeId) -> Option<types::PenaltyAppeal> {
+    disputes::get_trade_penalty_appeal(trade_id)
 }
 
-// Withdraw ckUSDC to Ethereum USDC
-// User must first approve canister to spend (withdrawal_amount + gas_fee + treasury_fee) ckUSDC
-// Canister pays Ethereum gas using its ckETH treasury
-// Treasury fee (0.05 USDC) covers operational costs (XRC calls, etc.)
-#[update]
-async fn withdraw_ckusdc_to_eth(
-    withdrawal_amount_e6: candid::Nat,
-    gas_fee_usdc_e6: candid::Nat,
-    treasury_fee_e6: candid::Nat,
-    gas_amount_wei: candid::Nat,
-    recipient_address: String,
-) -> Result<withdrawal_treasury::RetrieveErc20Request, String> {
+#[query]
+fn admin_get_penalty_appeals(open_only: bool) -> Vec<types::PenaltyAppeal> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Vec::new();
+    }
+    
+    state::get_penalty_appeals(open_only)
+}
+
+/// Daily settlement reports for UTC days in [from_day, to_day] (days since Unix epoch)
+#[query]
+fn admin_get_settlement_reports(from_day: u64, to_day: u64) -> Result<Vec<types::DailySettlementReport>, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can access settlement reports"));
+    }
+    
+    if to_day < from_day || to_day - from_day >= config::MAX_SETTLEMENT_REPORTS_PER_QUERY {
+        return Err(EasySwapError::invalid_input(format!(
+            "Day range must be ascending and span at most {} days",
+            config::MAX_SETTLEMENT_REPORTS_PER_QUERY
+        )));
+    }
+    
+    Ok(state::get_settlement_reports(from_day, to_day))
+}
+
+/// Orders whose subaccount balance is below what their open chunks are owed
+#[query]
+fn admin_get_reconciliation_issues() -> Result<Vec<types::ReconciliationIssue>, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can access reconciliation issues"));
+    }
+    
+    Ok(state::get_reconciliation_issues())
+}
+
+/// Dismiss a reconciliation issue after manual review
+#[update]
+fn admin_clear_reconciliation_issue(order_id: OrderId) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can clear reconciliation issues"));
+    }
+    
+    state::remove_reconciliation_issue(order_id)
+        .map(|_| ())
+        .ok_or_else(|| EasySwapError::not_found(format!("No reconciliation issue for order {}", order_id)))
+}
+
+/// Stored schema version, rollback flag and the last migration report
+#[query]
+fn admin_get_schema_state() -> Result<types::SchemaState, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can view schema state"));
+    }
+    
+    Ok(state::get_schema_state())
+}
+
+/// Validate pending migrations against stored records without writing - run before upgrading
+#[query]
+fn admin_dry_run_migrations() -> Result<types::MigrationReport, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can run migration checks"));
+    }
+    
+    Ok(migrations::dry_run())
+}
+
+/// Records quarantined by post_upgrade because no layout could decode them (oldest first)
+#[query]
+fn admin_get_corrupt_records(after_id: Option<u64>, limit: u64) -> Result<types::CorruptRecordPage, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can view corrupt records"));
+    }
+    validation::page_limit(limit)?;
+    
+    let (records, next_cursor) = state::get_corrupt_records_page(after_id, limit as usize);
+    Ok(types::CorruptRecordPage { records, next_cursor })
+}
+
+/// Rollback mode: post_upgrade skips pending migrations so a previous build can be
+/// reinstalled against records still in the older schema
+#[update]
+fn admin_set_rollback_mode(enabled: bool) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can change rollback mode"));
+    }
+    
+    state::update_schema_state(|s| s.rollback_mode = enabled);
+    ic_cdk::println!("🔧 Schema rollback mode {}", if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+/// Snapshot all stable state for download - chunks are fetched with admin_get_backup_chunk
+#[update]
+fn admin_create_backup() -> Result<types::BackupManifest, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can create backups"));
+    }
+    
+    Ok(backup::create_backup())
+}
+
+#[query]
+fn admin_get_backup_chunk(backup_id: u64, index: u64) -> Result<types::BackupChunk, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can download backups"));
+    }
+    
+    backup::get_backup_chunk(backup_id, index).map_err(EasySwapError::not_found)
+}
+
+/// Restore a backup into a fresh canister - upload chunks over one or more calls with the same manifest
+#[update]
+fn admin_restore_backup(manifest: types::BackupManifest, chunks: Vec<types::BackupChunk>) -> Result<types::RestoreStatus, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can restore backups"));
+    }
+    
+    backup::restore_backup(manifest, chunks).map_err(EasySwapError::invalid_state)
+}
+
+/// Fillers ranked by how often their trades timed out and released chunks back to the orderbook
+#[query]
+fn admin_get_churning_fillers(min_timeout_releases: u64) -> Vec<types::FillerAccount> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Vec::new();
+    }
+    
+    state::get_fillers_by_timeout_releases(min_timeout_releases)
+}
+
+#[update]
+fn admin_resolve_dispute(trade_id: TradeId, resolution: types::DisputeResolution) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can resolve disputes"));
+    }
+    
+    disputes::admin_resolve_dispute(trade_id, resolution)
+}
+
+/// Refund up to the appealed penalty from the treasury to the filler's deposit - 0 denies the appeal
+#[update]
+async fn admin_resolve_appeal(trade_id: TradeId, refund_amount: f64) -> Result<types::PenaltyAppeal, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can resolve penalty appeals"));
+    }
+    
+    disputes::admin_resolve_appeal(trade_id, refund_amount).await
+}
+
+#[query]
+fn admin_get_late_claims(unpaid_only: bool) -> Vec<types::LateClaim> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Vec::new();
+    }
+    
+    state::get_late_claims(unpaid_only)
+}
+
+/// Pay a verified late claim that arrived after the automatic window (or retry its payout)
+#[update]
+async fn admin_approve_late_claim(trade_id: TradeId) -> Result<types::LateClaim, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can approve late claims"));
+    }
+    
+    late_claims::admin_approve_late_claim(trade_id).await
+}
+
+// ===== ORDERBOOK FUNCTIONS =====
+
+/// Listed chunks of a book - None = the default ckUSDC book
+#[query]
+fn get_active_chunks(book_id: Option<types::BookId>) -> Vec<v1::OrderbookChunk> {
+    chunk_allocation::get_book_active_chunks(book_id.unwrap_or(books::DEFAULT_BOOK_ID))
+        .into_iter().map(v1::OrderbookChunk::from).collect()
+}
+
+#[query]
+fn get_active_chunks_paginated(offset: u64, limit: u64, book_id: Option<types::BookId>) -> Result<v1::PaginatedChunks, EasySwapError> {
+    validation::page(offset, limit)?;
+    Ok(chunk_allocation::get_active_chunks_paginated(offset, limit, book_id.unwrap_or(books::DEFAULT_BOOK_ID)).into())
+}
+
+/// Cursor page of Available chunks (FIFO order) - pass next_cursor back as after_id
+#[query]
+fn get_active_chunks_page(after_id: Option<types::ChunkId>, limit: u64, book_id: Option<types::BookId>) -> Result<v1::ChunkPage, EasySwapError> {
+    validation::page_limit(limit)?;
+    Ok(chunk_allocation::get_active_chunks_page(after_id, limit, book_id.unwrap_or(books::DEFAULT_BOOK_ID)).into())
+}
+
+/// Every orderbook - book 0 is the original ckUSDC/BSV-mainnet book
+#[query]
+fn get_books() -> Vec<types::OrderBook> {
+    books::get_books()
+}
+
+/// Orders, chunks, volume and trades of one book - None = the default ckUSDC book
+#[query]
+fn get_book_stats(book_id: Option<types::BookId>) -> Result<types::BookStats, EasySwapError> {
+    books::get_book_stats(book_id)
+}
+
+/// Orderbook depth, last trade price, 24h volume and oracle price in one call
+#[query]
+fn get_public_market_data() -> PublicMarketData {
+    market_data::get_public_market_data()
+}
+
+/// Latest completed trades (amount, price, time) with no principals or addresses
+#[query]
+fn get_recent_trades_public(limit: u64) -> Result<Vec<types::PublicTrade>, EasySwapError> {
+    validation::page_limit(limit)?;
+    Ok(market_data::get_recent_trades_public(limit))
+}
+
+/// Completed-trade volume, count, average price and unique participants over the last 24h or 7d
+#[query]
+fn get_market_stats(window: StatsWindow) -> MarketStats {
+    market_stats::get_market_stats(window)
+}
+
+/// BSV/USD oracle price candles between `from` and `to` (ns timestamps, inclusive)
+#[query]
+fn get_price_candles(from: u64, to: u64, interval: CandleInterval) -> Result<Vec<PriceCandle>, EasySwapError> {
+    price_candles::get_price_candles(from, to, interval).map_err(EasySwapError::invalid_input)
+}
+
+#[query]
+fn get_orderbook_stats() -> OrderbookStats {
+    chunk_allocation::get_orderbook_stats()
+}
+
+/// Every Available chunk plus stats, stamped with the feed sequence number
+#[query]
+fn get_orderbook_snapshot() -> v1::OrderbookSnapshot {
+    orderbook_feed::get_orderbook_snapshot().into()
+}
+
+/// Orderbook changes after since_seq (a snapshot's or previous delta's seq)
+#[query]
+fn get_orderbook_delta(since_seq: u64) -> v1::OrderbookDelta {
+    orderbook_feed::get_orderbook_delta(since_seq).into()
+}
+
+/// Certified JSON for the IC HTTP gateway - GET /orderbook and GET /stats
+#[query]
+fn http_request(request: types::HttpGatewayRequest) -> types::HttpGatewayResponse {
+    http_certification::http_request(request)
+}
+
+#[query]
+fn get_available_orderbook(book_id: Option<types::BookId>) -> f64 {
+    e6_to_usd(chunk_allocation::get_listed_book_e6(book_id.unwrap_or(books::DEFAULT_BOOK_ID)))
+}
+
+// ===== TREASURY =====
+
+// Get canister's cycles balance
+#[query]
+fn get_cycles_balance() -> u64 {
+    ic_cdk::api::canister_balance()
+}
+
+/// Operational health: cycles, order/trading switches and the ckETH gas treasury watermark
+#[query]
+fn get_health() -> types::HealthStatus {
+    let now = state::get_time();
+    types::HealthStatus {
+        cycles_balance: ic_cdk::api::canister_balance(),
+        new_orders_enabled: state::are_new_orders_enabled(),
+        trading_halted: circuit_breaker::is_trading_halted(now),
+        eth_withdrawals_enabled: !withdrawal_treasury::eth_withdrawals_paused(),
+        cketh: state::get_cketh_monitor(),
+        checked_at: now,
+    }
+}
+
+/// Stable memory pages and record counts per map, plus heap usage - for deciding when to
+/// tighten the retention policy
+#[query]
+fn get_memory_stats() -> Result<types::MemoryStats, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can view memory stats"));
+    }
+    
+    Ok(memory_stats::get_memory_stats())
+}
+
+/// Where critical alerts (header insertion errors, failing block sync, penalty spikes, low
+/// cycles) are pushed - a canister method or an HTTPS webhook. None turns alerts off
+#[update]
+fn admin_set_alert_sink(sink: Option<types::AlertSink>) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the alert sink"));
+    }
+    
+    alerts::set_alert_sink(sink)
+}
+
+/// Alert sink, undelivered alerts and delivery counts since the last upgrade
+#[query]
+fn admin_get_alert_status() -> Result<types::AlertStatus, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can view alert status"));
+    }
+    
+    Ok(alerts::get_alert_status())
+}
+
+/// Adjust how long data_cleanup keeps orders, trades, admin events and block headers
+#[update]
+fn admin_set_retention_policy(policy: types::RetentionPolicy) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the retention policy"));
+    }
+    
+    data_cleanup::set_retention_policy(policy)
+}
+
+/// Adjust the per-filler open interest cap and the orderbook-wide lock cap
+#[update]
+fn admin_set_exposure_limits(limits: types::ExposureLimits) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set exposure limits"));
+    }
+    
+    trade_lifecycle::set_exposure_limits(limits)
+}
+
+/// Range of filler incentive makers may offer on new orders
+#[query]
+fn get_filler_incentive_bounds() -> types::FillerIncentiveBounds {
+    state::get_filler_incentive_bounds()
+}
+
+/// Adjust the range of filler incentive makers may offer - existing orders keep theirs
+#[update]
+fn admin_set_filler_incentive_bounds(bounds: types::FillerIncentiveBounds) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set filler incentive bounds"));
+    }
+    
+    order_management::set_filler_incentive_bounds(bounds)
+}
+
+/// How create_trades spreads a fill across orders
+#[query]
+fn get_matching_policy() -> types::MatchingPolicy {
+    state::get_matching_policy()
+}
+
+/// Switch the matching policy - applies to trades created from now on
+#[update]
+fn admin_set_matching_policy(policy: types::MatchingPolicy) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the matching policy"));
+    }
+    
+    state::set_matching_policy(policy);
+    Ok(())
+}
+
+/// Smallest create_trades request or quote, and the most chunks one trade may lock - a book
+/// without limits of its own (or an unknown one) uses the global limits
+#[query]
+fn get_trade_size_limits(book_id: Option<types::BookId>) -> types::TradeSizeLimits {
+    books::resolve(book_id)
+        .map(|book| books::trade_size_limits(&book))
+        .unwrap_or_else(|_| state::get_trade_size_limits())
+}
+
+/// Adjust the minimum trade size and the per-trade chunk cap
+#[update]
+fn admin_set_trade_size_limits(limits: types::TradeSizeLimits) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set trade size limits"));
+    }
+    
+    trade_lifecycle::set_trade_size_limits(limits)
+}
+
+/// Adjust the timeout penalty schedule - applies to trades unlocked from now on
+#[update]
+fn admin_set_timeout_penalty_schedule(schedule: types::TimeoutPenaltySchedule) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the timeout penalty schedule"));
+    }
+    
+    trade_lifecycle::set_timeout_penalty_schedule(schedule)
+}
+
+/// Open a book for another settlement token on the canister's network - returns its ID
+#[update]
+fn admin_add_book(settlement_symbol: String, settlement_ledger: Principal) -> Result<types::BookId, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can add books"));
+    }
+    
+    books::add_book(settlement_symbol, settlement_ledger)
+}
+
+/// Set a book's own orderbook and trade size limits (None = the global ones), or close it to new orders
+#[update]
+fn admin_update_book(
+    book_id: types::BookId,
+    enabled: bool,
+    max_orderbook_usd_e6: Option<types::UsdE6>,
+    trade_size_limits: Option<types::TradeSizeLimits>,
+) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can update books"));
+    }
+    
+    books::update_book(book_id, enabled, max_orderbook_usd_e6, trade_size_limits)
+}
+
+/// Adjust how price improvements are split with fillers - applies to trades created from now on
+#[update]
+fn admin_set_price_improvement_sharing(sharing: types::PriceImprovementSharing) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set price improvement sharing"));
+    }
+    
+    trade_lifecycle::set_price_improvement_sharing(sharing)
+}
+
+/// Adjust confirmation depths by trade size - applies to trades submitted from now on
+#[update]
+fn admin_set_confirmation_depth_schedule(schedule: types::ConfirmationDepthSchedule) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the confirmation depth schedule"));
+    }
+    
+    trade_lifecycle::set_confirmation_depth_schedule(schedule)
+}
+
+/// How stale the local chain tip may be before claims sync on demand
+#[update]
+fn admin_set_max_chain_tip_age(seconds: u64) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the max chain tip age"));
+    }
+    
+    chain_sync::set_max_chain_tip_age(seconds)
+}
+
+/// Set the TxArchive canister, how far its blocks are trusted, and whether it is used at all
+#[update]
+fn admin_set_txarchive_policy(policy: types::TxArchivePolicy) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the TxArchive policy"));
+    }
+    
+    chain_sync::set_txarchive_policy(policy)
+}
+
+/// Follow BSV mainnet or testnet - staging deployments run the same code against testnet
+#[update]
+fn admin_set_bsv_network(network: types::BsvNetwork) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the BSV network"));
+    }
+    
+    chain_sync::set_bsv_network(network)
+}
+
+/// Switch the TxArchive fallback off (stricter deployments) or back on
+#[update]
+fn admin_set_txarchive_fallback_enabled(enabled: bool) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can toggle the TxArchive fallback"));
+    }
+    
+    chain_sync::set_txarchive_fallback_enabled(enabled)
+}
+
+/// Deposit, timeout, penalty and matching parameters currently in force
+#[query]
+fn get_config() -> types::PublicConfig {
+    types::PublicConfig {
+        security_deposit_percent: config::SECURITY_DEPOSIT_PERCENT,
+        max_lock_multiplier: config::MAX_LOCK_MULTIPLIER,
+        trusted_filler_min_successful_trades: config::TRUSTED_FILLER_MIN_SUCCESSFUL_TRADES,
+        trusted_filler_clean_window_ns: config::TRUSTED_FILLER_CLEAN_WINDOW_NS,
+        trusted_filler_security_deposit_percent: config::TRUSTED_FILLER_SECURITY_DEPOSIT_PERCENT,
+        trusted_filler_lock_multiplier: config::TRUSTED_FILLER_LOCK_MULTIPLIER,
+        min_chunk_size_usd_e6: config::MIN_CHUNK_SIZE_E6,
+        trade_timeout_ns: config::TRADE_TIMEOUT_NS,
+        lock_extension_ns: config::LOCK_EXTENSION_NS,
+        lock_extension_fee_bps: config::LOCK_EXTENSION_FEE_PERCENT,
+        default_filler_incentive_bps: config::FILLER_INCENTIVE_PERCENT,
+        filler_incentive_bounds: state::get_filler_incentive_bounds(),
+        trade_size_limits: state::get_trade_size_limits(),
+        matching_policy: state::get_matching_policy(),
+        timeout_penalty_schedule: state::get_timeout_penalty_schedule(),
+        price_improvement_sharing: state::get_price_improvement_sharing(),
+        confirmation_depth_schedule: state::get_confirmation_depth_schedule(),
+        instant_release_max_trade_usd_e6: config::INSTANT_RELEASE_MAX_TRADE_USD_E6,
+        instant_release_confirmations: config::INSTANT_RELEASE_CONFIRMATIONS,
+        max_chain_tip_age_seconds: state::get_max_chain_tip_age_seconds(),
+        txarchive_policy: state::get_txarchive_policy(),
+        bsv_network: state::get_bsv_network(),
+    }
+}
+
+/// Dry run of proposed limits - which existing orders, makers and fillers would no longer pass
+#[query]
+fn admin_simulate_config(proposal: types::ConfigProposal) -> Result<types::ConfigImpactReport, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can simulate config changes"));
+    }
+    
+    Ok(config_simulation::simulate_config(proposal))
+}
+
+/// Accounting invariants that no longer hold (empty when consistent) - debug builds only,
+/// so not part of the deployed interface
+#[cfg(debug_assertions)]
+#[query]
+fn debug_check_invariants() -> Vec<String> {
+    invariants::check_invariants()
+}
+
+/// Dry run of the next data cleanup - what would be deleted (or archived) under the current policy
+#[query]
+fn preview_cleanup() -> Result<types::CleanupPreview, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can preview cleanup"));
+    }
+    
+    Ok(data_cleanup::preview_cleanup())
+}
+
+/// Set the archive canister finished orders/trades are offloaded to (None = delete them instead)
+#[update]
+fn admin_set_archive_canister(canister: Option<Principal>) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the archive canister"));
+    }
+    
+    state::set_archive_canister(canister);
+    Ok(())
+}
+
+/// Set the ckETH low-watermark and whether ETH withdrawals pause below it
+/// The balance is re-checked immediately against the new watermark
+#[update]
+async fn admin_set_cketh_watermark(low_watermark_wei: candid::Nat, auto_disable_withdrawals: bool) -> Result<types::CkEthMonitor, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set the ckETH watermark"));
+    }
+    
+    state::update_cketh_monitor(|m| {
+        m.low_watermark_wei = low_watermark_wei;
+        m.auto_disable_withdrawals = auto_disable_withdrawals;
+    });
+    withdrawal_treasury::check_cketh_balance().await;
+    
+    Ok(state::get_cketh_monitor())
+}
+
+// Withdraw ckUSDC to Ethereum USDC
+// User must first approve canister to spend (withdrawal_amount + gas_fee + treasury_fee) ckUSDC
+// Canister pays Ethereum gas using its ckETH treasury
+// Treasury fee (0.05 USDC) covers operational costs (XRC calls, etc.)
+#[update]
+async fn withdraw_ckusdc_to_eth(
+    withdrawal_amount_e6: candid::Nat,
+    gas_fee_usdc_e6: candid::Nat,
+    treasury_fee_e6: candid::Nat,
+    gas_amount_wei: candid::Nat,
+    recipient_address: String,
+    chain: Option<String>,
+) -> Result<withdrawal_treasury::RetrieveErc20Request, EasySwapError> {
+    validation::evm_address(&recipient_address)?;
+    if let Some(chain) = &chain {
+        validation::chain_name(chain)?;
+    }
+    let caller = ic_cdk::caller();
+    withdrawal_treasury::withdraw_ckusdc_to_eth(
+        caller,
+        withdrawal_amount_e6,
+        gas_fee_usdc_e6,
+        treasury_fee_e6,
+        gas_amount_wei,
+        recipient_address,
+        chain,
+    ).await
+}
+
+/// Chains ckUSDC can be withdrawn to (the chain argument of withdraw_ckusdc_to_eth)
+#[query]
+fn get_withdrawal_routes() -> Vec<types::WithdrawalRoute> {
+    withdrawal_treasury::list_withdrawal_routes()
+}
+
+/// Add or replace the withdrawal route for route.chain
+/// Storing a route named "ethereum" overrides the configured default
+#[update]
+fn admin_set_withdrawal_route(route: types::WithdrawalRoute) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can manage withdrawal routes"));
+    }
+    
+    validation::chain_name(&route.chain)?;
+    let chain = route.chain.trim().to_lowercase();
+    
+    state::insert_withdrawal_route(types::WithdrawalRoute { chain, ..route });
+    Ok(())
+}
+
+/// Remove a stored withdrawal route - in-flight withdrawals keep the route they were submitted on
+#[update]
+fn admin_remove_withdrawal_route(chain: String) -> Result<types::WithdrawalRoute, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can manage withdrawal routes"));
+    }
+    
+    state::remove_withdrawal_route(&chain.trim().to_lowercase())
+        .ok_or_else(|| EasySwapError::not_found(format!("No stored withdrawal route for chain '{}'", chain)))
+}
+
+/// Caller's ETH withdrawals, newest first, with refund status for failed ones
+#[query]
+fn get_my_withdrawals() -> Vec<types::EthWithdrawal> {
+    let mut withdrawals = state::get_eth_withdrawals_by_user(ic_cdk::caller());
+    withdrawals.reverse();
+    withdrawals
+}
+
+/// Status of one of the caller's withdrawals, by the ckETH block index returned from
+/// withdraw_ckusdc_to_eth - includes the Ethereum tx hash once the minter has signed it
+#[query]
+fn get_withdrawal_status(cketh_block_index: u64) -> Result<types::EthWithdrawal, EasySwapError> {
     let caller = ic_cdk::caller();
-    withdrawal_treasury::withdraw_ckusdc_to_eth(
-        caller,
-        withdrawal_amount_e6,
-        gas_fee_usdc_e6,
-        treasury_fee_e6,
-        gas_amount_wei,
-        recipient_address,
-    ).await
+    
+    let withdrawal = state::get_eth_withdrawal_by_cketh_block(cketh_block_index)
+        .ok_or_else(|| EasySwapError::not_found(format!("No withdrawal with ckETH block index {}", cketh_block_index)))?;
+    
+    if withdrawal.user != caller && caller != state::get_admin() {
+        return Err(EasySwapError::unauthorized("Not your withdrawal"));
+    }
+    
+    Ok(withdrawal)
 }
 
 // ===== PRICE ORACLE =====
 
 #[update]
-async fn get_bsv_price() -> Result<f64, String> {
-    price_oracle::get_bsv_price().await
+async fn get_bsv_price() -> Result<f64, EasySwapError> {
+    price_oracle::get_bsv_price().await.map_err(EasySwapError::unavailable)
+}
+
+/// Per-source BSV/USD prices from the latest oracle round and which ones fed the median
+#[query]
+fn get_price_sources() -> Option<PriceSources> {
+    price_oracle::get_price_sources()
 }
 
 #[update]
-async fn get_eth_usd_price() -> Result<f64, String> {
-    xrc_oracle::get_eth_usd_rate().await
+async fn get_eth_usd_price() -> Result<f64, EasySwapError> {
+    xrc_oracle::get_eth_usd_rate().await.map_err(EasySwapError::unavailable)
 }
 
 // ===== HEARTBEAT =====
@@ -376,15 +1548,32 @@ fn get_admin_events(limit: Option<u64>) -> Vec<types::AdminEvent> {
 }
 
 #[query]
-fn get_admin_events_paginated(offset: u64, limit: u64) -> Vec<types::AdminEvent> {
+fn get_admin_events_paginated(offset: u64, limit: u64) -> Result<Vec<types::AdminEvent>, EasySwapError> {
     let caller = ic_cdk::caller();
     let admin = state::get_admin();
     
     if caller != admin {
-        return Vec::new(); // Only admin can view events
+        return Ok(Vec::new()); // Only admin can view events
+    }
+    validation::page(offset, limit)?;
+    
+    Ok(state::get_paginated_admin_events(offset as usize, limit as usize))
+}
+
+/// Cursor page of admin events (newest first) - pass next_cursor back as after_id
+/// `filter` narrows by event kind and time range - None = every event
+#[query]
+fn get_admin_events_page(after_id: Option<u64>, limit: u64, filter: Option<types::AdminEventFilter>) -> Result<types::AdminEventPage, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Ok(types::AdminEventPage { events: Vec::new(), next_cursor: None }); // Only admin can view events
     }
+    validation::page_limit(limit)?;
     
-    state::get_paginated_admin_events(offset as usize, limit as usize)
+    let (events, next_cursor) = state::query_admin_events(&filter.unwrap_or_default(), after_id, limit as usize);
+    Ok(types::AdminEventPage { events, next_cursor })
 }
 
 #[query]
@@ -399,27 +1588,177 @@ fn get_admin_events_count() -> u64 {
     state::get_admin_events_count()
 }
 
+/// Withdraw `amount_e6` of treasury revenue (None = all of it) to `to` / `to_subaccount`
+/// (None = the admin's default account) - funds earmarked for in-flight trades stay put
+#[update]
+async fn admin_withdraw_ckusdc_treasury(amount_e6: Option<u64>, to: Option<Principal>, to_subaccount: Option<Vec<u8>>) -> Result<candid::Nat, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can withdraw from treasury"));
+    }
+    
+    withdrawal_treasury::admin_withdraw_ckusdc_treasury(amount_e6, to, to_subaccount).await
+}
+
+#[update]
+async fn admin_sweep_order_dust(from_order_id: OrderId, dry_run: bool) -> Result<types::DustSweepReport, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can sweep order dust"));
+    }
+    
+    withdrawal_treasury::admin_sweep_order_dust(from_order_id, dry_run).await
+}
+
+/// Treasury revenue by category against the live main-account ckUSDC balance
+/// (update call - reads the ledger balance)
+#[update]
+async fn get_treasury_report() -> Result<types::TreasuryReport, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can access the treasury report"));
+    }
+    
+    Ok(treasury::get_treasury_report().await)
+}
+
+/// ckUSDC, ckETH and cycles held, with estimated order subaccount totals and in-flight
+/// trade obligations, in one call (update call - reads the ledger balances)
 #[update]
-async fn admin_withdraw_ckusdc_treasury() -> Result<candid::Nat, String> {
+async fn get_treasury_balances() -> Result<types::TreasuryBalances, EasySwapError> {
     let caller = ic_cdk::caller();
     let admin = state::get_admin();
     
     if caller != admin {
-        return Err("Only admin can withdraw from treasury".to_string());
+        return Err(EasySwapError::unauthorized("Only admin can access treasury balances"));
     }
     
-    withdrawal_treasury::admin_withdraw_ckusdc_treasury().await
+    Ok(treasury::get_treasury_balances().await)
 }
 
 // ===== BLOCK SYNC ADMIN =====
 
 #[update]
-async fn admin_force_resync() -> Result<String, String> {
+async fn admin_sync_dry_run() -> Result<chain_sync::SyncDryRunReport, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can run sync dry run"));
+    }
+
+    chain_sync::sync_dry_run().await.map_err(EasySwapError::unavailable)
+}
+
+#[query]
+fn admin_get_outcall_backoff_status() -> Vec<types::OutcallBackoff> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Vec::new();
+    }
+
+    outcall_retry::get_outcall_backoff_status()
+}
+
+/// Clear an API host's outcall backoff so it is called again right away
+#[update]
+fn admin_reset_outcall_backoff(target: String) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can reset outcall backoff"));
+    }
+
+    outcall_retry::reset_outcall_backoff(&target).map_err(EasySwapError::not_found)
+}
+
+/// Interval, enable flag, last run and backoff of each background task
+#[query]
+fn admin_get_scheduled_tasks() -> Result<Vec<types::ScheduledTask>, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can view scheduled tasks"));
+    }
+
+    Ok(scheduler::get_tasks())
+}
+
+/// Change a background task's interval (seconds) and/or enable flag
+#[update]
+fn admin_update_scheduled_task(task: types::BackgroundTask, interval_seconds: Option<u64>, enabled: Option<bool>) -> Result<types::ScheduledTask, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can update scheduled tasks"));
+    }
+
+    scheduler::update_task(task, interval_seconds, enabled)
+}
+
+/// Penalty and refund transfers that exhausted their retries
+#[query]
+fn admin_get_failed_transfers() -> Result<Vec<types::PendingTransfer>, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can view failed transfers"));
+    }
+
+    Ok(pending_transfers::get_failed_transfers())
+}
+
+/// Re-queue a failed transfer with a fresh attempt budget
+#[update]
+fn admin_retry_transfer(id: u64) -> Result<types::PendingTransfer, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can retry transfers"));
+    }
+
+    pending_transfers::retry_failed_transfer(id)
+}
+
+#[update]
+fn admin_set_provider_api_key(provider: types::ApiProvider, api_key: Option<String>) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can set provider API keys"));
+    }
+
+    block_sync::set_provider_api_key(provider, api_key).map_err(EasySwapError::invalid_input)
+}
+
+/// Bring the next block sync forward (any authenticated user, once headers are a couple of
+/// minutes old) - returns when it will start
+#[update]
+fn request_block_sync() -> Result<u64, EasySwapError> {
+    chain_sync::request_block_sync()
+}
+
+#[update]
+async fn admin_force_resync() -> Result<String, EasySwapError> {
     let caller = ic_cdk::caller();
     let admin = state::get_admin();
 
     if caller != admin {
-        return Err("Only admin can force resync".to_string());
+        return Err(EasySwapError::unauthorized("Only admin can force resync"));
     }
 
     match chain_sync::admin_force_resync().await {
@@ -430,7 +1769,7 @@ async fn admin_force_resync() -> Result<String, String> {
                 result.blocks_added, result.blocks_removed, result.new_tip_height
             ))
         }
-        Err(e) => Err(e),
+        Err(e) => Err(EasySwapError::unavailable(e)),
     }
 }
 
@@ -441,13 +1780,91 @@ fn are_new_orders_enabled() -> bool {
     state::are_new_orders_enabled()
 }
 
+/// Circuit breaker state while trading is halted by an extreme price move (None = trading normally)
+#[query]
+fn get_circuit_breaker_status() -> Option<CircuitBreakerState> {
+    circuit_breaker::get_status()
+}
+
+// ===== FILLER ALLOWLIST =====
+
+/// Whether the caller may create and claim trades (always true while allowlist mode is off)
+#[query]
+fn am_i_approved_filler() -> bool {
+    filler_accounts::is_approved_filler(ic_cdk::caller())
+}
+
+#[query]
+fn is_filler_allowlist_enabled() -> bool {
+    state::is_filler_allowlist_enabled()
+}
+
+/// Turn allowlist mode on or off - while on, only approved fillers may trade
+#[update]
+fn admin_set_filler_allowlist_enabled(enabled: bool) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can change filler allowlist mode"));
+    }
+    
+    state::set_filler_allowlist_enabled(enabled);
+    Ok(())
+}
+
+#[update]
+fn admin_add_approved_filler(filler: Principal) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can approve fillers"));
+    }
+    
+    if filler == Principal::anonymous() {
+        return Err(EasySwapError::invalid_input("Anonymous principal cannot be approved"));
+    }
+    
+    state::insert_approved_filler(filler, state::get_time());
+    Ok(())
+}
+
+/// Open trades of a removed filler are unaffected, but they can no longer claim them
+#[update]
+fn admin_remove_approved_filler(filler: Principal) -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can remove approved fillers"));
+    }
+    
+    if !state::remove_approved_filler(filler) {
+        return Err(EasySwapError::not_found(format!("{} is not an approved filler", filler)));
+    }
+    Ok(())
+}
+
+#[query]
+fn admin_get_approved_fillers() -> Result<Vec<types::ApprovedFiller>, EasySwapError> {
+    let caller = ic_cdk::caller();
+    let admin = state::get_admin();
+    
+    if caller != admin {
+        return Err(EasySwapError::unauthorized("Only admin can view approved fillers"));
+    }
+    
+    Ok(state::get_approved_fillers())
+}
+
 #[update]
-fn admin_toggle_new_orders(enable: bool) -> Result<String, String> {
+fn admin_toggle_new_orders(enable: bool) -> Result<String, EasySwapError> {
     let caller = ic_cdk::caller();
     let admin = state::get_admin();
     
     if caller != admin {
-        return Err("Only admin can toggle new order acceptance".to_string());
+        return Err(EasySwapError::unauthorized("Only admin can toggle new order acceptance"));
     }
     
     let was_enabled = state::are_new_orders_enabled();
@@ -483,17 +1900,17 @@ fn admin_toggle_new_orders(enable: bool) -> Result<String, String> {
 // ===== AUDIT METHODS (ADMIN ONLY) =====
 
 #[query]
-fn admin_get_orders_audit(params: types::AuditQueryParams) -> Result<types::OrderAuditResponse, String> {
+fn admin_get_orders_audit(params: types::AuditQueryParams) -> Result<types::OrderAuditResponse, EasySwapError> {
     let caller = ic_cdk::caller();
     let admin = state::get_admin();
     
     if caller != admin {
-        return Err("Only admin can access audit records".to_string());
+        return Err(EasySwapError::unauthorized("Only admin can access audit records"));
     }
 
     // Validate page size
     if params.page_size == 0 || params.page_size > 500 {
-        return Err("Page size must be between 1 and 500".to_string());
+        return Err(EasySwapError::invalid_input("Page size must be between 1 and 500"));
     }
 
     // Get all orders from state
@@ -529,20 +1946,21 @@ fn admin_get_orders_audit(params: types::AuditQueryParams) -> Result<types::Orde
                 .filter_map(|chunk_id| {
                     state::get_chunk(*chunk_id).map(|chunk| types::ChunkAuditInfo {
                         chunk_id: chunk.id,
-                        amount_usd: chunk.amount_usd,
+                        amount_usd: e6_to_usd(chunk.amount_usd_e6),
                         status: chunk.status,
                         locked_by_trade: chunk.locked_by,
                         filled_at: chunk.filled_at,
                     })
                 })
                 .collect();
+            let idle_duration_ns = order.idle_duration_ns(state::get_time());
 
             types::OrderAuditRecord {
                 order_id: order.id,
                 maker: order.maker,
-                amount_usd: order.amount_usd,
-                total_deposited_usd: order.total_deposited_usd,
-                activation_fee_usd: order.activation_fee_usd,
+                amount_usd: e6_to_usd(order.amount_usd_e6),
+                total_deposited_usd: order.total_deposited_usd_e6.map(e6_to_usd),
+                activation_fee_usd: order.activation_fee_usd_e6.map(e6_to_usd),
                 deposit_principal: order.deposit_principal,
                 deposit_subaccount: order.deposit_subaccount,
                 max_bsv_price: order.max_bsv_price,
@@ -554,10 +1972,11 @@ fn admin_get_orders_audit(params: types::AuditQueryParams) -> Result<types::Orde
                 deposit_confirmed_at: order.deposit_confirmed_at,
                 funded_at: order.funded_at,
                 activation_fee_confirmed_at: order.activation_fee_confirmed_at,
-                total_filled_usd: order.total_filled_usd,
-                total_locked_usd: order.total_locked_usd,
-                total_idle_usd: order.total_idle_usd,
-                total_refunded_usd: order.total_refunded_usd,
+                total_filled_usd: e6_to_usd(order.total_filled_usd_e6),
+                total_locked_usd: e6_to_usd(order.total_locked_usd_e6),
+                total_idle_usd: e6_to_usd(order.total_idle_usd_e6),
+                idle_duration_ns,
+                total_refunded_usd: order.total_refunded_usd_e6.map(e6_to_usd),
                 refund_count: order.refund_attempts.len() as u64,
             }
         })
@@ -572,17 +1991,17 @@ fn admin_get_orders_audit(params: types::AuditQueryParams) -> Result<types::Orde
 }
 
 #[query]
-fn admin_get_trades_audit(params: types::AuditQueryParams) -> Result<types::TradeAuditResponse, String> {
+fn admin_get_trades_audit(params: types::AuditQueryParams) -> Result<types::TradeAuditResponse, EasySwapError> {
     let caller = ic_cdk::caller();
     let admin = state::get_admin();
     
     if caller != admin {
-        return Err("Only admin can access audit records".to_string());
+        return Err(EasySwapError::unauthorized("Only admin can access audit records"));
     }
 
     // Validate page size
     if params.page_size == 0 || params.page_size > 500 {
-        return Err("Page size must be between 1 and 500".to_string());
+        return Err(EasySwapError::invalid_input("Page size must be between 1 and 500"));
     }
 
     // Get all trades from state
@@ -622,18 +2041,19 @@ fn admin_get_trades_audit(params: types::AuditQueryParams) -> Result<types::Trad
                 order_id: trade.order_id,
                 maker,
                 filler: trade.filler,
-                amount_usd: trade.amount_usd,
+                amount_usd: e6_to_usd(trade.amount_usd_e6),
                 chunks_count: trade.locked_chunks.len(),
                 agreed_bsv_price: trade.agreed_bsv_price,
                 min_bsv_price: trade.min_bsv_price,
                 status: trade.status,
-                bsv_tx_hex: trade.bsv_tx_hex,
+                bsv_txid: trade.bsv_txid,
                 maker_bsv_address,
                 created_at: trade.created_at,
                 tx_submitted_at: trade.tx_submitted_at,
                 lock_expires_at: trade.lock_expires_at,
                 release_available_at: trade.release_available_at,
                 claim_expires_at: trade.claim_expires_at,
+                required_confirmations: trade.required_confirmations,
                 withdrawal_initiated_at: trade.withdrawal_initiated_at,
                 withdrawal_tx_hash: trade.withdrawal_tx_hash,
                 withdrawal_confirmed_at: trade.withdrawal_confirmed_at,
@@ -656,6 +2076,174 @@ fn get_recent_blocks(count: u64) -> BlocksWithMetadata {
     block_headers::get_recent_blocks(count)
 }
 
+/// Headers from from_height to to_height inclusive, ascending - at most 100 per call
+#[query]
+fn get_block_headers_range(from_height: u64, to_height: u64) -> Vec<block_headers::BlockHeader> {
+    block_headers::get_block_headers_range(from_height, to_height)
+}
+
+// ===== API VERSIONING =====
+// Endpoints above are the v1 surface - their records are pinned in v1.rs and checked against the
+// deployed shapes by candid_interface_tests. New signatures go below as v2_* (see api_versions.rs)
+
+/// Current API version, plus version and deprecation status of every versioned endpoint
+#[query]
+fn get_api_versions() -> types::ApiVersionInfo {
+    api_versions::get_api_versions()
+}
+
+#[query]
+fn v2_get_orderbook_stats() -> types::OrderbookStatsV2 {
+    api_versions::get_orderbook_stats()
+}
+
+/// Available orderbook liquidity in micro-USD
+#[query]
+fn v2_get_available_orderbook() -> types::UsdE6 {
+    api_versions::get_available_orderbook()
+}
+
+/// BSV/USD oracle price in cents
+#[update]
+async fn v2_get_bsv_price() -> Result<u64, EasySwapError> {
+    api_versions::get_bsv_price().await
+}
+
+/// Default filler incentive in basis points (450 = 4.5%) - orders may offer their own
+#[query]
+fn v2_get_filler_incentive_bps() -> u64 {
+    api_versions::get_filler_incentive_bps()
+}
+
+/// Create an order offering fillers `filler_incentive_bps` (None = the default), within
+/// the bounds from get_filler_incentive_bounds
+#[update]
+async fn v2_create_order(
+    amount_usd: f64,
+    max_bsv_price: f64,
+    bsv_address: String,
+    filler_incentive_bps: Option<u64>,
+) -> Result<OrderId, EasySwapError> {
+    validation::bsv_address(&bsv_address)?;
+    order_management::create_order(amount_usd, max_bsv_price, bsv_address, filler_incentive_bps).await
+}
+
+/// get_order with micro-USD amounts
+#[query]
+fn v2_get_order(order_id: OrderId) -> Option<Order> {
+    visible_order(order_id)
+}
+
+#[query]
+fn v2_get_my_orders() -> Vec<Order> {
+    let maker = delegations::acting_principal(ic_cdk::caller(), types::OperatorScope::OrdersRead);
+    order_management::get_my_orders(maker)
+}
+
+/// get_trade with micro-USD amounts
+#[query]
+fn v2_get_trade(trade_id: TradeId) -> Option<Trade> {
+    visible_trade(trade_id)
+}
+
+#[query]
+fn v2_get_my_trades() -> Vec<Trade> {
+    trade_lifecycle::get_my_trades()
+}
+
+/// Listed chunks of a book with micro-USD amounts - None = the default ckUSDC book
+#[query]
+fn v2_get_active_chunks(book_id: Option<types::BookId>) -> Vec<OrderbookChunk> {
+    chunk_allocation::get_book_active_chunks(book_id.unwrap_or(books::DEFAULT_BOOK_ID))
+}
+
+// ===== LOCAL DEVELOPMENT (local-dev builds only) =====
+// Seed the in-canister ledger, price feeds and header providers that replace ckUSDC and the
+// HTTPS outcalls in these builds (see runtime::mock) - never part of the deployed interface
+// The admin or a controller (the dfx identity that deployed the canister) may call them
+
+#[cfg(feature = "local-dev")]
+fn check_dev_caller() -> Result<(), EasySwapError> {
+    let caller = ic_cdk::caller();
+    if caller != state::get_admin() && !ic_cdk::api::is_controller(&caller) {
+        return Err(EasySwapError::unauthorized("Only admin or a controller can seed local-dev services"));
+    }
+    Ok(())
+}
+
+/// Credit ckUSDC to any account, e.g. a maker's or filler's deposit subaccount
+#[cfg(feature = "local-dev")]
+#[update]
+fn admin_dev_mint(account: icrc_ledger_types::icrc1::account::Account, amount_e6: u64) -> Result<(), EasySwapError> {
+    check_dev_caller()?;
+    runtime::mock::mint(account, amount_e6 as u128);
+    Ok(())
+}
+
+/// Credit a token of another ledger (collateral, other books' settlement tokens)
+#[cfg(feature = "local-dev")]
+#[update]
+fn admin_dev_mint_token(ledger: Principal, account: icrc_ledger_types::icrc1::account::Account, amount: u64) -> Result<(), EasySwapError> {
+    check_dev_caller()?;
+    runtime::mock::mint_token(ledger, account, amount as u128);
+    Ok(())
+}
+
+/// ICRC-2 approval of ckUSDC from `owner` to this canister, for allowance-funded orders
+#[cfg(feature = "local-dev")]
+#[update]
+fn admin_dev_approve(owner: icrc_ledger_types::icrc1::account::Account, amount_e6: u64) -> Result<(), EasySwapError> {
+    check_dev_caller()?;
+    runtime::mock::approve(owner, ic_cdk::api::id(), amount_e6 as u128);
+    Ok(())
+}
+
+/// BSV/USD quotes served by the Xrc, WhatsOnChain and Coinlore feeds, in that order
+#[cfg(feature = "local-dev")]
+#[update]
+fn admin_dev_set_prices(prices: Vec<f64>) -> Result<(), EasySwapError> {
+    check_dev_caller()?;
+    runtime::mock::set_prices(&prices);
+    Ok(())
+}
+
+/// USD rate of a collateral asset, by its XRC symbol
+#[cfg(feature = "local-dev")]
+#[update]
+fn admin_dev_set_usd_rate(symbol: String, rate: f64) -> Result<(), EasySwapError> {
+    check_dev_caller()?;
+    runtime::mock::set_usd_rate(&symbol, rate);
+    Ok(())
+}
+
+/// Replace the chain every header provider agrees on - block sync picks it up on its next run
+#[cfg(feature = "local-dev")]
+#[update]
+fn admin_dev_set_chain(blocks: Vec<BlockHeader>) -> Result<(), EasySwapError> {
+    check_dev_caller()?;
+    runtime::mock::set_chain(blocks);
+    Ok(())
+}
+
+/// Raw transaction the providers return for `txid` - the parents of submitted payments
+#[cfg(feature = "local-dev")]
+#[update]
+fn admin_dev_add_raw_tx(txid: String, raw_hex: String) -> Result<(), EasySwapError> {
+    check_dev_caller()?;
+    runtime::mock::add_raw_tx(&txid, &raw_hex);
+    Ok(())
+}
+
+/// Balance of an account on the local-dev ledger - None = ckUSDC
+#[cfg(feature = "local-dev")]
+#[query]
+fn dev_balance_of(ledger: Option<Principal>, account: icrc_ledger_types::icrc1::account::Account) -> u64 {
+    match ledger {
+        Some(ledger) => runtime::mock::token_balance(ledger, account) as u64,
+        None => runtime::mock::balance(account) as u64,
+    }
+}
+
 // Note: Block sync, verification, and other SPV functions are internal only
 // They are used automatically by claim_usdc and the background timer
 // No public access needed for security
@@ -0,0 +1,1763 @@
+//! Order -> trade -> claim and block sync run end to end against the runtime::mock ledger,
+//! price feeds and header providers
+use crate::block_headers::{get_block_by_height, get_highest_block, BlockHeader, CONFIRMATION_DEPTH};
+use crate::config::{CKUSDC_TRANSFER_FEE, USDC_RELEASE_WAIT_NS, FILLER_INCENTIVE_PERCENT, LOCK_EXTENSION_NS, LOCK_EXTENSION_FEE_PERCENT, TRADE_TIMEOUT_NS};
+use crate::runtime::mock::{self, block_on};
+use crate::state::*;
+use crate::trade_lifecycle::CreateTradesRequest;
+use crate::types::*;
+use candid::Principal;
+use icrc_ledger_types::icrc1::account::Account;
+use sha2::{Digest, Sha256};
+
+const MAKER_HASH160: [u8; 20] = [0x11; 20];
+const CHANGE_HASH160: [u8; 20] = [0x22; 20];
+const BSV_PRICE: f64 = 50.0;
+
+// ===== FIXTURES =====
+
+fn maker() -> Principal {
+    Principal::from_slice(&[1, 1, 1])
+}
+
+fn filler() -> Principal {
+    Principal::from_slice(&[2, 2, 2])
+}
+
+fn double_sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// Display (reversed) hex of a double-SHA256 digest
+fn display_hex(mut digest: [u8; 32]) -> String {
+    digest.reverse();
+    hex::encode(digest)
+}
+
+fn internal_bytes(display: &str) -> Vec<u8> {
+    let mut bytes = hex::decode(display).expect("valid hex");
+    bytes.reverse();
+    bytes
+}
+
+fn base58_check(version: u8, hash160: &[u8; 20]) -> String {
+    let mut data = vec![version];
+    data.extend_from_slice(hash160);
+    let checksum = double_sha256(&data);
+    data.extend_from_slice(&checksum[..4]);
+    bs58::encode(data).into_string()
+}
+
+pub(crate) fn p2pkh_address(hash160: &[u8; 20]) -> String {
+    base58_check(BsvNetwork::Mainnet.p2pkh_version(), hash160)
+}
+
+fn testnet_p2pkh_address(hash160: &[u8; 20]) -> String {
+    base58_check(BsvNetwork::Testnet.p2pkh_version(), hash160)
+}
+
+/// Raw tx hex spending (txid, vout) inputs into P2PKH outputs
+fn tx_hex(inputs: &[(&str, u32)], outputs: &[(u64, [u8; 20])]) -> String {
+    let mut tx = 1u32.to_le_bytes().to_vec();
+    tx.push(inputs.len() as u8);
+    for (txid, vout) in inputs {
+        tx.extend(internal_bytes(txid));
+        tx.extend(vout.to_le_bytes());
+        tx.push(107);
+        tx.extend([0x30; 107]); // Signature-sized placeholder, never checked
+        tx.extend(u32::MAX.to_le_bytes());
+    }
+    tx.push(outputs.len() as u8);
+    for (sats, hash160) in outputs {
+        tx.extend(sats.to_le_bytes());
+        tx.extend([25, 0x76, 0xa9, 0x14]);
+        tx.extend(hash160);
+        tx.extend([0x88, 0xac]);
+    }
+    tx.extend(0u32.to_le_bytes());
+    hex::encode(tx)
+}
+
+/// Block with a real 80-byte header, so validate_chain accepts its hash
+fn block(height: u64, previous_hash: &str, merkle_root: &str, nonce: u32) -> BlockHeader {
+    let mut raw = 4i32.to_le_bytes().to_vec();
+    raw.extend(internal_bytes(previous_hash));
+    raw.extend(internal_bytes(merkle_root));
+    raw.extend((1_700_000_000 + height as u32).to_le_bytes());
+    raw.extend(0x1800_0000u32.to_le_bytes());
+    raw.extend(nonce.to_le_bytes());
+    BlockHeader {
+        height,
+        hash: display_hex(double_sha256(&raw)),
+        previous_hash: previous_hash.to_string(),
+        merkle_root: merkle_root.to_string(),
+        timestamp: 1_700_000_000 + height,
+        bits: 0x1800_0000,
+        nonce,
+        version: 4,
+        raw_header: hex::encode(raw),
+    }
+}
+
+/// Append count blocks to chain (starting at start_height when empty)
+/// Blocks listed in merkle_roots get that root, the rest a filler root derived from height and nonce
+fn extend_chain(chain: &mut Vec<BlockHeader>, start_height: u64, count: u64, nonce: u32, merkle_roots: &[(u64, String)]) {
+    for _ in 0..count {
+        let (height, previous_hash) = match chain.last() {
+            Some(tip) => (tip.height + 1, tip.hash.clone()),
+            None => (start_height, "00".repeat(32)),
+        };
+        let merkle_root = merkle_roots.iter()
+            .find(|(h, _)| *h == height)
+            .map(|(_, root)| root.clone())
+            .unwrap_or_else(|| display_hex(double_sha256(&[height.to_le_bytes(), (nonce as u64).to_le_bytes()].concat())));
+        chain.push(block(height, &previous_hash, &merkle_root, nonce));
+    }
+}
+
+/// BRC-74 BUMP for the only transaction of a block: one level, txid at offset 0 and a
+/// duplicate at offset 1
+/// Returns (bump hex, merkle root)
+fn single_tx_bump(block_height: u64, txid: &str) -> (String, String) {
+    let mut bump = vec![0xfe];
+    bump.extend((block_height as u32).to_le_bytes());
+    bump.extend([1, 2, 0, 2]);
+    bump.extend(internal_bytes(txid));
+    bump.extend([1, 1]);
+    let leaf = internal_bytes(txid);
+    let root = display_hex(double_sha256(&[leaf.clone(), leaf].concat()));
+    (hex::encode(bump), root)
+}
+
+fn account(owner: Principal, subaccount: Option<[u8; 32]>) -> Account {
+    Account { owner, subaccount }
+}
+
+// ===== ORDER -> TRADE -> CLAIM =====
+
+#[test]
+fn order_trade_claim_lifecycle() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+
+    // Maker's activation deposit comes from their shared deposit subaccount
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let order_id = block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None))
+        .expect("order is created and activated");
+
+    let order = get_order(order_id).expect("order stored");
+    assert_eq!(order.status, OrderStatus::Active);
+    assert_eq!(order.chunks.len(), 2);
+    let order_account = crate::ckusdc_integration::get_order_deposit_account(maker(), order_id);
+    // Activation fee went to treasury - what's left is $6 + the default filler incentive
+    assert_eq!(order.filler_incentive_bps, Some(FILLER_INCENTIVE_PERCENT));
+    let order_balance = 6_000_000 + bps_of(6_000_000, FILLER_INCENTIVE_PERCENT);
+    assert_eq!(mock::balance(order_account), order_balance as u128);
+    assert_eq!(get_available_orderbook(), 6_000_000);
+
+    // Filler locks both chunks
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .expect("trade is created");
+    assert_eq!(trade_ids.len(), 1);
+    let trade = get_trade(trade_ids[0]).expect("trade stored");
+    assert_eq!(trade.status, TradeStatus::ChunksLocked);
+    assert_eq!(trade.agreed_bsv_price, BSV_PRICE);
+    assert_eq!(get_available_orderbook(), 0);
+
+    // Filler pays the maker on-chain, funded by a parent the header provider knows about
+    let parent_hex = tx_hex(&[(&"ab".repeat(32), 0)], &[(20_000_000, CHANGE_HASH160)]);
+    let parent_txid = compute_bsv_txid(&parent_hex).unwrap();
+    mock::add_raw_tx(&parent_txid, &parent_hex);
+    let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+    let mut outputs: Vec<(u64, [u8; 20])> = trade.locked_chunks.iter().map(|c| (c.sats_amount, MAKER_HASH160)).collect();
+    outputs.push((20_000_000 - paid - 1_000, CHANGE_HASH160));
+    let payment_hex = tx_hex(&[(&parent_txid, 0)], &outputs);
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade.id, payment_hex.clone()))
+        .expect("payment accepted");
+    let submitted = get_trade(trade.id).unwrap();
+    assert_eq!(submitted.status, TradeStatus::TxSubmitted);
+    assert_eq!(get_trade_tx_hex(&submitted), Some(payment_hex.clone()));
+
+    // Payment is mined and buried under CONFIRMATION_DEPTH blocks, which block sync picks up
+    let payment_txid = compute_bsv_txid(&payment_hex).unwrap();
+    let mined_at = 800_300;
+    let (bump_hex, merkle_root) = single_tx_bump(mined_at, &payment_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, mined_at - 800_000 + CONFIRMATION_DEPTH, 0, &[(mined_at, merkle_root)]);
+    mock::set_chain(chain.clone());
+    block_on(crate::chain_sync::sync_blocks()).expect("headers synced");
+
+    // Too early - the release wait hasn't passed
+    let early = block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex.clone(), bump_hex.clone()));
+    assert!(early.is_err());
+
+    // By now the tip is hours old - with the providers unreachable it can't be refreshed
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+    mock::set_chain(Vec::new());
+    let stale = block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex.clone(), bump_hex.clone()));
+    assert!(matches!(stale, Err(EasySwapError::ServiceUnavailable { .. })), "{:?}", stale);
+
+    // Reachable again - the claim syncs on demand and goes through
+    mock::set_chain(chain);
+    block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex, bump_hex)).expect("claim paid out");
+
+    let trade = get_trade(trade.id).unwrap();
+    assert_eq!(trade.status, TradeStatus::WithdrawalConfirmed);
+    assert_eq!(trade.bsv_block_height, Some(mined_at));
+    for locked in &trade.locked_chunks {
+        assert_eq!(get_chunk(locked.chunk_id).unwrap().status, ChunkStatus::Filled);
+    }
+    // $6 + filler incentive, less the ledger fee; the order subaccount is emptied
+    assert_eq!(mock::balance(account(filler(), None)), order_balance as u128 - CKUSDC_TRANSFER_FEE);
+    assert_eq!(mock::balance(order_account), 0);
+    assert_eq!(get_filler_account(filler()).unwrap().successful_trades, 1);
+
+    let tape = crate::market_data::get_recent_trades_public(10);
+    assert_eq!(tape.len(), 1);
+    assert_eq!((tape[0].amount_usd_e6, tape[0].bsv_price), (trade.amount_usd_e6, trade.agreed_bsv_price));
+}
+
+#[test]
+fn claim_on_a_stale_tip_is_refused_until_the_tip_is_refreshed() {
+    use crate::chain_sync::ensure_fresh_tip;
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    let trade = get_trade(trade_ids[0]).unwrap();
+    let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+    let payment_hex = tx_hex(&[(&"cd".repeat(32), 0)], &[(paid, MAKER_HASH160), (5_000, CHANGE_HASH160)]);
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade.id, payment_hex.clone())).unwrap();
+
+    // Buried deep enough by the last sync
+    let payment_txid = compute_bsv_txid(&payment_hex).unwrap();
+    let mined_at = 800_300;
+    let (bump_hex, merkle_root) = single_tx_bump(mined_at, &payment_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, mined_at - 800_000 + CONFIRMATION_DEPTH, 0, &[(mined_at, merkle_root)]);
+    mock::set_chain(chain.clone());
+    block_on(crate::chain_sync::sync_blocks()).expect("headers synced");
+
+    // A fresh tip is trusted as is, even with the providers unreachable
+    mock::set_chain(Vec::new());
+    assert!(block_on(ensure_fresh_tip()).is_ok());
+
+    // Hours later the tip is stale and can't be refreshed - the claim is refused, nothing moves
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+    assert!(matches!(block_on(ensure_fresh_tip()), Err(EasySwapError::ServiceUnavailable { .. })));
+    let stale = block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex.clone(), bump_hex.clone()));
+    assert!(matches!(stale, Err(EasySwapError::ServiceUnavailable { .. })), "{:?}", stale);
+    assert_eq!(get_trade(trade.id).unwrap().status, TradeStatus::TxSubmitted);
+    assert_eq!(mock::balance(account(filler(), None)), 0);
+
+    // Meanwhile a longer branch replaced the payment's block - the claim syncs it first and
+    // no longer counts the confirmations the stale tip showed
+    chain.truncate(300);
+    extend_chain(&mut chain, 0, CONFIRMATION_DEPTH + 3, 1, &[]);
+    mock::set_chain(chain.clone());
+    let reorged = block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex, bump_hex));
+    assert!(matches!(reorged, Err(EasySwapError::SpvFailed { .. })), "{:?}", reorged);
+    assert_eq!(get_block_by_height(get_highest_block()).unwrap().hash, chain.last().unwrap().hash);
+    assert_eq!(get_trade(trade.id).unwrap().status, TradeStatus::TxSubmitted);
+    assert_eq!(mock::balance(account(filler(), None)), 0);
+}
+
+#[test]
+fn overlapping_single_and_batch_claims_pay_a_trade_once() {
+    use crate::trade_lifecycle::{claim_usdc, claim_usdc_batch, ClaimGuard};
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let order_id = block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    let order_account = crate::ckusdc_integration::get_order_deposit_account(maker(), order_id);
+    let order_balance = mock::balance(order_account);
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    let trade = get_trade(trade_ids[0]).unwrap();
+    let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+    let payment_hex = tx_hex(&[(&"ef".repeat(32), 0)], &[(paid, MAKER_HASH160), (5_000, CHANGE_HASH160)]);
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade.id, payment_hex.clone())).unwrap();
+
+    let payment_txid = compute_bsv_txid(&payment_hex).unwrap();
+    let mined_at = 800_300;
+    let (bump_hex, merkle_root) = single_tx_bump(mined_at, &payment_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, mined_at - 800_000 + CONFIRMATION_DEPTH, 0, &[(mined_at, merkle_root)]);
+    mock::set_chain(chain);
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+
+    // A single claim is still awaiting its verification - a batch claim of the same trade is refused
+    let in_flight = ClaimGuard::acquire(trade.id).expect("first claim takes the trade");
+    let batch = block_on(claim_usdc_batch(filler(), vec![(trade.id, payment_hex.clone(), bump_hex.clone())])).unwrap();
+    assert!(matches!(batch[0], Err(EasySwapError::InvalidState { .. })), "{:?}", batch);
+    assert!(ClaimGuard::acquire(trade.id).is_err());
+    assert_eq!(mock::balance(account(filler(), None)), 0);
+    drop(in_flight);
+
+    // Once it settles the trade can't be claimed again under either memo
+    block_on(claim_usdc(filler(), trade.id, payment_hex.clone(), bump_hex.clone())).expect("claim paid out");
+    let batch = block_on(claim_usdc_batch(filler(), vec![(trade.id, payment_hex.clone(), bump_hex.clone())])).unwrap();
+    assert!(batch[0].is_err());
+    assert!(block_on(claim_usdc(filler(), trade.id, payment_hex, bump_hex)).is_err());
+    assert_eq!(mock::balance(account(filler(), None)), order_balance - CKUSDC_TRANSFER_FEE);
+    assert_eq!(mock::balance(order_account), 0);
+}
+
+#[test]
+fn one_payment_covers_a_trade_set() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    for _ in 0..2 {
+        block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    }
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 2_000_000);
+    mock::set_caller(filler());
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 12.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    assert_eq!(trade_ids.len(), 2);
+
+    // Both orders pay the same address - four chunks become one output
+    let template = crate::trade_lifecycle::get_payment_template(trade_ids.clone()).unwrap();
+    let chunk_sats: u64 = trade_ids.iter()
+        .flat_map(|id| get_trade(*id).unwrap().locked_chunks)
+        .map(|chunk| chunk.sats_amount)
+        .sum();
+    assert_eq!(template.outputs, vec![PaymentOutput { bsv_address: p2pkh_address(&MAKER_HASH160), sats: chunk_sats }]);
+
+    let payment_hex = tx_hex(&[(&"ef".repeat(32), 0)], &[(template.total_sats, MAKER_HASH160), (5_000, CHANGE_HASH160)]);
+    let single = block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade_ids[0], payment_hex.clone()));
+    assert!(single.is_err(), "pays more than one trade's chunks");
+    block_on(crate::trade_lifecycle::submit_bsv_transaction_for_trades(filler(), trade_ids.clone(), payment_hex.clone())).unwrap();
+    for id in &trade_ids {
+        assert_eq!(get_trade(*id).unwrap().payment_group, Some(trade_ids.clone()));
+    }
+    assert!(crate::invariants::check_invariants().is_empty());
+
+    let payment_txid = compute_bsv_txid(&payment_hex).unwrap();
+    let (bump_hex, merkle_root) = single_tx_bump(800_300, &payment_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, 300 + CONFIRMATION_DEPTH, 0, &[(800_300, merkle_root)]);
+    mock::set_chain(chain);
+    block_on(crate::chain_sync::sync_blocks()).unwrap();
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+
+    for id in &trade_ids {
+        block_on(crate::trade_lifecycle::claim_usdc(filler(), *id, payment_hex.clone(), bump_hex.clone())).expect("each trade claims with the shared payment");
+        assert_eq!(get_trade(*id).unwrap().status, TradeStatus::WithdrawalConfirmed);
+    }
+}
+
+#[test]
+fn filler_extends_trade_lock_once_for_a_fee() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap()[0];
+    let locked_until = get_trade(trade_id).unwrap().lock_expires_at;
+
+    let extended_until = block_on(crate::trade_lifecycle::extend_trade_lock(trade_id)).unwrap();
+    assert_eq!(extended_until, locked_until + LOCK_EXTENSION_NS);
+    assert_eq!(get_trade(trade_id).unwrap().lock_expires_at, extended_until);
+    // Fee goes to the maker, less the ledger fee
+    let fee_e6 = bps_of(6_000_000, LOCK_EXTENSION_FEE_PERCENT) as u128;
+    assert_eq!(mock::balance(account(maker(), None)), fee_e6 - CKUSDC_TRANSFER_FEE);
+
+    let again = block_on(crate::trade_lifecycle::extend_trade_lock(trade_id));
+    assert!(matches!(again, Err(EasySwapError::InvalidState { .. })));
+}
+
+#[test]
+fn timeout_penalty_grows_with_how_late_the_trade_expired() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    let expire_after = |late_ns: u64| {
+        mock::set_caller(filler());
+        let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        }))
+        .unwrap()[0];
+        mock::advance_time(TRADE_TIMEOUT_NS + late_ns);
+        block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+        assert_eq!(get_trade(trade_id).unwrap().status, TradeStatus::PenaltyApplied);
+    };
+
+    // Penalties go to the maker, less the ledger fee
+    let schedule = get_timeout_penalty_schedule();
+    expire_after(60 * 1_000_000_000);
+    let early_e6 = bps_of(3_000_000, schedule.tiers[0].penalty_bps) as u128 - CKUSDC_TRANSFER_FEE;
+    assert_eq!(mock::balance(account(maker(), None)), early_e6);
+
+    expire_after(60 * 60 * 1_000_000_000);
+    let late_e6 = bps_of(3_000_000, schedule.final_penalty_bps) as u128 - CKUSDC_TRANSFER_FEE;
+    assert_eq!(mock::balance(account(maker(), None)), early_e6 + late_e6);
+
+    let over_deposit = TimeoutPenaltySchedule { tiers: vec![], final_penalty_bps: 2_000 };
+    assert!(matches!(crate::trade_lifecycle::set_timeout_penalty_schedule(over_deposit), Err(EasySwapError::InvalidInput { .. })));
+}
+
+#[test]
+fn cancelled_order_is_finalized_once_its_locked_trade_resolves() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    let order_id = block_on(crate::order_management::create_order(9.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap()[0];
+
+    // Only the free chunk is refunded - the locked ones stay reserved for the trade
+    mock::set_caller(maker());
+    block_on(crate::order_management::cancel_order(order_id)).unwrap();
+    let order = get_order(order_id).unwrap();
+    assert_eq!(order.status, OrderStatus::PartiallyFilled);
+    assert!(order.finalization_pending_since.is_some());
+    assert!(block_on(crate::ckusdc_integration::get_order_ckusdc_balance(maker(), order_id)).unwrap() > 6_000_000);
+    let refunds = crate::order_management::get_my_refunds();
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds[0].refund.status, RefundStatus::Confirmed);
+    assert_eq!(refunds[0].refund.chunk_ids.len(), 1);
+
+    // The trade times out; its chunks don't return to the book, and the order is closed out
+    mock::advance_time(TRADE_TIMEOUT_NS + 1);
+    block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+    assert_eq!(get_trade(trade_id).unwrap().status, TradeStatus::PenaltyApplied);
+    assert_eq!(get_available_orderbook(), 0);
+
+    let order = get_order(order_id).unwrap();
+    assert_eq!(order.status, OrderStatus::Cancelled);
+    assert!(order.finalization_pending_since.is_none());
+    assert!(order.chunks.iter().all(|id| get_chunk(*id).unwrap().status == ChunkStatus::Refunded));
+    assert_eq!(block_on(crate::ckusdc_integration::get_order_ckusdc_balance(maker(), order_id)).unwrap(), 0);
+    assert!(crate::invariants::check_invariants().is_empty());
+
+    // Both refunds are tracked, and what reached the maker is what the order counts as refunded
+    mock::set_caller(maker());
+    let refunds = crate::order_management::get_my_refunds();
+    assert_eq!(refunds.len(), 2);
+    assert!(refunds.iter().all(|r| r.order_id == order_id && r.refund.status == RefundStatus::Confirmed));
+    assert_eq!(refunds[0].refund.chunk_ids.len(), 2);
+    let refunded_e6: UsdE6 = refunds.iter().map(|r| r.refund.total_amount_e6).sum();
+    assert_eq!(order.total_refunded_usd_e6, Some(refunded_e6));
+}
+
+#[test]
+fn failed_refund_is_queued_and_its_attempt_confirmed_once_the_retry_lands() {
+    use crate::config::PENDING_TRANSFER_BASE_DELAY_SECONDS;
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let order_id = block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    // The ledger rejects the refund - the cancel still goes through and the refund is queued
+    mock::set_ledger_down(true);
+    block_on(crate::order_management::cancel_order(order_id)).expect("order cancelled");
+    assert_eq!(get_order(order_id).unwrap().status, OrderStatus::Cancelled);
+    let refunds = crate::order_management::get_my_refunds();
+    assert_eq!(refunds.len(), 1);
+    let refund = &refunds[0].refund;
+    assert_eq!((refund.refund_id, refund.status.clone()), (1, RefundStatus::Pending));
+    assert_eq!(refund.chunk_ids.len(), 2);
+    assert!(refund.pending_transfer_id.is_some() && refund.last_error.is_some());
+    assert_eq!((refund.block_index, refund.confirmed_at), (None, None));
+    assert_eq!(get_order(order_id).unwrap().total_refunded_usd_e6, None);
+
+    // A retry that fails again leaves the attempt Pending with the latest error
+    mock::advance_time(PENDING_TRANSFER_BASE_DELAY_SECONDS * 1_000_000_000);
+    assert_eq!(block_on(crate::pending_transfers::process_pending_transfers()), 0);
+    assert_eq!(crate::order_management::get_my_refunds()[0].refund.status, RefundStatus::Pending);
+
+    // Once it lands the attempt is confirmed and counted as refunded, once
+    mock::set_ledger_down(false);
+    mock::advance_time(2 * PENDING_TRANSFER_BASE_DELAY_SECONDS * 1_000_000_000);
+    assert_eq!(block_on(crate::pending_transfers::process_pending_transfers()), 1);
+    let refund = crate::order_management::get_my_refunds()[0].refund.clone();
+    assert_eq!(refund.status, RefundStatus::Confirmed);
+    assert_eq!(refund.last_error, None);
+    assert!(refund.block_index.is_some() && refund.confirmed_at.is_some());
+    assert_eq!(get_order(order_id).unwrap().total_refunded_usd_e6, Some(refund.total_amount_e6));
+    assert_eq!(mock::balance(account(maker(), None)), refund.total_amount_e6 as u128 - CKUSDC_TRANSFER_FEE);
+
+    assert_eq!(block_on(crate::pending_transfers::process_pending_transfers()), 0);
+    assert_eq!(get_order(order_id).unwrap().total_refunded_usd_e6, Some(refund.total_amount_e6));
+}
+
+#[test]
+fn security_breakdown_matches_lock_capacity_check() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(12.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let request = |requested_usd| CreateTradesRequest {
+        requested_usd,
+        allow_partial: false,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    };
+    block_on(crate::trade_lifecycle::create_trades(get_caller(), request(6.0))).unwrap();
+
+    let breakdown = block_on(crate::filler_accounts::get_my_security_breakdown()).unwrap();
+    assert_eq!(breakdown.total_balance_e6, 1_000_000);
+    assert_eq!(breakdown.pending_trades_e6, 6_000_000);
+    assert_eq!(breakdown.locked_e6, 600_000);
+    assert_eq!(breakdown.available_e6, 400_000);
+    assert_eq!(breakdown.max_lock_capacity_e6, 10_000_000);
+    assert_eq!(breakdown.remaining_lock_capacity_e6, 4_000_000);
+
+    // Asking for more than the remaining capacity is what create_trades rejects
+    let too_much = block_on(crate::trade_lifecycle::create_trades(get_caller(), request(6.0)));
+    assert!(matches!(too_much, Err(EasySwapError::LimitExceeded { .. })));
+    block_on(crate::trade_lifecycle::create_trades(get_caller(), request(3.0))).unwrap();
+}
+
+#[test]
+fn collateral_backs_trades_at_its_haircut_and_pays_penalties() {
+    let ckbtc = Principal::from_slice(&[9, 9]);
+    crate::filler_accounts::set_collateral_token(CollateralToken {
+        symbol: "ckBTC".to_string(),
+        ledger: ckbtc,
+        decimals: 8,
+        transfer_fee: 10,
+        price_symbol: Some("BTC".to_string()),
+        credit_bps: 8_000,
+        disabled: None,
+    })
+    .unwrap();
+    mock::set_usd_rate("BTC", 50_000.0);
+
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(12.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    // $1.25 of ckBTC and no ckUSDC - credited $1, so $10 of lock capacity
+    let filler_deposit = crate::filler_accounts::get_deposit_account(filler());
+    mock::mint_token(ckbtc, filler_deposit, 2_500);
+    mock::set_caller(filler());
+    let breakdown = block_on(crate::filler_accounts::get_my_security_breakdown()).unwrap();
+    assert_eq!(breakdown.ckusdc_balance_e6, 0);
+    assert_eq!(breakdown.collateral[0].usd_value_e6, 1_250_000);
+    assert_eq!(breakdown.total_balance_e6, 1_000_000);
+    assert_eq!(breakdown.max_lock_capacity_e6, 10_000_000);
+
+    let request = |requested_usd| CreateTradesRequest {
+        requested_usd,
+        allow_partial: false,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    };
+    assert!(block_on(crate::trade_lifecycle::create_trades(get_caller(), request(12.0))).is_err());
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), request(6.0))).unwrap()[0];
+
+    // The collateral can't be withdrawn from under the open trade
+    let withdraw = block_on(crate::filler_accounts::withdraw_collateral("ckBTC".to_string(), 2_000, filler()));
+    assert!(matches!(withdraw, Err(EasySwapError::InsufficientFunds { .. })));
+
+    // With no ckUSDC to take it from, the timeout penalty is paid to the maker in ckBTC
+    mock::advance_time(TRADE_TIMEOUT_NS + 1);
+    block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+    assert_eq!(get_trade(trade_id).unwrap().status, TradeStatus::PenaltyApplied);
+    let paid = mock::token_balance(ckbtc, account(maker(), None));
+    assert!(paid > 0);
+    assert_eq!(mock::token_balance(ckbtc, filler_deposit), 2_500 - paid - 10);
+    assert!(get_pending_transfers_by_status(PendingTransferStatus::Pending).is_empty());
+}
+
+#[test]
+fn trusted_filler_locks_more_until_penalized() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(15.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let request = |requested_usd| CreateTradesRequest {
+        requested_usd,
+        allow_partial: false,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    };
+    // $1 of security covers $10 on the standard deposit
+    assert!(block_on(crate::trade_lifecycle::create_trades(get_caller(), request(12.0))).is_err());
+
+    update_filler_account(filler(), |account| {
+        account.successful_trades = crate::config::TRUSTED_FILLER_MIN_SUCCESSFUL_TRADES;
+    })
+    .unwrap();
+    let breakdown = block_on(crate::filler_accounts::get_my_security_breakdown()).unwrap();
+    assert!(breakdown.trusted);
+    assert_eq!(breakdown.max_lock_capacity_e6, 20_000_000);
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), request(12.0))).unwrap()[0];
+
+    // Letting it time out costs the trusted tier
+    mock::advance_time(TRADE_TIMEOUT_NS + 1);
+    block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+    assert_eq!(get_trade(trade_id).unwrap().status, TradeStatus::PenaltyApplied);
+    let breakdown = block_on(crate::filler_accounts::get_my_security_breakdown()).unwrap();
+    assert!(!breakdown.trusted);
+    assert_eq!(breakdown.security_deposit_percent, crate::config::SECURITY_DEPOSIT_PERCENT);
+    assert!(block_on(crate::trade_lifecycle::create_trades(get_caller(), request(12.0))).is_err());
+}
+
+#[test]
+fn reserved_chunks_are_held_for_the_reserving_filler_until_they_expire() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(9.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    let rival = Principal::from_slice(&[2, 2, 3]);
+    for who in [filler(), rival] {
+        mock::mint(crate::filler_accounts::get_deposit_account(who), 1_000_000);
+    }
+    let request = |requested_usd| CreateTradesRequest {
+        requested_usd,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    };
+
+    mock::set_caller(filler());
+    assert!(block_on(crate::trade_lifecycle::reserve_chunks(filler(), 3.0, 600, None)).is_err());
+    let reservation = block_on(crate::trade_lifecycle::reserve_chunks(filler(), 3.0, 60, None)).unwrap();
+    assert_eq!(reservation.chunk_ids.len(), 1);
+    assert_eq!(reservation.amount_usd_e6, 3_000_000);
+
+    // Still on the book, but the rival's fill goes around it
+    assert_eq!(get_available_orderbook(), 9_000_000);
+    mock::set_caller(rival);
+    let rival_trade = block_on(crate::trade_lifecycle::create_trades(get_caller(), request(3.0))).unwrap()[0];
+    assert!(!get_trade(rival_trade).unwrap().locked_chunks.iter().any(|c| c.chunk_id == reservation.chunk_ids[0]));
+
+    mock::set_caller(filler());
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), request(3.0))).unwrap()[0];
+    assert_eq!(get_trade(trade_id).unwrap().locked_chunks[0].chunk_id, reservation.chunk_ids[0]);
+    assert_eq!(get_chunk(reservation.chunk_ids[0]).unwrap().reserved_by, None);
+
+    // A lapsed reservation no longer holds the chunk, and the heartbeat clears it
+    let reservation = block_on(crate::trade_lifecycle::reserve_chunks(filler(), 3.0, 60, None)).unwrap();
+    mock::advance_time(61 * 1_000_000_000);
+    block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+    assert_eq!(get_chunk(reservation.chunk_ids[0]).unwrap().reserved_by, None);
+    mock::set_caller(rival);
+    let rival_trade = block_on(crate::trade_lifecycle::create_trades(get_caller(), request(3.0))).unwrap()[0];
+    assert_eq!(get_trade(rival_trade).unwrap().locked_chunks[0].chunk_id, reservation.chunk_ids[0]);
+}
+
+#[test]
+fn hidden_order_is_matchable_but_left_out_of_public_stats() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    let hidden_order = block_on(crate::order_management::create_order(9.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    crate::order_management::set_order_hidden(hidden_order, true).unwrap();
+
+    assert_eq!(crate::chunk_allocation::get_active_chunks().len(), 2);
+    assert_eq!(crate::chunk_allocation::get_orderbook_stats_v2().total_available_usd_e6, 6_000_000);
+    assert_eq!(crate::market_data::get_public_market_data().total_available_usd_e6, 6_000_000);
+    assert_eq!(crate::api_versions::get_available_orderbook(), 6_000_000);
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 2_000_000);
+    mock::set_caller(filler());
+    let trades = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 15.0,
+        allow_partial: false,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    assert!(trades.iter().any(|id| get_trade(*id).unwrap().order_id == hidden_order));
+}
+
+#[test]
+fn order_tracks_how_long_its_chunks_sat_idle() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let order_id = block_on(crate::order_management::create_order(6.0, 52.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    assert!(get_order(order_id).unwrap().idle_since.is_none());
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 3.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+
+    // The lock times out with the price above the order's ceiling - its chunk comes back Idle
+    mock::set_prices(&[53.0, 53.0, 53.0]);
+    mock::advance_time(TRADE_TIMEOUT_NS + 1);
+    block_on(crate::price_oracle::get_bsv_price()).unwrap();
+    block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+    let idled = get_order(order_id).unwrap();
+    assert_eq!(idled.total_idle_usd_e6, 3_000_000);
+    assert_eq!(idled.idle_since, Some(get_time()));
+    assert_eq!(idled.last_idle_at, idled.idle_since);
+
+    let hour = 60 * 60 * 1_000_000_000;
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::advance_time(hour);
+    assert_eq!(get_order(order_id).unwrap().idle_duration_ns(get_time()), hour);
+    block_on(crate::heartbeat::reactivate_idle_chunks()).unwrap();
+    let relisted = get_order(order_id).unwrap();
+    assert_eq!(relisted.total_idle_usd_e6, 0);
+    assert_eq!(relisted.idle_since, None);
+    assert_eq!(relisted.idle_duration_total_ns, Some(hour));
+    assert_eq!(relisted.last_idle_at, idled.last_idle_at);
+}
+
+#[test]
+fn order_is_cancelled_once_price_passes_its_auto_cancel_threshold() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let order_id = block_on(crate::order_management::create_order(6.0, 51.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    assert!(crate::order_management::set_order_auto_cancel(order_id, Some(51.0)).is_err());
+    crate::order_management::set_order_auto_cancel(order_id, Some(52.0)).unwrap();
+
+    block_on(crate::heartbeat::reactivate_idle_chunks()).unwrap();
+    assert_eq!(get_order(order_id).unwrap().status, OrderStatus::Active);
+
+    mock::set_prices(&[53.0, 53.0, 53.0]);
+    mock::advance_time(6 * 60 * 1_000_000_000);
+    block_on(crate::heartbeat::reactivate_idle_chunks()).unwrap();
+    let order = get_order(order_id).unwrap();
+    assert_eq!(order.status, OrderStatus::Cancelled);
+    assert_eq!(order.refund_attempts.len(), 1);
+    assert!(get_admin_events().iter().any(|event| matches!(
+        event.event_type,
+        AdminEventType::OrderAutoCancelled { order_id: id, auto_cancel_above_price, .. } if id == order_id && auto_cancel_above_price == 52.0
+    )));
+}
+
+#[test]
+fn post_only_chunks_wait_a_cycle_and_list_against_the_new_price() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+    mock::set_caller(maker());
+    let moved = block_on(crate::order_management::create_post_only_order(6.0, 52.0, p2pkh_address(&MAKER_HASH160), None, None)).unwrap();
+    let confirmed = block_on(crate::order_management::create_post_only_order(3.0, 52.0, p2pkh_address(&MAKER_HASH160), None, None)).unwrap();
+    assert!(get_chunks_by_order(moved).iter().all(|chunk| chunk.status == ChunkStatus::Pending));
+    assert_eq!(get_available_orderbook(), 0);
+
+    // The maker confirms one at the price they quoted
+    assert_eq!(block_on(crate::order_management::confirm_order(confirmed)).unwrap(), 1);
+    assert_eq!(get_available_orderbook(), 3_000_000);
+
+    // The price moved past the other's ceiling before the next cycle - it is delisted, not matched
+    mock::set_prices(&[53.0, 53.0, 53.0]);
+    mock::advance_time(6 * 60 * 1_000_000_000);
+    block_on(crate::heartbeat::reactivate_idle_chunks()).unwrap();
+    assert!(get_chunks_by_order(moved).iter().all(|chunk| chunk.status == ChunkStatus::Idle));
+    assert_eq!(get_order(moved).unwrap().total_idle_usd_e6, 6_000_000);
+    assert!(crate::invariants::check_invariants().is_empty());
+}
+
+#[test]
+fn operator_acts_for_its_owner_only_within_its_scopes() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let order_id = block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    let bot = Principal::from_slice(&[3, 3, 3]);
+    crate::delegations::register_operator(bot, vec![OperatorScope::MakerPricing], None).unwrap();
+    assert_eq!(crate::delegations::get_my_operators().len(), 1);
+
+    // A proposal acts for nobody until the operator accepts it
+    assert_eq!(crate::delegations::acting_principal(bot, OperatorScope::MakerPricing), bot);
+    mock::set_caller(bot);
+    assert!(crate::delegations::accept_delegation(filler()).is_err());
+    crate::delegations::accept_delegation(maker()).unwrap();
+
+    // Resolved the way lib.rs does - only the delegated scope acts for the maker
+    assert!(crate::delegations::register_operator(filler(), vec![OperatorScope::MakerPricing], None).is_err());
+    let as_filler = crate::delegations::acting_principal(bot, OperatorScope::FillerSettlement);
+    assert_eq!(as_filler, bot);
+    assert!(block_on(crate::order_management::update_max_bsv_price(as_filler, order_id, 80.0)).is_err());
+    let as_maker = crate::delegations::acting_principal(bot, OperatorScope::MakerPricing);
+    block_on(crate::order_management::update_max_bsv_price(as_maker, order_id, 80.0)).unwrap();
+    assert_eq!(get_order(order_id).unwrap().max_bsv_price, 80.0);
+
+    mock::set_caller(maker());
+    crate::delegations::revoke_operator(bot).unwrap();
+    assert_eq!(crate::delegations::acting_principal(bot, OperatorScope::MakerPricing), bot);
+}
+
+#[test]
+fn unwanted_operator_can_resign_and_is_never_acted_through() {
+    // Anyone may propose anyone, but the proposal changes nothing until accepted
+    let victim = Principal::from_slice(&[5, 5, 5]);
+    mock::set_caller(filler());
+    crate::delegations::register_operator(victim, vec![OperatorScope::FillerSettlement, OperatorScope::OrdersRead], None).unwrap();
+    assert_eq!(crate::delegations::acting_principal(victim, OperatorScope::FillerSettlement), victim);
+
+    // The victim can still register its own operators and walk away from the proposal
+    mock::set_caller(victim);
+    crate::delegations::register_operator(Principal::from_slice(&[6, 6, 6]), vec![OperatorScope::OrdersRead], None).unwrap();
+    assert!(crate::delegations::get_my_delegation().is_some());
+    crate::delegations::resign_delegation().unwrap();
+    assert!(crate::delegations::get_my_delegation().is_none());
+
+    // An accepted operator can resign too
+    mock::set_caller(maker());
+    let bot = Principal::from_slice(&[7, 7, 7]);
+    crate::delegations::register_operator(bot, vec![OperatorScope::OrdersRead], None).unwrap();
+    mock::set_caller(bot);
+    crate::delegations::accept_delegation(maker()).unwrap();
+    assert_eq!(crate::delegations::acting_principal(bot, OperatorScope::OrdersRead), maker());
+    crate::delegations::resign_delegation().unwrap();
+    assert_eq!(crate::delegations::acting_principal(bot, OperatorScope::OrdersRead), bot);
+}
+
+#[test]
+fn session_delegation_creates_trades_until_it_expires() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let session = Principal::from_slice(&[4, 4, 4]);
+    let delegation = crate::delegations::register_operator(session, vec![OperatorScope::TradeCreate], Some(60)).unwrap();
+    assert!(delegation.expires_at.is_some());
+    mock::set_caller(session);
+    crate::delegations::accept_delegation(filler()).unwrap();
+
+    let request = CreateTradesRequest {
+        requested_usd: 3.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    };
+    mock::set_caller(session);
+    let acting = crate::delegations::acting_principal(session, OperatorScope::TradeCreate);
+    assert_eq!(acting, filler());
+    // The reservation is held for the filler, so the fill it quoted lands on the reserved chunk
+    let reservation = block_on(crate::trade_lifecycle::reserve_chunks(acting, 3.0, 60, None)).unwrap();
+    assert_eq!(get_chunk(reservation.chunk_ids[0]).unwrap().reserved_by, Some(filler()));
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(acting, request)).unwrap()[0];
+    assert_eq!(get_trade(trade_id).unwrap().filler, filler());
+    assert_eq!(get_trade(trade_id).unwrap().locked_chunks[0].chunk_id, reservation.chunk_ids[0]);
+
+    mock::advance_time(61 * 1_000_000_000);
+    assert_eq!(crate::delegations::acting_principal(session, OperatorScope::TradeCreate), session);
+}
+
+#[test]
+fn small_trade_releases_at_its_tier_depth() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    // Tiers must not ask less of larger trades
+    let inverted = ConfirmationDepthSchedule {
+        tiers: vec![
+            ConfirmationDepthTier { trade_under_usd_e6: 10_000_000, confirmations: 12 },
+            ConfirmationDepthTier { trade_under_usd_e6: 100_000_000, confirmations: 6 },
+        ],
+        default_confirmations: CONFIRMATION_DEPTH,
+    };
+    assert!(crate::trade_lifecycle::set_confirmation_depth_schedule(inverted).is_err());
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    let trade = get_trade(trade_ids[0]).unwrap();
+
+    let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+    let payment_hex = tx_hex(&[(&"cd".repeat(32), 0)], &[(paid, MAKER_HASH160), (5_000, CHANGE_HASH160)]);
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade.id, payment_hex.clone())).unwrap();
+    let small_depth = ConfirmationDepthSchedule::default().confirmations_for(trade.amount_usd_e6);
+    assert!(small_depth < CONFIRMATION_DEPTH);
+    assert_eq!(get_trade(trade.id).unwrap().required_confirmations, Some(small_depth));
+
+    // Raising the schedule later doesn't move a trade that's already submitted
+    let stricter = ConfirmationDepthSchedule { tiers: vec![], default_confirmations: CONFIRMATION_DEPTH };
+    crate::trade_lifecycle::set_confirmation_depth_schedule(stricter).unwrap();
+
+    // Mined and buried under the small-trade depth only
+    let payment_txid = compute_bsv_txid(&payment_hex).unwrap();
+    let mined_at = 800_300;
+    let (bump_hex, merkle_root) = single_tx_bump(mined_at, &payment_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, mined_at - 800_000 + small_depth, 0, &[(mined_at, merkle_root)]);
+    mock::set_chain(chain);
+    block_on(crate::chain_sync::sync_blocks()).expect("headers synced");
+
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+    block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex, bump_hex)).expect("claim paid out");
+    assert_eq!(get_trade(trade.id).unwrap().status, TradeStatus::WithdrawalConfirmed);
+}
+
+#[test]
+fn resubmitted_trade_holds_one_tx_blob_until_it_is_cleaned_up() {
+    let ref_count = |txid: &str| TX_BLOBS.with(|blobs| blobs.borrow().get(&TxidKey(txid.to_string())).map(|blob| blob.ref_count));
+
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 3.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap()[0];
+    let trade = get_trade(trade_id).unwrap();
+
+    let parent_hex = tx_hex(&[(&"ab".repeat(32), 0)], &[(20_000_000, CHANGE_HASH160)]);
+    let parent_txid = compute_bsv_txid(&parent_hex).unwrap();
+    mock::add_raw_tx(&parent_txid, &parent_hex);
+    let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+    let payment = |fee: u64| tx_hex(&[(&parent_txid, 0)], &[(paid, MAKER_HASH160), (20_000_000 - paid - fee, CHANGE_HASH160)]);
+    let (first_hex, second_hex) = (payment(1_000), payment(2_000));
+    let (first_txid, second_txid) = (compute_bsv_txid(&first_hex).unwrap(), compute_bsv_txid(&second_hex).unwrap());
+
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade_id, first_hex)).unwrap();
+    assert_eq!(ref_count(&first_txid), Some(1));
+    block_on(crate::trade_lifecycle::resubmit_bsv_transaction(filler(), trade_id, second_hex.clone())).unwrap();
+    assert_eq!((ref_count(&first_txid), ref_count(&second_txid)), (None, Some(1)));
+
+    let mined_at = 800_300;
+    let (bump_hex, merkle_root) = single_tx_bump(mined_at, &second_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, mined_at - 800_000 + CONFIRMATION_DEPTH, 0, &[(mined_at, merkle_root)]);
+    mock::set_chain(chain);
+    block_on(crate::chain_sync::sync_blocks()).expect("headers synced");
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+    block_on(crate::trade_lifecycle::claim_usdc(filler(), trade_id, second_hex, bump_hex)).expect("claim paid out");
+
+    // Past retention the trade goes, and its transaction with it
+    mock::advance_time(get_retention_policy().trade_retention_days * 24 * 60 * 60 * 1_000_000_000);
+    assert_eq!(crate::data_cleanup::cleanup_old_trades(), 1);
+    assert_eq!(ref_count(&second_txid), None);
+}
+
+#[test]
+fn instant_release_bond_is_forfeited_when_a_reorg_drops_the_payment() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    // Security well beyond the 5% deposit, so the released amount can be bonded
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 20_000_000);
+    mock::set_caller(filler());
+    crate::release_bonds::set_instant_release(true).unwrap();
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    let trade = get_trade(trade_ids[0]).unwrap();
+
+    let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+    let payment_hex = tx_hex(&[(&"ce".repeat(32), 0)], &[(paid, MAKER_HASH160), (5_000, CHANGE_HASH160)]);
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade.id, payment_hex.clone())).unwrap();
+
+    // One confirmation only
+    let payment_txid = compute_bsv_txid(&payment_hex).unwrap();
+    let mined_at = 800_300;
+    let (bump_hex, merkle_root) = single_tx_bump(mined_at, &payment_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, mined_at - 800_000 + 1, 0, &[(mined_at, merkle_root)]);
+    mock::set_chain(chain.clone());
+    block_on(crate::chain_sync::sync_blocks()).expect("headers synced");
+
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+    block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex, bump_hex)).expect("released on one confirmation");
+    assert_eq!(get_trade(trade.id).unwrap().status, TradeStatus::WithdrawalConfirmed);
+
+    let released_e6 = 6_000_000 + bps_of(6_000_000, FILLER_INCENTIVE_PERCENT);
+    let bonds = get_release_bonds_by_filler(filler());
+    assert_eq!(bonds.len(), 1);
+    assert_eq!(bonds[0].amount_usd_e6, released_e6);
+    assert_eq!(block_on(crate::release_bonds::settle_release_bonds()), 0);
+
+    // The bond can't be withdrawn while it is held
+    let deposit = mock::balance(crate::filler_accounts::get_deposit_account(filler())) as u64;
+    assert!(block_on(crate::filler_accounts::withdraw_security(deposit - CKUSDC_TRANSFER_FEE as u64, filler())).is_err());
+
+    // A competing branch replaces the payment's block
+    chain.truncate(300);
+    extend_chain(&mut chain, 0, 3, 1, &[]);
+    mock::set_chain(chain);
+    block_on(crate::chain_sync::sync_blocks()).expect("reorg synced");
+
+    // The payment may still be re-mined on the new branch - nothing is forfeited until the window closes
+    assert_eq!(block_on(crate::release_bonds::settle_release_bonds()), 0);
+    assert!(get_release_bonds_by_filler(filler())[0].reorged_at.is_some());
+    mock::advance_time(crate::config::RELEASE_BOND_REPROOF_WINDOW_NS);
+    assert_eq!(block_on(crate::release_bonds::settle_release_bonds()), 1);
+    assert!(get_release_bonds_by_filler(filler()).is_empty());
+    assert_eq!(mock::balance(account(maker(), None)), released_e6 as u128 - CKUSDC_TRANSFER_FEE);
+}
+
+#[test]
+fn instant_release_bond_follows_a_payment_re_mined_after_a_reorg() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 20_000_000);
+    mock::set_caller(filler());
+    crate::release_bonds::set_instant_release(true).unwrap();
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    let trade = get_trade(trade_ids[0]).unwrap();
+
+    let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+    let payment_hex = tx_hex(&[(&"ce".repeat(32), 0)], &[(paid, MAKER_HASH160), (5_000, CHANGE_HASH160)]);
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade.id, payment_hex.clone())).unwrap();
+
+    let payment_txid = compute_bsv_txid(&payment_hex).unwrap();
+    let mined_at = 800_300;
+    let (bump_hex, merkle_root) = single_tx_bump(mined_at, &payment_txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, mined_at - 800_000 + 1, 0, &[(mined_at, merkle_root.clone())]);
+    mock::set_chain(chain.clone());
+    block_on(crate::chain_sync::sync_blocks()).expect("headers synced");
+
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+    block_on(crate::trade_lifecycle::claim_usdc(filler(), trade.id, payment_hex, bump_hex)).expect("released on one confirmation");
+
+    // The competing branch mines the same payment one block later
+    chain.truncate(300);
+    extend_chain(&mut chain, 0, 3, 1, &[(mined_at + 1, merkle_root)]);
+    mock::set_chain(chain);
+    block_on(crate::chain_sync::sync_blocks()).expect("reorg synced");
+    assert_eq!(block_on(crate::release_bonds::settle_release_bonds()), 0);
+
+    let (new_bump_hex, _) = single_tx_bump(mined_at + 1, &payment_txid);
+    assert!(block_on(crate::release_bonds::reprove_release_bond(maker(), trade.id, new_bump_hex.clone())).is_err());
+    block_on(crate::release_bonds::reprove_release_bond(filler(), trade.id, new_bump_hex)).unwrap();
+    let bond = &get_release_bonds_by_filler(filler())[0];
+    assert_eq!((bond.block_height, bond.reorged_at), (mined_at + 1, None));
+
+    // Past the window the bond is kept, and returned once the new block is buried
+    mock::advance_time(crate::config::RELEASE_BOND_REPROOF_WINDOW_NS);
+    assert_eq!(block_on(crate::release_bonds::settle_release_bonds()), 0);
+    assert_eq!(get_release_bonds_by_filler(filler()).len(), 1);
+    assert_eq!(mock::balance(account(maker(), None)), 0);
+}
+
+#[test]
+fn allowance_funded_order_pulls_the_exact_deposit() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::set_caller(maker());
+    let wallet = account(maker(), None);
+    mock::mint(wallet, 10_000_000);
+    let required = 3_000_000 + bps_of(3_000_000, crate::config::ACTIVATION_FEE_PERCENT) + bps_of(3_000_000, FILLER_INCENTIVE_PERCENT);
+
+    // Approved for the deposit but not the ledger fee on top - nothing moves, nothing is stored
+    mock::approve(wallet, mock::canister_id(), required as u128);
+    let short = block_on(crate::order_management::create_order_with_allowance(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None, None));
+    assert!(matches!(short, Err(EasySwapError::InsufficientFunds { .. })), "{:?}", short);
+    assert_eq!(mock::balance(wallet), 10_000_000);
+    assert!(get_orders_by_maker(maker()).is_empty());
+
+    mock::approve(wallet, mock::canister_id(), required as u128 + CKUSDC_TRANSFER_FEE);
+    let order_id = block_on(crate::order_management::create_order_with_allowance(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None, None)).unwrap();
+    let order = get_order(order_id).unwrap();
+    assert_eq!(order.status, OrderStatus::Active);
+    assert_eq!(order.total_deposited_usd_e6, Some(required));
+    assert_eq!(mock::balance(wallet), 10_000_000 - required as u128 - CKUSDC_TRANSFER_FEE);
+    assert_eq!(mock::allowance(wallet, mock::canister_id()), 0);
+}
+
+#[test]
+fn opted_in_maker_is_topped_up_from_wallet() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::set_caller(maker());
+    let wallet = account(maker(), None);
+    mock::mint(wallet, 10_000_000);
+    mock::approve(wallet, mock::canister_id(), 10_000_000);
+
+    // A standing approval alone isn't used
+    let not_opted_in = block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None));
+    assert!(matches!(not_opted_in, Err(EasySwapError::InsufficientFunds { .. })), "{:?}", not_opted_in);
+
+    crate::wallet_top_up::set_my_auto_top_up(Some(2.0)).unwrap();
+    let over_limit = block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None));
+    assert!(matches!(over_limit, Err(EasySwapError::InsufficientFunds { .. })), "{:?}", over_limit);
+    assert_eq!(mock::balance(wallet), 10_000_000);
+
+    crate::wallet_top_up::set_my_auto_top_up(Some(5.0)).unwrap();
+    let order_id = block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    assert_eq!(get_order(order_id).unwrap().status, OrderStatus::Active);
+    assert!(mock::balance(wallet) < 10_000_000 - 3_000_000);
+
+    crate::wallet_top_up::set_my_auto_top_up(None).unwrap();
+    assert!(crate::wallet_top_up::get_my_auto_top_up().is_none());
+}
+
+#[test]
+fn activation_fee_rebates_are_split_by_filled_volume() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    let small_maker = Principal::from_slice(&[1, 1, 2]);
+    let mut order_ids = Vec::new();
+    for (owner, amount_usd) in [(maker(), 6.0), (small_maker, 3.0)] {
+        mock::mint(crate::filler_accounts::get_deposit_account(owner), 10_000_000);
+        mock::set_caller(owner);
+        order_ids.push(block_on(crate::order_management::create_order(amount_usd, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap());
+    }
+    let pool_e6 = get_latest_rebate_epoch().unwrap().pool_e6;
+    assert!(pool_e6 > 0);
+    assert_eq!(get_treasury_buckets().rebates_accrued_e6, Some(pool_e6));
+
+    for order_id in &order_ids {
+        let chunk_ids: Vec<ChunkId> = get_order(*order_id).unwrap().chunks;
+        crate::chunk_allocation::mark_chunks_filled(&chunk_ids).unwrap();
+    }
+    assert_eq!(get_maker_rebates(maker()).unwrap().epoch_volume_e6, 6_000_000);
+
+    // Nothing is owed until the epoch closes
+    assert!(crate::rebates::close_epoch_if_due().is_none());
+    assert!(block_on(crate::rebates::claim_rebates()).is_err());
+
+    mock::advance_time(crate::config::REBATE_EPOCH_NS);
+    let closed = crate::rebates::close_epoch_if_due().expect("epoch is due");
+    assert_eq!((closed.total_volume_e6, closed.makers_rebated), (9_000_000, 2));
+    let big = get_maker_rebates(maker()).unwrap();
+    let small = get_maker_rebates(small_maker).unwrap();
+    assert_eq!(big.owed_e6, pool_e6 * 2 / 3);
+    assert_eq!(small.owed_e6, pool_e6 / 3);
+    assert_eq!(big.epoch_volume_e6, 0);
+    assert_eq!(get_latest_rebate_epoch().unwrap().pool_e6, pool_e6 - closed.distributed_e6);
+
+    // Paid from the treasury's main account to the maker's wallet, less the ledger fee
+    mock::set_caller(maker());
+    let received = block_on(crate::rebates::claim_rebates()).unwrap();
+    assert_eq!(received as u128, big.owed_e6 as u128 - CKUSDC_TRANSFER_FEE);
+    assert_eq!(mock::balance(account(maker(), None)), received as u128);
+    let paid = crate::rebates::get_my_rebates();
+    assert_eq!((paid.owed_e6, paid.claimed_e6), (0, big.owed_e6));
+    assert!(paid.pending_claim.is_none());
+    assert!(block_on(crate::rebates::claim_rebates()).is_err());
+}
+
+#[test]
+fn treasury_withdrawals_leave_in_flight_trade_funds() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    let revenue_e6 = get_treasury_buckets().total_revenue_e6() - get_treasury_buckets().rebates_accrued_e6.unwrap_or(0);
+    assert_eq!(crate::treasury::earmarked_for_trades_e6(), 0);
+    // Only held revenue can leave, however much passes through the main account
+    assert_eq!(crate::treasury::withdrawable_e6(50_000_000), revenue_e6 - CKUSDC_TRANSFER_FEE as u64);
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 3.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    let earmarked_e6 = 3_000_000 + bps_of(3_000_000, FILLER_INCENTIVE_PERCENT);
+    assert_eq!(crate::treasury::earmarked_for_trades_e6(), earmarked_e6);
+    assert_eq!(crate::treasury::withdrawable_e6(earmarked_e6 + 50_000), 50_000 - CKUSDC_TRANSFER_FEE as u64);
+    assert_eq!(crate::treasury::withdrawable_e6(earmarked_e6), 0);
+    // Locked or not, the whole order is still held in its subaccount
+    assert_eq!(crate::treasury::estimate_order_holdings_e6(), (1, 6_000_000 + bps_of(6_000_000, FILLER_INCENTIVE_PERCENT)));
+}
+
+#[test]
+fn legacy_p2sh_orders_are_still_paid_with_p2sh_outputs() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let p2sh_address = base58_check(BsvNetwork::Mainnet.p2sh_version(), &MAKER_HASH160);
+    let refused = block_on(crate::order_management::create_order(3.0, 100.0, p2sh_address.clone(), None));
+    assert!(matches!(refused, Err(EasySwapError::InvalidInput { .. })));
+    block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 3.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap()[0];
+
+    // As if the order was created while P2SH addresses were accepted
+    update_trade(trade_id, |trade| trade.locked_chunks[0].bsv_address = p2sh_address.clone()).unwrap();
+    let trade = get_trade(trade_id).unwrap();
+    let sats = trade.locked_chunks[0].sats_amount;
+    let p2pkh_hex = tx_hex(&[(&"ab".repeat(32), 0)], &[(sats, MAKER_HASH160)]);
+    let p2sh_hex = p2pkh_hex.replace(
+        &format!("1976a914{}88ac", hex::encode(MAKER_HASH160)),
+        &format!("17a914{}87", hex::encode(MAKER_HASH160)),
+    );
+
+    let parse = |hex: &str| crate::bsv_parser::parse_bsv_transaction(hex).unwrap();
+    assert_eq!(crate::bsv_parser::validate_transaction_outputs(&parse(&p2sh_hex), &[&trade]), Ok(1));
+    assert!(crate::bsv_parser::validate_transaction_outputs(&parse(&p2pkh_hex), &[&trade]).is_err());
+}
+
+#[test]
+fn price_improvement_over_the_threshold_is_shared_with_the_filler() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    assert!(crate::trade_lifecycle::set_price_improvement_sharing(PriceImprovementSharing { threshold_bps: 100, filler_share_bps: 10_001 }).is_err());
+    crate::trade_lifecycle::set_price_improvement_sharing(PriceImprovementSharing { threshold_bps: 100, filler_share_bps: 5_000 }).unwrap();
+
+    // One order $10 above the oracle price, one within the 1% threshold
+    let close_maker = Principal::from_slice(&[1, 1, 2]);
+    for (maker, max_bsv_price) in [(maker(), 60.0), (close_maker, 50.25)] {
+        mock::mint(crate::filler_accounts::get_deposit_account(maker), 10_000_000);
+        mock::set_caller(maker);
+        block_on(crate::order_management::create_order(3.0, max_bsv_price, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    }
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap();
+    let shared = get_trade(trade_ids[0]).unwrap();
+    let unshared = get_trade(trade_ids[1]).unwrap();
+    let sats_at = |price: f64| (3.0 / price * crate::config::SATOSHIS_PER_BSV as f64) as u64;
+
+    // Half the $10 gap moves the price sats are calculated at
+    assert_eq!(shared.agreed_bsv_price, BSV_PRICE);
+    assert_eq!(shared.effective_bsv_price, Some(55.0));
+    assert_eq!(shared.locked_chunks[0].sats_amount, sats_at(55.0));
+    assert_eq!(unshared.effective_bsv_price, None);
+    assert_eq!(unshared.locked_chunks[0].sats_amount, sats_at(BSV_PRICE));
+
+    // The maker sees the same effective price
+    mock::set_caller(maker());
+    let activity = crate::order_management::get_order_trades(shared.order_id).unwrap();
+    assert_eq!(activity[0].effective_bsv_price, 55.0);
+
+    // Payments are checked against the effective price, not the oracle price
+    let payment = |sats: u64| crate::bsv_parser::parse_bsv_transaction(&tx_hex(&[(&"ab".repeat(32), 0)], &[(sats, MAKER_HASH160)])).unwrap();
+    assert!(crate::bsv_parser::validate_price_paid(&payment(sats_at(55.0)), &[&shared], 1).is_ok());
+    assert!(crate::bsv_parser::validate_price_paid(&payment(sats_at(58.0)), &[&shared], 1).is_err());
+}
+
+#[test]
+fn books_keep_their_own_orders_matching_and_stats() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    let ckusdt_ledger = Principal::from_slice(&[9, 9]);
+    let ckusdt_book = crate::books::add_book("ckUSDT".to_string(), ckusdt_ledger).unwrap();
+    assert!(crate::books::add_book("ckUSDT again".to_string(), ckusdt_ledger).is_err());
+
+    // The first order is funded in the book's own token, since security deposits are ckUSDC
+    mock::set_caller(maker());
+    let unfunded = block_on(crate::order_management::create_order_in_book(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None, Some(ckusdt_book)));
+    assert!(matches!(unfunded, Err(EasySwapError::InsufficientFunds { .. })));
+    let next_order_id = APP_STATE.with(|cell| cell.borrow().get().next_order_id);
+    mock::mint_token(ckusdt_ledger, crate::ckusdc_integration::get_order_deposit_account(maker(), next_order_id), 10_000_000);
+    let ckusdt_order = block_on(crate::order_management::create_order_in_book(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None, Some(ckusdt_book))).unwrap();
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    let ckusdc_order = block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+    assert_eq!(get_order(ckusdt_order).unwrap().book_id(), ckusdt_book);
+
+    // Each activation fee is revenue on its own ledger - only the ckUSDC one accrues rebates
+    let buckets = get_treasury_buckets();
+    let ckusdt_revenue = crate::treasury::ledger_revenue(ckusdt_ledger);
+    assert!(ckusdt_revenue.activation_fees > 0);
+    assert!(buckets.activation_fees_e6 > 0 && buckets.activation_fees_e6 < ckusdt_revenue.activation_fees);
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let request = CreateTradesRequest {
+        requested_usd: 6.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: Some(ckusdt_book),
+    };
+    let trade_ids = block_on(crate::trade_lifecycle::create_trades(get_caller(), request)).unwrap();
+    assert!(trade_ids.iter().all(|id| get_trade(*id).unwrap().order_id == ckusdt_order));
+
+    let stats = crate::books::get_book_stats(Some(ckusdt_book)).unwrap();
+    assert_eq!((stats.total_orders, stats.total_trades as usize), (1, trade_ids.len()));
+    let default_stats = crate::books::get_book_stats(None).unwrap();
+    assert_eq!((default_stats.total_orders, default_stats.total_trades), (1, 0));
+    assert!(crate::chunk_allocation::get_book_active_chunks(crate::books::DEFAULT_BOOK_ID).iter().all(|chunk| chunk.order_id == ckusdc_order));
+
+    // A closed book takes no new orders
+    crate::books::update_book(ckusdt_book, false, None, None).unwrap();
+    mock::set_caller(maker());
+    let closed = block_on(crate::order_management::create_order_in_book(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None, Some(ckusdt_book)));
+    assert!(matches!(closed, Err(EasySwapError::ServiceUnavailable { .. })));
+}
+
+#[test]
+fn testnet_deployment_takes_testnet_addresses_and_keeps_its_own_headers() {
+    let mut mainnet = Vec::new();
+    extend_chain(&mut mainnet, 800_000, 1, 0, &[]);
+    crate::block_headers::store_block(mainnet[0].clone());
+
+    crate::chain_sync::set_bsv_network(BsvNetwork::Testnet).unwrap();
+    assert!(!get_txarchive_policy().enabled);
+    assert!(get_block_by_height(800_000).is_none());
+    let mut testnet = Vec::new();
+    extend_chain(&mut testnet, 1_600_000, 1, 7, &[]);
+    crate::block_headers::store_block(testnet[0].clone());
+    assert_eq!(get_highest_block(), 1_600_000);
+
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    let mainnet_address = block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None));
+    assert!(matches!(mainnet_address, Err(EasySwapError::InvalidInput { .. })));
+    block_on(crate::order_management::create_order(3.0, 100.0, testnet_p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    // The open order pins the canister to testnet
+    assert!(crate::chain_sync::set_bsv_network(BsvNetwork::Mainnet).is_err());
+    assert_eq!(crate::books::get_book(crate::books::DEFAULT_BOOK_ID).unwrap().bsv_network, BsvNetwork::Testnet);
+}
+
+#[test]
+fn oversized_and_malformed_strings_are_rejected_at_the_api_boundary() {
+    use crate::config::{MAX_BSV_TX_BYTES, MAX_BUMP_BYTES, MAX_DISPUTE_REASON_LENGTH};
+    mock::set_caller(filler());
+
+    // Bad strings are turned away before they reach the trade they name
+    let bad_charset = block_on(crate::submit_bsv_transaction(1, "zz".repeat(100)));
+    assert!(matches!(bad_charset, Err(EasySwapError::InvalidInput { .. })), "{:?}", bad_charset);
+    let odd_length = block_on(crate::resubmit_bsv_transaction(1, "abc".to_string()));
+    assert!(matches!(odd_length, Err(EasySwapError::InvalidInput { .. })), "{:?}", odd_length);
+    let oversized_tx = block_on(crate::claim_usdc(1, "00".repeat(MAX_BSV_TX_BYTES + 1), "00".to_string()));
+    assert!(matches!(oversized_tx, Err(EasySwapError::LimitExceeded { .. })), "{:?}", oversized_tx);
+    let oversized_bump = block_on(crate::claim_usdc(1, "00".to_string(), "00".repeat(MAX_BUMP_BYTES + 1)));
+    assert!(matches!(oversized_bump, Err(EasySwapError::LimitExceeded { .. })), "{:?}", oversized_bump);
+    let bad_address = block_on(crate::create_order(6.0, 100.0, "1Invalid0OIl".to_string(), None, None));
+    assert!(matches!(bad_address, Err(EasySwapError::InvalidInput { .. })), "{:?}", bad_address);
+    assert!(matches!(crate::maker_flag_dispute(1, "x".repeat(MAX_DISPUTE_REASON_LENGTH + 1)), Err(EasySwapError::LimitExceeded { .. })));
+    assert!(matches!(crate::maker_flag_dispute(1, "paid\u{0}twice".to_string()), Err(EasySwapError::InvalidInput { .. })));
+}
+
+#[test]
+fn paginated_queries_reject_oversized_pages() {
+    use crate::config::{MAX_PAGE_LIMIT, MAX_PAGE_OFFSET};
+    mock::set_caller(filler());
+
+    assert!(matches!(crate::get_my_trades_paginated(0, 10_000_000, None), Err(EasySwapError::LimitExceeded { .. })));
+    assert!(matches!(crate::get_my_orders_page(None, 0, None), Err(EasySwapError::InvalidInput { .. })));
+    assert!(matches!(crate::get_active_chunks_paginated(MAX_PAGE_OFFSET + 1, 10, None), Err(EasySwapError::LimitExceeded { .. })));
+    assert!(matches!(crate::get_recent_trades_public(MAX_PAGE_LIMIT + 1), Err(EasySwapError::LimitExceeded { .. })));
+
+    let page = crate::get_my_trades_paginated(0, MAX_PAGE_LIMIT, None).unwrap();
+    assert_eq!((page.total, page.limit), (0, MAX_PAGE_LIMIT));
+    assert!(crate::get_active_chunks_page(None, MAX_PAGE_LIMIT, None).is_ok());
+}
+
+#[test]
+fn upheld_penalty_appeal_refunds_the_filler_from_treasury() {
+    use crate::disputes::{admin_resolve_appeal, appeal_penalty};
+
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    let deposit = crate::filler_accounts::get_deposit_account(filler());
+    mock::mint(deposit, 1_000_000);
+    let lock_trade = || {
+        mock::set_caller(filler());
+        block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        }))
+        .unwrap()[0]
+    };
+    let penalized = lock_trade();
+    mock::advance_time(TRADE_TIMEOUT_NS + 60 * 1_000_000_000);
+    block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+    let clean = lock_trade();
+
+    // Only the filler, only trades with a logged penalty, only once
+    assert!(matches!(appeal_penalty(clean, "Paid on time".to_string()), Err(EasySwapError::InvalidState { .. })));
+    mock::set_caller(maker());
+    assert!(matches!(appeal_penalty(penalized, "Not mine".to_string()), Err(EasySwapError::Unauthorized { .. })));
+    mock::set_caller(filler());
+    assert!(appeal_penalty(penalized, "  ".to_string()).is_err());
+    appeal_penalty(penalized, "Wallet broadcast failed, see attached txid".to_string()).unwrap();
+    assert!(matches!(appeal_penalty(penalized, "Again".to_string()), Err(EasySwapError::InvalidState { .. })));
+
+    let appeal = get_penalty_appeal(penalized).unwrap();
+    assert_eq!(appeal.status, AppealStatus::Open);
+    assert!(appeal.penalty_amount > 0.0);
+    assert!(block_on(admin_resolve_appeal(penalized, appeal.penalty_amount * 2.0)).is_err());
+
+    // Cleanup and the archive keep the trade while its appeal is open, however old it gets
+    mock::advance_time(crate::config::PENALTY_APPEAL_WINDOW_NS + 1);
+    let expired = || crate::data_cleanup::expired_trades(get_time(), &get_retention_policy());
+    assert!(!expired().contains(&penalized));
+
+    // Paid from held revenue, while the main account also carries the open trade's earmark
+    mock::mint(account(crate::runtime::canister_id(), None), 10_000_000);
+    let before = mock::balance(deposit);
+    let resolved = block_on(admin_resolve_appeal(penalized, appeal.penalty_amount)).unwrap();
+    let refund_e6 = crate::ckusdc_integration::usd_to_ckusdc_e6(appeal.penalty_amount);
+    assert_eq!(resolved.status, AppealStatus::Resolved);
+    assert!(resolved.refund_block_index.is_some());
+    assert_eq!(mock::balance(deposit), before + refund_e6);
+    assert_eq!(get_treasury_buckets().appeal_refunds_e6, Some((refund_e6 + CKUSDC_TRANSFER_FEE) as u64));
+    let logged = AdminEventFilter { kinds: Some(vec![AdminEventKind::PenaltyAppealed, AdminEventKind::AppealResolved]), ..Default::default() };
+    assert_eq!(query_admin_events(&logged, None, 10).0.len(), 2);
+    assert!(matches!(block_on(admin_resolve_appeal(penalized, 0.0)), Err(EasySwapError::InvalidState { .. })));
+    assert!(expired().contains(&penalized));
+}
+
+#[test]
+fn late_claim_on_reclaimed_trade_is_paid_from_treasury() {
+    use crate::config::{LATE_CLAIM_AUTO_WINDOW_NS, TRADE_CLAIM_EXPIRY_NS};
+    use crate::late_claims::{admin_approve_late_claim, late_claim_review};
+
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    // Two trades, each paid on-chain in its own block, then left unclaimed past the 24h expiry
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    let mut chain = Vec::new();
+    let mut mined = Vec::new();
+    let mut payments = Vec::new();
+    for (index, parent_seed) in ["ab", "cd"].into_iter().enumerate() {
+        mock::set_caller(filler());
+        let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        }))
+        .unwrap()[0];
+        let trade = get_trade(trade_id).unwrap();
+        let parent_hex = tx_hex(&[(&parent_seed.repeat(32), 0)], &[(20_000_000, CHANGE_HASH160)]);
+        let parent_txid = compute_bsv_txid(&parent_hex).unwrap();
+        mock::add_raw_tx(&parent_txid, &parent_hex);
+        let paid: u64 = trade.locked_chunks.iter().map(|c| c.sats_amount).sum();
+        let payment_hex = tx_hex(&[(&parent_txid, 0)], &[(paid, MAKER_HASH160), (20_000_000 - paid - 1_000, CHANGE_HASH160)]);
+        block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade_id, payment_hex.clone())).unwrap();
+        let (bump_hex, merkle_root) = single_tx_bump(800_300 + index as u64, &compute_bsv_txid(&payment_hex).unwrap());
+        mined.push((800_300 + index as u64, merkle_root));
+        payments.push((trade_id, payment_hex, bump_hex));
+    }
+    extend_chain(&mut chain, 800_000, 302 + CONFIRMATION_DEPTH, 0, &mined);
+    mock::set_chain(chain);
+    mock::advance_time(TRADE_CLAIM_EXPIRY_NS + 1);
+    block_on(crate::heartbeat::process_cleanup_tasks()).unwrap();
+    let [(early, early_hex, early_bump), (late, late_hex, late_bump)] = payments.try_into().unwrap();
+    assert!(get_trade(early).unwrap().withdrawal_tx_hash.unwrap().starts_with("treasury_reclaim_"));
+    let reclaimed_e6 = get_treasury_buckets().expired_trade_reclaims_e6;
+
+    // Only the filler, only with the trade's own payment
+    mock::set_caller(maker());
+    assert!(matches!(block_on(late_claim_review(maker(), early, early_hex.clone(), early_bump.clone())), Err(EasySwapError::Unauthorized { .. })));
+    assert!(matches!(block_on(late_claim_review(filler(), early, late_hex.clone(), early_bump.clone())), Err(EasySwapError::InvalidState { .. })));
+
+    // Within the window - paid at once, what an on-time claim would have paid
+    let wallet = account(filler(), None);
+    let claim = block_on(late_claim_review(filler(), early, early_hex.clone(), early_bump.clone())).unwrap();
+    assert_eq!(claim.status, LateClaimStatus::Paid);
+    assert_eq!(claim.amount_e6, 3_000_000 + bps_of(3_000_000, FILLER_INCENTIVE_PERCENT));
+    assert_eq!(mock::balance(wallet), claim.amount_e6 as u128 - CKUSDC_TRANSFER_FEE);
+    assert_eq!(get_treasury_buckets().late_claims_paid_e6, Some(claim.amount_e6));
+    assert!(matches!(block_on(late_claim_review(filler(), early, early_hex, early_bump)), Err(EasySwapError::InvalidState { .. })));
+
+    // Past the window - verified, then paid once the admin approves
+    // Until then cleanup and the archive keep the trade, though it's past the trade retention
+    mock::advance_time(LATE_CLAIM_AUTO_WINDOW_NS + 1);
+    let expired = || crate::data_cleanup::expired_trades(get_time(), &get_retention_policy());
+    assert_eq!(expired(), vec![early]);
+    let waiting = block_on(late_claim_review(filler(), late, late_hex.clone(), late_bump.clone())).unwrap();
+    assert_eq!(waiting.status, LateClaimStatus::AwaitingAdmin);
+    mock::advance_time(crate::config::LATE_CLAIM_WINDOW_NS);
+    assert_eq!(expired(), vec![early]);
+    assert_eq!(mock::balance(wallet), claim.amount_e6 as u128 - CKUSDC_TRANSFER_FEE);
+    let approved = block_on(admin_approve_late_claim(late)).unwrap();
+    assert_eq!(approved.status, LateClaimStatus::Paid);
+    assert_eq!(get_treasury_buckets().late_claims_paid_e6, Some(claim.amount_e6 + approved.amount_e6));
+    // Each payout undoes its reclaim, which arrived less a ledger fee
+    assert_eq!(get_treasury_buckets().late_claims_paid_e6, Some(reclaimed_e6 + 2 * CKUSDC_TRANSFER_FEE as u64));
+    assert!(matches!(block_on(admin_approve_late_claim(late)), Err(EasySwapError::InvalidState { .. })));
+    assert_eq!(expired(), vec![early, late]);
+}
+
+#[test]
+fn create_order_without_funds_is_not_activated() {
+    mock::set_caller(maker());
+    let result = block_on(crate::order_management::create_order(6.0, 100.0, p2pkh_address(&MAKER_HASH160), None));
+    assert!(matches!(result, Err(EasySwapError::InsufficientFunds { .. })));
+    assert_eq!(get_available_orderbook(), 0);
+}
+
+#[test]
+fn claim_rejects_bump_for_unknown_block() {
+    mock::set_prices(&[BSV_PRICE, BSV_PRICE, BSV_PRICE]);
+    mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+    mock::set_caller(maker());
+    block_on(crate::order_management::create_order(3.0, 100.0, p2pkh_address(&MAKER_HASH160), None)).unwrap();
+
+    mock::mint(crate::filler_accounts::get_deposit_account(filler()), 1_000_000);
+    mock::set_caller(filler());
+    let trade_id = block_on(crate::trade_lifecycle::create_trades(get_caller(), CreateTradesRequest {
+        requested_usd: 3.0,
+        allow_partial: true,
+        min_bsv_price: 40.0,
+        prefer_higher_incentive: None,
+        book_id: None,
+    }))
+    .unwrap()[0];
+    let trade = get_trade(trade_id).unwrap();
+
+    // Parent unknown to the provider - the fee check is skipped, not failed
+    let sats = trade.locked_chunks[0].sats_amount;
+    let payment_hex = tx_hex(&[(&"cd".repeat(32), 0)], &[(sats, MAKER_HASH160)]);
+    block_on(crate::trade_lifecycle::submit_bsv_transaction(filler(), trade_id, payment_hex.clone())).unwrap();
+
+    // Headers are current, just not as far as the BUMP claims
+    mock::advance_time(USDC_RELEASE_WAIT_NS);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, 300, 0, &[]);
+    mock::set_chain(chain);
+    let (bump_hex, _) = single_tx_bump(900_000, &compute_bsv_txid(&payment_hex).unwrap());
+    let result = block_on(crate::trade_lifecycle::claim_usdc(filler(), trade_id, payment_hex, bump_hex));
+    assert!(matches!(result, Err(EasySwapError::SpvFailed { .. })), "{:?}", result);
+    assert_eq!(get_trade(trade_id).unwrap().status, TradeStatus::TxSubmitted);
+    assert_eq!(mock::balance(account(filler(), None)), 0);
+}
+
+// ===== BLOCK SYNC =====
+
+#[test]
+fn sync_follows_reorg() {
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, 300, 0, &[]);
+    mock::set_chain(chain.clone());
+
+    let initial = block_on(crate::chain_sync::sync_blocks()).expect("initial sync");
+    assert_eq!(initial.blocks_added, crate::config::MAX_BLOCKS_TO_KEEP);
+    assert_eq!(get_highest_block(), 800_299);
+
+    // Last 3 blocks replaced by a competing branch that is 2 blocks longer
+    chain.truncate(297);
+    extend_chain(&mut chain, 0, 5, 1, &[]);
+    mock::set_chain(chain.clone());
+
+    let result = block_on(crate::chain_sync::sync_blocks()).expect("reorg sync");
+    assert_eq!(result.blocks_removed, 3);
+    assert_eq!(result.blocks_added, 5);
+    assert_eq!(get_highest_block(), 800_301);
+    assert_eq!(get_block_by_height(800_298).unwrap().hash, chain[298].hash);
+    assert_eq!(get_block_by_height(800_296).unwrap().hash, chain[296].hash);
+}
+
+#[test]
+fn compact_headers_round_trip_through_range_queries() {
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, 5, 0, &[]);
+    // WhatsOnChain headers arrive without raw bytes - they are rebuilt from the fields
+    let mut from_fields = chain[1].clone();
+    from_fields.raw_header = String::new();
+    crate::block_headers::store_block(from_fields);
+    for header in chain.iter().filter(|header| header.height != 800_001) {
+        crate::block_headers::store_block(header.clone());
+    }
+
+    let range = crate::block_headers::get_block_headers_range(800_001, 800_003);
+    assert_eq!(range.iter().map(|header| header.height).collect::<Vec<_>>(), vec![800_001, 800_002, 800_003]);
+    for (stored, original) in range.iter().zip(&chain[1..]) {
+        assert_eq!(stored.hash, original.hash);
+        assert_eq!(stored.previous_hash, original.previous_hash);
+        assert_eq!(stored.raw_header, original.raw_header);
+        assert_eq!((stored.timestamp, stored.bits, stored.nonce, stored.version), (original.timestamp, original.bits, original.nonce, original.version));
+    }
+    assert!(crate::block_headers::get_block_headers_range(800_003, 800_001).is_empty());
+    assert!(crate::block_headers::validate_chain(800_000, 800_004).is_ok());
+}
+
+#[test]
+fn verified_proofs_are_reused_until_their_block_is_replaced() {
+    let txid = "ab".repeat(32);
+    let (bump_hex, merkle_root) = single_tx_bump(800_296, &txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, 300, 0, &[(800_296, merkle_root)]);
+    mock::set_chain(chain.clone());
+    block_on(crate::chain_sync::sync_blocks()).expect("initial sync");
+
+    let verified = block_on(crate::bump_verification::verify_tx_bump_async(&txid, &bump_hex, 1)).unwrap();
+    assert!(verified.verified);
+    assert_eq!(verified.confirmations, 4);
+
+    // A retry is answered from the cache - its proof isn't even parsed
+    let retried = block_on(crate::bump_verification::verify_tx_bump_async(&txid, "00", 1)).unwrap();
+    assert!(retried.verified);
+    assert_eq!(retried.block_hash, verified.block_hash);
+
+    chain.truncate(296);
+    extend_chain(&mut chain, 0, 5, 1, &[]);
+    mock::set_chain(chain);
+    block_on(crate::chain_sync::sync_blocks()).expect("reorg sync");
+    assert!(crate::bump_verification::verify_tx_cached(&txid, 1).is_none());
+    assert!(block_on(crate::bump_verification::verify_tx_bump_async(&txid, &bump_hex, 1)).is_err());
+}
+
+#[test]
+fn txarchive_fallback_follows_its_policy() {
+    let txid = "cd".repeat(32);
+    let (bump_hex, merkle_root) = single_tx_bump(800_002, &txid);
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, 5, 0, &[(800_002, merkle_root)]);
+    // Nothing synced - the block is only reachable through TxArchive
+    mock::set_chain(chain);
+    let verify = || block_on(crate::bump_verification::verify_tx_bump_async(&txid, &bump_hex, 6));
+
+    let assumed = verify().unwrap();
+    assert!(assumed.verified);
+    assert_eq!(assumed.confirmations, crate::config::CONFIRMATION_DEPTH + 1);
+
+    let policy = get_txarchive_policy();
+    crate::chain_sync::set_txarchive_policy(TxArchivePolicy { trust: TxArchiveTrust::CountFromLocalTip, ..policy.clone() }).unwrap();
+    let counted = verify();
+    assert!(counted.as_ref().is_err_and(|e| e.contains("ahead of our chain tip")), "{:?}", counted);
+
+    crate::chain_sync::set_txarchive_fallback_enabled(false).unwrap();
+    let disabled = verify();
+    assert!(disabled.as_ref().is_err_and(|e| e.contains("fallback is disabled")), "{:?}", disabled);
+    assert_eq!(get_txarchive_policy().trust, TxArchiveTrust::CountFromLocalTip);
+
+    let anonymous = crate::chain_sync::set_txarchive_policy(TxArchivePolicy { canister: Principal::anonymous(), ..policy });
+    assert!(matches!(anonymous, Err(EasySwapError::InvalidInput { .. })));
+}
+
+#[test]
+fn block_sync_requests_wait_for_headers_to_age() {
+    mock::set_caller(filler());
+    let scheduled_at = crate::chain_sync::request_block_sync().expect("never synced");
+    let task = get_scheduled_task("block_sync").unwrap();
+    assert!(task.next_run_at <= get_time() && get_time() < scheduled_at);
+
+    let mut chain = Vec::new();
+    extend_chain(&mut chain, 800_000, 300, 0, &[]);
+    mock::set_chain(chain);
+    block_on(crate::chain_sync::sync_blocks()).expect("synced");
+    let again = crate::chain_sync::request_block_sync();
+    assert!(matches!(again, Err(EasySwapError::LimitExceeded { .. })), "{:?}", again);
+
+    mock::advance_time(crate::config::ON_DEMAND_SYNC_MIN_AGE_SECONDS * 1_000_000_000);
+    assert!(crate::chain_sync::request_block_sync().is_ok());
+}
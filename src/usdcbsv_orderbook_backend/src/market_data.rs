@@ -0,0 +1,84 @@
+/// Public market data for aggregators and bots - one query instead of separate
+/// orderbook, price and trade calls
+use std::collections::BTreeMap;
+use crate::types::*;
+use crate::state::*;
+use crate::config::MAX_PAGE_LIMIT;
+
+const DAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Listed chunks grouped by max price, highest price first (best for fillers)
+pub fn get_orderbook_depth() -> Vec<DepthLevel> {
+    let mut levels: BTreeMap<u64, DepthLevel> = BTreeMap::new();
+
+    for chunk in get_available_chunks().into_iter().filter(|chunk| chunk.is_listed()) {
+        let price_cents = (chunk.max_bsv_price * 100.0).round() as u64;
+        let level = levels.entry(price_cents).or_insert(DepthLevel {
+            max_price_per_bsv_in_cents: price_cents,
+            amount_usd_e6: 0,
+            chunk_count: 0,
+        });
+        level.amount_usd_e6 += chunk.amount_usd_e6;
+        level.chunk_count += 1;
+    }
+
+    levels.into_values().rev().collect()
+}
+
+pub fn get_public_market_data() -> PublicMarketData {
+    let now = get_time();
+    let (bsv_price, price_updated_at) = get_cached_bsv_price();
+
+    let depth = get_orderbook_depth();
+    let total_available_usd_e6 = depth.iter().map(|l| l.amount_usd_e6).sum();
+
+    let mut last_trade: Option<(u64, f64)> = None;
+    let mut volume_24h_usd_e6: UsdE6 = 0;
+    let mut trades_24h = 0u64;
+
+    for trade in get_trades_by_status(TradeStatus::WithdrawalConfirmed) {
+        let completed_at = match trade.withdrawal_confirmed_at {
+            Some(t) => t,
+            None => continue,
+        };
+
+        if last_trade.is_none_or(|(at, _)| completed_at > at) {
+            last_trade = Some((completed_at, trade.agreed_bsv_price));
+        }
+
+        if now.saturating_sub(completed_at) <= DAY_NS {
+            volume_24h_usd_e6 += trade.amount_usd_e6;
+            trades_24h += 1;
+        }
+    }
+
+    PublicMarketData {
+        bsv_price,
+        price_updated_at,
+        last_trade_price: last_trade.map(|(_, price)| price),
+        last_trade_at: last_trade.map(|(at, _)| at),
+        volume_24h_usd_e6,
+        trades_24h,
+        total_available_usd_e6,
+        depth,
+        generated_at: now,
+    }
+}
+
+/// Trade tape for analytics sites - the latest completed trades, newest first, stripped of
+/// anything that identifies the maker or filler
+pub fn get_recent_trades_public(limit: u64) -> Vec<PublicTrade> {
+    let mut tape: Vec<PublicTrade> = get_trades_by_status(TradeStatus::WithdrawalConfirmed).into_iter()
+        .filter_map(|trade| Some(PublicTrade {
+            amount_usd_e6: trade.amount_usd_e6,
+            sats_amount: trade.locked_chunks.iter().map(|lc| lc.sats_amount).sum(),
+            bsv_price: trade.agreed_bsv_price,
+            status: trade.status,
+            completed_at: trade.withdrawal_confirmed_at?,
+        }))
+        .collect();
+
+    tape.sort_by_key(|trade| std::cmp::Reverse(trade.completed_at));
+    tape.truncate(limit.min(MAX_PAGE_LIMIT) as usize);
+    tape
+}
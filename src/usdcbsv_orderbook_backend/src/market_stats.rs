@@ -0,0 +1,79 @@
+/// Rolling market statistics - each completed trade is added to its UTC hour bucket,
+/// windows are summed from the buckets on query
+use candid::Principal;
+use std::collections::HashSet;
+use crate::types::*;
+use crate::state::*;
+use crate::config::{MARKET_STATS_RETENTION_HOURS, SATOSHIS_PER_BSV};
+
+const HOUR_NS: u64 = 60 * 60 * 1_000_000_000;
+
+fn hour_of(timestamp_ns: u64) -> u64 {
+    timestamp_ns / HOUR_NS
+}
+
+/// Record a trade whose ckUSDC was released to the filler
+pub fn record_completed_trade(trade: &Trade, maker: Principal, completed_at: u64) {
+    let hour = hour_of(completed_at);
+    let mut stats = get_hourly_stats(hour).unwrap_or(HourlyMarketStats {
+        hour,
+        volume_usd_e6: 0,
+        volume_sats: 0,
+        trade_count: 0,
+        makers: Vec::new(),
+        fillers: Vec::new(),
+    });
+
+    stats.volume_usd_e6 += trade.amount_usd_e6;
+    stats.volume_sats += trade.locked_chunks.iter().map(|lc| lc.sats_amount).sum::<u64>();
+    stats.trade_count += 1;
+    if !stats.makers.contains(&maker) {
+        stats.makers.push(maker);
+    }
+    if !stats.fillers.contains(&trade.filler) {
+        stats.fillers.push(trade.filler);
+    }
+
+    insert_hourly_stats(stats);
+    prune_hourly_stats(hour.saturating_sub(MARKET_STATS_RETENTION_HOURS));
+}
+
+pub fn get_market_stats(window: StatsWindow) -> MarketStats {
+    let window_hours = match window {
+        StatsWindow::Day => 24,
+        StatsWindow::Week => 7 * 24,
+    };
+    let to_hour = hour_of(get_time());
+    let from_hour = (to_hour + 1).saturating_sub(window_hours);
+
+    let mut volume_usd_e6: UsdE6 = 0;
+    let mut volume_sats = 0u64;
+    let mut trade_count = 0u64;
+    let mut makers: HashSet<Principal> = HashSet::new();
+    let mut fillers: HashSet<Principal> = HashSet::new();
+
+    for bucket in get_hourly_stats_range(from_hour, to_hour) {
+        volume_usd_e6 += bucket.volume_usd_e6;
+        volume_sats += bucket.volume_sats;
+        trade_count += bucket.trade_count;
+        makers.extend(bucket.makers);
+        fillers.extend(bucket.fillers);
+    }
+
+    let average_price = if volume_sats > 0 {
+        Some(e6_to_usd(volume_usd_e6) / (volume_sats as f64 / SATOSHIS_PER_BSV as f64))
+    } else {
+        None
+    };
+
+    MarketStats {
+        window,
+        from_hour,
+        to_hour,
+        volume_usd_e6,
+        trade_count,
+        average_price,
+        unique_makers: makers.len() as u64,
+        unique_fillers: fillers.len() as u64,
+    }
+}
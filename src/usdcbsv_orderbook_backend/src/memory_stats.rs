@@ -0,0 +1,137 @@
+/// Stable memory and heap usage per map, for forecasting when retention needs tightening
+/// Maps are named as in backup sections (admin_event_index is derived, so not backed up). Pages are 64 KiB WebAssembly pages claimed by the
+/// map's virtual memory - the memory manager hands them out in buckets and never returns them,
+/// so pages only shrink by restoring into a fresh canister
+use ic_stable_structures::memory_manager::MemoryId;
+use ic_stable_structures::Memory;
+use crate::types::*;
+use crate::state::*;
+use crate::block_headers::{BLOCK_HEADERS, LEGACY_BLOCK_HEADERS, TESTNET_BLOCK_HEADERS};
+
+const WASM_PAGE_BYTES: u64 = 65_536;
+
+fn map_stats(name: &str, memory_id: u8, records: u64) -> MapMemoryStats {
+    let pages = MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(memory_id)).size());
+    MapMemoryStats {
+        name: name.to_string(),
+        memory_id,
+        pages,
+        bytes: pages * WASM_PAGE_BYTES,
+        records,
+    }
+}
+
+/// Pages of the canister's whole stable memory, memory manager header included
+#[cfg(target_arch = "wasm32")]
+fn stable_pages_total() -> u64 {
+    ic_cdk::api::stable_size()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn stable_pages_total() -> u64 {
+    0
+}
+
+#[cfg(target_arch = "wasm32")]
+fn heap_bytes() -> u64 {
+    core::arch::wasm32::memory_size(0) as u64 * WASM_PAGE_BYTES
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn heap_bytes() -> u64 {
+    0
+}
+
+pub fn get_memory_stats() -> MemoryStats {
+    let mut maps = vec![
+        ORDERS.with(|m| map_stats("orders", 0, m.borrow().len())),
+        CHUNKS.with(|m| map_stats("chunks", 1, m.borrow().len())),
+        TRADES.with(|m| map_stats("trades", 2, m.borrow().len())),
+        FILLER_ACCOUNTS.with(|m| map_stats("filler_accounts", 3, m.borrow().len())),
+        map_stats("app_state", 4, 1),
+        USED_BSV_TXIDS.with(|m| map_stats("used_bsv_txids", 5, m.borrow().len())),
+        ADMIN_EVENTS.with(|m| map_stats("admin_events", 6, m.borrow().len())),
+        LEGACY_BLOCK_HEADERS.with(|m| map_stats("legacy_block_headers", 7, m.borrow().len())),
+        DEPOSIT_RECOVERIES.with(|m| map_stats("deposit_recoveries", 8, m.borrow().len())),
+        DISPUTES.with(|m| map_stats("disputes", 9, m.borrow().len())),
+        MAKER_QUIET_HOURS.with(|m| map_stats("maker_quiet_hours", 10, m.borrow().len())),
+        SETTLEMENT_REPORTS.with(|m| map_stats("settlement_reports", 11, m.borrow().len())),
+        map_stats("schema_state", 12, 1),
+        MARKET_STATS.with(|m| map_stats("market_stats", 13, m.borrow().len())),
+        PRICE_CANDLES.with(|m| map_stats("price_candles", 14, m.borrow().len())),
+        RECONCILIATION_ISSUES.with(|m| map_stats("reconciliation_issues", 15, m.borrow().len())),
+        ETH_WITHDRAWALS.with(|m| map_stats("eth_withdrawals", 16, m.borrow().len())),
+        WITHDRAWAL_ROUTES.with(|m| map_stats("withdrawal_routes", 17, m.borrow().len())),
+        QUOTES.with(|m| map_stats("quotes", 18, m.borrow().len())),
+        SCHEDULED_TASKS.with(|m| map_stats("scheduled_tasks", 19, m.borrow().len())),
+        PENDING_TRANSFERS.with(|m| map_stats("pending_transfers", 20, m.borrow().len())),
+        TRANSFER_DEDUP.with(|m| map_stats("transfer_dedup", 21, m.borrow().len())),
+        APPROVED_FILLERS.with(|m| map_stats("approved_fillers", 22, m.borrow().len())),
+        MAKER_FILLER_BLOCKLISTS.with(|m| map_stats("maker_filler_blocklists", 23, m.borrow().len())),
+        ORDERBOOK_CHANGES.with(|m| map_stats("orderbook_changes", 24, m.borrow().len())),
+        CORRUPT_RECORDS.with(|m| map_stats("corrupt_records", 25, m.borrow().len())),
+        TX_BLOBS.with(|m| map_stats("tx_blobs", 26, m.borrow().len())),
+        DELEGATIONS.with(|m| map_stats("delegations", 27, m.borrow().len())),
+        RELEASE_BONDS.with(|m| map_stats("release_bonds", 28, m.borrow().len())),
+        BLOCK_HEADERS.with(|m| map_stats("block_headers", 29, m.borrow().len())),
+        VERIFIED_INCLUSIONS.with(|m| map_stats("verified_inclusions", 30, m.borrow().len())),
+        MAKER_AUTO_TOP_UPS.with(|m| map_stats("maker_auto_top_ups", 31, m.borrow().len())),
+        REBATE_EPOCHS.with(|m| map_stats("rebate_epochs", 32, m.borrow().len())),
+        MAKER_REBATES.with(|m| map_stats("maker_rebates", 33, m.borrow().len())),
+        BOOKS.with(|m| map_stats("books", 34, m.borrow().len())),
+        TESTNET_BLOCK_HEADERS.with(|m| map_stats("testnet_block_headers", 35, m.borrow().len())),
+        ADMIN_EVENT_INDEX.with(|m| map_stats("admin_event_index", 36, m.borrow().len())),
+        PENALTY_APPEALS.with(|m| map_stats("penalty_appeals", 37, m.borrow().len())),
+        LATE_CLAIMS.with(|m| map_stats("late_claims", 38, m.borrow().len())),
+    ];
+    // Largest first - those are the ones retention tuning is about
+    maps.sort_by(|a, b| b.pages.cmp(&a.pages).then(a.memory_id.cmp(&b.memory_id)));
+
+    let stable_pages = stable_pages_total();
+    MemoryStats {
+        map_pages_total: maps.iter().map(|map| map.pages).sum(),
+        maps,
+        stable_pages_total: stable_pages,
+        stable_bytes_total: stable_pages * WASM_PAGE_BYTES,
+        heap_bytes: heap_bytes(),
+        measured_at: get_time(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use candid::Principal;
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn create_order() -> OrderId {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        let address = crate::lifecycle_tests::p2pkh_address(&[0x11; 20]);
+        block_on(crate::order_management::create_order(6.0, 100.0, address, None)).unwrap()
+    }
+
+
+    #[test]
+    fn memory_stats_cover_every_map_and_count_records() {
+        create_order();
+
+        let stats = get_memory_stats();
+        let mut memory_ids: Vec<u8> = stats.maps.iter().map(|map| map.memory_id).collect();
+        memory_ids.sort();
+        assert_eq!(memory_ids, (0..=38).collect::<Vec<u8>>());
+
+        let orders = stats.maps.iter().find(|map| map.name == "orders").unwrap();
+        let chunks = stats.maps.iter().find(|map| map.name == "chunks").unwrap();
+        assert_eq!(orders.records, 1);
+        assert_eq!(chunks.records, 2);
+        assert!(orders.pages > 0 && orders.bytes == orders.pages * 65_536);
+        assert_eq!(stats.map_pages_total, stats.maps.iter().map(|map| map.pages).sum::<u64>());
+        assert!(stats.maps.windows(2).all(|pair| pair[0].pages >= pair[1].pages));
+    }
+}
@@ -0,0 +1,303 @@
+/// Versioned migrations of the records held in stable memory
+/// post_upgrade validates every pending migration against the stored bytes before
+/// rewriting anything - a record that can't be decoded aborts the upgrade's migration
+/// (leaving data untouched) instead of panicking inside `Decode!`
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::memory_manager::{MemoryId, VirtualMemory};
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap};
+use crate::types::*;
+use crate::state::*;
+use crate::record_envelope::{decode_record, RawRecord, VersionedRecord};
+use crate::block_headers::{BlockHeader, CompactHeader, BLOCK_HEADERS, LEGACY_BLOCK_HEADERS};
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Schema version produced by this build - bump when adding a migration below
+pub const CURRENT_SCHEMA_VERSION: u32 = 4;
+
+// Stable memory IDs of the migrated maps (see state.rs)
+const ORDERS_MEMORY_ID: u8 = 0;
+const CHUNKS_MEMORY_ID: u8 = 1;
+const TRADES_MEMORY_ID: u8 = 2;
+const ADMIN_EVENTS_MEMORY_ID: u8 = 6;
+const LEGACY_BLOCK_HEADERS_MEMORY_ID: u8 = 7;
+
+// Failures beyond this are collapsed into a single "omitted" entry
+const MAX_REPORTED_FAILURES: usize = 50;
+
+struct Migration {
+    version: u32,
+    description: &'static str,
+    validate: fn(&mut MigrationReport),
+    apply: fn(),
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "USD amounts f64 -> micro-USD u64",
+        validate: validate_usd_e6,
+        apply: apply_usd_e6,
+    },
+    Migration {
+        version: 2,
+        description: "Trade tx hex -> TX_BLOBS",
+        validate: validate_tx_blobs,
+        apply: apply_tx_blobs,
+    },
+    Migration {
+        version: 3,
+        description: "Block headers -> 80-byte compact store",
+        validate: validate_compact_headers,
+        apply: apply_compact_headers,
+    },
+    Migration {
+        version: 4,
+        description: "Admin events indexed by kind",
+        validate: validate_admin_event_index,
+        apply: rebuild_admin_event_index,
+    },
+];
+
+/// Read-only view of a stable map's raw values (same memory, same key type)
+fn raw_records(memory_id: u8) -> Vec<(u64, Vec<u8>)> {
+    let memory = MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(memory_id)));
+    let map: StableBTreeMap<u64, RawRecord, Memory> = StableBTreeMap::init(memory);
+    map.iter().map(|(key, value)| (key, value.0)).collect()
+}
+
+fn record_failure(report: &mut MigrationReport, failure: String) {
+    if report.failures.len() < MAX_REPORTED_FAILURES {
+        report.failures.push(failure);
+    } else if report.failures.len() == MAX_REPORTED_FAILURES {
+        report.failures.push("... further failures omitted".to_string());
+    }
+}
+
+// ===== MIGRATION 1: micro-USD amounts =====
+
+fn validate_usd_e6(report: &mut MigrationReport) {
+    for (id, bytes) in raw_records(ORDERS_MEMORY_ID) {
+        report.orders_checked += 1;
+        if let Err(e) = decode_record::<Order>(&bytes) {
+            record_failure(report, format!("order {}: {}", id, e));
+        }
+    }
+    for (id, bytes) in raw_records(CHUNKS_MEMORY_ID) {
+        report.chunks_checked += 1;
+        if let Err(e) = decode_record::<Chunk>(&bytes) {
+            record_failure(report, format!("chunk {}: {}", id, e));
+        }
+    }
+    for (id, bytes) in raw_records(TRADES_MEMORY_ID) {
+        report.trades_checked += 1;
+        match decode_record::<Trade>(&bytes) {
+            // apply_usd_e6 also moves the tx hex out
+            Ok(_) => check_stored_tx_hex(id, &bytes, report),
+            Err(e) => record_failure(report, format!("trade {}: {}", id, e)),
+        }
+    }
+}
+
+/// Rewrite every order, chunk and trade - Storable reads the legacy layout, insert writes the new one
+fn apply_usd_e6() {
+    // Legacy trades carry their tx hex, which Trade no longer has - move it out before the
+    // rewrite drops it (migration 2 then finds nothing left to move)
+    apply_tx_blobs();
+
+    for order in get_all_orders() {
+        insert_order(order);
+    }
+    let chunks: Vec<Chunk> = CHUNKS.with(|c| c.borrow().iter().map(|(_, chunk)| chunk).collect());
+    for chunk in chunks {
+        insert_chunk(chunk);
+    }
+    for trade in get_all_trades() {
+        insert_trade(trade);
+    }
+}
+
+// ===== MIGRATION 2: tx hex out of line =====
+
+/// The fields of a stored trade this migration reads - candid skips the rest, so both the
+/// legacy and the current layout decode into it
+#[derive(CandidType, Deserialize)]
+struct StoredTradeTx {
+    bsv_tx_hex: Option<String>,
+    bsv_txid: Option<String>,
+}
+
+impl VersionedRecord for StoredTradeTx {}
+
+/// A trade's stored tx hex must hash to its recorded txid, if it has one
+fn check_stored_tx_hex(id: TradeId, bytes: &[u8], report: &mut MigrationReport) {
+    let stored = match decode_record::<StoredTradeTx>(bytes) {
+        Ok(stored) => stored,
+        Err(e) => return record_failure(report, format!("trade {}: {}", id, e)),
+    };
+    let Some(tx_hex) = stored.bsv_tx_hex else { return };
+    match compute_bsv_txid(&tx_hex) {
+        Err(e) => record_failure(report, format!("trade {}: stored tx hex has no txid: {}", id, e)),
+        Ok(txid) if stored.bsv_txid.as_ref().is_some_and(|recorded| *recorded != txid) => {
+            record_failure(report, format!("trade {}: stored tx hex hashes to {}, bsv_txid is {:?}", id, txid, stored.bsv_txid));
+        }
+        Ok(_) => {}
+    }
+}
+
+fn validate_tx_blobs(report: &mut MigrationReport) {
+    for (id, bytes) in raw_records(TRADES_MEMORY_ID) {
+        report.trades_checked += 1;
+        match decode_record::<Trade>(&bytes) {
+            Ok(_) => check_stored_tx_hex(id, &bytes, report),
+            Err(e) => record_failure(report, format!("trade {}: {}", id, e)),
+        }
+    }
+}
+
+/// Move each trade's tx hex into TX_BLOBS and rewrite the trade with only its txid
+fn apply_tx_blobs() {
+    for (id, bytes) in raw_records(TRADES_MEMORY_ID) {
+        let Some(tx_hex) = decode_record::<StoredTradeTx>(&bytes).ok().and_then(|stored| stored.bsv_tx_hex) else {
+            continue;
+        };
+        // Unhashable hex fails check_stored_tx_hex, so this is never skipped
+        let Ok(txid) = compute_bsv_txid(&tx_hex) else { continue };
+        if let Some(mut trade) = get_trade(id) {
+            retain_tx_blob(&txid, tx_hex);
+            trade.bsv_txid = Some(txid);
+            insert_trade(trade);
+        }
+    }
+}
+
+// ===== MIGRATION 3: compact block headers =====
+
+fn validate_compact_headers(report: &mut MigrationReport) {
+    for (height, bytes) in raw_records(LEGACY_BLOCK_HEADERS_MEMORY_ID) {
+        if let Err(e) = decode_record::<BlockHeader>(&bytes) {
+            record_failure(report, format!("block header {}: {}", height, e));
+        }
+    }
+}
+
+/// Copy each legacy header into the compact store and empty the legacy map
+/// A header whose bytes don't hash to its recorded hash is dropped - the next sync refetches it
+fn apply_compact_headers() {
+    let legacy: Vec<BlockHeader> = LEGACY_BLOCK_HEADERS.with(|h| h.borrow().iter().map(|(_, header)| header).collect());
+    let mut dropped = 0;
+
+    BLOCK_HEADERS.with(|headers| {
+        let mut headers = headers.borrow_mut();
+        for header in &legacy {
+            match CompactHeader::from_header(header) {
+                Ok(compact) if compact.to_header(header.height).hash == header.hash => {
+                    headers.insert(header.height, compact);
+                }
+                _ => dropped += 1,
+            }
+        }
+    });
+    LEGACY_BLOCK_HEADERS.with(|h| h.borrow_mut().clear_new());
+
+    if dropped > 0 {
+        ic_cdk::println!("⚠️  {} of {} block headers didn't match their hash and were dropped", dropped, legacy.len());
+    }
+}
+
+// ===== MIGRATION 4: admin event index =====
+
+fn validate_admin_event_index(report: &mut MigrationReport) {
+    for (id, bytes) in raw_records(ADMIN_EVENTS_MEMORY_ID) {
+        if let Err(e) = decode_record::<AdminEvent>(&bytes) {
+            record_failure(report, format!("admin event {}: {}", id, e));
+        }
+    }
+}
+
+// ===== RUNNER =====
+
+fn pending_migrations(from_version: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |m| m.version > from_version && m.version <= CURRENT_SCHEMA_VERSION)
+}
+
+/// Validate pending migrations against the stored records without writing anything
+/// With nothing pending, checks that every record decodes under the current schema
+pub fn dry_run() -> MigrationReport {
+    let from_version = get_schema_state().version;
+    let mut report = MigrationReport {
+        from_version,
+        to_version: CURRENT_SCHEMA_VERSION,
+        dry_run: true,
+        orders_checked: 0,
+        chunks_checked: 0,
+        trades_checked: 0,
+        failures: Vec::new(),
+        ran_at: get_time(),
+    };
+
+    // Later migrations read what earlier ones write, so only the next one can be checked up front
+    if let Some(migration) = pending_migrations(from_version).next().or(MIGRATIONS.last()) {
+        (migration.validate)(&mut report);
+    }
+
+    report
+}
+
+/// Called from post_upgrade (and init) - brings stored records up to CURRENT_SCHEMA_VERSION
+/// Each migration is validated first; on any failure the run stops at the last good version
+pub fn run_pending_migrations() {
+    let schema = get_schema_state();
+
+    if schema.rollback_mode {
+        ic_cdk::println!("⏸️  Rollback mode set - leaving stored records at schema v{}", schema.version);
+        return;
+    }
+
+    if schema.version > CURRENT_SCHEMA_VERSION {
+        ic_cdk::println!(
+            "⚠️  Stored schema v{} is newer than this build (v{}) - enable rollback mode before downgrading",
+            schema.version, CURRENT_SCHEMA_VERSION
+        );
+        return;
+    }
+
+    for migration in pending_migrations(schema.version) {
+        let mut report = MigrationReport {
+            from_version: migration.version - 1,
+            to_version: migration.version,
+            dry_run: false,
+            orders_checked: 0,
+            chunks_checked: 0,
+            trades_checked: 0,
+            failures: Vec::new(),
+            ran_at: get_time(),
+        };
+
+        (migration.validate)(&mut report);
+
+        if !report.failures.is_empty() {
+            ic_cdk::println!(
+                "❌ Migration to schema v{} ({}) aborted: {} records failed validation",
+                migration.version, migration.description, report.failures.len()
+            );
+            update_schema_state(|s| s.last_report = Some(report));
+            return;
+        }
+
+        (migration.apply)();
+
+        ic_cdk::println!(
+            "💾 Migrated to schema v{} ({}): {} orders, {} chunks, {} trades",
+            migration.version, migration.description,
+            report.orders_checked, report.chunks_checked, report.trades_checked
+        );
+
+        let now = get_time();
+        update_schema_state(|s| {
+            s.version = migration.version;
+            s.last_migrated_at = Some(now);
+            s.last_report = Some(report);
+        });
+    }
+}
@@ -2,182 +2,388 @@ use crate::types::*;
 use crate::state::*;
 use crate::ckusdc_integration;
 use crate::filler_accounts;
-use crate::config::{MIN_CHUNK_SIZE, MAX_CHUNKS_ALLOWED, MAX_MAKER_TOTAL_ORDERS_USD, MAX_ORDERBOOK_USD_LIMIT, MIN_CYCLES_FOR_NEW_ORDERS, MAKER_FEE_PERCENT, ACTIVATION_FEE_PERCENT, FILLER_INCENTIVE_PERCENT};
-use candid::Principal;
+use crate::runtime::LedgerClient;
+use crate::config::{MIN_CHUNK_SIZE_E6, MAX_CHUNKS_ALLOWED, MAX_MAKER_TOTAL_ORDERS_USD_E6, MIN_CYCLES_FOR_NEW_ORDERS, ACTIVATION_FEE_PERCENT, FILLER_INCENTIVE_PERCENT, FILLER_INCENTIVE_CEILING_BPS, MAX_ORDERS_PER_BATCH, MAX_BULK_ORDER_OPERATIONS, CKUSDC_TRANSFER_FEE, MAX_PAGE_LIMIT};
+use candid::{CandidType, Deserialize, Principal};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
-pub async fn create_order(
-    amount_usd: f64,
-    max_bsv_price: f64,
-    bsv_address: String,
-) -> Result<OrderId, String> {
-    let caller = get_caller();
-    
+thread_local! {
+    // Order IDs allocated by create_order that haven't been inserted yet, with their book
+    // Their subaccounts hold funds of an in-progress activation and must not be recovered
+    static ORDERS_IN_CREATION: RefCell<HashMap<OrderId, BookId>> = RefCell::new(HashMap::new());
+}
+
+/// Marks an order ID as being created until dropped
+struct OrderCreationGuard(OrderId);
+
+impl OrderCreationGuard {
+    fn new(order_id: OrderId, book_id: BookId) -> Self {
+        ORDERS_IN_CREATION.with(|set| set.borrow_mut().insert(order_id, book_id));
+        OrderCreationGuard(order_id)
+    }
+}
+
+/// Book of an order that create_order hasn't stored yet
+pub(crate) fn book_in_creation(order_id: OrderId) -> Option<BookId> {
+    ORDERS_IN_CREATION.with(|set| set.borrow().get(&order_id).copied())
+}
+
+impl Drop for OrderCreationGuard {
+    fn drop(&mut self) {
+        ORDERS_IN_CREATION.with(|set| set.borrow_mut().remove(&self.0));
+    }
+}
+
+/// One order of a create_orders_batch call - the single-order entry points build one as well
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CreateOrderRequest {
+    pub amount_usd: f64,
+    pub max_bsv_price: f64,
+    pub bsv_address: String,
+    pub filler_incentive_bps: Option<u64>,  // None = FILLER_INCENTIVE_PERCENT
+    pub post_only: Option<bool>,            // Hold the chunks Pending for one idle check - see create_order_for
+    pub book_id: Option<BookId>,            // None = the default ckUSDC book
+}
+
+/// Checks that apply to every order of a caller, regardless of its parameters
+fn check_order_creation_allowed(caller: Principal) -> Result<(), EasySwapError> {
     // Reject anonymous principal
     if caller == candid::Principal::anonymous() {
-        return Err("Anonymous principal cannot create orders. Please authenticate first.".to_string());
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot create orders. Please authenticate first."));
     }
     
     // Check if new orders are enabled (emergency control)
     if !are_new_orders_enabled() {
-        return Err("New order creation is disabled due to maintenance or technical fixes. Existing orders and trades continue normally. Please try again later.".to_string());
+        return Err(EasySwapError::unavailable("New order creation is disabled due to maintenance or technical fixes. Existing orders and trades continue normally. Please try again later."));
     }
     
     // Check canister has sufficient cycles to continue operating
-    let cycles_balance = ic_cdk::api::canister_balance128();
+    let cycles_balance = crate::runtime::cycles_balance();
     if cycles_balance < MIN_CYCLES_FOR_NEW_ORDERS {
-        return Err(format!(
+        return Err(EasySwapError::unavailable(format!(
             "Insufficient canister cycles. Current: {:.2} TC, Minimum required: {:.2} TC. Please try again later.",
             cycles_balance as f64 / 1_000_000_000_000.0,
             MIN_CYCLES_FOR_NEW_ORDERS as f64 / 1_000_000_000_000.0
-        ));
+        )));
+    }
+    
+    Ok(())
+}
+
+pub async fn create_order(
+    amount_usd: f64,
+    max_bsv_price: f64,
+    bsv_address: String,
+    filler_incentive_bps: Option<u64>,
+) -> Result<OrderId, EasySwapError> {
+    create_order_in_book(amount_usd, max_bsv_price, bsv_address, filler_incentive_bps, None).await
+}
+
+/// Create an order in `book_id` (None = the default ckUSDC book) - funded like create_order
+pub async fn create_order_in_book(
+    amount_usd: f64,
+    max_bsv_price: f64,
+    bsv_address: String,
+    filler_incentive_bps: Option<u64>,
+    book_id: Option<BookId>,
+) -> Result<OrderId, EasySwapError> {
+    let caller = get_caller();
+    check_order_creation_allowed(caller)?;
+    
+    let mut security_balance = None;
+    let request = CreateOrderRequest { amount_usd, max_bsv_price, bsv_address, filler_incentive_bps, post_only: None, book_id };
+    create_order_for(caller, request, Funding::SecurityDeposit(&mut security_balance)).await
+}
+
+/// Create a post-only order - its chunks wait Pending until the next idle check (or
+/// confirm_order) re-checks the price, so a price that moved between the maker's quote and
+/// activation can't be matched straight away
+pub async fn create_post_only_order(
+    amount_usd: f64,
+    max_bsv_price: f64,
+    bsv_address: String,
+    filler_incentive_bps: Option<u64>,
+    book_id: Option<BookId>,
+) -> Result<OrderId, EasySwapError> {
+    let caller = get_caller();
+    check_order_creation_allowed(caller)?;
+    
+    let mut security_balance = None;
+    let request = CreateOrderRequest { amount_usd, max_bsv_price, bsv_address, filler_incentive_bps, post_only: Some(true), book_id };
+    create_order_for(caller, request, Funding::SecurityDeposit(&mut security_balance)).await
+}
+
+/// Create an order funded from an ICRC-2 allowance - the exact deposit the order subaccount is
+/// short of is pulled from the caller's default ckUSDC account with icrc2_transfer_from
+/// The caller approves this canister for the deposit plus one ledger fee beforehand; if the pull
+/// fails nothing is stored and nothing is left in the order subaccount
+pub async fn create_order_with_allowance(
+    amount_usd: f64,
+    max_bsv_price: f64,
+    bsv_address: String,
+    filler_incentive_bps: Option<u64>,
+    book_id: Option<BookId>,
+) -> Result<OrderId, EasySwapError> {
+    let caller = get_caller();
+    check_order_creation_allowed(caller)?;
+    
+    let request = CreateOrderRequest { amount_usd, max_bsv_price, bsv_address, filler_incentive_bps, post_only: None, book_id };
+    create_order_for(caller, request, Funding::Allowance).await
+}
+
+/// Create and activate up to MAX_ORDERS_PER_BATCH orders in one call
+/// Orders are processed in sequence, so each one sees the limits as left by the previous
+/// ones. The caller's available security balance is read from the ledger at most once
+/// and reused for top-ups across the batch. One failed order doesn't stop the others
+pub async fn create_orders_batch(requests: Vec<CreateOrderRequest>) -> Result<Vec<Result<OrderId, EasySwapError>>, EasySwapError> {
+    let caller = get_caller();
+    check_order_creation_allowed(caller)?;
+    
+    if requests.is_empty() {
+        return Err(EasySwapError::invalid_input("Batch contains no orders"));
+    }
+    if requests.len() > MAX_ORDERS_PER_BATCH {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "Batch contains {} orders, maximum is {}",
+            requests.len(), MAX_ORDERS_PER_BATCH
+        )));
+    }
+    
+    let mut security_balance = None;
+    let mut results = Vec::with_capacity(requests.len());
+    for request in requests {
+        results.push(create_order_for(caller, request, Funding::SecurityDeposit(&mut security_balance)).await);
+    }
+    
+    Ok(results)
+}
+
+/// Caller's available security balance (USD), read from the ledger only on first use
+async fn cached_security_balance(caller: Principal, cache: &mut Option<f64>) -> Result<f64, String> {
+    if let Some(balance) = *cache {
+        return Ok(balance);
+    }
+    let balance = filler_accounts::get_available_security_balance(caller).await?;
+    *cache = Some(balance);
+    Ok(balance)
+}
+
+/// Where create_order_for covers a shortfall in the order subaccount
+enum Funding<'a> {
+    /// The caller's security deposit subaccount - the balance is cached between orders of a batch
+    SecurityDeposit(&'a mut Option<f64>),
+    /// An ICRC-2 allowance on the caller's default ckUSDC account
+    Allowance,
+}
+
+/// Validate, fund and activate a single order
+async fn create_order_for(caller: Principal, request: CreateOrderRequest, funding: Funding<'_>) -> Result<OrderId, EasySwapError> {
+    let CreateOrderRequest { amount_usd, max_bsv_price, bsv_address, filler_incentive_bps, post_only, book_id } = request;
+    let post_only = post_only.unwrap_or(false);
+    let book = crate::books::resolve(book_id)?;
+    if !book.enabled {
+        return Err(EasySwapError::unavailable(format!("Book {} ({}) is closed to new orders", book.id, book.settlement_symbol)));
     }
     
     // Validate amount is positive and multiple of minimum chunk size
     if amount_usd <= 0.0 {
-        return Err("Amount must be greater than zero".to_string());
+        return Err(EasySwapError::invalid_input("Amount must be greater than zero"));
     }
     
-    let remainder = amount_usd % MIN_CHUNK_SIZE;
-    if amount_usd < MIN_CHUNK_SIZE || remainder.abs() > 0.000001 {
-        return Err(format!("Amount must be a multiple of ${}", MIN_CHUNK_SIZE));
+    // All accounting below is in micro-USD
+    let amount_e6 = usd_to_e6(amount_usd);
+    if amount_e6 < MIN_CHUNK_SIZE_E6 || !amount_e6.is_multiple_of(MIN_CHUNK_SIZE_E6) {
+        return Err(EasySwapError::invalid_input(format!("Amount must be a multiple of ${}", e6_to_usd(MIN_CHUNK_SIZE_E6))));
     }
     
     // Validate amount doesn't exceed maximum allowed
-    let max_order_size = MIN_CHUNK_SIZE * (MAX_CHUNKS_ALLOWED as f64);
-    if amount_usd > max_order_size {
-        return Err(format!("Amount cannot exceed ${} (max {} chunks of ${})", max_order_size, MAX_CHUNKS_ALLOWED, MIN_CHUNK_SIZE));
+    let max_order_size_e6 = MIN_CHUNK_SIZE_E6 * MAX_CHUNKS_ALLOWED as u64;
+    if amount_e6 > max_order_size_e6 {
+        return Err(EasySwapError::limit_exceeded(format!("Amount cannot exceed ${} (max {} chunks of ${})", e6_to_usd(max_order_size_e6), MAX_CHUNKS_ALLOWED, e6_to_usd(MIN_CHUNK_SIZE_E6))));
     }
     
-    // Validate BSV address format (mainnet)
-    if !is_valid_bsv_mainnet_address(&bsv_address) {
-        return Err("Invalid BSV mainnet address".to_string());
+    // Validate BSV address format on the network the canister follows
+    let network = get_bsv_network();
+    if book.bsv_network != network {
+        return Err(EasySwapError::unavailable(format!(
+            "Book {} pays makers on BSV {:?} but this canister follows {:?}",
+            book.id, book.bsv_network, network
+        )));
+    }
+    if !is_valid_bsv_address(&bsv_address, network) {
+        return Err(EasySwapError::invalid_input(invalid_address_message(network)));
     }
     
     // Validate max price
     if max_bsv_price <= 0.0 {
-        return Err("Max BSV price must be positive".to_string());
+        return Err(EasySwapError::invalid_input("Max BSV price must be positive"));
     }
     
-    // Check if adding this order would exceed the orderbook limit
-    let current_orderbook = get_available_orderbook();
-    if current_orderbook + amount_usd > MAX_ORDERBOOK_USD_LIMIT {
-        return Err(format!(
+    // Validate the offered filler incentive against the admin-set bounds
+    let filler_incentive_bps = filler_incentive_bps.unwrap_or(FILLER_INCENTIVE_PERCENT);
+    let bounds = get_filler_incentive_bounds();
+    if filler_incentive_bps < bounds.min_bps || filler_incentive_bps > bounds.max_bps {
+        return Err(EasySwapError::invalid_input(format!(
+            "Filler incentive must be between {:.2}% and {:.2}%",
+            bounds.min_bps as f64 / 100.0, bounds.max_bps as f64 / 100.0
+        )));
+    }
+    
+    // Check if adding this order would exceed the book's orderbook limit
+    let current_orderbook_e6 = crate::books::available_e6(book.id);
+    let orderbook_limit_e6 = crate::books::max_orderbook_usd_e6(&book);
+    if current_orderbook_e6 + amount_e6 > orderbook_limit_e6 {
+        return Err(EasySwapError::limit_exceeded(format!(
             "Orderbook limit exceeded. Current orderbook: ${:.2}, Your order: ${:.2}, Total would be: ${:.2}, Limit: ${:.2}. Please wait for existing orders to be filled.",
-            current_orderbook, amount_usd, current_orderbook + amount_usd, MAX_ORDERBOOK_USD_LIMIT
-        ));
+            e6_to_usd(current_orderbook_e6), e6_to_usd(amount_e6), e6_to_usd(current_orderbook_e6 + amount_e6), e6_to_usd(orderbook_limit_e6)
+        )));
     }
     
     ic_cdk::println!("✅ Orderbook limit check passed: ${:.2} + ${:.2} = ${:.2} / ${:.2}", 
-        current_orderbook, amount_usd, current_orderbook + amount_usd, MAX_ORDERBOOK_USD_LIMIT);
+        e6_to_usd(current_orderbook_e6), e6_to_usd(amount_e6), e6_to_usd(current_orderbook_e6 + amount_e6), e6_to_usd(orderbook_limit_e6));
     
     // Check maker's total active order value doesn't exceed limit
     let maker_orders = get_orders_by_maker(caller);
-    let total_active_value: f64 = maker_orders.iter()
+    let total_active_value_e6: UsdE6 = maker_orders.iter()
         .filter(|o| matches!(
             o.status, 
             OrderStatus::Active | OrderStatus::Idle
         ))
         .map(|o| {
             // For partially filled orders, count only remaining unfilled amount
-            o.amount_usd - o.total_filled_usd
+            o.amount_usd_e6.saturating_sub(o.total_filled_usd_e6)
         })
         .sum();
     
-    let new_total = total_active_value + amount_usd;
+    let new_total_e6 = total_active_value_e6 + amount_e6;
     
-    if new_total > MAX_MAKER_TOTAL_ORDERS_USD {
-        return Err(format!(
+    if new_total_e6 > MAX_MAKER_TOTAL_ORDERS_USD_E6 {
+        return Err(EasySwapError::limit_exceeded(format!(
             "Maker order limit exceeded. Current active orders: ${:.2}, New order: ${:.2}, Total: ${:.2}, Limit: ${:.2}. Please wait for existing orders to be filled or cancel them.",
-            total_active_value, amount_usd, new_total, MAX_MAKER_TOTAL_ORDERS_USD
-        ));
+            e6_to_usd(total_active_value_e6), e6_to_usd(amount_e6), e6_to_usd(new_total_e6), e6_to_usd(MAX_MAKER_TOTAL_ORDERS_USD_E6)
+        )));
     }
     
-    ic_cdk::println!("✅ Maker limit check passed: ${:.2} / ${:.2}", new_total, MAX_MAKER_TOTAL_ORDERS_USD);
+    ic_cdk::println!("✅ Maker limit check passed: ${:.2} / ${:.2}", e6_to_usd(new_total_e6), e6_to_usd(MAX_MAKER_TOTAL_ORDERS_USD_E6));
     
     // ALWAYS increment order ID - even if activation fails, we keep the ID sequence
     let order_id = create_order_id();
+    let _creation_guard = OrderCreationGuard::new(order_id, book.id);
     
     // Get deposit info for ckUSDC
-    let deposit_info = ckusdc_integration::get_deposit_info_for_order(caller, order_id).await?;
+    let deposit_info = ckusdc_integration::get_deposit_info_for_order(caller, order_id).await.map_err(EasySwapError::ledger)?;
     
     ic_cdk::println!("========================================");
     ic_cdk::println!("🆕 CREATE_ORDER (with auto-activation)");
     ic_cdk::println!("   Order ID: {}", order_id);
     ic_cdk::println!("   Maker: {}", caller);
-    ic_cdk::println!("   Amount: ${:.2}", amount_usd);
+    ic_cdk::println!("   Amount: ${:.2}", e6_to_usd(amount_e6));
     ic_cdk::println!("   IC Principal: {}", deposit_info.principal);
     ic_cdk::println!("   Subaccount: {}", deposit_info.subaccount_hex);
     ic_cdk::println!("========================================");
     
     let now = get_time();
     
-    // Calculate fees - exact percentage calculations with f64 precision
-    // MAKER_FEE_PERCENT = 350 basis points = 3.5%
-    // ACTIVATION_FEE_PERCENT = 150 basis points = 1.5% (sent to treasury, non-refundable)
-    // FILLER_INCENTIVE_PERCENT = 200 basis points = 2.0% (stays in order balance, paid to filler on completion)
-    let maker_fee_usd = amount_usd * (MAKER_FEE_PERCENT as f64 / 10000.0);  // 3.5% total
-    let activation_fee_usd = amount_usd * (ACTIVATION_FEE_PERCENT as f64 / 10000.0); // 1.5% to treasury
-    let filler_incentive_reserved = amount_usd * (FILLER_INCENTIVE_PERCENT as f64 / 10000.0); // 2.0% stays in balance
-    let required_deposit_usd = amount_usd + maker_fee_usd; // What maker must deposit = amount + 3.5%
-    
-    ic_cdk::println!("💰 Fee Breakdown for ${:.6}:", amount_usd);
-    ic_cdk::println!("   Activation Fee (1.5%): ${:.6}", activation_fee_usd);
-    ic_cdk::println!("   Filler Incentive (2.0%): ${:.6}", filler_incentive_reserved);
-    ic_cdk::println!("   Total Maker Fee (3.5%): ${:.6}", maker_fee_usd);
-    ic_cdk::println!("   Total Required Deposit: ${:.6}", required_deposit_usd);
+    // Calculate fees in micro-USD (basis points, rounded down)
+    // ACTIVATION_FEE_PERCENT (sent to treasury, non-refundable)
+    // filler_incentive_bps - the maker's offer (stays in order balance, paid to filler on completion)
+    let activation_fee_e6 = bps_of(amount_e6, ACTIVATION_FEE_PERCENT);
+    let filler_incentive_reserved_e6 = bps_of(amount_e6, filler_incentive_bps);
+    let maker_fee_e6 = activation_fee_e6 + filler_incentive_reserved_e6;
+    let required_deposit_e6 = amount_e6 + maker_fee_e6; // What maker must deposit = amount + fees
+    
+    ic_cdk::println!("💰 Fee Breakdown for ${:.6}:", e6_to_usd(amount_e6));
+    ic_cdk::println!("   Activation Fee ({:.2}%): ${:.6}", ACTIVATION_FEE_PERCENT as f64 / 100.0, e6_to_usd(activation_fee_e6));
+    ic_cdk::println!("   Filler Incentive ({:.2}%): ${:.6}", filler_incentive_bps as f64 / 100.0, e6_to_usd(filler_incentive_reserved_e6));
+    ic_cdk::println!("   Total Maker Fee: ${:.6}", e6_to_usd(maker_fee_e6));
+    ic_cdk::println!("   Total Required Deposit: ${:.6}", e6_to_usd(required_deposit_e6));
     
     // Check ckUSDC balance in order subaccount BEFORE creating the order
-    let balance_e6 = ckusdc_integration::get_order_ckusdc_balance(caller, order_id).await?;
-    let balance_usd = ckusdc_integration::ckusdc_e6_to_usd(balance_e6);
+    let mut balance_e6 = ckusdc_integration::get_order_ckusdc_balance(caller, order_id).await.map_err(EasySwapError::ledger)? as UsdE6;
+    let required_deposit_usd = e6_to_usd(required_deposit_e6);
+    
+    ic_cdk::println!("💰 Current balance in order subaccount: ${:.6}", e6_to_usd(balance_e6));
+    
+    // An allowance covers the whole shortfall or the order isn't created, so the security
+    // deposit top-up below never runs for it
+    let mut no_security_balance = None;
+    let security_balance = match funding {
+        Funding::SecurityDeposit(security_balance) => security_balance,
+        Funding::Allowance => {
+            if balance_e6 < required_deposit_e6 {
+                balance_e6 = fund_order_from_allowance(caller, order_id, balance_e6, required_deposit_e6).await?;
+            }
+            &mut no_security_balance
+        }
+    };
     
-    ic_cdk::println!("💰 Current balance in order subaccount: ${:.6}", balance_usd);
+    // Opted-in makers have the shortfall pulled from their wallet; if that fails the
+    // security deposit top-up below still gets its turn
+    if balance_e6 < required_deposit_e6 && crate::wallet_top_up::covers(caller, required_deposit_e6 - balance_e6) {
+        match fund_order_from_allowance(caller, order_id, balance_e6, required_deposit_e6).await {
+            Ok(new_balance_e6) => balance_e6 = new_balance_e6,
+            Err(e) => ic_cdk::println!("⚠️ Wallet top-up for order {} failed: {}", order_id, e),
+        }
+    }
+    let balance_usd = e6_to_usd(balance_e6);
+    
+    // Security deposits are ckUSDC - other books' orders are funded in their own token
+    if balance_e6 < required_deposit_e6 && !crate::books::settles_in_ckusdc(&book) {
+        return Err(EasySwapError::insufficient_funds(format!(
+            "Order #{} created but not activated. Insufficient balance: ${:.6} / ${:.6} required. Please deposit ${:.6} more {} to: {}",
+            order_id, balance_usd, required_deposit_usd, required_deposit_usd - balance_usd, book.settlement_symbol, deposit_info.subaccount_hex
+        )));
+    }
     
     // If insufficient balance, try to top up from user's security deposit account
-    if balance_usd < required_deposit_usd {
-        let shortfall = required_deposit_usd - balance_usd;
+    if balance_e6 < required_deposit_e6 {
+        let shortfall_e6 = required_deposit_e6 - balance_e6;
+        let shortfall = e6_to_usd(shortfall_e6);
         ic_cdk::println!("⚠️ Insufficient balance. Required: ${:.6}, Found: ${:.6}, Shortfall: ${:.6}", 
             required_deposit_usd, balance_usd, shortfall);
         
         // Check if user has available balance in their security deposit account
-        match filler_accounts::get_available_security_balance(caller).await {
+        match cached_security_balance(caller, security_balance).await {
             Ok(available_balance) => {
-                if available_balance >= shortfall {
+                if usd_to_e6(available_balance) >= shortfall_e6 {
                     ic_cdk::println!("🔄 Attempting to transfer ${:.6} from user security deposit...", shortfall);
                     
-                    match transfer_from_user_account_to_order(caller, order_id, shortfall).await {
+                    match transfer_from_user_account_to_order(caller, order_id, shortfall_e6).await {
                         Ok(block_index) => {
                             ic_cdk::println!("✅ Transferred ${:.6} from user account (block: {})", shortfall, block_index);
+                            *security_balance = Some(available_balance - e6_to_usd(shortfall_e6 + CKUSDC_TRANSFER_FEE as UsdE6));
                             
                             // Re-check balance after transfer
-                            let new_balance_e6 = ckusdc_integration::get_order_ckusdc_balance(caller, order_id).await?;
-                            let new_balance_usd = ckusdc_integration::ckusdc_e6_to_usd(new_balance_e6);
+                            let new_balance_e6 = ckusdc_integration::get_order_ckusdc_balance(caller, order_id).await.map_err(EasySwapError::ledger)? as UsdE6;
+                            let new_balance_usd = e6_to_usd(new_balance_e6);
                             
-                            if new_balance_usd < required_deposit_usd {
-                                return Err(format!(
+                            if new_balance_e6 < required_deposit_e6 {
+                                return Err(EasySwapError::insufficient_funds(format!(
                                     "Order #{} created but not activated. Transfer succeeded but balance still insufficient: ${:.6} / ${:.6} required. Please deposit more ckUSDC to subaccount: {}",
                                     order_id, new_balance_usd, required_deposit_usd, deposit_info.subaccount_hex
-                                ));
+                                )));
                             }
                         },
                         Err(e) => {
-                            return Err(format!(
+                            return Err(EasySwapError::insufficient_funds(format!(
                                 "Order #{} created but not activated. Insufficient balance in order subaccount (${:.6}) and transfer from user account failed: {}. Please deposit ${:.6} ckUSDC to: {}",
                                 order_id, balance_usd, e, shortfall, deposit_info.subaccount_hex
-                            ));
+                            )));
                         }
                     }
                 } else {
-                    return Err(format!(
+                    return Err(EasySwapError::insufficient_funds(format!(
                         "Order #{} created but not activated. Insufficient funds. Order subaccount: ${:.6}, Available in security deposit: ${:.6}, Required: ${:.6}. Please deposit ${:.6} more ckUSDC to: {}",
                         order_id, balance_usd, available_balance, required_deposit_usd, shortfall, deposit_info.subaccount_hex
-                    ));
+                    )));
                 }
             },
             Err(_) => {
-                return Err(format!(
+                return Err(EasySwapError::insufficient_funds(format!(
                     "Order #{} created but not activated. Insufficient balance: ${:.6} / ${:.6} required. Please deposit ${:.6} more ckUSDC to: {}",
                     order_id, balance_usd, required_deposit_usd, shortfall, deposit_info.subaccount_hex
-                ));
+                )));
             }
         }
     }
@@ -186,9 +392,9 @@ pub async fn create_order(
     ic_cdk::println!("✅ Balance sufficient (${:.6}), creating and activating order...", balance_usd);
     
     // Transfer activation fee to treasury
-    ic_cdk::println!("💸 Transferring ${:.6} activation fee to treasury", activation_fee_usd);
-    let treasury_principal = ic_cdk::api::id(); // Treasury is the canister itself
-    let fee_amount_e6 = ckusdc_integration::usd_to_ckusdc_e6(activation_fee_usd);
+    ic_cdk::println!("💸 Transferring ${:.6} activation fee to treasury", e6_to_usd(activation_fee_e6));
+    let treasury_principal = crate::runtime::canister_id(); // Treasury is the canister itself
+    let fee_amount_e6 = activation_fee_e6 as u128;
     
     let activation_block_index = ckusdc_integration::transfer_activation_fee_to_treasury(
         caller,
@@ -196,26 +402,34 @@ pub async fn create_order(
         treasury_principal,
         fee_amount_e6,
         Some(format!("Activation O{}", order_id).into_bytes()),
-    ).await?;
+    ).await.map_err(EasySwapError::ledger)?;
     
     ic_cdk::println!("✅ Activation fee transferred! Block index: {}", activation_block_index);
+    crate::treasury::record_order_inflow(
+        order_id,
+        TreasuryInflowKind::ActivationFee,
+        activation_fee_e6.saturating_sub(CKUSDC_TRANSFER_FEE as u64),
+    );
     
     // Get current BSV price to determine if chunks should be Available or Idle
     let (current_bsv_price, _) = crate::state::get_cached_bsv_price();
     let price_exceeds_max = current_bsv_price > max_bsv_price;
     
     // Determine initial status and idle amount
-    let (initial_status, initial_idle_usd, chunk_status) = if price_exceeds_max {
+    let (initial_status, initial_idle_e6, chunk_status) = if price_exceeds_max {
         ic_cdk::println!("⚠️ Current BSV price ${:.4} exceeds max ${:.4} - order starts as Idle", current_bsv_price, max_bsv_price);
-        (OrderStatus::Idle, amount_usd, ChunkStatus::Idle)
+        (OrderStatus::Idle, amount_e6, ChunkStatus::Idle)
+    } else if post_only {
+        ic_cdk::println!("⏸️ Post-only order - chunks stay Pending until the next idle check");
+        (OrderStatus::Active, 0, ChunkStatus::Pending)
     } else {
         ic_cdk::println!("✅ Current BSV price ${:.4} within limit ${:.4} - order starts as Active", current_bsv_price, max_bsv_price);
-        (OrderStatus::Active, 0.0, ChunkStatus::Available)
+        (OrderStatus::Active, 0, ChunkStatus::Available)
     };
     
     // Create chunks with correct status from the start
-    let chunk_amount = MIN_CHUNK_SIZE;
-    let num_chunks = (amount_usd / chunk_amount).round() as u64;
+    let chunk_amount_e6 = MIN_CHUNK_SIZE_E6;
+    let num_chunks = amount_e6 / chunk_amount_e6;
     let mut chunk_ids = Vec::new();
     
     for _ in 0..num_chunks {
@@ -224,13 +438,21 @@ pub async fn create_order(
         let chunk = Chunk {
             id: chunk_id,
             order_id,
-            amount_usd: chunk_amount,
-            status: chunk_status.clone(), // Use the determined status (Available or Idle)
+            amount_usd_e6: chunk_amount_e6,
+            status: chunk_status.clone(), // Use the determined status (Available, Idle or Pending)
             locked_by: None,
             filled_at: None,
             bsv_address: bsv_address.clone(),
             sats_amount: None,  // Will be set at trade creation time
             max_bsv_price,  // Inherit from order
+            filler_incentive_bps: Some(filler_incentive_bps),
+            lock_count: Some(0),
+            timeout_release_count: Some(0),
+            last_timeout_filler: None,
+            reserved_by: None,
+            reserved_until: None,
+            hidden: None,
+            book_id: Some(book.id),
         };
         insert_chunk(chunk);
         chunk_ids.push(chunk_id);
@@ -238,13 +460,14 @@ pub async fn create_order(
     
     ic_cdk::println!("✅ Created {} chunks with status {:?}", num_chunks, chunk_status);
     
-    let order = Order {
+    let mut order = Order {
         id: order_id,
         maker: caller,
-        amount_usd,
-        total_deposited_usd: Some(balance_usd),
-        activation_fee_usd: Some(activation_fee_usd),
-        filler_incentive_reserved: Some(filler_incentive_reserved),
+        amount_usd_e6: amount_e6,
+        total_deposited_usd_e6: Some(balance_e6),
+        activation_fee_usd_e6: Some(activation_fee_e6),
+        filler_incentive_reserved_e6: Some(filler_incentive_reserved_e6),
+        filler_incentive_bps: Some(filler_incentive_bps),
         deposit_principal: deposit_info.principal.to_string(),
         deposit_subaccount: deposit_info.subaccount_hex,
         max_bsv_price,
@@ -257,12 +480,21 @@ pub async fn create_order(
         funded_at: Some(now),
         activation_fee_block_index: Some(activation_block_index),
         activation_fee_confirmed_at: Some(now),
-        total_filled_usd: 0.0,
-        total_locked_usd: 0.0,
-        total_idle_usd: initial_idle_usd,
-        total_refunded_usd: None,
+        total_filled_usd_e6: 0,
+        total_locked_usd_e6: 0,
+        total_idle_usd_e6: initial_idle_e6,
+        total_refunded_usd_e6: None,
         refund_attempts: Vec::new(),
+        finalization_pending_since: None,
+        hidden: None,
+        idle_since: None,
+        last_idle_at: None,
+        idle_duration_total_ns: None,
+        auto_cancel_above_price: None,
+        post_only: Some(post_only),
+        book_id: Some(book.id),
     };
+    order.track_idle(now);
     
     insert_order(order);
     
@@ -271,21 +503,74 @@ pub async fn create_order(
     Ok(order_id)
 }
 
+/// Pull the order subaccount's shortfall from the maker's ICRC-2 allowance - returns the new balance
+async fn fund_order_from_allowance(
+    maker: Principal,
+    order_id: OrderId,
+    balance_e6: UsdE6,
+    required_deposit_e6: UsdE6,
+) -> Result<UsdE6, EasySwapError> {
+    use candid::Nat;
+    use icrc_ledger_types::icrc1::account::Account;
+    use icrc_ledger_types::icrc1::transfer::Memo;
+    use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
+    
+    let shortfall_e6 = required_deposit_e6 - balance_e6;
+    ic_cdk::println!("💸 Pulling ${:.6} from {}'s allowance into order {}", e6_to_usd(shortfall_e6), maker, order_id);
+    
+    let memo = format!("Order {} allowance funding", order_id).into_bytes();
+    let transfer_args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account { owner: maker, subaccount: None },
+        to: ckusdc_integration::get_order_deposit_account(maker, order_id),
+        amount: Nat::from(shortfall_e6),
+        fee: None, // Ledger will use default fee, paid by the maker on top of the amount
+        memo: Some(Memo::from(memo.clone())),
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+    };
+    
+    let ledger_id = crate::books::order_ledger(order_id);
+    let result = crate::runtime::ledger().icrc2_transfer_from(ledger_id, transfer_args).await;
+    
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
+    
+    let failure = match result {
+        Ok((Ok(block_index),)) | Ok((Err(TransferFromError::Duplicate { duplicate_of: block_index }),)) => {
+            ic_cdk::println!("✅ Allowance pull successful! Block: {}", block_index);
+            let new_balance_e6 = ckusdc_integration::get_order_ckusdc_balance(maker, order_id).await.map_err(EasySwapError::ledger)? as UsdE6;
+            if new_balance_e6 < required_deposit_e6 {
+                return Err(EasySwapError::insufficient_funds(format!(
+                    "Order #{} created but not activated. Allowance pull succeeded but balance still insufficient: ${:.6} / ${:.6} required",
+                    order_id, e6_to_usd(new_balance_e6), e6_to_usd(required_deposit_e6)
+                )));
+            }
+            return Ok(new_balance_e6);
+        }
+        Ok((Err(TransferFromError::InsufficientAllowance { allowance }),)) => format!("allowance is only {} e6", allowance),
+        Ok((Err(e),)) => format!("Transfer failed: {:?}", e),
+        Err((code, msg)) => return Err(EasySwapError::ledger(format!("Call failed: {:?}: {}", code, msg))),
+    };
+    
+    Err(EasySwapError::insufficient_funds(format!(
+        "Order #{} not created: pulling ${:.6} from your wallet failed ({}). Approve this canister for at least ${:.6} ckUSDC (deposit plus ledger fee) and try again.",
+        order_id, e6_to_usd(shortfall_e6), failure, e6_to_usd(shortfall_e6 + CKUSDC_TRANSFER_FEE as UsdE6)
+    )))
+}
+
 /// Helper function to transfer funds from caller's security deposit subaccount to order subaccount
 /// The caller's subaccount is shared for all canister operations (maker orders + filler trades)
 /// Only transfers the available balance (after accounting for locked security deposits in active trades)
 async fn transfer_from_user_account_to_order(
     user: Principal,
     order_id: OrderId,
-    amount_usd: f64,
+    amount_e6: UsdE6,
 ) -> Result<u64, String> {
-    use crate::ckusdc_integration::usd_to_ckusdc_e6;
     use candid::{Nat, Principal as CandidPrincipal};
     use icrc_ledger_types::icrc1::account::Account;
     use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError, Memo};
     
-    let amount_e6 = usd_to_ckusdc_e6(amount_usd);
-    
     // Get user's security deposit subaccount (same subaccount used for trading)
     let from_account = filler_accounts::get_deposit_account(user);
     
@@ -293,25 +578,29 @@ async fn transfer_from_user_account_to_order(
     let to_account = ckusdc_integration::get_order_deposit_account(user, order_id);
     
     ic_cdk::println!("💸 Transferring ${:.6} ({} e6) from user {} account to order {}", 
-        amount_usd, amount_e6, user, order_id);
+        e6_to_usd(amount_e6), amount_e6, user, order_id);
     
+    let memo = format!("Order {} funding", order_id).into_bytes();
     let transfer_args = TransferArg {
         from_subaccount: from_account.subaccount,
         to: to_account,
         fee: None, // Ledger will use default fee
-        created_at_time: None,
-        memo: Some(Memo::from(format!("Order {} funding", order_id).into_bytes())),
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+        memo: Some(Memo::from(memo.clone())),
         amount: Nat::from(amount_e6),
     };
     
     let ledger_id = CandidPrincipal::from_text(crate::config::CK_USDC_LEDGER)
         .map_err(|e| format!("Invalid ledger canister ID: {:?}", e))?;
     
-    let result: Result<(Result<Nat, TransferError>,), _> = 
-        ic_cdk::call(ledger_id, "icrc1_transfer", (transfer_args,)).await;
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_id, transfer_args).await;
+    
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
     
     match result {
-        Ok((Ok(block_index),)) => {
+        Ok((Ok(block_index),)) | Ok((Err(TransferError::Duplicate { duplicate_of: block_index }),)) => {
             let block_u64 = crate::ckusdc_integration::nat_to_u64(&block_index)
                 .unwrap_or_else(|_| 0);
             ic_cdk::println!("✅ Transfer successful! Block: {}", block_u64);
@@ -328,17 +617,15 @@ async fn transfer_from_user_account_to_order(
 // ===== QUERY FUNCTIONS =====
 
 /// Get all orders for the caller (for "Past Orders" page - shows everything)
-pub fn get_my_orders() -> Vec<Order> {
-    let caller = get_caller();
+/// `caller` is the maker, or the maker a delegation acts for (resolved in lib.rs)
+pub fn get_my_orders(caller: Principal) -> Vec<Order> {
     get_orders_by_maker(caller)
 }
 
 /// Get active orders for the caller (for "My Requests" page - only orders with active chunks)
 /// An order is "active" if it has ANY chunks with status: Available, Idle, or Locked
 /// Optimized to filter at storage level instead of loading all orders into memory
-pub fn get_my_active_orders() -> Vec<Order> {
-    let caller = get_caller();
-    
+pub fn get_my_active_orders(caller: Principal) -> Vec<Order> {
     // Filter at storage level to avoid loading unnecessary orders
     ORDERS.with(|orders| {
         let mut results: Vec<Order> = orders.borrow().iter()
@@ -349,10 +636,10 @@ pub fn get_my_active_orders() -> Vec<Order> {
                 }
                 
                 
-                // Include if order has any active chunks (Available, Idle, or Locked)
+                // Include if order has any active chunks (Available, Idle, Pending or Locked)
                 order.chunks.iter().any(|chunk_id| {
                     if let Some(chunk) = crate::state::get_chunk(*chunk_id) {
-                        matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Locked)
+                        matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending | ChunkStatus::Locked)
                     } else {
                         false
                     }
@@ -367,40 +654,33 @@ pub fn get_my_active_orders() -> Vec<Order> {
     })
 }
 
-pub fn get_my_orders_paginated(offset: u64, limit: u64, status_filter: Option<Vec<OrderStatus>>) -> PaginatedOrders {
+/// Cursor page of the caller's orders, newest first
+pub fn get_my_orders_page(after_id: Option<OrderId>, limit: u64, status_filter: Option<Vec<OrderStatus>>) -> OrderPage {
     let caller = get_caller();
+    let limit = limit.min(MAX_PAGE_LIMIT) as usize;
     
-    // Filter at storage level to avoid loading unnecessary orders
-    let filtered_orders: Vec<Order> = crate::state::ORDERS.with(|orders| {
-        let mut results: Vec<Order> = orders.borrow().iter()
-            .filter(|(_, order)| {
-                // Must be owned by caller
-                if order.maker != caller {
-                    return false;
-                }
-                
-                // Apply status filter if provided
-                if let Some(ref statuses) = status_filter {
-                    statuses.contains(&order.status)
-                } else {
-                    true // No filter, include all
-                }
-            })
-            .map(|(_, order)| order)
-            .collect();
-        
-        // Sort by created_at descending (newest first)
-        results.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-        results
+    let (orders, next_cursor) = ORDERS.with(|orders| {
+        scan_page(&orders.borrow(), after_id, limit, true, |order| {
+            order.maker == caller && status_filter.as_ref().is_none_or(|statuses| statuses.contains(&order.status))
+        })
     });
     
-    let total = filtered_orders.len() as u64;
+    OrderPage { orders, next_cursor }
+}
+
+/// Offset wrapper over the same newest-first scan as get_my_orders_page
+pub fn get_my_orders_paginated(offset: u64, limit: u64, status_filter: Option<Vec<OrderStatus>>) -> PaginatedOrders {
+    let caller = get_caller();
+    let keep = |order: &Order| {
+        order.maker == caller && status_filter.as_ref().is_none_or(|statuses| statuses.contains(&order.status))
+    };
     
-    let start = offset as usize;
-    let orders: Vec<Order> = filtered_orders.into_iter()
-        .skip(start)
-        .take(limit as usize)
-        .collect();
+    let (total, orders) = ORDERS.with(|orders| {
+        let map = orders.borrow();
+        let total = map.iter().filter(|(_, order)| keep(order)).count() as u64;
+        let (page, _) = scan_page(&map, None, offset.saturating_add(limit) as usize, true, keep);
+        (total, page.into_iter().skip(offset as usize).collect())
+    });
     
     PaginatedOrders {
         orders,
@@ -426,10 +706,10 @@ pub fn get_my_active_orders_paginated(offset: u64, limit: u64) -> PaginatedOrder
                 }
                 
                 // Include if order is awaiting deposit
-                // Include if order has any active chunks (Available, Idle, or Locked)
+                // Include if order has any active chunks (Available, Idle, Pending or Locked)
                 order.chunks.iter().any(|chunk_id| {
                     if let Some(chunk) = crate::state::get_chunk(*chunk_id) {
-                        matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Locked)
+                        matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending | ChunkStatus::Locked)
                     } else {
                         false
                     }
@@ -494,10 +774,13 @@ pub fn get_order_chunks(order_id: OrderId) -> Vec<ChunkDetails> {
             chunk_details.push(ChunkDetails {
                 id: chunk.id,
                 order_id: chunk.order_id,
-                amount_usd: chunk.amount_usd,
+                amount_usd_e6: chunk.amount_usd_e6,
                 status: chunk.status.clone(),
                 locked_by: chunk.locked_by,
                 filled_at: chunk.filled_at,
+                lock_count: chunk.lock_count.unwrap_or(0),
+                timeout_release_count: chunk.timeout_release_count.unwrap_or(0),
+                last_timeout_filler: chunk.last_timeout_filler,
             });
         }
     }
@@ -505,6 +788,62 @@ pub fn get_order_chunks(order_id: OrderId) -> Vec<ChunkDetails> {
     chunk_details
 }
 
+/// Trades touching an order's chunks - only the order's maker or admin
+pub fn get_order_trades(order_id: OrderId) -> Result<Vec<OrderTradeActivity>, EasySwapError> {
+    let caller = get_caller();
+    let order = crate::state::get_order(order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+    
+    if caller != order.maker && caller != get_admin() {
+        return Err(EasySwapError::unauthorized("Only the order maker or admin can view its trades"));
+    }
+    
+    Ok(get_trades_by_order(order_id).into_iter()
+        .map(|trade| OrderTradeActivity {
+            bsv_txid: trade.bsv_txid.clone(),
+            effective_bsv_price: trade.effective_bsv_price(),
+            trade_id: trade.id,
+            status: trade.status,
+            amount_usd_e6: trade.amount_usd_e6,
+            agreed_bsv_price: trade.agreed_bsv_price,
+            locked_chunks: trade.locked_chunks,
+            created_at: trade.created_at,
+            tx_submitted_at: trade.tx_submitted_at,
+            release_available_at: trade.release_available_at,
+            withdrawal_confirmed_at: trade.withdrawal_confirmed_at,
+        })
+        .collect())
+}
+
+/// BSV payments submitted to the caller's orders, newest first
+/// Confirmations are known once the filler has claimed with an SPV proof
+pub fn get_incoming_bsv_payments() -> Vec<IncomingBsvPayment> {
+    let caller = get_caller();
+    let tip_height = crate::block_headers::get_highest_block();
+    
+    let mut payments: Vec<IncomingBsvPayment> = get_orders_by_maker(caller).iter()
+        .flat_map(|order| get_trades_by_order(order.id))
+        .filter_map(|trade| {
+            let txid = trade.bsv_txid.clone()?;
+            Some(IncomingBsvPayment {
+                trade_id: trade.id,
+                order_id: trade.order_id,
+                txid,
+                outputs: trade.locked_chunks,
+                status: trade.status,
+                block_height: trade.bsv_block_height,
+                confirmations: trade.bsv_block_height
+                    .filter(|height| tip_height >= *height)
+                    .map(|height| tip_height - height + 1),
+                tx_submitted_at: trade.tx_submitted_at,
+            })
+        })
+        .collect();
+    
+    payments.sort_by_key(|payment| std::cmp::Reverse(payment.tx_submitted_at));
+    payments
+}
+
 // Helper function to check and mark orders as idle if price exceeds max
 // Optimized to filter at storage level instead of loading all orders
 pub async fn check_and_mark_idle_orders() -> Result<(), String> {
@@ -515,12 +854,14 @@ pub async fn check_and_mark_idle_orders() -> Result<(), String> {
         
         if price_exceeds {
             // Mark all available chunks as idle
+            let mut idled_e6 = 0;
             for chunk_id in &order.chunks {
                 if let Some(chunk) = get_chunk(*chunk_id) {
                     if chunk.status == ChunkStatus::Available {
                         update_chunk(*chunk_id, |c| {
                             c.status = ChunkStatus::Idle;
                         })?;
+                        idled_e6 += chunk.amount_usd_e6;
                     }
                 }
             }
@@ -528,6 +869,7 @@ pub async fn check_and_mark_idle_orders() -> Result<(), String> {
             // Update order to idle
             update_order(order.id, |o| {
                 o.status = OrderStatus::Idle;
+                o.total_idle_usd_e6 += idled_e6;
             })?;
         }
     }
@@ -535,17 +877,16 @@ pub async fn check_and_mark_idle_orders() -> Result<(), String> {
     Ok(())
 }
 
-fn is_valid_bsv_mainnet_address(address: &str) -> bool {
-    // BSV mainnet addresses start with '1' (P2PKH) or '3' (P2SH)
-    if address.is_empty() {
-        return false;
+fn invalid_address_message(network: BsvNetwork) -> &'static str {
+    match network {
+        BsvNetwork::Mainnet => "Invalid BSV mainnet address (must be a P2PKH address starting with 1)",
+        BsvNetwork::Testnet => "Invalid BSV testnet address (must be a P2PKH address starting with m or n)",
     }
-    
-    let first_char = address.chars().next().unwrap();
-    if first_char != '1' && first_char != '3' {
-        return false;
-    }
-    
+}
+
+fn is_valid_bsv_address(address: &str, network: BsvNetwork) -> bool {
+    // Only P2PKH addresses for new orders and address changes - open orders created while P2SH
+    // addresses were accepted keep theirs, and bsv_parser still takes P2SH payments to them
     // Length check: typically 26-35 characters
     if address.len() < 26 || address.len() > 35 {
         return false;
@@ -554,8 +895,9 @@ fn is_valid_bsv_mainnet_address(address: &str) -> bool {
     // Try to decode as base58
     match bs58::decode(address).into_vec() {
         Ok(decoded) => {
-            // Should be at least 25 bytes (1 version + 20 hash + 4 checksum)
-            if decoded.len() < 25 {
+            // Should be at least 25 bytes (1 version + 20 hash + 4 checksum), led by the
+            // network's P2PKH version ('1' on mainnet, 'm'/'n' on testnet)
+            if decoded.len() < 25 || decoded[0] != network.p2pkh_version() {
                 return false;
             }
             
@@ -573,19 +915,19 @@ fn is_valid_bsv_mainnet_address(address: &str) -> bool {
     }
 }
 
-pub async fn update_max_bsv_price(order_id: OrderId, new_max_price: f64) -> Result<(), String> {
-    let caller = get_caller();
+/// `caller` is the maker, or the maker an operator acts for (resolved in lib.rs, see delegations)
+pub async fn update_max_bsv_price(caller: Principal, order_id: OrderId, new_max_price: f64) -> Result<(), EasySwapError> {
     let mut order = get_order(order_id)
-        .ok_or_else(|| "Order not found".to_string())?;
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
     
     // Verify caller is the maker
     if order.maker != caller {
-        return Err("Only the order maker can update price".to_string());
+        return Err(EasySwapError::unauthorized("Only the order maker can update price"));
     }
     
     // Validate new price
     if new_max_price <= 0.0 {
-        return Err("Max BSV price must be positive".to_string());
+        return Err(EasySwapError::invalid_input("Max BSV price must be positive"));
     }
     
     // Check if order has any chunks that can be updated (Available or Idle only)
@@ -595,7 +937,7 @@ pub async fn update_max_bsv_price(order_id: OrderId, new_max_price: f64) -> Resu
     for chunk_id in &order.chunks {
         if let Some(chunk) = get_chunk(*chunk_id) {
             match chunk.status {
-                ChunkStatus::Available | ChunkStatus::Idle => {
+                ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending => {
                     has_editable_chunks = true;
                 },
                 ChunkStatus::Locked | ChunkStatus::Filled | ChunkStatus::Refunding | ChunkStatus::Refunded => {
@@ -608,7 +950,7 @@ pub async fn update_max_bsv_price(order_id: OrderId, new_max_price: f64) -> Resu
     
     // Only allow update if there are Available or Idle chunks
     if !has_editable_chunks {
-        return Err("Cannot update price: no Available or Idle chunks. All chunks are either locked, filled, or inactive.".to_string());
+        return Err(EasySwapError::invalid_state("Cannot update price: no Available or Idle chunks. All chunks are either locked, filled, or inactive."));
     }
     
     ic_cdk::println!("📝 Updating max BSV price for order {} from ${:.4} to ${:.4}", order_id, order.max_bsv_price, new_max_price);
@@ -630,16 +972,16 @@ pub async fn update_max_bsv_price(order_id: OrderId, new_max_price: f64) -> Resu
                     ChunkStatus::Available => {
                         // If new price is too low, delist to Idle
                         if new_max_price < current_bsv_price {
-                            ic_cdk::println!("   Chunk {} (${:.2}): Available → Idle (price exceeded)", chunk_id, chunk.amount_usd);
+                            ic_cdk::println!("   Chunk {} (${:.2}): Available → Idle (price exceeded)", chunk_id, e6_to_usd(chunk.amount_usd_e6));
                             update_chunk(*chunk_id, |c| {
                                 c.status = ChunkStatus::Idle;
                                 c.max_bsv_price = new_max_price;
                             })?;
                             
                             // Update order tracking
-                            order.total_idle_usd += chunk.amount_usd;
+                            order.total_idle_usd_e6 += chunk.amount_usd_e6;
                         } else {
-                            ic_cdk::println!("   Chunk {} (${:.2}): Available (price updated)", chunk_id, chunk.amount_usd);
+                            ic_cdk::println!("   Chunk {} (${:.2}): Available (price updated)", chunk_id, e6_to_usd(chunk.amount_usd_e6));
                             update_chunk(*chunk_id, |c| {
                                 c.max_bsv_price = new_max_price;
                             })?;
@@ -648,24 +990,30 @@ pub async fn update_max_bsv_price(order_id: OrderId, new_max_price: f64) -> Resu
                     ChunkStatus::Idle => {
                         // If new price is acceptable, re-list to Available
                         if new_max_price >= current_bsv_price {
-                            ic_cdk::println!("   Chunk {} (${:.2}): Idle → Available (price now acceptable)", chunk_id, chunk.amount_usd);
+                            ic_cdk::println!("   Chunk {} (${:.2}): Idle → Available (price now acceptable)", chunk_id, e6_to_usd(chunk.amount_usd_e6));
                             update_chunk(*chunk_id, |c| {
                                 c.status = ChunkStatus::Available;
                                 c.max_bsv_price = new_max_price;
                             })?;
                             
                             // Update order tracking
-                            order.total_idle_usd -= chunk.amount_usd;
+                            order.total_idle_usd_e6 = order.total_idle_usd_e6.saturating_sub(chunk.amount_usd_e6);
                         } else {
-                            ic_cdk::println!("   Chunk {} (${:.2}): Idle (price updated)", chunk_id, chunk.amount_usd);
+                            ic_cdk::println!("   Chunk {} (${:.2}): Idle (price updated)", chunk_id, e6_to_usd(chunk.amount_usd_e6));
                             update_chunk(*chunk_id, |c| {
                                 c.max_bsv_price = new_max_price;
                             })?;
                         }
                     },
+                    // Listed (or delisted) when the next idle check activates it, against the new price
+                    ChunkStatus::Pending => {
+                        update_chunk(*chunk_id, |c| {
+                            c.max_bsv_price = new_max_price;
+                        })?;
+                    },
                     // For Locked, Filled, Refunding, Refunded - DO NOT update (price locked at trade time)
                     ChunkStatus::Locked | ChunkStatus::Filled | ChunkStatus::Refunding | ChunkStatus::Refunded => {
-                        ic_cdk::println!("   Chunk {} (${:.2}): {:?} (price locked, not updated)", chunk_id, chunk.amount_usd, chunk.status);
+                        ic_cdk::println!("   Chunk {} (${:.2}): {:?} (price locked, not updated)", chunk_id, e6_to_usd(chunk.amount_usd_e6), chunk.status);
                         // Don't update max_bsv_price for these - they're committed at their trade price
                     }
                 }
@@ -676,35 +1024,359 @@ pub async fn update_max_bsv_price(order_id: OrderId, new_max_price: f64) -> Resu
     
     update_order(order_id, |o| {
         o.max_bsv_price = new_max_price;
-        o.total_idle_usd = order.total_idle_usd;
+        o.total_idle_usd_e6 = order.total_idle_usd_e6;
+    })?;
+    
+    ic_cdk::println!("✅ Max BSV price updated successfully. Order total idle: ${:.2}", e6_to_usd(order.total_idle_usd_e6));
+    Ok(())
+}
+
+/// Point an open order's Available and Idle chunks at a new BSV address
+/// Locked chunks keep the address their trade was created with - the filler is already paying it
+pub fn update_order_bsv_address(order_id: OrderId, new_address: String) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    let order = get_order(order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+    
+    if order.maker != caller {
+        return Err(EasySwapError::unauthorized("Only the order maker can update the BSV address"));
+    }
+    
+    if !matches!(order.status, OrderStatus::Active | OrderStatus::Idle) {
+        return Err(EasySwapError::invalid_state(format!("Cannot update BSV address of a {:?} order", order.status)));
+    }
+    
+    let new_address = new_address.trim().to_string();
+    let network = get_bsv_network();
+    if !is_valid_bsv_address(&new_address, network) {
+        return Err(EasySwapError::invalid_input(invalid_address_message(network)));
+    }
+    if new_address == order.bsv_address {
+        return Err(EasySwapError::invalid_input("Order already uses this BSV address"));
+    }
+    
+    let editable_chunks: Vec<ChunkId> = order.chunks.iter()
+        .filter(|chunk_id| get_chunk(**chunk_id)
+            .is_some_and(|c| matches!(c.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending)))
+        .copied()
+        .collect();
+    
+    if editable_chunks.is_empty() {
+        return Err(EasySwapError::invalid_state("Cannot update BSV address: no Available or Idle chunks"));
+    }
+    
+    for chunk_id in &editable_chunks {
+        update_chunk(*chunk_id, |c| {
+            c.bsv_address = new_address.clone();
+        })?;
+    }
+    
+    update_order(order_id, |o| {
+        o.bsv_address = new_address.clone();
+    })?;
+    
+    ic_cdk::println!("📝 Order {} BSV address changed to {} ({} chunks)", order_id, new_address, editable_chunks.len());
+    
+    create_admin_event(AdminEventType::OrderBsvAddressUpdated {
+        order_id,
+        maker: caller,
+        old_address: order.bsv_address,
+        new_address,
+        chunks_updated: editable_chunks.len() as u64,
+    });
+    
+    Ok(())
+}
+
+/// Hide an open order's chunks from the public orderbook, depth and stats, or list them again
+/// Matching is unaffected - fillers still fill hidden chunks, they just can't see the size
+pub fn set_order_hidden(order_id: OrderId, hidden: bool) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    let order = get_order(order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+    
+    if order.maker != caller {
+        return Err(EasySwapError::unauthorized("Only the order maker can change its visibility"));
+    }
+    
+    if !matches!(order.status, OrderStatus::Active | OrderStatus::Idle) {
+        return Err(EasySwapError::invalid_state(format!("Cannot change visibility of a {:?} order", order.status)));
+    }
+    
+    // Every chunk, so Idle or Locked ones come back to the book with the same visibility
+    for chunk_id in &order.chunks {
+        update_chunk(*chunk_id, |c| {
+            c.hidden = Some(hidden);
+        })?;
+    }
+    
+    update_order(order_id, |o| {
+        o.hidden = Some(hidden);
+    })?;
+    
+    ic_cdk::println!("🙈 Order {} {} public stats", order_id, if hidden { "hidden from" } else { "shown in" });
+    
+    Ok(())
+}
+
+/// List a post-only order's Pending chunks - Idle if `delist` (the price is above the order's
+/// max, or trading is halted), Available otherwise. Returns how many chunks left Pending
+pub fn activate_pending_chunks(order_id: OrderId, delist: bool) -> Result<u64, String> {
+    let order = get_order(order_id).ok_or_else(|| "Order not found".to_string())?;
+    let pending: Vec<Chunk> = order.chunks.iter()
+        .filter_map(|chunk_id| get_chunk(*chunk_id))
+        .filter(|chunk| chunk.status == ChunkStatus::Pending)
+        .collect();
+    if pending.is_empty() {
+        return Ok(0);
+    }
+    
+    let new_status = if delist { ChunkStatus::Idle } else { ChunkStatus::Available };
+    for chunk in &pending {
+        update_chunk(chunk.id, |c| {
+            c.status = new_status.clone();
+        })?;
+    }
+    if delist {
+        let pending_e6: UsdE6 = pending.iter().map(|chunk| chunk.amount_usd_e6).sum();
+        update_order(order_id, |o| {
+            o.total_idle_usd_e6 += pending_e6;
+        })?;
+    }
+    
+    ic_cdk::println!("▶️ Order {}: {} pending chunks now {:?}", order_id, pending.len(), new_status);
+    Ok(pending.len() as u64)
+}
+
+/// Activate a post-only order's Pending chunks now instead of at the next idle check
+/// Returns how many chunks were activated
+pub async fn confirm_order(order_id: OrderId) -> Result<u64, EasySwapError> {
+    let caller = get_caller();
+    let order = get_order(order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+    
+    if order.maker != caller {
+        return Err(EasySwapError::unauthorized("Only the order maker can confirm it"));
+    }
+    
+    if !matches!(order.status, OrderStatus::Active | OrderStatus::Idle) {
+        return Err(EasySwapError::invalid_state(format!("Cannot confirm a {:?} order", order.status)));
+    }
+    
+    let bsv_price = crate::price_oracle::get_bsv_price().await
+        .map_err(|e| EasySwapError::unavailable(format!("Could not check the BSV price, please retry: {}", e)))?;
+    let halted = crate::circuit_breaker::is_trading_halted(get_time());
+    Ok(activate_pending_chunks(order_id, halted || bsv_price > order.max_bsv_price)?)
+}
+
+/// Cancel the order once the BSV price exceeds `price` instead of leaving it Idle - None clears it
+/// Must be above the order's max BSV price, where its chunks are already delisted
+pub fn set_order_auto_cancel(order_id: OrderId, price: Option<f64>) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    let order = get_order(order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+    
+    if order.maker != caller {
+        return Err(EasySwapError::unauthorized("Only the order maker can set its auto-cancel price"));
+    }
+    
+    if !matches!(order.status, OrderStatus::Active | OrderStatus::Idle) {
+        return Err(EasySwapError::invalid_state(format!("Cannot set auto-cancel on a {:?} order", order.status)));
+    }
+    
+    if let Some(price) = price {
+        if !price.is_finite() || price <= order.max_bsv_price {
+            return Err(EasySwapError::invalid_input(format!(
+                "Auto-cancel price must be above the order's max BSV price ${:.2}",
+                order.max_bsv_price
+            )));
+        }
+    }
+    
+    update_order(order_id, |o| {
+        o.auto_cancel_above_price = price;
     })?;
     
-    ic_cdk::println!("✅ Max BSV price updated successfully. Order total idle: ${:.2}", order.total_idle_usd);
+    ic_cdk::println!("🛑 Order {} auto-cancel price set to {:?}", order_id, price);
+    
     Ok(())
 }
 
+/// Cancel and refund open orders whose auto-cancel price `bsv_price` exceeds, recording an
+/// OrderAutoCancelled event for each - returns how many were cancelled
+/// A cancel that fails (e.g. the balance check) is retried on the next idle check
+pub async fn auto_cancel_orders_above_price(bsv_price: f64) -> u64 {
+    let triggered: Vec<Order> = get_all_orders().into_iter()
+        .filter(|o| matches!(o.status, OrderStatus::Active | OrderStatus::Idle | OrderStatus::PartiallyFilled))
+        .filter(|o| o.finalization_pending_since.is_none())
+        .filter(|o| o.auto_cancel_above_price.is_some_and(|threshold| bsv_price > threshold))
+        .collect();
+    
+    let mut cancelled = 0;
+    for order in triggered {
+        let (order_id, maker, threshold) = (order.id, order.maker, order.auto_cancel_above_price.unwrap_or_default());
+        match cancel_maker_order(order).await {
+            Ok(()) => {
+                create_admin_event(AdminEventType::OrderAutoCancelled {
+                    order_id,
+                    maker,
+                    bsv_price,
+                    auto_cancel_above_price: threshold,
+                });
+                cancelled += 1;
+            }
+            Err(e) => ic_cdk::println!("⚠️ Auto-cancel of order {} failed: {}", order_id, e),
+        }
+    }
+    cancelled
+}
+
+/// Send `refund_e6` from the order subaccount back to the maker and record it as a RefundAttempt
+/// on the order - a failed transfer is queued and retried from the pending queue, which keeps the
+/// attempt's status current, so callers carry on either way
+async fn refund_order_balance(order: &Order, refund_e6: UsdE6, chunk_ids: Vec<ChunkId>) {
+    let refund_amount_e6 = refund_e6 as u128;
+    let now = get_time();
+    let mut refund_id = 0;
+    update_order(order.id, |o| {
+        refund_id = o.refund_attempts.len() as u64 + 1;
+        o.refund_attempts.push(RefundAttempt {
+            refund_id,
+            requested_at: now,
+            chunk_ids,
+            total_amount_e6: refund_e6,
+            recipient_address: order.maker.to_text(),
+            tx_hash: None,
+            tx_sent_at: None,
+            confirmed_at: None,
+            status: RefundStatus::Pending,
+            block_index: None,
+            pending_transfer_id: None,
+            last_error: None,
+        });
+    }).ok();
+    
+    ic_cdk::println!("💸 Transferring refund: ${:.6}", e6_to_usd(refund_e6));
+    
+    let result = ckusdc_integration::transfer_ckusdc_from_order(
+        order.maker,
+        order.id,
+        order.maker,
+        None, // Maker's default subaccount
+        refund_amount_e6,
+        Some(format!("Refund O{}", order.id).into_bytes()),
+    ).await;
+    
+    let now = get_time();
+    match result {
+        Ok(block_index) => {
+            let net_refund = ckusdc_integration::ckusdc_e6_to_usd(
+                refund_amount_e6.saturating_sub(crate::config::CKUSDC_TRANSFER_FEE)
+            );
+            ic_cdk::println!("✅ Refunded ${:.6} to maker. Block: {}", net_refund, block_index);
+            update_refund_attempt(order.id, |r| r.refund_id == refund_id, |r| {
+                r.status = RefundStatus::Confirmed;
+                r.block_index = Some(block_index);
+                r.tx_sent_at = Some(now);
+                r.confirmed_at = Some(now);
+            });
+        },
+        Err(e) => {
+            ic_cdk::println!("⚠️ Failed to refund: {}", e);
+            let transfer = crate::pending_transfers::queue_order_refund(
+                order.maker,
+                order.id,
+                refund_amount_e6,
+                format!("Refund O{}", order.id).into_bytes(),
+                e.clone(),
+            );
+            update_refund_attempt(order.id, |r| r.refund_id == refund_id, |r| {
+                r.pending_transfer_id = Some(transfer.id);
+                r.last_error = Some(e);
+            });
+        }
+    }
+}
+
+/// Apply `f` to the order's refund attempt matching `matches`, counting confirmed refunds
+/// into total_refunded_usd_e6
+fn update_refund_attempt(
+    order_id: OrderId,
+    matches: impl Fn(&RefundAttempt) -> bool,
+    f: impl FnOnce(&mut RefundAttempt),
+) {
+    update_order(order_id, |o| {
+        let Some(attempt) = o.refund_attempts.iter_mut().find(|r| matches(r)) else {
+            return;
+        };
+        let was_confirmed = attempt.status == RefundStatus::Confirmed;
+        f(attempt);
+        if !was_confirmed && attempt.status == RefundStatus::Confirmed {
+            o.total_refunded_usd_e6 = Some(o.total_refunded_usd_e6.unwrap_or(0) + attempt.total_amount_e6);
+        }
+    }).ok();
+}
+
+/// Mirror a queued refund's progress onto the RefundAttempt it was queued for
+pub fn sync_refund_attempt(order_id: OrderId, transfer: &PendingTransfer) {
+    update_refund_attempt(order_id, |r| r.pending_transfer_id == Some(transfer.id), |r| {
+        r.last_error = transfer.last_error.clone();
+        match transfer.status {
+            PendingTransferStatus::Completed => {
+                r.status = RefundStatus::Confirmed;
+                r.block_index = transfer.block_index;
+                r.tx_sent_at = Some(transfer.updated_at);
+                r.confirmed_at = Some(transfer.updated_at);
+            }
+            PendingTransferStatus::Failed => r.status = RefundStatus::Failed,
+            PendingTransferStatus::Pending => r.status = RefundStatus::Pending,
+        }
+    });
+}
+
+/// Refunds of the caller's orders, most recent first
+pub fn get_my_refunds() -> Vec<OrderRefund> {
+    let caller = get_caller();
+    let mut refunds: Vec<OrderRefund> = get_orders_by_maker(caller).into_iter()
+        .flat_map(|order| {
+            let order_id = order.id;
+            order.refund_attempts.into_iter().map(move |refund| OrderRefund { order_id, refund })
+        })
+        .collect();
+    refunds.sort_by_key(|r| std::cmp::Reverse(r.refund.requested_at));
+    refunds
+}
+
 /// Cancel order and refund unfilled chunks
 /// - If order not yet fully used: Refund ckUSDC for unfilled chunks (not locked/filled)
 /// - Note: 1.5% activation fee is non-refundable (already sent to treasury)
-pub async fn cancel_order(order_id: OrderId) -> Result<(), String> {
+/// - Nothing changes until the order balance is read, so a cancel that fails there can simply be retried
+pub async fn cancel_order(order_id: OrderId) -> Result<(), EasySwapError> {
     let caller = get_caller();
     
     // Reject anonymous principal
     if caller == candid::Principal::anonymous() {
-        return Err("Anonymous principal cannot cancel orders. Please authenticate first.".to_string());
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot cancel orders. Please authenticate first."));
     }
     
     let order = get_order(order_id)
-        .ok_or_else(|| "Order not found".to_string())?;
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
     
     // Verify caller is the maker
     if order.maker != caller {
-        return Err("Only the order maker can cancel".to_string());
+        return Err(EasySwapError::unauthorized("Only the order maker can cancel"));
     }
     
+    cancel_maker_order(order).await
+}
+
+/// Cancel an order the maker's authority was already checked for - refund what isn't locked
+async fn cancel_maker_order(order: Order) -> Result<(), EasySwapError> {
+    let order_id = order.id;
+    
     // Cannot cancel if order is already completed/cancelled
     if matches!(order.status, OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Refunded) {
-        return Err(format!("Order is already {:?}", order.status));
+        return Err(EasySwapError::invalid_state(format!("Order is already {:?}", order.status)));
     }
     
     let now = get_time();
@@ -715,67 +1387,42 @@ pub async fn cancel_order(order_id: OrderId) -> Result<(), String> {
     ic_cdk::println!("========================================");
     
     // Calculate locked chunks amount (these need to stay in the account)
-    let mut locked_chunk_amount = 0.0;
+    let mut locked_chunk_amount_e6: UsdE6 = 0;
     let mut locked_chunk_count = 0;
     
     for chunk_id in order.chunks.iter() {
         if let Some(chunk) = get_chunk(*chunk_id) {
             if chunk.status == ChunkStatus::Locked {
-                locked_chunk_amount += chunk.amount_usd;
+                locked_chunk_amount_e6 += chunk.amount_usd_e6;
                 locked_chunk_count += 1;
             }
         }
     }
     
-    ic_cdk::println!("� Locked chunks: {} chunks = ${:.6}", locked_chunk_count, locked_chunk_amount);
+    ic_cdk::println!("� Locked chunks: {} chunks = ${:.6}", locked_chunk_count, e6_to_usd(locked_chunk_amount_e6));
     
     // Calculate amount needed for locked chunks (including filler incentive)
-    let filler_incentive_percent = crate::config::FILLER_INCENTIVE_PERCENT as f64 / 10000.0;
-    let locked_with_incentive = locked_chunk_amount * (1.0 + filler_incentive_percent);
+    let locked_with_incentive_e6 = locked_chunk_amount_e6 + bps_of(locked_chunk_amount_e6, order.filler_incentive_bps());
     
-    ic_cdk::println!("💵 Amount reserved for locked chunks (with incentive): ${:.6}", locked_with_incentive);
+    ic_cdk::println!("💵 Amount reserved for locked chunks (with incentive): ${:.6}", e6_to_usd(locked_with_incentive_e6));
     
-    // Check actual balance in order subaccount
-    match ckusdc_integration::get_order_ckusdc_balance(order.maker, order_id).await {
-        Ok(balance_e6) => {
-            let balance_usd = ckusdc_integration::ckusdc_e6_to_usd(balance_e6);
-            ic_cdk::println!("💰 Order deposit balance: ${:.6}", balance_usd);
-            
-            // Calculate refundable amount = balance - locked_with_incentive
-            let refundable_usd = balance_usd - locked_with_incentive;
-            
-            if refundable_usd > 0.01 { // Only refund if more than 1 cent
-                let refund_amount_e6 = ckusdc_integration::usd_to_ckusdc_e6(refundable_usd);
-                
-                ic_cdk::println!("💸 Transferring refund: ${:.6}", refundable_usd);
-                
-                match ckusdc_integration::transfer_ckusdc_from_order(
-                    order.maker,
-                    order_id,
-                    order.maker,
-                    None, // Maker's default subaccount
-                    refund_amount_e6,
-                    Some(format!("Refund O{}", order_id).into_bytes()),
-                ).await {
-                    Ok(block_index) => {
-                        let net_refund = ckusdc_integration::ckusdc_e6_to_usd(
-                            refund_amount_e6.saturating_sub(crate::config::CKUSDC_TRANSFER_FEE)
-                        );
-                        ic_cdk::println!("✅ Refunded ${:.6} to maker. Block: {}", net_refund, block_index);
-                    },
-                    Err(e) => {
-                        ic_cdk::println!("⚠️ Failed to refund: {}", e);
-                        // Continue with cancellation even if refund fails
-                    }
-                }
-            } else {
-                ic_cdk::println!("   No refundable amount (balance needed for locked chunks)");
-            }
-        },
-        Err(e) => {
-            ic_cdk::println!("⚠️ Could not check balance: {}", e);
-            // Continue with cancellation
-        }
+    // Check actual balance in order subaccount - without it the refund can't be sized, so stop
+    // here and let the maker retry rather than cancel with the funds left behind
+    let balance_e6 = ckusdc_integration::get_order_ckusdc_balance(order.maker, order_id).await
+        .map_err(|e| EasySwapError::ledger(format!("Could not check order balance, please retry: {}", e)))? as UsdE6;
+    ic_cdk::println!("💰 Order deposit balance: ${:.6}", e6_to_usd(balance_e6));
+    
+    // Calculate refundable amount = balance - locked_with_incentive
+    let refundable_e6 = balance_e6.saturating_sub(locked_with_incentive_e6);
+    
+    if refundable_e6 > 10_000 { // Only refund if more than 1 cent
+        let refunded_chunks: Vec<ChunkId> = order.chunks.iter()
+            .filter(|id| get_chunk(**id).is_some_and(|c| matches!(c.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending)))
+            .copied()
+            .collect();
+        refund_order_balance(&order, refundable_e6, refunded_chunks).await;
+    } else {
+        ic_cdk::println!("   No refundable amount (balance needed for locked chunks)");
     }
         
         // Update order status based on what happened
@@ -793,7 +1440,7 @@ pub async fn cancel_order(order_id: OrderId) -> Result<(), String> {
         // Do NOT mark already Refunded chunks again - they were refunded in a previous cancel call
         for chunk_id in order.chunks.iter() {
             if let Some(chunk) = get_chunk(*chunk_id) {
-                if matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle) {
+                if matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending) {
                     update_chunk(*chunk_id, |c| {
                         c.status = ChunkStatus::Refunded;
                     }).ok(); // Ignore errors, continue with other chunks
@@ -806,12 +1453,247 @@ pub async fn cancel_order(order_id: OrderId) -> Result<(), String> {
         
         update_order(order_id, |o| {
             o.status = new_status;
+            if any_locked {
+                o.finalization_pending_since.get_or_insert(now);
+            }
         })?;
         
         ic_cdk::println!("✅ Order {} cancelled successfully (status: {:?})", order_id, status_for_log);
         Ok(())
 }
 
+/// Finish orders cancelled while some chunks were Locked, once none are - refund what's left in
+/// the order subaccount, retire the released chunks and settle on Filled, PartiallyFilled or
+/// Cancelled depending on what was filled. Returns how many orders were finalized
+pub async fn finalize_cancelled_orders() -> u64 {
+    let pending: Vec<Order> = get_all_orders().into_iter()
+        .filter(|o| o.finalization_pending_since.is_some())
+        .collect();
+    
+    let mut finalized = 0;
+    for order in pending {
+        let chunks: Vec<Chunk> = order.chunks.iter().filter_map(|id| get_chunk(*id)).collect();
+        if chunks.iter().any(|c| c.status == ChunkStatus::Locked) {
+            continue;
+        }
+        
+        let released: Vec<ChunkId> = chunks.iter()
+            .filter(|c| c.status == ChunkStatus::Refunding)
+            .map(|c| c.id)
+            .collect();
+        
+        // Nothing is reserved any more - whatever remains belongs to the maker
+        match ckusdc_integration::get_order_ckusdc_balance(order.maker, order.id).await {
+            Ok(balance_e6) => {
+                let balance_e6 = balance_e6 as UsdE6;
+                if balance_e6 > 10_000 { // Only refund if more than 1 cent
+                    refund_order_balance(&order, balance_e6, released.clone()).await;
+                }
+            }
+            Err(e) => {
+                // Retried on the next pass
+                ic_cdk::println!("⚠️ Could not check balance of order {} for finalization: {}", order.id, e);
+                continue;
+            }
+        }
+        
+        for chunk_id in &released {
+            update_chunk(*chunk_id, |c| c.status = ChunkStatus::Refunded).ok();
+        }
+        
+        let result = update_order(order.id, |o| {
+            o.status = if o.total_filled_usd_e6 >= o.amount_usd_e6 {
+                OrderStatus::Filled
+            } else if o.total_filled_usd_e6 > 0 {
+                OrderStatus::PartiallyFilled
+            } else {
+                OrderStatus::Cancelled
+            };
+            o.finalization_pending_since = None;
+        });
+        match result {
+            Ok(()) => {
+                ic_cdk::println!("🏁 Finalized cancelled order {} ({} chunks released)", order.id, released.len());
+                finalized += 1;
+            }
+            Err(e) => ic_cdk::println!("⚠️ Failed to finalize order {}: {}", order.id, e),
+        }
+    }
+    finalized
+}
+
+/// Caller's Active/Idle orders, oldest first, at most MAX_BULK_ORDER_OPERATIONS
+fn open_orders_of(caller: Principal) -> Vec<OrderId> {
+    let mut order_ids: Vec<OrderId> = get_orders_by_maker(caller).into_iter()
+        .filter(|o| matches!(o.status, OrderStatus::Active | OrderStatus::Idle))
+        .map(|o| o.id)
+        .collect();
+    order_ids.sort();
+    order_ids.truncate(MAX_BULK_ORDER_OPERATIONS);
+    order_ids
+}
+
+/// Cancel all of the caller's Active/Idle orders - returns (order_id, result) per order
+/// Each order is cancelled on its own, so one failure doesn't stop the rest
+/// Makers with more than MAX_BULK_ORDER_OPERATIONS open orders call again for the remainder
+pub async fn cancel_all_my_orders() -> Result<Vec<(OrderId, Result<(), EasySwapError>)>, EasySwapError> {
+    let caller = get_caller();
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot cancel orders. Please authenticate first."));
+    }
+    
+    let order_ids = open_orders_of(caller);
+    ic_cdk::println!("❌ Cancelling {} open orders of {}", order_ids.len(), caller);
+    
+    let mut results = Vec::with_capacity(order_ids.len());
+    for order_id in order_ids {
+        results.push((order_id, cancel_order(order_id).await));
+    }
+    Ok(results)
+}
+
+/// Set the same max BSV price on several of the caller's orders - returns (order_id, result) per order
+pub async fn update_max_bsv_price_bulk(caller: Principal, order_ids: Vec<OrderId>, new_max_price: f64) -> Result<Vec<(OrderId, Result<(), EasySwapError>)>, EasySwapError> {
+    if new_max_price <= 0.0 {
+        return Err(EasySwapError::invalid_input("Max BSV price must be positive"));
+    }
+    if order_ids.is_empty() {
+        return Err(EasySwapError::invalid_input("No orders given"));
+    }
+    if order_ids.len() > MAX_BULK_ORDER_OPERATIONS {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "{} orders given, maximum is {}",
+            order_ids.len(), MAX_BULK_ORDER_OPERATIONS
+        )));
+    }
+    
+    let mut seen = HashSet::new();
+    let mut results = Vec::with_capacity(order_ids.len());
+    for order_id in order_ids {
+        if !seen.insert(order_id) {
+            continue;
+        }
+        let result = match get_order(order_id) {
+            Some(order) if !matches!(order.status, OrderStatus::Active | OrderStatus::Idle) => {
+                Err(EasySwapError::invalid_state(format!("Order is {:?}", order.status)))
+            }
+            _ => update_max_bsv_price(caller, order_id, new_max_price).await,
+        };
+        results.push((order_id, result));
+    }
+    Ok(results)
+}
+
+// ===== ORPHANED DEPOSIT RECOVERY =====
+
+/// Scan order subaccounts derived from the caller's principal for funds with no matching order
+/// (e.g. failed activation) and sweep them back to the caller's wallet
+/// Scans at most MAX_ORPHAN_SCAN_PER_CALL order IDs starting at from_order_id
+pub async fn recover_orphaned_deposits(from_order_id: OrderId) -> Result<DepositRecoveryScan, EasySwapError> {
+    let caller = get_caller();
+    
+    if caller == candid::Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot recover deposits. Please authenticate first."));
+    }
+    
+    let next_order_id = APP_STATE.with(|cell| cell.borrow().get().next_order_id);
+    if from_order_id >= next_order_id {
+        return Err(EasySwapError::not_found(format!("Order ID {} has not been allocated yet", from_order_id)));
+    }
+    
+    let scan_to = from_order_id.saturating_add(crate::config::MAX_ORPHAN_SCAN_PER_CALL).min(next_order_id);
+    
+    ic_cdk::println!("🔍 Scanning order subaccounts {}..{} for orphaned deposits of {}", from_order_id, scan_to, caller);
+    
+    let mut recovered = Vec::new();
+    let mut errors = Vec::new();
+    
+    for order_id in from_order_id..scan_to {
+        // Skip subaccounts backing one of the caller's orders - those are handled by cancel_order
+        if get_order(order_id).is_some_and(|o| o.maker == caller) {
+            continue;
+        }
+        
+        // Skip orders still being created (deposit is about to be used for activation)
+        if ORDERS_IN_CREATION.with(|set| set.borrow().contains_key(&order_id)) {
+            continue;
+        }
+        
+        let balance_e6 = match ckusdc_integration::get_order_ckusdc_balance(caller, order_id).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                errors.push(format!("Order {}: balance check failed: {}", order_id, e));
+                continue;
+            }
+        };
+        
+        if balance_e6 <= crate::config::CKUSDC_TRANSFER_FEE {
+            continue;
+        }
+        
+        // Re-check after the await - an order may have been created meanwhile
+        if get_order(order_id).is_some_and(|o| o.maker == caller)
+            || ORDERS_IN_CREATION.with(|set| set.borrow().contains_key(&order_id)) {
+            continue;
+        }
+        
+        match ckusdc_integration::transfer_ckusdc_from_order(
+            caller,
+            order_id,
+            caller,
+            None,
+            balance_e6,
+            Some(format!("Recover O{}", order_id).into_bytes()),
+        ).await {
+            Ok(block_index) => {
+                let net_e6 = balance_e6.saturating_sub(crate::config::CKUSDC_TRANSFER_FEE);
+                let mut recovery = DepositRecovery {
+                    id: 0,
+                    maker: caller,
+                    order_id,
+                    amount_usd: ckusdc_integration::ckusdc_e6_to_usd(net_e6),
+                    block_index,
+                    recovered_at: get_time(),
+                };
+                recovery.id = insert_deposit_recovery(recovery.clone());
+                ic_cdk::println!("✅ Recovered ${:.6} from orphaned order {} subaccount (block: {})", recovery.amount_usd, order_id, block_index);
+                recovered.push(recovery);
+            }
+            Err(e) => {
+                ic_cdk::println!("❌ Failed to recover order {} subaccount: {}", order_id, e);
+                errors.push(format!("Order {}: transfer failed: {}", order_id, e));
+            }
+        }
+    }
+    
+    Ok(DepositRecoveryScan {
+        scanned_from: from_order_id,
+        scanned_to: scan_to,
+        recovered,
+        errors,
+        next_order_id: if scan_to < next_order_id { Some(scan_to) } else { None },
+    })
+}
+
+/// Get the caller's deposit recoveries paginated (newest first)
+pub fn get_my_deposit_recoveries(offset: u64, limit: u64) -> PaginatedDepositRecoveries {
+    let caller = get_caller();
+    let all = get_deposit_recoveries_by_maker(caller);
+    let total = all.len() as u64;
+    
+    let recoveries: Vec<DepositRecovery> = all.into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect();
+    
+    PaginatedDepositRecoveries {
+        recoveries,
+        total,
+        offset,
+        limit,
+    }
+}
+
 // Helper functions to remove from stable storage
 fn remove_order(order_id: OrderId) {
     ORDERS.with(|orders| {
@@ -827,3 +1709,186 @@ fn remove_chunk(chunk_id: ChunkId) {
 
 // update_order_network removed - no longer needed with ckUSDC-only approach
 
+/// Validate and store the range makers may offer as filler incentive
+/// The default must stay inside it, so v1 create_order keeps working
+pub fn set_filler_incentive_bounds(bounds: FillerIncentiveBounds) -> Result<(), EasySwapError> {
+    if bounds.min_bps > bounds.max_bps {
+        return Err(EasySwapError::invalid_input("Minimum filler incentive cannot exceed the maximum"));
+    }
+    if bounds.max_bps > FILLER_INCENTIVE_CEILING_BPS {
+        return Err(EasySwapError::invalid_input(format!(
+            "Maximum filler incentive cannot exceed {:.2}%",
+            FILLER_INCENTIVE_CEILING_BPS as f64 / 100.0
+        )));
+    }
+    if FILLER_INCENTIVE_PERCENT < bounds.min_bps || FILLER_INCENTIVE_PERCENT > bounds.max_bps {
+        return Err(EasySwapError::invalid_input(format!(
+            "Bounds must include the default filler incentive of {:.2}%",
+            FILLER_INCENTIVE_PERCENT as f64 / 100.0
+        )));
+    }
+    
+    crate::state::set_filler_incentive_bounds(bounds);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use icrc_ledger_types::icrc1::account::Account;
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn maker_address() -> String {
+        crate::lifecycle_tests::p2pkh_address(&[0x11; 20])
+    }
+
+    #[test]
+    fn late_deposit_of_a_failed_order_is_recovered_and_live_orders_are_left_alone() {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::set_caller(maker());
+        assert!(block_on(recover_orphaned_deposits(0)).is_err(), "no order IDs allocated yet");
+
+        // Activation fails, then the deposit for it lands
+        assert!(block_on(create_order(6.0, 100.0, maker_address(), None)).is_err());
+        let failed_order_id = 0;
+        mock::mint(ckusdc_integration::get_order_deposit_account(maker(), failed_order_id), 2_000_000);
+
+        mock::mint(filler_accounts::get_deposit_account(maker()), 10_000_000);
+        let live_order_id = block_on(create_order(6.0, 100.0, maker_address(), None)).unwrap();
+        let live_account = ckusdc_integration::get_order_deposit_account(maker(), live_order_id);
+        let live_balance = mock::balance(live_account);
+
+        let scan = block_on(recover_orphaned_deposits(0)).unwrap();
+        assert!(scan.errors.is_empty());
+        assert_eq!(scan.next_order_id, None);
+        assert_eq!(scan.recovered.len(), 1);
+        assert_eq!(scan.recovered[0].order_id, failed_order_id);
+        assert_eq!(mock::balance(Account { owner: maker(), subaccount: None }), 2_000_000 - CKUSDC_TRANSFER_FEE);
+        assert_eq!(mock::balance(live_account), live_balance);
+
+        // Nothing left to sweep on a second pass
+        assert!(block_on(recover_orphaned_deposits(0)).unwrap().recovered.is_empty());
+        assert_eq!(get_my_deposit_recoveries(0, 10).total, 1);
+    }
+
+    fn order_request(amount_usd: f64) -> CreateOrderRequest {
+        CreateOrderRequest {
+            amount_usd,
+            max_bsv_price: 100.0,
+            bsv_address: maker_address(),
+            filler_incentive_bps: None,
+            post_only: None,
+            book_id: None,
+        }
+    }
+
+    #[test]
+    fn batch_orders_share_one_security_deposit_and_fail_independently() {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::set_caller(maker());
+        assert!(block_on(create_orders_batch(vec![])).is_err());
+        assert!(block_on(create_orders_batch(vec![order_request(3.0); MAX_ORDERS_PER_BATCH + 1])).is_err());
+
+        // Covers two $3 orders with their fees, not a third
+        mock::mint(filler_accounts::get_deposit_account(maker()), 7_000_000);
+        let results = block_on(create_orders_batch(vec![
+            order_request(3.0),
+            order_request(2.5),
+            order_request(3.0),
+            order_request(3.0),
+        ])).unwrap();
+
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok() && results[2].is_ok());
+        assert!(matches!(results[1], Err(EasySwapError::InvalidInput { .. })));
+        assert!(matches!(results[3], Err(EasySwapError::InsufficientFunds { .. })));
+        assert_eq!(get_available_orderbook(), 6_000_000);
+    }
+
+    fn filler() -> Principal {
+        Principal::from_slice(&[2, 2, 2])
+    }
+
+    fn funded_order(amount_usd: f64) -> OrderId {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        block_on(create_order(amount_usd, 100.0, maker_address(), None)).unwrap()
+    }
+
+    #[test]
+    fn new_bsv_address_applies_to_unlocked_chunks_only() {
+        let order_id = funded_order(6.0);
+        mock::mint(filler_accounts::get_deposit_account(filler()), 1_000_000);
+        mock::set_caller(filler());
+        let trade_id = block_on(crate::trade_lifecycle::create_trades(filler(), crate::trade_lifecycle::CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        })).unwrap()[0];
+        let locked_chunk = get_trade(trade_id).unwrap().locked_chunks[0].chunk_id;
+
+        let new_address = crate::lifecycle_tests::p2pkh_address(&[0x33; 20]);
+        assert!(update_order_bsv_address(order_id, new_address.clone()).is_err(), "only the maker");
+        mock::set_caller(maker());
+        assert!(update_order_bsv_address(order_id, "not-an-address".to_string()).is_err());
+        assert!(update_order_bsv_address(order_id, maker_address()).is_err(), "unchanged address");
+        update_order_bsv_address(order_id, format!(" {} ", new_address)).unwrap();
+
+        let order = get_order(order_id).unwrap();
+        assert_eq!(order.bsv_address, new_address);
+        for chunk_id in order.chunks {
+            let expected = if chunk_id == locked_chunk { maker_address() } else { new_address.clone() };
+            assert_eq!(get_chunk(chunk_id).unwrap().bsv_address, expected);
+        }
+    }
+
+    #[test]
+    fn bulk_price_update_and_cancel_all_cover_each_open_order_once() {
+        let first = funded_order(3.0);
+        let second = funded_order(3.0);
+
+        let results = block_on(update_max_bsv_price_bulk(maker(), vec![first, first, second, 999], 80.0)).unwrap();
+        let outcomes: Vec<(OrderId, bool)> = results.iter().map(|(id, result)| (*id, result.is_ok())).collect();
+        assert_eq!(outcomes, vec![(first, true), (second, true), (999, false)]);
+        assert_eq!(get_order(second).unwrap().max_bsv_price, 80.0);
+        assert!(block_on(update_max_bsv_price_bulk(maker(), vec![first], 0.0)).is_err());
+        assert!(block_on(update_max_bsv_price_bulk(maker(), vec![first; MAX_BULK_ORDER_OPERATIONS + 1], 80.0)).is_err());
+
+        let results = block_on(cancel_all_my_orders()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert!(block_on(cancel_all_my_orders()).unwrap().is_empty(), "nothing left open");
+        let cancelled = block_on(update_max_bsv_price_bulk(maker(), vec![first], 90.0)).unwrap();
+        assert!(matches!(cancelled[0].1, Err(EasySwapError::InvalidState { .. })));
+    }
+
+    #[test]
+    fn offered_filler_incentive_must_sit_inside_the_admin_bounds() {
+        let bounds = |min_bps, max_bps| FillerIncentiveBounds { min_bps, max_bps };
+        assert!(set_filler_incentive_bounds(bounds(800, 600)).is_err());
+        assert!(set_filler_incentive_bounds(bounds(200, FILLER_INCENTIVE_CEILING_BPS + 1)).is_err());
+        assert!(set_filler_incentive_bounds(bounds(FILLER_INCENTIVE_PERCENT + 1, 1_000)).is_err(), "default left out");
+        set_filler_incentive_bounds(bounds(300, 800)).unwrap();
+
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        for out_of_bounds in [299, 801] {
+            let result = block_on(create_order(3.0, 100.0, maker_address(), Some(out_of_bounds)));
+            assert!(matches!(result, Err(EasySwapError::InvalidInput { .. })), "{:?}", result);
+        }
+        let generous = block_on(create_order(3.0, 100.0, maker_address(), Some(800))).unwrap();
+        let default_order = block_on(create_order(3.0, 100.0, maker_address(), None)).unwrap();
+        let order_bps = |order_id| get_order(order_id).unwrap().filler_incentive_bps();
+        assert_eq!((order_bps(generous), order_bps(default_order)), (800, FILLER_INCENTIVE_PERCENT));
+        assert!(crate::chunk_allocation::get_active_chunks().iter()
+            .all(|chunk| chunk.filler_incentive_bps == if chunk.order_id == generous { 800 } else { FILLER_INCENTIVE_PERCENT }));
+    }
+}
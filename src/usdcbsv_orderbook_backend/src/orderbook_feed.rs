@@ -0,0 +1,89 @@
+/// Live orderbook feed - every change to the set of Available chunks gets the next sequence
+/// number, so a client holding a snapshot at seq N only needs the changes after N instead of
+/// re-downloading the whole book each poll
+/// Only the last ORDERBOOK_CHANGES_RETAINED changes are kept; older cursors must resync
+use crate::types::*;
+use crate::state::*;
+use crate::config::{ORDERBOOK_CHANGES_RETAINED, MAX_ORDERBOOK_DELTA_CHANGES};
+use crate::chunk_allocation::to_orderbook_chunk;
+
+fn book_entry(chunk: &Chunk) -> Option<OrderbookChunk> {
+    chunk.is_listed().then(|| to_orderbook_chunk(chunk.clone()))
+}
+
+/// Seq of the latest change, 0 before the first
+pub fn current_seq() -> u64 {
+    ORDERBOOK_CHANGES.with(|changes| changes.borrow().last_key_value().map_or(0, |(seq, _)| seq))
+}
+
+/// Record a chunk write if it changed what the book shows for that chunk
+/// Called by insert_chunk and update_chunk
+pub fn record_chunk_change(previous: Option<&Chunk>, chunk: &Chunk) {
+    let before = previous.and_then(book_entry);
+    let after = book_entry(chunk);
+    if before == after {
+        return;
+    }
+
+    ORDERBOOK_CHANGES.with(|changes| {
+        let mut changes = changes.borrow_mut();
+        let seq = changes.last_key_value().map_or(0, |(seq, _)| seq) + 1;
+        changes.insert(seq, OrderbookChange {
+            seq,
+            chunk_id: chunk.id,
+            chunk: after,
+            at: get_time(),
+        });
+
+        if changes.len() > ORDERBOOK_CHANGES_RETAINED {
+            if let Some((oldest, _)) = changes.first_key_value() {
+                changes.remove(&oldest);
+            }
+        }
+    });
+}
+
+/// All Available chunks (FIFO order) and stats, stamped with the latest change's seq
+pub fn get_orderbook_snapshot() -> OrderbookSnapshot {
+    OrderbookSnapshot {
+        seq: current_seq(),
+        chunks: crate::chunk_allocation::get_active_chunks(),
+        stats: crate::chunk_allocation::get_orderbook_stats(),
+        generated_at: get_time(),
+    }
+}
+
+/// Changes after since_seq, oldest first, up to MAX_ORDERBOOK_DELTA_CHANGES
+/// resync_required when changes after since_seq were already pruned, or since_seq is ahead
+/// of the feed (e.g. after a restore)
+pub fn get_orderbook_delta(since_seq: u64) -> OrderbookDelta {
+    ORDERBOOK_CHANGES.with(|changes| {
+        let changes = changes.borrow();
+        let latest = changes.last_key_value().map_or(0, |(seq, _)| seq);
+        let oldest = changes.first_key_value().map_or(latest + 1, |(seq, _)| seq);
+
+        if since_seq > latest || since_seq.saturating_add(1) < oldest {
+            return OrderbookDelta {
+                since_seq,
+                seq: latest,
+                changes: Vec::new(),
+                has_more: false,
+                resync_required: true,
+            };
+        }
+
+        let page: Vec<OrderbookChange> = changes.range(since_seq + 1..)
+            .take(MAX_ORDERBOOK_DELTA_CHANGES)
+            .map(|(_, change)| change)
+            .collect();
+        let seq = page.last().map_or(since_seq, |change| change.seq);
+
+        OrderbookDelta {
+            since_seq,
+            seq,
+            changes: page,
+            has_more: seq < latest,
+            resync_required: false,
+        }
+    })
+}
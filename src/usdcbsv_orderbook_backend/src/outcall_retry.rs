@@ -0,0 +1,203 @@
+/// Per-host backoff for HTTP outcalls (block sync, price feeds, parent tx lookups, alert webhooks)
+/// A failing host is skipped until its backoff expires, so retries happen on later calls instead
+/// of hammering it. State lives in AppState, so it survives upgrades
+use ic_cdk::api::call::{CallResult, RejectionCode};
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpResponse,
+};
+use sha2::{Digest, Sha256};
+use crate::config::{OUTCALL_BASE_BACKOFF_SECONDS, OUTCALL_MAX_ATTEMPTS, OUTCALL_MAX_BACKOFF_SECONDS};
+use crate::state::{create_admin_event, get_outcall_backoffs, get_time, update_outcall_backoffs};
+use crate::types::{AdminEventType, OutcallBackoff};
+
+/// Drop-in replacement for http_request that honours per-target backoff
+///
+/// - Skips the call (SysTransient rejection) while the target is backing off
+/// - Records transport errors, 429 and 5xx responses as failures and schedules the next attempt
+/// - Past OUTCALL_MAX_ATTEMPTS failures in a row, retries every OUTCALL_MAX_BACKOFF_SECONDS
+/// - Resets the target's backoff on success
+///
+/// Other responses are returned unchanged so callers keep their own status handling
+pub async fn http_request_with_backoff(
+    request: CanisterHttpRequestArgument,
+    cycles: u128,
+) -> CallResult<(HttpResponse,)> {
+    let target = outcall_target(&request.url);
+    let now = get_time();
+
+    if let Some(backoff) = get_backoff(&target) {
+        if now < backoff.next_attempt_at {
+            let wait_secs = (backoff.next_attempt_at - now) / 1_000_000_000;
+            return Err((
+                RejectionCode::SysTransient,
+                format!(
+                    "{} is backing off after {} failed attempts, next attempt in {}s",
+                    target, backoff.consecutive_failures, wait_secs
+                ),
+            ));
+        }
+    }
+
+    match http_request(request, cycles).await {
+        Ok((response,)) => {
+            if response.status == 429u64 || response.status >= 500u64 {
+                record_failure(&target, format!("HTTP status {}", response.status));
+            } else {
+                record_success(&target);
+            }
+            Ok((response,))
+        }
+        Err((code, msg)) => {
+            record_failure(&target, format!("{:?}: {}", code, msg));
+            Err((code, msg))
+        }
+    }
+}
+
+/// Backoff state of all targets that have failed since the last success
+pub fn get_outcall_backoff_status() -> Vec<OutcallBackoff> {
+    get_outcall_backoffs()
+}
+
+/// Clear a target's backoff so the next call goes through - Err if it has none
+pub fn reset_outcall_backoff(target: &str) -> Result<(), String> {
+    if get_backoff(target).is_none() {
+        return Err(format!("{} is not backing off", target));
+    }
+    update_outcall_backoffs(|backoffs| backoffs.retain(|backoff| backoff.target != target));
+    ic_cdk::println!("🔄 Outcall backoff for {} reset by admin", target);
+    Ok(())
+}
+
+fn get_backoff(target: &str) -> Option<OutcallBackoff> {
+    get_outcall_backoffs().into_iter().find(|backoff| backoff.target == target)
+}
+
+fn record_success(target: &str) {
+    // Most calls succeed with no backoff recorded - skip the state write for them
+    let Some(backoff) = get_backoff(target) else { return };
+    update_outcall_backoffs(|backoffs| backoffs.retain(|backoff| backoff.target != target));
+    ic_cdk::println!("✅ {} recovered after {} failed attempts", target, backoff.consecutive_failures);
+}
+
+fn record_failure(target: &str, error: String) {
+    let now = get_time();
+
+    let mut backoff = get_backoff(target).unwrap_or_else(|| OutcallBackoff {
+        target: target.to_string(),
+        consecutive_failures: 0,
+        next_attempt_at: 0,
+        last_error: None,
+        last_failure_at: None,
+        capped_at: None,
+    });
+    backoff.consecutive_failures = backoff.consecutive_failures.saturating_add(1);
+    backoff.next_attempt_at = now + backoff_delay_ns(target, backoff.consecutive_failures, now);
+    backoff.last_error = Some(error.clone());
+    backoff.last_failure_at = Some(now);
+    if backoff.consecutive_failures >= OUTCALL_MAX_ATTEMPTS && backoff.capped_at.is_none() {
+        backoff.capped_at = Some(now);
+    }
+    update_outcall_backoffs(|backoffs| {
+        backoffs.retain(|existing| existing.target != target);
+        backoffs.push(backoff.clone());
+    });
+
+    ic_cdk::println!(
+        "⚠️ Outcall to {} failed (attempt {}): {} - retrying in {}s",
+        target,
+        backoff.consecutive_failures,
+        error,
+        (backoff.next_attempt_at - now) / 1_000_000_000
+    );
+
+    create_admin_event(AdminEventType::OutcallFailed {
+        target: target.to_string(),
+        attempt: backoff.consecutive_failures,
+        error_message: error,
+        retry_at: backoff.next_attempt_at,
+    });
+}
+
+/// Exponential backoff (base * 2^(attempt-1), capped) plus up to 25% jitter - from
+/// OUTCALL_MAX_ATTEMPTS on, always the cap
+/// Jitter is derived from consensus time so all replicas compute the same value
+fn backoff_delay_ns(target: &str, attempt: u32, now: u64) -> u64 {
+    let exponential = if attempt >= OUTCALL_MAX_ATTEMPTS {
+        OUTCALL_MAX_BACKOFF_SECONDS
+    } else {
+        OUTCALL_BASE_BACKOFF_SECONDS
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(20))
+            .min(OUTCALL_MAX_BACKOFF_SECONDS)
+    };
+
+    let mut hasher = Sha256::new();
+    hasher.update(target.as_bytes());
+    hasher.update(attempt.to_be_bytes());
+    hasher.update(now.to_be_bytes());
+    let hash = hasher.finalize();
+    let mut seed = [0u8; 8];
+    seed.copy_from_slice(&hash[..8]);
+
+    let max_jitter = exponential / 4;
+    let jitter = if max_jitter > 0 { u64::from_be_bytes(seed) % (max_jitter + 1) } else { 0 };
+
+    (exponential + jitter) * 1_000_000_000
+}
+
+/// Backoff is tracked per API host
+fn outcall_target(url: &str) -> String {
+    url.split("://")
+        .nth(1)
+        .unwrap_or(url)
+        .split('/')
+        .next()
+        .unwrap_or(url)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock;
+
+    const HOST: &str = "api.whatsonchain.com";
+
+    fn outcall_events() -> Vec<u32> {
+        crate::state::get_admin_events().into_iter()
+            .filter_map(|event| match event.event_type {
+                AdminEventType::OutcallFailed { attempt, .. } => Some(attempt),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn failing_host_backs_off_to_the_cap_and_keeps_retrying() {
+        for attempt in 1..=OUTCALL_MAX_ATTEMPTS + 2 {
+            let now = get_time();
+            record_failure(HOST, "HTTP status 503".to_string());
+            let backoff = get_backoff(HOST).unwrap();
+            assert_eq!(backoff.consecutive_failures, attempt);
+            let delay_s = (backoff.next_attempt_at - now) / 1_000_000_000;
+            let expected_s = (OUTCALL_BASE_BACKOFF_SECONDS << (attempt - 1).min(20)).min(OUTCALL_MAX_BACKOFF_SECONDS);
+            let expected_s = if attempt >= OUTCALL_MAX_ATTEMPTS { OUTCALL_MAX_BACKOFF_SECONDS } else { expected_s };
+            assert!((expected_s..=expected_s + expected_s / 4).contains(&delay_s), "attempt {}: {}s", attempt, delay_s);
+            assert_eq!(backoff.capped_at.is_some(), attempt >= OUTCALL_MAX_ATTEMPTS);
+            mock::advance_time(delay_s * 1_000_000_000);
+        }
+
+        // One event per failed attempt, newest first
+        assert_eq!(outcall_events(), (1..=OUTCALL_MAX_ATTEMPTS + 2).rev().collect::<Vec<_>>());
+
+        record_success(HOST);
+        assert!(get_backoff(HOST).is_none());
+        assert!(reset_outcall_backoff(HOST).is_err());
+    }
+
+    #[test]
+    fn backoff_is_tracked_per_host() {
+        assert_eq!(outcall_target("https://api.whatsonchain.com/v1/bsv/main/tx/ab/hex"), HOST);
+        assert_eq!(outcall_target("api.bitails.io/block/latest"), "api.bitails.io");
+    }
+}
@@ -0,0 +1,226 @@
+/// Retry queue for ledger transfers that failed inline - timeout/unclaimed penalties and
+/// cancel refunds used to be logged and dropped, losing the money movement
+/// Queued transfers are retried from the cleanup task with exponential backoff and end up
+/// Failed (for admin review) once PENDING_TRANSFER_MAX_ATTEMPTS is reached
+use crate::types::*;
+use crate::state::*;
+use crate::config::{
+    PENDING_TRANSFER_BASE_DELAY_SECONDS, PENDING_TRANSFER_MAX_DELAY_SECONDS,
+    PENDING_TRANSFER_MAX_ATTEMPTS, MAX_PENDING_TRANSFERS_PER_RUN, CKUSDC_TRANSFER_FEE,
+};
+use candid::Principal;
+
+/// BASE * 2^(attempts-1), capped at PENDING_TRANSFER_MAX_DELAY_SECONDS
+fn retry_delay_seconds(attempts: u32) -> u64 {
+    PENDING_TRANSFER_BASE_DELAY_SECONDS
+        .saturating_mul(1u64 << attempts.saturating_sub(1).min(20))
+        .min(PENDING_TRANSFER_MAX_DELAY_SECONDS)
+}
+
+fn queue(kind: PendingTransferKind, amount_e6: u128, memo: Vec<u8>, error: String) -> PendingTransfer {
+    let now = get_time();
+    let transfer = insert_pending_transfer(|id| PendingTransfer {
+        id,
+        kind,
+        amount_e6,
+        memo,
+        status: PendingTransferStatus::Pending,
+        attempts: 1,
+        next_attempt_at: now + retry_delay_seconds(1) * 1_000_000_000,
+        last_error: Some(error),
+        block_index: None,
+        created_at: now,
+        updated_at: now,
+    });
+    ic_cdk::println!("🔁 Queued transfer #{} for retry: {:?}", transfer.id, transfer.kind);
+    transfer
+}
+
+/// Queue the ledger side of a penalty whose deduct_penalty transfer failed
+/// (the filler's penalties_paid was already updated)
+pub fn queue_penalty(filler: Principal, recipient: Option<Principal>, penalty_amount: f64, memo_hint: String, error: String) -> PendingTransfer {
+    queue(
+        PendingTransferKind::Penalty { filler, recipient },
+        crate::filler_accounts::penalty_transfer_amount_e6(penalty_amount),
+        crate::filler_accounts::penalty_memo(Some(memo_hint)),
+        error,
+    )
+}
+
+/// Queue a cancel refund to the maker - desired_amount_e6 as passed to transfer_ckusdc_from_order
+pub fn queue_order_refund(maker: Principal, order_id: OrderId, desired_amount_e6: u128, memo: Vec<u8>, error: String) -> PendingTransfer {
+    queue(
+        PendingTransferKind::OrderRefund { maker, order_id },
+        desired_amount_e6.saturating_sub(CKUSDC_TRANSFER_FEE),
+        memo,
+        error,
+    )
+}
+
+async fn attempt(transfer: &PendingTransfer) -> Result<u64, String> {
+    match transfer.kind {
+        PendingTransferKind::Penalty { filler, recipient } => {
+            crate::filler_accounts::transfer_penalty(filler, recipient, transfer.amount_e6, transfer.memo.clone()).await
+        }
+        PendingTransferKind::OrderRefund { maker, order_id } => {
+            crate::ckusdc_integration::transfer_ckusdc_from_order(
+                maker,
+                order_id,
+                maker,
+                None, // Maker's default subaccount
+                transfer.amount_e6 + CKUSDC_TRANSFER_FEE,
+                Some(transfer.memo.clone()),
+            ).await
+        }
+    }
+}
+
+/// Keep the RefundAttempt of a queued order refund in step with the transfer
+fn sync_refund_attempt(id: u64) {
+    if let Some(transfer) = get_pending_transfer(id) {
+        if let PendingTransferKind::OrderRefund { order_id, .. } = transfer.kind {
+            crate::order_management::sync_refund_attempt(order_id, &transfer);
+        }
+    }
+}
+
+/// Retry due pending transfers, oldest due first
+/// Returns the number completed in this run
+pub async fn process_pending_transfers() -> u64 {
+    let now = get_time();
+    let mut due: Vec<PendingTransfer> = get_pending_transfers_by_status(PendingTransferStatus::Pending)
+        .into_iter()
+        .filter(|t| t.next_attempt_at <= now)
+        .collect();
+    due.sort_by_key(|t| t.next_attempt_at);
+
+    let mut completed = 0u64;
+    for transfer in due.into_iter().take(MAX_PENDING_TRANSFERS_PER_RUN) {
+        // Push the next attempt out before awaiting so an overlapping run can't pick it up too
+        update_pending_transfer(transfer.id, |t| {
+            t.next_attempt_at = now + retry_delay_seconds(t.attempts + 1) * 1_000_000_000;
+        });
+
+        let result = attempt(&transfer).await;
+        let now = get_time();
+        update_pending_transfer(transfer.id, |t| {
+            t.attempts += 1;
+            t.updated_at = now;
+            match result {
+                Ok(block_index) => {
+                    ic_cdk::println!("✅ Pending transfer #{} completed on attempt {}. Block: {}", t.id, t.attempts, block_index);
+                    t.status = PendingTransferStatus::Completed;
+                    t.block_index = Some(block_index);
+                    t.last_error = None;
+                    completed += 1;
+                }
+                Err(e) => {
+                    if t.attempts >= PENDING_TRANSFER_MAX_ATTEMPTS {
+                        ic_cdk::println!("❌ Pending transfer #{} failed permanently after {} attempts: {}", t.id, t.attempts, e);
+                        t.status = PendingTransferStatus::Failed;
+                    } else {
+                        ic_cdk::println!("⚠️ Pending transfer #{} attempt {} failed: {}", t.id, t.attempts, e);
+                        t.next_attempt_at = now + retry_delay_seconds(t.attempts) * 1_000_000_000;
+                    }
+                    t.last_error = Some(e);
+                }
+            }
+        });
+        sync_refund_attempt(transfer.id);
+    }
+
+    completed
+}
+
+/// Whether a refund of the order is still queued or gave up pending admin review -
+/// until it lands the order's subaccount balance is owed to the maker
+pub fn has_outstanding_order_refund(order_id: OrderId) -> bool {
+    [PendingTransferStatus::Pending, PendingTransferStatus::Failed].into_iter()
+        .flat_map(get_pending_transfers_by_status)
+        .any(|t| matches!(t.kind, PendingTransferKind::OrderRefund { order_id: id, .. } if id == order_id))
+}
+
+/// Transfers that gave up after PENDING_TRANSFER_MAX_ATTEMPTS, most recent first
+pub fn get_failed_transfers() -> Vec<PendingTransfer> {
+    let mut failed = get_pending_transfers_by_status(PendingTransferStatus::Failed);
+    failed.sort_by_key(|t| std::cmp::Reverse(t.updated_at));
+    failed
+}
+
+/// Put a Failed transfer back in the queue with a fresh attempt budget, due immediately
+pub fn retry_failed_transfer(id: u64) -> Result<PendingTransfer, EasySwapError> {
+    let transfer = get_pending_transfer(id)
+        .ok_or_else(|| EasySwapError::not_found(format!("Pending transfer {} not found", id)))?;
+    if transfer.status != PendingTransferStatus::Failed {
+        return Err(EasySwapError::invalid_state(format!(
+            "Pending transfer {} is {:?}, only Failed transfers can be retried", id, transfer.status
+        )));
+    }
+
+    let now = get_time();
+    update_pending_transfer(id, |t| {
+        t.status = PendingTransferStatus::Pending;
+        t.attempts = 0;
+        t.next_attempt_at = now;
+        t.updated_at = now;
+    });
+    sync_refund_attempt(id);
+    get_pending_transfer(id).ok_or_else(|| EasySwapError::not_found(format!("Pending transfer {} not found", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        assert_eq!(retry_delay_seconds(0), PENDING_TRANSFER_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay_seconds(1), PENDING_TRANSFER_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay_seconds(2), 2 * PENDING_TRANSFER_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay_seconds(4), 8 * PENDING_TRANSFER_BASE_DELAY_SECONDS);
+        assert_eq!(retry_delay_seconds(u32::MAX), PENDING_TRANSFER_MAX_DELAY_SECONDS);
+    }
+
+    #[test]
+    fn queued_refund_backs_off_gives_up_and_can_be_requeued() {
+        let order_account = crate::ckusdc_integration::get_order_deposit_account(maker(), 7);
+        mock::mint(order_account, 1_000_000);
+        mock::set_ledger_down(true);
+        let transfer = queue_order_refund(maker(), 7, 1_000_000, b"Refund O7".to_vec(), "rejected".to_string());
+        assert!(has_outstanding_order_refund(7));
+        assert!(!has_outstanding_order_refund(8));
+
+        // Not due yet
+        assert_eq!(block_on(process_pending_transfers()), 0);
+        assert_eq!(get_pending_transfer(transfer.id).unwrap().attempts, 1);
+
+        for _ in 1..PENDING_TRANSFER_MAX_ATTEMPTS {
+            mock::advance_time(PENDING_TRANSFER_MAX_DELAY_SECONDS * 1_000_000_000);
+            assert_eq!(block_on(process_pending_transfers()), 0);
+        }
+        let failed = get_pending_transfer(transfer.id).unwrap();
+        assert_eq!((failed.status, failed.attempts), (PendingTransferStatus::Failed, PENDING_TRANSFER_MAX_ATTEMPTS));
+        assert_eq!(get_failed_transfers().len(), 1);
+        // Still owed to the maker while it waits for review
+        assert!(has_outstanding_order_refund(7));
+
+        mock::set_ledger_down(false);
+        mock::advance_time(PENDING_TRANSFER_MAX_DELAY_SECONDS * 1_000_000_000);
+        assert_eq!(block_on(process_pending_transfers()), 0, "Failed transfers aren't retried on their own");
+        assert!(retry_failed_transfer(transfer.id).is_ok());
+        assert!(retry_failed_transfer(transfer.id).is_err(), "only Failed transfers can be requeued");
+        assert_eq!(block_on(process_pending_transfers()), 1);
+
+        let completed = get_pending_transfer(transfer.id).unwrap();
+        assert_eq!(completed.status, PendingTransferStatus::Completed);
+        assert!(completed.block_index.is_some() && completed.last_error.is_none());
+        assert!(!has_outstanding_order_refund(7));
+        assert_eq!(mock::balance(order_account), 0);
+        assert_eq!(mock::balance(icrc_ledger_types::icrc1::account::Account { owner: maker(), subaccount: None }), 1_000_000 - CKUSDC_TRANSFER_FEE);
+    }
+}
@@ -0,0 +1,79 @@
+/// BSV/USD price history - every oracle fetch updates the hourly OHLC candle it falls in,
+/// so charts show exactly the prices used for matching
+use crate::types::*;
+use crate::state::*;
+use crate::config::{PRICE_CANDLE_RETENTION_HOURS, MAX_PRICE_CANDLES_PER_QUERY};
+
+const HOUR_NS: u64 = 60 * 60 * 1_000_000_000;
+
+fn interval_ns(interval: &CandleInterval) -> u64 {
+    match interval {
+        CandleInterval::Hour => HOUR_NS,
+        CandleInterval::FourHours => 4 * HOUR_NS,
+        CandleInterval::Day => 24 * HOUR_NS,
+    }
+}
+
+/// Fold an oracle price into the current hour's candle
+pub fn record_price_sample(price: f64, timestamp: u64) {
+    if price <= 0.0 {
+        return;
+    }
+
+    let start = timestamp - timestamp % HOUR_NS;
+    let candle = match get_price_candle(start) {
+        Some(mut candle) => {
+            candle.high = candle.high.max(price);
+            candle.low = candle.low.min(price);
+            candle.close = price;
+            candle.samples += 1;
+            candle
+        }
+        None => PriceCandle {
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            samples: 1,
+        },
+    };
+
+    insert_price_candle(candle);
+    prune_price_candles(start.saturating_sub(PRICE_CANDLE_RETENTION_HOURS * HOUR_NS));
+}
+
+/// Candles between `from` and `to` (inclusive), hourly candles merged into the requested interval
+/// Intervals without any oracle fetch are omitted
+pub fn get_price_candles(from: u64, to: u64, interval: CandleInterval) -> Result<Vec<PriceCandle>, String> {
+    if from > to {
+        return Err("from must not be after to".to_string());
+    }
+
+    let step = interval_ns(&interval);
+    let from = from - from % step;
+    let mut candles: Vec<PriceCandle> = Vec::new();
+
+    for hourly in get_price_candles_range(from, to) {
+        let start = hourly.start - hourly.start % step;
+        match candles.last_mut() {
+            Some(candle) if candle.start == start => {
+                candle.high = candle.high.max(hourly.high);
+                candle.low = candle.low.min(hourly.low);
+                candle.close = hourly.close;
+                candle.samples += hourly.samples;
+            }
+            _ => {
+                if candles.len() == MAX_PRICE_CANDLES_PER_QUERY {
+                    return Err(format!(
+                        "Range spans more than {} candles - narrow it or use a larger interval",
+                        MAX_PRICE_CANDLES_PER_QUERY
+                    ));
+                }
+                candles.push(PriceCandle { start, ..hourly });
+            }
+        }
+    }
+
+    Ok(candles)
+}
@@ -1,9 +1,13 @@
 use ic_cdk::api::management_canister::http_request::{
-    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs, TransformContext,
+    CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse, TransformArgs, TransformContext,
 };
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use crate::state::*;
+use crate::types::{ApiProvider, PriceSource, PriceSourceSample, PriceSources};
+use crate::config::{PRICE_MAX_DEVIATION_BPS, PRICE_MIN_AGREEING_SOURCES};
+use crate::runtime::Oracle;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct CoinloreResponse {
@@ -18,8 +22,13 @@ struct CoinloreResponse {
 }
 
 const BSV_API_URL: &str = "https://api.coinlore.net/api/ticker/?id=33234";
+const WOC_EXCHANGE_RATE_URL: &str = "https://api.whatsonchain.com/v1/bsv/main/exchangerate";
 const PRICE_CACHE_DURATION_NS: u64 = 5 * 60 * 1_000_000_000; // 5 minutes in nanoseconds
 
+thread_local! {
+    static LATEST_PRICE_SOURCES: RefCell<Option<PriceSources>> = RefCell::new(None);
+}
+
 pub async fn get_bsv_price() -> Result<f64, String> {
     // Check cache first
     let (cached_price, last_update) = get_cached_bsv_price();
@@ -29,22 +38,138 @@ pub async fn get_bsv_price() -> Result<f64, String> {
         return Ok(cached_price);
     }
     
-    // Try XRC oracle first (decentralized)
-    match crate::xrc_oracle::get_bsv_usd_rate().await {
-        Ok(price) => {
-            // Update cache
-            crate::state::update_cached_bsv_price(price);
-            return Ok(price);
+    // Query every feed so a single manipulated or broken source can't set the price
+    let results = crate::runtime::oracle().fetch_bsv_prices().await;
+
+    let sources = aggregate_prices(results, now);
+    LATEST_PRICE_SOURCES.with(|s| *s.borrow_mut() = Some(sources.clone()));
+
+    match sources.median_price {
+        Some(price) => {
+            store_bsv_price(price);
+            Ok(price)
+        }
+        None => {
+            let summary: Vec<String> = sources.samples.iter()
+                .map(|s| match (&s.price, &s.error) {
+                    (Some(p), _) => format!("{:?}=${:.4}", s.source, p),
+                    (None, Some(e)) => format!("{:?} failed: {}", s.source, e),
+                    (None, None) => format!("{:?} failed", s.source),
+                })
+                .collect();
+            Err(format!(
+                "Fewer than {} price sources agree within {}bps: {}",
+                PRICE_MIN_AGREEING_SOURCES, PRICE_MAX_DEVIATION_BPS, summary.join(", ")
+            ))
+        }
+    }
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
+/// Median of the responding feeds, outliers beyond PRICE_MAX_DEVIATION_BPS dropped and the
+/// median taken again over the rest
+fn aggregate_prices(results: Vec<(PriceSource, Result<f64, String>)>, now: u64) -> PriceSources {
+    let mut samples: Vec<PriceSourceSample> = results.into_iter()
+        .map(|(source, result)| match result {
+            Ok(price) if price.is_finite() && price > 0.0 => PriceSourceSample {
+                source, price: Some(price), error: None, accepted: false,
+            },
+            Ok(price) => PriceSourceSample {
+                source, price: None, error: Some(format!("Invalid price {}", price)), accepted: false,
+            },
+            Err(e) => PriceSourceSample {
+                source, price: None, error: Some(e), accepted: false,
+            },
+        })
+        .collect();
+
+    let mut prices: Vec<f64> = samples.iter().filter_map(|s| s.price).collect();
+    let median_price = median(&mut prices).and_then(|raw_median| {
+        let max_deviation = raw_median * PRICE_MAX_DEVIATION_BPS as f64 / 10_000.0;
+        for sample in samples.iter_mut() {
+            sample.accepted = sample.price.is_some_and(|p| (p - raw_median).abs() <= max_deviation);
+        }
+
+        let mut accepted: Vec<f64> = samples.iter()
+            .filter(|s| s.accepted)
+            .filter_map(|s| s.price)
+            .collect();
+        if accepted.len() < PRICE_MIN_AGREEING_SOURCES {
+            return None;
+        }
+        median(&mut accepted)
+    });
+
+    for sample in samples.iter().filter(|s| s.price.is_some() && !s.accepted) {
+        ic_cdk::println!("⚠️  {:?} price ${:?} rejected as outlier", sample.source, sample.price);
+    }
+
+    PriceSources {
+        median_price,
+        samples,
+        fetched_at: now,
+    }
+}
+
+/// Per-feed results of the latest oracle round (not kept across upgrades)
+pub fn get_price_sources() -> Option<PriceSources> {
+    LATEST_PRICE_SOURCES.with(|s| s.borrow().clone())
+}
+
+/// Update the cache and the price candle for this fetch
+fn store_bsv_price(price: f64) {
+    let now = get_time();
+    update_cached_bsv_price(price);
+    crate::price_candles::record_price_sample(price, now);
+    crate::circuit_breaker::record_price_sample(price, now);
+}
+
+pub(crate) async fn fetch_bsv_price_from_woc() -> Result<f64, String> {
+    let request = CanisterHttpRequestArgument {
+        url: WOC_EXCHANGE_RATE_URL.to_string(),
+        method: HttpMethod::GET,
+        body: None,
+        max_response_bytes: Some(2048),
+        transform: Some(TransformContext::from_name(
+            "transform_exchange_rate_response".to_string(),
+            vec![],
+        )),
+        headers: crate::block_sync::provider_headers(ApiProvider::WhatsOnChain),
+    };
+
+    match crate::outcall_retry::http_request_with_backoff(request, 25_000_000_000).await {
+        Ok((response,)) => {
+            if response.status != 200u64 {
+                return Err(format!("WoC API error: status {}", response.status));
+            }
+
+            let body_str = String::from_utf8(response.body)
+                .map_err(|e| format!("Failed to parse response as UTF-8: {}", e))?;
+            let json: serde_json::Value = serde_json::from_str(&body_str)
+                .map_err(|e| format!("Failed to parse JSON: {} - Body: {}", e, body_str))?;
+
+            json["rate"].as_f64()
+                .ok_or_else(|| format!("Missing 'rate' field - Body: {}", body_str))
         }
-        Err(e) => {
-            ic_cdk::println!("XRC oracle failed, falling back to HTTP: {}", e);
-            // Fall back to HTTP API
-            fetch_bsv_price_from_api().await
+        Err((r, m)) => {
+            Err(format!("HTTP request failed. RejectionCode: {:?}, Error: {}", r, m))
         }
     }
 }
 
-async fn fetch_bsv_price_from_api() -> Result<f64, String> {
+pub(crate) async fn fetch_bsv_price_from_api() -> Result<f64, String> {
     let request_headers = vec![
         HttpHeader {
             name: "User-Agent".to_string(),
@@ -73,7 +198,7 @@ async fn fetch_bsv_price_from_api() -> Result<f64, String> {
         headers: request_headers,
     };
 
-    match http_request(request, 25_000_000_000).await {
+    match crate::outcall_retry::http_request_with_backoff(request, 25_000_000_000).await {
         Ok((response,)) => {
             let body_str = String::from_utf8(response.body)
                 .map_err(|e| format!("Failed to parse response as UTF-8: {}", e))?;
@@ -87,13 +212,8 @@ async fn fetch_bsv_price_from_api() -> Result<f64, String> {
             }
             
             let price_str = &prices[0].price_usd;
-            let price = price_str.parse::<f64>()
-                .map_err(|e| format!("Failed to parse price: {}", e))?;
-            
-            // Update cache
-            update_cached_bsv_price(price);
-            
-            Ok(price)
+            price_str.parse::<f64>()
+                .map_err(|e| format!("Failed to parse price: {}", e))
         }
         Err((r, m)) => {
             Err(format!("HTTP request failed. RejectionCode: {:?}, Error: {}", r, m))
@@ -101,6 +221,27 @@ async fn fetch_bsv_price_from_api() -> Result<f64, String> {
     }
 }
 
+/// Keep only the rate - WoC also returns a timestamp that differs between replicas
+#[ic_cdk::query]
+fn transform_exchange_rate_response(args: TransformArgs) -> HttpResponse {
+    let rate = serde_json::from_slice::<serde_json::Value>(&args.response.body)
+        .ok()
+        .and_then(|json| match &json["rate"] {
+            serde_json::Value::Number(n) => n.as_f64(),
+            serde_json::Value::String(s) => s.parse::<f64>().ok(),
+            _ => None,
+        });
+
+    HttpResponse {
+        status: args.response.status.clone(),
+        body: match rate {
+            Some(rate) => format!(r#"{{"rate":{}}}"#, rate).into_bytes(),
+            None => Vec::new(),
+        },
+        headers: vec![],
+    }
+}
+
 #[ic_cdk::query]
 fn transform_price_response(args: TransformArgs) -> HttpResponse {
     HttpResponse {
@@ -144,3 +285,52 @@ pub fn price_exceeds_max(max_bsv_price: f64) -> Result<bool, String> {
     
     Ok(cached_price > max_bsv_price)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_averages_the_middle_pair_of_an_even_count() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [42.0]), Some(42.0));
+        assert_eq!(median(&mut [50.0, 40.0, 45.0]), Some(45.0));
+        assert_eq!(median(&mut [50.0, 40.0, 44.0, 46.0]), Some(45.0));
+    }
+
+    #[test]
+    fn outlier_feed_is_dropped_before_the_median() {
+        let sources = aggregate_prices(vec![
+            (PriceSource::Xrc, Ok(50.0)),
+            (PriceSource::WhatsOnChain, Ok(50.5)),
+            (PriceSource::Coinlore, Ok(60.0)),
+        ], 7);
+
+        assert_eq!(sources.median_price, Some(50.25));
+        assert_eq!(sources.fetched_at, 7);
+        let accepted: Vec<bool> = sources.samples.iter().map(|s| s.accepted).collect();
+        assert_eq!(accepted, vec![true, true, false]);
+    }
+
+    #[test]
+    fn too_few_agreeing_feeds_give_no_price() {
+        // One feed down, one bogus - a single survivor is not enough
+        let sources = aggregate_prices(vec![
+            (PriceSource::Xrc, Err("timeout".to_string())),
+            (PriceSource::WhatsOnChain, Ok(-1.0)),
+            (PriceSource::Coinlore, Ok(50.0)),
+        ], 0);
+        assert_eq!(sources.median_price, None);
+        assert_eq!(sources.samples[0].error.as_deref(), Some("timeout"));
+        assert!(sources.samples[1].price.is_none() && sources.samples[1].error.is_some());
+        assert!(sources.samples[2].accepted);
+
+        // Two feeds that disagree beyond PRICE_MAX_DEVIATION_BPS reject each other
+        let sources = aggregate_prices(vec![
+            (PriceSource::Xrc, Ok(40.0)),
+            (PriceSource::Coinlore, Ok(50.0)),
+        ], 0);
+        assert_eq!(sources.median_price, None);
+        assert!(sources.samples.iter().all(|s| !s.accepted));
+    }
+}
@@ -0,0 +1,70 @@
+/// Maker quiet hours - UTC hours/weekdays during which a maker's chunks are kept Idle
+/// (e.g. weekends when they can't monitor BSV receipts). Applied by the idle/reactivation
+/// heartbeat, so makers don't have to cancel and recreate orders around their schedule
+use candid::Principal;
+use crate::types::*;
+use crate::state::*;
+
+const NANOS_PER_HOUR: u64 = 60 * 60 * 1_000_000_000;
+
+/// Set the caller's quiet hours; empty hours and days clears the schedule
+pub fn set_my_quiet_hours(quiet_hours: Vec<u8>, quiet_days: Vec<u8>) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot set quiet hours. Please authenticate first."));
+    }
+    
+    if let Some(hour) = quiet_hours.iter().find(|h| **h > 23) {
+        return Err(EasySwapError::invalid_input(format!("Invalid hour {} (must be 0-23 UTC)", hour)));
+    }
+    if let Some(day) = quiet_days.iter().find(|d| **d > 6) {
+        return Err(EasySwapError::invalid_input(format!("Invalid day {} (must be 0-6, Monday = 0)", day)));
+    }
+    
+    let mut quiet_hours = quiet_hours;
+    quiet_hours.sort();
+    quiet_hours.dedup();
+    let mut quiet_days = quiet_days;
+    quiet_days.sort();
+    quiet_days.dedup();
+    
+    // Quiet all day, every day is just a cancelled order
+    if quiet_hours.len() == 24 || quiet_days.len() == 7 {
+        return Err(EasySwapError::invalid_input("Quiet hours cannot cover the whole week. Cancel your orders instead."));
+    }
+    
+    if quiet_hours.is_empty() && quiet_days.is_empty() {
+        remove_maker_quiet_hours(caller);
+        ic_cdk::println!("🔔 Quiet hours cleared for maker {}", caller);
+        return Ok(());
+    }
+    
+    ic_cdk::println!("🔕 Quiet hours for maker {}: hours {:?}, days {:?}", caller, quiet_hours, quiet_days);
+    set_maker_quiet_hours(MakerQuietHours {
+        maker: caller,
+        quiet_hours,
+        quiet_days,
+        updated_at: get_time(),
+    });
+    
+    Ok(())
+}
+
+pub fn get_my_quiet_hours() -> Option<MakerQuietHours> {
+    get_maker_quiet_hours(get_caller())
+}
+
+/// Whether the maker is inside one of their quiet windows at `now` (nanoseconds)
+pub fn is_maker_quiet(maker: Principal, now: u64) -> bool {
+    match get_maker_quiet_hours(maker) {
+        Some(schedule) => {
+            let hours_since_epoch = now / NANOS_PER_HOUR;
+            let hour = (hours_since_epoch % 24) as u8;
+            // 1970-01-01 was a Thursday (Monday = 0)
+            let day = ((hours_since_epoch / 24 + 3) % 7) as u8;
+            schedule.quiet_hours.contains(&hour) || schedule.quiet_days.contains(&day)
+        }
+        None => false,
+    }
+}
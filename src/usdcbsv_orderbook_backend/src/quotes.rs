@@ -0,0 +1,320 @@
+/// Request-for-quote flow - fillers post standing quotes (amount, price, expiry) and a maker
+/// picks one to fill against their own order at the quoted price instead of the oracle price
+/// Quotes are authenticated by the filler's IC principal - only the caller can post or cancel them
+use crate::types::*;
+use crate::state::*;
+use crate::trade_lifecycle;
+use crate::config::{MIN_CHUNK_SIZE_E6, MAX_QUOTE_LIFETIME_SECONDS, MAX_OPEN_QUOTES_PER_FILLER, QUOTE_RETENTION_SECONDS};
+use candid::Principal;
+
+fn is_live(quote: &Quote, now: u64) -> bool {
+    quote.status == QuoteStatus::Open && quote.expires_at > now
+}
+
+/// Post a quote to buy up to amount_usd of maker USD at bsv_price, valid for expires_in_seconds
+/// The filler's security deposit must already cover the full quoted amount
+pub async fn post_quote(amount_usd: f64, bsv_price: f64, expires_in_seconds: u64) -> Result<Quote, EasySwapError> {
+    let caller = get_caller();
+    let now = get_time();
+
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot post quotes. Please authenticate first."));
+    }
+    crate::filler_accounts::ensure_approved_filler(caller)?;
+
+    let amount_usd_e6 = usd_to_e6(amount_usd);
+    let min_amount_e6 = get_trade_size_limits().min_trade_usd_e6.max(MIN_CHUNK_SIZE_E6);
+    if amount_usd_e6 < min_amount_e6 {
+        return Err(EasySwapError::invalid_input(format!(
+            "Quote amount must be at least ${}",
+            e6_to_usd(min_amount_e6)
+        )));
+    }
+
+    if !bsv_price.is_finite() || bsv_price <= 0.0 {
+        return Err(EasySwapError::invalid_input("BSV price must be positive"));
+    }
+
+    if expires_in_seconds == 0 || expires_in_seconds > MAX_QUOTE_LIFETIME_SECONDS {
+        return Err(EasySwapError::invalid_input(format!(
+            "Quote expiry must be between 1 and {} seconds",
+            MAX_QUOTE_LIFETIME_SECONDS
+        )));
+    }
+
+    let open_quotes = get_all_quotes().into_iter()
+        .filter(|q| q.filler == caller && is_live(q, now))
+        .count();
+    if open_quotes >= MAX_OPEN_QUOTES_PER_FILLER {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "At most {} open quotes per filler",
+            MAX_OPEN_QUOTES_PER_FILLER
+        )));
+    }
+
+    trade_lifecycle::check_filler_capacity(caller, amount_usd_e6).await?;
+
+    let now = get_time();
+    let quote = insert_new_quote(|id| Quote {
+        id,
+        filler: caller,
+        amount_usd_e6,
+        bsv_price,
+        status: QuoteStatus::Open,
+        created_at: now,
+        expires_at: now + expires_in_seconds * 1_000_000_000,
+        updated_at: now,
+    });
+
+    ic_cdk::println!("📝 Quote {} posted by {}: ${} at ${}/BSV", quote.id, caller, e6_to_usd(amount_usd_e6), bsv_price);
+
+    Ok(quote)
+}
+
+/// Withdraw one of the caller's open quotes
+pub fn cancel_quote(quote_id: QuoteId) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    let quote = get_quote(quote_id)
+        .ok_or_else(|| EasySwapError::not_found("Quote not found"))?;
+
+    if quote.filler != caller {
+        return Err(EasySwapError::unauthorized("Only the filler who posted a quote can cancel it"));
+    }
+
+    if quote.status != QuoteStatus::Open {
+        return Err(EasySwapError::invalid_state(format!("Cannot cancel a {:?} quote", quote.status)));
+    }
+
+    let now = get_time();
+    update_quote(quote_id, |q| {
+        q.status = QuoteStatus::Cancelled;
+        q.updated_at = now;
+    })?;
+
+    Ok(())
+}
+
+/// Open, unexpired quotes - lowest price first (the most BSV per dollar for the maker)
+pub fn get_open_quotes() -> Vec<Quote> {
+    let now = get_time();
+    let mut quotes: Vec<Quote> = get_all_quotes().into_iter()
+        .filter(|q| is_live(q, now))
+        .collect();
+    quotes.sort_by(|a, b| a.bsv_price.total_cmp(&b.bsv_price).then(a.id.cmp(&b.id)));
+    quotes
+}
+
+/// All quotes posted by the caller, newest first
+pub fn get_my_quotes() -> Vec<Quote> {
+    let caller = get_caller();
+    let mut quotes: Vec<Quote> = get_all_quotes().into_iter()
+        .filter(|q| q.filler == caller)
+        .collect();
+    quotes.sort_by_key(|q| std::cmp::Reverse(q.id));
+    quotes
+}
+
+/// Maker accepts a quote against one of their Active orders - the order's Available chunks
+/// (up to the quoted amount and the per-trade chunk cap) are locked into a single trade at the quote's price
+pub async fn accept_quote(quote_id: QuoteId, order_id: OrderId) -> Result<TradeId, EasySwapError> {
+    let caller = get_caller();
+
+    let quote = get_quote(quote_id)
+        .ok_or_else(|| EasySwapError::not_found("Quote not found"))?;
+    validate_acceptance(&quote, order_id, caller, get_time())?;
+
+    // The filler's deposit may have moved since the quote was posted
+    trade_lifecycle::check_filler_capacity(quote.filler, quote.amount_usd_e6).await?;
+
+    // Re-check after the await - the quote or order may have changed meanwhile
+    let now = get_time();
+    let quote = get_quote(quote_id)
+        .ok_or_else(|| EasySwapError::not_found("Quote not found"))?;
+    let order = validate_acceptance(&quote, order_id, caller, now)?;
+
+    // The order's book may cap trades differently from the global limits
+    let max_chunks = crate::books::resolve(order.book_id)
+        .map(|book| crate::books::trade_size_limits(&book))
+        .unwrap_or_else(|_| get_trade_size_limits())
+        .max_chunks_per_trade as usize;
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut total_e6: UsdE6 = 0;
+    for chunk_id in &order.chunks {
+        if chunks.len() >= max_chunks {
+            break;
+        }
+        if let Some(chunk) = get_chunk(*chunk_id) {
+            if chunk.status != ChunkStatus::Available {
+                continue;
+            }
+            // Another filler's live reservation keeps the chunk out of this trade
+            if chunk.reservation_holder(now).is_some_and(|holder| holder != quote.filler) {
+                continue;
+            }
+            // Chunks can't be split - skip any that would overshoot the quote
+            if total_e6 + chunk.amount_usd_e6 <= quote.amount_usd_e6 {
+                total_e6 += chunk.amount_usd_e6;
+                chunks.push(chunk);
+            }
+        }
+    }
+
+    if chunks.is_empty() {
+        return Err(EasySwapError::invalid_state("Order has no Available chunks that fit within the quoted amount"));
+    }
+
+    let trade_id = trade_lifecycle::create_single_trade(
+        quote.filler,
+        order_id,
+        chunks,
+        quote.bsv_price,
+        quote.bsv_price,
+        None, // Quoted prices are firm - no improvement to share
+        now,
+    )?;
+
+    update_filler_account(quote.filler, |account| {
+        account.total_trades += 1;
+    })?;
+
+    update_quote(quote_id, |q| {
+        q.status = QuoteStatus::Accepted { order_id, trade_id };
+        q.updated_at = now;
+    })?;
+
+    ic_cdk::println!(
+        "🤝 Quote {} accepted on order {}: trade {} for ${} at ${}/BSV",
+        quote_id, order_id, trade_id, e6_to_usd(total_e6), quote.bsv_price
+    );
+
+    Ok(trade_id)
+}
+
+fn validate_acceptance(quote: &Quote, order_id: OrderId, caller: Principal, now: u64) -> Result<Order, EasySwapError> {
+    if !is_live(quote, now) {
+        return Err(EasySwapError::invalid_state("Quote is no longer open"));
+    }
+
+    let order = get_order(order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+
+    if order.maker != caller {
+        return Err(EasySwapError::unauthorized("Only the order maker can accept a quote against it"));
+    }
+
+    if order.status != OrderStatus::Active {
+        return Err(EasySwapError::invalid_state(format!("Cannot accept a quote on a {:?} order", order.status)));
+    }
+
+    if quote.filler == order.maker {
+        return Err(EasySwapError::invalid_input("Cannot accept your own quote"));
+    }
+
+    if quote.bsv_price > order.max_bsv_price {
+        return Err(EasySwapError::price_out_of_range(format!(
+            "Quote price (${}) exceeds the order's maximum BSV price (${})",
+            quote.bsv_price,
+            order.max_bsv_price
+        )));
+    }
+
+    if crate::circuit_breaker::is_trading_halted(now) {
+        return Err(EasySwapError::unavailable(
+            "Trading is paused after an extreme BSV price move and resumes automatically once the price stabilizes"
+        ));
+    }
+
+    Ok(order)
+}
+
+/// Delete accepted, cancelled and expired quotes older than QUOTE_RETENTION_SECONDS
+/// Returns the number of quotes removed
+pub fn prune_quotes(now: u64) -> u64 {
+    let cutoff = now.saturating_sub(QUOTE_RETENTION_SECONDS * 1_000_000_000);
+    let stale: Vec<QuoteId> = get_all_quotes().into_iter()
+        .filter(|q| !is_live(q, now) && q.updated_at.max(q.expires_at) < cutoff)
+        .map(|q| q.id)
+        .collect();
+
+    for quote_id in &stale {
+        remove_quote(*quote_id);
+    }
+
+    stale.len() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+
+    const SECOND_NS: u64 = 1_000_000_000;
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn filler() -> Principal {
+        Principal::from_slice(&[2, 2, 2])
+    }
+
+    /// $9 order of three $3 chunks, accepting up to $70/BSV
+    fn maker_order() -> OrderId {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker()), 20_000_000);
+        mock::set_caller(maker());
+        block_on(crate::order_management::create_order(9.0, 70.0, crate::lifecycle_tests::p2pkh_address(&[0x11; 20]), None)).unwrap()
+    }
+
+    #[test]
+    fn accepted_quote_locks_chunks_at_the_quoted_price_once() {
+        let order_id = maker_order();
+        mock::mint(crate::filler_accounts::get_deposit_account(filler()), 2_000_000);
+        mock::set_caller(filler());
+        assert!(block_on(post_quote(1.0, 60.0, 60)).is_err(), "below the minimum amount");
+        assert!(block_on(post_quote(6.0, 60.0, MAX_QUOTE_LIFETIME_SECONDS + 1)).is_err());
+        let too_dear = block_on(post_quote(6.0, 80.0, 600)).unwrap();
+        let quote = block_on(post_quote(7.0, 60.0, 600)).unwrap();
+        assert_eq!(get_open_quotes().iter().map(|q| q.id).collect::<Vec<_>>(), vec![quote.id, too_dear.id]);
+
+        assert!(block_on(accept_quote(quote.id, order_id)).is_err(), "only the order's maker");
+        mock::set_caller(maker());
+        assert!(matches!(block_on(accept_quote(too_dear.id, order_id)), Err(EasySwapError::PriceOutOfRange { .. })));
+        let trade_id = block_on(accept_quote(quote.id, order_id)).unwrap();
+
+        // Two whole chunks fit in $7
+        let trade = get_trade(trade_id).unwrap();
+        assert_eq!((trade.filler, trade.agreed_bsv_price, trade.amount_usd_e6), (filler(), 60.0, 6_000_000));
+        assert_eq!(trade.locked_chunks.len(), 2);
+        assert!(matches!(get_quote(quote.id).unwrap().status, QuoteStatus::Accepted { trade_id: accepted, .. } if accepted == trade_id));
+        assert!(block_on(accept_quote(quote.id, order_id)).is_err());
+        assert_eq!(get_open_quotes().len(), 1);
+    }
+
+    #[test]
+    fn quotes_lapse_can_be_cancelled_by_their_filler_and_are_pruned_later() {
+        mock::mint(crate::filler_accounts::get_deposit_account(filler()), 2_000_000);
+        mock::set_caller(filler());
+        let short = block_on(post_quote(3.0, 50.0, 60)).unwrap();
+        let cancelled = block_on(post_quote(3.0, 50.0, 600)).unwrap();
+
+        mock::set_caller(maker());
+        assert!(cancel_quote(cancelled.id).is_err(), "only the quote's filler");
+        mock::set_caller(filler());
+        cancel_quote(cancelled.id).unwrap();
+        assert!(cancel_quote(cancelled.id).is_err());
+
+        mock::advance_time(61 * SECOND_NS);
+        assert!(get_open_quotes().is_empty());
+        assert_eq!(get_my_quotes().len(), 2);
+
+        assert_eq!(prune_quotes(get_time()), 0, "kept for QUOTE_RETENTION_SECONDS");
+        mock::advance_time(QUOTE_RETENTION_SECONDS * SECOND_NS);
+        assert_eq!(prune_quotes(get_time()), 1);
+        assert!(get_quote(short.id).is_none());
+        // The cancelled quote is kept as long as it would have stayed open
+        mock::advance_time(600 * SECOND_NS);
+        assert_eq!(prune_quotes(get_time()), 1);
+        assert!(get_my_quotes().is_empty());
+    }
+}
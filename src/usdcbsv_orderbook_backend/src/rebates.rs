@@ -0,0 +1,186 @@
+/// Maker fee rebates - REBATE_SHARE_BPS of every ckUSDC activation fee accrues into the open epoch's
+/// pool, and when the epoch closes (every REBATE_EPOCH_NS, from the cleanup heartbeat) the
+/// pool is split between makers pro-rata by the volume their orders filled during it
+/// Rebates are paid in ckUSDC from the treasury's main account when the maker claims them -
+/// other books' fees stay in their own ledger's revenue (treasury::record_order_inflow)
+use candid::{Nat, Principal};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError};
+use crate::types::*;
+use crate::state::*;
+use crate::runtime::LedgerClient;
+use crate::config::{CKUSDC_TRANSFER_FEE, CK_USDC_LEDGER, REBATE_EPOCH_NS, REBATE_SHARE_BPS};
+
+fn empty_rebates(maker: Principal) -> MakerRebates {
+    MakerRebates {
+        maker,
+        epoch_volume_e6: 0,
+        owed_e6: 0,
+        claimed_e6: 0,
+        pending_claim: None,
+        last_claimed_at: None,
+    }
+}
+
+fn new_epoch(epoch: u64, started_at: u64, pool_e6: UsdE6) -> RebateEpoch {
+    RebateEpoch {
+        epoch,
+        started_at,
+        closed_at: None,
+        pool_e6,
+        total_volume_e6: 0,
+        distributed_e6: 0,
+        makers_rebated: 0,
+        claims: 0,
+    }
+}
+
+/// The open epoch - the first one starts with the first activation fee or fill
+fn open_epoch() -> RebateEpoch {
+    match get_latest_rebate_epoch() {
+        Some(epoch) => epoch,
+        None => {
+            let epoch = new_epoch(0, get_time(), 0);
+            insert_rebate_epoch(epoch.clone());
+            epoch
+        }
+    }
+}
+
+/// Set aside the rebate share of an activation fee that reached the treasury
+pub fn accrue(activation_fee_e6: UsdE6) {
+    let share_e6 = bps_of(activation_fee_e6, REBATE_SHARE_BPS);
+    if share_e6 == 0 {
+        return;
+    }
+    let mut epoch = open_epoch();
+    epoch.pool_e6 += share_e6;
+    insert_rebate_epoch(epoch);
+    update_treasury_buckets(|buckets| {
+        buckets.rebates_accrued_e6 = Some(buckets.rebates_accrued_e6.unwrap_or(0) + share_e6);
+    });
+}
+
+/// Count a filled chunk towards its maker's volume for the open epoch
+pub fn record_filled_volume(maker: Principal, amount_e6: UsdE6) {
+    open_epoch();
+    let mut rebates = get_maker_rebates(maker).unwrap_or_else(|| empty_rebates(maker));
+    rebates.epoch_volume_e6 += amount_e6;
+    set_maker_rebates(rebates);
+}
+
+/// Close the open epoch once REBATE_EPOCH_NS has passed - its pool is split by filled volume
+/// and whatever isn't distributed opens the next epoch's pool. Returns the closed epoch
+pub fn close_epoch_if_due() -> Option<RebateEpoch> {
+    let now = get_time();
+    let mut epoch = get_latest_rebate_epoch()?;
+    if now < epoch.started_at + REBATE_EPOCH_NS {
+        return None;
+    }
+
+    let makers: Vec<MakerRebates> = get_all_maker_rebates().into_iter()
+        .filter(|rebates| rebates.epoch_volume_e6 > 0)
+        .collect();
+    epoch.total_volume_e6 = makers.iter().map(|rebates| rebates.epoch_volume_e6).sum();
+
+    // An epoch without fills carries its whole pool over
+    if epoch.total_volume_e6 > 0 {
+        for mut rebates in makers {
+            let share_e6 = (epoch.pool_e6 as u128 * rebates.epoch_volume_e6 as u128 / epoch.total_volume_e6 as u128) as UsdE6;
+            rebates.owed_e6 += share_e6;
+            rebates.epoch_volume_e6 = 0;
+            set_maker_rebates(rebates);
+            epoch.distributed_e6 += share_e6;
+            epoch.makers_rebated += 1;
+        }
+    }
+
+    epoch.closed_at = Some(now);
+    insert_rebate_epoch(epoch.clone());
+    insert_rebate_epoch(new_epoch(epoch.epoch + 1, now, epoch.pool_e6 - epoch.distributed_e6));
+
+    Some(epoch)
+}
+
+/// The caller's rebate volume and balances (zeroes if they never had a fill)
+pub fn get_my_rebates() -> MakerRebates {
+    let caller = get_caller();
+    get_maker_rebates(caller).unwrap_or_else(|| empty_rebates(caller))
+}
+
+/// Pay the caller's owed rebates to their default ckUSDC account, less the ledger fee
+/// A claim whose outcome was lost is resent unchanged before anything newly owed is paid
+/// Returns the amount received
+pub async fn claim_rebates() -> Result<UsdE6, EasySwapError> {
+    let caller = get_caller();
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot claim rebates. Please authenticate first."));
+    }
+
+    let mut rebates = get_maker_rebates(caller).unwrap_or_else(|| empty_rebates(caller));
+    let claim = match rebates.pending_claim.clone() {
+        Some(claim) => claim,
+        None => {
+            if rebates.owed_e6 <= CKUSDC_TRANSFER_FEE as UsdE6 {
+                return Err(EasySwapError::insufficient_funds(format!(
+                    "Rebates owed (${:.6}) don't cover the ${:.2} ledger fee",
+                    e6_to_usd(rebates.owed_e6), e6_to_usd(CKUSDC_TRANSFER_FEE as UsdE6)
+                )));
+            }
+            // Numbered within the open epoch, so every claim's memo is unique
+            let mut epoch = open_epoch();
+            epoch.claims += 1;
+            let claim = PendingRebateClaim {
+                memo: format!("Rebate E{} #{}", epoch.epoch, epoch.claims),
+                amount_e6: rebates.owed_e6,
+            };
+            insert_rebate_epoch(epoch);
+            rebates.owed_e6 = 0;
+            rebates.pending_claim = Some(claim.clone());
+            set_maker_rebates(rebates.clone());
+            claim
+        }
+    };
+
+    let memo = claim.memo.clone().into_bytes();
+    let transfer_args = TransferArg {
+        from_subaccount: None,
+        to: Account { owner: caller, subaccount: None },
+        fee: None,
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+        memo: Some(Memo::from(memo.clone())),
+        amount: Nat::from(claim.amount_e6 - CKUSDC_TRANSFER_FEE as UsdE6),
+    };
+    let ledger_id = Principal::from_text(CK_USDC_LEDGER)
+        .map_err(|e| EasySwapError::ledger(format!("Invalid ledger canister ID: {:?}", e)))?;
+
+    let result = crate::runtime::ledger().icrc1_transfer(ledger_id, transfer_args).await;
+    if result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
+
+    // Re-read - an epoch may have closed and added to owed_e6 during the call
+    let mut rebates = get_maker_rebates(caller).unwrap_or_else(|| empty_rebates(caller));
+    match result {
+        Ok((Ok(_),)) | Ok((Err(TransferError::Duplicate { .. }),)) => {
+            rebates.claimed_e6 += claim.amount_e6;
+            rebates.pending_claim = None;
+            rebates.last_claimed_at = Some(get_time());
+            set_maker_rebates(rebates);
+            ic_cdk::println!("🎁 Paid ${:.6} of rebates to {}", e6_to_usd(claim.amount_e6), caller);
+            Ok(claim.amount_e6 - CKUSDC_TRANSFER_FEE as UsdE6)
+        }
+        // Rejected - nothing was paid, so the amount is owed again
+        Ok((Err(e),)) => {
+            rebates.owed_e6 += claim.amount_e6;
+            rebates.pending_claim = None;
+            set_maker_rebates(rebates);
+            Err(EasySwapError::ledger(format!("Rebate transfer failed: {:?}", e)))
+        }
+        // Outcome unknown - the pending claim stays for the next attempt
+        Err((code, msg)) => Err(EasySwapError::ledger(format!(
+            "Rebate transfer call failed: {:?}: {}. Claim again to retry it.",
+            code, msg
+        ))),
+    }
+}
@@ -0,0 +1,134 @@
+/// Order balance reconciliation - compares each open order's subaccount balance on the
+/// ckUSDC ledger with what its chunks are still owed, so refund/claim accounting bugs
+/// show up before a filler's claim fails
+use std::cell::RefCell;
+use crate::types::*;
+use crate::state::*;
+use crate::ckusdc_integration;
+use crate::config::{MAX_RECONCILIATION_ORDERS_PER_RUN, RECONCILIATION_TOLERANCE_E6};
+
+thread_local! {
+    // Next order ID to check - runs walk the open orders in batches
+    static NEXT_ORDER_TO_CHECK: RefCell<OrderId> = RefCell::new(0);
+}
+
+/// Balance an order's subaccount must hold: every chunk not yet filled or refunded,
+/// plus the filler incentive paid out with it
+fn expected_order_balance(order: &Order) -> UsdE6 {
+    order.chunks.iter()
+        .filter_map(|chunk_id| get_chunk(*chunk_id))
+        .filter(|chunk| matches!(chunk.status, ChunkStatus::Available | ChunkStatus::Idle | ChunkStatus::Pending | ChunkStatus::Locked))
+        .map(|chunk| chunk.amount_usd_e6 + bps_of(chunk.amount_usd_e6, order.filler_incentive_bps()))
+        .sum()
+}
+
+pub async fn run_reconciliation() {
+    let start_id = NEXT_ORDER_TO_CHECK.with(|n| *n.borrow());
+
+    let mut open_orders: Vec<Order> = get_all_orders().into_iter()
+        .filter(|o| matches!(o.status, OrderStatus::Active | OrderStatus::Idle | OrderStatus::PartiallyFilled))
+        .collect();
+    open_orders.sort_by_key(|o| o.id);
+
+    // Continue after the last checked order, wrapping around to the start
+    let batch: Vec<Order> = open_orders.iter()
+        .filter(|o| o.id >= start_id)
+        .chain(open_orders.iter().filter(|o| o.id < start_id))
+        .take(MAX_RECONCILIATION_ORDERS_PER_RUN)
+        .cloned()
+        .collect();
+
+    let next_id = batch.last().map_or(0, |o| o.id + 1);
+    NEXT_ORDER_TO_CHECK.with(|n| *n.borrow_mut() = next_id);
+
+    let mut issues = 0;
+    for order in batch {
+        let ledger_balance_e6 = match ckusdc_integration::get_order_ckusdc_balance(order.maker, order.id).await {
+            Ok(balance) => balance as UsdE6,
+            Err(e) => {
+                ic_cdk::println!("⚠️  Reconciliation skipped order {}: {}", order.id, e);
+                continue;
+            }
+        };
+
+        // Read chunk statuses after the ledger call so a claim finishing meanwhile is reflected
+        let order = match get_order(order.id) {
+            Some(o) => o,
+            None => continue,
+        };
+        let expected_balance_e6 = expected_order_balance(&order);
+        let shortfall_e6 = expected_balance_e6.saturating_sub(ledger_balance_e6);
+
+        if shortfall_e6 <= RECONCILIATION_TOLERANCE_E6 {
+            if remove_reconciliation_issue(order.id).is_some() {
+                ic_cdk::println!("✅ Reconciliation issue for order {} resolved", order.id);
+            }
+            continue;
+        }
+
+        issues += 1;
+        let now = get_time();
+        let issue = match get_reconciliation_issue(order.id) {
+            Some(previous) => ReconciliationIssue {
+                expected_balance_e6,
+                ledger_balance_e6,
+                shortfall_e6,
+                last_detected_at: now,
+                occurrences: previous.occurrences + 1,
+                ..previous
+            },
+            None => ReconciliationIssue {
+                order_id: order.id,
+                maker: order.maker,
+                expected_balance_e6,
+                ledger_balance_e6,
+                shortfall_e6,
+                first_detected_at: now,
+                last_detected_at: now,
+                occurrences: 1,
+            },
+        };
+
+        ic_cdk::println!(
+            "❌ Order {} balance ${:.6} is ${:.6} short of expected ${:.6} (seen {} times)",
+            order.id, e6_to_usd(ledger_balance_e6), e6_to_usd(shortfall_e6),
+            e6_to_usd(expected_balance_e6), issue.occurrences
+        );
+        insert_reconciliation_issue(issue);
+    }
+
+    ic_cdk::println!("🔎 Reconciliation run from order {}: {} discrepancies", start_id, issues);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use candid::Principal;
+
+    #[test]
+    fn short_order_subaccount_is_flagged_until_it_is_made_whole() {
+        let maker = Principal::from_slice(&[1, 1, 1]);
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker), 10_000_000);
+        mock::set_caller(maker);
+        let order_id = block_on(crate::order_management::create_order(6.0, 100.0, crate::lifecycle_tests::p2pkh_address(&[0x11; 20]), None)).unwrap();
+        let expected_e6 = expected_order_balance(&get_order(order_id).unwrap());
+
+        block_on(run_reconciliation());
+        assert!(get_reconciliation_issue(order_id).is_none());
+
+        // $1 leaves the subaccount behind the canister's back
+        block_on(ckusdc_integration::transfer_ckusdc_from_order(maker, order_id, maker, None, 1_000_000, None)).unwrap();
+        block_on(run_reconciliation());
+        block_on(run_reconciliation());
+        let issue = get_reconciliation_issue(order_id).unwrap();
+        assert_eq!((issue.expected_balance_e6, issue.shortfall_e6, issue.occurrences), (expected_e6, 1_000_000, 2));
+
+        // Within the tolerance counts as whole
+        let order_account = ckusdc_integration::get_order_deposit_account(maker, order_id);
+        mock::mint(order_account, (1_000_000 - RECONCILIATION_TOLERANCE_E6) as u128);
+        block_on(run_reconciliation());
+        assert!(get_reconciliation_issue(order_id).is_none());
+    }
+}
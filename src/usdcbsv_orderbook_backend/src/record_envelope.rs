@@ -0,0 +1,216 @@
+/// Versioned encoding of stable records - every value is written as
+/// [ENVELOPE_TAG, layout version] + candid payload, so a build can tell which layout it is
+/// reading and decode older ones instead of trapping on them
+/// Bare candid records written before the envelope are layout 0 (candid always starts "DIDL")
+/// Records no layout can decode are moved into CORRUPT_RECORDS by quarantine_corrupt_records
+/// from post_upgrade, before anything reads the maps, so Storable::from_bytes never sees them
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::memory_manager::{MemoryId, VirtualMemory};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, Storable};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::thread::LocalKey;
+use crate::types::*;
+use crate::state::*;
+use crate::block_headers::LEGACY_BLOCK_HEADERS;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+const ENVELOPE_TAG: u8 = 0xE5;
+
+pub trait VersionedRecord: CandidType + for<'de> Deserialize<'de> {
+    /// Layout written by this build - bump it (and decode the old one in decode_previous)
+    /// when a change to the type isn't candid-compatible
+    const LAYOUT_VERSION: u8 = 1;
+
+    /// Decode a payload written under an older layout (0 = bare candid, pre-envelope)
+    fn decode_previous(version: u8, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            0 => candid::decode_one(payload).map_err(|e| e.to_string()),
+            _ => Err(format!("Unknown layout version {}", version)),
+        }
+    }
+}
+
+pub fn encode_record<T: VersionedRecord>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![ENVELOPE_TAG, T::LAYOUT_VERSION];
+    bytes.extend(candid::encode_one(value).expect("candid encoding of a record cannot fail"));
+    bytes
+}
+
+pub fn decode_record<T: VersionedRecord>(bytes: &[u8]) -> Result<T, String> {
+    match bytes {
+        // Newer layouts are only read in rollback mode - best effort, since candid skips unknown fields
+        [ENVELOPE_TAG, version, payload @ ..] if *version >= T::LAYOUT_VERSION => {
+            candid::decode_one(payload).map_err(|e| format!("layout {}: {}", version, e))
+        }
+        [ENVELOPE_TAG, version, payload @ ..] => {
+            T::decode_previous(*version, payload).map_err(|e| format!("layout {}: {}", version, e))
+        }
+        _ => T::decode_previous(0, bytes).map_err(|e| format!("layout 0: {}", e)),
+    }
+}
+
+/// For Storable::from_bytes, which can't fail - stored records were checked by the
+/// quarantine sweep, so this only traps on a value written after it that doesn't decode
+pub fn decode_stored<T: VersionedRecord>(bytes: &[u8]) -> T {
+    decode_record(bytes).unwrap_or_else(|e| panic!("Stored {} failed to decode: {}", std::any::type_name::<T>(), e))
+}
+
+/// Value bytes as stored, so records can be read without the typed Storable decode
+pub struct RawRecord(pub Vec<u8>);
+
+impl Storable for RawRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        RawRecord(bytes.into_owned())
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// ===== QUARANTINE =====
+
+/// Move every record of one map that no longer decodes into CORRUPT_RECORDS
+/// Works on a raw view of the map's memory; the typed map caches the tree header, so it is
+/// reloaded afterwards
+fn quarantine_map<K, V>(
+    name: &str,
+    memory_id: u8,
+    typed_map: &'static LocalKey<RefCell<StableBTreeMap<K, V, Memory>>>,
+) -> u64
+where
+    K: Storable + Ord + Clone,
+    V: Storable + VersionedRecord,
+{
+    let memory = || MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(memory_id)));
+    let mut raw_map: StableBTreeMap<K, RawRecord, Memory> = StableBTreeMap::init(memory());
+
+    let corrupt: Vec<(K, Vec<u8>, String)> = raw_map.iter()
+        .filter_map(|(key, value)| decode_record::<V>(&value.0).err().map(|e| (key, value.0, e)))
+        .collect();
+    if corrupt.is_empty() {
+        return 0;
+    }
+
+    let now = get_time();
+    let count = corrupt.len() as u64;
+    for (key, value, error) in corrupt {
+        raw_map.remove(&key);
+        ic_cdk::println!("🧯 Quarantined undecodable {} record: {}", name, error);
+        insert_corrupt_record(|id| CorruptRecord {
+            id,
+            map_name: name.to_string(),
+            memory_id,
+            key: key.to_bytes().into_owned(),
+            value,
+            error,
+            quarantined_at: now,
+        });
+    }
+
+    typed_map.with(|m| *m.borrow_mut() = StableBTreeMap::init(memory()));
+    count
+}
+
+/// Called first thing in post_upgrade - returns how many records were quarantined
+pub fn quarantine_corrupt_records() -> u64 {
+    let quarantined = quarantine_map("orders", 0, &ORDERS)
+        + quarantine_map("chunks", 1, &CHUNKS)
+        + quarantine_map("trades", 2, &TRADES)
+        + quarantine_map("filler_accounts", 3, &FILLER_ACCOUNTS)
+        + quarantine_map("admin_events", 6, &ADMIN_EVENTS)
+        + quarantine_map("block_headers", 7, &LEGACY_BLOCK_HEADERS)
+        + quarantine_map("deposit_recoveries", 8, &DEPOSIT_RECOVERIES)
+        + quarantine_map("disputes", 9, &DISPUTES)
+        + quarantine_map("maker_quiet_hours", 10, &MAKER_QUIET_HOURS)
+        + quarantine_map("settlement_reports", 11, &SETTLEMENT_REPORTS)
+        + quarantine_map("market_stats", 13, &MARKET_STATS)
+        + quarantine_map("price_candles", 14, &PRICE_CANDLES)
+        + quarantine_map("reconciliation_issues", 15, &RECONCILIATION_ISSUES)
+        + quarantine_map("eth_withdrawals", 16, &ETH_WITHDRAWALS)
+        + quarantine_map("withdrawal_routes", 17, &WITHDRAWAL_ROUTES)
+        + quarantine_map("quotes", 18, &QUOTES)
+        + quarantine_map("scheduled_tasks", 19, &SCHEDULED_TASKS)
+        + quarantine_map("pending_transfers", 20, &PENDING_TRANSFERS)
+        + quarantine_map("maker_filler_blocklists", 23, &MAKER_FILLER_BLOCKLISTS)
+        + quarantine_map("orderbook_changes", 24, &ORDERBOOK_CHANGES)
+        + quarantine_map("tx_blobs", 26, &TX_BLOBS)
+        + quarantine_map("delegations", 27, &DELEGATIONS)
+        + quarantine_map("release_bonds", 28, &RELEASE_BONDS)
+        + quarantine_map("verified_inclusions", 30, &VERIFIED_INCLUSIONS)
+        + quarantine_map("maker_auto_top_ups", 31, &MAKER_AUTO_TOP_UPS)
+        + quarantine_map("rebate_epochs", 32, &REBATE_EPOCHS)
+        + quarantine_map("maker_rebates", 33, &MAKER_REBATES)
+        + quarantine_map("books", 34, &BOOKS)
+        + quarantine_map("penalty_appeals", 37, &PENALTY_APPEALS)
+        + quarantine_map("late_claims", 38, &LATE_CLAIMS);
+
+    if quarantined > 0 {
+        ic_cdk::println!("⚠️  {} undecodable records moved to corrupt_records - see admin_get_corrupt_records", quarantined);
+    }
+    quarantined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use candid::Principal;
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn create_order() -> OrderId {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        let address = crate::lifecycle_tests::p2pkh_address(&[0x11; 20]);
+        block_on(crate::order_management::create_order(6.0, 100.0, address, None)).unwrap()
+    }
+
+
+    #[test]
+    fn legacy_and_enveloped_records_decode_and_garbage_is_quarantined() {
+        let order_id = create_order();
+        let order = get_order(order_id).unwrap();
+
+        // Bare candid from before the envelope, the current envelope, and bytes neither can read
+        let legacy = candid::encode_one(&order).unwrap();
+        let enveloped = encode_record(&order);
+        let garbage = vec![0xE5, 0x01, 0xDE, 0xAD, 0xBE, 0xEF];
+        assert_eq!(decode_record::<Order>(&legacy).unwrap().id, order_id);
+        assert_eq!(decode_record::<Order>(&enveloped).unwrap().id, order_id);
+        assert!(decode_record::<Order>(&garbage).is_err());
+        assert!(decode_record::<Order>(b"not candid").is_err());
+
+        // Write a legacy record and the garbage straight into the orders memory, as an old build would
+        let legacy_id = order_id + 100;
+        let mut legacy_order = order.clone();
+        legacy_order.id = legacy_id;
+        let mut raw_orders: StableBTreeMap<OrderId, RawRecord, _> =
+            StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0))));
+        raw_orders.insert(legacy_id, RawRecord(candid::encode_one(&legacy_order).unwrap()));
+        raw_orders.insert(order_id + 200, RawRecord(garbage.clone()));
+        ORDERS.with(|m| *m.borrow_mut() = StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0)))));
+
+        assert_eq!(quarantine_corrupt_records(), 1);
+        let (corrupt, _) = get_corrupt_records_page(None, 10);
+        assert_eq!(corrupt.len(), 1);
+        assert_eq!((corrupt[0].map_name.as_str(), corrupt[0].memory_id), ("orders", 0));
+        assert_eq!(corrupt[0].value, garbage);
+
+        assert_eq!(get_order(order_id).unwrap().id, order_id);
+        assert_eq!(get_order(legacy_id).unwrap().id, legacy_id);
+        assert!(get_order(order_id + 200).is_none());
+        assert_eq!(ORDERS.with(|m| m.borrow().len()), 2);
+
+        // Nothing left to quarantine on the next upgrade
+        assert_eq!(quarantine_corrupt_records(), 0);
+    }
+}
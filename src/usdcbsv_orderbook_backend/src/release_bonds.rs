@@ -0,0 +1,166 @@
+/// Bonded instant release - a filler who opts in may claim trades under
+/// INSTANT_RELEASE_MAX_TRADE_USD_E6 after INSTANT_RELEASE_CONFIRMATIONS instead of the trade's full
+/// depth, as long as their free security deposit covers the released amount
+/// That amount stays bonded in their deposit subaccount until the payment's block is
+/// CONFIRMATION_DEPTH deep; if a reorg replaces the block first and the payment isn't proven on
+/// the new branch within RELEASE_BOND_REPROOF_WINDOW_NS, the bond goes to the maker
+use candid::Principal;
+use crate::types::*;
+use crate::state::*;
+use crate::block_headers::{get_block_by_height, get_highest_block, CONFIRMATION_DEPTH};
+use crate::config::{INSTANT_RELEASE_MAX_TRADE_USD_E6, RELEASE_BOND_REPROOF_WINDOW_NS};
+
+/// Opt the caller in or out of instant release - bonds already posted are unaffected
+pub fn set_instant_release(enabled: bool) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot opt in to instant release. Please authenticate first."));
+    }
+
+    crate::filler_accounts::create_account_if_needed(caller);
+    update_filler_account(caller, |account| {
+        account.instant_release = Some(enabled);
+    })?;
+    Ok(())
+}
+
+/// Whether a claim of this trade may be verified at INSTANT_RELEASE_CONFIRMATIONS
+pub fn is_eligible(trade: &Trade) -> bool {
+    trade.amount_usd_e6 < INSTANT_RELEASE_MAX_TRADE_USD_E6
+        && get_filler_account(trade.filler).is_some_and(|account| account.instant_release == Some(true))
+}
+
+/// Security deposit the filler currently has bonded
+pub fn bonded_e6(filler: Principal) -> UsdE6 {
+    get_release_bonds_by_filler(filler).iter().map(|bond| bond.amount_usd_e6).sum()
+}
+
+/// Bond `amount_usd_e6` of the filler's security against an early release of `trade`
+/// Their security less what open trades lock and what is already bonded must cover it
+pub async fn post_bond(
+    trade: &Trade,
+    maker: Principal,
+    amount_usd_e6: UsdE6,
+    block_height: u64,
+    block_hash: String,
+) -> Result<(), EasySwapError> {
+    let security_e6 = crate::filler_accounts::get_security_value_e6(trade.filler).await.map_err(EasySwapError::ledger)?;
+
+    // Read after the ledger call, so concurrent claims see each other's bonds
+    let (security_deposit_percent, _) = crate::filler_accounts::security_terms(trade.filler);
+    let locked_e6 = calculate_pending_trades_for_filler(trade.filler) * security_deposit_percent / 100;
+    let free_e6 = security_e6.saturating_sub(locked_e6 + bonded_e6(trade.filler));
+    if free_e6 < amount_usd_e6 {
+        return Err(EasySwapError::insufficient_funds(format!(
+            "Instant release needs ${:.6} of free security to bond, ${:.6} available. Wait for {} confirmations or deposit more.",
+            e6_to_usd(amount_usd_e6), e6_to_usd(free_e6), trade.required_confirmations.unwrap_or(CONFIRMATION_DEPTH)
+        )));
+    }
+
+    insert_release_bond(ReleaseBond {
+        trade_id: trade.id,
+        filler: trade.filler,
+        maker,
+        amount_usd_e6,
+        block_height,
+        block_hash,
+        posted_at: get_time(),
+        reorged_at: None,
+    });
+    ic_cdk::println!("⚡ Bonded ${:.6} of {}'s security for instant release of trade {}", e6_to_usd(amount_usd_e6), trade.filler, trade.id);
+    Ok(())
+}
+
+/// The caller's outstanding bonds
+pub fn get_my_release_bonds() -> Vec<ReleaseBond> {
+    get_release_bonds_by_filler(get_caller())
+}
+
+/// Filler proves a bonded payment in the branch that replaced its block - the bond then waits
+/// for CONFIRMATION_DEPTH on the new block
+pub async fn reprove_release_bond(caller: Principal, trade_id: TradeId, bump_hex: String) -> Result<(), EasySwapError> {
+    let bond = get_release_bond(trade_id)
+        .filter(|bond| bond.filler == caller)
+        .ok_or_else(|| EasySwapError::not_found("No release bond for this trade"))?;
+    let txid = get_trade(trade_id)
+        .and_then(|trade| trade.bsv_txid)
+        .ok_or_else(|| EasySwapError::not_found("Trade has no BSV transaction"))?;
+
+    let verification = crate::bump_verification::verify_tx_bump_async(&txid, &bump_hex, 1).await
+        .map_err(EasySwapError::invalid_input)?;
+    move_bond(bond, verification.block_height, verification.block_hash);
+    Ok(())
+}
+
+fn move_bond(mut bond: ReleaseBond, block_height: u64, block_hash: String) {
+    ic_cdk::println!("⚡ Payment for trade {} proven again in block {} - bond kept", bond.trade_id, block_height);
+    bond.block_height = block_height;
+    bond.block_hash = block_hash;
+    bond.reorged_at = None;
+    insert_release_bond(bond);
+}
+
+/// Return bonds whose block is CONFIRMATION_DEPTH deep and forfeit those whose block a reorg
+/// replaced, unless the payment is proven on the new branch within RELEASE_BOND_REPROOF_WINDOW_NS
+/// Returns how many were settled
+pub async fn settle_release_bonds() -> usize {
+    let highest = get_highest_block();
+    let now = get_time();
+    let mut settled = 0;
+
+    for mut bond in get_release_bonds() {
+        let replaced = get_block_by_height(bond.block_height).is_some_and(|block| block.hash != bond.block_hash);
+        if replaced || bond.reorged_at.is_some() {
+            // Any claim or reproof since may already have proven the payment on the new branch
+            let reproven = get_trade(bond.trade_id)
+                .and_then(|trade| trade.bsv_txid)
+                .and_then(|txid| crate::bump_verification::verify_tx_cached(&txid, 1));
+            match (reproven, bond.reorged_at) {
+                (Some(verification), _) => move_bond(bond, verification.block_height, verification.block_hash),
+                (None, None) => {
+                    ic_cdk::println!("⚠️  Block {} holding trade {}'s payment was replaced - awaiting a proof on the new branch", bond.block_height, bond.trade_id);
+                    bond.reorged_at = Some(now);
+                    insert_release_bond(bond);
+                }
+                (None, Some(reorged_at)) if now >= reorged_at + RELEASE_BOND_REPROOF_WINDOW_NS => {
+                    forfeit(&bond).await;
+                    remove_release_bond(bond.trade_id);
+                    settled += 1;
+                }
+                (None, Some(_)) => {}
+            }
+            continue;
+        }
+
+        // Once deep enough the block may also have been pruned
+        if highest >= bond.block_height + CONFIRMATION_DEPTH - 1 {
+            ic_cdk::println!("⚡ Release bond for trade {} returned to {}", bond.trade_id, bond.filler);
+            remove_release_bond(bond.trade_id);
+            settled += 1;
+        }
+    }
+
+    settled
+}
+
+async fn forfeit(bond: &ReleaseBond) {
+    let penalty_amount = e6_to_usd(bond.amount_usd_e6);
+    let memo = format!("Reorg bond T{}", bond.trade_id);
+    ic_cdk::println!("⚠️  Trade {}'s payment wasn't proven after block {} was replaced - forfeiting bond to maker", bond.trade_id, bond.block_height);
+
+    if let Err(e) = crate::filler_accounts::deduct_penalty(bond.filler, penalty_amount, Some(bond.maker), Some(memo.clone())).await {
+        ic_cdk::println!("❌ Failed to transfer forfeited bond: {}", e);
+        crate::pending_transfers::queue_penalty(bond.filler, Some(bond.maker), penalty_amount, memo, e);
+    }
+
+    let trade = get_trade(bond.trade_id);
+    create_admin_event(AdminEventType::PenaltyApplied {
+        trade_id: bond.trade_id,
+        order_id: trade.as_ref().map(|t| t.order_id),
+        filler: bond.filler,
+        order_maker: Some(bond.maker),
+        penalty_amount,
+        bsv_txid: trade.and_then(|t| t.bsv_txid),
+        reason: format!("Instant release bond forfeited - block {} was reorganised out", bond.block_height),
+    });
+}
@@ -0,0 +1,572 @@
+/// Seam between the canister logic and everything outside it - system API (caller, time,
+/// canister id, cycles), the ckUSDC and collateral ledgers, the price feeds, the block header providers
+/// and the ckERC20 minter
+/// Production builds talk to the IC directly. Under cfg(test) the same entry points are served
+/// by the in-memory mocks in runtime::mock, so order_management, trade_lifecycle and chain_sync
+/// run their full flows as plain unit tests
+/// Built with the local-dev feature, the canister serves the ledgers, price feeds and header
+/// providers from those mocks too (seeded via the admin_dev_* endpoints), so a dfx-local
+/// replica runs order -> trade -> claim without ckUSDC or any HTTPS outcall
+use crate::block_headers::BlockHeader;
+use crate::block_sync::{BlockInfo, ConsensusResult};
+use crate::types::{Alert, AlertSink, PriceSource};
+use crate::withdrawal_treasury::{RetrieveEthStatus, WithdrawalDetail, WithdrawalSearchParameter};
+use candid::{Nat, Principal};
+use ic_cdk::api::call::CallResult;
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
+use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
+
+// ===== SYSTEM API =====
+
+#[cfg(not(test))]
+pub fn caller() -> Principal {
+    ic_cdk::caller()
+}
+
+#[cfg(not(test))]
+pub fn time() -> u64 {
+    ic_cdk::api::time()
+}
+
+#[cfg(not(test))]
+pub fn canister_id() -> Principal {
+    ic_cdk::api::id()
+}
+
+#[cfg(not(test))]
+pub fn cycles_balance() -> u128 {
+    ic_cdk::api::canister_balance128()
+}
+
+#[cfg(test)]
+pub use mock::{caller, time, canister_id, cycles_balance};
+
+// ===== EXTERNAL SERVICES =====
+
+/// ICRC-1 calls made against the ckUSDC ledger and the collateral token ledgers, plus the
+/// ICRC-2 pull used to fund orders from a maker's allowance
+pub trait LedgerClient {
+    async fn icrc1_transfer(&self, ledger: Principal, arg: TransferArg) -> CallResult<(Result<Nat, TransferError>,)>;
+    async fn icrc1_balance_of(&self, ledger: Principal, account: Account) -> CallResult<(Nat,)>;
+    async fn icrc2_transfer_from(&self, ledger: Principal, arg: TransferFromArgs) -> CallResult<(Result<Nat, TransferFromError>,)>;
+}
+
+/// One round of BSV/USD quotes, one result per feed - aggregation stays in price_oracle
+pub trait Oracle {
+    async fn fetch_bsv_prices(&self) -> Vec<(PriceSource, Result<f64, String>)>;
+    /// USD rate of a collateral asset, by its XRC symbol
+    async fn fetch_usd_rate(&self, symbol: &str) -> Result<f64, String>;
+}
+
+/// Block headers and raw transactions from WoC, Bitails and TxArchive
+pub trait HeaderProvider {
+    async fn consensus_tip(&self) -> Result<ConsensusResult, String>;
+    async fn woc_tip(&self) -> Result<BlockInfo, String>;
+    async fn bitails_tip(&self) -> Result<BlockInfo, String>;
+    async fn bitails_block_header(&self, height: u64) -> Result<BlockHeader, String>;
+    /// Newest first, skip counted from the tip
+    async fn bitails_blocks_batch(&self, skip: u64, count: u64) -> Result<Vec<BlockHeader>, String>;
+    async fn txarchive_blocks(&self, network_tip_height: u64, local_tip: Option<(u64, String)>) -> Result<Vec<BlockHeader>, String>;
+    /// Single block for SPV verification when it isn't stored locally
+    async fn txarchive_block(&self, height: u64) -> Result<BlockHeader, String>;
+    async fn raw_tx(&self, txid: &str) -> Result<String, String>;
+}
+
+/// Pushes operator alerts to the admin's sink
+pub trait AlertSender {
+    async fn send_alert(&self, sink: &AlertSink, alert: &Alert) -> Result<(), String>;
+}
+
+/// ckERC20 minter calls behind Ethereum withdrawals
+pub trait Minter {
+    /// Progress of a withdrawal, by the ckETH burn block index the minter returned for it
+    async fn retrieve_eth_status(&self, minter: Principal, block_index: u64) -> CallResult<(RetrieveEthStatus,)>;
+    /// Withdrawals the minter knows about, e.g. every one sent from an account
+    async fn withdrawal_status(&self, minter: Principal, search: WithdrawalSearchParameter) -> CallResult<(Vec<WithdrawalDetail>,)>;
+}
+
+#[cfg_attr(any(test, feature = "local-dev"), allow(dead_code))]
+pub struct IcLedger;
+
+impl LedgerClient for IcLedger {
+    async fn icrc1_transfer(&self, ledger: Principal, arg: TransferArg) -> CallResult<(Result<Nat, TransferError>,)> {
+        ic_cdk::call(ledger, "icrc1_transfer", (arg,)).await
+    }
+
+    async fn icrc1_balance_of(&self, ledger: Principal, account: Account) -> CallResult<(Nat,)> {
+        ic_cdk::call(ledger, "icrc1_balance_of", (account,)).await
+    }
+
+    async fn icrc2_transfer_from(&self, ledger: Principal, arg: TransferFromArgs) -> CallResult<(Result<Nat, TransferFromError>,)> {
+        ic_cdk::call(ledger, "icrc2_transfer_from", (arg,)).await
+    }
+}
+
+#[cfg_attr(any(test, feature = "local-dev"), allow(dead_code))]
+pub struct IcOracle;
+
+impl Oracle for IcOracle {
+    async fn fetch_bsv_prices(&self) -> Vec<(PriceSource, Result<f64, String>)> {
+        vec![
+            (PriceSource::Xrc, crate::xrc_oracle::get_bsv_usd_rate().await),
+            (PriceSource::WhatsOnChain, crate::price_oracle::fetch_bsv_price_from_woc().await),
+            (PriceSource::Coinlore, crate::price_oracle::fetch_bsv_price_from_api().await),
+        ]
+    }
+
+    async fn fetch_usd_rate(&self, symbol: &str) -> Result<f64, String> {
+        crate::xrc_oracle::get_usd_rate(symbol).await
+    }
+}
+
+#[cfg_attr(any(test, feature = "local-dev"), allow(dead_code))]
+pub struct IcHeaders;
+
+impl HeaderProvider for IcHeaders {
+    async fn consensus_tip(&self) -> Result<ConsensusResult, String> {
+        crate::block_sync::find_consensus_tip().await
+    }
+
+    async fn woc_tip(&self) -> Result<BlockInfo, String> {
+        crate::block_sync::fetch_woc_tip().await
+    }
+
+    async fn bitails_tip(&self) -> Result<BlockInfo, String> {
+        crate::block_sync::fetch_bitails_tip().await
+    }
+
+    async fn bitails_block_header(&self, height: u64) -> Result<BlockHeader, String> {
+        crate::block_sync::fetch_bitails_block_header(height).await
+    }
+
+    async fn bitails_blocks_batch(&self, skip: u64, count: u64) -> Result<Vec<BlockHeader>, String> {
+        crate::block_sync::fetch_bitails_blocks_batch(skip, count).await
+    }
+
+    async fn txarchive_blocks(&self, network_tip_height: u64, local_tip: Option<(u64, String)>) -> Result<Vec<BlockHeader>, String> {
+        crate::block_sync::fetch_blocks_from_txarchive(network_tip_height, local_tip).await
+    }
+
+    async fn txarchive_block(&self, height: u64) -> Result<BlockHeader, String> {
+        crate::bump_verification::fetch_block_from_txarchive(height).await
+    }
+
+    async fn raw_tx(&self, txid: &str) -> Result<String, String> {
+        crate::block_sync::fetch_woc_raw_tx(txid).await
+    }
+}
+
+#[cfg_attr(any(test, feature = "local-dev"), allow(dead_code))]
+pub struct IcAlerts;
+
+impl AlertSender for IcAlerts {
+    async fn send_alert(&self, sink: &AlertSink, alert: &Alert) -> Result<(), String> {
+        match sink {
+            AlertSink::Canister { canister_id, method } => crate::alerts::call_canister(*canister_id, method, alert).await,
+            AlertSink::Webhook { url } => crate::alerts::post_webhook(url, alert).await,
+        }
+    }
+}
+
+#[cfg_attr(any(test, feature = "local-dev"), allow(dead_code))]
+pub struct IcMinter;
+
+impl Minter for IcMinter {
+    async fn retrieve_eth_status(&self, minter: Principal, block_index: u64) -> CallResult<(RetrieveEthStatus,)> {
+        ic_cdk::call(minter, "retrieve_eth_status", (block_index,)).await
+    }
+
+    async fn withdrawal_status(&self, minter: Principal, search: WithdrawalSearchParameter) -> CallResult<(Vec<WithdrawalDetail>,)> {
+        ic_cdk::call(minter, "withdrawal_status", (search,)).await
+    }
+}
+
+#[cfg(not(any(test, feature = "local-dev")))]
+pub fn ledger() -> impl LedgerClient {
+    IcLedger
+}
+
+#[cfg(not(any(test, feature = "local-dev")))]
+pub fn oracle() -> impl Oracle {
+    IcOracle
+}
+
+#[cfg(not(any(test, feature = "local-dev")))]
+pub fn headers() -> impl HeaderProvider {
+    IcHeaders
+}
+
+#[cfg(not(any(test, feature = "local-dev")))]
+pub fn alert_sender() -> impl AlertSender {
+    IcAlerts
+}
+
+#[cfg(not(any(test, feature = "local-dev")))]
+pub fn minter() -> impl Minter {
+    IcMinter
+}
+
+#[cfg(any(test, feature = "local-dev"))]
+pub fn ledger() -> impl LedgerClient {
+    mock::MockLedger
+}
+
+#[cfg(any(test, feature = "local-dev"))]
+pub fn oracle() -> impl Oracle {
+    mock::MockOracle
+}
+
+#[cfg(any(test, feature = "local-dev"))]
+pub fn headers() -> impl HeaderProvider {
+    mock::MockHeaders
+}
+
+#[cfg(any(test, feature = "local-dev"))]
+pub fn alert_sender() -> impl AlertSender {
+    mock::MockAlerts
+}
+
+#[cfg(any(test, feature = "local-dev"))]
+pub fn minter() -> impl Minter {
+    mock::MockMinter
+}
+
+/// In-memory stand-ins for the external services, per test thread - and for the system API
+/// under cfg(test) only. In a local-dev canister they live on the heap, so an upgrade clears them
+#[cfg(any(test, feature = "local-dev"))]
+pub mod mock {
+    use super::*;
+    use crate::config::CKUSDC_TRANSFER_FEE;
+    use num_traits::cast::ToPrimitive;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    pub struct MockEnv {
+        #[cfg(test)]
+        pub caller: Principal,
+        #[cfg(test)]
+        pub time: u64,
+        #[cfg(test)]
+        pub canister_id: Principal,
+        #[cfg(test)]
+        pub cycles: u128,
+        pub balances: BTreeMap<Account, u128>,
+        pub next_block_index: u64,
+        pub transfers: Vec<TransferArg>,
+        pub allowances: BTreeMap<(Account, Principal), u128>,      // ckUSDC, (owner, spender)
+        pub prices: Vec<(PriceSource, Result<f64, String>)>,
+        pub token_balances: BTreeMap<(Principal, Account), u128>,   // Ledgers other than ckUSDC
+        pub usd_rates: BTreeMap<String, f64>,
+        pub chain: BTreeMap<u64, BlockHeader>,
+        pub raw_txs: BTreeMap<String, String>,
+        pub sent_alerts: Vec<(AlertSink, Alert)>,
+        pub alert_sink_down: bool,
+        pub ledger_down: bool,
+        pub minter_statuses: BTreeMap<u64, RetrieveEthStatus>,    // By ckETH burn block index
+        pub minter_withdrawals: Vec<WithdrawalDetail>,
+    }
+
+    thread_local! {
+        static ENV: RefCell<MockEnv> = RefCell::new(MockEnv {
+            #[cfg(test)]
+            caller: Principal::anonymous(),
+            #[cfg(test)]
+            time: 1_700_000_000_000_000_000,
+            #[cfg(test)]
+            canister_id: Principal::from_slice(&[0, 0, 0, 0, 0, 0, 0, 42, 1, 1]),
+            #[cfg(test)]
+            cycles: 10_000_000_000_000,
+            balances: BTreeMap::new(),
+            next_block_index: 0,
+            transfers: Vec::new(),
+            allowances: BTreeMap::new(),
+            prices: Vec::new(),
+            token_balances: BTreeMap::new(),
+            usd_rates: BTreeMap::new(),
+            chain: BTreeMap::new(),
+            raw_txs: BTreeMap::new(),
+            sent_alerts: Vec::new(),
+            alert_sink_down: false,
+            ledger_down: false,
+            minter_statuses: BTreeMap::new(),
+            minter_withdrawals: Vec::new(),
+        });
+    }
+
+    pub fn with_env<R>(f: impl FnOnce(&mut MockEnv) -> R) -> R {
+        ENV.with(|env| f(&mut env.borrow_mut()))
+    }
+
+    #[cfg(test)]
+    pub fn caller() -> Principal {
+        with_env(|env| env.caller)
+    }
+
+    #[cfg(test)]
+    pub fn time() -> u64 {
+        with_env(|env| env.time)
+    }
+
+    #[cfg(test)]
+    pub fn canister_id() -> Principal {
+        with_env(|env| env.canister_id)
+    }
+
+    #[cfg(test)]
+    pub fn cycles_balance() -> u128 {
+        with_env(|env| env.cycles)
+    }
+
+    #[cfg(test)]
+    pub fn set_caller(caller: Principal) {
+        with_env(|env| env.caller = caller);
+    }
+
+    #[cfg(test)]
+    pub fn advance_time(ns: u64) {
+        with_env(|env| env.time += ns);
+    }
+
+    #[cfg(test)]
+    pub fn set_cycles(cycles: u128) {
+        with_env(|env| env.cycles = cycles);
+    }
+
+    pub fn set_prices(prices: &[f64]) {
+        let sources = [PriceSource::Xrc, PriceSource::WhatsOnChain, PriceSource::Coinlore];
+        with_env(|env| {
+            env.prices = sources.into_iter().zip(prices.iter().map(|p| Ok(*p))).collect();
+        });
+    }
+
+    pub fn mint(account: Account, amount: u128) {
+        with_env(|env| *env.balances.entry(account).or_insert(0) += amount);
+    }
+
+    pub fn balance(account: Account) -> u128 {
+        with_env(|env| env.balances.get(&account).copied().unwrap_or(0))
+    }
+
+    /// ICRC-2 approval of ckUSDC from `owner` to `spender`, replacing any earlier one
+    pub fn approve(owner: Account, spender: Principal, amount: u128) {
+        with_env(|env| env.allowances.insert((owner, spender), amount));
+    }
+
+    pub fn allowance(owner: Account, spender: Principal) -> u128 {
+        with_env(|env| env.allowances.get(&(owner, spender)).copied().unwrap_or(0))
+    }
+
+    /// Credit a balance on a ledger other than ckUSDC
+    pub fn mint_token(ledger: Principal, account: Account, amount: u128) {
+        with_env(|env| *env.token_balances.entry((ledger, account)).or_insert(0) += amount);
+    }
+
+    pub fn token_balance(ledger: Principal, account: Account) -> u128 {
+        with_env(|env| env.token_balances.get(&(ledger, account)).copied().unwrap_or(0))
+    }
+
+    pub fn set_usd_rate(symbol: &str, rate: f64) {
+        with_env(|env| env.usd_rates.insert(symbol.to_string(), rate));
+    }
+
+    /// Replace the provider chain - WoC, Bitails and TxArchive all agree on it
+    pub fn set_chain(blocks: Vec<BlockHeader>) {
+        with_env(|env| env.chain = blocks.into_iter().map(|b| (b.height, b)).collect());
+    }
+
+    pub fn add_raw_tx(txid: &str, raw_hex: &str) {
+        with_env(|env| env.raw_txs.insert(txid.to_string(), raw_hex.to_string()));
+    }
+
+    /// Alerts delivered so far, oldest first
+    pub fn sent_alerts() -> Vec<(AlertSink, Alert)> {
+        with_env(|env| env.sent_alerts.clone())
+    }
+
+    /// While down, every alert delivery fails
+    pub fn set_alert_sink_down(down: bool) {
+        with_env(|env| env.alert_sink_down = down);
+    }
+
+    /// While down, every icrc1_transfer call is rejected before it reaches the ledger
+    pub fn set_ledger_down(down: bool) {
+        with_env(|env| env.ledger_down = down);
+    }
+
+    /// What the minter reports for the withdrawal burned at block_index - NotFound until set
+    pub fn set_minter_status(block_index: u64, status: RetrieveEthStatus) {
+        with_env(|env| env.minter_statuses.insert(block_index, status));
+    }
+
+    /// A withdrawal the minter lists in withdrawal_status
+    pub fn add_minter_withdrawal(detail: WithdrawalDetail) {
+        with_env(|env| env.minter_withdrawals.push(detail));
+    }
+
+    /// Mock services answer immediately, so every future completes on its first poll
+    #[cfg(test)]
+    pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        match future.as_mut().poll(&mut std::task::Context::from_waker(std::task::Waker::noop())) {
+            std::task::Poll::Ready(output) => output,
+            std::task::Poll::Pending => panic!("future waited on something the mocks don't provide"),
+        }
+    }
+
+    fn chain_tip() -> Result<BlockInfo, String> {
+        with_env(|env| env.chain.last_key_value().map(|(height, block)| BlockInfo {
+            height: *height,
+            hash: block.hash.clone(),
+        }))
+        .ok_or_else(|| "Mock chain is empty".to_string())
+    }
+
+    pub struct MockLedger;
+
+    fn is_ckusdc(ledger: Principal) -> bool {
+        ledger.to_text() == crate::ckusdc_integration::CKUSDC_LEDGER_CANISTER_ID
+    }
+
+    fn ledger_balance(ledger: Principal, account: Account) -> u128 {
+        if is_ckusdc(ledger) { balance(account) } else { token_balance(ledger, account) }
+    }
+
+    /// Debits amount + fee (CKUSDC_TRANSFER_FEE unless the arg names one) from the source
+    /// subaccount of this canister - ckUSDC and every other ledger keep separate balances
+    impl LedgerClient for MockLedger {
+        async fn icrc1_transfer(&self, ledger: Principal, arg: TransferArg) -> CallResult<(Result<Nat, TransferError>,)> {
+            if with_env(|env| env.ledger_down) {
+                return Err((ic_cdk::api::call::RejectionCode::SysTransient, "Mock ledger is down".to_string()));
+            }
+            let from = Account { owner: canister_id(), subaccount: arg.from_subaccount };
+            let amount = arg.amount.0.to_u128().expect("mock transfer amount fits u128");
+            let fee = arg.fee.as_ref().map_or(CKUSDC_TRANSFER_FEE, |fee| fee.0.to_u128().expect("mock fee fits u128"));
+            let available = ledger_balance(ledger, from);
+            if available < amount + fee {
+                return Ok((Err(TransferError::InsufficientFunds { balance: Nat::from(available) }),));
+            }
+            Ok((Ok(with_env(|env| {
+                if is_ckusdc(ledger) {
+                    *env.balances.get_mut(&from).expect("checked above") -= amount + fee;
+                    *env.balances.entry(arg.to).or_insert(0) += amount;
+                } else {
+                    *env.token_balances.get_mut(&(ledger, from)).expect("checked above") -= amount + fee;
+                    *env.token_balances.entry((ledger, arg.to)).or_insert(0) += amount;
+                }
+                env.transfers.push(arg);
+                env.next_block_index += 1;
+                Nat::from(env.next_block_index)
+            })),))
+        }
+
+        async fn icrc1_balance_of(&self, ledger: Principal, account: Account) -> CallResult<(Nat,)> {
+            Ok((Nat::from(ledger_balance(ledger, account)),))
+        }
+
+        /// ckUSDC only - amount + fee comes out of both the owner's balance and this canister's allowance
+        async fn icrc2_transfer_from(&self, ledger: Principal, arg: TransferFromArgs) -> CallResult<(Result<Nat, TransferFromError>,)> {
+            assert!(is_ckusdc(ledger), "mock transfer_from only supports ckUSDC");
+            let amount = arg.amount.0.to_u128().expect("mock transfer amount fits u128");
+            let fee = arg.fee.as_ref().map_or(CKUSDC_TRANSFER_FEE, |fee| fee.0.to_u128().expect("mock fee fits u128"));
+            let approved = allowance(arg.from, canister_id());
+            if approved < amount + fee {
+                return Ok((Err(TransferFromError::InsufficientAllowance { allowance: Nat::from(approved) }),));
+            }
+            let available = balance(arg.from);
+            if available < amount + fee {
+                return Ok((Err(TransferFromError::InsufficientFunds { balance: Nat::from(available) }),));
+            }
+            let spender = canister_id();
+            Ok((Ok(with_env(|env| {
+                *env.allowances.get_mut(&(arg.from, spender)).expect("checked above") -= amount + fee;
+                *env.balances.get_mut(&arg.from).expect("checked above") -= amount + fee;
+                *env.balances.entry(arg.to).or_insert(0) += amount;
+                env.next_block_index += 1;
+                Nat::from(env.next_block_index)
+            })),))
+        }
+    }
+
+    pub struct MockOracle;
+
+    impl Oracle for MockOracle {
+        async fn fetch_bsv_prices(&self) -> Vec<(PriceSource, Result<f64, String>)> {
+            with_env(|env| env.prices.clone())
+        }
+
+        async fn fetch_usd_rate(&self, symbol: &str) -> Result<f64, String> {
+            with_env(|env| env.usd_rates.get(symbol).copied())
+                .ok_or_else(|| format!("No mock USD rate for {}", symbol))
+        }
+    }
+
+    pub struct MockHeaders;
+
+    impl HeaderProvider for MockHeaders {
+        async fn consensus_tip(&self) -> Result<ConsensusResult, String> {
+            Ok(ConsensusResult { tip: chain_tip()?, use_txarchive_fallback: false })
+        }
+
+        async fn woc_tip(&self) -> Result<BlockInfo, String> {
+            chain_tip()
+        }
+
+        async fn bitails_tip(&self) -> Result<BlockInfo, String> {
+            chain_tip()
+        }
+
+        async fn bitails_block_header(&self, height: u64) -> Result<BlockHeader, String> {
+            self.txarchive_block(height).await
+        }
+
+        async fn bitails_blocks_batch(&self, skip: u64, count: u64) -> Result<Vec<BlockHeader>, String> {
+            Ok(with_env(|env| env.chain.values().rev().skip(skip as usize).take(count as usize).cloned().collect()))
+        }
+
+        async fn txarchive_blocks(&self, network_tip_height: u64, local_tip: Option<(u64, String)>) -> Result<Vec<BlockHeader>, String> {
+            let start = local_tip.map_or(0, |(height, _)| height + 1);
+            Ok(with_env(|env| env.chain.range(start..=network_tip_height).map(|(_, b)| b.clone()).collect()))
+        }
+
+        async fn txarchive_block(&self, height: u64) -> Result<BlockHeader, String> {
+            with_env(|env| env.chain.get(&height).cloned())
+                .ok_or_else(|| format!("Block {} not in mock chain", height))
+        }
+
+        async fn raw_tx(&self, txid: &str) -> Result<String, String> {
+            with_env(|env| env.raw_txs.get(txid).cloned())
+                .ok_or_else(|| format!("Transaction {} not in mock provider", txid))
+        }
+    }
+
+    pub struct MockAlerts;
+
+    impl AlertSender for MockAlerts {
+        async fn send_alert(&self, sink: &AlertSink, alert: &Alert) -> Result<(), String> {
+            with_env(|env| {
+                if env.alert_sink_down {
+                    return Err("Mock alert sink is down".to_string());
+                }
+                env.sent_alerts.push((sink.clone(), alert.clone()));
+                Ok(())
+            })
+        }
+    }
+
+    pub struct MockMinter;
+
+    impl Minter for MockMinter {
+        async fn retrieve_eth_status(&self, _minter: Principal, block_index: u64) -> CallResult<(RetrieveEthStatus,)> {
+            Ok((with_env(|env| env.minter_statuses.get(&block_index).cloned()).unwrap_or(RetrieveEthStatus::NotFound),))
+        }
+
+        async fn withdrawal_status(&self, _minter: Principal, search: WithdrawalSearchParameter) -> CallResult<(Vec<WithdrawalDetail>,)> {
+            let WithdrawalSearchParameter::BySenderAccount(sender) = search;
+            Ok((with_env(|env| env.minter_withdrawals.iter()
+                .filter(|w| w.from == sender.owner && w.from_subaccount == sender.subaccount)
+                .cloned()
+                .collect()),))
+        }
+    }
+}
@@ -0,0 +1,296 @@
+/// Background job scheduler - a single timer ticks every SCHEDULER_TICK_SECONDS and starts each
+/// enabled task whose next run is due
+/// Interval and enable flag are adjustable at runtime and persist across upgrades; a task that
+/// fails backs off exponentially until it succeeds again
+use crate::types::*;
+use crate::state::*;
+use crate::config::{
+    SCHEDULER_TICK_SECONDS, MAX_TASK_INTERVAL_SECONDS, TASK_MAX_BACKOFF_SECONDS,
+    CONFIRMATIONS_INTERVAL_SECONDS, IDLE_REACTIVATION_INTERVAL_SECONDS, CLEANUP_TASKS_INTERVAL_SECONDS,
+    BLOCK_SYNC_INTERVAL_SECONDS, CLEANUP_INTERVAL_SECONDS, SETTLEMENT_CHECK_INTERVAL_SECONDS,
+    RECONCILIATION_INTERVAL_SECONDS, CKETH_BALANCE_CHECK_INTERVAL_SECONDS, WITHDRAWAL_FOLLOWUP_INTERVAL_SECONDS,
+};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+const ALL_TASKS: [BackgroundTask; 9] = [
+    BackgroundTask::Confirmations,
+    BackgroundTask::IdleReactivation,
+    BackgroundTask::CleanupTasks,
+    BackgroundTask::BlockSync,
+    BackgroundTask::DataCleanup,
+    BackgroundTask::SettlementReport,
+    BackgroundTask::Reconciliation,
+    BackgroundTask::CkEthBalanceCheck,
+    BackgroundTask::WithdrawalFollowUp,
+];
+
+thread_local! {
+    // Tasks started but not yet finished - a due task is skipped while its previous run is still awaiting
+    static RUNNING_TASKS: RefCell<BTreeSet<BackgroundTask>> = RefCell::new(BTreeSet::new());
+}
+
+impl BackgroundTask {
+    fn name(&self) -> &'static str {
+        match self {
+            BackgroundTask::Confirmations => "confirmations",
+            BackgroundTask::IdleReactivation => "idle_reactivation",
+            BackgroundTask::CleanupTasks => "cleanup_tasks",
+            BackgroundTask::BlockSync => "block_sync",
+            BackgroundTask::DataCleanup => "data_cleanup",
+            BackgroundTask::SettlementReport => "settlement_report",
+            BackgroundTask::Reconciliation => "reconciliation",
+            BackgroundTask::CkEthBalanceCheck => "cketh_balance_check",
+            BackgroundTask::WithdrawalFollowUp => "withdrawal_follow_up",
+        }
+    }
+
+    fn default_interval_seconds(&self) -> u64 {
+        match self {
+            BackgroundTask::Confirmations => CONFIRMATIONS_INTERVAL_SECONDS,
+            BackgroundTask::IdleReactivation => IDLE_REACTIVATION_INTERVAL_SECONDS,
+            BackgroundTask::CleanupTasks => CLEANUP_TASKS_INTERVAL_SECONDS,
+            BackgroundTask::BlockSync => BLOCK_SYNC_INTERVAL_SECONDS,
+            BackgroundTask::DataCleanup => CLEANUP_INTERVAL_SECONDS,
+            BackgroundTask::SettlementReport => SETTLEMENT_CHECK_INTERVAL_SECONDS,
+            BackgroundTask::Reconciliation => RECONCILIATION_INTERVAL_SECONDS,
+            BackgroundTask::CkEthBalanceCheck => CKETH_BALANCE_CHECK_INTERVAL_SECONDS,
+            BackgroundTask::WithdrawalFollowUp => WITHDRAWAL_FOLLOWUP_INTERVAL_SECONDS,
+        }
+    }
+}
+
+/// Stored state of a task, created with defaults on first use (first run one interval from now)
+fn load_task(task: BackgroundTask, now: u64) -> ScheduledTask {
+    get_scheduled_task(task.name()).unwrap_or_else(|| {
+        let interval_seconds = task.default_interval_seconds();
+        ScheduledTask {
+            task,
+            interval_seconds,
+            enabled: true,
+            next_run_at: now + interval_seconds * 1_000_000_000,
+            last_started_at: None,
+            last_finished_at: None,
+            last_error: None,
+            consecutive_failures: 0,
+            total_runs: 0,
+        }
+    })
+}
+
+/// Register every task and start the scheduler timer
+/// Called by both init and post_upgrade
+pub fn start() {
+    let now = get_time();
+    for task in ALL_TASKS {
+        insert_scheduled_task(task.name(), load_task(task, now));
+    }
+    crate::http_certification::refresh();
+
+    ic_cdk_timers::set_timer_interval(std::time::Duration::from_secs(SCHEDULER_TICK_SECONDS), tick);
+}
+
+/// Start every enabled task that is due and not already running, then re-certify the
+/// http_request bodies if they changed
+fn tick() {
+    crate::http_certification::refresh();
+
+    let now = get_time();
+    for task in ALL_TASKS {
+        let state = load_task(task, now);
+        if !state.enabled || now < state.next_run_at {
+            continue;
+        }
+
+        let already_running = RUNNING_TASKS.with(|running| !running.borrow_mut().insert(task));
+        if already_running {
+            continue;
+        }
+
+        insert_scheduled_task(task.name(), ScheduledTask {
+            last_started_at: Some(now),
+            // Provisional - replaced when the run finishes
+            next_run_at: now + state.interval_seconds * 1_000_000_000,
+            ..state
+        });
+
+        ic_cdk::spawn(async move {
+            let result = run_task(task).await;
+            RUNNING_TASKS.with(|running| running.borrow_mut().remove(&task));
+            record_result(task, result);
+        });
+    }
+
+    crate::alerts::check_cycles(crate::runtime::cycles_balance());
+    if crate::alerts::has_pending() {
+        ic_cdk::spawn(crate::alerts::deliver_pending());
+    }
+}
+
+fn record_result(task: BackgroundTask, result: Result<(), String>) {
+    let now = get_time();
+    let mut state = load_task(task, now);
+    state.last_finished_at = Some(now);
+    state.total_runs += 1;
+
+    match result {
+        Ok(()) => {
+            if state.consecutive_failures > 0 {
+                ic_cdk::println!("✅ Task {} recovered after {} failed runs", task.name(), state.consecutive_failures);
+            }
+            state.consecutive_failures = 0;
+            state.last_error = None;
+            state.next_run_at = now + state.interval_seconds * 1_000_000_000;
+        }
+        Err(e) => {
+            state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            let delay_seconds = backoff_delay_seconds(state.interval_seconds, state.consecutive_failures);
+            ic_cdk::println!(
+                "⚠️ Task {} failed ({} in a row): {} - next run in {}s",
+                task.name(), state.consecutive_failures, e, delay_seconds
+            );
+            if task == BackgroundTask::BlockSync {
+                crate::alerts::on_block_sync_failed(state.consecutive_failures, &e);
+            }
+            state.last_error = Some(e);
+            state.next_run_at = now + delay_seconds * 1_000_000_000;
+        }
+    }
+
+    insert_scheduled_task(task.name(), state);
+}
+
+/// interval * 2^failures, capped at max(interval, TASK_MAX_BACKOFF_SECONDS)
+fn backoff_delay_seconds(interval_seconds: u64, consecutive_failures: u32) -> u64 {
+    interval_seconds
+        .saturating_mul(1u64 << consecutive_failures.min(20))
+        .min(interval_seconds.max(TASK_MAX_BACKOFF_SECONDS))
+}
+
+/// Log the cycles a task consumed (for the heavier jobs)
+fn log_cycles(label: &str, cycles_start: u128) {
+    let cycles_end = ic_cdk::api::canister_balance128();
+    let cycles_consumed = cycles_start.saturating_sub(cycles_end);
+
+    ic_cdk::println!(
+        "⏱️  {} consumed {} cycles ({:.4} TC)",
+        label,
+        cycles_consumed,
+        cycles_consumed as f64 / 1_000_000_000_000.0
+    );
+}
+
+async fn run_task(task: BackgroundTask) -> Result<(), String> {
+    match task {
+        BackgroundTask::Confirmations => crate::heartbeat::process_confirmations().await,
+        BackgroundTask::IdleReactivation => crate::heartbeat::reactivate_idle_chunks().await,
+        BackgroundTask::CleanupTasks => crate::heartbeat::process_cleanup_tasks().await,
+        BackgroundTask::BlockSync => {
+            let cycles_start = ic_cdk::api::canister_balance128();
+
+            let result = match crate::chain_sync::sync_blocks().await {
+                Ok(result) => {
+                    ic_cdk::println!(
+                        "✅ Block sync successful: {} blocks added, {} removed",
+                        result.blocks_added,
+                        result.blocks_removed
+                    );
+                    Ok(())
+                }
+                Err(e) => {
+                    ic_cdk::println!("❌ Block sync failed: {}", e);
+                    Err(e)
+                }
+            };
+
+            log_cycles("sync_blocks", cycles_start);
+            result
+        }
+        BackgroundTask::DataCleanup => {
+            let cycles_start = ic_cdk::api::canister_balance128();
+
+            let (archived_orders, archived_trades) = crate::archive::run_archive().await;
+            if archived_orders > 0 || archived_trades > 0 {
+                ic_cdk::println!("📦 Archive: {} orders, {} trades offloaded", archived_orders, archived_trades);
+            }
+
+            let (orders, trades, blocks, admin_events) = crate::data_cleanup::run_cleanup();
+            ic_cdk::println!("🧹 Cleanup: {} orders, {} trades, {} blocks, {} admin_events deleted", orders, trades, blocks, admin_events);
+
+            let quotes = crate::quotes::prune_quotes(ic_cdk::api::time());
+            ic_cdk::println!("🧹 Cleanup: {} stale quotes deleted", quotes);
+
+            let dedup_entries = crate::transfer_dedup::prune(ic_cdk::api::time());
+            ic_cdk::println!("🧹 Cleanup: {} expired transfer dedup entries deleted", dedup_entries);
+
+            log_cycles("data_cleanup", cycles_start);
+            Ok(())
+        }
+        BackgroundTask::SettlementReport => {
+            crate::settlement::generate_pending_report().await;
+            Ok(())
+        }
+        BackgroundTask::Reconciliation => {
+            crate::reconciliation::run_reconciliation().await;
+            Ok(())
+        }
+        BackgroundTask::CkEthBalanceCheck => {
+            crate::withdrawal_treasury::check_cketh_balance().await;
+            Ok(())
+        }
+        BackgroundTask::WithdrawalFollowUp => {
+            crate::withdrawal_treasury::reconcile_unconfirmed_withdrawals().await;
+            crate::withdrawal_treasury::retry_withdrawal_refunds().await;
+            crate::withdrawal_treasury::poll_withdrawal_statuses().await;
+            Ok(())
+        }
+    }
+}
+
+/// Settings and last-run status of every task
+pub fn get_tasks() -> Vec<ScheduledTask> {
+    let now = get_time();
+    ALL_TASKS.iter().map(|task| load_task(*task, now)).collect()
+}
+
+/// Bring an enabled task's next run forward to the next tick - returns when it will start
+pub fn run_soon(task: BackgroundTask) -> Result<u64, EasySwapError> {
+    let now = get_time();
+    let mut state = load_task(task, now);
+    if !state.enabled {
+        return Err(EasySwapError::unavailable(format!("Task {} is disabled", task.name())));
+    }
+
+    state.next_run_at = state.next_run_at.min(now);
+    insert_scheduled_task(task.name(), state);
+    Ok(now + SCHEDULER_TICK_SECONDS * 1_000_000_000)
+}
+
+/// Change a task's interval and/or enable flag
+/// A new interval takes effect from now; re-enabling a task clears its backoff
+pub fn update_task(task: BackgroundTask, interval_seconds: Option<u64>, enabled: Option<bool>) -> Result<ScheduledTask, EasySwapError> {
+    let now = get_time();
+    let mut state = load_task(task, now);
+
+    if let Some(interval_seconds) = interval_seconds {
+        if !(SCHEDULER_TICK_SECONDS..=MAX_TASK_INTERVAL_SECONDS).contains(&interval_seconds) {
+            return Err(EasySwapError::invalid_input(format!(
+                "Interval must be between {} and {} seconds",
+                SCHEDULER_TICK_SECONDS, MAX_TASK_INTERVAL_SECONDS
+            )));
+        }
+        state.interval_seconds = interval_seconds;
+        state.next_run_at = now + interval_seconds * 1_000_000_000;
+    }
+
+    if let Some(enabled) = enabled {
+        if enabled && !state.enabled {
+            state.consecutive_failures = 0;
+            state.next_run_at = now + state.interval_seconds * 1_000_000_000;
+        }
+        state.enabled = enabled;
+    }
+
+    insert_scheduled_task(task.name(), state.clone());
+    Ok(state)
+}
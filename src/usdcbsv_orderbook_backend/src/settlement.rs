@@ -0,0 +1,127 @@
+/// Daily settlement reports - the canonical daily close finance reconciles against
+/// Compiled once per UTC day from trades, orders and admin events, plus ending
+/// treasury balances and cycles
+use candid::{Nat, Principal};
+use ic_cdk::api::call::CallResult;
+use crate::config::{CK_ETH_LEDGER, CK_USDC_LEDGER, FILLER_INCENTIVE_PERCENT};
+use crate::state::*;
+use crate::types::*;
+use crate::withdrawal_treasury::Account;
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Compile the report for the last completed UTC day if it doesn't exist yet
+/// Older missed days are not backfilled - their trades/events may already be cleaned up
+pub async fn generate_pending_report() {
+    let today = get_time() / NANOS_PER_DAY;
+    if today == 0 {
+        return;
+    }
+    let day = today - 1;
+    
+    if get_settlement_report(day).is_some() {
+        return;
+    }
+    
+    let report = compile_report(day).await;
+    ic_cdk::println!(
+        "📒 Settlement report day {}: volume ${:.2}, fees ${:.2}, penalties ${:.2}, reclaimed ${:.2}",
+        day, report.volume_usd, report.activation_fees_usd, report.penalties_usd, report.reclaimed_usd
+    );
+    
+    // Another tick may have finished first while we awaited the ledgers
+    if get_settlement_report(day).is_none() {
+        insert_settlement_report(report);
+    }
+}
+
+async fn compile_report(day: u64) -> DailySettlementReport {
+    let day_start = day * NANOS_PER_DAY;
+    let day_end = day_start + NANOS_PER_DAY;
+    let in_day = |ts: u64| ts >= day_start && ts < day_end;
+    
+    let mut report = DailySettlementReport {
+        day,
+        generated_at: 0,
+        orders_activated: 0,
+        activation_fees_usd: 0.0,
+        trades_completed: 0,
+        volume_usd: 0.0,
+        filler_incentives_usd: 0.0,
+        penalties_count: 0,
+        penalties_usd: 0.0,
+        reclaimed_trades: 0,
+        reclaimed_usd: 0.0,
+        dust_swept_usd: 0.0,
+        treasury_ckusdc_e6: None,
+        treasury_cketh_wei: None,
+        cycles_balance: 0,
+        cycles_burned: None,
+    };
+    
+    for order in get_all_orders() {
+        if order.activation_fee_confirmed_at.is_some_and(in_day) {
+            report.orders_activated += 1;
+            report.activation_fees_usd += e6_to_usd(order.activation_fee_usd_e6.unwrap_or(0));
+        }
+    }
+    
+    for trade in get_trades_by_status(TradeStatus::WithdrawalConfirmed) {
+        if trade.withdrawal_confirmed_at.is_some_and(in_day) {
+            report.trades_completed += 1;
+            report.volume_usd += e6_to_usd(trade.amount_usd_e6);
+            let incentive_bps = get_order(trade.order_id).map_or(FILLER_INCENTIVE_PERCENT, |order| order.filler_incentive_bps());
+            report.filler_incentives_usd += e6_to_usd(bps_of(trade.amount_usd_e6, incentive_bps));
+        }
+    }
+    
+    let treasury_events = AdminEventFilter {
+        kinds: Some(vec![AdminEventKind::PenaltyApplied, AdminEventKind::TradeExpiredToTreasury, AdminEventKind::OrderDustSwept]),
+        from_time: Some(day_start),
+        to_time: Some(day_end - 1),
+    };
+    for event in get_admin_events_matching(&treasury_events) {
+        match event.event_type {
+            AdminEventType::PenaltyApplied { penalty_amount, .. } => {
+                report.penalties_count += 1;
+                report.penalties_usd += penalty_amount;
+            }
+            AdminEventType::TradeExpiredToTreasury { amount_sent, .. } => {
+                report.reclaimed_trades += 1;
+                report.reclaimed_usd += amount_sent;
+            }
+            AdminEventType::OrderDustSwept { amount_swept, .. } => {
+                report.dust_swept_usd += amount_swept;
+            }
+            _ => {}
+        }
+    }
+    
+    report.treasury_ckusdc_e6 = treasury_balance(CK_USDC_LEDGER).await.ok();
+    report.treasury_cketh_wei = treasury_balance(CK_ETH_LEDGER).await.ok();
+    
+    report.cycles_balance = ic_cdk::api::canister_balance();
+    report.cycles_burned = get_latest_settlement_report()
+        .map(|previous| previous.cycles_balance.saturating_sub(report.cycles_balance));
+    report.generated_at = get_time();
+    
+    report
+}
+
+pub(crate) async fn treasury_balance(ledger: &str) -> Result<Nat, String> {
+    let ledger = Principal::from_text(ledger)
+        .map_err(|e| format!("Invalid ledger principal: {}", e))?;
+    let account = Account {
+        owner: ic_cdk::id(),
+        subaccount: None,
+    };
+    
+    let result: CallResult<(Nat,)> = ic_cdk::call(ledger, "icrc1_balance_of", (account,)).await;
+    match result {
+        Ok((balance,)) => Ok(balance),
+        Err((code, msg)) => {
+            ic_cdk::println!("⚠️ Failed to read treasury balance: {:?}: {}", code, msg);
+            Err(format!("{:?}: {}", code, msg))
+        }
+    }
+}
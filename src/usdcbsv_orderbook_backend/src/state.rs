@@ -1,4 +1,4 @@
-use candid::{Principal, CandidType, Encode, Decode};
+use candid::{Principal, CandidType};
 use serde::{Serialize, Deserialize};
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
 use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell, Storable};
@@ -6,6 +6,7 @@ use ic_stable_structures::storable::Bound;
 use std::cell::RefCell;
 use std::borrow::Cow;
 use crate::types::*;
+use crate::record_envelope::{encode_record, decode_stored, VersionedRecord};
 
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 
@@ -26,6 +27,27 @@ pub struct AppState {
     pub treasury_address_arbitrum: Option<String>,
     pub treasury_address_optimism: Option<String>,
     pub new_orders_enabled: bool,
+    pub woc_api_key: Option<String>,
+    pub bitails_api_key: Option<String>,
+    pub circuit_breaker: Option<CircuitBreakerState>,  // Set while tripped - see circuit_breaker.rs
+    pub treasury_buckets: Option<TreasuryBuckets>,     // None until the first inflow after upgrade
+    pub cketh_monitor: Option<CkEthMonitor>,           // None = default watermark, never checked
+    pub archive_canister: Option<Principal>,          // None = data_cleanup deletes instead of archiving
+    pub retention_policy: Option<RetentionPolicy>,    // None = config defaults
+    pub exposure_limits: Option<ExposureLimits>,      // None = config defaults
+    pub filler_allowlist_enabled: Option<bool>,       // None = off, anyone may trade
+    pub filler_incentive_bounds: Option<FillerIncentiveBounds>, // None = config defaults
+    pub matching_policy: Option<MatchingPolicy>,      // None = Fifo
+    pub trade_size_limits: Option<TradeSizeLimits>,   // None = config defaults
+    pub timeout_penalty_schedule: Option<TimeoutPenaltySchedule>,   // None = config defaults
+    pub price_improvement_sharing: Option<PriceImprovementSharing>, // None = config defaults
+    pub confirmation_depth_schedule: Option<ConfirmationDepthSchedule>, // None = config defaults
+    pub max_chain_tip_age_seconds: Option<u64>,   // None = MAX_CHAIN_TIP_AGE_SECONDS
+    pub txarchive_policy: Option<TxArchivePolicy>, // None = config defaults
+    pub bsv_network: Option<BsvNetwork>,           // None = Mainnet
+    pub collateral_tokens: Option<Vec<CollateralToken>>,   // None = ckUSDC only
+    pub alert_sink: Option<AlertSink>,             // None = alerts off
+    pub outcall_backoffs: Option<Vec<OutcallBackoff>>, // None = no background outcall target failing
 }
 
 impl Default for AppState {
@@ -45,23 +67,47 @@ impl Default for AppState {
             treasury_address_arbitrum: None,
             treasury_address_optimism: None,
             new_orders_enabled: true, // Default: accept new orders
+            woc_api_key: None,
+            bitails_api_key: None,
+            circuit_breaker: None,
+            treasury_buckets: None,
+            cketh_monitor: None,
+            archive_canister: None,
+            retention_policy: None,
+            exposure_limits: None,
+            filler_allowlist_enabled: None,
+            filler_incentive_bounds: None,
+            matching_policy: None,
+            trade_size_limits: None,
+            timeout_penalty_schedule: None,
+            price_improvement_sharing: None,
+            confirmation_depth_schedule: None,
+            max_chain_tip_age_seconds: None,
+            txarchive_policy: None,
+            bsv_network: None,
+            collateral_tokens: None,
+            alert_sink: None,
+            outcall_backoffs: None,
         }
     }
 }
 
 // Implement Storable for AppState
+// A single cell can't be quarantined - an undecodable AppState still traps here
 impl Storable for AppState {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+        Cow::Owned(encode_record(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(&bytes)
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
+impl VersionedRecord for AppState {}
+
 thread_local! {
     pub static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = 
         RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
@@ -105,6 +151,215 @@ thread_local! {
         )
     );
     
+    // Deposits swept back to makers from orphaned order subaccounts
+    // MemoryId 7 is used by block_headers::LEGACY_BLOCK_HEADERS, 29 by block_headers::BLOCK_HEADERS
+    pub static DEPOSIT_RECOVERIES: RefCell<StableBTreeMap<u64, DepositRecovery, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8))),
+        )
+    );
+    
+    // Maker-flagged trade disputes (keyed by trade ID)
+    pub static DISPUTES: RefCell<StableBTreeMap<TradeId, TradeDispute, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(9))),
+        )
+    );
+    
+    // Maker quiet hours schedules (keyed by maker principal)
+    pub static MAKER_QUIET_HOURS: RefCell<StableBTreeMap<Principal, MakerQuietHours, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(10))),
+        )
+    );
+    
+    // Daily settlement reports (keyed by UTC day index)
+    pub static SETTLEMENT_REPORTS: RefCell<StableBTreeMap<u64, DailySettlementReport, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(11))),
+        )
+    );
+    
+    // Completed-trade statistics per UTC hour (keyed by hours since epoch)
+    pub static MARKET_STATS: RefCell<StableBTreeMap<u64, HourlyMarketStats, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(13))),
+        )
+    );
+    
+    // Hourly oracle price candles, keyed by candle start (ns)
+    pub static PRICE_CANDLES: RefCell<StableBTreeMap<u64, PriceCandle, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(14))),
+        )
+    );
+    
+    // Order balance discrepancies found by the reconciliation job, keyed by order
+    pub static RECONCILIATION_ISSUES: RefCell<StableBTreeMap<OrderId, ReconciliationIssue, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(15))),
+        )
+    );
+    
+    // ckUSDC -> ETH withdrawals, keyed by withdrawal ID
+    pub static ETH_WITHDRAWALS: RefCell<StableBTreeMap<u64, EthWithdrawal, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(16))),
+        )
+    );
+    
+    // Admin-managed withdrawal routes, keyed by chain name
+    pub static WITHDRAWAL_ROUTES: RefCell<StableBTreeMap<String, WithdrawalRoute, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(17))),
+        )
+    );
+    
+    // Filler quotes (RFQ), keyed by quote ID
+    pub static QUOTES: RefCell<StableBTreeMap<QuoteId, Quote, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(18))),
+        )
+    );
+    
+    // Background task settings and status, keyed by task name - see scheduler.rs
+    pub static SCHEDULED_TASKS: RefCell<StableBTreeMap<String, ScheduledTask, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(19))),
+        )
+    );
+    
+    // Failed penalty/refund transfers awaiting retry, keyed by ID - see pending_transfers.rs
+    pub static PENDING_TRANSFERS: RefCell<StableBTreeMap<u64, PendingTransfer, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(20))),
+        )
+    );
+    
+    // created_at_time of ledger transfers whose outcome is still unknown, keyed by memo - see transfer_dedup.rs
+    pub static TRANSFER_DEDUP: RefCell<StableBTreeMap<Vec<u8>, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(21))),
+        )
+    );
+    
+    // Fillers approved to trade while the allowlist is enabled, with the approval time
+    pub static APPROVED_FILLERS: RefCell<StableBTreeMap<Principal, u64, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(22))),
+        )
+    );
+    
+    // Per-maker filler blocklists, keyed by maker - see filler_blocklist.rs
+    pub static MAKER_FILLER_BLOCKLISTS: RefCell<StableBTreeMap<Principal, MakerFillerBlocklist, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(23))),
+        )
+    );
+    
+    // Changes to the set of Available chunks, keyed by sequence number - see orderbook_feed.rs
+    pub static ORDERBOOK_CHANGES: RefCell<StableBTreeMap<u64, OrderbookChange, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(24))),
+        )
+    );
+    
+    // Records that no longer decode, moved out of their maps on upgrade - see record_envelope.rs
+    pub static CORRUPT_RECORDS: RefCell<StableBTreeMap<u64, CorruptRecord, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(25))),
+        )
+    );
+    
+    // Submitted BSV transactions keyed by txid - trades hold only the txid, so listing
+    // and auditing them doesn't copy the hex
+    pub static TX_BLOBS: RefCell<StableBTreeMap<TxidKey, TxBlob, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(26))),
+        )
+    );
+    
+    // Operator delegations, keyed by operator principal - see delegations.rs
+    pub static DELEGATIONS: RefCell<StableBTreeMap<Principal, OperatorDelegation, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(27))),
+        )
+    );
+    
+    // Outstanding release bonds, keyed by trade - see release_bonds.rs
+    pub static RELEASE_BONDS: RefCell<StableBTreeMap<TradeId, ReleaseBond, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(28))),
+        )
+    );
+    
+    // Merkle proofs already verified, keyed by txid - see bump_verification::verify_tx_cached
+    // MemoryId 29 is used by block_headers::BLOCK_HEADERS
+    pub static VERIFIED_INCLUSIONS: RefCell<StableBTreeMap<TxidKey, VerifiedInclusion, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(30))),
+        )
+    );
+    
+    // Makers who let activation pull shortfalls from their wallet - see wallet_top_up.rs
+    pub static MAKER_AUTO_TOP_UPS: RefCell<StableBTreeMap<Principal, MakerAutoTopUp, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(31))),
+        )
+    );
+    
+    // Maker fee rebate epochs, keyed by epoch number - the last one is open - see rebates.rs
+    pub static REBATE_EPOCHS: RefCell<StableBTreeMap<u64, RebateEpoch, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(32))),
+        )
+    );
+    
+    // Per-maker rebate volume and balances - see rebates.rs
+    pub static MAKER_REBATES: RefCell<StableBTreeMap<Principal, MakerRebates, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(33))),
+        )
+    );
+    
+    // Orderbooks besides the default one, keyed by book ID - see books.rs
+    pub static BOOKS: RefCell<StableBTreeMap<BookId, OrderBook, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(34))),
+        )
+    );
+    // MemoryId 35 is used by block_headers::TESTNET_BLOCK_HEADERS
+    
+    // ADMIN_EVENTS by (AdminEventKind::index_tag, event ID) - derived, so not backed up but
+    // rebuilt after a restore and by schema migration 4
+    pub static ADMIN_EVENT_INDEX: RefCell<StableBTreeMap<(u8, u64), (), Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(36))),
+        )
+    );
+    
+    // Filler appeals against trade penalties (keyed by trade ID)
+    pub static PENALTY_APPEALS: RefCell<StableBTreeMap<TradeId, PenaltyAppeal, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(37))),
+        )
+    );
+    
+    // Claims on trades already reclaimed to treasury (keyed by trade ID)
+    pub static LATE_CLAIMS: RefCell<StableBTreeMap<TradeId, LateClaim, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(38))),
+        )
+    );
+    
+    // Schema version of stored orders/chunks/trades - see migrations.rs
+    pub static SCHEMA_STATE: RefCell<StableCell<SchemaState, Memory>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(12))),
+            SchemaState::default()
+        ).expect("Failed to initialize schema state")
+    );
+    
     // Stable app state - persists across upgrades!
     pub static APP_STATE: RefCell<StableCell<AppState, Memory>> = RefCell::new(
         StableCell::init(
@@ -114,14 +369,59 @@ thread_local! {
     );
 }
 
+// ===== CURSOR SCANS =====
+
+/// Range-scan a u64-keyed map from just past `after_id` (descending keys when newest_first)
+/// and stop once `limit` values pass `keep` - nothing is loaded or sorted beyond that
+/// Returns the page and the key to resume from, or None once the map is exhausted
+pub fn scan_page<V, F>(
+    map: &StableBTreeMap<u64, V, Memory>,
+    after_id: Option<u64>,
+    limit: usize,
+    newest_first: bool,
+    mut keep: F,
+) -> (Vec<V>, Option<u64>)
+where
+    V: Storable,
+    F: FnMut(&V) -> bool,
+{
+    use std::ops::Bound;
+    
+    let bounds = match (after_id, newest_first) {
+        (None, _) => (Bound::Unbounded, Bound::Unbounded),
+        (Some(id), true) => (Bound::Unbounded, Bound::Excluded(id)),
+        (Some(id), false) => (Bound::Excluded(id), Bound::Unbounded),
+    };
+    let entries = map.range(bounds);
+    let entries: Box<dyn Iterator<Item = (u64, V)> + '_> = if newest_first {
+        Box::new(entries.rev())
+    } else {
+        Box::new(entries)
+    };
+    
+    let mut page = Vec::new();
+    if limit == 0 {
+        return (page, after_id);
+    }
+    for (key, value) in entries {
+        if keep(&value) {
+            page.push(value);
+            if page.len() >= limit {
+                return (page, Some(key));
+            }
+        }
+    }
+    (page, None)
+}
+
 // ===== STATE GETTERS AND SETTERS =====
 
 pub fn get_caller() -> Principal {
-    ic_cdk::caller()
+    crate::runtime::caller()
 }
 
 pub fn get_time() -> u64 {
-    ic_cdk::api::time()
+    crate::runtime::time()
 }
 
 pub fn is_admin(principal: Principal) -> bool {
@@ -138,7 +438,7 @@ pub fn get_admin() -> Principal {
 
 pub fn get_treasury_principal() -> Principal {
     // Treasury is the canister itself
-    ic_cdk::api::id()
+    crate::runtime::canister_id()
 }
 
 pub fn set_admin(principal_str: String) -> Result<(), String> {
@@ -164,10 +464,10 @@ pub fn set_admin(principal_str: String) -> Result<(), String> {
 
 /// Get available orderbook balance by summing all Available chunks
 /// This is always accurate as it's calculated from the source of truth
-pub fn get_available_orderbook() -> f64 {
+pub fn get_available_orderbook() -> UsdE6 {
     let available_chunks = get_available_chunks();
     available_chunks.iter()
-        .map(|c| c.amount_usd)
+        .map(|c| c.amount_usd_e6)
         .sum()
 }
 
@@ -215,6 +515,8 @@ where
         let mut order = orders.get(&order_id)
             .ok_or_else(|| "Order not found".to_string())?;
         updater(&mut order);
+        // Every change to total_idle_usd_e6 goes through here
+        order.track_idle(get_time());
         orders.insert(order_id, order);
         Ok(())
     })
@@ -291,9 +593,10 @@ pub fn create_chunk_id() -> ChunkId {
 }
 
 pub fn insert_chunk(chunk: Chunk) {
-    CHUNKS.with(|chunks| {
-        chunks.borrow_mut().insert(chunk.id, chunk);
+    let previous = CHUNKS.with(|chunks| {
+        chunks.borrow_mut().insert(chunk.id, chunk.clone())
     });
+    crate::orderbook_feed::record_chunk_change(previous.as_ref(), &chunk);
 }
 
 pub fn get_chunk(chunk_id: ChunkId) -> Option<Chunk> {
@@ -308,12 +611,13 @@ where
 {
     CHUNKS.with(|chunks| {
         let mut chunks = chunks.borrow_mut();
-        let mut chunk = chunks.get(&chunk_id)
+        let previous = chunks.get(&chunk_id)
             .ok_or_else(|| "Chunk not found".to_string())?;
+        let mut chunk = previous.clone();
         updater(&mut chunk);
-        chunks.insert(chunk_id, chunk);
-        Ok(())
-    })
+        chunks.insert(chunk_id, chunk.clone());
+        Ok((previous, chunk))
+    }).map(|(previous, chunk)| crate::orderbook_feed::record_chunk_change(Some(&previous), &chunk))
 }
 
 pub fn get_available_chunks() -> Vec<Chunk> {
@@ -372,6 +676,15 @@ where
     })
 }
 
+/// Delete a trade along with its reference to the submitted transaction
+pub fn remove_trade(trade_id: TradeId) -> Option<Trade> {
+    let trade = TRADES.with(|trades| trades.borrow_mut().remove(&trade_id))?;
+    if let Some(txid) = &trade.bsv_txid {
+        release_tx_blob(txid);
+    }
+    Some(trade)
+}
+
 pub fn get_trades_by_filler(filler: Principal) -> Vec<Trade> {
     TRADES.with(|trades| {
         let mut results: Vec<Trade> = trades.borrow().iter()
@@ -384,6 +697,18 @@ pub fn get_trades_by_filler(filler: Principal) -> Vec<Trade> {
     })
 }
 
+/// Trades that locked chunks of an order, newest first
+pub fn get_trades_by_order(order_id: OrderId) -> Vec<Trade> {
+    TRADES.with(|trades| {
+        let mut results: Vec<Trade> = trades.borrow().iter()
+            .filter(|(_, trade)| trade.order_id == order_id)
+            .map(|(_, trade)| trade)
+            .collect();
+        results.sort_by_key(|trade| std::cmp::Reverse(trade.created_at));
+        results
+    })
+}
+
 pub fn get_trades_by_status(status: TradeStatus) -> Vec<Trade> {
     TRADES.with(|trades| {
         trades.borrow().iter()
@@ -405,13 +730,13 @@ pub fn get_all_trades() -> Vec<Trade> {
 
 /// Calculate pending trades total from actual active trades
 /// Active trades are those not in final states (WithdrawalConfirmed, Cancelled, PenaltyApplied)
-pub fn calculate_pending_trades_for_filler(filler: Principal) -> f64 {
+pub fn calculate_pending_trades_for_filler(filler: Principal) -> UsdE6 {
     TRADES.with(|trades| {
         let all_trades: Vec<_> = trades.borrow().iter().collect();
         ic_cdk::println!("🔍 Calculating pending trades for filler: {}", filler);
         ic_cdk::println!("   Total trades in system: {}", all_trades.len());
         
-        let mut pending_total = 0.0;
+        let mut pending_total: UsdE6 = 0;
         let mut count = 0;
         
         for (trade_id, trade) in all_trades {
@@ -423,16 +748,16 @@ pub fn calculate_pending_trades_for_filler(filler: Principal) -> f64 {
                 );
                 
                 ic_cdk::println!("   Trade {}: status={:?}, amount=${:.2}, pending={}", 
-                    trade_id, trade.status, trade.amount_usd, is_pending);
+                    trade_id, trade.status, e6_to_usd(trade.amount_usd_e6), is_pending);
                 
                 if is_pending {
-                    pending_total += trade.amount_usd;
+                    pending_total += trade.amount_usd_e6;
                     count += 1;
                 }
             }
         }
         
-        ic_cdk::println!("   ✅ Result: {} pending trades, total ${:.2}", count, pending_total);
+        ic_cdk::println!("   ✅ Result: {} pending trades, total ${:.2}", count, e6_to_usd(pending_total));
         pending_total
     })
 }
@@ -447,12 +772,28 @@ pub fn get_filler_account(principal: Principal) -> Option<FillerAccount> {
     FILLER_ACCOUNTS.with(|accounts| {
         accounts.borrow().get(&principal).map(|mut account| {
             // Recalculate pending_trades_total from actual active trades
-            account.pending_trades_total = calculate_pending_trades_for_filler(principal);
+            account.pending_trades_total = e6_to_usd(calculate_pending_trades_for_filler(principal));
             account
         })
     })
 }
 
+/// Filler accounts whose trades have timed out at least `min_timeout_releases` times, worst first
+pub fn get_fillers_by_timeout_releases(min_timeout_releases: u64) -> Vec<FillerAccount> {
+    let mut fillers: Vec<FillerAccount> = FILLER_ACCOUNTS.with(|accounts| {
+        accounts.borrow()
+            .iter()
+            .filter(|(_, account)| account.timeout_releases.unwrap_or(0) >= min_timeout_releases)
+            .map(|(principal, mut account)| {
+                account.pending_trades_total = e6_to_usd(calculate_pending_trades_for_filler(principal));
+                account
+            })
+            .collect()
+    });
+    fillers.sort_by_key(|account| std::cmp::Reverse(account.timeout_releases.unwrap_or(0)));
+    fillers
+}
+
 pub fn update_filler_account<F>(principal: Principal, updater: F) -> Result<(), String>
 where
     F: FnOnce(&mut FillerAccount),
@@ -568,11 +909,148 @@ pub fn unmark_bsv_tx(txid: &str) {
     });
 }
 
+// ===== TX BLOBS =====
+
+/// Take a reference to the blob for txid, storing tx_hex if no trade references it yet
+pub fn retain_tx_blob(txid: &str, tx_hex: String) {
+    TX_BLOBS.with(|map| {
+        let mut map = map.borrow_mut();
+        let key = TxidKey(txid.to_string());
+        let blob = match map.get(&key) {
+            Some(blob) => TxBlob { ref_count: blob.ref_count + 1, ..blob },
+            None => TxBlob { tx_hex, ref_count: 1 },
+        };
+        map.insert(key, blob);
+    });
+}
+
+/// Drop a reference to the blob for txid - the hex is removed with the last one
+pub fn release_tx_blob(txid: &str) {
+    TX_BLOBS.with(|map| {
+        let mut map = map.borrow_mut();
+        let key = TxidKey(txid.to_string());
+        match map.get(&key) {
+            Some(blob) if blob.ref_count > 1 => {
+                map.insert(key, TxBlob { ref_count: blob.ref_count - 1, ..blob });
+            }
+            Some(_) => {
+                map.remove(&key);
+            }
+            None => ic_cdk::println!("⚠️ Released tx blob {} which isn't stored", txid),
+        }
+    });
+}
+
+pub fn get_tx_hex(txid: &str) -> Option<String> {
+    TX_BLOBS.with(|map| map.borrow().get(&TxidKey(txid.to_string())).map(|blob| blob.tx_hex))
+}
+
+/// Raw hex of the transaction submitted for a trade
+pub fn get_trade_tx_hex(trade: &Trade) -> Option<String> {
+    trade.bsv_txid.as_deref().and_then(get_tx_hex)
+}
+
+// ===== DEPOSIT RECOVERIES =====
+
+/// Record a deposit recovery and return its ID
+pub fn insert_deposit_recovery(mut recovery: DepositRecovery) -> u64 {
+    DEPOSIT_RECOVERIES.with(|map| {
+        let mut map = map.borrow_mut();
+        let id = map.last_key_value().map(|(id, _)| id + 1).unwrap_or(0);
+        recovery.id = id;
+        map.insert(id, recovery);
+        id
+    })
+}
+
+/// Get all deposit recoveries for a maker (newest first)
+pub fn get_deposit_recoveries_by_maker(maker: Principal) -> Vec<DepositRecovery> {
+    DEPOSIT_RECOVERIES.with(|map| {
+        map.borrow().iter()
+            .rev()
+            .filter(|(_, r)| r.maker == maker)
+            .map(|(_, r)| r)
+            .collect()
+    })
+}
+
+// ===== DISPUTES =====
+
+pub fn insert_dispute(dispute: TradeDispute) {
+    DISPUTES.with(|map| {
+        map.borrow_mut().insert(dispute.trade_id, dispute);
+    });
+}
+
+pub fn get_dispute(trade_id: TradeId) -> Option<TradeDispute> {
+    DISPUTES.with(|map| map.borrow().get(&trade_id))
+}
+
+/// Check if a trade is on hold due to an open dispute
+pub fn is_trade_on_hold(trade_id: TradeId) -> bool {
+    get_dispute(trade_id).is_some_and(|d| d.status == DisputeStatus::Open)
+}
+
+/// Get disputes, optionally only open ones (oldest first)
+pub fn get_disputes(open_only: bool) -> Vec<TradeDispute> {
+    DISPUTES.with(|map| {
+        map.borrow().iter()
+            .map(|(_, d)| d)
+            .filter(|d| !open_only || d.status == DisputeStatus::Open)
+            .collect()
+    })
+}
+
+// ===== PENALTY APPEALS =====
+
+pub fn insert_penalty_appeal(appeal: PenaltyAppeal) {
+    PENALTY_APPEALS.with(|map| {
+        map.borrow_mut().insert(appeal.trade_id, appeal);
+    });
+}
+
+pub fn get_penalty_appeal(trade_id: TradeId) -> Option<PenaltyAppeal> {
+    PENALTY_APPEALS.with(|map| map.borrow().get(&trade_id))
+}
+
+/// Get penalty appeals, optionally only unresolved ones (oldest trade first)
+pub fn get_penalty_appeals(open_only: bool) -> Vec<PenaltyAppeal> {
+    PENALTY_APPEALS.with(|map| {
+        map.borrow().iter()
+            .map(|(_, a)| a)
+            .filter(|a| !open_only || a.status != AppealStatus::Resolved)
+            .collect()
+    })
+}
+
+// ===== LATE CLAIMS =====
+
+pub fn insert_late_claim(claim: LateClaim) {
+    LATE_CLAIMS.with(|map| {
+        map.borrow_mut().insert(claim.trade_id, claim);
+    });
+}
+
+pub fn get_late_claim(trade_id: TradeId) -> Option<LateClaim> {
+    LATE_CLAIMS.with(|map| map.borrow().get(&trade_id))
+}
+
+/// Get late claims, optionally only unpaid ones (oldest trade first)
+pub fn get_late_claims(unpaid_only: bool) -> Vec<LateClaim> {
+    LATE_CLAIMS.with(|map| {
+        map.borrow().iter()
+            .map(|(_, c)| c)
+            .filter(|c| !unpaid_only || c.status != LateClaimStatus::Paid)
+            .collect()
+    })
+}
+
 // ===== ADMIN EVENT LOG =====
 
-/// Create a new admin event and return its ID
+/// Create a new admin event and return its ID - critical ones also raise an operator alert
 pub fn create_admin_event(event_type: AdminEventType) -> u64 {
-    APP_STATE.with(|state| {
+    let alert_event_type = event_type.clone();
+    let event_id = APP_STATE.with(|state| {
         let mut app_state = state.borrow().get().clone();
         let event_id = app_state.next_admin_event_id;
         app_state.next_admin_event_id += 1;
@@ -584,55 +1062,141 @@ pub fn create_admin_event(event_type: AdminEventType) -> u64 {
             event_type,
         };
         
-        ADMIN_EVENTS.with(|events| {
-            let mut events_map = events.borrow_mut();
-            events_map.insert(event_id, event);
-            
-            // Keep only the 10000 most recent events
-            const MAX_ADMIN_EVENTS: u64 = 10000;
-            if events_map.len() > MAX_ADMIN_EVENTS {
-                // Get all event IDs sorted by timestamp (oldest first)
-                let mut event_list: Vec<(u64, u64)> = events_map.iter()
-                    .map(|(id, evt)| (id, evt.timestamp))
-                    .collect();
-                event_list.sort_by_key(|(_, ts)| *ts);
-                
-                // Remove oldest events until we have MAX_ADMIN_EVENTS
-                let to_remove = (events_map.len() - MAX_ADMIN_EVENTS) as usize;
-                for i in 0..to_remove {
-                    events_map.remove(&event_list[i].0);
-                }
+        ADMIN_EVENT_INDEX.with(|index| index.borrow_mut().insert((event.event_type.kind().index_tag(), event_id), ()));
+        ADMIN_EVENTS.with(|events| events.borrow_mut().insert(event_id, event));
+        
+        // Keep only the most recent events (RetentionPolicy::max_admin_events) - IDs are
+        // assigned in time order, so the lowest are the oldest
+        let max_admin_events = get_retention_policy().max_admin_events;
+        while get_admin_events_count() > max_admin_events {
+            match ADMIN_EVENTS.with(|events| events.borrow().first_key_value()) {
+                Some((oldest_id, _)) => remove_admin_event(oldest_id),
+                None => break,
             }
-        });
+        }
         
         event_id
+    });
+    crate::alerts::on_admin_event(event_id, &alert_event_type);
+    event_id
+}
+
+/// Remove an event and its index entry
+pub fn remove_admin_event(event_id: u64) {
+    if let Some(event) = ADMIN_EVENTS.with(|events| events.borrow_mut().remove(&event_id)) {
+        ADMIN_EVENT_INDEX.with(|index| index.borrow_mut().remove(&(event.event_type.kind().index_tag(), event_id)));
+    }
+}
+
+/// Rebuild ADMIN_EVENT_INDEX from ADMIN_EVENTS
+pub fn rebuild_admin_event_index() {
+    ADMIN_EVENT_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        index.clear_new();
+        ADMIN_EVENTS.with(|events| {
+            for (id, event) in events.borrow().iter() {
+                index.insert((event.event_type.kind().index_tag(), id), ());
+            }
+        });
+    });
+}
+
+/// Lowest event ID from which every event is at or after `time` (None = no such event)
+/// Event IDs are assigned in time order, so this is a binary search over the ID range
+fn first_admin_event_at_or_after(time: u64) -> Option<u64> {
+    ADMIN_EVENTS.with(|events| {
+        let events = events.borrow();
+        let first_at = |id: u64| events.range(id..).next().map(|(id, event)| (id, event.timestamp));
+        let (mut lo, mut hi) = (events.first_key_value()?.0, events.last_key_value()?.0.saturating_add(1));
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match first_at(mid) {
+                Some((_, timestamp)) if timestamp >= time => hi = mid,
+                _ => lo = mid + 1,
+            }
+        }
+        first_at(lo).map(|(id, _)| id)
     })
 }
 
+/// ID range [from, to) of the events inside the filter's time range
+fn admin_event_id_range(filter: &AdminEventFilter) -> (u64, u64) {
+    let from = match filter.from_time {
+        Some(time) => first_admin_event_at_or_after(time).unwrap_or(u64::MAX),
+        None => 0,
+    };
+    let to = match filter.to_time {
+        Some(time) => time.checked_add(1).and_then(first_admin_event_at_or_after).unwrap_or(u64::MAX),
+        None => u64::MAX,
+    };
+    (from, to)
+}
+
+/// Cursor page of the admin events matching `filter`, newest first - pass next_cursor back as
+/// after_id. Walks only the matching part of the log: time bounds are found by binary search,
+/// kinds through ADMIN_EVENT_INDEX
+pub fn query_admin_events(filter: &AdminEventFilter, after_id: Option<u64>, limit: usize) -> (Vec<AdminEvent>, Option<u64>) {
+    let (from, mut to) = admin_event_id_range(filter);
+    if let Some(after_id) = after_id {
+        to = to.min(after_id);
+    }
+    if from >= to || limit == 0 {
+        return (Vec::new(), None);
+    }
+    
+    let mut ids: Vec<u64> = match &filter.kinds {
+        None => ADMIN_EVENTS.with(|events| events.borrow().range(from..to).rev().take(limit.saturating_add(1)).map(|(id, _)| id).collect()),
+        Some(kinds) => {
+            let mut tags: Vec<u8> = kinds.iter().map(|kind| kind.index_tag()).collect();
+            tags.sort();
+            tags.dedup();
+            ADMIN_EVENT_INDEX.with(|index| {
+                let index = index.borrow();
+                tags.iter()
+                    .flat_map(|tag| index.range((*tag, from)..(*tag, to)).rev().take(limit.saturating_add(1)).map(|((_, id), _)| id).collect::<Vec<_>>())
+                    .collect()
+            })
+        }
+    };
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    let more = ids.len() > limit;
+    ids.truncate(limit);
+    
+    // An index entry can outlive its event if the post_upgrade sweep quarantined the record
+    let events: Vec<AdminEvent> = ADMIN_EVENTS.with(|events| {
+        let events = events.borrow();
+        ids.iter().filter_map(|id| events.get(id)).collect()
+    });
+    let next_cursor = if more { ids.last().copied() } else { None };
+    (events, next_cursor)
+}
+
+/// Every admin event matching `filter`, newest first
+pub fn get_admin_events_matching(filter: &AdminEventFilter) -> Vec<AdminEvent> {
+    let (events, _) = query_admin_events(filter, None, usize::MAX);
+    events
+}
+
 /// Get all admin events (newest first)
 pub fn get_admin_events() -> Vec<AdminEvent> {
-    ADMIN_EVENTS.with(|events| {
-        let map = events.borrow();
-        let mut result: Vec<AdminEvent> = map.iter()
-            .map(|(_, event)| event.clone())
-            .collect();
-        result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)); // Newest first
-        result
-    })
+    ADMIN_EVENTS.with(|events| events.borrow().iter().rev().map(|(_, event)| event).collect())
 }
 
 /// Get recent admin events (last N events)
 pub fn get_recent_admin_events(limit: usize) -> Vec<AdminEvent> {
-    let all_events = get_admin_events();
-    all_events.into_iter().take(limit).collect()
+    ADMIN_EVENTS.with(|events| events.borrow().iter().rev().take(limit).map(|(_, event)| event).collect())
+}
+
+/// Cursor page of admin events, newest first
+pub fn get_admin_events_page(after_id: Option<u64>, limit: usize) -> (Vec<AdminEvent>, Option<u64>) {
+    ADMIN_EVENTS.with(|events| scan_page(&events.borrow(), after_id, limit, true, |_| true))
 }
 
 /// Get paginated admin events (offset + limit)
 pub fn get_paginated_admin_events(offset: usize, limit: usize) -> Vec<AdminEvent> {
-    let all_events = get_admin_events();
-    all_events.into_iter()
+    let (events, _) = get_admin_events_page(None, offset.saturating_add(limit));
+    events.into_iter()
         .skip(offset)
-        .take(limit)
         .collect()
 }
 
@@ -661,3 +1225,896 @@ pub fn set_new_orders_enabled(enabled: bool) {
     });
 }
 
+/// Circuit breaker state while tripped (None = trading normally)
+pub fn get_circuit_breaker() -> Option<CircuitBreakerState> {
+    APP_STATE.with(|cell| cell.borrow().get().circuit_breaker.clone())
+}
+
+pub fn set_circuit_breaker(breaker: Option<CircuitBreakerState>) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.circuit_breaker = breaker;
+        cell.borrow_mut().set(state).expect("Failed to update circuit breaker");
+    });
+}
+
+// ===== TREASURY ACCOUNTING =====
+
+pub fn get_treasury_buckets() -> TreasuryBuckets {
+    APP_STATE.with(|cell| cell.borrow().get().treasury_buckets.clone().unwrap_or_default())
+}
+
+pub fn update_treasury_buckets<F>(updater: F)
+where
+    F: FnOnce(&mut TreasuryBuckets),
+{
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        let mut buckets = state.treasury_buckets.take().unwrap_or_default();
+        updater(&mut buckets);
+        buckets.last_updated_at = Some(get_time());
+        state.treasury_buckets = Some(buckets);
+        cell.borrow_mut().set(state).expect("Failed to update treasury buckets");
+    });
+}
+
+// ===== OUTCALL BACKOFF =====
+
+pub fn get_outcall_backoffs() -> Vec<OutcallBackoff> {
+    APP_STATE.with(|cell| cell.borrow().get().outcall_backoffs.clone().unwrap_or_default())
+}
+
+pub fn update_outcall_backoffs<F>(updater: F)
+where
+    F: FnOnce(&mut Vec<OutcallBackoff>),
+{
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        let mut backoffs = state.outcall_backoffs.take().unwrap_or_default();
+        updater(&mut backoffs);
+        state.outcall_backoffs = (!backoffs.is_empty()).then_some(backoffs);
+        cell.borrow_mut().set(state).expect("Failed to update outcall backoffs");
+    });
+}
+
+// ===== ckETH GAS BUDGET =====
+
+pub fn get_cketh_monitor() -> CkEthMonitor {
+    APP_STATE.with(|cell| cell.borrow().get().cketh_monitor.clone().unwrap_or_default())
+}
+
+pub fn update_cketh_monitor<F>(updater: F)
+where
+    F: FnOnce(&mut CkEthMonitor),
+{
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        let mut monitor = state.cketh_monitor.take().unwrap_or_default();
+        updater(&mut monitor);
+        state.cketh_monitor = Some(monitor);
+        cell.borrow_mut().set(state).expect("Failed to update ckETH monitor");
+    });
+}
+
+// ===== ARCHIVE CANISTER =====
+
+pub fn get_archive_canister() -> Option<Principal> {
+    APP_STATE.with(|cell| cell.borrow().get().archive_canister)
+}
+
+pub fn set_archive_canister(canister: Option<Principal>) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.archive_canister = canister;
+        cell.borrow_mut().set(state).expect("Failed to update archive canister");
+    });
+}
+
+// ===== RETENTION POLICY =====
+
+pub fn get_retention_policy() -> RetentionPolicy {
+    APP_STATE.with(|cell| cell.borrow().get().retention_policy.clone().unwrap_or_default())
+}
+
+pub fn set_retention_policy(policy: RetentionPolicy) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.retention_policy = Some(policy);
+        cell.borrow_mut().set(state).expect("Failed to update retention policy");
+    });
+}
+
+// ===== EXPOSURE LIMITS =====
+
+pub fn get_exposure_limits() -> ExposureLimits {
+    APP_STATE.with(|cell| cell.borrow().get().exposure_limits.clone().unwrap_or_default())
+}
+
+pub fn set_exposure_limits(limits: ExposureLimits) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.exposure_limits = Some(limits);
+        cell.borrow_mut().set(state).expect("Failed to update exposure limits");
+    });
+}
+
+// ===== TRADE SIZE LIMITS =====
+
+pub fn get_trade_size_limits() -> TradeSizeLimits {
+    APP_STATE.with(|cell| cell.borrow().get().trade_size_limits.clone().unwrap_or_default())
+}
+
+pub fn set_trade_size_limits(limits: TradeSizeLimits) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.trade_size_limits = Some(limits);
+        cell.borrow_mut().set(state).expect("Failed to update trade size limits");
+    });
+}
+
+// ===== TIMEOUT PENALTY SCHEDULE =====
+
+pub fn get_timeout_penalty_schedule() -> TimeoutPenaltySchedule {
+    APP_STATE.with(|cell| cell.borrow().get().timeout_penalty_schedule.clone().unwrap_or_default())
+}
+
+pub fn set_timeout_penalty_schedule(schedule: TimeoutPenaltySchedule) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.timeout_penalty_schedule = Some(schedule);
+        cell.borrow_mut().set(state).expect("Failed to update timeout penalty schedule");
+    });
+}
+
+// ===== PRICE IMPROVEMENT SHARING =====
+
+pub fn get_price_improvement_sharing() -> PriceImprovementSharing {
+    APP_STATE.with(|cell| cell.borrow().get().price_improvement_sharing.clone().unwrap_or_default())
+}
+
+pub fn set_price_improvement_sharing(sharing: PriceImprovementSharing) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.price_improvement_sharing = Some(sharing);
+        cell.borrow_mut().set(state).expect("Failed to update price improvement sharing");
+    });
+}
+
+// ===== COLLATERAL TOKENS =====
+
+pub fn get_collateral_tokens() -> Vec<CollateralToken> {
+    APP_STATE.with(|cell| cell.borrow().get().collateral_tokens.clone().unwrap_or_default())
+}
+
+pub fn set_collateral_tokens(tokens: Vec<CollateralToken>) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.collateral_tokens = Some(tokens);
+        cell.borrow_mut().set(state).expect("Failed to update collateral tokens");
+    });
+}
+
+// ===== OPERATOR ALERTS =====
+
+pub fn get_alert_sink() -> Option<AlertSink> {
+    APP_STATE.with(|cell| cell.borrow().get().alert_sink.clone())
+}
+
+pub fn set_alert_sink(sink: Option<AlertSink>) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.alert_sink = sink;
+        cell.borrow_mut().set(state).expect("Failed to update alert sink");
+    });
+}
+
+// ===== CONFIRMATION DEPTH SCHEDULE =====
+
+pub fn get_confirmation_depth_schedule() -> ConfirmationDepthSchedule {
+    APP_STATE.with(|cell| cell.borrow().get().confirmation_depth_schedule.clone().unwrap_or_default())
+}
+
+pub fn set_confirmation_depth_schedule(schedule: ConfirmationDepthSchedule) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.confirmation_depth_schedule = Some(schedule);
+        cell.borrow_mut().set(state).expect("Failed to update confirmation depth schedule");
+    });
+}
+
+// ===== CHAIN TIP FRESHNESS =====
+
+pub fn get_max_chain_tip_age_seconds() -> u64 {
+    APP_STATE.with(|cell| cell.borrow().get().max_chain_tip_age_seconds.unwrap_or(crate::config::MAX_CHAIN_TIP_AGE_SECONDS))
+}
+
+pub fn set_max_chain_tip_age_seconds(seconds: u64) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.max_chain_tip_age_seconds = Some(seconds);
+        cell.borrow_mut().set(state).expect("Failed to update max chain tip age");
+    });
+}
+
+// ===== TXARCHIVE FALLBACK =====
+
+pub fn get_txarchive_policy() -> TxArchivePolicy {
+    APP_STATE.with(|cell| cell.borrow().get().txarchive_policy.clone().unwrap_or_default())
+}
+
+pub fn set_txarchive_policy(policy: TxArchivePolicy) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.txarchive_policy = Some(policy);
+        cell.borrow_mut().set(state).expect("Failed to update TxArchive policy");
+    });
+}
+
+// ===== BSV NETWORK =====
+
+pub fn get_bsv_network() -> BsvNetwork {
+    APP_STATE.with(|cell| cell.borrow().get().bsv_network.unwrap_or(BsvNetwork::Mainnet))
+}
+
+pub fn set_bsv_network(network: BsvNetwork) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.bsv_network = Some(network);
+        cell.borrow_mut().set(state).expect("Failed to update BSV network");
+    });
+}
+
+// ===== FILLER INCENTIVE BOUNDS =====
+
+pub fn get_filler_incentive_bounds() -> FillerIncentiveBounds {
+    APP_STATE.with(|cell| cell.borrow().get().filler_incentive_bounds.clone().unwrap_or_default())
+}
+
+pub fn set_filler_incentive_bounds(bounds: FillerIncentiveBounds) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.filler_incentive_bounds = Some(bounds);
+        cell.borrow_mut().set(state).expect("Failed to update filler incentive bounds");
+    });
+}
+
+// ===== MATCHING POLICY =====
+
+pub fn get_matching_policy() -> MatchingPolicy {
+    APP_STATE.with(|cell| cell.borrow().get().matching_policy.unwrap_or_default())
+}
+
+pub fn set_matching_policy(policy: MatchingPolicy) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.matching_policy = Some(policy);
+        cell.borrow_mut().set(state).expect("Failed to update matching policy");
+    });
+}
+
+// ===== FILLER ALLOWLIST =====
+
+pub fn is_filler_allowlist_enabled() -> bool {
+    APP_STATE.with(|cell| cell.borrow().get().filler_allowlist_enabled.unwrap_or(false))
+}
+
+pub fn set_filler_allowlist_enabled(enabled: bool) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        state.filler_allowlist_enabled = Some(enabled);
+        cell.borrow_mut().set(state).expect("Failed to update filler allowlist mode");
+    });
+}
+
+pub fn is_filler_on_allowlist(filler: Principal) -> bool {
+    APPROVED_FILLERS.with(|map| map.borrow().contains_key(&filler))
+}
+
+/// Add a filler, keeping the original approval time if already listed
+pub fn insert_approved_filler(filler: Principal, approved_at: u64) {
+    APPROVED_FILLERS.with(|map| {
+        let mut map = map.borrow_mut();
+        if !map.contains_key(&filler) {
+            map.insert(filler, approved_at);
+        }
+    });
+}
+
+/// Returns true if the filler was listed
+pub fn remove_approved_filler(filler: Principal) -> bool {
+    APPROVED_FILLERS.with(|map| map.borrow_mut().remove(&filler).is_some())
+}
+
+pub fn get_approved_fillers() -> Vec<ApprovedFiller> {
+    APPROVED_FILLERS.with(|map| {
+        map.borrow().iter()
+            .map(|(filler, approved_at)| ApprovedFiller { filler, approved_at })
+            .collect()
+    })
+}
+
+// ===== PROVIDER API KEYS =====
+
+/// Get the API key configured for a provider (None = free tier)
+pub fn get_provider_api_key(provider: &ApiProvider) -> Option<String> {
+    APP_STATE.with(|cell| {
+        let state = cell.borrow();
+        match provider {
+            ApiProvider::WhatsOnChain => state.get().woc_api_key.clone(),
+            ApiProvider::Bitails => state.get().bitails_api_key.clone(),
+        }
+    })
+}
+
+/// Set or clear the API key for a provider (admin only)
+pub fn set_provider_api_key(provider: &ApiProvider, api_key: Option<String>) {
+    APP_STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        match provider {
+            ApiProvider::WhatsOnChain => state.woc_api_key = api_key,
+            ApiProvider::Bitails => state.bitails_api_key = api_key,
+        }
+        cell.borrow_mut().set(state).expect("Failed to update provider API key");
+    });
+}
+
+
+// ===== MAKER QUIET HOURS =====
+
+pub fn get_maker_quiet_hours(maker: Principal) -> Option<MakerQuietHours> {
+    MAKER_QUIET_HOURS.with(|map| map.borrow().get(&maker))
+}
+
+pub fn set_maker_quiet_hours(schedule: MakerQuietHours) {
+    MAKER_QUIET_HOURS.with(|map| {
+        map.borrow_mut().insert(schedule.maker, schedule);
+    });
+}
+
+pub fn remove_maker_quiet_hours(maker: Principal) {
+    MAKER_QUIET_HOURS.with(|map| {
+        map.borrow_mut().remove(&maker);
+    });
+}
+
+// ===== MAKER FILLER BLOCKLISTS =====
+
+pub fn get_maker_filler_blocklist(maker: Principal) -> Option<MakerFillerBlocklist> {
+    MAKER_FILLER_BLOCKLISTS.with(|map| map.borrow().get(&maker))
+}
+
+pub fn set_maker_filler_blocklist(blocklist: MakerFillerBlocklist) {
+    MAKER_FILLER_BLOCKLISTS.with(|map| {
+        map.borrow_mut().insert(blocklist.maker, blocklist);
+    });
+}
+
+pub fn remove_maker_filler_blocklist(maker: Principal) {
+    MAKER_FILLER_BLOCKLISTS.with(|map| {
+        map.borrow_mut().remove(&maker);
+    });
+}
+
+// ===== MAKER WALLET TOP-UPS =====
+
+pub fn get_maker_auto_top_up(maker: Principal) -> Option<MakerAutoTopUp> {
+    MAKER_AUTO_TOP_UPS.with(|map| map.borrow().get(&maker))
+}
+
+pub fn set_maker_auto_top_up(setting: MakerAutoTopUp) {
+    MAKER_AUTO_TOP_UPS.with(|map| {
+        map.borrow_mut().insert(setting.maker, setting);
+    });
+}
+
+pub fn remove_maker_auto_top_up(maker: Principal) {
+    MAKER_AUTO_TOP_UPS.with(|map| {
+        map.borrow_mut().remove(&maker);
+    });
+}
+
+// ===== MAKER FEE REBATES =====
+
+pub fn get_latest_rebate_epoch() -> Option<RebateEpoch> {
+    REBATE_EPOCHS.with(|map| map.borrow().last_key_value().map(|(_, epoch)| epoch))
+}
+
+pub fn insert_rebate_epoch(epoch: RebateEpoch) {
+    REBATE_EPOCHS.with(|map| {
+        map.borrow_mut().insert(epoch.epoch, epoch);
+    });
+}
+
+pub fn get_maker_rebates(maker: Principal) -> Option<MakerRebates> {
+    MAKER_REBATES.with(|map| map.borrow().get(&maker))
+}
+
+pub fn get_all_maker_rebates() -> Vec<MakerRebates> {
+    MAKER_REBATES.with(|map| map.borrow().iter().map(|(_, rebates)| rebates).collect())
+}
+
+pub fn set_maker_rebates(rebates: MakerRebates) {
+    MAKER_REBATES.with(|map| {
+        map.borrow_mut().insert(rebates.maker, rebates);
+    });
+}
+
+// ===== ORDERBOOKS =====
+
+pub fn get_stored_book(book_id: BookId) -> Option<OrderBook> {
+    BOOKS.with(|map| map.borrow().get(&book_id))
+}
+
+pub fn get_stored_books() -> Vec<OrderBook> {
+    BOOKS.with(|map| map.borrow().iter().map(|(_, book)| book).collect())
+}
+
+pub fn insert_book(book: OrderBook) {
+    BOOKS.with(|map| {
+        map.borrow_mut().insert(book.id, book);
+    });
+}
+
+// ===== OPERATOR DELEGATIONS =====
+
+pub fn get_delegation(operator: Principal) -> Option<OperatorDelegation> {
+    DELEGATIONS.with(|map| map.borrow().get(&operator))
+}
+
+pub fn set_delegation(delegation: OperatorDelegation) {
+    DELEGATIONS.with(|map| {
+        map.borrow_mut().insert(delegation.operator, delegation);
+    });
+}
+
+pub fn remove_delegation(operator: Principal) {
+    DELEGATIONS.with(|map| {
+        map.borrow_mut().remove(&operator);
+    });
+}
+
+pub fn get_delegations_by_owner(owner: Principal) -> Vec<OperatorDelegation> {
+    DELEGATIONS.with(|map| {
+        map.borrow().iter()
+            .filter(|(_, delegation)| delegation.owner == owner)
+            .map(|(_, delegation)| delegation)
+            .collect()
+    })
+}
+
+// ===== RELEASE BONDS =====
+
+pub fn insert_release_bond(bond: ReleaseBond) {
+    RELEASE_BONDS.with(|map| {
+        map.borrow_mut().insert(bond.trade_id, bond);
+    });
+}
+
+pub fn remove_release_bond(trade_id: TradeId) {
+    RELEASE_BONDS.with(|map| {
+        map.borrow_mut().remove(&trade_id);
+    });
+}
+
+pub fn get_release_bond(trade_id: TradeId) -> Option<ReleaseBond> {
+    RELEASE_BONDS.with(|map| map.borrow().get(&trade_id))
+}
+
+pub fn get_release_bonds() -> Vec<ReleaseBond> {
+    RELEASE_BONDS.with(|map| map.borrow().iter().map(|(_, bond)| bond).collect())
+}
+
+pub fn get_release_bonds_by_filler(filler: Principal) -> Vec<ReleaseBond> {
+    RELEASE_BONDS.with(|map| {
+        map.borrow().iter()
+            .filter(|(_, bond)| bond.filler == filler)
+            .map(|(_, bond)| bond)
+            .collect()
+    })
+}
+
+// ===== VERIFIED INCLUSIONS =====
+
+pub fn get_verified_inclusion(txid: &str) -> Option<VerifiedInclusion> {
+    VERIFIED_INCLUSIONS.with(|map| map.borrow().get(&TxidKey(txid.to_string())))
+}
+
+/// Cache a verified proof - evicts the oldest entry once MAX_VERIFIED_INCLUSIONS are held
+pub fn insert_verified_inclusion(inclusion: VerifiedInclusion) {
+    VERIFIED_INCLUSIONS.with(|map| {
+        let mut map = map.borrow_mut();
+        let key = TxidKey(inclusion.txid.clone());
+        if !map.contains_key(&key) && map.len() >= crate::config::MAX_VERIFIED_INCLUSIONS {
+            let oldest = map.iter()
+                .min_by_key(|(_, cached)| cached.verified_at)
+                .map(|(txid, _)| txid);
+            if let Some(oldest) = oldest {
+                map.remove(&oldest);
+            }
+        }
+        map.insert(key, inclusion);
+    });
+}
+
+pub fn remove_verified_inclusion(txid: &str) {
+    VERIFIED_INCLUSIONS.with(|map| {
+        map.borrow_mut().remove(&TxidKey(txid.to_string()));
+    });
+}
+
+
+// ===== SETTLEMENT REPORTS =====
+
+pub fn insert_settlement_report(report: DailySettlementReport) {
+    SETTLEMENT_REPORTS.with(|map| {
+        map.borrow_mut().insert(report.day, report);
+    });
+}
+
+pub fn get_settlement_report(day: u64) -> Option<DailySettlementReport> {
+    SETTLEMENT_REPORTS.with(|map| map.borrow().get(&day))
+}
+
+pub fn get_latest_settlement_report() -> Option<DailySettlementReport> {
+    SETTLEMENT_REPORTS.with(|map| map.borrow().last_key_value().map(|(_, report)| report))
+}
+
+/// Reports for days in [from_day, to_day], oldest first
+pub fn get_settlement_reports(from_day: u64, to_day: u64) -> Vec<DailySettlementReport> {
+    SETTLEMENT_REPORTS.with(|map| {
+        map.borrow()
+            .range(from_day..=to_day)
+            .map(|(_, report)| report)
+            .collect()
+    })
+}
+
+// ===== MARKET STATISTICS =====
+
+pub fn get_hourly_stats(hour: u64) -> Option<HourlyMarketStats> {
+    MARKET_STATS.with(|map| map.borrow().get(&hour))
+}
+
+pub fn insert_hourly_stats(stats: HourlyMarketStats) {
+    MARKET_STATS.with(|map| {
+        map.borrow_mut().insert(stats.hour, stats);
+    });
+}
+
+pub fn get_hourly_stats_range(from_hour: u64, to_hour: u64) -> Vec<HourlyMarketStats> {
+    MARKET_STATS.with(|map| {
+        map.borrow()
+            .range(from_hour..=to_hour)
+            .map(|(_, stats)| stats)
+            .collect()
+    })
+}
+
+/// Drop buckets older than `before_hour`
+pub fn prune_hourly_stats(before_hour: u64) -> u64 {
+    MARKET_STATS.with(|map| {
+        let old: Vec<u64> = map.borrow().range(..before_hour).map(|(hour, _)| hour).collect();
+        let mut map = map.borrow_mut();
+        for hour in &old {
+            map.remove(hour);
+        }
+        old.len() as u64
+    })
+}
+
+// ===== PRICE CANDLES =====
+
+pub fn get_price_candle(start: u64) -> Option<PriceCandle> {
+    PRICE_CANDLES.with(|map| map.borrow().get(&start))
+}
+
+pub fn insert_price_candle(candle: PriceCandle) {
+    PRICE_CANDLES.with(|map| {
+        map.borrow_mut().insert(candle.start, candle);
+    });
+}
+
+pub fn get_price_candles_range(from: u64, to: u64) -> Vec<PriceCandle> {
+    PRICE_CANDLES.with(|map| {
+        map.borrow()
+            .range(from..=to)
+            .map(|(_, candle)| candle)
+            .collect()
+    })
+}
+
+/// Drop candles starting before `before`
+pub fn prune_price_candles(before: u64) -> u64 {
+    PRICE_CANDLES.with(|map| {
+        let old: Vec<u64> = map.borrow().range(..before).map(|(start, _)| start).collect();
+        let mut map = map.borrow_mut();
+        for start in &old {
+            map.remove(start);
+        }
+        old.len() as u64
+    })
+}
+
+// ===== RECONCILIATION =====
+
+pub fn get_reconciliation_issue(order_id: OrderId) -> Option<ReconciliationIssue> {
+    RECONCILIATION_ISSUES.with(|map| map.borrow().get(&order_id))
+}
+
+pub fn insert_reconciliation_issue(issue: ReconciliationIssue) {
+    RECONCILIATION_ISSUES.with(|map| {
+        map.borrow_mut().insert(issue.order_id, issue);
+    });
+}
+
+pub fn remove_reconciliation_issue(order_id: OrderId) -> Option<ReconciliationIssue> {
+    RECONCILIATION_ISSUES.with(|map| map.borrow_mut().remove(&order_id))
+}
+
+pub fn get_reconciliation_issues() -> Vec<ReconciliationIssue> {
+    RECONCILIATION_ISSUES.with(|map| {
+        map.borrow().iter().map(|(_, issue)| issue).collect()
+    })
+}
+
+// ===== ETH WITHDRAWALS =====
+
+/// Store a new withdrawal under the next free ID
+pub fn insert_eth_withdrawal<F>(build: F) -> EthWithdrawal
+where
+    F: FnOnce(u64) -> EthWithdrawal,
+{
+    ETH_WITHDRAWALS.with(|map| {
+        let mut map = map.borrow_mut();
+        let id = map.last_key_value().map_or(0, |(last_id, _)| last_id + 1);
+        let withdrawal = build(id);
+        map.insert(id, withdrawal.clone());
+        withdrawal
+    })
+}
+
+pub fn get_eth_withdrawal(id: u64) -> Option<EthWithdrawal> {
+    ETH_WITHDRAWALS.with(|map| map.borrow().get(&id))
+}
+
+pub fn update_eth_withdrawal<F>(id: u64, updater: F)
+where
+    F: FnOnce(&mut EthWithdrawal),
+{
+    ETH_WITHDRAWALS.with(|map| {
+        let mut map = map.borrow_mut();
+        if let Some(mut withdrawal) = map.get(&id) {
+            updater(&mut withdrawal);
+            map.insert(id, withdrawal);
+        }
+    });
+}
+
+pub fn get_eth_withdrawal_by_cketh_block(cketh_block_index: u64) -> Option<EthWithdrawal> {
+    let block_index = candid::Nat::from(cketh_block_index);
+    ETH_WITHDRAWALS.with(|map| {
+        map.borrow().iter()
+            .map(|(_, w)| w)
+            .find(|w| w.cketh_block_index.as_ref() == Some(&block_index))
+    })
+}
+
+pub fn get_eth_withdrawals_by_user(user: Principal) -> Vec<EthWithdrawal> {
+    ETH_WITHDRAWALS.with(|map| {
+        map.borrow().iter()
+            .map(|(_, w)| w)
+            .filter(|w| w.user == user)
+            .collect()
+    })
+}
+
+pub fn get_eth_withdrawals_by_status(status: EthWithdrawalStatus) -> Vec<EthWithdrawal> {
+    ETH_WITHDRAWALS.with(|map| {
+        map.borrow().iter()
+            .map(|(_, w)| w)
+            .filter(|w| w.status == status)
+            .collect()
+    })
+}
+
+// ===== PENDING TRANSFERS =====
+
+/// Store a new pending transfer under the next free ID
+pub fn insert_pending_transfer<F>(build: F) -> PendingTransfer
+where
+    F: FnOnce(u64) -> PendingTransfer,
+{
+    PENDING_TRANSFERS.with(|map| {
+        let mut map = map.borrow_mut();
+        let id = map.last_key_value().map_or(0, |(last_id, _)| last_id + 1);
+        let transfer = build(id);
+        map.insert(id, transfer.clone());
+        transfer
+    })
+}
+
+pub fn get_pending_transfer(id: u64) -> Option<PendingTransfer> {
+    PENDING_TRANSFERS.with(|map| map.borrow().get(&id))
+}
+
+pub fn update_pending_transfer<F>(id: u64, updater: F)
+where
+    F: FnOnce(&mut PendingTransfer),
+{
+    PENDING_TRANSFERS.with(|map| {
+        let mut map = map.borrow_mut();
+        if let Some(mut transfer) = map.get(&id) {
+            updater(&mut transfer);
+            map.insert(id, transfer);
+        }
+    });
+}
+
+pub fn get_pending_transfers_by_status(status: PendingTransferStatus) -> Vec<PendingTransfer> {
+    PENDING_TRANSFERS.with(|map| {
+        map.borrow().iter()
+            .map(|(_, t)| t)
+            .filter(|t| t.status == status)
+            .collect()
+    })
+}
+
+// ===== CORRUPT RECORDS =====
+
+/// Store a quarantined record under the next free ID
+pub fn insert_corrupt_record<F>(build: F) -> CorruptRecord
+where
+    F: FnOnce(u64) -> CorruptRecord,
+{
+    CORRUPT_RECORDS.with(|map| {
+        let mut map = map.borrow_mut();
+        let id = map.last_key_value().map_or(0, |(last_id, _)| last_id + 1);
+        let record = build(id);
+        map.insert(id, record.clone());
+        record
+    })
+}
+
+/// Cursor page of quarantined records, oldest first
+pub fn get_corrupt_records_page(after_id: Option<u64>, limit: usize) -> (Vec<CorruptRecord>, Option<u64>) {
+    CORRUPT_RECORDS.with(|map| scan_page(&map.borrow(), after_id, limit, false, |_| true))
+}
+
+// ===== WITHDRAWAL ROUTES =====
+
+pub fn get_withdrawal_route(chain: &str) -> Option<WithdrawalRoute> {
+    WITHDRAWAL_ROUTES.with(|map| map.borrow().get(&chain.to_string()))
+}
+
+pub fn get_withdrawal_routes() -> Vec<WithdrawalRoute> {
+    WITHDRAWAL_ROUTES.with(|map| map.borrow().iter().map(|(_, route)| route).collect())
+}
+
+pub fn insert_withdrawal_route(route: WithdrawalRoute) {
+    WITHDRAWAL_ROUTES.with(|map| {
+        map.borrow_mut().insert(route.chain.clone(), route);
+    });
+}
+
+pub fn remove_withdrawal_route(chain: &str) -> Option<WithdrawalRoute> {
+    WITHDRAWAL_ROUTES.with(|map| map.borrow_mut().remove(&chain.to_string()))
+}
+
+// ===== QUOTES =====
+
+/// Store a new quote under the next free ID
+pub fn insert_new_quote<F>(build: F) -> Quote
+where
+    F: FnOnce(QuoteId) -> Quote,
+{
+    QUOTES.with(|map| {
+        let mut map = map.borrow_mut();
+        let id = map.last_key_value().map_or(0, |(last_id, _)| last_id + 1);
+        let quote = build(id);
+        map.insert(id, quote.clone());
+        quote
+    })
+}
+
+pub fn get_quote(id: QuoteId) -> Option<Quote> {
+    QUOTES.with(|map| map.borrow().get(&id))
+}
+
+pub fn update_quote<F>(id: QuoteId, updater: F) -> Result<(), String>
+where
+    F: FnOnce(&mut Quote),
+{
+    QUOTES.with(|map| {
+        let mut map = map.borrow_mut();
+        let mut quote = map.get(&id).ok_or_else(|| format!("Quote {} not found", id))?;
+        updater(&mut quote);
+        map.insert(id, quote);
+        Ok(())
+    })
+}
+
+pub fn get_all_quotes() -> Vec<Quote> {
+    QUOTES.with(|map| map.borrow().iter().map(|(_, quote)| quote).collect())
+}
+
+pub fn remove_quote(id: QuoteId) {
+    QUOTES.with(|map| {
+        map.borrow_mut().remove(&id);
+    });
+}
+
+// ===== SCHEDULED TASKS =====
+
+pub fn get_scheduled_task(name: &str) -> Option<ScheduledTask> {
+    SCHEDULED_TASKS.with(|map| map.borrow().get(&name.to_string()))
+}
+
+pub fn insert_scheduled_task(name: &str, task: ScheduledTask) {
+    SCHEDULED_TASKS.with(|map| {
+        map.borrow_mut().insert(name.to_string(), task);
+    });
+}
+
+// ===== SCHEMA VERSION =====
+
+pub fn get_schema_state() -> SchemaState {
+    SCHEMA_STATE.with(|cell| cell.borrow().get().clone())
+}
+
+pub fn update_schema_state<F>(updater: F)
+where
+    F: FnOnce(&mut SchemaState),
+{
+    SCHEMA_STATE.with(|cell| {
+        let mut schema = cell.borrow().get().clone();
+        updater(&mut schema);
+        cell.borrow_mut().set(schema).expect("Failed to update schema state");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock;
+
+
+    #[test]
+    fn admin_events_filter_by_kind_and_time_with_cursor_pages() {
+        let start = get_time();
+        for round in 0..5u64 {
+            create_admin_event(AdminEventType::NewOrdersDisabled);
+            create_admin_event(AdminEventType::BlockInsertionError { block_height: round, error_message: "bad header".to_string() });
+            create_admin_event(AdminEventType::NewOrdersEnabled);
+            mock::advance_time(60 * 1_000_000_000);
+        }
+        let ids = |events: &[AdminEvent]| events.iter().map(|event| event.id).collect::<Vec<u64>>();
+        let kind_filter = |kinds: Vec<AdminEventKind>| AdminEventFilter { kinds: Some(kinds), ..Default::default() };
+
+        // Kinds, newest first, paged by cursor
+        let toggles = kind_filter(vec![AdminEventKind::NewOrdersEnabled, AdminEventKind::NewOrdersDisabled]);
+        let (first, cursor) = query_admin_events(&toggles, None, 4);
+        assert_eq!(ids(&first), vec![14, 12, 11, 9]);
+        let (rest, end) = query_admin_events(&toggles, cursor, 10);
+        assert_eq!(ids(&rest), vec![8, 6, 5, 3, 2, 0]);
+        assert_eq!(end, None);
+
+        // Time range (inclusive) - rounds 1 and 2
+        let rounds = AdminEventFilter {
+            kinds: Some(vec![AdminEventKind::BlockInsertionError]),
+            from_time: Some(start + 60 * 1_000_000_000),
+            to_time: Some(start + 120 * 1_000_000_000),
+        };
+        assert_eq!(ids(&query_admin_events(&rounds, None, 10).0), vec![7, 4]);
+        let (all_in_range, _) = query_admin_events(&AdminEventFilter { kinds: None, ..rounds.clone() }, None, 10);
+        assert_eq!(ids(&all_in_range), vec![8, 7, 6, 5, 4, 3]);
+        let future = AdminEventFilter { from_time: Some(get_time() + 1), ..Default::default() };
+        assert!(query_admin_events(&future, None, 10).0.is_empty());
+
+        // Trimming and restores keep the index in step with the log
+        remove_admin_event(14);
+        ADMIN_EVENT_INDEX.with(|index| index.borrow_mut().clear_new());
+        rebuild_admin_event_index();
+        assert_eq!(ids(&query_admin_events(&toggles, None, 2).0), vec![12, 11]);
+        assert_eq!(ADMIN_EVENT_INDEX.with(|index| index.borrow().len()), 14);
+    }
+}
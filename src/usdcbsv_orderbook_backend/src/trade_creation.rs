@@ -178,7 +178,6 @@ fn create_single_trade(
         agreed_bsv_price,
         min_bsv_price,
         status: TradeStatus::ChunksLocked,
-        bsv_tx_hex: None,
         created_at: now,
         tx_submitted_at: None,
         lock_expires_at: now + TRADE_TIMEOUT_NS,
@@ -5,9 +5,39 @@ use crate::bsv_parser;
 use crate::filler_accounts;
 use crate::ckusdc_integration; // For ckUSDC transfers
 use crate::bump_verification; // For SPV verification
+use crate::release_bonds;
+use crate::chain_sync;
 use crate::block_headers::CONFIRMATION_DEPTH;
-use crate::config::{SECURITY_DEPOSIT_PERCENT, USDC_RELEASE_WAIT_NS, TRADE_TIMEOUT_NS, SATOSHIS_PER_BSV, MAX_LOCK_MULTIPLIER, FILLER_INCENTIVE_PERCENT, TRADE_CLAIM_EXPIRY_NS, RESUBMISSION_PENALTY_PERCENT, RESUBMISSION_WINDOW_NS};
+use crate::config::{SECURITY_DEPOSIT_PERCENT, USDC_RELEASE_WAIT_NS, TRADE_TIMEOUT_NS, LOCK_EXTENSION_NS, LOCK_EXTENSION_FEE_PERCENT, SATOSHIS_PER_BSV, MIN_CHUNK_SIZE_E6, TRADE_CLAIM_EXPIRY_NS, RESUBMISSION_PENALTY_PERCENT, RESUBMISSION_WINDOW_NS, MAX_CLAIMS_PER_BATCH, MAX_TRADES_PER_PAYMENT, MIN_TX_FEE_RATE_SATS_PER_KB, MAX_FEE_CHECK_PARENT_TXS, SATS_TOLERANCE_ABSOLUTE, SATS_TOLERANCE_BASIS_POINTS, MAX_PAGE_LIMIT, MAX_CHUNK_RESERVATION_SECONDS, MAX_CONFIRMATION_DEPTH, INSTANT_RELEASE_CONFIRMATIONS};
+use crate::runtime::HeaderProvider;
 use candid::{CandidType, Deserialize, Principal};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+thread_local! {
+    // Trades with a claim in flight - a claim awaits the tip sync, SPV and bonding before it
+    // pays out, so a second claim of the same trade must not pass validation meanwhile
+    static TRADES_BEING_CLAIMED: RefCell<BTreeSet<TradeId>> = RefCell::new(BTreeSet::new());
+}
+
+/// Marks a trade as being claimed until dropped
+pub(crate) struct ClaimGuard(TradeId);
+
+impl ClaimGuard {
+    pub(crate) fn acquire(trade_id: TradeId) -> Result<Self, EasySwapError> {
+        if !TRADES_BEING_CLAIMED.with(|set| set.borrow_mut().insert(trade_id)) {
+            return Err(EasySwapError::invalid_state(format!("Trade {} is already being claimed", trade_id)));
+        }
+        Ok(ClaimGuard(trade_id))
+    }
+}
+
+impl Drop for ClaimGuard {
+    fn drop(&mut self) {
+        TRADES_BEING_CLAIMED.with(|set| set.borrow_mut().remove(&self.0));
+    }
+}
 
 /// Request structure for creating trades
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -15,263 +45,494 @@ pub struct CreateTradesRequest {
     pub requested_usd: f64,
     pub allow_partial: bool, // Kept for API compatibility but always treated as true
     pub min_bsv_price: f64,
+    // When true, this request matches under MatchingPolicy::BestIncentive - None/false uses
+    // the policy set by admin (get_matching_policy)
+    pub prefer_higher_incentive: Option<bool>,
+    pub book_id: Option<BookId>, // Book to match in - None = the default ckUSDC book
     // agreed_bsv_price removed - uses canister's current market price to prevent manipulation
     // filler_evm_address removed - ckUSDC transfers go to filler's IC principal
 }
 
-/// Create multiple trades, one per order, grouped by FIFO matching
+/// Create multiple trades, one per order, matched under the current MatchingPolicy
 /// NOTE: All trades are now partial by default - if orderbook has less than requested, we fill what's available
-pub async fn create_trades(request: CreateTradesRequest) -> Result<Vec<TradeId>, String> {
-    let caller = get_caller();
+/// `caller` is the filler, or the filler a delegation acts for (resolved in lib.rs, see delegations)
+pub async fn create_trades(caller: Principal, request: CreateTradesRequest) -> Result<Vec<TradeId>, EasySwapError> {
     let now = get_time();
     
     // Reject anonymous principal
     if caller == candid::Principal::anonymous() {
-        return Err("Anonymous principal cannot create trades. Please authenticate first.".to_string());
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot create trades. Please authenticate first."));
     }
+    filler_accounts::ensure_approved_filler(caller)?;
     
     // 1. Get current market price from canister (prevents frontend manipulation)
-    let agreed_bsv_price = crate::price_oracle::get_bsv_price().await?;
+    let agreed_bsv_price = crate::price_oracle::get_bsv_price().await.map_err(EasySwapError::unavailable)?;
+    
+    if crate::circuit_breaker::is_trading_halted(now) {
+        return Err(EasySwapError::unavailable(
+            "Trading is paused after an extreme BSV price move and resumes automatically once the price stabilizes"
+        ));
+    }
     
     ic_cdk::println!("📊 Creating trade with market price: ${}", agreed_bsv_price);
     
-    // 2. Get orderbook balance from Available chunks (always accurate)
-    let available = get_available_orderbook();
+    // 2. Get the book's balance from Available chunks (always accurate)
+    let book = crate::books::resolve(request.book_id)?;
+    let available_e6 = crate::books::available_e6(book.id);
+    let requested_e6 = usd_to_e6(request.requested_usd);
     
-    ic_cdk::println!("💰 Available orderbook: ${:.2}", available);
+    ic_cdk::println!("💰 Available orderbook: ${:.2}", e6_to_usd(available_e6));
     
     // Always allow partial fills - if requested amount > available, just use what's available
-    if request.requested_usd > available {
-        ic_cdk::println!("⚠️ Partial fill: Requested ${:.2}, available ${:.2}", request.requested_usd, available);
+    if requested_e6 > available_e6 {
+        ic_cdk::println!("⚠️ Partial fill: Requested ${:.2}, available ${:.2}", e6_to_usd(requested_e6), e6_to_usd(available_e6));
+    }
+    
+    // Reject dust requests up front
+    let size_limits = crate::books::trade_size_limits(&book);
+    if requested_e6 < size_limits.min_trade_usd_e6 {
+        return Err(EasySwapError::invalid_input(format!(
+            "Trade request must be at least ${}",
+            e6_to_usd(size_limits.min_trade_usd_e6)
+        )));
     }
     
     // 3. Validate prices
     if agreed_bsv_price <= 0.0 || request.min_bsv_price <= 0.0 {
-        return Err("BSV prices must be positive".to_string());
+        return Err(EasySwapError::invalid_input("BSV prices must be positive"));
     }
     
     if request.min_bsv_price > agreed_bsv_price {
-        return Err(format!(
+        return Err(EasySwapError::price_out_of_range(format!(
             "Minimum BSV price (${}) cannot exceed current market price (${})",
             request.min_bsv_price,
             agreed_bsv_price
-        ));
+        )));
+    }
+    
+    // 4. Check the filler's security deposit covers the new lock
+    check_filler_capacity(caller, requested_e6).await?;
+    
+    // 5. Find and create trades under the matching policy
+    let policy = if request.prefer_higher_incentive == Some(true) {
+        MatchingPolicy::BestIncentive
+    } else {
+        get_matching_policy()
+    };
+    ic_cdk::println!("🧮 Matching policy: {:?}", policy);
+    let trades = create_trades_from_chunks(&MatchRequest {
+        filler: caller,
+        book_id: book.id,
+        requested_e6,
+        allow_partial: request.allow_partial,
+        agreed_bsv_price,
+        min_bsv_price: request.min_bsv_price,
+        policy,
+        max_chunks: size_limits.max_chunks_per_trade as usize,
+    }, now)?;
+    
+    if trades.is_empty() {
+        return Err(EasySwapError::not_found("No matching chunks found"));
     }
     
-    // 4. Get live security balance from ckUSDC ledger
-    let security_balance = filler_accounts::get_security_balance_for_principal(caller).await?;
+    // 6. Calculate total locked
+    let total_locked_e6: UsdE6 = trades.iter()
+        .map(|&trade_id| get_trade(trade_id).unwrap().amount_usd_e6)
+        .sum();
+    
+    // 7. Update filler account stats (pending_trades_total calculated from active trades)
+    update_filler_account(caller, |account| {
+        account.total_trades += trades.len() as u64;
+    })?;
+    
+    ic_cdk::println!("✅ Created {} trades totaling ${}", trades.len(), e6_to_usd(total_locked_e6));
     
-    // Calculate required security deposit (5%)
-    let required_security = request.requested_usd * (SECURITY_DEPOSIT_PERCENT as f64 / 100.0);
+    Ok(trades)
+}
+
+/// Check the filler's live security balance covers their deposit percent of a new lock of
+/// requested_e6 and stays within their lock multiplier of their pending trades - trusted fillers
+/// trade on a reduced deposit (see filler_accounts::security_terms)
+/// Creates the filler account on first use
+pub(crate) async fn check_filler_capacity(filler: Principal, requested_e6: UsdE6) -> Result<(), EasySwapError> {
+    // Live security: ckUSDC plus haircut collateral, from their ledgers, less release bonds
+    let security_balance = filler_accounts::get_security_value_e6(filler).await.map_err(EasySwapError::ledger)?
+        .saturating_sub(release_bonds::bonded_e6(filler));
     
     // Create account record only when submitting first trade
-    filler_accounts::create_account_if_needed(caller);
+    filler_accounts::create_account_if_needed(filler);
+    
+    // Calculate required security deposit
+    let (security_deposit_percent, lock_multiplier) = filler_accounts::security_terms(filler);
+    let required_security_e6 = requested_e6 * security_deposit_percent / 100;
     
-    let filler_account = get_filler_account(caller)
+    let filler_account = get_filler_account(filler)
         .ok_or_else(|| "Failed to create filler account".to_string())?;
     
-    // Convert security balance from USDC units to USD
-    let security_balance_usd = security_balance as f64 / 1_000_000.0;
+    // Security value is in micro-USD
+    let security_balance_e6: UsdE6 = security_balance;
     
     // Check if filler has enough security balance
-    if security_balance_usd < required_security {
-        return Err(format!(
+    if security_balance_e6 < required_security_e6 {
+        return Err(EasySwapError::insufficient_funds(format!(
             "Insufficient security deposit. Required: ${}, Available: ${}",
-            required_security,
-            security_balance_usd
-        ));
+            e6_to_usd(required_security_e6),
+            e6_to_usd(security_balance_e6)
+        )));
     }
     
-    // Check 5% security deposit allows locking up to 20x
-    let max_allowed = security_balance_usd * (MAX_LOCK_MULTIPLIER as f64);
-    let total_pending = filler_account.pending_trades_total + request.requested_usd;
+    // The deposit allows locking up to lock_multiplier times the security balance
+    let max_allowed_e6 = security_balance_e6 * lock_multiplier;
+    let total_pending_e6 = usd_to_e6(filler_account.pending_trades_total) + requested_e6;
     
-    if total_pending > max_allowed {
-        return Err(format!(
+    if total_pending_e6 > max_allowed_e6 {
+        return Err(EasySwapError::limit_exceeded(format!(
             "Exceeds maximum lock capacity. Max allowed: ${}, Would be: ${}",
-            max_allowed,
-            total_pending
-        ));
+            e6_to_usd(max_allowed_e6),
+            e6_to_usd(total_pending_e6)
+        )));
     }
     
-    // 5. Find and create trades using new FIFO logic
-    let trades = create_trades_from_chunks(
-        caller,
-        request.requested_usd,
-        request.allow_partial,
-        agreed_bsv_price,
-        request.min_bsv_price,
-        now,
-    )?;
-    
-    if trades.is_empty() {
-        return Err("No matching chunks found".to_string());
+    // Exposure caps apply however large the deposit is
+    let limits = get_exposure_limits();
+    if total_pending_e6 > limits.max_filler_open_interest_e6 {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "Exceeds per-filler open interest limit. Max: ${}, Would be: ${}",
+            e6_to_usd(limits.max_filler_open_interest_e6),
+            e6_to_usd(total_pending_e6)
+        )));
     }
     
-    // 6. Calculate total locked
-    let total_locked: f64 = trades.iter()
-        .map(|&trade_id| get_trade(trade_id).unwrap().amount_usd)
-        .sum();
+    let total_locked_e6 = chunk_allocation::get_total_locked_e6() + requested_e6;
+    if total_locked_e6 > limits.max_total_locked_e6 {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "Orderbook-wide lock limit reached. Max: ${}, Would be: ${}",
+            e6_to_usd(limits.max_total_locked_e6),
+            e6_to_usd(total_locked_e6)
+        )));
+    }
     
-    // 7. Update filler account stats (pending_trades_total calculated from active trades)
-    update_filler_account(caller, |account| {
-        account.total_trades += trades.len() as u64;
-    })?;
+    Ok(())
+}
+
+pub fn validate_exposure_limits(limits: &ExposureLimits) -> Result<(), EasySwapError> {
+    if limits.max_filler_open_interest_e6 == 0 || limits.max_total_locked_e6 == 0 {
+        return Err(EasySwapError::invalid_input("Exposure limits must be positive"));
+    }
     
-    ic_cdk::println!("✅ Created {} trades totaling ${}", trades.len(), total_locked);
+    if limits.max_filler_open_interest_e6 > limits.max_total_locked_e6 {
+        return Err(EasySwapError::invalid_input("Per-filler open interest limit cannot exceed the total lock limit"));
+    }
     
-    Ok(trades)
+    Ok(())
 }
 
-/// Create trades from chunks using FIFO matching with proper partial filling logic
-/// This function iterates through chunks in FIFO order and creates trades as we go
-fn create_trades_from_chunks(
-    filler: Principal,
-    requested_usd: f64,
-    allow_partial: bool,
-    agreed_bsv_price: f64,
-    min_bsv_price: f64,
-    now: u64,
-) -> Result<Vec<TradeId>, String> {
-    let mut trade_ids = Vec::new();
-    let mut total_filled = 0.0;
+/// Validate and store new exposure caps
+pub fn set_exposure_limits(limits: ExposureLimits) -> Result<(), EasySwapError> {
+    validate_exposure_limits(&limits)?;
+    crate::state::set_exposure_limits(limits);
+    Ok(())
+}
+
+/// Validate and store new trade size limits
+pub fn set_trade_size_limits(limits: TradeSizeLimits) -> Result<(), EasySwapError> {
+    validate_trade_size_limits(&limits)?;
+    crate::state::set_trade_size_limits(limits);
+    Ok(())
+}
+
+/// Checks shared by the global limits and each book's own (see books.rs)
+pub(crate) fn validate_trade_size_limits(limits: &TradeSizeLimits) -> Result<(), EasySwapError> {
+    if limits.max_chunks_per_trade == 0 {
+        return Err(EasySwapError::invalid_input("A trade must be allowed at least one chunk"));
+    }
     
-    // Get all active orders sorted by creation time (FIFO) - optimized to filter at storage level
-    let orders = crate::state::get_active_orders_fifo();
+    // Otherwise no request could pass both limits
+    if limits.min_trade_usd_e6 > limits.max_chunks_per_trade * MIN_CHUNK_SIZE_E6 {
+        return Err(EasySwapError::invalid_input(format!(
+            "Minimum trade size cannot exceed {} chunks of ${}",
+            limits.max_chunks_per_trade, e6_to_usd(MIN_CHUNK_SIZE_E6)
+        )));
+    }
     
-    // Current trade being built
-    let mut current_order_id: Option<OrderId> = None;
-    let mut current_chunks: Vec<Chunk> = Vec::new();
+    Ok(())
+}
+
+/// Validate and store a new timeout penalty schedule - tiers ascend in both lateness and penalty,
+/// and no penalty can exceed the security deposit that backs it
+pub fn set_timeout_penalty_schedule(schedule: TimeoutPenaltySchedule) -> Result<(), EasySwapError> {
+    if schedule.final_penalty_bps > SECURITY_DEPOSIT_PERCENT * 100 {
+        return Err(EasySwapError::invalid_input(format!(
+            "Timeout penalty cannot exceed the {}% security deposit", SECURITY_DEPOSIT_PERCENT
+        )));
+    }
     
-    // Iterate through orders in FIFO order
-    for order in orders {
-        // Check if we've already filled the requested amount
-        if total_filled >= requested_usd {
-            break;
+    let mut previous: Option<&TimeoutPenaltyTier> = None;
+    for tier in &schedule.tiers {
+        if tier.expired_under_ns == 0 {
+            return Err(EasySwapError::invalid_input("Penalty tiers need a non-zero expiry window"));
         }
-        
-        // Check if order accepts this price
-        if order.max_bsv_price < agreed_bsv_price {
-            continue; // Skip orders that don't accept current market price
-        }
-        
-        // Iterate through chunks in this order
-        for chunk_id in &order.chunks {
-            // Check if we've already filled the requested amount
-            if total_filled >= requested_usd {
-                break;
-            }
-            
-            if let Some(chunk) = get_chunk(*chunk_id) {
-                // Only consider Available chunks
-                if chunk.status != ChunkStatus::Available {
-                    continue;
-                }
-                
-                // Calculate how much more we need
-                let remaining = requested_usd - total_filled;
-                
-                // Check if chunk + total_filled <= requested_usd
-                if chunk.amount_usd <= remaining {
-                    // This chunk fits!
-                    
-                    // If this is a different order, finalize previous trade first
-                    if let Some(prev_order_id) = current_order_id {
-                        if prev_order_id != order.id && !current_chunks.is_empty() {
-                            // Create trade for previous order
-                            let trade_id = create_single_trade(
-                                filler,
-                                prev_order_id,
-                                current_chunks.clone(),
-                                agreed_bsv_price,
-                                min_bsv_price,
-                                now,
-                            )?;
-                            trade_ids.push(trade_id);
-                            
-                            // Reset for new order
-                            current_chunks.clear();
-                        }
-                    }
-                    
-                    // Add chunk to current trade
-                    current_order_id = Some(order.id);
-                    total_filled += chunk.amount_usd;
-                    current_chunks.push(chunk);
-                    
-                    // Check if we've exactly filled the request
-                    if total_filled >= requested_usd {
-                        break;
-                    }
-                }
-                // If chunk is larger than remaining, skip it (can't partially use a chunk)
+        if let Some(previous) = previous {
+            if tier.expired_under_ns <= previous.expired_under_ns || tier.penalty_bps < previous.penalty_bps {
+                return Err(EasySwapError::invalid_input("Penalty tiers must be ordered by expiry window with non-decreasing penalties"));
             }
         }
+        previous = Some(tier);
+    }
+    if previous.is_some_and(|last| last.penalty_bps > schedule.final_penalty_bps) {
+        return Err(EasySwapError::invalid_input("Final timeout penalty cannot be below the last tier"));
+    }
+    
+    crate::state::set_timeout_penalty_schedule(schedule);
+    Ok(())
+}
+
+/// Validate and store how price improvements are split between maker and filler
+pub fn set_price_improvement_sharing(sharing: PriceImprovementSharing) -> Result<(), EasySwapError> {
+    if sharing.threshold_bps >= 10_000 || sharing.filler_share_bps > 10_000 {
+        return Err(EasySwapError::invalid_input("Threshold must be below 100% and the filler share at most 100%"));
     }
     
-    // Finalize last trade if we have chunks
-    if !current_chunks.is_empty() {
-        if let Some(order_id) = current_order_id {
-            let trade_id = create_single_trade(
-                filler,
-                order_id,
-                current_chunks,
-                agreed_bsv_price,
-                min_bsv_price,
-                now,
-            )?;
-            trade_ids.push(trade_id);
+    crate::state::set_price_improvement_sharing(sharing);
+    Ok(())
+}
+
+/// Penalty owed on an expired trade unlocked at `now`, per the timeout penalty schedule
+pub fn timeout_penalty_usd(trade: &Trade, now: u64) -> f64 {
+    let penalty_bps = get_timeout_penalty_schedule().penalty_bps(now.saturating_sub(trade.lock_expires_at));
+    e6_to_usd(trade.amount_usd_e6 * penalty_bps / 10_000)
+}
+
+/// A create_trades call once its price and capacity checks have passed - what the book is matched against
+struct MatchRequest {
+    filler: Principal,
+    book_id: BookId,
+    requested_e6: UsdE6,
+    allow_partial: bool,
+    agreed_bsv_price: f64,
+    min_bsv_price: f64,
+    policy: MatchingPolicy,
+    max_chunks: usize,               // Per order matched - the book's max_chunks_per_trade
+}
+
+/// Create trades from the orders accepting the market price, picking chunks under `policy`
+/// (see chunk_allocation::select_chunks) - one trade per order matched, of at most max_chunks chunks
+fn create_trades_from_chunks(request: &MatchRequest, now: u64) -> Result<Vec<TradeId>, String> {
+    let MatchRequest {
+        filler,
+        book_id,
+        requested_e6,
+        allow_partial,
+        agreed_bsv_price,
+        min_bsv_price,
+        policy,
+        max_chunks,
+    } = *request;
+    let (reserved, open) = split_reserved(matchable_candidates(filler, book_id, agreed_bsv_price), filler, now);
+    
+    // Chunks the filler reserved with reserve_chunks are matched first, the rest of the
+    // book under the policy - with each order still capped at max_chunks overall
+    let mut picks = chunk_allocation::select_chunks(MatchingPolicy::Fifo, reserved, requested_e6, max_chunks);
+    let reserved_e6: UsdE6 = picks.iter()
+        .flat_map(|(_, chunks)| chunks)
+        .map(|chunk| chunk.amount_usd_e6)
+        .sum();
+    if reserved_e6 < requested_e6 {
+        let open: Candidates = open.into_iter()
+            .map(|(order, mut chunks)| {
+                let already = picks.iter()
+                    .find(|(order_id, _)| *order_id == order.id)
+                    .map_or(0, |(_, taken)| taken.len());
+                chunks.truncate(max_chunks.saturating_sub(already));
+                (order, chunks)
+            })
+            .filter(|(_, chunks)| !chunks.is_empty())
+            .collect();
+        for (order_id, chunks) in chunk_allocation::select_chunks(policy, open, requested_e6 - reserved_e6, max_chunks) {
+            match picks.iter_mut().find(|(picked, _)| *picked == order_id) {
+                Some((_, taken)) => taken.extend(chunks),
+                None => picks.push((order_id, chunks)),
+            }
         }
     }
+    let total_filled_e6: UsdE6 = picks.iter()
+        .flat_map(|(_, chunks)| chunks)
+        .map(|chunk| chunk.amount_usd_e6)
+        .sum();
     
     // Check if we filled enough based on allow_partial
-    if !allow_partial && total_filled < requested_usd {
-        // Return error before committing - trades are built in memory and not persisted yet
+    if !allow_partial && total_filled_e6 < requested_e6 {
+        // Return error before committing - nothing has been locked yet
         return Err(format!(
             "Cannot fill complete order. Requested: ${}, Available: ${}. Set allow_partial=true to proceed.",
-            requested_usd,
-            total_filled
+            e6_to_usd(requested_e6),
+            e6_to_usd(total_filled_e6)
         ));
     }
     
     // If allow_partial=true and we couldn't find any chunks
-    if trade_ids.is_empty() {
+    if picks.is_empty() {
         return Err("No matching chunks available at current market price".to_string());
     }
     
+    let sharing = crate::state::get_price_improvement_sharing();
+    let mut trade_ids = Vec::with_capacity(picks.len());
+    for (order_id, chunks) in picks {
+        let effective_bsv_price = get_order(order_id)
+            .and_then(|order| sharing.effective_bsv_price(agreed_bsv_price, order.max_bsv_price));
+        trade_ids.push(create_single_trade(
+            filler,
+            order_id,
+            chunks,
+            agreed_bsv_price,
+            min_bsv_price,
+            effective_bsv_price,
+            now,
+        )?);
+    }
+    
     Ok(trade_ids)
 }
 
+/// Orders a fill may take from, each with the chunks it may take
+type Candidates = Vec<(Order, Vec<Chunk>)>;
+
+/// Orders of the book the filler may match at agreed_bsv_price, oldest first, each with its Available chunks
+fn matchable_candidates(filler: Principal, book_id: BookId, agreed_bsv_price: f64) -> Candidates {
+    // Get all active orders sorted by creation time (FIFO) - optimized to filter at storage level
+    crate::state::get_active_orders_fifo().into_iter()
+        .filter(|order| order.book_id() == book_id)
+        // Skip orders that don't accept current market price
+        .filter(|order| order.max_bsv_price >= agreed_bsv_price)
+        // Skip makers who have blocked this filler
+        .filter(|order| !crate::filler_blocklist::is_filler_blocked(order.maker, filler))
+        .map(|order| {
+            let chunks: Vec<Chunk> = order.chunks.iter()
+                .filter_map(|chunk_id| get_chunk(*chunk_id))
+                .filter(|chunk| chunk.status == ChunkStatus::Available)
+                .collect();
+            (order, chunks)
+        })
+        .filter(|(_, chunks)| !chunks.is_empty())
+        .collect()
+}
+
+/// Split candidates into the chunks reserved for `filler` and the unreserved ones - chunks
+/// other fillers hold live reservations on are dropped
+fn split_reserved(candidates: Candidates, filler: Principal, now: u64) -> (Candidates, Candidates) {
+    let mut reserved = Vec::new();
+    let mut open = Vec::new();
+    for (order, chunks) in candidates {
+        let (own, rest): (Vec<Chunk>, Vec<Chunk>) = chunks.into_iter()
+            .filter(|chunk| chunk.reservation_holder(now).is_none_or(|holder| holder == filler))
+            .partition(|chunk| chunk.reservation_holder(now) == Some(filler));
+        if !own.is_empty() {
+            reserved.push((order.clone(), own));
+        }
+        if !rest.is_empty() {
+            open.push((order, rest));
+        }
+    }
+    (reserved, open)
+}
+
+/// Soft-reserve up to amount_usd of matchable chunks for the caller for ttl_secs, so a fill
+/// quoted in the UI is still there when create_trades runs
+/// Chunks stay Available - other fillers' matching skips them until the reservation lapses,
+/// and a new reservation replaces the caller's previous one
+/// `caller` is the filler, or the filler a delegation acts for, as in create_trades
+pub async fn reserve_chunks(caller: Principal, amount_usd: f64, ttl_secs: u64, book_id: Option<BookId>) -> Result<ChunkReservation, EasySwapError> {
+    if caller == candid::Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot reserve chunks. Please authenticate first."));
+    }
+    filler_accounts::ensure_approved_filler(caller)?;
+    
+    if ttl_secs == 0 || ttl_secs > MAX_CHUNK_RESERVATION_SECONDS {
+        return Err(EasySwapError::invalid_input(format!(
+            "Reservation TTL must be between 1 and {} seconds",
+            MAX_CHUNK_RESERVATION_SECONDS
+        )));
+    }
+    
+    let book = crate::books::resolve(book_id)?;
+    let requested_e6 = usd_to_e6(amount_usd);
+    let size_limits = crate::books::trade_size_limits(&book);
+    if requested_e6 < size_limits.min_trade_usd_e6 {
+        return Err(EasySwapError::invalid_input(format!(
+            "Reservation must be at least ${}",
+            e6_to_usd(size_limits.min_trade_usd_e6)
+        )));
+    }
+    
+    let agreed_bsv_price = crate::price_oracle::get_bsv_price().await.map_err(EasySwapError::unavailable)?;
+    if crate::circuit_breaker::is_trading_halted(get_time()) {
+        return Err(EasySwapError::unavailable(
+            "Trading is paused after an extreme BSV price move and resumes automatically once the price stabilizes"
+        ));
+    }
+    
+    // Only reserve what the filler's deposit could actually lock
+    check_filler_capacity(caller, requested_e6).await?;
+    
+    // Re-read after the awaits - the book may have changed meanwhile
+    let now = get_time();
+    chunk_allocation::release_reservations_of(caller);
+    let (_, open) = split_reserved(matchable_candidates(caller, book.id, agreed_bsv_price), caller, now);
+    let picks = chunk_allocation::select_chunks(
+        get_matching_policy(),
+        open,
+        requested_e6,
+        size_limits.max_chunks_per_trade as usize,
+    );
+    
+    let chunks: Vec<Chunk> = picks.into_iter().flat_map(|(_, chunks)| chunks).collect();
+    if chunks.is_empty() {
+        return Err(EasySwapError::not_found("No matching chunks available to reserve"));
+    }
+    
+    let chunk_ids: Vec<ChunkId> = chunks.iter().map(|chunk| chunk.id).collect();
+    let amount_usd_e6: UsdE6 = chunks.iter().map(|chunk| chunk.amount_usd_e6).sum();
+    let expires_at = now + ttl_secs * 1_000_000_000;
+    chunk_allocation::reserve_chunks_for(&chunk_ids, caller, expires_at)?;
+    
+    ic_cdk::println!("🔖 {} reserved {} chunks (${}) for {}s", caller, chunk_ids.len(), e6_to_usd(amount_usd_e6), ttl_secs);
+    
+    Ok(ChunkReservation { chunk_ids, amount_usd_e6, expires_at })
+}
+
 /// Create a single trade from one order's chunks
-fn create_single_trade(
+/// Sats are calculated at `effective_bsv_price` when a price improvement is shared, else at agreed_bsv_price
+pub(crate) fn create_single_trade(
     filler: Principal,
     order_id: OrderId,
     chunks: Vec<Chunk>,
     agreed_bsv_price: f64,
     min_bsv_price: f64,
+    effective_bsv_price: Option<f64>,
     now: u64,
 ) -> Result<TradeId, String> {
     let trade_id = create_trade_id();
     
-    let amount_usd: f64 = chunks.iter().map(|c| c.amount_usd).sum();
+    let amount_usd_e6: UsdE6 = chunks.iter().map(|c| c.amount_usd_e6).sum();
     let chunk_ids: Vec<ChunkId> = chunks.iter().map(|c| c.id).collect();
     
     // Lock the chunks (this also decrements orderbook balance)
     chunk_allocation::lock_chunks_for_trade(&chunk_ids, trade_id)?;
     
     // Build locked chunks with all details
+    let sats_bsv_price = effective_bsv_price.unwrap_or(agreed_bsv_price);
     let locked_chunks: Vec<LockedChunk> = chunks.iter().map(|chunk| {
-        // Calculate sats_amount based on the agreed BSV price at trade time
-        // chunk.amount_usd is already in USD (e.g., 1.0 = $1)
-        let bsv_amount = chunk.amount_usd / agreed_bsv_price;
+        // Calculate sats_amount based on the agreed (or shared improvement) BSV price at trade time
+        // chunk.amount_usd_e6 is in micro-USD (e.g., 1_000_000 = $1)
+        let bsv_amount = e6_to_usd(chunk.amount_usd_e6) / sats_bsv_price;
         let sats_amount = (bsv_amount * SATOSHIS_PER_BSV as f64) as u64;
         
         LockedChunk {
             chunk_id: chunk.id,
             order_id: chunk.order_id,
-            amount_usd: chunk.amount_usd,
+            amount_usd_e6: chunk.amount_usd_e6,
             bsv_address: chunk.bsv_address.clone(),
-            sats_amount,  // Calculated based on agreed_bsv_price
+            sats_amount,  // Calculated based on sats_bsv_price
         }
     }).collect();
     
@@ -279,17 +540,26 @@ fn create_single_trade(
         id: trade_id,
         order_id,
         filler,
-        amount_usd,
+        amount_usd_e6,
         locked_chunks,
         agreed_bsv_price,
         min_bsv_price,
+        effective_bsv_price,
         status: TradeStatus::ChunksLocked,
-        bsv_tx_hex: None,
+        bsv_txid: None,
+        payment_group: None,
+        bsv_block_height: None,
         created_at: now,
         tx_submitted_at: None,
         lock_expires_at: now + TRADE_TIMEOUT_NS,
+        lock_extended_at: None,
         release_available_at: None,
         claim_expires_at: None,
+        required_confirmations: None,
+        sats_tolerance: Some(SatsTolerance {
+            absolute_sats: SATS_TOLERANCE_ABSOLUTE,
+            basis_points: SATS_TOLERANCE_BASIS_POINTS,
+        }),
         withdrawal_initiated_at: None,
         withdrawal_tx_hash: None,
         withdrawal_confirmed_at: None,
@@ -300,136 +570,358 @@ fn create_single_trade(
     Ok(trade_id)
 }
 
-pub async fn submit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) -> Result<(), String> {
-    let caller = get_caller();
+/// `caller` is the filler, or the filler an operator acts for (resolved in lib.rs, see delegations)
+pub async fn submit_bsv_transaction(caller: Principal, trade_id: TradeId, raw_tx_hex: String) -> Result<(), EasySwapError> {
     let now = get_time();
     
     // Reject anonymous principal
     if caller == candid::Principal::anonymous() {
-        return Err("Anonymous principal cannot submit transactions. Please authenticate first.".to_string());
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot submit transactions. Please authenticate first."));
     }
     
     let trade = get_trade(trade_id)
-        .ok_or_else(|| "Trade not found".to_string())?;
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
     
     // Verify caller is the filler
     if trade.filler != caller {
-        return Err("Only the trade filler can submit transaction".to_string());
+        return Err(EasySwapError::unauthorized("Only the trade filler can submit transaction"));
     }
     
     // Verify trade status
     if trade.status != TradeStatus::ChunksLocked {
-        return Err("Trade is not in ChunksLocked status".to_string());
+        return Err(EasySwapError::invalid_state("Trade is not in ChunksLocked status"));
     }
     
     // Check if lock has expired - NO SUBMISSION ALLOWED after expiry
     if now > trade.lock_expires_at {
-        return Err("Trade lock has expired. Submission no longer allowed. Penalty will be applied automatically.".to_string());
+        return Err(EasySwapError::invalid_state("Trade lock has expired. Submission no longer allowed. Penalty will be applied automatically."));
     }
     
     // Compute transaction ID to check for duplicates
-    let txid = compute_bsv_txid(&raw_tx_hex)?;
+    let txid = compute_bsv_txid(&raw_tx_hex).map_err(EasySwapError::invalid_input)?;
     
     // Check if this transaction has been used in another trade
     if let Some(other_trade_id) = get_trade_using_tx(&txid) {
         if other_trade_id != trade_id {
-            return Err(format!(
+            return Err(EasySwapError::invalid_state(format!(
                 "This transaction has already been used in trade #{}. Each transaction can only be used once.",
                 other_trade_id
-            ));
+            )));
         }
     }
     
     // Parse BSV transaction
-    let parsed_tx = bsv_parser::parse_bsv_transaction(&raw_tx_hex)?;
+    let parsed_tx = bsv_parser::parse_bsv_transaction(&raw_tx_hex).map_err(EasySwapError::invalid_input)?;
     
     // Validate outputs match locked chunks
-    bsv_parser::validate_transaction_outputs(&parsed_tx, &trade.locked_chunks)?;
+    bsv_parser::validate_transaction_outputs(&parsed_tx, &[&trade]).map_err(EasySwapError::invalid_input)?;
+    
+    // Reject transactions unlikely to confirm before the claim window closes
+    check_transaction_fee_rate(&parsed_tx).await?;
+    
+    // Re-check after the await - the lock may have expired or the tx been used meanwhile
+    let now = get_time();
+    let trade = locked_trades_for_payment(caller, &[trade_id], now)?.remove(0);
+    if let Some(other_trade_id) = get_trade_using_tx(&txid).filter(|&other| other != trade_id) {
+        return Err(EasySwapError::invalid_state(format!(
+            "This transaction has already been used in trade #{}. Each transaction can only be used once.",
+            other_trade_id
+        )));
+    }
     
     // Mark transaction as used by this trade
-    mark_bsv_tx_used(txid, trade_id);
+    mark_bsv_tx_used(txid.clone(), trade_id);
     
     // Update trade
     let release_time = now + USDC_RELEASE_WAIT_NS;
     let claim_expiry = now + TRADE_CLAIM_EXPIRY_NS;
+    let required_confirmations = required_confirmations_for_trade(&trade);
     
+    retain_tx_blob(&txid, raw_tx_hex);
     update_trade(trade_id, |trade| {
         trade.status = TradeStatus::TxSubmitted;
-        trade.bsv_tx_hex = Some(raw_tx_hex);
+        trade.bsv_txid = Some(txid);
         trade.tx_submitted_at = Some(now);
         trade.release_available_at = Some(release_time);
         trade.claim_expires_at = Some(claim_expiry);
+        trade.required_confirmations = Some(required_confirmations);
     })?;
     
     Ok(())
 }
 
+/// Caller's ChunksLocked, unexpired trades among trade_ids - sorted by id, duplicates dropped
+fn locked_trades_for_payment(caller: Principal, trade_ids: &[TradeId], now: u64) -> Result<Vec<Trade>, EasySwapError> {
+    let mut trade_ids = trade_ids.to_vec();
+    trade_ids.sort_unstable();
+    trade_ids.dedup();
+    
+    if trade_ids.is_empty() {
+        return Err(EasySwapError::invalid_input("No trades given"));
+    }
+    if trade_ids.len() > MAX_TRADES_PER_PAYMENT {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "A payment can cover at most {} trades",
+            MAX_TRADES_PER_PAYMENT
+        )));
+    }
+    
+    trade_ids.into_iter().map(|trade_id| {
+        let trade = get_trade(trade_id)
+            .ok_or_else(|| EasySwapError::not_found(format!("Trade {} not found", trade_id)))?;
+        if trade.filler != caller {
+            return Err(EasySwapError::unauthorized(format!("Trade {} belongs to another filler", trade_id)));
+        }
+        if trade.status != TradeStatus::ChunksLocked {
+            return Err(EasySwapError::invalid_state(format!("Trade {} is not in ChunksLocked status", trade_id)));
+        }
+        if now > trade.lock_expires_at {
+            return Err(EasySwapError::invalid_state(format!("Trade {} lock has expired", trade_id)));
+        }
+        Ok(trade)
+    }).collect()
+}
+
+/// Outputs the filler's payment must start with to pay the given locked trades in one
+/// transaction, totaled per maker address - exact sats, so wallets don't re-derive them from prices
+pub fn get_payment_template(trade_ids: Vec<TradeId>) -> Result<PaymentTemplate, EasySwapError> {
+    let trades = locked_trades_for_payment(get_caller(), &trade_ids, get_time())?;
+    let trade_refs: Vec<&Trade> = trades.iter().collect();
+    
+    let outputs = bsv_parser::payment_template_outputs(&trade_refs);
+    Ok(PaymentTemplate {
+        trade_ids: trades.iter().map(|t| t.id).collect(),
+        total_sats: outputs.iter().map(|o| o.sats).sum(),
+        outputs,
+        expires_at: trades.iter().map(|t| t.lock_expires_at).min().unwrap_or_default(),
+    })
+}
+
+/// Submit one BSV transaction paying several locked trades, laid out as in get_payment_template
+/// (or one output per chunk, trades in id order). Every trade records the txid and the group;
+/// each is then claimed on its own with the same transaction
+pub async fn submit_bsv_transaction_for_trades(caller: Principal, trade_ids: Vec<TradeId>, raw_tx_hex: String) -> Result<(), EasySwapError> {
+    
+    // Reject anonymous principal
+    if caller == candid::Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot submit transactions. Please authenticate first."));
+    }
+    
+    let trades = locked_trades_for_payment(caller, &trade_ids, get_time())?;
+    
+    let txid = compute_bsv_txid(&raw_tx_hex).map_err(EasySwapError::invalid_input)?;
+    if let Some(other_trade_id) = get_trade_using_tx(&txid) {
+        return Err(EasySwapError::invalid_state(format!(
+            "This transaction has already been used in trade #{}. Each transaction can only be used once.",
+            other_trade_id
+        )));
+    }
+    
+    let parsed_tx = bsv_parser::parse_bsv_transaction(&raw_tx_hex).map_err(EasySwapError::invalid_input)?;
+    let trade_refs: Vec<&Trade> = trades.iter().collect();
+    bsv_parser::validate_transaction_outputs(&parsed_tx, &trade_refs).map_err(EasySwapError::invalid_input)?;
+    
+    check_transaction_fee_rate(&parsed_tx).await?;
+    
+    // Re-check after the await - a lock may have expired or the tx been used meanwhile
+    let now = get_time();
+    let trades = locked_trades_for_payment(caller, &trade_ids, now)?;
+    if let Some(other_trade_id) = get_trade_using_tx(&txid) {
+        return Err(EasySwapError::invalid_state(format!(
+            "This transaction has already been used in trade #{}. Each transaction can only be used once.",
+            other_trade_id
+        )));
+    }
+    
+    let group: Vec<TradeId> = trades.iter().map(|t| t.id).collect();
+    mark_bsv_tx_used(txid.clone(), group[0]);
+    
+    for trade in &trades {
+        retain_tx_blob(&txid, raw_tx_hex.clone());
+        let required_confirmations = required_confirmations_for_trade(trade);
+        update_trade(trade.id, |trade| {
+            trade.status = TradeStatus::TxSubmitted;
+            trade.bsv_txid = Some(txid.clone());
+            trade.payment_group = Some(group.clone());
+            trade.tx_submitted_at = Some(now);
+            trade.release_available_at = Some(now + USDC_RELEASE_WAIT_NS);
+            trade.claim_expires_at = Some(now + TRADE_CLAIM_EXPIRY_NS);
+            trade.required_confirmations = Some(required_confirmations);
+        })?;
+    }
+    
+    ic_cdk::println!("📤 Transaction {} submitted for trades {:?}", txid, group);
+    
+    Ok(())
+}
+
+/// Look up the values of the outputs spent by the transaction and validate its fee rate
+/// The check is skipped (not failed) when parent transactions can't be fetched, so a
+/// provider outage doesn't push fillers into lock expiry
+async fn check_transaction_fee_rate(parsed_tx: &ParsedBsvTx) -> Result<(), EasySwapError> {
+    let mut parent_txids: Vec<String> = parsed_tx.inputs.iter()
+        .map(|input| hex::encode(&input.prev_tx_hash))
+        .collect();
+    parent_txids.sort();
+    parent_txids.dedup();
+    
+    if parent_txids.len() > MAX_FEE_CHECK_PARENT_TXS {
+        ic_cdk::println!("⚠️ Skipping fee rate check: {} parent transactions", parent_txids.len());
+        return Ok(());
+    }
+    
+    let mut parents = std::collections::BTreeMap::new();
+    for txid in parent_txids {
+        let parent = match crate::runtime::headers().raw_tx(&txid).await
+            .and_then(|raw_hex| bsv_parser::parse_bsv_transaction(&raw_hex))
+        {
+            Ok(parent) => parent,
+            Err(e) => {
+                ic_cdk::println!("⚠️ Skipping fee rate check: could not load parent tx {}: {}", txid, e);
+                return Ok(());
+            }
+        };
+        parents.insert(txid, parent);
+    }
+    
+    let mut input_values = Vec::with_capacity(parsed_tx.inputs.len());
+    for input in &parsed_tx.inputs {
+        let parent = &parents[&hex::encode(&input.prev_tx_hash)];
+        let spent = parent.outputs.get(input.prev_output_index as usize)
+            .ok_or_else(|| EasySwapError::invalid_input(format!(
+                "Input spends output {} of {} which does not exist",
+                input.prev_output_index,
+                hex::encode(&input.prev_tx_hash)
+            )))?;
+        input_values.push(spent.satoshis);
+    }
+    
+    bsv_parser::validate_fee_rate(parsed_tx, &input_values, MIN_TX_FEE_RATE_SATS_PER_KB)
+        .map_err(EasySwapError::invalid_input)
+}
+
+/// Confirmation depth that applies to a trade submitted now, by its size
+/// Recorded on the trade at submission so later config changes don't affect in-flight trades
+pub fn required_confirmations_for_trade(trade: &Trade) -> u64 {
+    get_confirmation_depth_schedule().confirmations_for(trade.amount_usd_e6)
+}
+
+/// Confirmation depth claim_usdc holds a trade to (trades submitted before it was recorded use the default)
+pub(crate) fn claim_confirmations(trade: &Trade) -> u64 {
+    trade.required_confirmations.unwrap_or(CONFIRMATION_DEPTH)
+}
+
+/// Validate and store a new confirmation depth schedule - larger trades never need fewer
+/// confirmations than smaller ones
+pub fn set_confirmation_depth_schedule(schedule: ConfirmationDepthSchedule) -> Result<(), EasySwapError> {
+    let in_range = |confirmations: u64| (1..=MAX_CONFIRMATION_DEPTH).contains(&confirmations);
+    if !in_range(schedule.default_confirmations) || !schedule.tiers.iter().all(|tier| in_range(tier.confirmations)) {
+        return Err(EasySwapError::invalid_input(format!(
+            "Confirmation depths must be between 1 and {} blocks", MAX_CONFIRMATION_DEPTH
+        )));
+    }
+    
+    let mut previous: Option<&ConfirmationDepthTier> = None;
+    for tier in &schedule.tiers {
+        if tier.trade_under_usd_e6 == 0 {
+            return Err(EasySwapError::invalid_input("Confirmation tiers need a non-zero trade size"));
+        }
+        if let Some(previous) = previous {
+            if tier.trade_under_usd_e6 <= previous.trade_under_usd_e6 || tier.confirmations < previous.confirmations {
+                return Err(EasySwapError::invalid_input("Confirmation tiers must be ordered by trade size with non-decreasing depths"));
+            }
+        }
+        previous = Some(tier);
+    }
+    if previous.is_some_and(|last| last.confirmations > schedule.default_confirmations) {
+        return Err(EasySwapError::invalid_input("Default confirmation depth cannot be below the last tier"));
+    }
+    
+    crate::state::set_confirmation_depth_schedule(schedule);
+    Ok(())
+}
+
 /// Allow trader to resubmit/edit BSV transaction within first hours of INITIAL submission
-pub async fn resubmit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) -> Result<(), String> {
-    let caller = get_caller();
+pub async fn resubmit_bsv_transaction(caller: Principal, trade_id: TradeId, raw_tx_hex: String) -> Result<(), EasySwapError> {
     let now = get_time();
     
     // Reject anonymous principal
     if caller == candid::Principal::anonymous() {
-        return Err("Anonymous principal cannot resubmit transactions. Please authenticate first.".to_string());
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot resubmit transactions. Please authenticate first."));
     }
     
     let trade = get_trade(trade_id)
-        .ok_or_else(|| "Trade not found".to_string())?;
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
     
     // Verify caller is the filler
     if trade.filler != caller {
-        return Err("Only the trade filler can resubmit transaction".to_string());
+        return Err(EasySwapError::unauthorized("Only the trade filler can resubmit transaction"));
     }
     
     // Verify trade status - must be TxSubmitted
     if trade.status != TradeStatus::TxSubmitted {
-        return Err("Trade is not in TxSubmitted status. Cannot resubmit.".to_string());
+        return Err(EasySwapError::invalid_state("Trade is not in TxSubmitted status. Cannot resubmit."));
+    }
+    
+    if is_trade_on_hold(trade_id) {
+        return Err(EasySwapError::invalid_state("This trade is on hold due to a maker dispute pending admin review"));
+    }
+    
+    // The other trades of a shared payment would be left pointing at the old transaction
+    if let Some(group) = &trade.payment_group {
+        return Err(EasySwapError::invalid_state(format!(
+            "This trade was paid together with trades {:?} - a shared payment can't be resubmitted",
+            group
+        )));
     }
     
     // Check if still within 3-hour resubmission window from INITIAL submission
     let initial_submission_time = trade.tx_submitted_at
-        .ok_or_else(|| "Transaction submission time not found".to_string())?;
+        .ok_or_else(|| EasySwapError::not_found("Transaction submission time not found"))?;
     
     if now > initial_submission_time + RESUBMISSION_WINDOW_NS {
-        return Err("Resubmission window expired. You can only resubmit within hours of initial submission.".to_string());
+        return Err(EasySwapError::invalid_state("Resubmission window expired. You can only resubmit within hours of initial submission."));
     }
     
     // Calculate 1% resubmission penalty (of trade amount, not security deposit)
-    let penalty_amount = trade.amount_usd * (RESUBMISSION_PENALTY_PERCENT / 100.0);
+    let penalty_e6 = bps_of(trade.amount_usd_e6, RESUBMISSION_PENALTY_PERCENT);
+    let penalty_amount = e6_to_usd(penalty_e6);
     
     // Get filler's AVAILABLE security balance (total - locked in other trades)
     let available_balance_usd = filler_accounts::get_available_security_balance(caller).await
-        .map_err(|e| format!("Failed to check available security balance: {}", e))?;
+        .map_err(|e| EasySwapError::ledger(format!("Failed to check available security balance: {}", e)))?;
     
     // Check if filler has enough AVAILABLE balance to cover penalty
-    if available_balance_usd < penalty_amount {
-        return Err(format!(
+    if usd_to_e6(available_balance_usd) < penalty_e6 {
+        return Err(EasySwapError::insufficient_funds(format!(
             "Insufficient available security balance. Need ${:.2} for 1% resubmission penalty (1% of ${:.2} trade), but only have ${:.2} available. Deposit more security or close some trades.",
             penalty_amount,
-            trade.amount_usd,
+            e6_to_usd(trade.amount_usd_e6),
             available_balance_usd
-        ));
+        )));
     }
     
     // Compute new transaction ID
-    let new_txid = compute_bsv_txid(&raw_tx_hex)?;
+    let new_txid = compute_bsv_txid(&raw_tx_hex).map_err(EasySwapError::invalid_input)?;
     
     // Check if this NEW transaction has been used in another trade
     if let Some(other_trade_id) = get_trade_using_tx(&new_txid) {
         if other_trade_id != trade_id {
-            return Err(format!(
+            return Err(EasySwapError::invalid_state(format!(
                 "This transaction has already been used in trade #{}. Each transaction can only be used once.",
                 other_trade_id
-            ));
+            )));
         }
     }
     
     // Parse BSV transaction
-    let parsed_tx = bsv_parser::parse_bsv_transaction(&raw_tx_hex)?;
+    let parsed_tx = bsv_parser::parse_bsv_transaction(&raw_tx_hex).map_err(EasySwapError::invalid_input)?;
     
     // Validate outputs match locked chunks (same validation as initial submission)
-    bsv_parser::validate_transaction_outputs(&parsed_tx, &trade.locked_chunks)?;
+    bsv_parser::validate_transaction_outputs(&parsed_tx, &[&trade]).map_err(EasySwapError::invalid_input)?;
+    
+    // Reject transactions unlikely to confirm before the claim window closes
+    check_transaction_fee_rate(&parsed_tx).await?;
     
     ic_cdk::println!("🔄 Resubmitting BSV transaction for trade {} with 1% penalty (${:.2})", trade_id, penalty_amount);
     
@@ -443,17 +935,24 @@ pub async fn resubmit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) ->
         penalty_amount,
         recipient,
         Some(format!("Resubmit penalty T{}", trade_id)),
-    ).await?;
+    ).await.map_err(EasySwapError::ledger)?;
+    
+    // Another resubmission may have swapped the transaction during the penalty transfer -
+    // release the one the trade holds now, not the one read before it
+    let trade = get_trade(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
+    
+    // Mark new transaction as used by this trade
+    // Retained before the old one is released, so resubmitting the same tx keeps its blob
+    retain_tx_blob(&new_txid, raw_tx_hex);
     
     // If trade already has a previous tx, unmark it
-    if let Some(old_tx_hex) = &trade.bsv_tx_hex {
-        if let Ok(old_txid) = compute_bsv_txid(old_tx_hex) {
-            unmark_bsv_tx(&old_txid);
-        }
+    if let Some(old_txid) = &trade.bsv_txid {
+        unmark_bsv_tx(old_txid);
+        release_tx_blob(old_txid);
     }
     
-    // Mark new transaction as used by this trade
-    mark_bsv_tx_used(new_txid, trade_id);
+    mark_bsv_tx_used(new_txid.clone(), trade_id);
     
     // Reset claim timer: Add 3 more hours from NOW
     // NOTE: We do NOT extend claim_expires_at - trader still has 24h from initial submission to claim
@@ -462,7 +961,7 @@ pub async fn resubmit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) ->
     // Update transaction hex and reset claim availability timer
     // Keep original tx_submitted_at (for resubmission window) and claim_expires_at (still 24h limit)
     update_trade(trade_id, |trade| {
-        trade.bsv_tx_hex = Some(raw_tx_hex);
+        trade.bsv_txid = Some(new_txid);
         trade.release_available_at = Some(new_release_time);
         // claim_expires_at stays unchanged - 24h limit from initial submission
     })?;
@@ -470,141 +969,425 @@ pub async fn resubmit_bsv_transaction(trade_id: TradeId, raw_tx_hex: String) ->
     Ok(())
 }
 
-pub async fn claim_usdc(trade_id: TradeId, tx_hex: String, bump_hex: String) -> Result<(), String> {
+/// Push a locked trade's expiry back by LOCK_EXTENSION_NS, once per trade, for
+/// LOCK_EXTENSION_FEE_PERCENT of the trade amount paid to the maker from the filler's security deposit
+/// Returns the new lock_expires_at
+pub async fn extend_trade_lock(trade_id: TradeId) -> Result<u64, EasySwapError> {
     let caller = get_caller();
     let now = get_time();
     
     // Reject anonymous principal
     if caller == candid::Principal::anonymous() {
-        return Err("Anonymous principal cannot claim USDC. Please authenticate first.".to_string());
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot extend trade locks. Please authenticate first."));
     }
     
     let trade = get_trade(trade_id)
-        .ok_or_else(|| "Trade not found".to_string())?;
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
+    
+    if trade.filler != caller {
+        return Err(EasySwapError::unauthorized("Only the trade filler can extend the lock"));
+    }
+    if trade.status != TradeStatus::ChunksLocked {
+        return Err(EasySwapError::invalid_state("Trade is not in ChunksLocked status"));
+    }
+    if now > trade.lock_expires_at {
+        return Err(EasySwapError::invalid_state("Trade lock has already expired"));
+    }
+    if trade.lock_extended_at.is_some() {
+        return Err(EasySwapError::invalid_state("Trade lock has already been extended once"));
+    }
+    
+    let fee_e6 = bps_of(trade.amount_usd_e6, LOCK_EXTENSION_FEE_PERCENT);
+    let available_balance_usd = filler_accounts::get_available_security_balance(caller).await
+        .map_err(|e| EasySwapError::ledger(format!("Failed to check available security balance: {}", e)))?;
+    if usd_to_e6(available_balance_usd) < fee_e6 {
+        return Err(EasySwapError::insufficient_funds(format!(
+            "Insufficient available security balance. Need ${:.2} for the lock extension fee, but only have ${:.2} available.",
+            e6_to_usd(fee_e6),
+            available_balance_usd
+        )));
+    }
+    
+    // Extend before charging, so the heartbeat can't time the trade out while the fee is in
+    // flight and a concurrent call can't extend it twice - rolled back if the fee fails
+    let now = get_time();
+    let extended_until = {
+        let trade = get_trade(trade_id)
+            .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
+        if trade.status != TradeStatus::ChunksLocked || now > trade.lock_expires_at || trade.lock_extended_at.is_some() {
+            return Err(EasySwapError::invalid_state("Trade changed while the extension was being checked - please retry"));
+        }
+        trade.lock_expires_at + LOCK_EXTENSION_NS
+    };
+    update_trade(trade_id, |trade| {
+        trade.lock_expires_at = extended_until;
+        trade.lock_extended_at = Some(now);
+    })?;
+    
+    let maker = crate::state::get_order(trade.order_id).map(|o| o.maker);
+    let charged = filler_accounts::transfer_penalty(
+        caller,
+        maker,
+        filler_accounts::penalty_transfer_amount_e6(e6_to_usd(fee_e6)),
+        filler_accounts::penalty_memo(Some(format!("Lock extension T{}", trade_id))),
+    ).await;
+    
+    if let Err(e) = charged {
+        update_trade(trade_id, |t| {
+            if t.lock_extended_at == Some(now) {
+                t.lock_expires_at = trade.lock_expires_at;
+                t.lock_extended_at = None;
+            }
+        })?;
+        return Err(EasySwapError::ledger(format!("Failed to charge the lock extension fee: {}", e)));
+    }
+    
+    ic_cdk::println!("⏳ Trade {} lock extended to {} for ${:.6}", trade_id, extended_until, e6_to_usd(fee_e6));
+    
+    Ok(extended_until)
+}
+
+/// `caller` is the filler, or the filler an operator acts for - the ckUSDC always goes to trade.filler
+/// Fillers opted in to instant release claim small trades after INSTANT_RELEASE_CONFIRMATIONS,
+/// bonding the released amount until the payment is buried (see release_bonds.rs)
+pub async fn claim_usdc(caller: Principal, trade_id: TradeId, tx_hex: String, bump_hex: String) -> Result<(), EasySwapError> {
+    let now = get_time();
+    
+    filler_accounts::ensure_approved_filler(caller)?;
+    
+    let mut trade = validate_claim(caller, trade_id, &tx_hex, now)?;
+    let _claiming = ClaimGuard::acquire(trade_id)?;
+    chain_sync::ensure_fresh_tip().await?;
+    let full_confirmations = claim_confirmations(&trade);
+    let required_confirmations = if release_bonds::is_eligible(&trade) {
+        INSTANT_RELEASE_CONFIRMATIONS.min(full_confirmations)
+    } else {
+        full_confirmations
+    };
+    
+    // ===== SPV VERIFICATION - NEW REQUIREMENT =====
+    // Verify the BSV transaction is included in a confirmed block before releasing USDC
+    // Uses TxArchive fallback if local block storage has gaps
+    ic_cdk::println!("🔍 Verifying BSV transaction with SPV for trade {}", trade_id);
+    let verification = bump_verification::verify_tx_raw_async(&tx_hex, &bump_hex, required_confirmations).await;
+    let verification = check_claim_verification(verification, required_confirmations)?;
+    trade.bsv_block_height = Some(verification.block_height);
+    // ===== END SPV VERIFICATION =====
+    
+    // A dispute may have been flagged while verification was in flight
+    if is_trade_on_hold(trade_id) {
+        return Err(EasySwapError::invalid_state("This trade is on hold due to a maker dispute pending admin review"));
+    }
+    
+    // Get order to extract maker for subaccount
+    let order = get_order(trade.order_id)
+        .ok_or_else(|| EasySwapError::not_found("Order not found"))?;
+    
+    // Released before the full depth - bond what the maker stands to lose to a reorg
+    let bonded = verification.confirmations < full_confirmations;
+    if bonded {
+        let released_e6 = trade.amount_usd_e6 + bps_of(trade.amount_usd_e6, order.filler_incentive_bps());
+        release_bonds::post_bond(&trade, order.maker, released_e6, verification.block_height, verification.block_hash).await?;
+    }
+    
+    let released = release_claimed_trades(caller, &order, vec![trade], now).await;
+    if bonded && released.is_err() {
+        remove_release_bond(trade_id);
+    }
+    released
+}
+
+/// Claim several trades in one call - returns one result per claim, in order
+/// SPV verification runs once per distinct transaction, and trades from the same order
+/// are paid out in a single ledger transfer. Failed claims don't affect the others
+/// Every trade needs its full confirmation depth here - instant release is claim_usdc only
+pub async fn claim_usdc_batch(caller: Principal, claims: Vec<(TradeId, String, String)>) -> Result<Vec<Result<(), EasySwapError>>, EasySwapError> {
+    let now = get_time();
+    
+    filler_accounts::ensure_approved_filler(caller)?;
+    
+    if claims.is_empty() {
+        return Err(EasySwapError::invalid_input("Batch contains no claims"));
+    }
+    if claims.len() > MAX_CLAIMS_PER_BATCH {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "Batch contains {} claims, maximum is {}",
+            claims.len(), MAX_CLAIMS_PER_BATCH
+        )));
+    }
+    
+    chain_sync::ensure_fresh_tip().await?;
+    
+    let mut results: Vec<Result<(), EasySwapError>> = Vec::with_capacity(claims.len());
+    // Keyed by (txid, required confirmations) - trades sharing a payment may be held to different depths
+    let mut verifications: BTreeMap<(String, u64), Result<bump_verification::TxVerification, String>> = BTreeMap::new();
+    // order_id -> (result index, trade) of claims that passed verification
+    let mut ready: BTreeMap<OrderId, Vec<(usize, Trade)>> = BTreeMap::new();
+    
+    let mut seen_trades: Vec<TradeId> = Vec::new();
+    let mut _claiming: Vec<ClaimGuard> = Vec::new();
+    
+    for (index, (trade_id, tx_hex, bump_hex)) in claims.into_iter().enumerate() {
+        results.push(Ok(()));
+        
+        if seen_trades.contains(&trade_id) {
+            results[index] = Err(EasySwapError::invalid_input(format!("Trade {} appears more than once in the batch", trade_id)));
+            continue;
+        }
+        seen_trades.push(trade_id);
+        
+        let mut trade = match validate_claim(caller, trade_id, &tx_hex, now) {
+            Ok(trade) => trade,
+            Err(e) => {
+                results[index] = Err(e);
+                continue;
+            }
+        };
+        match ClaimGuard::acquire(trade_id) {
+            Ok(guard) => _claiming.push(guard),
+            Err(e) => {
+                results[index] = Err(e);
+                continue;
+            }
+        }
+        
+        let txid = compute_bsv_txid(&tx_hex).unwrap_or_else(|_| tx_hex.to_lowercase());
+        let key = (txid, claim_confirmations(&trade));
+        if !verifications.contains_key(&key) {
+            ic_cdk::println!("🔍 Verifying BSV transaction {} with SPV (batch)", key.0);
+            let verification = bump_verification::verify_tx_raw_async(&tx_hex, &bump_hex, key.1).await;
+            verifications.insert(key.clone(), verification);
+        }
+        
+        match check_claim_verification(verifications[&key].clone(), key.1) {
+            Ok(verification) => trade.bsv_block_height = Some(verification.block_height),
+            Err(e) => {
+                results[index] = Err(e);
+                continue;
+            }
+        }
+        
+        ready.entry(trade.order_id).or_default().push((index, trade));
+    }
+    
+    for (order_id, entries) in ready {
+        // A dispute may have been flagged while verification was in flight
+        let (held, entries): (Vec<_>, Vec<_>) = entries.into_iter()
+            .partition(|(_, trade)| is_trade_on_hold(trade.id));
+        for (index, _) in held {
+            results[index] = Err(EasySwapError::invalid_state("This trade is on hold due to a maker dispute pending admin review"));
+        }
+        if entries.is_empty() {
+            continue;
+        }
+        
+        let (indices, trades): (Vec<usize>, Vec<Trade>) = entries.into_iter().unzip();
+        let outcome = match get_order(order_id) {
+            Some(order) => release_claimed_trades(caller, &order, trades, now).await,
+            None => Err(EasySwapError::not_found("Order not found")),
+        };
+        for index in indices {
+            results[index] = outcome.clone();
+        }
+    }
+    
+    Ok(results)
+}
+
+/// Checks before SPV verification: ownership, status, timing, and that the tx still
+/// pays the locked chunks at the trade's prices
+fn validate_claim(caller: Principal, trade_id: TradeId, tx_hex: &str, now: u64) -> Result<Trade, EasySwapError> {
+    // Reject anonymous principal
+    if caller == candid::Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot claim USDC. Please authenticate first."));
+    }
+    
+    let trade = get_trade(trade_id)
+        .ok_or_else(|| EasySwapError::not_found("Trade not found"))?;
     
     // Verify caller is the filler
     if trade.filler != caller {
-        return Err("Only the trade filler can claim USDC".to_string());
+        return Err(EasySwapError::unauthorized("Only the trade filler can claim USDC"));
     }
     
     // Verify trade status
     if trade.status != TradeStatus::TxSubmitted && trade.status != TradeStatus::ReadyForRelease {
-        return Err("Trade is not ready for USDC release".to_string());
+        return Err(EasySwapError::invalid_state("Trade is not ready for USDC release"));
+    }
+    
+    // Disputed trades are on hold until admin resolves the dispute
+    if is_trade_on_hold(trade_id) {
+        return Err(EasySwapError::invalid_state("This trade is on hold due to a maker dispute pending admin review"));
     }
     
-    // CRITICAL: Verify submitted tx_hex matches the one stored in the trade
-    let stored_tx_hex = trade.bsv_tx_hex.as_ref()
-        .ok_or_else(|| "No BSV transaction submitted for this trade".to_string())?;
+    // CRITICAL: Verify submitted tx_hex is the transaction stored for the trade
+    // The txid is the hash of the raw bytes, so equal txids mean identical transactions
+    let stored_txid = trade.bsv_txid.as_ref()
+        .ok_or_else(|| EasySwapError::invalid_state("No BSV transaction submitted for this trade"))?;
     
-    if tx_hex.to_lowercase() != stored_tx_hex.to_lowercase() {
-        return Err("Transaction hex does not match the submitted transaction for this trade".to_string());
+    if compute_bsv_txid(tx_hex).ok().as_ref() != Some(stored_txid) {
+        return Err(EasySwapError::invalid_state("Transaction hex does not match the submitted transaction for this trade"));
     }
     
     // Check if release time has passed
     if let Some(release_time) = trade.release_available_at {
         if now < release_time {
-            return Err(format!(
+            return Err(EasySwapError::invalid_state(format!(
                 "USDC release available in {} minutes",
                 (release_time - now) / 60_000_000_000
-            ));
+            )));
         }
     } else {
-        return Err("Release time not set".to_string());
+        return Err(EasySwapError::invalid_state("Release time not set"));
     }
     
     // Check if claim has expired (24 hours passed)
     if let Some(claim_expiry) = trade.claim_expires_at {
         if now > claim_expiry {
-            return Err("This trade was not claimed within 24 hours. The ckUSDC has been sent to treasury. Please contact support if you believe this was an error.".to_string());
+            return Err(EasySwapError::invalid_state("This trade was not claimed within 24 hours, so its ckUSDC goes to treasury. Once reclaimed, a confirmed payment can still be claimed with late_claim_review."));
         }
     }
     
-    // ===== SPV VERIFICATION - NEW REQUIREMENT =====
-    // Verify the BSV transaction is included in a confirmed block before releasing USDC
-    // Uses TxArchive fallback if local block storage has gaps
-    ic_cdk::println!("🔍 Verifying BSV transaction with SPV for trade {}", trade_id);
+    check_claim_outputs(&trade, tx_hex)?;
     
-    let verification = match bump_verification::verify_tx_raw_async(&tx_hex, &bump_hex).await {
+    Ok(trade)
+}
+
+/// Re-check the outputs against the trade's agreed and minimum prices - the tx was only
+/// validated at submission, against sats amounts derived from the agreed price
+/// A shared payment is checked against every trade it paid, as at submission
+pub(crate) fn check_claim_outputs(trade: &Trade, tx_hex: &str) -> Result<(), EasySwapError> {
+    let group: Vec<Trade> = match &trade.payment_group {
+        Some(trade_ids) => trade_ids.iter()
+            .map(|id| get_trade(*id).ok_or_else(|| EasySwapError::not_found(format!("Trade {} paid by this transaction no longer exists", id))))
+            .collect::<Result<_, _>>()?,
+        None => vec![trade.clone()],
+    };
+    let group: Vec<&Trade> = group.iter().collect();
+    let parsed_tx = bsv_parser::parse_bsv_transaction(tx_hex).map_err(EasySwapError::invalid_input)?;
+    let maker_outputs = bsv_parser::validate_transaction_outputs(&parsed_tx, &group)
+        .map_err(EasySwapError::invalid_input)?;
+    bsv_parser::validate_price_paid(&parsed_tx, &group, maker_outputs)
+        .map_err(EasySwapError::price_out_of_range)
+}
+
+/// Check an SPV verification result against the confirmations the claim needs
+pub(crate) fn check_claim_verification(
+    verification: Result<bump_verification::TxVerification, String>,
+    required_confirmations: u64,
+) -> Result<bump_verification::TxVerification, EasySwapError> {
+    let verification = match verification {
         Ok(v) => v,
         Err(e) => {
             ic_cdk::println!("❌ SPV verification failed: {}", e);
-            return Err(format!("Transaction verification failed: {}", e));
+            return Err(EasySwapError::spv_failed(format!("Transaction verification failed: {}", e)));
         }
     };
     
     if !verification.verified {
         ic_cdk::println!("❌ Transaction not verified: {}", verification.message);
-        return Err(format!("Transaction not verified: {}", verification.message));
+        return Err(EasySwapError::spv_failed(format!("Transaction not verified: {}", verification.message)));
     }
     
-    if verification.confirmations < CONFIRMATION_DEPTH {
+    if verification.confirmations < required_confirmations {
         ic_cdk::println!("❌ Insufficient confirmations: {} (need {})", 
-            verification.confirmations, CONFIRMATION_DEPTH);
-        return Err(format!(
+            verification.confirmations, required_confirmations);
+        return Err(EasySwapError::spv_failed(format!(
             "Insufficient confirmations: {} blocks (need {} blocks). Please wait for more confirmations.",
-            verification.confirmations, CONFIRMATION_DEPTH
-        ));
+            verification.confirmations, required_confirmations
+        )));
     }
     
     ic_cdk::println!("✅ Transaction verified at block {} (hash: {}) with {} confirmations", 
         verification.block_height, verification.block_hash, verification.confirmations);
-    // ===== END SPV VERIFICATION =====
+    
+    Ok(verification)
+}
+
+/// Pay out verified trades of one order in a single ckUSDC transfer and mark them complete
+async fn release_claimed_trades(caller: Principal, order: &Order, trades: Vec<Trade>, now: u64) -> Result<(), EasySwapError> {
+    // Validated before the awaits - make sure none was paid out or cancelled since
+    for trade in &trades {
+        let current = get_trade(trade.id).map(|t| t.status);
+        if current != Some(TradeStatus::TxSubmitted) && current != Some(TradeStatus::ReadyForRelease) {
+            return Err(EasySwapError::invalid_state(format!("Trade {} is no longer ready for USDC release", trade.id)));
+        }
+    }
     
     // Transfer ckUSDC to filler from order's subaccount
-    // Filler receives chunk amount + incentive % (from config)
-    let incentive_multiplier = 1.0 + (FILLER_INCENTIVE_PERCENT as f64 / 10000.0);
-    let total_to_send = trade.amount_usd * incentive_multiplier;
-    let total_to_send_e6 = ckusdc_integration::usd_to_ckusdc_e6(total_to_send);
-    
-    let incentive_percent = FILLER_INCENTIVE_PERCENT as f64 / 100.0;
-    ic_cdk::println!("💰 Claiming USDC for trade {}", trade_id);
-    ic_cdk::println!("  Base amount: ${:.6}", trade.amount_usd);
+    // Filler receives chunk amount + the order's incentive %
+    let incentive_bps = order.filler_incentive_bps();
+    let total_to_send_e6: u128 = trades.iter()
+        .map(|t| (t.amount_usd_e6 + bps_of(t.amount_usd_e6, incentive_bps)) as u128)
+        .sum();
+    
+    let incentive_percent = incentive_bps as f64 / 100.0;
+    for trade in &trades {
+        ic_cdk::println!("💰 Claiming USDC for trade {}", trade.id);
+        ic_cdk::println!("  Base amount: ${:.6}", e6_to_usd(trade.amount_usd_e6));
+    }
     ic_cdk::println!("  With {:.1}% incentive: {} e6 (${:.6})", incentive_percent, total_to_send_e6, ckusdc_integration::ckusdc_e6_to_usd(total_to_send_e6));
     
-    // Get order to extract maker for subaccount
-    let order = get_order(trade.order_id)
-        .ok_or_else(|| "Order not found".to_string())?;
+    let trade_ids: Vec<TradeId> = trades.iter().map(|t| t.id).collect();
+    let memo = claim_memo(&trade_ids);
     
     // Transfer ckUSDC to filler from order's subaccount
     // ckUSDC transfers on ICP are instant (ICRC-1 ledger)
     // transfer_ckusdc_from_order automatically deducts the transfer fee
-    // Compute txid for memo if available
-    let txid = match compute_bsv_txid(&stored_tx_hex) {
-        Ok(id) => id,
-        Err(_) => format!("trade_{}", trade_id),
-    };
-
     let block_index = ckusdc_integration::transfer_ckusdc_from_order(
         order.maker,
-        trade.order_id,
-        trade.filler,
+        order.id,
+        caller,
         None, // Filler's default subaccount
         total_to_send_e6,
-        Some(format!("Claim T{}", trade_id).into_bytes()),
-    ).await?;
-    
-    // Record block index and mark as confirmed (instant on ICP)
-    update_trade(trade_id, |trade| {
-        trade.withdrawal_tx_hash = Some(format!("{}", block_index));
-        trade.withdrawal_confirmed_at = Some(now);
-        trade.withdrawal_initiated_at = Some(now);
-        trade.status = TradeStatus::WithdrawalConfirmed;
-    })?;
-    
-    // Mark chunks as filled (autonomous heartbeat will confirm withdrawal later)
-    let chunk_ids: Vec<ChunkId> = trade.locked_chunks.iter()
-        .map(|lc| lc.chunk_id)
-        .collect();
-    chunk_allocation::mark_chunks_filled(&chunk_ids)?;
+        Some(memo.into_bytes()),
+    ).await.map_err(EasySwapError::ledger)?;
+    
+    for trade in &trades {
+        // Record block index and mark as confirmed (instant on ICP)
+        update_trade(trade.id, |t| {
+            t.bsv_block_height = trade.bsv_block_height;
+            t.withdrawal_tx_hash = Some(format!("{}", block_index));
+            t.withdrawal_confirmed_at = Some(now);
+            t.withdrawal_initiated_at = Some(now);
+            t.status = TradeStatus::WithdrawalConfirmed;
+        })?;
+        
+        // Mark chunks as filled (autonomous heartbeat will confirm withdrawal later)
+        let chunk_ids: Vec<ChunkId> = trade.locked_chunks.iter()
+            .map(|lc| lc.chunk_id)
+            .collect();
+        chunk_allocation::mark_chunks_filled(&chunk_ids)?;
+        crate::market_stats::record_completed_trade(trade, order.maker, now);
+    }
     
     // Update filler account stats (pending_trades_total calculated from active trades)
     update_filler_account(caller, |account| {
-        account.successful_trades += 1;
+        account.successful_trades += trades.len() as u64;
     })?;
     
     Ok(())
 }
 
+/// Transfer memo of a claim payout - unique per set of trades, so the dedup window only
+/// collapses retries of the same payout
+/// ICRC-1 memos are capped at 32 bytes, so grouped claims carry a hash of their sorted trade IDs
+fn claim_memo(trade_ids: &[TradeId]) -> String {
+    match trade_ids {
+        [trade_id] => format!("Claim T{}", trade_id),
+        _ => {
+            let mut trade_ids = trade_ids.to_vec();
+            trade_ids.sort_unstable();
+            let mut hasher = Sha256::new();
+            for trade_id in &trade_ids {
+                hasher.update(trade_id.to_be_bytes());
+            }
+            // "Claim B" + 24 hex chars = 31 bytes
+            format!("Claim B{}", &hex::encode(hasher.finalize())[..24])
+        }
+    }
+}
+
 async fn apply_penalty_and_cancel(trade_id: TradeId) -> Result<(), String> {
     let trade = get_trade(trade_id)
         .ok_or_else(|| "Trade not found".to_string())?;
@@ -613,7 +1396,7 @@ async fn apply_penalty_and_cancel(trade_id: TradeId) -> Result<(), String> {
     let order = get_order(trade.order_id)
         .ok_or_else(|| format!("Order {} not found for trade {}", trade.order_id, trade_id))?;
     
-    let penalty_amount = trade.amount_usd * (SECURITY_DEPOSIT_PERCENT as f64 / 100.0);
+    let penalty_amount = timeout_penalty_usd(&trade, get_time());
     
     // Deduct penalty from filler account and send to order maker
     filler_accounts::deduct_penalty(
@@ -645,41 +1428,33 @@ pub fn get_my_trades() -> Vec<Trade> {
     get_trades_by_filler(caller)
 }
 
-pub fn get_my_trades_paginated(offset: u64, limit: u64, status_filter: Option<Vec<TradeStatus>>) -> PaginatedTrades {
+/// Cursor page of the caller's trades, newest first
+pub fn get_my_trades_page(after_id: Option<TradeId>, limit: u64, status_filter: Option<Vec<TradeStatus>>) -> TradePage {
     let caller = get_caller();
+    let limit = limit.min(MAX_PAGE_LIMIT) as usize;
     
-    // Filter at storage level to avoid loading unnecessary trades
-    let filtered_trades: Vec<Trade> = crate::state::TRADES.with(|trades| {
-        let mut results: Vec<Trade> = trades.borrow().iter()
-            .filter(|(_, trade)| {
-                // Must be owned by caller
-                if trade.filler != caller {
-                    return false;
-                }
-                
-                // Apply status filter if provided
-                if let Some(ref statuses) = status_filter {
-                    statuses.contains(&trade.status)
-                } else {
-                    true // No filter, include all
-                }
-            })
-            .map(|(_, trade)| trade)
-            .collect();
-        
-        // Sort by created_at descending (newest first)
-        results.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-        results
+    let (trades, next_cursor) = TRADES.with(|trades| {
+        scan_page(&trades.borrow(), after_id, limit, true, |trade| {
+            trade.filler == caller && status_filter.as_ref().is_none_or(|statuses| statuses.contains(&trade.status))
+        })
     });
     
-    let total = filtered_trades.len() as u64;
+    TradePage { trades, next_cursor }
+}
+
+/// Offset wrapper over the same newest-first scan as get_my_trades_page
+pub fn get_my_trades_paginated(offset: u64, limit: u64, status_filter: Option<Vec<TradeStatus>>) -> PaginatedTrades {
+    let caller = get_caller();
+    let keep = |trade: &Trade| {
+        trade.filler == caller && status_filter.as_ref().is_none_or(|statuses| statuses.contains(&trade.status))
+    };
     
-    let start = offset as usize;
-    let _end = (offset + limit) as usize;
-    let trades: Vec<Trade> = filtered_trades.into_iter()
-        .skip(start)
-        .take(limit as usize)
-        .collect();
+    let (total, trades) = TRADES.with(|trades| {
+        let map = trades.borrow();
+        let total = map.iter().filter(|(_, trade)| keep(trade)).count() as u64;
+        let (page, _) = scan_page(&map, None, offset.saturating_add(limit) as usize, true, keep);
+        (total, page.into_iter().skip(offset as usize).collect())
+    });
     
     PaginatedTrades {
         trades,
@@ -693,8 +1468,114 @@ pub fn get_trade(trade_id: TradeId) -> Option<Trade> {
     crate::state::get_trade(trade_id)
 }
 
-fn is_valid_evm_address(address: &str) -> bool {
-    address.len() == 42 && 
-    address.starts_with("0x") && 
-    address[2..].chars().all(|c| c.is_ascii_hexdigit())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+
+    fn maker() -> Principal {
+        Principal::from_slice(&[1, 1, 1])
+    }
+
+    fn filler(n: u8) -> Principal {
+        Principal::from_slice(&[2, 2, n])
+    }
+
+    fn fill(filler: Principal, requested_usd: f64) -> Result<Vec<TradeId>, EasySwapError> {
+        mock::set_caller(filler);
+        block_on(create_trades(filler, CreateTradesRequest {
+            requested_usd,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: None,
+            book_id: None,
+        }))
+    }
+
+    #[test]
+    fn exposure_caps_bind_however_large_the_deposit() {
+        assert!(set_exposure_limits(ExposureLimits { max_filler_open_interest_e6: 0, max_total_locked_e6: 9_000_000 }).is_err());
+        assert!(set_exposure_limits(ExposureLimits { max_filler_open_interest_e6: 10_000_000, max_total_locked_e6: 9_000_000 }).is_err());
+        set_exposure_limits(ExposureLimits { max_filler_open_interest_e6: 6_000_000, max_total_locked_e6: 9_000_000 }).unwrap();
+
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(filler_accounts::get_deposit_account(maker()), 20_000_000);
+        mock::set_caller(maker());
+        block_on(crate::order_management::create_order(12.0, 100.0, crate::lifecycle_tests::p2pkh_address(&[0x11; 20]), None)).unwrap();
+        for n in 1..=2 {
+            mock::mint(filler_accounts::get_deposit_account(filler(n)), 50_000_000);
+        }
+
+        // Per filler: $6 open, then nothing more
+        assert!(matches!(fill(filler(1), 9.0), Err(EasySwapError::LimitExceeded { .. })));
+        fill(filler(1), 6.0).unwrap();
+        assert!(matches!(fill(filler(1), 3.0), Err(EasySwapError::LimitExceeded { .. })));
+
+        // Book-wide: $9 locked across everyone
+        assert!(matches!(fill(filler(2), 6.0), Err(EasySwapError::LimitExceeded { .. })));
+        fill(filler(2), 3.0).unwrap();
+        assert_eq!(chunk_allocation::get_total_locked_e6(), 9_000_000);
+    }
+
+    #[test]
+    fn trade_size_limits_reject_dust_and_cap_chunks() {
+        let limits = |min_trade_usd_e6, max_chunks_per_trade| TradeSizeLimits { min_trade_usd_e6, max_chunks_per_trade };
+        assert!(set_trade_size_limits(limits(0, 0)).is_err());
+        assert!(set_trade_size_limits(limits(2 * MIN_CHUNK_SIZE_E6 + 1, 2)).is_err(), "no request could pass both");
+        set_trade_size_limits(limits(6_000_000, 2)).unwrap();
+
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        let order_id = block_on(crate::order_management::create_order(9.0, 100.0, crate::lifecycle_tests::p2pkh_address(&[0x11; 20]), None)).unwrap();
+        mock::mint(filler_accounts::get_deposit_account(filler(1)), 1_000_000);
+
+        assert!(matches!(fill(filler(1), 3.0), Err(EasySwapError::InvalidInput { .. })));
+        let trade_ids = fill(filler(1), 9.0).unwrap();
+        let trade = get_trade(trade_ids[0]).unwrap();
+        assert_eq!((trade_ids.len(), trade.order_id, trade.locked_chunks.len()), (1, order_id, 2));
+        assert_eq!(crate::state::get_available_orderbook(), 3_000_000);
+    }
+
+    #[test]
+    fn prefer_higher_incentive_overrides_the_fifo_policy_for_one_request() {
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(filler_accounts::get_deposit_account(maker()), 10_000_000);
+        mock::set_caller(maker());
+        let address = crate::lifecycle_tests::p2pkh_address(&[0x11; 20]);
+        let default_order = block_on(crate::order_management::create_order(3.0, 100.0, address.clone(), None)).unwrap();
+        let generous_order = block_on(crate::order_management::create_order(3.0, 100.0, address, Some(800))).unwrap();
+        mock::mint(filler_accounts::get_deposit_account(filler(1)), 1_000_000);
+
+        mock::set_caller(filler(1));
+        let preferred = block_on(create_trades(filler(1), CreateTradesRequest {
+            requested_usd: 3.0,
+            allow_partial: true,
+            min_bsv_price: 40.0,
+            prefer_higher_incentive: Some(true),
+            book_id: None,
+        })).unwrap();
+        assert_eq!(get_trade(preferred[0]).unwrap().order_id, generous_order);
+        let fifo = fill(filler(1), 3.0).unwrap();
+        assert_eq!(get_trade(fifo[0]).unwrap().order_id, default_order);
+    }
+
+    #[test]
+    fn price_improvement_sharing_rejects_out_of_range_splits() {
+        assert!(set_price_improvement_sharing(PriceImprovementSharing { threshold_bps: 10_000, filler_share_bps: 0 }).is_err());
+        assert!(set_price_improvement_sharing(PriceImprovementSharing { threshold_bps: 0, filler_share_bps: 10_001 }).is_err());
+        assert!(set_price_improvement_sharing(PriceImprovementSharing { threshold_bps: 9_999, filler_share_bps: 10_000 }).is_ok());
+        assert_eq!(crate::state::get_price_improvement_sharing().filler_share_bps, 10_000);
+    }
+
+    #[test]
+    fn grouped_claim_memo_is_unique_per_trade_set_and_fits_the_ledger_cap() {
+        assert_eq!(claim_memo(&[7]), "Claim T7");
+        let memo = claim_memo(&[3, 1, 2]);
+        assert!(memo.len() <= 32, "{}", memo);
+        assert_eq!(memo, claim_memo(&[1, 2, 3]));
+        // Same order and count, different trades
+        assert_ne!(memo, claim_memo(&[1, 2, 4]));
+        assert_ne!(memo, claim_memo(&[1, 2]));
+    }
 }
@@ -0,0 +1,93 @@
+/// Exactly-once protection for outgoing ledger transfers
+/// ICRC-1 ledgers answer a transfer identical to one from the last 24h (same accounts, amount,
+/// memo and created_at_time) with Duplicate { duplicate_of } instead of executing it again
+/// Every payout carries a deterministic memo, and until the ledger has replied to it the memo
+/// keeps the created_at_time of the first attempt - so retrying a call whose reply was lost
+/// returns the original block rather than paying twice
+use crate::state::*;
+use crate::config::TRANSFER_DEDUP_WINDOW_SECONDS;
+
+/// created_at_time to send with the transfer identified by memo
+/// Reuses the time of an earlier attempt whose outcome is unknown while it is still inside
+/// TRANSFER_DEDUP_WINDOW_SECONDS, and records now otherwise
+pub fn created_at_time(memo: &[u8]) -> u64 {
+    let now = get_time();
+    let window_ns = TRANSFER_DEDUP_WINDOW_SECONDS * 1_000_000_000;
+    TRANSFER_DEDUP.with(|map| {
+        let mut map = map.borrow_mut();
+        match map.get(&memo.to_vec()) {
+            Some(first_attempt) if now.saturating_sub(first_attempt) < window_ns => first_attempt,
+            _ => {
+                map.insert(memo.to_vec(), now);
+                now
+            }
+        }
+    })
+}
+
+/// The ledger replied to the transfer identified by memo (success, duplicate or rejection),
+/// so its outcome is known - a later transfer with the same memo is a new one
+pub fn settle(memo: &[u8]) {
+    TRANSFER_DEDUP.with(|map| {
+        map.borrow_mut().remove(&memo.to_vec());
+    });
+}
+
+/// Drop entries too old for the ledger to deduplicate against
+/// Returns the number removed
+pub fn prune(now: u64) -> u64 {
+    let window_ns = TRANSFER_DEDUP_WINDOW_SECONDS * 1_000_000_000;
+    TRANSFER_DEDUP.with(|map| {
+        let mut map = map.borrow_mut();
+        let stale: Vec<Vec<u8>> = map.iter()
+            .filter(|(_, first_attempt)| now.saturating_sub(*first_attempt) >= window_ns)
+            .map(|(memo, _)| memo)
+            .collect();
+        for memo in &stale {
+            map.remove(memo);
+        }
+        stale.len() as u64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock;
+
+    const WINDOW_NS: u64 = TRANSFER_DEDUP_WINDOW_SECONDS * 1_000_000_000;
+
+    #[test]
+    fn unsettled_memo_reuses_its_first_attempt_inside_the_window() {
+        let first = created_at_time(b"Claim T1");
+        mock::advance_time(60_000_000_000);
+        assert_eq!(created_at_time(b"Claim T1"), first);
+        // Other memos are timed independently
+        assert_eq!(created_at_time(b"Claim T2"), get_time());
+
+        // Past the window the ledger no longer dedups, so the retry is timed afresh
+        mock::advance_time(WINDOW_NS);
+        assert_eq!(created_at_time(b"Claim T1"), get_time());
+    }
+
+    #[test]
+    fn settled_memo_is_a_new_transfer() {
+        let first = created_at_time(b"Claim T1");
+        settle(b"Claim T1");
+        mock::advance_time(1);
+        let second = created_at_time(b"Claim T1");
+        assert_eq!(second, first + 1);
+    }
+
+    #[test]
+    fn prune_drops_only_entries_outside_the_window() {
+        let first = created_at_time(b"Claim T1");
+        mock::advance_time(WINDOW_NS / 2);
+        let second = created_at_time(b"Claim T2");
+
+        assert_eq!(prune(first + WINDOW_NS - 1), 0);
+        assert_eq!(prune(first + WINDOW_NS), 1);
+        assert_eq!(prune(second + WINDOW_NS), 1);
+        assert_eq!(prune(u64::MAX), 0);
+    }
+}
@@ -0,0 +1,212 @@
+/// Treasury accounting - the canister's main ckUSDC account holds both operator revenue
+/// and user funds in transit (ETH withdrawals awaiting the minter), so every revenue
+/// transfer into it is counted by category in stable state
+use candid::Principal;
+use crate::types::*;
+use crate::state::*;
+use crate::config::{CKUSDC_TRANSFER_FEE, CK_ETH_LEDGER, CK_USDC_LEDGER};
+
+/// Record an order's funds that reached the treasury on the order's book ledger - ckUSDC goes
+/// to the buckets (and accrues rebates), other tokens to their own ledger's revenue
+pub fn record_order_inflow(order_id: OrderId, kind: TreasuryInflowKind, amount_e6: UsdE6) {
+    let ledger = crate::books::order_ledger(order_id);
+    if ledger == crate::books::ckusdc_ledger() {
+        record_inflow(kind, amount_e6);
+        return;
+    }
+    if amount_e6 == 0 {
+        return;
+    }
+    update_ledger_revenue(ledger, |revenue| match kind {
+        TreasuryInflowKind::ActivationFee => revenue.activation_fees += amount_e6,
+        TreasuryInflowKind::ExpiredTradeReclaim => revenue.expired_trade_reclaims += amount_e6,
+        // Penalties, withdrawal fees and dust are ckUSDC whatever the book
+        _ => {}
+    });
+}
+
+fn update_ledger_revenue<F: FnOnce(&mut LedgerRevenue)>(ledger: Principal, updater: F) {
+    update_treasury_buckets(|buckets| {
+        let ledgers = buckets.other_ledgers.get_or_insert_with(Vec::new);
+        let index = match ledgers.iter().position(|revenue| revenue.ledger == ledger) {
+            Some(index) => index,
+            None => {
+                ledgers.push(LedgerRevenue::new(ledger));
+                ledgers.len() - 1
+            }
+        };
+        updater(&mut ledgers[index]);
+    });
+}
+
+/// Revenue held on a non-ckUSDC ledger (zero if it never received any)
+pub fn ledger_revenue(ledger: Principal) -> LedgerRevenue {
+    get_treasury_buckets().other_ledgers.unwrap_or_default().into_iter()
+        .find(|revenue| revenue.ledger == ledger)
+        .unwrap_or_else(|| LedgerRevenue::new(ledger))
+}
+
+/// Record ckUSDC that reached the treasury (amount net of the ledger fee)
+pub fn record_inflow(kind: TreasuryInflowKind, amount_e6: UsdE6) {
+    if amount_e6 == 0 {
+        return;
+    }
+    update_treasury_buckets(|buckets| {
+        let bucket = match kind {
+            TreasuryInflowKind::ActivationFee => &mut buckets.activation_fees_e6,
+            TreasuryInflowKind::ExpiredTradeReclaim => &mut buckets.expired_trade_reclaims_e6,
+            TreasuryInflowKind::Penalty => &mut buckets.penalties_e6,
+            TreasuryInflowKind::WithdrawalFee => &mut buckets.withdrawal_fees_e6,
+            TreasuryInflowKind::DustSweep => &mut buckets.dust_swept_e6,
+        };
+        *bucket += amount_e6;
+    });
+    
+    if matches!(kind, TreasuryInflowKind::ActivationFee) {
+        crate::rebates::accrue(amount_e6);
+    }
+}
+
+/// Record revenue paid out to the admin
+pub fn record_admin_withdrawal(amount_e6: UsdE6) {
+    update_treasury_buckets(|buckets| {
+        buckets.withdrawn_by_admin_e6 += amount_e6;
+    });
+}
+
+/// Record an upheld penalty appeal's refund paid out of the main account
+pub fn record_appeal_refund(amount_e6: UsdE6) {
+    update_treasury_buckets(|buckets| {
+        buckets.appeal_refunds_e6 = Some(buckets.appeal_refunds_e6.unwrap_or(0) + amount_e6);
+    });
+}
+
+/// Record a late claim paid out of the main account on `ledger` - the expired trade reclaim it undoes
+pub fn record_late_claim_payout(ledger: Principal, amount_e6: UsdE6) {
+    if ledger != crate::books::ckusdc_ledger() {
+        update_ledger_revenue(ledger, |revenue| revenue.late_claims_paid += amount_e6);
+        return;
+    }
+    update_treasury_buckets(|buckets| {
+        buckets.late_claims_paid_e6 = Some(buckets.late_claims_paid_e6.unwrap_or(0) + amount_e6);
+    });
+}
+
+/// Revenue not yet withdrawn by the admin
+fn revenue_held_e6(buckets: &TreasuryBuckets) -> UsdE6 {
+    // Rebates are the makers' - owed, pooled or already paid out of the main account
+    buckets.total_revenue_e6()
+        .saturating_sub(buckets.withdrawn_by_admin_e6)
+        .saturating_sub(buckets.rebates_accrued_e6.unwrap_or(0))
+        .saturating_sub(buckets.appeal_refunds_e6.unwrap_or(0))
+        .saturating_sub(buckets.late_claims_paid_e6.unwrap_or(0))
+}
+
+/// ckUSDC that in-flight trades have locked - their chunks plus the filler incentive reserved on them
+pub fn earmarked_for_trades_e6() -> UsdE6 {
+    get_all_orders().iter()
+        .filter(|order| order.total_locked_usd_e6 > 0)
+        .map(|order| order.total_locked_usd_e6 + bps_of(order.total_locked_usd_e6, order.filler_incentive_bps()))
+        .sum()
+}
+
+/// Most the admin may withdraw from a main account holding `balance_e6` - held revenue, never
+/// dipping into what in-flight trades have earmarked, and leaving room for the ledger fee
+pub fn withdrawable_e6(balance_e6: UsdE6) -> UsdE6 {
+    revenue_held_e6(&get_treasury_buckets())
+        .min(balance_e6.saturating_sub(earmarked_for_trades_e6()))
+        .saturating_sub(CKUSDC_TRANSFER_FEE as UsdE6)
+}
+
+pub async fn get_treasury_report() -> TreasuryReport {
+    let buckets = get_treasury_buckets();
+    let total_revenue_e6 = buckets.total_revenue_e6();
+    let revenue_held_e6 = revenue_held_e6(&buckets);
+
+    let main_account_balance_e6 = crate::settlement::treasury_balance(CK_USDC_LEDGER).await
+        .ok()
+        .and_then(|balance| crate::ckusdc_integration::nat_to_u64(&balance).ok());
+
+    TreasuryReport {
+        buckets,
+        total_revenue_e6,
+        revenue_held_e6,
+        main_account_balance_e6,
+        pass_through_e6: main_account_balance_e6.map(|balance| balance.saturating_sub(revenue_held_e6)),
+        generated_at: get_time(),
+    }
+}
+
+/// Open orders and the ckUSDC their subaccounts should still hold - the unfilled, unrefunded
+/// amount plus its filler incentive (estimated from order totals, no ledger calls)
+pub fn estimate_order_holdings_e6() -> (u64, UsdE6) {
+    get_all_orders().iter()
+        .filter(|order| matches!(order.status, OrderStatus::Active | OrderStatus::Idle) || order.total_locked_usd_e6 > 0)
+        .fold((0, 0), |(count, held_e6), order| {
+            let remaining_e6 = order.amount_usd_e6
+                .saturating_sub(order.total_filled_usd_e6 + order.total_refunded_usd_e6.unwrap_or(0));
+            (count + 1, held_e6 + remaining_e6 + bps_of(remaining_e6, order.filler_incentive_bps()))
+        })
+}
+
+pub async fn get_treasury_balances() -> TreasuryBalances {
+    let ckusdc_main_account_e6 = crate::settlement::treasury_balance(CK_USDC_LEDGER).await
+        .ok()
+        .and_then(|balance| crate::ckusdc_integration::nat_to_u64(&balance).ok());
+    let cketh_wei = crate::settlement::treasury_balance(CK_ETH_LEDGER).await.ok();
+    let (open_orders, order_subaccounts_e6) = estimate_order_holdings_e6();
+
+    TreasuryBalances {
+        ckusdc_main_account_e6,
+        cketh_wei,
+        cketh_below_watermark: get_cketh_monitor().below_watermark,
+        cycles_balance: ic_cdk::api::canister_balance(),
+        open_orders,
+        order_subaccounts_e6,
+        earmarked_for_trades_e6: earmarked_for_trades_e6(),
+        revenue_held_e6: revenue_held_e6(&get_treasury_buckets()),
+        generated_at: get_time(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revenue_is_bucketed_and_pass_through_funds_are_not_counted_as_revenue() {
+        record_inflow(TreasuryInflowKind::ActivationFee, 100_000);
+        record_inflow(TreasuryInflowKind::ExpiredTradeReclaim, 3_000_000);
+        record_inflow(TreasuryInflowKind::Penalty, 200_000);
+        record_inflow(TreasuryInflowKind::WithdrawalFee, 50_000);
+        record_inflow(TreasuryInflowKind::DustSweep, 0);
+        record_admin_withdrawal(1_000_000);
+        record_appeal_refund(110_000);
+
+        let buckets = get_treasury_buckets();
+        assert_eq!(
+            (buckets.activation_fees_e6, buckets.expired_trade_reclaims_e6, buckets.penalties_e6, buckets.withdrawal_fees_e6, buckets.dust_swept_e6),
+            (100_000, 3_000_000, 200_000, 50_000, 0)
+        );
+        assert_eq!(buckets.total_revenue_e6(), 3_350_000);
+        let held_e6 = 3_350_000 - 1_000_000 - 110_000 - buckets.rebates_accrued_e6.unwrap_or(0);
+        assert_eq!(revenue_held_e6(&buckets), held_e6);
+
+        // Anything above held revenue is passing through (e.g. ETH withdrawals awaiting the
+        // minter) and never withdrawable - nor is more than the account holds, less the fee
+        assert_eq!(withdrawable_e6(held_e6 + 4_000_000), held_e6 - CKUSDC_TRANSFER_FEE as UsdE6);
+        assert_eq!(withdrawable_e6(1_000_000), 1_000_000 - CKUSDC_TRANSFER_FEE as UsdE6);
+    }
+
+    #[test]
+    fn other_ledgers_revenue_is_kept_out_of_the_ckusdc_buckets() {
+        let ckusdt = Principal::from_slice(&[9, 9, 9]);
+        update_ledger_revenue(ckusdt, |revenue| revenue.activation_fees += 75_000);
+        record_late_claim_payout(ckusdt, 25_000);
+
+        let revenue = ledger_revenue(ckusdt);
+        assert_eq!((revenue.activation_fees, revenue.late_claims_paid), (75_000, 25_000));
+        assert_eq!(get_treasury_buckets().total_revenue_e6(), 0);
+        assert_eq!(ledger_revenue(Principal::from_slice(&[8, 8, 8])).activation_fees, 0);
+    }
+}
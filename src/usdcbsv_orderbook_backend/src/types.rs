@@ -1,12 +1,106 @@
-use candid::{CandidType, Deserialize, Principal, Encode, Decode};
+use candid::{CandidType, Deserialize, Principal, Decode, Nat};
 use serde::Serialize;
 use ic_stable_structures::{Storable, storable::Bound};
 use std::borrow::Cow;
+use crate::record_envelope::{encode_record, decode_stored, VersionedRecord};
 
 pub type OrderId = u64;
 pub type ChunkId = u64;
 pub type TradeId = u64;
 pub type FillerAccountId = Principal;
+pub type QuoteId = u64;
+pub type BookId = u32;
+
+/// USD amount in micro-USD (1 USD = 1_000_000) - the same base unit as ckUSDC
+/// All order, chunk and trade accounting uses this instead of f64
+pub type UsdE6 = u64;
+
+pub const USD_E6: u64 = 1_000_000;
+
+/// Convert a USD amount at the API boundary to micro-USD (rounded to the nearest unit)
+pub fn usd_to_e6(usd: f64) -> UsdE6 {
+    if usd <= 0.0 {
+        return 0;
+    }
+    (usd * USD_E6 as f64).round() as UsdE6
+}
+
+/// Convert micro-USD to USD for display and reporting
+pub fn e6_to_usd(amount: UsdE6) -> f64 {
+    amount as f64 / USD_E6 as f64
+}
+
+/// `basis_points` / 10_000 of an amount, rounded down
+pub fn bps_of(amount: UsdE6, basis_points: u64) -> UsdE6 {
+    (amount as u128 * basis_points as u128 / 10_000) as UsdE6
+}
+
+// ===== ERROR TYPES =====
+
+/// Error returned by all public endpoints
+/// Frontends branch on the variant; `reason` is a human-readable message for display
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum EasySwapError {
+    Unauthorized { reason: String },        // Anonymous caller, not the owner, not admin
+    NotFound { reason: String },
+    InvalidInput { reason: String },        // Malformed or out-of-bounds arguments
+    InvalidState { reason: String },        // Wrong status, expired, on hold
+    InsufficientFunds { reason: String },
+    PriceOutOfRange { reason: String },
+    LimitExceeded { reason: String },       // Orderbook, maker, lock or size limits
+    SpvFailed { reason: String },           // BSV transaction/merkle proof not verified or not deep enough
+    LedgerError { reason: String },         // ckUSDC/ckETH ledger or minter call failed
+    ServiceUnavailable { reason: String },  // New orders disabled, low cycles, price/chain data unavailable
+    Internal { reason: String },
+}
+
+impl EasySwapError {
+    pub fn unauthorized(reason: impl Into<String>) -> Self { EasySwapError::Unauthorized { reason: reason.into() } }
+    pub fn not_found(reason: impl Into<String>) -> Self { EasySwapError::NotFound { reason: reason.into() } }
+    pub fn invalid_input(reason: impl Into<String>) -> Self { EasySwapError::InvalidInput { reason: reason.into() } }
+    pub fn invalid_state(reason: impl Into<String>) -> Self { EasySwapError::InvalidState { reason: reason.into() } }
+    pub fn insufficient_funds(reason: impl Into<String>) -> Self { EasySwapError::InsufficientFunds { reason: reason.into() } }
+    pub fn price_out_of_range(reason: impl Into<String>) -> Self { EasySwapError::PriceOutOfRange { reason: reason.into() } }
+    pub fn limit_exceeded(reason: impl Into<String>) -> Self { EasySwapError::LimitExceeded { reason: reason.into() } }
+    pub fn spv_failed(reason: impl Into<String>) -> Self { EasySwapError::SpvFailed { reason: reason.into() } }
+    pub fn ledger(reason: impl Into<String>) -> Self { EasySwapError::LedgerError { reason: reason.into() } }
+    pub fn unavailable(reason: impl Into<String>) -> Self { EasySwapError::ServiceUnavailable { reason: reason.into() } }
+    
+    pub fn reason(&self) -> &str {
+        match self {
+            EasySwapError::Unauthorized { reason }
+            | EasySwapError::NotFound { reason }
+            | EasySwapError::InvalidInput { reason }
+            | EasySwapError::InvalidState { reason }
+            | EasySwapError::InsufficientFunds { reason }
+            | EasySwapError::PriceOutOfRange { reason }
+            | EasySwapError::LimitExceeded { reason }
+            | EasySwapError::SpvFailed { reason }
+            | EasySwapError::LedgerError { reason }
+            | EasySwapError::ServiceUnavailable { reason }
+            | EasySwapError::Internal { reason } => reason,
+        }
+    }
+}
+
+impl std::fmt::Display for EasySwapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason())
+    }
+}
+
+/// Internal helpers still return String errors - anything not classified is Internal
+impl From<String> for EasySwapError {
+    fn from(reason: String) -> Self {
+        EasySwapError::Internal { reason }
+    }
+}
+
+impl From<EasySwapError> for String {
+    fn from(err: EasySwapError) -> Self {
+        err.to_string()
+    }
+}
 
 // ===== ORDER TYPES =====
 
@@ -38,22 +132,33 @@ pub struct RefundAttempt {
     pub refund_id: u64,
     pub requested_at: u64,
     pub chunk_ids: Vec<ChunkId>,
-    pub total_amount: f64,
+    pub total_amount_e6: UsdE6,
     pub recipient_address: String,
     pub tx_hash: Option<String>,
     pub tx_sent_at: Option<u64>,
     pub confirmed_at: Option<u64>,
     pub status: RefundStatus,
+    pub block_index: Option<u64>,           // ckUSDC ledger block of the refund transfer
+    pub pending_transfer_id: Option<u64>,   // Set when the inline transfer failed and was queued for retry
+    pub last_error: Option<String>,
+}
+
+/// A refund together with the order it came from - see get_my_refunds
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderRefund {
+    pub order_id: OrderId,
+    pub refund: RefundAttempt,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct Order {
     pub id: OrderId,
     pub maker: Principal,
-    pub amount_usd: f64,
-    pub total_deposited_usd: Option<f64>,
-    pub activation_fee_usd: Option<f64>,
-    pub filler_incentive_reserved: Option<f64>,
+    pub amount_usd_e6: UsdE6,
+    pub total_deposited_usd_e6: Option<UsdE6>,
+    pub activation_fee_usd_e6: Option<UsdE6>,
+    pub filler_incentive_reserved_e6: Option<UsdE6>,
+    pub filler_incentive_bps: Option<u64>,  // Offered by the maker at creation - None = FILLER_INCENTIVE_PERCENT
     pub deposit_principal: String,
     pub deposit_subaccount: String,
     pub max_bsv_price: f64,
@@ -66,199 +171,2065 @@ pub struct Order {
     pub funded_at: Option<u64>,
     pub activation_fee_block_index: Option<u64>,
     pub activation_fee_confirmed_at: Option<u64>,
-    pub total_filled_usd: f64,
-    pub total_locked_usd: f64,
-    pub total_idle_usd: f64,
-    pub total_refunded_usd: Option<f64>,
+    pub total_filled_usd_e6: UsdE6,
+    pub total_locked_usd_e6: UsdE6,
+    pub total_idle_usd_e6: UsdE6,
+    pub total_refunded_usd_e6: Option<UsdE6>,
     pub refund_attempts: Vec<RefundAttempt>,
+    pub finalization_pending_since: Option<u64>,  // Cancelled with chunks still Locked - finalized by the heartbeat once their trades resolve
+    pub hidden: Option<bool>,                     // Maker opted out of public depth/stats (see set_order_hidden) - still matchable
+    pub idle_since: Option<u64>,                  // Start of the current price-induced delisting - None while no chunk is Idle
+    pub last_idle_at: Option<u64>,                // When chunks last went Idle
+    pub idle_duration_total_ns: Option<u64>,     // Delisting periods that have ended - see idle_duration_ns
+    pub auto_cancel_above_price: Option<f64>,    // Cancelled and refunded at the idle check once BSV trades above this
+    pub post_only: Option<bool>,                  // Chunks started Pending instead of Available - see activate_pending_chunks
+    pub book_id: Option<BookId>,                  // None = DEFAULT_BOOK_ID
+}
+
+impl Order {
+    pub fn filler_incentive_bps(&self) -> u64 {
+        self.filler_incentive_bps.unwrap_or(crate::config::FILLER_INCENTIVE_PERCENT)
+    }
+
+    pub fn book_id(&self) -> BookId {
+        self.book_id.unwrap_or(crate::books::DEFAULT_BOOK_ID)
+    }
+
+    /// Open or close the idle period as total_idle_usd_e6 leaves or returns to zero
+    pub fn track_idle(&mut self, now: u64) {
+        if self.total_idle_usd_e6 > 0 && self.idle_since.is_none() {
+            self.idle_since = Some(now);
+            self.last_idle_at = Some(now);
+        } else if self.total_idle_usd_e6 == 0 {
+            if let Some(since) = self.idle_since.take() {
+                self.idle_duration_total_ns = Some(self.idle_duration_total_ns.unwrap_or(0) + now.saturating_sub(since));
+            }
+        }
+    }
+
+    /// How long any of the order's chunks have been delisted by its price ceiling, including the current period
+    pub fn idle_duration_ns(&self, now: u64) -> u64 {
+        self.idle_duration_total_ns.unwrap_or(0) + self.idle_since.map_or(0, |since| now.saturating_sub(since))
+    }
+}
+
+// ===== CHUNK TYPES =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ChunkStatus {
+    Available,     // In orderbook, ready to fill
+    Locked,        // Locked in a trade
+    Filled,        // Successfully filled
+    Idle,          // Delisted due to price breaking upward
+    Pending,       // Post-only order - held off the book until the next idle check or the maker confirms
+    Refunding,     // Refund transaction sent, awaiting confirmation
+    Refunded,      // Refunded to maker (confirmed)
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct Chunk {
+    pub id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd_e6: UsdE6,
+    pub status: ChunkStatus,
+    pub locked_by: Option<TradeId>,
+    pub filled_at: Option<u64>,
+    pub bsv_address: String,
+    pub sats_amount: Option<u64>,  // Not set at creation, only for reference/legacy
+    pub max_bsv_price: f64,  // Inherited from order - chunks go idle if BSV price exceeds this
+    pub filler_incentive_bps: Option<u64>,    // Inherited from order - None = FILLER_INCENTIVE_PERCENT
+    pub lock_count: Option<u64>,              // Times this chunk has been locked by a trade
+    pub timeout_release_count: Option<u64>,   // Times a lock was released because the filler timed out
+    pub last_timeout_filler: Option<Principal>,
+    pub reserved_by: Option<Principal>,       // Filler holding a reserve_chunks reservation
+    pub reserved_until: Option<u64>,          // Reservation lapses at this time, even before the heartbeat clears it
+    pub hidden: Option<bool>,                 // Copied from the order - left out of public book views, still matchable
+    pub book_id: Option<BookId>,              // Inherited from order - None = DEFAULT_BOOK_ID
+}
+
+impl Chunk {
+    pub fn book_id(&self) -> BookId {
+        self.book_id.unwrap_or(crate::books::DEFAULT_BOOK_ID)
+    }
+
+    /// Shown in the public orderbook, depth and stats - Available and not hidden by its maker
+    pub fn is_listed(&self) -> bool {
+        self.status == ChunkStatus::Available && self.hidden != Some(true)
+    }
+
+    /// Filler whose reservation on this chunk is still live at `now`
+    pub fn reservation_holder(&self, now: u64) -> Option<Principal> {
+        match self.reserved_until {
+            Some(until) if until > now => self.reserved_by,
+            _ => None,
+        }
+    }
+}
+
+/// Chunks soft-reserved by reserve_chunks - the filler's create_trades matches them first,
+/// and other fillers' matching skips them, until expires_at
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ChunkReservation {
+    pub chunk_ids: Vec<ChunkId>,
+    pub amount_usd_e6: UsdE6,
+    pub expires_at: u64,
+}
+
+// ChunkInfo removed - no longer needed with ckUSDC-only approach
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ChunkDetails {
+    pub id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd_e6: UsdE6,
+    pub status: ChunkStatus,
+    pub locked_by: Option<TradeId>,
+    pub filled_at: Option<u64>,
+    pub lock_count: u64,
+    pub timeout_release_count: u64,
+    pub last_timeout_filler: Option<Principal>,
+}
+
+/// A BSV payment a filler submitted to one of the caller's orders
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct IncomingBsvPayment {
+    pub trade_id: TradeId,
+    pub order_id: OrderId,
+    pub txid: String,
+    pub outputs: Vec<LockedChunk>,          // Address and sats paid per chunk
+    pub status: TradeStatus,
+    pub block_height: Option<u64>,          // Known once the filler claimed with an SPV proof
+    pub confirmations: Option<u64>,
+    pub tx_submitted_at: Option<u64>,
+}
+
+/// A trade that locked chunks of an order, as shown to the order's maker
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderTradeActivity {
+    pub trade_id: TradeId,
+    pub status: TradeStatus,
+    pub amount_usd_e6: UsdE6,
+    pub agreed_bsv_price: f64,
+    pub effective_bsv_price: f64,           // Price the chunks' sats were calculated at
+    pub locked_chunks: Vec<LockedChunk>,    // Chunks, addresses and sats the BSV tx must pay
+    pub bsv_txid: Option<String>,           // Set once the filler submitted the BSV transaction
+    pub created_at: u64,
+    pub tx_submitted_at: Option<u64>,
+    pub release_available_at: Option<u64>,
+    pub withdrawal_confirmed_at: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OrderbookChunk {
+    pub chunk_id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd_e6: UsdE6,  // The actual USD amount of this specific chunk
+    pub max_price_per_bsv_in_cents: u64,
+    pub filler_incentive_bps: u64,  // Paid to the filler on top of amount_usd_e6
+}
+
+/// One change to the set of Available chunks, numbered by a sequence that only grows
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderbookChange {
+    pub seq: u64,
+    pub chunk_id: ChunkId,
+    pub chunk: Option<OrderbookChunk>,  // New contents if added or changed in the book, None if it left the book
+    pub at: u64,
+}
+
+/// Every Available chunk plus stats as of seq - apply get_orderbook_delta(seq) to keep it current
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderbookSnapshot {
+    pub seq: u64,
+    pub chunks: Vec<OrderbookChunk>,
+    pub stats: OrderbookStats,
+    pub generated_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderbookDelta {
+    pub since_seq: u64,
+    pub seq: u64,                       // Pass as since_seq on the next call
+    pub changes: Vec<OrderbookChange>,
+    pub has_more: bool,
+    pub resync_required: bool,          // since_seq is no longer retained - fetch a new snapshot
+}
+
+// ===== TRADE TYPES =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TradeStatus {
+    ChunksLocked,           // Chunks locked, waiting for BSV tx
+    TxSubmitted,            // BSV tx submitted, waiting for release timer
+    ReadyForRelease,        // Wait period passed, can claim USDC
+    WithdrawalConfirmed,    // ckUSDC transferred (instant on ICP)
+    Cancelled,              // Cancelled (timeout or admin)
+    PenaltyApplied,         // Penalty deducted
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct LockedChunk {
+    pub chunk_id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd_e6: UsdE6,
+    pub bsv_address: String,
+    pub sats_amount: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct Trade {
+    pub id: TradeId,
+    pub order_id: OrderId,              // Which order this trade is from
+    pub filler: Principal,
+    pub amount_usd_e6: UsdE6,
+    pub locked_chunks: Vec<LockedChunk>,
+    pub agreed_bsv_price: f64,          // BSV price at trade creation
+    pub min_bsv_price: f64,             // Minimum acceptable BSV price (filler protection)
+    pub effective_bsv_price: Option<f64>, // Price sats were calculated at when a price improvement was shared - None = agreed_bsv_price
+    pub status: TradeStatus,
+    pub bsv_txid: Option<String>,       // Txid of the submitted tx, recorded at (re)submission - raw hex is in TX_BLOBS
+    pub payment_group: Option<Vec<TradeId>>, // Every trade paid by bsv_txid when submitted together - None = paid alone
+    pub bsv_block_height: Option<u64>,  // Block the BSV tx was mined in, recorded by SPV at claim
+    pub created_at: u64,
+    pub tx_submitted_at: Option<u64>,
+    pub lock_expires_at: u64,           // 30 minutes from creation
+    pub lock_extended_at: Option<u64>,  // Set when the filler extended the lock - allowed once
+    pub release_available_at: Option<u64>, // Configured wait time from tx submission
+    pub claim_expires_at: Option<u64>,  // 24 hours from tx submission - funds go to treasury if not claimed
+    pub required_confirmations: Option<u64>, // Locked in at tx submission - later config changes don't apply
+    pub sats_tolerance: Option<SatsTolerance>, // Locked in at trade creation - None means exact amounts
+    
+    // Withdrawal tracking (ckUSDC transfers to filler's principal)
+    pub withdrawal_initiated_at: Option<u64>,
+    pub withdrawal_tx_hash: Option<String>,
+    pub withdrawal_confirmed_at: Option<u64>,
+}
+
+impl Trade {
+    /// Price the locked chunks' sats were calculated at
+    pub fn effective_bsv_price(&self) -> f64 {
+        self.effective_bsv_price.unwrap_or(self.agreed_bsv_price)
+    }
+}
+
+/// Allowed deviation of a BSV output from a chunk's sats_amount, absorbing rounding
+/// differences between the canister and wallets. The larger of the two bounds applies
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SatsTolerance {
+    pub absolute_sats: u64,
+    pub basis_points: u64,  // 5 = 0.05% of the expected amount
+}
+
+impl SatsTolerance {
+    pub fn allowed_deviation(&self, expected_sats: u64) -> u64 {
+        let relative = (expected_sats as u128 * self.basis_points as u128 / 10_000) as u64;
+        self.absolute_sats.max(relative)
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PaymentOutput {
+    pub bsv_address: String,
+    pub sats: u64,
+}
+
+/// Outputs one BSV transaction must start with to pay a set of locked trades - one per maker
+/// address, totaling its chunks. Change goes after them
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PaymentTemplate {
+    pub trade_ids: Vec<TradeId>,        // Sorted - submit with submit_bsv_transaction_for_trades (or submit_bsv_transaction for one)
+    pub outputs: Vec<PaymentOutput>,
+    pub total_sats: u64,
+    pub expires_at: u64,                // Earliest lock expiry of the trades
+}
+
+// ===== BACKGROUND SCHEDULER TYPES =====
+
+/// Background jobs run by scheduler.rs
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BackgroundTask {
+    Confirmations,
+    IdleReactivation,
+    CleanupTasks,
+    BlockSync,
+    DataCleanup,
+    SettlementReport,
+    Reconciliation,
+    CkEthBalanceCheck,
+    WithdrawalFollowUp,
+}
+
+/// Runtime settings and last-run status of one background task
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduledTask {
+    pub task: BackgroundTask,
+    pub interval_seconds: u64,
+    pub enabled: bool,
+    pub next_run_at: u64,               // Pushed out by backoff after failures
+    pub last_started_at: Option<u64>,
+    pub last_finished_at: Option<u64>,
+    pub last_error: Option<String>,     // None if the last run succeeded
+    pub consecutive_failures: u32,
+    pub total_runs: u64,
+}
+
+// ===== PENDING TRANSFER TYPES =====
+
+/// Ledger transfer that failed inline and is retried by pending_transfers.rs
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PendingTransferKind {
+    Penalty { filler: Principal, recipient: Option<Principal> },  // None = treasury
+    OrderRefund { maker: Principal, order_id: OrderId },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PendingTransferStatus {
+    Pending,    // Waiting for its next attempt
+    Completed,
+    Failed,     // Gave up after PENDING_TRANSFER_MAX_ATTEMPTS - needs admin attention
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PendingTransfer {
+    pub id: u64,
+    pub kind: PendingTransferKind,
+    pub amount_e6: u128,                // Net amount the recipient receives (ledger fee on top)
+    pub memo: Vec<u8>,
+    pub status: PendingTransferStatus,
+    pub attempts: u32,                  // Includes the original inline attempt
+    pub next_attempt_at: u64,
+    pub last_error: Option<String>,
+    pub block_index: Option<u64>,       // Set once completed
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+// ===== ARCHIVE TYPES =====
+
+/// Record kinds held by the archive canister - mirrors its RecordKind variant
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveRecordKind {
+    Order,
+    Trade,
+}
+
+/// An order together with its chunks, as stored in the archive canister
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ArchivedOrder {
+    pub order: Order,
+    pub chunks: Vec<Chunk>,
+    pub archived_at: Option<u64>,   // None = still held by this canister
+}
+
+// ===== QUOTE (RFQ) TYPES =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum QuoteStatus {
+    Open,
+    Accepted { order_id: OrderId, trade_id: TradeId },
+    Cancelled,
+}
+
+/// A filler's standing offer to buy up to amount_usd_e6 of maker USD at bsv_price
+/// Posted by the filler's authenticated principal - a maker accepting it creates a trade
+/// against one of their orders at exactly this price
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct Quote {
+    pub id: QuoteId,
+    pub filler: Principal,
+    pub amount_usd_e6: UsdE6,
+    pub bsv_price: f64,             // USD per BSV the filler pays at
+    pub status: QuoteStatus,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub updated_at: u64,
+}
+
+// ===== DISPUTE TYPES =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum DisputeStatus {
+    Open,       // Trade is on hold - no claim, no auto-reclaim
+    Resolved,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum DisputeResolution {
+    ReleaseToFiller,  // Hold lifted, filler may claim (claim window extended by hold duration)
+    ReturnToMaker,    // Trade cancelled, chunks returned to the order
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TradeDispute {
+    pub trade_id: TradeId,
+    pub order_id: OrderId,
+    pub maker: Principal,
+    pub filler: Principal,
+    pub reason: String,
+    pub status: DisputeStatus,
+    pub flagged_at: u64,
+    pub resolution: Option<DisputeResolution>,
+    pub resolved_at: Option<u64>,
+}
+
+// ===== PENALTY APPEALS =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AppealStatus {
+    Open,
+    Refunding,  // Refund transfer outcome unknown - resolving again resends it unchanged
+    Resolved,
+}
+
+/// A filler's appeal against the penalties logged on one of their trades
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PenaltyAppeal {
+    pub trade_id: TradeId,
+    pub filler: Principal,
+    pub explanation: String,
+    pub penalty_amount: f64,            // Sum of the trade's PenaltyApplied events
+    pub status: AppealStatus,
+    pub appealed_at: u64,
+    pub refund_amount: Option<f64>,     // Set once resolving - 0 = appeal denied
+    pub refund_block_index: Option<u64>,
+    pub resolved_at: Option<u64>,
+}
+
+// ===== LATE CLAIMS =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LateClaimStatus {
+    AwaitingAdmin,  // Verified after the automatic window - paid once the admin approves
+    Approved,       // Cleared to pay - a failed payout is retried by reviewing or approving again
+    Paid,
+}
+
+/// A verified claim on a trade whose funds the 24h reclaim already sent to treasury
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct LateClaim {
+    pub trade_id: TradeId,
+    pub filler: Principal,
+    pub bsv_txid: String,
+    pub bsv_block_height: u64,
+    pub amount_e6: UsdE6,               // Reclaimed to treasury - the filler receives it less the ledger fee, as on time
+    pub reclaimed_at: u64,
+    pub verified_at: u64,
+    pub status: LateClaimStatus,
+    pub block_index: Option<u64>,
+    pub paid_at: Option<u64>,
+}
+
+// ===== MAKER QUIET HOURS =====
+
+/// UTC windows during which a maker's Available chunks are moved to Idle
+/// A chunk is quiet if the current hour OR the current weekday is listed
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MakerQuietHours {
+    pub maker: Principal,
+    pub quiet_hours: Vec<u8>,  // Hours of day 0-23 (UTC)
+    pub quiet_days: Vec<u8>,   // Days of week 0-6, Monday = 0 (UTC)
+    pub updated_at: u64,
+}
+
+// ===== MAKER FILLER BLOCKLIST =====
+
+/// Fillers whose trades skip this maker's chunks during FIFO matching
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MakerFillerBlocklist {
+    pub maker: Principal,
+    pub blocked_fillers: Vec<Principal>,
+    pub updated_at: u64,
+}
+
+// ===== MAKER WALLET TOP-UP =====
+
+/// A maker's standing authorization to pull an order's deposit shortfall from their default
+/// ckUSDC account at activation, through an ICRC-2 approval they keep in place
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MakerAutoTopUp {
+    pub maker: Principal,
+    pub max_per_order_usd_e6: UsdE6,   // Larger shortfalls still need a manual deposit
+    pub updated_at: u64,
+}
+
+// ===== OPERATOR DELEGATIONS =====
+
+/// Calls an operator may make on its owner's behalf - withdrawals are never delegable
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OperatorScope {
+    FillerSettlement,   // submit/resubmit BSV transactions and claim ckUSDC for the owner's trades
+    MakerPricing,       // update max BSV price on the owner's orders
+    TradeCreate,        // "trade:create" - create_trades against the owner's security deposit
+    OrdersRead,         // "orders:read" - get_my_orders / get_my_active_orders for the owner
+}
+
+/// A secondary principal (a bot, or a frontend's session principal) acting for `owner` within `scopes`
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OperatorDelegation {
+    pub operator: Principal,
+    pub owner: Principal,
+    pub scopes: Vec<OperatorScope>,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,    // None = until revoked
+    pub accepted_at: Option<u64>,   // None = proposed by the owner, not yet accepted by the operator
+}
+
+// ===== RELEASE BONDS =====
+
+/// Security deposit held against a trade released before CONFIRMATION_DEPTH - returned once the
+/// payment is buried, forfeited to the maker if a reorg drops it from the chain
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ReleaseBond {
+    pub trade_id: TradeId,
+    pub filler: Principal,
+    pub maker: Principal,
+    pub amount_usd_e6: UsdE6,       // The amount the claim released
+    pub block_height: u64,
+    pub block_hash: String,         // Block the payment was proven in at claim
+    pub posted_at: u64,
+    pub reorged_at: Option<u64>,    // When the block was found replaced - cleared by a proof on the new branch
+}
+
+/// Backoff state for one background outcall target (API host) - see outcall_retry.rs
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OutcallBackoff {
+    pub target: String,
+    pub consecutive_failures: u32,
+    pub next_attempt_at: u64,        // Nanoseconds - calls before this are skipped
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<u64>,
+    pub capped_at: Option<u64>,      // Reached OUTCALL_MAX_ATTEMPTS failures - retried every OUTCALL_MAX_BACKOFF_SECONDS since
+}
+
+/// A txid whose merkle proof already checked out against a stored block header - lets retried
+/// and batched claims skip parsing and hashing the BUMP again (see bump_verification::verify_tx_cached)
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct VerifiedInclusion {
+    pub txid: String,
+    pub block_height: u64,
+    pub block_hash: String,         // Only trusted while the header at block_height still has this hash
+    pub verified_at: u64,
+}
+
+// ===== SETTLEMENT REPORTS =====
+
+/// Canonical daily close - compiled once per UTC day by the settlement timer
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DailySettlementReport {
+    pub day: u64,                       // Days since Unix epoch (UTC)
+    pub generated_at: u64,
+    pub orders_activated: u64,
+    pub activation_fees_usd: f64,       // Treasury revenue from order activations
+    pub trades_completed: u64,
+    pub volume_usd: f64,                // Chunk value released to fillers
+    pub filler_incentives_usd: f64,
+    pub penalties_count: u64,
+    pub penalties_usd: f64,
+    pub reclaimed_trades: u64,
+    pub reclaimed_usd: f64,             // Unclaimed trades sent to treasury
+    pub dust_swept_usd: f64,
+    pub treasury_ckusdc_e6: Option<Nat>,  // Ending balances - None if the ledger call failed
+    pub treasury_cketh_wei: Option<Nat>,
+    pub cycles_balance: u64,
+    pub cycles_burned: Option<u64>,     // Since the previous report (top-ups reduce this)
+}
+
+// ===== TREASURY ACCOUNTING =====
+
+/// Category of ckUSDC arriving in the canister's main account that belongs to the operator
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TreasuryInflowKind {
+    ActivationFee,
+    ExpiredTradeReclaim,
+    Penalty,
+    WithdrawalFee,
+    DustSweep,
+}
+
+/// Running totals of treasury revenue by category, net of ledger fees (micro-USD)
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TreasuryBuckets {
+    pub activation_fees_e6: UsdE6,
+    pub expired_trade_reclaims_e6: UsdE6,
+    pub penalties_e6: UsdE6,
+    pub withdrawal_fees_e6: UsdE6,
+    pub dust_swept_e6: UsdE6,
+    pub withdrawn_by_admin_e6: UsdE6,   // Paid out via admin_withdraw_ckusdc_treasury
+    pub last_updated_at: Option<u64>,
+    pub rebates_accrued_e6: Option<UsdE6>,  // Activation fees set aside for maker rebates (pooled, owed or paid)
+    pub appeal_refunds_e6: Option<UsdE6>,   // Paid back to fillers whose penalty appeals were upheld
+    pub late_claims_paid_e6: Option<UsdE6>, // Expired trade reclaims paid out to late claims, with ledger fees
+    pub other_ledgers: Option<Vec<LedgerRevenue>>, // Other books' settlement tokens - never counted above
+}
+
+/// Revenue held in the main account on a non-ckUSDC book's settlement ledger (token base units,
+/// net of ledger fees). Rebates, appeal refunds and admin withdrawals only ever spend ckUSDC
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct LedgerRevenue {
+    pub ledger: Principal,
+    pub activation_fees: u64,
+    pub expired_trade_reclaims: u64,
+    pub late_claims_paid: u64,
+}
+
+impl LedgerRevenue {
+    pub fn new(ledger: Principal) -> Self {
+        Self { ledger, activation_fees: 0, expired_trade_reclaims: 0, late_claims_paid: 0 }
+    }
+
+    /// Reclaims not yet paid back out to late claims - all late claims on the ledger may spend
+    pub fn reclaims_held(&self) -> u64 {
+        self.expired_trade_reclaims.saturating_sub(self.late_claims_paid)
+    }
+}
+
+impl TreasuryBuckets {
+    pub fn total_revenue_e6(&self) -> UsdE6 {
+        self.activation_fees_e6
+            + self.expired_trade_reclaims_e6
+            + self.penalties_e6
+            + self.withdrawal_fees_e6
+            + self.dust_swept_e6
+    }
+}
+
+/// Split of the main-account ckUSDC balance into operator revenue and funds passing through
+/// (withdrawal amounts and gas fees awaiting the minter)
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TreasuryReport {
+    pub buckets: TreasuryBuckets,
+    pub total_revenue_e6: UsdE6,
+    pub revenue_held_e6: UsdE6,                 // Revenue not yet withdrawn by the admin, less rebates
+    pub main_account_balance_e6: Option<UsdE6>, // None if the ledger call failed
+    pub pass_through_e6: Option<UsdE6>,         // Balance beyond the revenue held
+    pub generated_at: u64,
+}
+
+/// Everything the canister holds across currencies, next to what it owes - see get_treasury_balances
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TreasuryBalances {
+    pub ckusdc_main_account_e6: Option<UsdE6>,  // None if the ledger call failed
+    pub cketh_wei: Option<Nat>,                 // Gas treasury - None if the ledger call failed
+    pub cketh_below_watermark: bool,
+    pub cycles_balance: u64,
+    pub open_orders: u64,
+    pub order_subaccounts_e6: UsdE6,            // Estimated: unfilled amount + incentive of open orders
+    pub earmarked_for_trades_e6: UsdE6,         // Part of the above locked by in-flight trades
+    pub revenue_held_e6: UsdE6,
+    pub generated_at: u64,
+}
+
+// ===== MAKER FEE REBATES =====
+
+/// One rebate epoch - REBATE_SHARE_BPS of every activation fee goes into its pool while it is
+/// open, and on close the pool is split between makers by the volume their orders filled
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RebateEpoch {
+    pub epoch: u64,
+    pub started_at: u64,
+    pub closed_at: Option<u64>,
+    pub pool_e6: UsdE6,                 // Includes what the previous epoch carried over
+    pub total_volume_e6: UsdE6,         // Filled volume of all makers, set on close
+    pub distributed_e6: UsdE6,          // Rounding and volume-less epochs carry the rest over
+    pub makers_rebated: u64,
+    pub claims: u64,                    // Claims started while this epoch was open - numbers their memos
+}
+
+/// A claim whose ledger outcome isn't known yet - retried with the same memo and amount, so
+/// the ledger deduplicates it instead of paying twice
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PendingRebateClaim {
+    pub memo: String,
+    pub amount_e6: UsdE6,
+}
+
+/// A maker's rebates - volume filled in the open epoch, and rebates owed and paid
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MakerRebates {
+    pub maker: Principal,
+    pub epoch_volume_e6: UsdE6,
+    pub owed_e6: UsdE6,
+    pub claimed_e6: UsdE6,              // Gross - the ledger fee came out of each payout
+    pub pending_claim: Option<PendingRebateClaim>,
+    pub last_claimed_at: Option<u64>,
+}
+
+// ===== ORDERBOOKS =====
+
+/// BSV network a book's makers are paid on - the canister follows one at a time
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BsvNetwork {
+    Mainnet,
+    Testnet,
+}
+
+impl BsvNetwork {
+    /// Base58Check version byte of P2PKH addresses
+    pub fn p2pkh_version(self) -> u8 {
+        match self {
+            BsvNetwork::Mainnet => 0x00,
+            BsvNetwork::Testnet => 0x6f,
+        }
+    }
+
+    /// Base58Check version byte of P2SH addresses
+    pub fn p2sh_version(self) -> u8 {
+        match self {
+            BsvNetwork::Mainnet => 0x05,
+            BsvNetwork::Testnet => 0xc4,
+        }
+    }
+
+    pub fn woc_api(self) -> &'static str {
+        match self {
+            BsvNetwork::Mainnet => crate::config::WOC_MAINNET_API,
+            BsvNetwork::Testnet => crate::config::WOC_TESTNET_API,
+        }
+    }
+
+    pub fn bitails_api(self) -> &'static str {
+        match self {
+            BsvNetwork::Mainnet => crate::config::BITAILS_MAINNET_API,
+            BsvNetwork::Testnet => crate::config::BITAILS_TESTNET_API,
+        }
+    }
+}
+
+/// An independent book of orders settling in one token against one BSV network - orders only
+/// match trades of their own book. Book 0 is the original ckUSDC/BSV-mainnet book
+/// Settlement tokens are 6-decimal USD stablecoins, so amounts stay in micro-USD
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderBook {
+    pub id: BookId,
+    pub settlement_symbol: String,          // e.g. "ckUSDT"
+    pub settlement_ledger: Principal,       // Funds, refunds and filler payouts of its orders
+    pub bsv_network: BsvNetwork,
+    pub enabled: bool,                      // Disabled books take no new orders - open ones continue
+    pub max_orderbook_usd_e6: Option<UsdE6>, // None = MAX_ORDERBOOK_USD_LIMIT_E6
+    pub trade_size_limits: Option<TradeSizeLimits>, // None = the global trade size limits
+    pub created_at: u64,
+}
+
+/// Size and activity of one book
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BookStats {
+    pub book_id: BookId,
+    pub settlement_symbol: String,
+    pub total_orders: u64,
+    pub open_orders: u64,                   // Active or Idle
+    pub total_active_chunks: u64,           // Listed chunks
+    pub total_available_usd_e6: UsdE6,
+    pub total_locked_usd_e6: UsdE6,
+    pub total_filled_usd_e6: UsdE6,
+    pub total_trades: u64,
+}
+
+// ===== ckETH GAS BUDGET =====
+
+/// Watermark settings and last observed ckETH balance of the gas treasury
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CkEthMonitor {
+    pub low_watermark_wei: Nat,
+    pub auto_disable_withdrawals: bool,     // Reject ETH withdrawals while below the watermark
+    pub last_balance_wei: Option<Nat>,
+    pub last_checked_at: Option<u64>,
+    pub below_watermark: bool,
+}
+
+impl Default for CkEthMonitor {
+    fn default() -> Self {
+        Self {
+            low_watermark_wei: Nat::from(crate::config::DEFAULT_CKETH_LOW_WATERMARK_WEI),
+            auto_disable_withdrawals: true,
+            last_balance_wei: None,
+            last_checked_at: None,
+            below_watermark: false,
+        }
+    }
+}
+
+/// Runtime retention table for data_cleanup - defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RetentionPolicy {
+    pub order_retention_days: u64,
+    pub trade_retention_days: u64,
+    pub admin_event_retention_days: u64,
+    pub max_admin_events: u64,
+    pub blocks_to_keep: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        const DAY_SECONDS: u64 = 24 * 60 * 60;
+        Self {
+            order_retention_days: crate::config::ORDER_RETENTION_SECONDS / DAY_SECONDS,
+            trade_retention_days: crate::config::TRADE_RETENTION_SECONDS / DAY_SECONDS,
+            admin_event_retention_days: crate::config::ADMIN_EVENTS_RETENTION_SECONDS / DAY_SECONDS,
+            max_admin_events: crate::config::MAX_ADMIN_EVENTS,
+            blocks_to_keep: crate::config::MAX_BLOCKS_TO_KEEP,
+        }
+    }
+}
+
+/// Runtime open-interest caps checked when trades are created - defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ExposureLimits {
+    pub max_filler_open_interest_e6: UsdE6,     // Locked notional per filler across their open trades
+    pub max_total_locked_e6: UsdE6,             // Locked notional across all fillers
+}
+
+/// Proposed limits for admin_simulate_config - None keeps the current value
+/// Chunk size and order/maker/orderbook caps are compile-time (config.rs) and change with an upgrade;
+/// exposure limits change at runtime via admin_set_exposure_limits
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigProposal {
+    pub min_chunk_size_e6: Option<UsdE6>,
+    pub max_chunks_per_order: Option<u64>,
+    pub max_orderbook_usd_e6: Option<UsdE6>,
+    pub max_maker_total_orders_usd_e6: Option<UsdE6>,
+    pub exposure_limits: Option<ExposureLimits>,
+}
+
+/// What existing Active/Idle orders, makers and fillers would violate under a ConfigProposal
+/// Nothing is changed - orders already placed stay valid, but over-limit makers and the
+/// orderbook block new orders, and over-limit fillers new locks, until they drain
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigImpactReport {
+    // Effective values evaluated (proposal merged over the current config)
+    pub min_chunk_size_e6: UsdE6,
+    pub max_chunks_per_order: u64,
+    pub max_orderbook_usd_e6: UsdE6,
+    pub max_maker_total_orders_usd_e6: UsdE6,
+    pub exposure_limits: ExposureLimits,
+    pub exposure_limits_error: Option<String>,    // admin_set_exposure_limits would reject these
+    
+    pub active_orders: u64,                       // Active or Idle orders checked
+    pub open_chunks: u64,                         // Their Available or Idle chunks
+    pub invalid_orders: Vec<OrderId>,             // Would fail the chunk size or count rules
+    pub chunks_below_min_size: u64,
+    pub orders_with_chunks_below_min_size: Vec<OrderId>,
+    pub orders_over_max_chunks: Vec<OrderId>,
+    pub orderbook_usd_e6: UsdE6,
+    pub orderbook_over_limit: bool,
+    pub makers_over_total_limit: Vec<Principal>,
+    pub total_locked_usd_e6: UsdE6,
+    pub total_locked_over_limit: bool,
+    pub fillers_over_open_interest_limit: Vec<Principal>,
+    pub generated_at: u64,
+}
+
+impl Default for ExposureLimits {
+    fn default() -> Self {
+        Self {
+            max_filler_open_interest_e6: crate::config::MAX_FILLER_OPEN_INTEREST_E6,
+            max_total_locked_e6: crate::config::MAX_TOTAL_LOCKED_E6,
+        }
+    }
+}
+
+/// Runtime bounds on a single trade request - defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TradeSizeLimits {
+    pub min_trade_usd_e6: UsdE6,     // Smaller create_trades requests and quotes are rejected
+    pub max_chunks_per_trade: u64,   // Chunks one trade may lock
+}
+
+impl Default for TradeSizeLimits {
+    fn default() -> Self {
+        Self {
+            min_trade_usd_e6: crate::config::MIN_TRADE_SIZE_E6,
+            max_chunks_per_trade: crate::config::MAX_CHUNKS_PER_TRADE,
+        }
+    }
+}
+
+/// One step of the timeout penalty schedule
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TimeoutPenaltyTier {
+    pub expired_under_ns: u64,   // Applies while the trade is less than this far past lock_expires_at
+    pub penalty_bps: u64,        // Of the trade amount, taken from the filler's deposit for the maker
+}
+
+/// Penalty on a trade that expired without a BSV payment, by how late it was unlocked -
+/// defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TimeoutPenaltySchedule {
+    pub tiers: Vec<TimeoutPenaltyTier>,   // Ascending expired_under_ns
+    pub final_penalty_bps: u64,           // Once past the last tier
+}
+
+impl TimeoutPenaltySchedule {
+    pub fn penalty_bps(&self, expired_for_ns: u64) -> u64 {
+        self.tiers.iter()
+            .find(|tier| expired_for_ns < tier.expired_under_ns)
+            .map(|tier| tier.penalty_bps)
+            .unwrap_or(self.final_penalty_bps)
+    }
+}
+
+impl Default for TimeoutPenaltySchedule {
+    fn default() -> Self {
+        Self {
+            tiers: crate::config::TIMEOUT_PENALTY_TIERS.iter()
+                .map(|&(expired_under_ns, penalty_bps)| TimeoutPenaltyTier { expired_under_ns, penalty_bps })
+                .collect(),
+            final_penalty_bps: crate::config::TIMEOUT_PENALTY_FINAL_BPS,
+        }
+    }
+}
+
+/// How a maker's headroom between the oracle price and their max_bsv_price is split at match time -
+/// defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PriceImprovementSharing {
+    pub threshold_bps: u64,     // Gaps up to this share of the max price aren't split
+    pub filler_share_bps: u64,  // Filler's share of a larger gap - the maker keeps the rest
+}
+
+impl PriceImprovementSharing {
+    /// Price a trade's sats are calculated at, when a gap is split - None = the oracle price
+    pub fn effective_bsv_price(&self, oracle_bsv_price: f64, max_bsv_price: f64) -> Option<f64> {
+        let gap = max_bsv_price - oracle_bsv_price;
+        if self.filler_share_bps == 0 || gap <= max_bsv_price * self.threshold_bps as f64 / 10_000.0 {
+            return None;
+        }
+        Some(oracle_bsv_price + gap * self.filler_share_bps as f64 / 10_000.0)
+    }
+}
+
+impl Default for PriceImprovementSharing {
+    fn default() -> Self {
+        Self {
+            threshold_bps: crate::config::PRICE_IMPROVEMENT_THRESHOLD_BPS,
+            filler_share_bps: crate::config::PRICE_IMPROVEMENT_FILLER_SHARE_BPS,
+        }
+    }
+}
+
+/// One step of the confirmation depth schedule
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ConfirmationDepthTier {
+    pub trade_under_usd_e6: UsdE6,  // Applies to trades smaller than this
+    pub confirmations: u64,
+}
+
+/// Blocks a trade's BSV payment must be buried under before claim_usdc releases it, by trade
+/// size - defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ConfirmationDepthSchedule {
+    pub tiers: Vec<ConfirmationDepthTier>,  // Ascending trade_under_usd_e6
+    pub default_confirmations: u64,         // Trades at or above the last tier
+}
+
+impl ConfirmationDepthSchedule {
+    pub fn confirmations_for(&self, amount_usd_e6: UsdE6) -> u64 {
+        self.tiers.iter()
+            .find(|tier| amount_usd_e6 < tier.trade_under_usd_e6)
+            .map(|tier| tier.confirmations)
+            .unwrap_or(self.default_confirmations)
+    }
+}
+
+impl Default for ConfirmationDepthSchedule {
+    fn default() -> Self {
+        Self {
+            tiers: crate::config::CONFIRMATION_DEPTH_TIERS.iter()
+                .map(|&(trade_under_usd_e6, confirmations)| ConfirmationDepthTier { trade_under_usd_e6, confirmations })
+                .collect(),
+            default_confirmations: crate::config::CONFIRMATION_DEPTH,
+        }
+    }
+}
+
+/// How far a block fetched from TxArchive (not in local storage) is trusted
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum TxArchiveTrust {
+    /// TxArchive only holds buried blocks - treat them as CONFIRMATION_DEPTH deep
+    #[default]
+    AssumeConfirmed,
+    /// Count confirmations from the local chain tip like any stored block
+    CountFromLocalTip,
+}
+
+/// TxArchive fallback - defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TxArchivePolicy {
+    pub enabled: bool,              // false = block sync and claims never fall back to TxArchive
+    pub canister: Principal,
+    pub trust: TxArchiveTrust,
+}
+
+impl Default for TxArchivePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            canister: Principal::from_text(crate::config::TXARCHIVE_CANISTER_ID).expect("Invalid TXARCHIVE_CANISTER_ID"),
+            trust: TxArchiveTrust::default(),
+        }
+    }
+}
+
+/// Trading parameters fillers and makers need to price their risk - runtime values where the
+/// admin can adjust them, config constants otherwise
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PublicConfig {
+    pub security_deposit_percent: u64,
+    pub max_lock_multiplier: u64,
+    pub trusted_filler_min_successful_trades: u64,
+    pub trusted_filler_clean_window_ns: u64,
+    pub trusted_filler_security_deposit_percent: u64,
+    pub trusted_filler_lock_multiplier: u64,
+    pub min_chunk_size_usd_e6: UsdE6,
+    pub trade_timeout_ns: u64,
+    pub lock_extension_ns: u64,
+    pub lock_extension_fee_bps: u64,
+    pub default_filler_incentive_bps: u64,
+    pub filler_incentive_bounds: FillerIncentiveBounds,
+    pub trade_size_limits: TradeSizeLimits,
+    pub matching_policy: MatchingPolicy,
+    pub timeout_penalty_schedule: TimeoutPenaltySchedule,
+    pub price_improvement_sharing: PriceImprovementSharing,
+    pub confirmation_depth_schedule: ConfirmationDepthSchedule,
+    pub instant_release_max_trade_usd_e6: UsdE6,
+    pub instant_release_confirmations: u64,
+    pub max_chain_tip_age_seconds: u64,
+    pub txarchive_policy: TxArchivePolicy,
+    pub bsv_network: BsvNetwork,
+}
+
+/// Range of filler incentives makers may offer on new orders - defaults mirror the config constants
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct FillerIncentiveBounds {
+    pub min_bps: u64,
+    pub max_bps: u64,
+}
+
+impl Default for FillerIncentiveBounds {
+    fn default() -> Self {
+        Self {
+            min_bps: crate::config::FILLER_INCENTIVE_MIN_BPS,
+            max_bps: crate::config::FILLER_INCENTIVE_MAX_BPS,
+        }
+    }
+}
+
+/// How create_trades spreads a fill across orders accepting the market price
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum MatchingPolicy {
+    #[default]
+    Fifo,           // Oldest order first, drained before the next
+    ProRata,        // Split in proportion to each order's available amount
+    BestIncentive,  // Highest filler incentive first, FIFO among equal incentives
+}
+
+/// What the next data_cleanup run would remove under the current policy
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CleanupPreview {
+    pub policy: RetentionPolicy,
+    pub archive_enabled: bool,      // Orders/trades go to the archive canister instead of being deleted
+    pub order_ids: Vec<OrderId>,
+    pub chunk_count: u64,
+    pub trade_ids: Vec<TradeId>,
+    pub block_count: u64,
+    pub min_block_height_kept: u64,
+    pub admin_event_count: u64,
+    pub generated_at: u64,
+}
+
+/// Operational health summary for monitoring
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct HealthStatus {
+    pub cycles_balance: u64,
+    pub new_orders_enabled: bool,
+    pub trading_halted: bool,               // Circuit breaker tripped
+    pub eth_withdrawals_enabled: bool,
+    pub cketh: CkEthMonitor,
+    pub checked_at: u64,
+}
+
+/// Stable memory claimed by one map - see memory_stats.rs
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MapMemoryStats {
+    pub name: String,           // Backup section name
+    pub memory_id: u8,
+    pub pages: u64,             // 64 KiB pages
+    pub bytes: u64,
+    pub records: u64,
+}
+
+/// Stable memory per map (largest first) and heap usage
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MemoryStats {
+    pub maps: Vec<MapMemoryStats>,
+    pub map_pages_total: u64,
+    pub stable_pages_total: u64,    // Whole stable memory, memory manager header included
+    pub stable_bytes_total: u64,
+    pub heap_bytes: u64,
+    pub measured_at: u64,
+}
+
+// ===== ETH WITHDRAWALS =====
+
+/// Where a ckUSDC withdrawal goes: the minter for a chain, the ck-token ledger it burns
+/// and the ledger of the gas token the canister pays with
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct WithdrawalRoute {
+    pub chain: String,              // Route name passed to withdraw_ckusdc_to_eth, e.g. "ethereum"
+    pub minter: Principal,
+    pub ckusdc_ledger: Principal,
+    pub gas_ledger: Principal,      // ckETH on Ethereum
+    pub gas_token_symbol: String,
+    pub enabled: bool,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum EthWithdrawalStatus {
+    Pending,        // ckUSDC pulled from the user, minter call in progress
+    Submitted,      // Accepted by the minter
+    Unconfirmed,    // withdraw_erc20 call rejected - may have gone through, see reconcile_unconfirmed_withdrawals
+    RefundPending,  // Minter steps failed or the minter reimbursed - refund not yet sent (retried by timer)
+    Refunded,       // ckUSDC returned to the user
+    Completed,      // USDC delivered on Ethereum
+}
+
+/// Progress of a submitted withdrawal as reported by the minter's retrieve_eth_status
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MinterWithdrawalStatus {
+    NotFound,
+    Pending,
+    TxCreated,
+    TxSigned,
+    TxSent,
+    Finalized,              // USDC delivered on Ethereum
+    PendingReimbursement,   // Ethereum transaction failed - minter will reimburse
+    Reimbursed,             // Minter returned the ckUSDC to the canister
+}
+
+/// A ckUSDC -> Ethereum USDC withdrawal, recorded once the user's ckUSDC is pulled
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct EthWithdrawal {
+    pub id: u64,
+    pub user: Principal,
+    pub recipient_address: String,
+    pub withdrawal_amount_e6: UsdE6,
+    pub gas_fee_e6: UsdE6,
+    pub treasury_fee_e6: UsdE6,
+    pub status: EthWithdrawalStatus,
+    pub pull_block_index: Option<u64>,      // icrc2_transfer_from from the user
+    pub cketh_block_index: Option<Nat>,     // From the minter's RetrieveErc20Request
+    pub ckerc20_block_index: Option<Nat>,
+    pub refund_block_index: Option<u64>,
+    pub refund_attempts: u32,
+    pub error: Option<String>,              // Why the minter steps failed
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub minter_status: Option<MinterWithdrawalStatus>,  // Last polled, None before the first poll
+    pub eth_tx_hash: Option<String>,
+    pub finalized_at: Option<u64>,
+    pub route: Option<WithdrawalRoute>,     // Route at submission - None = default Ethereum route
+    pub reimbursed_e6: Option<UsdE6>,       // ckUSDC the minter returned after a failed Ethereum transaction - refunded instead of the full pull
+}
+
+// ===== SCHEMA TYPES =====
+
+/// Schema version of the records held in stable memory
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SchemaState {
+    pub version: u32,
+    pub last_migrated_at: Option<u64>,
+    pub rollback_mode: bool,                    // Admin hold - post_upgrade leaves records at `version`
+    pub last_report: Option<MigrationReport>,   // Most recent migration run (or aborted run)
+}
+
+/// Outcome of validating (and optionally applying) pending migrations
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub dry_run: bool,
+    pub orders_checked: u64,
+    pub chunks_checked: u64,
+    pub trades_checked: u64,
+    pub failures: Vec<String>,  // "order 12: <decode error>" - any failure aborts the migration
+    pub ran_at: u64,
+}
+
+/// A stored record no layout could decode, moved out of its map by the post_upgrade sweep
+/// (see record_envelope.rs) - the raw bytes are kept so it can be repaired by hand
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CorruptRecord {
+    pub id: u64,
+    pub map_name: String,       // e.g. "orders"
+    pub memory_id: u8,
+    pub key: Vec<u8>,           // Storable bytes of the key
+    pub value: Vec<u8>,         // Value bytes as stored
+    pub error: String,          // Decode error of the last layout tried
+    pub quarantined_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CorruptRecordPage {
+    pub records: Vec<CorruptRecord>,
+    pub next_cursor: Option<u64>,
+}
+
+// ===== BACKUP TYPES =====
+
+/// Describes one backup snapshot - needed again (unchanged) to restore it
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupManifest {
+    pub backup_id: u64,                   // Creation time (ns), identifies the snapshot
+    pub format_version: u32,
+    pub schema_version: u32,              // Schema of the records inside (see migrations.rs)
+    pub created_at: u64,
+    pub total_bytes: u64,
+    pub chunk_size: u64,
+    pub chunk_count: u64,
+    pub sha256: String,                   // Hex hash over the whole snapshot
+    pub chunk_hashes: Vec<String>,        // Hex hash of each chunk, in order
+    pub sections: Vec<BackupSectionCount>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupSectionCount {
+    pub section: String,  // Stable map name, e.g. "orders"
+    pub entries: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BackupChunk {
+    pub backup_id: u64,
+    pub index: u64,
+    pub data: Vec<u8>,
+}
+
+/// Progress of a restore - chunks can arrive over several calls
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RestoreStatus {
+    pub backup_id: u64,
+    pub chunks_received: u64,
+    pub chunk_count: u64,
+    pub completed: bool,
+    pub entries_restored: u64,
+}
+
+// ===== FILLER ACCOUNT TYPES =====
+
+/// A principal cleared to trade while the filler allowlist is enabled
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ApprovedFiller {
+    pub filler: Principal,
+    pub approved_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct FillerAccount {
+    pub id: FillerAccountId,
+    pub pending_trades_total: f64,  // Total USD in pending trades
+    pub total_trades: u64,
+    pub successful_trades: u64,
+    pub penalties_paid: f64,
+    pub created_at: u64,
+    pub timeout_releases: Option<u64>,  // Chunk locks released because this filler let the trade time out
+    pub last_penalty_at: Option<u64>,   // Keeps them off the trusted tier for TRUSTED_FILLER_CLEAN_WINDOW_NS
+    pub instant_release: Option<bool>,  // Opted in to bonded instant release - see release_bonds.rs
+}
+
+/// Token fillers may post as security besides ckUSDC, held in the same deposit subaccount
+/// on its own ledger - only credit_bps of its USD value counts, the rest is the haircut
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CollateralToken {
+    pub symbol: String,                 // e.g. "ckBTC" - also the key for admin updates
+    pub ledger: Principal,
+    pub decimals: u8,
+    pub transfer_fee: u64,              // Ledger fee, in token units
+    pub price_symbol: Option<String>,   // XRC asset quoted against USD - None = pegged to $1
+    pub credit_bps: u64,                // e.g. 8_000 = 80% of USD value credited
+    pub disabled: Option<bool>,         // Removed by admin - no longer credited or taken for penalties, still withdrawable
+}
+
+impl CollateralToken {
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.unwrap_or(false)
+    }
+}
+
+/// One collateral token held by a filler, valued at the cached oracle rate
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CollateralBalance {
+    pub symbol: String,
+    pub balance: u64,                   // Token units
+    pub usd_value_e6: UsdE6,
+    pub credited_e6: UsdE6,             // After the haircut
+}
+
+/// Live security deposit of a filler against their open trades - see get_my_security_breakdown
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SecurityBreakdown {
+    pub total_balance_e6: UsdE6,              // ckUSDC plus credited collateral
+    pub ckusdc_balance_e6: UsdE6,             // On the ckUSDC ledger
+    pub collateral: Vec<CollateralBalance>,   // Other tokens, priced when this was built
+    pub trusted: bool,                        // On the reduced-deposit tier
+    pub security_deposit_percent: u64,        // Of each trade, for this filler
+    pub lock_multiplier: u64,                 // Times their security they may lock
+    pub pending_trades_e6: UsdE6,             // Open trades not yet released or penalized
+    pub locked_e6: UsdE6,                     // security_deposit_percent of pending_trades_e6
+    pub release_bonds_e6: UsdE6,              // Held against instantly released trades
+    pub available_e6: UsdE6,                  // total_balance_e6 - locked_e6 - release_bonds_e6
+    pub max_lock_capacity_e6: UsdE6,          // lock_multiplier x total, capped by the open interest limit
+    pub remaining_lock_capacity_e6: UsdE6,    // Largest create_trades request that fits
+    pub checked_at: u64,
+}
+
+// ===== API VERSIONING TYPES =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ApiEndpointStatus {
+    Stable,
+    Deprecated,
+}
+
+/// Version and deprecation status of a public endpoint - see api_versions.rs
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ApiEndpointInfo {
+    pub name: String,
+    pub version: u32,                  // API version that introduced this signature
+    pub status: ApiEndpointStatus,
+    pub deprecated_in: Option<u32>,    // API version that deprecated it
+    pub replaced_by: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ApiVersionInfo {
+    pub current_version: u32,
+    pub supported_versions: Vec<u32>,
+    pub endpoints: Vec<ApiEndpointInfo>,  // Endpoints not listed are v1 and Stable
+}
+
+// ===== HTTP GATEWAY TYPES =====
+
+/// Request passed to http_request by the IC HTTP gateway
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpGatewayRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(CandidType, Serialize, Clone, Debug)]
+pub struct HttpGatewayResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+// ===== STATS TYPES =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderbookStats {
+    pub total_active_chunks: u64,
+    pub total_available_usd: f64,
+    pub total_locked_usd: f64,
+    pub total_orders: u64,
+    pub total_trades: u64,
+    pub current_bsv_price: f64,
+    pub largest_filler_open_interest_usd: f64,
+    pub max_filler_open_interest_usd: f64,
+    pub max_total_locked_usd: f64,
+}
+
+/// OrderbookStats in fixed point for the v2 API - USD in micro-USD, price in cents
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderbookStatsV2 {
+    pub total_active_chunks: u64,
+    pub total_available_usd_e6: UsdE6,
+    pub total_locked_usd_e6: UsdE6,
+    pub total_orders: u64,
+    pub total_trades: u64,
+    pub current_bsv_price_cents: u64,
+    pub largest_filler_open_interest_usd_e6: UsdE6,
+    pub max_filler_open_interest_usd_e6: UsdE6,
+    pub max_total_locked_usd_e6: UsdE6,
+}
+
+/// An order whose subaccount holds less ckUSDC than its open chunks are owed
+/// Cleared automatically once a later reconciliation run finds the balance sufficient
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ReconciliationIssue {
+    pub order_id: OrderId,
+    pub maker: Principal,
+    pub expected_balance_e6: UsdE6,   // Available + Idle + Locked chunks, each plus filler incentive
+    pub ledger_balance_e6: UsdE6,
+    pub shortfall_e6: UsdE6,
+    pub first_detected_at: u64,
+    pub last_detected_at: u64,
+    pub occurrences: u64,             // Consecutive runs reporting it - 1 may be a claim in flight
+}
+
+/// Completed-trade totals for one UTC hour - kept for MARKET_STATS_RETENTION_HOURS
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct HourlyMarketStats {
+    pub hour: u64,                  // Hours since Unix epoch (UTC)
+    pub volume_usd_e6: UsdE6,
+    pub volume_sats: u64,           // BSV paid to makers - volume_usd / volume_bsv gives the average price
+    pub trade_count: u64,
+    pub makers: Vec<Principal>,     // Distinct makers/fillers with a completed trade this hour
+    pub fillers: Vec<Principal>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum StatsWindow {
+    Day,
+    Week,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MarketStats {
+    pub window: StatsWindow,
+    pub from_hour: u64,
+    pub to_hour: u64,
+    pub volume_usd_e6: UsdE6,
+    pub trade_count: u64,
+    pub average_price: Option<f64>,  // Volume-weighted USD per BSV, None without trades
+    pub unique_makers: u64,
+    pub unique_fillers: u64,
+}
+
+/// Independent BSV/USD feeds aggregated by the price oracle
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PriceSource {
+    Xrc,
+    WhatsOnChain,
+    Coinlore,
+}
+
+/// Trading halt after an extreme price move - new trades and idle chunk reactivation pause
+/// until the price has stayed within bounds for CIRCUIT_BREAKER_COOLDOWN_NS
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct CircuitBreakerState {
+    pub tripped_at: u64,
+    pub last_breach_at: u64,    // Latest sample at which the window move still exceeded the limit
+    pub low_price: f64,         // Price range within the window when tripped
+    pub high_price: f64,
+    pub move_bps: u64,
+}
+
+/// One feed's result in the latest oracle round
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PriceSourceSample {
+    pub source: PriceSource,
+    pub price: Option<f64>,
+    pub error: Option<String>,
+    pub accepted: bool,         // false if the feed failed or deviated too far from the median
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PriceSources {
+    pub median_price: Option<f64>,  // Price adopted this round (None if too few feeds agreed)
+    pub samples: Vec<PriceSourceSample>,
+    pub fetched_at: u64,
+}
+
+/// OHLC of the oracle prices used for matching - stored per hour, larger intervals merged on query
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PriceCandle {
+    pub start: u64,     // Interval start (ns)
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub samples: u64,   // Oracle fetches in the interval
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CandleInterval {
+    Hour,
+    FourHours,
+    Day,
+}
+
+/// Aggregated orderbook liquidity at one max price
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DepthLevel {
+    pub max_price_per_bsv_in_cents: u64,
+    pub amount_usd_e6: UsdE6,
+    pub chunk_count: u64,
+}
+
+/// Composite market snapshot for aggregator canisters and bots
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PublicMarketData {
+    pub bsv_price: f64,                 // Cached oracle price (USD)
+    pub price_updated_at: u64,
+    pub last_trade_price: Option<f64>,  // Agreed price of the most recently completed trade
+    pub last_trade_at: Option<u64>,
+    pub volume_24h_usd_e6: UsdE6,       // Completed trades in the last 24 hours
+    pub trades_24h: u64,
+    pub total_available_usd_e6: UsdE6,
+    pub depth: Vec<DepthLevel>,         // Highest max price first
+    pub generated_at: u64,
+}
+
+/// One line of the public trade tape - no principals, addresses or txids
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PublicTrade {
+    pub amount_usd_e6: UsdE6,
+    pub sats_amount: u64,
+    pub bsv_price: f64,                 // Agreed USD per BSV
+    pub status: TradeStatus,
+    pub completed_at: u64,
+}
+
+// ===== BSV TRANSACTION TYPES =====
+
+#[derive(Debug, Clone)]
+pub struct BsvOutput {
+    pub address: String,
+    pub satoshis: u64,
+    pub script_type: OutputScriptType,
+    pub script_pubkey: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputScriptType {
+    P2PKH,    // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+    P2SH,     // OP_HASH160 <20 bytes> OP_EQUAL
+    OpReturn, // OP_RETURN or OP_FALSE OP_RETURN data carrier
+    Other,    // Anything else (non-standard)
+}
+
+#[derive(Debug)]
+pub struct ParsedBsvTx {
+    pub version: u32,
+    pub inputs: Vec<BsvInput>,
+    pub outputs: Vec<BsvOutput>,
+    pub locktime: u32,
+    pub size_bytes: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BsvInput {
+    pub prev_tx_hash: Vec<u8>,
+    pub prev_output_index: u32,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+}
+
+// ===== HTTP PROVIDER TYPES =====
+
+/// External block data providers queried via HTTPS outcalls
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ApiProvider {
+    WhatsOnChain,
+    Bitails,
+}
+
+// ===== DEPOSIT RECOVERY TYPES =====
+
+/// Funds swept back to a maker from an order subaccount with no matching order
+/// (e.g. order creation failed after the maker had already deposited)
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DepositRecovery {
+    pub id: u64,
+    pub maker: Principal,
+    pub order_id: OrderId,       // Order ID the subaccount was derived from
+    pub amount_usd: f64,         // Net amount received by maker (after transfer fee)
+    pub block_index: u64,
+    pub recovered_at: u64,
+}
+
+/// Result of one recover_orphaned_deposits call
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DepositRecoveryScan {
+    pub scanned_from: OrderId,
+    pub scanned_to: OrderId,             // Exclusive
+    pub recovered: Vec<DepositRecovery>,
+    pub errors: Vec<String>,
+    pub next_order_id: Option<OrderId>,  // Pass as from_order_id to continue scanning, None when done
+}
+
+// ===== DUST SWEEP TYPES =====
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DustSweepEntry {
+    pub order_id: OrderId,
+    pub maker: Principal,
+    pub order_status: OrderStatus,
+    pub balance_usd: f64,
+    pub block_index: Option<u64>,  // Set when swept (None in dry-run or when skipped)
+    pub note: Option<String>,      // Why the balance was skipped or failed
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DustSweepReport {
+    pub dry_run: bool,
+    pub scanned_from: OrderId,
+    pub entries: Vec<DustSweepEntry>,  // Only orders with a non-zero balance
+    pub total_swept_usd: f64,          // Gross amount leaving subaccounts (or that would in dry-run)
+    pub next_order_id: Option<OrderId>, // Pass as from_order_id to continue, None when done
+}
+
+// ===== STORABLE IMPLEMENTATIONS =====
+
+impl Storable for Order {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for Order {
+    // Bare records written before the micro-USD migration still hold f64 amounts
+    fn decode_previous(version: u8, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            0 => Decode!(payload, Self)
+                .or_else(|_| Decode!(payload, crate::legacy_types::LegacyOrder).map(Self::from))
+                .map_err(|e| e.to_string()),
+            _ => Err(format!("Unknown layout version {}", version)),
+        }
+    }
+}
+
+impl Storable for Chunk {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for Chunk {
+    // Bare records written before the micro-USD migration still hold f64 amounts
+    fn decode_previous(version: u8, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            0 => Decode!(payload, Self)
+                .or_else(|_| Decode!(payload, crate::legacy_types::LegacyChunk).map(Self::from))
+                .map_err(|e| e.to_string()),
+            _ => Err(format!("Unknown layout version {}", version)),
+        }
+    }
+}
+
+impl Storable for Trade {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for Trade {
+    // Bare records written before the micro-USD migration still hold f64 amounts
+    fn decode_previous(version: u8, payload: &[u8]) -> Result<Self, String> {
+        match version {
+            0 => Decode!(payload, Self)
+                .or_else(|_| Decode!(payload, crate::legacy_types::LegacyTrade).map(Self::from))
+                .map_err(|e| e.to_string()),
+            _ => Err(format!("Unknown layout version {}", version)),
+        }
+    }
+}
+
+
+impl Storable for TradeDispute {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for TradeDispute {}
+
+impl Storable for PenaltyAppeal {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for PenaltyAppeal {}
+
+impl Storable for LateClaim {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for LateClaim {}
+
+impl Storable for OrderbookChange {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for OrderbookChange {}
+
+impl Storable for MakerFillerBlocklist {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for MakerFillerBlocklist {}
+
+impl Storable for MakerAutoTopUp {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for MakerAutoTopUp {}
+
+impl Storable for RebateEpoch {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for RebateEpoch {}
+
+impl Storable for MakerRebates {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for MakerRebates {}
+
+impl Storable for OrderBook {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for OrderBook {}
+
+impl Storable for OperatorDelegation {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for OperatorDelegation {}
+
+impl Storable for ReleaseBond {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for ReleaseBond {}
+
+impl Storable for VerifiedInclusion {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for VerifiedInclusion {}
+
+impl Storable for MakerQuietHours {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for MakerQuietHours {}
+
+impl Storable for DailySettlementReport {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for DailySettlementReport {}
+
+impl Storable for HourlyMarketStats {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for HourlyMarketStats {}
+
+impl Storable for ReconciliationIssue {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for ReconciliationIssue {}
+
+impl Storable for PriceCandle {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for PriceCandle {}
+
+impl Storable for ScheduledTask {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// ===== CHUNK TYPES =====
+impl VersionedRecord for ScheduledTask {}
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub enum ChunkStatus {
-    Available,     // In orderbook, ready to fill
-    Locked,        // Locked in a trade
-    Filled,        // Successfully filled
-    Idle,          // Delisted due to price breaking upward
-    Refunding,     // Refund transaction sent, awaiting confirmation
-    Refunded,      // Refunded to maker (confirmed)
-}
+impl Storable for PendingTransfer {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-pub struct Chunk {
-    pub id: ChunkId,
-    pub order_id: OrderId,
-    pub amount_usd: f64,
-    pub status: ChunkStatus,
-    pub locked_by: Option<TradeId>,
-    pub filled_at: Option<u64>,
-    pub bsv_address: String,
-    pub sats_amount: Option<u64>,  // Not set at creation, only for reference/legacy
-    pub max_bsv_price: f64,  // Inherited from order - chunks go idle if BSV price exceeds this
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// ChunkInfo removed - no longer needed with ckUSDC-only approach
+impl VersionedRecord for PendingTransfer {}
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-pub struct ChunkDetails {
-    pub id: ChunkId,
-    pub order_id: OrderId,
-    pub amount_usd: f64,
-    pub status: ChunkStatus,
-    pub locked_by: Option<TradeId>,
-    pub filled_at: Option<u64>,
-}
+impl Storable for Quote {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-pub struct OrderbookChunk {
-    pub order_id: OrderId,
-    pub amount_usd: f64,  // The actual USD amount of this specific chunk
-    pub max_price_per_bsv_in_cents: u64,
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// ===== TRADE TYPES =====
+impl VersionedRecord for Quote {}
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub enum TradeStatus {
-    ChunksLocked,           // Chunks locked, waiting for BSV tx
-    TxSubmitted,            // BSV tx submitted, waiting for release timer
-    ReadyForRelease,        // Wait period passed, can claim USDC
-    WithdrawalConfirmed,    // ckUSDC transferred (instant on ICP)
-    Cancelled,              // Cancelled (timeout or admin)
-    PenaltyApplied,         // Penalty deducted
-}
+impl Storable for WithdrawalRoute {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-pub struct LockedChunk {
-    pub chunk_id: ChunkId,
-    pub order_id: OrderId,
-    pub amount_usd: f64,
-    pub bsv_address: String,
-    pub sats_amount: u64,
-}
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-pub struct Trade {
-    pub id: TradeId,
-    pub order_id: OrderId,              // Which order this trade is from
-    pub filler: Principal,
-    pub amount_usd: f64,
-    pub locked_chunks: Vec<LockedChunk>,
-    pub agreed_bsv_price: f64,          // BSV price at trade creation
-    pub min_bsv_price: f64,             // Minimum acceptable BSV price (filler protection)
-    pub status: TradeStatus,
-    pub bsv_tx_hex: Option<String>,
-    pub created_at: u64,
-    pub tx_submitted_at: Option<u64>,
-    pub lock_expires_at: u64,           // 30 minutes from creation
-    pub release_available_at: Option<u64>, // Configured wait time from tx submission
-    pub claim_expires_at: Option<u64>,  // 24 hours from tx submission - funds go to treasury if not claimed
-    
-    // Withdrawal tracking (ckUSDC transfers to filler's principal)
-    pub withdrawal_initiated_at: Option<u64>,
-    pub withdrawal_tx_hash: Option<String>,
-    pub withdrawal_confirmed_at: Option<u64>,
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// ===== FILLER ACCOUNT TYPES =====
+impl VersionedRecord for WithdrawalRoute {}
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-pub struct FillerAccount {
-    pub id: FillerAccountId,
-    pub pending_trades_total: f64,  // Total USD in pending trades
-    pub total_trades: u64,
-    pub successful_trades: u64,
-    pub penalties_paid: f64,
-    pub created_at: u64,
-}
+impl Storable for EthWithdrawal {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
 
-// ===== STATS TYPES =====
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
-pub struct OrderbookStats {
-    pub total_active_chunks: u64,
-    pub total_available_usd: f64,
-    pub total_locked_usd: f64,
-    pub total_orders: u64,
-    pub total_trades: u64,
-    pub current_bsv_price: f64,
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// ===== BSV TRANSACTION TYPES =====
+impl VersionedRecord for EthWithdrawal {}
 
-#[derive(Debug, Clone)]
-pub struct BsvOutput {
-    pub address: String,
-    pub satoshis: u64,
-}
+impl Storable for SchemaState {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode_record(self))
+    }
 
-#[derive(Debug)]
-pub struct ParsedBsvTx {
-    pub version: u32,
-    pub inputs: Vec<BsvInput>,
-    pub outputs: Vec<BsvOutput>,
-    pub locktime: u32,
-}
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode_stored(&bytes)
+    }
 
-#[derive(Debug, Clone)]
-pub struct BsvInput {
-    pub prev_tx_hash: Vec<u8>,
-    pub prev_output_index: u32,
-    pub script_sig: Vec<u8>,
-    pub sequence: u32,
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// ===== STORABLE IMPLEMENTATIONS =====
+impl VersionedRecord for SchemaState {}
 
-impl Storable for Order {
+impl Storable for DepositRecovery {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+        Cow::Owned(encode_record(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(&bytes)
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
-impl Storable for Chunk {
+impl VersionedRecord for DepositRecovery {}
+impl Storable for FillerAccount {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+        Cow::Owned(encode_record(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(&bytes)
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
-impl Storable for Trade {
+impl VersionedRecord for FillerAccount {}
+
+impl Storable for CorruptRecord {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+        Cow::Owned(encode_record(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(&bytes)
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
-impl Storable for FillerAccount {
+impl VersionedRecord for CorruptRecord {}
+
+/// Raw hex of a submitted BSV transaction, shared by every trade whose bsv_txid names it
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TxBlob {
+    pub tx_hex: String,
+    pub ref_count: u32,
+}
+
+impl Storable for TxBlob {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+        Cow::Owned(encode_record(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        decode_stored(&bytes)
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
+impl VersionedRecord for TxBlob {}
+
 // ===== PAGINATION TYPES =====
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -285,6 +2256,77 @@ pub struct PaginatedChunks {
     pub limit: u64,
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PaginatedDepositRecoveries {
+    pub recoveries: Vec<DepositRecovery>,
+    pub total: u64,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+// Cursor pages - pass next_cursor back as after_id for the next page, None once the end is reached
+// A page may come back short (or empty) right before the end
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct OrderPage {
+    pub orders: Vec<Order>,
+    pub next_cursor: Option<OrderId>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TradePage {
+    pub trades: Vec<Trade>,
+    pub next_cursor: Option<TradeId>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ChunkPage {
+    pub chunks: Vec<OrderbookChunk>,
+    pub next_cursor: Option<ChunkId>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct AdminEventPage {
+    pub events: Vec<AdminEvent>,
+    pub next_cursor: Option<u64>,
+}
+
+// ===== DATA EXPORT TYPES =====
+
+/// Where the next export_my_data page resumes - orders are exported first, then trades
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum DataExportCursor {
+    Orders { after_id: OrderId },
+    Trades { after_id: Option<TradeId> },
+}
+
+/// Per-account records, returned on the first export page only
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DataExportAccount {
+    pub filler_account: Option<FillerAccount>,
+    pub quiet_hours: Option<MakerQuietHours>,
+    pub filler_blocklist: Option<MakerFillerBlocklist>,
+    pub quotes: Vec<Quote>,
+    pub disputes: Vec<TradeDispute>,                // As maker or filler
+    pub penalty_appeals: Vec<PenaltyAppeal>,
+    pub late_claims: Vec<LateClaim>,
+    pub deposit_recoveries: Vec<DepositRecovery>,
+    pub eth_withdrawals: Vec<EthWithdrawal>,
+    pub pending_transfers: Vec<PendingTransfer>,    // Retried penalties/refunds involving the caller
+}
+
+/// One page of export_my_data - keep calling with next_cursor until it is None
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct DataExport {
+    pub principal: Principal,
+    pub generated_at: u64,
+    pub account: Option<DataExportAccount>,
+    pub orders: Vec<Order>,                         // Caller's orders, oldest first
+    pub chunks: Vec<Chunk>,                         // Chunks of the orders on this page
+    pub trades: Vec<Trade>,                         // As filler or against the caller's orders, oldest first
+    pub next_cursor: Option<DataExportCursor>,
+}
+
 // ===== WRAPPER TYPES FOR STABLE STORAGE =====
 
 /// Wrapper for String to use as key in StableBTreeMap (for BSV txids)
@@ -337,7 +2379,7 @@ pub enum AdminEventType {
         filler: Principal,
         order_maker: Option<Principal>,
         penalty_amount: f64,
-        bsv_tx_hex: Option<String>,
+        bsv_txid: Option<String>,
         reason: String,
     },
     TradeExpiredToTreasury {
@@ -358,6 +2400,192 @@ pub enum AdminEventType {
     },
     NewOrdersEnabled,
     NewOrdersDisabled,
+    TradeDisputed {
+        trade_id: TradeId,
+        order_id: OrderId,
+        maker: Principal,
+        reason: String,
+    },
+    DisputeResolved {
+        trade_id: TradeId,
+        resolution: DisputeResolution,
+    },
+    OutcallFailed {
+        target: String,
+        attempt: u32,
+        error_message: String,
+        retry_at: u64,
+    },
+    OrderDustSwept {
+        order_id: OrderId,
+        maker: Principal,
+        amount_swept: f64,
+        block_index: u64,
+    },
+    FillerChurnFlagged {
+        filler: Principal,
+        trade_id: TradeId,
+        timeout_releases: u64,
+    },
+    BackupRestored {
+        backup_id: u64,
+        entries: u64,
+    },
+    CircuitBreakerTripped {
+        low_price: f64,
+        high_price: f64,
+        move_bps: u64,
+    },
+    CircuitBreakerReset {
+        tripped_at: u64,
+    },
+    CkEthBalanceLow {
+        balance_wei: Nat,
+        low_watermark_wei: Nat,
+    },
+    CkEthBalanceRestored {
+        balance_wei: Nat,
+    },
+    OrderBsvAddressUpdated {
+        order_id: OrderId,
+        maker: Principal,
+        old_address: String,
+        new_address: String,
+        chunks_updated: u64,
+    },
+    OrderAutoCancelled {
+        order_id: OrderId,
+        maker: Principal,
+        bsv_price: f64,
+        auto_cancel_above_price: f64,
+    },
+    TreasuryWithdrawn {
+        amount_withdrawn: f64,
+        to: Principal,
+        to_subaccount: Option<Vec<u8>>,
+        block_index: u64,
+    },
+    PenaltyAppealed {
+        trade_id: TradeId,
+        filler: Principal,
+        penalty_amount: f64,
+        explanation: String,
+    },
+    AppealResolved {
+        trade_id: TradeId,
+        filler: Principal,
+        refund_amount: f64,
+        block_index: Option<u64>,   // None when the appeal was denied
+    },
+    LateClaimVerified {
+        trade_id: TradeId,
+        filler: Principal,
+        bsv_txid: String,
+        awaiting_admin: bool,
+    },
+    LateClaimPaid {
+        trade_id: TradeId,
+        filler: Principal,
+        amount_paid: f64,
+        block_index: u64,
+    },
+}
+
+impl AdminEventType {
+    pub fn kind(&self) -> AdminEventKind {
+        match self {
+            AdminEventType::PenaltyApplied { .. } => AdminEventKind::PenaltyApplied,
+            AdminEventType::TradeExpiredToTreasury { .. } => AdminEventKind::TradeExpiredToTreasury,
+            AdminEventType::BlockInsertionError { .. } => AdminEventKind::BlockInsertionError,
+            AdminEventType::HeartbeatExecution { .. } => AdminEventKind::HeartbeatExecution,
+            AdminEventType::NewOrdersEnabled => AdminEventKind::NewOrdersEnabled,
+            AdminEventType::NewOrdersDisabled => AdminEventKind::NewOrdersDisabled,
+            AdminEventType::TradeDisputed { .. } => AdminEventKind::TradeDisputed,
+            AdminEventType::DisputeResolved { .. } => AdminEventKind::DisputeResolved,
+            AdminEventType::OutcallFailed { .. } => AdminEventKind::OutcallFailed,
+            AdminEventType::OrderDustSwept { .. } => AdminEventKind::OrderDustSwept,
+            AdminEventType::FillerChurnFlagged { .. } => AdminEventKind::FillerChurnFlagged,
+            AdminEventType::BackupRestored { .. } => AdminEventKind::BackupRestored,
+            AdminEventType::CircuitBreakerTripped { .. } => AdminEventKind::CircuitBreakerTripped,
+            AdminEventType::CircuitBreakerReset { .. } => AdminEventKind::CircuitBreakerReset,
+            AdminEventType::CkEthBalanceLow { .. } => AdminEventKind::CkEthBalanceLow,
+            AdminEventType::CkEthBalanceRestored { .. } => AdminEventKind::CkEthBalanceRestored,
+            AdminEventType::OrderBsvAddressUpdated { .. } => AdminEventKind::OrderBsvAddressUpdated,
+            AdminEventType::OrderAutoCancelled { .. } => AdminEventKind::OrderAutoCancelled,
+            AdminEventType::TreasuryWithdrawn { .. } => AdminEventKind::TreasuryWithdrawn,
+            AdminEventType::PenaltyAppealed { .. } => AdminEventKind::PenaltyAppealed,
+            AdminEventType::AppealResolved { .. } => AdminEventKind::AppealResolved,
+            AdminEventType::LateClaimVerified { .. } => AdminEventKind::LateClaimVerified,
+            AdminEventType::LateClaimPaid { .. } => AdminEventKind::LateClaimPaid,
+        }
+    }
+}
+
+/// AdminEventType without its fields, for filtering the event log
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminEventKind {
+    PenaltyApplied,
+    TradeExpiredToTreasury,
+    BlockInsertionError,
+    HeartbeatExecution,
+    NewOrdersEnabled,
+    NewOrdersDisabled,
+    TradeDisputed,
+    DisputeResolved,
+    OutcallFailed,
+    OrderDustSwept,
+    FillerChurnFlagged,
+    BackupRestored,
+    CircuitBreakerTripped,
+    CircuitBreakerReset,
+    CkEthBalanceLow,
+    CkEthBalanceRestored,
+    OrderBsvAddressUpdated,
+    OrderAutoCancelled,
+    TreasuryWithdrawn,
+    PenaltyAppealed,
+    AppealResolved,
+    LateClaimVerified,
+    LateClaimPaid,
+}
+
+impl AdminEventKind {
+    /// Key of the kind in the stored event index - fixed once assigned, new kinds take the next number
+    pub fn index_tag(self) -> u8 {
+        match self {
+            AdminEventKind::PenaltyApplied => 0,
+            AdminEventKind::TradeExpiredToTreasury => 1,
+            AdminEventKind::BlockInsertionError => 2,
+            AdminEventKind::HeartbeatExecution => 3,
+            AdminEventKind::NewOrdersEnabled => 4,
+            AdminEventKind::NewOrdersDisabled => 5,
+            AdminEventKind::TradeDisputed => 6,
+            AdminEventKind::DisputeResolved => 7,
+            AdminEventKind::OutcallFailed => 8,
+            AdminEventKind::OrderDustSwept => 9,
+            AdminEventKind::FillerChurnFlagged => 10,
+            AdminEventKind::BackupRestored => 11,
+            AdminEventKind::CircuitBreakerTripped => 12,
+            AdminEventKind::CircuitBreakerReset => 13,
+            AdminEventKind::CkEthBalanceLow => 14,
+            AdminEventKind::CkEthBalanceRestored => 15,
+            AdminEventKind::OrderBsvAddressUpdated => 16,
+            AdminEventKind::OrderAutoCancelled => 17,
+            AdminEventKind::TreasuryWithdrawn => 18,
+            AdminEventKind::PenaltyAppealed => 19,
+            AdminEventKind::AppealResolved => 20,
+            AdminEventKind::LateClaimVerified => 21,
+            AdminEventKind::LateClaimPaid => 22,
+        }
+    }
+}
+
+/// Which admin events a query returns - None = no restriction. Times are inclusive (ns)
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AdminEventFilter {
+    pub kinds: Option<Vec<AdminEventKind>>,
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -369,16 +2597,54 @@ pub struct AdminEvent {
 
 impl Storable for AdminEvent {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).expect("Failed to encode AdminEvent"))
+        Cow::Owned(encode_record(self))
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).expect("Failed to decode AdminEvent")
+        decode_stored(&bytes)
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
+impl VersionedRecord for AdminEvent {}
+
+// ===== OPERATOR ALERTS =====
+
+/// Where critical alerts are pushed - see alerts.rs
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AlertSink {
+    /// Called with the Alert as its only argument; the reply is ignored
+    Canister { canister_id: Principal, method: String },
+    /// HTTPS POST of the Alert as JSON
+    Webhook { url: String },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AlertKind {
+    BlockInsertionError { block_height: u64, error_message: String },
+    BlockSyncFailing { consecutive_failures: u32, last_error: String },
+    PenaltySpike { penalties: u64, window_seconds: u64 },
+    LowCycles { balance: u128, threshold: u128 },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct Alert {
+    pub id: u64,                        // Per canister install - with raised_at, the webhook idempotency key
+    pub kind: AlertKind,
+    pub admin_event_id: Option<u64>,    // Event that raised it, if any
+    pub raised_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct AlertStatus {
+    pub sink: Option<AlertSink>,
+    pub pending: u64,
+    pub delivered: u64,
+    pub dropped: u64,                   // Gave up after ALERT_MAX_ATTEMPTS, or pushed out of a full outbox
+    pub last_error: Option<String>,
+}
+
 // ===== AUDIT TYPES =====
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -411,6 +2677,7 @@ pub struct OrderAuditRecord {
     pub total_filled_usd: f64,
     pub total_locked_usd: f64,
     pub total_idle_usd: f64,
+    pub idle_duration_ns: u64,              // Time any chunks sat Idle, including a current period
     pub total_refunded_usd: Option<f64>,
     pub refund_count: u64,
 }
@@ -426,13 +2693,14 @@ pub struct TradeAuditRecord {
     pub agreed_bsv_price: f64,
     pub min_bsv_price: f64,
     pub status: TradeStatus,
-    pub bsv_tx_hex: Option<String>,
+    pub bsv_txid: Option<String>,
     pub maker_bsv_address: String,
     pub created_at: u64,
     pub tx_submitted_at: Option<u64>,
     pub lock_expires_at: u64,
     pub release_available_at: Option<u64>,
     pub claim_expires_at: Option<u64>,
+    pub required_confirmations: Option<u64>,
     pub withdrawal_initiated_at: Option<u64>,
     pub withdrawal_tx_hash: Option<String>,
     pub withdrawal_confirmed_at: Option<u64>,
@@ -462,3 +2730,32 @@ pub struct TradeAuditResponse {
     pub page_size: u64,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sats_tolerance_is_the_larger_of_its_bounds() {
+        let tolerance = SatsTolerance { absolute_sats: 10, basis_points: 5 };
+        assert_eq!(tolerance.allowed_deviation(1_000), 10);
+        assert_eq!(tolerance.allowed_deviation(6_000_000), 3_000);
+        assert_eq!(tolerance.allowed_deviation(u64::MAX), u64::MAX / 2_000);
+    }
+
+    #[test]
+    fn price_improvement_is_split_only_past_the_threshold() {
+        let sharing = PriceImprovementSharing { threshold_bps: 500, filler_share_bps: 2_500 };
+
+        // A $4 gap on a $100 max is within the 5% threshold - the oracle price stands
+        assert_eq!(sharing.effective_bsv_price(96.0, 100.0), None);
+        assert_eq!(sharing.effective_bsv_price(95.0, 100.0), None);
+        // $20 gap - the filler is paid at a quarter of it above the oracle price
+        assert_eq!(sharing.effective_bsv_price(80.0, 100.0), Some(85.0));
+
+        let off = PriceImprovementSharing { threshold_bps: 0, filler_share_bps: 0 };
+        assert_eq!(off.effective_bsv_price(80.0, 100.0), None);
+        let all_to_filler = PriceImprovementSharing { threshold_bps: 0, filler_share_bps: 10_000 };
+        assert_eq!(all_to_filler.effective_bsv_price(80.0, 100.0), Some(100.0));
+    }
+}
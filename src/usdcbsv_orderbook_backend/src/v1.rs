@@ -0,0 +1,542 @@
+/// Public v1 records - the Candid shapes deployed integrators decode, with USD amounts as
+/// float64 under their original field names. Storage keeps micro-USD (types.rs); lib.rs
+/// converts to these at the endpoint boundary, and only v2_ endpoints return the e6 records
+use candid::{CandidType, Principal};
+use serde::Deserialize;
+use crate::types::{
+    self, e6_to_usd, BookId, ChunkId, ChunkStatus, DataExportAccount, DataExportCursor,
+    OrderId, OrderStatus, OrderbookStats, RefundStatus, SatsTolerance, TradeId, TradeStatus,
+};
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct RefundAttempt {
+    pub refund_id: u64,
+    pub requested_at: u64,
+    pub chunk_ids: Vec<ChunkId>,
+    pub total_amount: f64,
+    pub recipient_address: String,
+    pub tx_hash: Option<String>,
+    pub tx_sent_at: Option<u64>,
+    pub confirmed_at: Option<u64>,
+    pub status: RefundStatus,
+    pub block_index: Option<u64>,
+    pub pending_transfer_id: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl From<types::RefundAttempt> for RefundAttempt {
+    fn from(r: types::RefundAttempt) -> Self {
+        Self {
+            refund_id: r.refund_id,
+            requested_at: r.requested_at,
+            chunk_ids: r.chunk_ids,
+            total_amount: e6_to_usd(r.total_amount_e6),
+            recipient_address: r.recipient_address,
+            tx_hash: r.tx_hash,
+            tx_sent_at: r.tx_sent_at,
+            confirmed_at: r.confirmed_at,
+            status: r.status,
+            block_index: r.block_index,
+            pending_transfer_id: r.pending_transfer_id,
+            last_error: r.last_error,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OrderRefund {
+    pub order_id: OrderId,
+    pub refund: RefundAttempt,
+}
+
+impl From<types::OrderRefund> for OrderRefund {
+    fn from(r: types::OrderRefund) -> Self {
+        Self { order_id: r.order_id, refund: r.refund.into() }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Order {
+    pub id: OrderId,
+    pub maker: Principal,
+    pub amount_usd: f64,
+    pub total_deposited_usd: Option<f64>,
+    pub activation_fee_usd: Option<f64>,
+    pub filler_incentive_reserved: Option<f64>,
+    pub filler_incentive_bps: Option<u64>,
+    pub deposit_principal: String,
+    pub deposit_subaccount: String,
+    pub max_bsv_price: f64,
+    pub allow_partial_fill: bool,
+    pub bsv_address: String,
+    pub status: OrderStatus,
+    pub chunks: Vec<ChunkId>,
+    pub created_at: u64,
+    pub deposit_confirmed_at: Option<u64>,
+    pub funded_at: Option<u64>,
+    pub activation_fee_block_index: Option<u64>,
+    pub activation_fee_confirmed_at: Option<u64>,
+    pub total_filled_usd: f64,
+    pub total_locked_usd: f64,
+    pub total_idle_usd: f64,
+    pub total_refunded_usd: Option<f64>,
+    pub refund_attempts: Vec<RefundAttempt>,
+    pub finalization_pending_since: Option<u64>,
+    pub hidden: Option<bool>,
+    pub idle_since: Option<u64>,
+    pub last_idle_at: Option<u64>,
+    pub idle_duration_total_ns: Option<u64>,
+    pub auto_cancel_above_price: Option<f64>,
+    pub post_only: Option<bool>,
+    pub book_id: Option<BookId>,
+}
+
+impl From<types::Order> for Order {
+    fn from(o: types::Order) -> Self {
+        Self {
+            id: o.id,
+            maker: o.maker,
+            amount_usd: e6_to_usd(o.amount_usd_e6),
+            total_deposited_usd: o.total_deposited_usd_e6.map(e6_to_usd),
+            activation_fee_usd: o.activation_fee_usd_e6.map(e6_to_usd),
+            filler_incentive_reserved: o.filler_incentive_reserved_e6.map(e6_to_usd),
+            filler_incentive_bps: o.filler_incentive_bps,
+            deposit_principal: o.deposit_principal,
+            deposit_subaccount: o.deposit_subaccount,
+            max_bsv_price: o.max_bsv_price,
+            allow_partial_fill: o.allow_partial_fill,
+            bsv_address: o.bsv_address,
+            status: o.status,
+            chunks: o.chunks,
+            created_at: o.created_at,
+            deposit_confirmed_at: o.deposit_confirmed_at,
+            funded_at: o.funded_at,
+            activation_fee_block_index: o.activation_fee_block_index,
+            activation_fee_confirmed_at: o.activation_fee_confirmed_at,
+            total_filled_usd: e6_to_usd(o.total_filled_usd_e6),
+            total_locked_usd: e6_to_usd(o.total_locked_usd_e6),
+            total_idle_usd: e6_to_usd(o.total_idle_usd_e6),
+            total_refunded_usd: o.total_refunded_usd_e6.map(e6_to_usd),
+            refund_attempts: o.refund_attempts.into_iter().map(RefundAttempt::from).collect(),
+            finalization_pending_since: o.finalization_pending_since,
+            hidden: o.hidden,
+            idle_since: o.idle_since,
+            last_idle_at: o.last_idle_at,
+            idle_duration_total_ns: o.idle_duration_total_ns,
+            auto_cancel_above_price: o.auto_cancel_above_price,
+            post_only: o.post_only,
+            book_id: o.book_id,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Chunk {
+    pub id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd: f64,
+    pub status: ChunkStatus,
+    pub locked_by: Option<TradeId>,
+    pub filled_at: Option<u64>,
+    pub bsv_address: String,
+    pub sats_amount: Option<u64>,
+    pub max_bsv_price: f64,
+    pub filler_incentive_bps: Option<u64>,
+    pub lock_count: Option<u64>,
+    pub timeout_release_count: Option<u64>,
+    pub last_timeout_filler: Option<Principal>,
+    pub reserved_by: Option<Principal>,
+    pub reserved_until: Option<u64>,
+    pub hidden: Option<bool>,
+    pub book_id: Option<BookId>,
+}
+
+impl From<types::Chunk> for Chunk {
+    fn from(c: types::Chunk) -> Self {
+        Self {
+            id: c.id,
+            order_id: c.order_id,
+            amount_usd: e6_to_usd(c.amount_usd_e6),
+            status: c.status,
+            locked_by: c.locked_by,
+            filled_at: c.filled_at,
+            bsv_address: c.bsv_address,
+            sats_amount: c.sats_amount,
+            max_bsv_price: c.max_bsv_price,
+            filler_incentive_bps: c.filler_incentive_bps,
+            lock_count: c.lock_count,
+            timeout_release_count: c.timeout_release_count,
+            last_timeout_filler: c.last_timeout_filler,
+            reserved_by: c.reserved_by,
+            reserved_until: c.reserved_until,
+            hidden: c.hidden,
+            book_id: c.book_id,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ChunkDetails {
+    pub id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd: f64,
+    pub status: ChunkStatus,
+    pub locked_by: Option<TradeId>,
+    pub filled_at: Option<u64>,
+    pub lock_count: u64,
+    pub timeout_release_count: u64,
+    pub last_timeout_filler: Option<Principal>,
+}
+
+impl From<types::ChunkDetails> for ChunkDetails {
+    fn from(c: types::ChunkDetails) -> Self {
+        Self {
+            id: c.id,
+            order_id: c.order_id,
+            amount_usd: e6_to_usd(c.amount_usd_e6),
+            status: c.status,
+            locked_by: c.locked_by,
+            filled_at: c.filled_at,
+            lock_count: c.lock_count,
+            timeout_release_count: c.timeout_release_count,
+            last_timeout_filler: c.last_timeout_filler,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OrderbookChunk {
+    pub chunk_id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd: f64,
+    pub max_price_per_bsv_in_cents: u64,
+    pub filler_incentive_bps: u64,
+}
+
+impl From<types::OrderbookChunk> for OrderbookChunk {
+    fn from(c: types::OrderbookChunk) -> Self {
+        Self {
+            chunk_id: c.chunk_id,
+            order_id: c.order_id,
+            amount_usd: e6_to_usd(c.amount_usd_e6),
+            max_price_per_bsv_in_cents: c.max_price_per_bsv_in_cents,
+            filler_incentive_bps: c.filler_incentive_bps,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LockedChunk {
+    pub chunk_id: ChunkId,
+    pub order_id: OrderId,
+    pub amount_usd: f64,
+    pub bsv_address: String,
+    pub sats_amount: u64,
+}
+
+impl From<types::LockedChunk> for LockedChunk {
+    fn from(c: types::LockedChunk) -> Self {
+        Self {
+            chunk_id: c.chunk_id,
+            order_id: c.order_id,
+            amount_usd: e6_to_usd(c.amount_usd_e6),
+            bsv_address: c.bsv_address,
+            sats_amount: c.sats_amount,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Trade {
+    pub id: TradeId,
+    pub order_id: OrderId,
+    pub filler: Principal,
+    pub amount_usd: f64,
+    pub locked_chunks: Vec<LockedChunk>,
+    pub agreed_bsv_price: f64,
+    pub min_bsv_price: f64,
+    pub effective_bsv_price: Option<f64>,
+    pub status: TradeStatus,
+    pub bsv_txid: Option<String>,
+    pub payment_group: Option<Vec<TradeId>>,
+    pub bsv_block_height: Option<u64>,
+    pub created_at: u64,
+    pub tx_submitted_at: Option<u64>,
+    pub lock_expires_at: u64,
+    pub lock_extended_at: Option<u64>,
+    pub release_available_at: Option<u64>,
+    pub claim_expires_at: Option<u64>,
+    pub required_confirmations: Option<u64>,
+    pub sats_tolerance: Option<SatsTolerance>,
+    pub withdrawal_initiated_at: Option<u64>,
+    pub withdrawal_tx_hash: Option<String>,
+    pub withdrawal_confirmed_at: Option<u64>,
+}
+
+impl From<types::Trade> for Trade {
+    fn from(t: types::Trade) -> Self {
+        Self {
+            id: t.id,
+            order_id: t.order_id,
+            filler: t.filler,
+            amount_usd: e6_to_usd(t.amount_usd_e6),
+            locked_chunks: t.locked_chunks.into_iter().map(LockedChunk::from).collect(),
+            agreed_bsv_price: t.agreed_bsv_price,
+            min_bsv_price: t.min_bsv_price,
+            effective_bsv_price: t.effective_bsv_price,
+            status: t.status,
+            bsv_txid: t.bsv_txid,
+            payment_group: t.payment_group,
+            bsv_block_height: t.bsv_block_height,
+            created_at: t.created_at,
+            tx_submitted_at: t.tx_submitted_at,
+            lock_expires_at: t.lock_expires_at,
+            lock_extended_at: t.lock_extended_at,
+            release_available_at: t.release_available_at,
+            claim_expires_at: t.claim_expires_at,
+            required_confirmations: t.required_confirmations,
+            sats_tolerance: t.sats_tolerance,
+            withdrawal_initiated_at: t.withdrawal_initiated_at,
+            withdrawal_tx_hash: t.withdrawal_tx_hash,
+            withdrawal_confirmed_at: t.withdrawal_confirmed_at,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct IncomingBsvPayment {
+    pub trade_id: TradeId,
+    pub order_id: OrderId,
+    pub txid: String,
+    pub outputs: Vec<LockedChunk>,
+    pub status: TradeStatus,
+    pub block_height: Option<u64>,
+    pub confirmations: Option<u64>,
+    pub tx_submitted_at: Option<u64>,
+}
+
+impl From<types::IncomingBsvPayment> for IncomingBsvPayment {
+    fn from(p: types::IncomingBsvPayment) -> Self {
+        Self {
+            trade_id: p.trade_id,
+            order_id: p.order_id,
+            txid: p.txid,
+            outputs: p.outputs.into_iter().map(LockedChunk::from).collect(),
+            status: p.status,
+            block_height: p.block_height,
+            confirmations: p.confirmations,
+            tx_submitted_at: p.tx_submitted_at,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OrderTradeActivity {
+    pub trade_id: TradeId,
+    pub status: TradeStatus,
+    pub amount_usd: f64,
+    pub agreed_bsv_price: f64,
+    pub effective_bsv_price: f64,
+    pub locked_chunks: Vec<LockedChunk>,
+    pub bsv_txid: Option<String>,
+    pub created_at: u64,
+    pub tx_submitted_at: Option<u64>,
+    pub release_available_at: Option<u64>,
+    pub withdrawal_confirmed_at: Option<u64>,
+}
+
+impl From<types::OrderTradeActivity> for OrderTradeActivity {
+    fn from(a: types::OrderTradeActivity) -> Self {
+        Self {
+            trade_id: a.trade_id,
+            status: a.status,
+            amount_usd: e6_to_usd(a.amount_usd_e6),
+            agreed_bsv_price: a.agreed_bsv_price,
+            effective_bsv_price: a.effective_bsv_price,
+            locked_chunks: a.locked_chunks.into_iter().map(LockedChunk::from).collect(),
+            bsv_txid: a.bsv_txid,
+            created_at: a.created_at,
+            tx_submitted_at: a.tx_submitted_at,
+            release_available_at: a.release_available_at,
+            withdrawal_confirmed_at: a.withdrawal_confirmed_at,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchivedOrder {
+    pub order: Order,
+    pub chunks: Vec<Chunk>,
+    pub archived_at: Option<u64>,
+}
+
+impl From<types::ArchivedOrder> for ArchivedOrder {
+    fn from(a: types::ArchivedOrder) -> Self {
+        Self {
+            order: a.order.into(),
+            chunks: a.chunks.into_iter().map(Chunk::from).collect(),
+            archived_at: a.archived_at,
+        }
+    }
+}
+
+// ===== ORDERBOOK FEED =====
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OrderbookChange {
+    pub seq: u64,
+    pub chunk_id: ChunkId,
+    pub chunk: Option<OrderbookChunk>,
+    pub at: u64,
+}
+
+impl From<types::OrderbookChange> for OrderbookChange {
+    fn from(c: types::OrderbookChange) -> Self {
+        Self { seq: c.seq, chunk_id: c.chunk_id, chunk: c.chunk.map(OrderbookChunk::from), at: c.at }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OrderbookSnapshot {
+    pub seq: u64,
+    pub chunks: Vec<OrderbookChunk>,
+    pub stats: OrderbookStats,
+    pub generated_at: u64,
+}
+
+impl From<types::OrderbookSnapshot> for OrderbookSnapshot {
+    fn from(s: types::OrderbookSnapshot) -> Self {
+        Self {
+            seq: s.seq,
+            chunks: s.chunks.into_iter().map(OrderbookChunk::from).collect(),
+            stats: s.stats,
+            generated_at: s.generated_at,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OrderbookDelta {
+    pub since_seq: u64,
+    pub seq: u64,
+    pub changes: Vec<OrderbookChange>,
+    pub has_more: bool,
+    pub resync_required: bool,
+}
+
+impl From<types::OrderbookDelta> for OrderbookDelta {
+    fn from(d: types::OrderbookDelta) -> Self {
+        Self {
+            since_seq: d.since_seq,
+            seq: d.seq,
+            changes: d.changes.into_iter().map(OrderbookChange::from).collect(),
+            has_more: d.has_more,
+            resync_required: d.resync_required,
+        }
+    }
+}
+
+// ===== PAGES =====
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PaginatedOrders {
+    pub orders: Vec<Order>,
+    pub total: u64,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl From<types::PaginatedOrders> for PaginatedOrders {
+    fn from(p: types::PaginatedOrders) -> Self {
+        Self { orders: p.orders.into_iter().map(Order::from).collect(), total: p.total, offset: p.offset, limit: p.limit }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PaginatedTrades {
+    pub trades: Vec<Trade>,
+    pub total: u64,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl From<types::PaginatedTrades> for PaginatedTrades {
+    fn from(p: types::PaginatedTrades) -> Self {
+        Self { trades: p.trades.into_iter().map(Trade::from).collect(), total: p.total, offset: p.offset, limit: p.limit }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PaginatedChunks {
+    pub chunks: Vec<OrderbookChunk>,
+    pub total: u64,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl From<types::PaginatedChunks> for PaginatedChunks {
+    fn from(p: types::PaginatedChunks) -> Self {
+        Self { chunks: p.chunks.into_iter().map(OrderbookChunk::from).collect(), total: p.total, offset: p.offset, limit: p.limit }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OrderPage {
+    pub orders: Vec<Order>,
+    pub next_cursor: Option<OrderId>,
+}
+
+impl From<types::OrderPage> for OrderPage {
+    fn from(p: types::OrderPage) -> Self {
+        Self { orders: p.orders.into_iter().map(Order::from).collect(), next_cursor: p.next_cursor }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TradePage {
+    pub trades: Vec<Trade>,
+    pub next_cursor: Option<TradeId>,
+}
+
+impl From<types::TradePage> for TradePage {
+    fn from(p: types::TradePage) -> Self {
+        Self { trades: p.trades.into_iter().map(Trade::from).collect(), next_cursor: p.next_cursor }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ChunkPage {
+    pub chunks: Vec<OrderbookChunk>,
+    pub next_cursor: Option<ChunkId>,
+}
+
+impl From<types::ChunkPage> for ChunkPage {
+    fn from(p: types::ChunkPage) -> Self {
+        Self { chunks: p.chunks.into_iter().map(OrderbookChunk::from).collect(), next_cursor: p.next_cursor }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DataExport {
+    pub principal: Principal,
+    pub generated_at: u64,
+    pub account: Option<DataExportAccount>,
+    pub orders: Vec<Order>,
+    pub chunks: Vec<Chunk>,
+    pub trades: Vec<Trade>,
+    pub next_cursor: Option<DataExportCursor>,
+}
+
+impl From<types::DataExport> for DataExport {
+    fn from(e: types::DataExport) -> Self {
+        Self {
+            principal: e.principal,
+            generated_at: e.generated_at,
+            account: e.account,
+            orders: e.orders.into_iter().map(Order::from).collect(),
+            chunks: e.chunks.into_iter().map(Chunk::from).collect(),
+            trades: e.trades.into_iter().map(Trade::from).collect(),
+            next_cursor: e.next_cursor,
+        }
+    }
+}
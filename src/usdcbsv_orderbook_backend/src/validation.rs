@@ -0,0 +1,140 @@
+/// Length and charset checks for caller-supplied strings, applied in lib.rs before an endpoint
+/// hands them on - nothing oversized or malformed reaches the parsers or stable memory
+/// Semantic checks (address checksums, tx contents) stay with the modules that use the value
+use crate::config::{
+    MAX_BSV_ADDRESS_LENGTH, MAX_BSV_TX_BYTES, MAX_BUMP_BYTES, MAX_CHAIN_NAME_LENGTH,
+    MAX_APPEAL_EXPLANATION_LENGTH, MAX_DISPUTE_REASON_LENGTH, MAX_PAGE_LIMIT, MAX_PAGE_OFFSET, MAX_PRINCIPAL_TEXT_LENGTH,
+};
+use crate::types::EasySwapError;
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn check_length(field: &str, value: &str, max_length: usize) -> Result<(), EasySwapError> {
+    if value.is_empty() {
+        return Err(EasySwapError::invalid_input(format!("{} is required", field)));
+    }
+    if value.len() > max_length {
+        return Err(EasySwapError::limit_exceeded(format!("{} too long (max {} characters)", field, max_length)));
+    }
+    Ok(())
+}
+
+fn check_hex(field: &str, value: &str, max_bytes: usize) -> Result<(), EasySwapError> {
+    check_length(field, value, max_bytes * 2)?;
+    if !value.len().is_multiple_of(2) || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(EasySwapError::invalid_input(format!("{} must be an even-length hex string", field)));
+    }
+    Ok(())
+}
+
+pub fn bsv_address(address: &str) -> Result<(), EasySwapError> {
+    check_length("BSV address", address, MAX_BSV_ADDRESS_LENGTH)?;
+    if !address.chars().all(|c| BASE58_ALPHABET.contains(c)) {
+        return Err(EasySwapError::invalid_input("BSV address contains non-base58 characters"));
+    }
+    Ok(())
+}
+
+/// 0x-prefixed 20-byte hex address
+pub fn evm_address(address: &str) -> Result<(), EasySwapError> {
+    check_length("Recipient address", address, 42)?;
+    let valid = address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].bytes().all(|b| b.is_ascii_hexdigit());
+    if !valid {
+        return Err(EasySwapError::invalid_input("Recipient address must be 0x followed by 40 hex characters"));
+    }
+    Ok(())
+}
+
+pub fn principal_text(text: &str) -> Result<(), EasySwapError> {
+    check_length("Principal", text, MAX_PRINCIPAL_TEXT_LENGTH)?;
+    if !text.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-') {
+        return Err(EasySwapError::invalid_input("Principal contains invalid characters"));
+    }
+    Ok(())
+}
+
+pub fn tx_hex(hex: &str) -> Result<(), EasySwapError> {
+    check_hex("Transaction hex", hex, MAX_BSV_TX_BYTES)
+}
+
+pub fn bump_hex(hex: &str) -> Result<(), EasySwapError> {
+    check_hex("BUMP hex", hex, MAX_BUMP_BYTES)
+}
+
+/// Withdrawal route name - matched case-insensitively, so upper case is allowed here
+pub fn chain_name(chain: &str) -> Result<(), EasySwapError> {
+    let chain = chain.trim();
+    check_length("Chain name", chain, MAX_CHAIN_NAME_LENGTH)?;
+    if !chain.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_') {
+        return Err(EasySwapError::invalid_input("Chain name may only contain letters, digits, '-' and '_'"));
+    }
+    Ok(())
+}
+
+/// Free text shown to the admin - any printable text, but no control characters
+pub fn dispute_reason(reason: &str) -> Result<(), EasySwapError> {
+    check_length("Dispute reason", reason.trim(), MAX_DISPUTE_REASON_LENGTH)?;
+    if reason.chars().any(|c| c.is_control() && c != '\n') {
+        return Err(EasySwapError::invalid_input("Dispute reason contains control characters"));
+    }
+    Ok(())
+}
+
+/// Free text shown to the admin, like a dispute reason
+pub fn appeal_explanation(explanation: &str) -> Result<(), EasySwapError> {
+    check_length("Appeal explanation", explanation.trim(), MAX_APPEAL_EXPLANATION_LENGTH)?;
+    if explanation.chars().any(|c| c.is_control() && c != '\n') {
+        return Err(EasySwapError::invalid_input("Appeal explanation contains control characters"));
+    }
+    Ok(())
+}
+
+/// Limit of a paginated query - 1 to MAX_PAGE_LIMIT
+pub fn page_limit(limit: u64) -> Result<(), EasySwapError> {
+    if limit == 0 {
+        return Err(EasySwapError::invalid_input("Page limit must be at least 1"));
+    }
+    if limit > MAX_PAGE_LIMIT {
+        return Err(EasySwapError::limit_exceeded(format!("Page limit {} too large (max {})", limit, MAX_PAGE_LIMIT)));
+    }
+    Ok(())
+}
+
+/// Offset and limit of an offset-paginated query
+pub fn page(offset: u64, limit: u64) -> Result<(), EasySwapError> {
+    page_limit(limit)?;
+    if offset > MAX_PAGE_OFFSET {
+        return Err(EasySwapError::limit_exceeded(format!(
+            "Page offset {} too large (max {}) - use the cursor-paginated query", offset, MAX_PAGE_OFFSET
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    #[test]
+    fn strings_are_checked_for_length_and_charset() {
+        assert!(bsv_address(&crate::lifecycle_tests::p2pkh_address(&[0x11; 20])).is_ok());
+        assert!(matches!(bsv_address(&"1".repeat(MAX_BSV_ADDRESS_LENGTH + 1)), Err(EasySwapError::LimitExceeded { .. })));
+        assert!(matches!(bsv_address(""), Err(EasySwapError::InvalidInput { .. })));
+        assert!(evm_address(&format!("0x{}", "ab".repeat(20))).is_ok());
+        assert!(evm_address(&format!("0x{}", "zz".repeat(20))).is_err());
+        assert!(evm_address(&"ab".repeat(21)).is_err());
+        assert!(principal_text(&Principal::from_slice(&[1, 1, 1]).to_text()).is_ok());
+        assert!(principal_text("AAAAA-aa").is_err());
+        assert!(chain_name(" Base_Sepolia ").is_ok());
+        assert!(chain_name("base sepolia").is_err());
+        assert!(appeal_explanation("paid on time\nsee tx").is_ok());
+        assert!(appeal_explanation("   ").is_err());
+        assert!(tx_hex("abc").is_err());
+        assert!(tx_hex(&"00".repeat(MAX_BSV_TX_BYTES)).is_ok());
+        assert!(page(MAX_PAGE_OFFSET, MAX_PAGE_LIMIT).is_ok());
+        assert!(matches!(page_limit(MAX_PAGE_LIMIT + 1), Err(EasySwapError::LimitExceeded { .. })));
+    }
+}
@@ -0,0 +1,50 @@
+/// Maker wallet top-up - a maker who keeps an ICRC-2 approval for this canister on their default
+/// ckUSDC account can opt in to having activation pull an order's deposit shortfall from it,
+/// instead of the order failing with a manual deposit instruction
+/// order_management::create_order_for does the pull; this module only holds the setting
+use candid::Principal;
+use crate::types::*;
+use crate::state::*;
+use crate::config::MAX_MAKER_TOTAL_ORDERS_USD_E6;
+
+/// Let activation pull up to `max_per_order_usd` per order from the caller's wallet; None turns it off
+/// The approval itself is granted on the ckUSDC ledger - this only says it may be used
+pub fn set_my_auto_top_up(max_per_order_usd: Option<f64>) -> Result<(), EasySwapError> {
+    let caller = get_caller();
+    
+    if caller == Principal::anonymous() {
+        return Err(EasySwapError::unauthorized("Anonymous principal cannot set up wallet top-ups. Please authenticate first."));
+    }
+    
+    let Some(max_per_order_usd) = max_per_order_usd else {
+        remove_maker_auto_top_up(caller);
+        ic_cdk::println!("💳 Wallet top-up turned off for maker {}", caller);
+        return Ok(());
+    };
+    
+    let max_per_order_usd_e6 = usd_to_e6(max_per_order_usd);
+    if max_per_order_usd_e6 == 0 || max_per_order_usd_e6 > MAX_MAKER_TOTAL_ORDERS_USD_E6 {
+        return Err(EasySwapError::invalid_input(format!(
+            "Top-up limit must be above $0 and at most ${:.2}",
+            e6_to_usd(MAX_MAKER_TOTAL_ORDERS_USD_E6)
+        )));
+    }
+    
+    ic_cdk::println!("💳 Wallet top-up for maker {}: up to ${:.6} per order", caller, max_per_order_usd);
+    set_maker_auto_top_up(MakerAutoTopUp {
+        maker: caller,
+        max_per_order_usd_e6,
+        updated_at: get_time(),
+    });
+    
+    Ok(())
+}
+
+pub fn get_my_auto_top_up() -> Option<MakerAutoTopUp> {
+    get_maker_auto_top_up(get_caller())
+}
+
+/// Whether activation may pull `shortfall_e6` from the maker's wallet
+pub fn covers(maker: Principal, shortfall_e6: UsdE6) -> bool {
+    get_maker_auto_top_up(maker).is_some_and(|setting| shortfall_e6 <= setting.max_per_order_usd_e6)
+}
@@ -1,7 +1,11 @@
 use candid::{CandidType, Deserialize, Nat, Principal};
 use ic_cdk::api::call::CallResult;
+use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError as IcrcTransferError};
+use crate::runtime::{LedgerClient, Minter};
 use serde::Serialize;
-use crate::config::{CK_ETH_LEDGER, CK_USDC_LEDGER, CK_USDC_MINTER};
+use crate::config::{CK_ETH_LEDGER, CK_USDC_LEDGER, CK_USDC_MINTER, DEFAULT_WITHDRAWAL_CHAIN};
+use crate::types::{EasySwapError, EthWithdrawal, EthWithdrawalStatus, WithdrawalRoute};
+use crate::ckusdc_integration::nat_to_u64;
 
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub struct Account {
@@ -105,6 +109,59 @@ pub enum WithdrawalError {
     RecipientAddressBlocked { address: String },
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct EthTransaction {
+    pub transaction_hash: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TxFinalizedSuccess {
+    pub transaction_hash: String,
+    pub effective_transaction_fee: Option<Nat>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TxReimbursed {
+    pub transaction_hash: String,
+    pub reimbursed_amount: Nat,
+    pub reimbursed_in_block: Nat,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum TxFinalizedStatus {
+    Success(TxFinalizedSuccess),
+    PendingReimbursement(EthTransaction),
+    Reimbursed(TxReimbursed),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum RetrieveEthStatus {
+    NotFound,
+    Pending,
+    TxCreated,
+    TxSigned(EthTransaction),
+    TxSent(EthTransaction),
+    TxFinalized(TxFinalizedStatus),
+}
+
+/// Argument of the minter's withdrawal_status query - only the search this canister uses
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum WithdrawalSearchParameter {
+    BySenderAccount(Account),
+}
+
+/// One withdrawal listed by withdrawal_status - withdrawal_id is the ckETH burn block index
+/// that retrieve_eth_status takes (the minter's status field is left out)
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WithdrawalDetail {
+    pub withdrawal_id: u64,
+    pub recipient_address: String,
+    pub from: Principal,
+    pub from_subaccount: Option<Vec<u8>>,
+    pub token_symbol: String,
+    pub withdrawal_amount: Nat,
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone, Serialize)]
 pub enum LedgerError {
     InsufficientFunds { balance: Nat, failed_burn_amount: Nat },
@@ -114,13 +171,10 @@ pub enum LedgerError {
     GenericError { error_message: String, error_code: Nat },
 }
 
-/// Approve the minter to spend canister's ckETH for gas fees
-pub async fn approve_cketh_for_gas(amount: Nat) -> Result<Nat, String> {
-    let ledger = Principal::from_text(CK_ETH_LEDGER)
-        .map_err(|e| format!("Invalid ckETH ledger principal: {}", e))?;
-    
-    let minter = Principal::from_text(CK_USDC_MINTER)
-        .map_err(|e| format!("Invalid minter principal: {}", e))?;
+/// Approve the route's minter to spend canister's gas token (ckETH) for gas fees
+pub async fn approve_cketh_for_gas(route: &WithdrawalRoute, amount: Nat) -> Result<Nat, String> {
+    let ledger = route.gas_ledger;
+    let minter = route.minter;
 
     let args = ApproveArgs {
         spender: Account {
@@ -159,7 +213,85 @@ async fn calculate_expected_gas_fee_usd(gas_wei: u64) -> Result<f64, String> {
     Ok(gas_usd)
 }
 
-/// Withdraw ckUSDC to Ethereum USDC
+/// Compare a fresh ckETH balance reading against the low-watermark
+/// Logs an admin event when the balance crosses the watermark in either direction
+pub fn record_cketh_balance(balance_wei: Nat) {
+    let monitor = crate::state::get_cketh_monitor();
+    let below_watermark = balance_wei < monitor.low_watermark_wei;
+
+    if below_watermark && !monitor.below_watermark {
+        ic_cdk::println!("⚠️ ckETH gas treasury low: {} wei (watermark {} wei)", balance_wei, monitor.low_watermark_wei);
+        crate::state::create_admin_event(crate::types::AdminEventType::CkEthBalanceLow {
+            balance_wei: balance_wei.clone(),
+            low_watermark_wei: monitor.low_watermark_wei.clone(),
+        });
+    } else if !below_watermark && monitor.below_watermark {
+        ic_cdk::println!("✅ ckETH gas treasury refilled: {} wei", balance_wei);
+        crate::state::create_admin_event(crate::types::AdminEventType::CkEthBalanceRestored {
+            balance_wei: balance_wei.clone(),
+        });
+    }
+
+    crate::state::update_cketh_monitor(|m| {
+        m.last_balance_wei = Some(balance_wei);
+        m.last_checked_at = Some(crate::state::get_time());
+        m.below_watermark = below_watermark;
+    });
+}
+
+/// Timer task: read the ckETH balance and update the watermark state
+pub async fn check_cketh_balance() {
+    if let Ok(balance) = crate::settlement::treasury_balance(CK_ETH_LEDGER).await {
+        record_cketh_balance(balance);
+    }
+}
+
+/// True while withdraw_ckusdc_to_eth is rejected for lack of gas funds
+pub fn eth_withdrawals_paused() -> bool {
+    let monitor = crate::state::get_cketh_monitor();
+    monitor.auto_disable_withdrawals && monitor.below_watermark
+}
+
+/// Ethereum route from the configured ledger/minter IDs
+pub fn default_withdrawal_route() -> WithdrawalRoute {
+    WithdrawalRoute {
+        chain: DEFAULT_WITHDRAWAL_CHAIN.to_string(),
+        minter: Principal::from_text(CK_USDC_MINTER).expect("Invalid minter principal in config"),
+        ckusdc_ledger: Principal::from_text(CK_USDC_LEDGER).expect("Invalid ckUSDC ledger principal in config"),
+        gas_ledger: Principal::from_text(CK_ETH_LEDGER).expect("Invalid ckETH ledger principal in config"),
+        gas_token_symbol: "ckETH".to_string(),
+        enabled: true,
+    }
+}
+
+/// Stored routes plus the default Ethereum route, unless the admin overrode it
+pub fn list_withdrawal_routes() -> Vec<WithdrawalRoute> {
+    let mut routes = crate::state::get_withdrawal_routes();
+    if !routes.iter().any(|r| r.chain == DEFAULT_WITHDRAWAL_CHAIN) {
+        routes.insert(0, default_withdrawal_route());
+    }
+    routes
+}
+
+/// Route for a withdrawal request (None = default chain)
+fn resolve_withdrawal_route(chain: Option<String>) -> Result<WithdrawalRoute, EasySwapError> {
+    let chain = chain.map_or_else(|| DEFAULT_WITHDRAWAL_CHAIN.to_string(), |c| c.trim().to_lowercase());
+    let route = crate::state::get_withdrawal_route(&chain)
+        .or_else(|| (chain == DEFAULT_WITHDRAWAL_CHAIN).then(default_withdrawal_route))
+        .ok_or_else(|| EasySwapError::not_found(format!("No withdrawal route for chain '{}'", chain)))?;
+    
+    if !route.enabled {
+        return Err(EasySwapError::unavailable(format!("Withdrawals to {} are currently disabled", chain)));
+    }
+    Ok(route)
+}
+
+/// Route a stored withdrawal was submitted on
+fn route_of(withdrawal: &EthWithdrawal) -> WithdrawalRoute {
+    withdrawal.route.clone().unwrap_or_else(default_withdrawal_route)
+}
+
+/// Withdraw ckUSDC to USDC on Ethereum, or on another chain via its withdrawal route
 /// User pays: withdrawal_amount + gas_fee_in_usdc + treasury_fee (20% of gas, min $0.05)
 /// Canister uses its ckETH treasury to pay Ethereum gas
 /// 
@@ -168,6 +300,9 @@ async fn calculate_expected_gas_fee_usd(gas_wei: u64) -> Result<f64, String> {
 /// 2. Gas fee validated against actual cost (with 15% buffer check)
 /// 3. Only caller's approved funds can be pulled
 /// 4. Transfer happens atomically before minter call
+/// 5. If the minter steps fail after the transfer, the user is refunded (retried by timer
+///    until it succeeds) - a rejected withdraw_erc20 call is first checked with the minter,
+///    see reconcile_unconfirmed_withdrawals. Progress is tracked per withdrawal, see get_my_withdrawals
 /// 
 /// Fee structure:
 /// - Gas fee: Dynamic based on ETH price (typically $0.40-$0.80)
@@ -179,8 +314,10 @@ pub async fn withdraw_ckusdc_to_eth(
     treasury_fee_e6: Nat,
     gas_amount_wei: Nat,
     recipient_address: String,
-) -> Result<RetrieveErc20Request, String> {
+    chain: Option<String>,
+) -> Result<RetrieveErc20Request, EasySwapError> {
     let canister_id = ic_cdk::id();
+    let route = resolve_withdrawal_route(chain)?;
     
     // Validate treasury fee: must be at least 20% of gas fee, with $0.05 minimum
     let gas_fee_usdc = gas_fee_usdc_e6.0.to_u64_digits();
@@ -195,17 +332,17 @@ pub async fn withdraw_ckusdc_to_eth(
     );
     
     if treasury_fee_amount < min_treasury_fee {
-        return Err(format!(
+        return Err(EasySwapError::invalid_input(format!(
             "Treasury fee too low. Minimum ${:.2} required (20% of gas fee: ${:.2})",
             min_treasury_fee as f64 / 1_000_000.0,
             gas_fee_amount as f64 / 1_000_000.0
-        ));
+        )));
     }
     
     // Convert gas amount from Nat to u64
     let gas_wei_digits = gas_amount_wei.0.to_u64_digits();
     let gas_wei = if gas_wei_digits.len() > 0 { gas_wei_digits[0] } else { 
-        return Err("Invalid gas amount".to_string());
+        return Err(EasySwapError::invalid_input("Invalid gas amount"));
     };
     
     // Gas amount in ckETH (e18) - from minter's estimate
@@ -217,10 +354,10 @@ pub async fn withdraw_ckusdc_to_eth(
     let gas_fee_amount = if gas_fee_usdc.len() > 0 { gas_fee_usdc[0] } else { 0 };
     
     if gas_fee_amount < 300_000 {
-        return Err("Gas fee too low. Minimum $0.30 required.".to_string());
+        return Err(EasySwapError::invalid_input("Gas fee too low. Minimum $0.30 required."));
     }
     if gas_fee_amount > 2_000_000 {
-        return Err("Gas fee too high. Maximum $2.00 allowed.".to_string());
+        return Err(EasySwapError::invalid_input("Gas fee too high. Maximum $2.00 allowed."));
     }
     
     // Second check: validate against current ETH price from XRC
@@ -238,10 +375,10 @@ pub async fn withdraw_ckusdc_to_eth(
     
     // User must pay at least 80% of expected cost (protects treasury)
     if user_gas_fee_usd < expected_gas_usd * 0.8 {
-        return Err(format!(
+        return Err(EasySwapError::invalid_input(format!(
             "Gas fee too low for current ETH price. Expected ${:.2}, got ${:.2}",
             expected_gas_usd, user_gas_fee_usd
-        ));
+        )));
     }
     
     ic_cdk::println!(
@@ -250,18 +387,51 @@ pub async fn withdraw_ckusdc_to_eth(
         user_gas_fee_usd
     );
     
-    // Step 1: Transfer total ckUSDC from user to canister (withdrawal + gas fee + treasury fee)
+    // Step 1: Check canister has enough ckETH (including ICRC-2 fee) before pulling the user's ckUSDC
+    let account = Account {
+        owner: canister_id,
+        subaccount: None,
+    };
+    let balance_result: CallResult<(Nat,)> = ic_cdk::call(route.gas_ledger, "icrc1_balance_of", (account,)).await;
+    let cketh_balance = match balance_result {
+        Ok((balance,)) => balance,
+        Err((code, msg)) => return Err(EasySwapError::ledger(format!("Failed to get {} balance: {:?}: {}", route.gas_token_symbol, code, msg))),
+    };
+    
+    // The watermark monitor tracks the Ethereum ckETH gas treasury
+    if route.gas_ledger == default_withdrawal_route().gas_ledger {
+        record_cketh_balance(cketh_balance.clone());
+        
+        if eth_withdrawals_paused() {
+            return Err(EasySwapError::unavailable(
+                "ETH withdrawals are paused until the ckETH gas treasury is refilled"
+            ));
+        }
+    }
+    
+    // ckETH ICRC-2 fee is 2,000,000,000,000 wei (0.000002 ETH)
+    // But the minter also needs to BURN ckETH which has its own fee
+    // Approve 2x gas amount to avoid issues with existing allowances
+    let cketh_icrc2_fee = Nat::from(2_000_000_000_000u64); // 0.000002 ETH
+    let cketh_burn_fee = Nat::from(2_000_000_000_000u64);  // 0.000002 ETH (burn fee)
+    let cketh_total_needed = gas_amount_e18.clone() + cketh_icrc2_fee.clone() + cketh_burn_fee.clone();
+    
+    if cketh_balance < cketh_total_needed {
+        return Err(EasySwapError::insufficient_funds(format!(
+            "Insufficient {} in treasury. Have: {}, Need: {} (gas: {} + icrc2_fee: {} + burn_fee: {})",
+            route.gas_token_symbol, cketh_balance, cketh_total_needed, gas_amount_e18, cketh_icrc2_fee, cketh_burn_fee
+        )));
+    }
+    
+    // Step 2: Transfer total ckUSDC from user to canister (withdrawal + gas fee + treasury fee)
     // Note: ICRC-2 transfer_from has a fee (typically 0.01 USDC = 10,000 e6)
     // The user's approval must cover: total + transfer fee
     // But we only transfer the total (ledger deducts fee automatically)
     let total_usdc = withdrawal_amount_e6.clone() + gas_fee_usdc_e6.clone() + treasury_fee_e6.clone();
     
-    let ckusdc_ledger = Principal::from_text(CK_USDC_LEDGER)
-        .map_err(|e| format!("Invalid ckUSDC ledger principal: {}", e))?;
-    
     // Transfer from user to canister using icrc2_transfer_from (user must approve backend first)
     let transfer_result: CallResult<(Result<Nat, TransferFromError>,)> = ic_cdk::call(
-        ckusdc_ledger,
+        route.ckusdc_ledger,
         "icrc2_transfer_from",
         (
             TransferFromArgs {
@@ -282,80 +452,330 @@ pub async fn withdraw_ckusdc_to_eth(
         ),
     ).await;
     
-    match transfer_result {
-        Ok((Ok(_block_index),)) => {},
-        Ok((Err(transfer_error),)) => return Err(format!("Failed to transfer ckUSDC from user: {:?}", transfer_error)),
-        Err((code, msg)) => return Err(format!("Failed to call transfer_from: {:?}: {}", code, msg)),
-    }
-    
-    // Step 2: Check canister has enough ckETH (including ICRC-2 fee)
-    let canister_id = ic_cdk::id();
-    let account = Account {
-        owner: canister_id,
-        subaccount: None,
-    };
-    let cketh_ledger = Principal::from_text(CK_ETH_LEDGER)
-        .map_err(|e| format!("Invalid ckETH ledger principal: {}", e))?;
-    let balance_result: CallResult<(Nat,)> = ic_cdk::call(cketh_ledger, "icrc1_balance_of", (account,)).await;
-    let cketh_balance = match balance_result {
-        Ok((balance,)) => balance,
-        Err((code, msg)) => return Err(format!("Failed to get ckETH balance: {:?}: {}", code, msg)),
+    let pull_block_index = match transfer_result {
+        Ok((Ok(block_index),)) => nat_to_u64(&block_index).ok(),
+        Ok((Err(transfer_error),)) => return Err(EasySwapError::ledger(format!("Failed to transfer ckUSDC from user: {:?}", transfer_error))),
+        Err((code, msg)) => return Err(EasySwapError::ledger(format!("Failed to call transfer_from: {:?}: {}", code, msg))),
     };
     
-    // ckETH ICRC-2 fee is 2,000,000,000,000 wei (0.000002 ETH)
-    // But the minter also needs to BURN ckETH which has its own fee
-    // Approve 2x gas amount to avoid issues with existing allowances
-    let cketh_icrc2_fee = Nat::from(2_000_000_000_000u64); // 0.000002 ETH
-    let cketh_burn_fee = Nat::from(2_000_000_000_000u64);  // 0.000002 ETH (burn fee)
-    let cketh_total_needed = gas_amount_e18.clone() + cketh_icrc2_fee.clone() + cketh_burn_fee.clone();
+    // From here on the user's ckUSDC sits in the main account - track it until it reaches the
+    // minter or is refunded
+    let now = crate::state::get_time();
+    let withdrawal = crate::state::insert_eth_withdrawal(|id| EthWithdrawal {
+        id,
+        user,
+        recipient_address: recipient_address.clone(),
+        withdrawal_amount_e6: nat_to_u64(&withdrawal_amount_e6).unwrap_or(0),
+        gas_fee_e6: gas_fee_amount,
+        treasury_fee_e6: treasury_fee_amount,
+        status: EthWithdrawalStatus::Pending,
+        pull_block_index,
+        cketh_block_index: None,
+        ckerc20_block_index: None,
+        refund_block_index: None,
+        refund_attempts: 0,
+        error: None,
+        created_at: now,
+        updated_at: now,
+        minter_status: None,
+        eth_tx_hash: None,
+        finalized_at: None,
+        route: Some(route.clone()),
+        reimbursed_e6: None,
+    });
     
-    if cketh_balance < cketh_total_needed {
-        return Err(format!(
-            "Insufficient ckETH in treasury. Have: {}, Need: {} (gas: {} + icrc2_fee: {} + burn_fee: {})",
-            cketh_balance, cketh_total_needed, gas_amount_e18, cketh_icrc2_fee, cketh_burn_fee
-        ));
+    match submit_to_minter(&route, withdrawal_amount_e6, cketh_total_needed, recipient_address).await {
+        Ok(retrieve_request) => {
+            crate::state::update_eth_withdrawal(withdrawal.id, |w| {
+                w.status = EthWithdrawalStatus::Submitted;
+                w.cketh_block_index = Some(retrieve_request.cketh_block_index.clone());
+                w.ckerc20_block_index = Some(retrieve_request.ckerc20_block_index.clone());
+                w.updated_at = crate::state::get_time();
+            });
+            // Sender pays the ledger fee on transfer_from - the full treasury fee arrives
+            crate::treasury::record_inflow(crate::types::TreasuryInflowKind::WithdrawalFee, treasury_fee_amount);
+            Ok(retrieve_request)
+        }
+        Err(e) => Err(handle_submit_failure(withdrawal.id, e).await),
     }
+}
+
+/// Why submit_to_minter failed
+#[derive(Debug)]
+enum SubmitError {
+    /// The minter never took the ckUSDC - an approval failed or withdraw_erc20 returned an error
+    Rejected(EasySwapError),
+    /// The withdraw_erc20 call itself was rejected - it may still have gone through
+    Unconfirmed(EasySwapError),
+}
+
+/// Refund the user right away if the minter definitely didn't take the ckUSDC, otherwise park
+/// the withdrawal until reconcile_unconfirmed_withdrawals finds out
+async fn handle_submit_failure(withdrawal_id: u64, error: SubmitError) -> EasySwapError {
+    let e = match error {
+        SubmitError::Unconfirmed(e) => {
+            ic_cdk::println!("⚠️ Withdrawal {} has an unknown outcome: {} - checking with the minter", withdrawal_id, e);
+            crate::state::update_eth_withdrawal(withdrawal_id, |w| {
+                w.status = EthWithdrawalStatus::Unconfirmed;
+                w.error = Some(e.reason().to_string());
+                w.updated_at = crate::state::get_time();
+            });
+            return EasySwapError::ledger(format!(
+                "{} - the withdrawal is being checked with the minter and is refunded if it didn't go through", e
+            ));
+        }
+        SubmitError::Rejected(e) => e,
+    };
     
+    ic_cdk::println!("❌ Withdrawal {} failed after pulling ckUSDC: {} - refunding user", withdrawal_id, e);
+    crate::state::update_eth_withdrawal(withdrawal_id, |w| {
+        w.status = EthWithdrawalStatus::RefundPending;
+        w.error = Some(e.reason().to_string());
+        w.updated_at = crate::state::get_time();
+    });
+    let refund_note = match refund_withdrawal(withdrawal_id).await {
+        Ok(_) => "your ckUSDC has been refunded (minus the ledger fee)".to_string(),
+        Err(refund_error) => format!("refund queued for retry ({})", refund_error),
+    };
+    EasySwapError::ledger(format!("{} - {}", e, refund_note))
+}
+
+/// Steps 3-5 of a withdrawal: approve the minter and call withdraw_erc20
+/// The user's ckUSDC stays in the main account unless withdraw_erc20 went through - a rejected
+/// withdraw_erc20 call leaves that unknown
+async fn submit_to_minter(
+    route: &WithdrawalRoute,
+    withdrawal_amount_e6: Nat,
+    cketh_total_needed: Nat,
+    recipient_address: String,
+) -> Result<RetrieveErc20Request, SubmitError> {
     // Step 3: Approve minter to spend canister's ckETH for gas
     // Approve 2x the needed amount to handle existing allowances
-    let cketh_approval_amount = cketh_total_needed.clone() * Nat::from(2u8);
+    let cketh_approval_amount = cketh_total_needed * Nat::from(2u8);
     ic_cdk::println!("Approving ckETH: {} wei (2x needed amount)", cketh_approval_amount);
-    approve_cketh_for_gas(cketh_approval_amount).await?;
+    approve_cketh_for_gas(route, cketh_approval_amount).await
+        .map_err(|e| SubmitError::Rejected(EasySwapError::ledger(e)))?;
     
     // Step 4: Approve minter to spend canister's ckUSDC (only withdrawal amount, not gas fee)
     // ckUSDC ICRC-2 fee is 10,000 e6 (0.01 USDC)
     let ckusdc_fee_e6 = Nat::from(10_000u64);
     let ckusdc_approval_amount = withdrawal_amount_e6.clone() + ckusdc_fee_e6;
-    approve_ckusdc_for_withdrawal(ckusdc_approval_amount).await?;
+    approve_ckusdc_for_withdrawal(route, ckusdc_approval_amount).await
+        .map_err(|e| SubmitError::Rejected(EasySwapError::ledger(e)))?;
     
     // Step 5: Call minter's withdraw_erc20
-    let minter = Principal::from_text(CK_USDC_MINTER)
-        .map_err(|e| format!("Invalid minter principal: {}", e))?;
-    
     let withdraw_arg = WithdrawErc20Arg {
         amount: withdrawal_amount_e6,
-        ckerc20_ledger_id: ckusdc_ledger,
+        ckerc20_ledger_id: route.ckusdc_ledger,
         recipient: recipient_address,
         from_cketh_subaccount: None,
         from_ckerc20_subaccount: None,
     };
     
     let result: CallResult<(Result<RetrieveErc20Request, WithdrawalError>,)> = 
-        ic_cdk::call(minter, "withdraw_erc20", (withdraw_arg,)).await;
-    
+        ic_cdk::call(route.minter, "withdraw_erc20", (withdraw_arg,)).await;
+    withdraw_erc20_outcome(result)
+}
+
+fn withdraw_erc20_outcome(result: CallResult<(Result<RetrieveErc20Request, WithdrawalError>,)>) -> Result<RetrieveErc20Request, SubmitError> {
     match result {
         Ok((Ok(retrieve_request),)) => Ok(retrieve_request),
-        Ok((Err(withdrawal_error),)) => Err(format!("Withdrawal failed: {:?}", withdrawal_error)),
-        Err((code, msg)) => Err(format!("Failed to call withdraw_erc20: {:?}: {}", code, msg)),
+        Ok((Err(withdrawal_error),)) => Err(SubmitError::Rejected(EasySwapError::ledger(format!("Withdrawal failed: {:?}", withdrawal_error)))),
+        Err((code, msg)) => Err(SubmitError::Unconfirmed(EasySwapError::ledger(format!("Failed to call withdraw_erc20: {:?}: {}", code, msg)))),
     }
 }
-/// Approve the minter to spend canister's ckUSDC (user deposited)
-pub async fn approve_ckusdc_for_withdrawal(amount: Nat) -> Result<Nat, String> {
-    let ledger = Principal::from_text(CK_USDC_LEDGER)
-        .map_err(|e| format!("Invalid ckUSDC ledger principal: {}", e))?;
+
+/// Return the ckUSDC pulled for a failed withdrawal (withdrawal + gas + treasury fee,
+/// minus the ledger fee) to the user's main account - or, once the minter reimbursed a failed
+/// Ethereum transaction, what it reimbursed: the gas was spent on that transaction
+async fn refund_withdrawal(withdrawal_id: u64) -> Result<u64, String> {
+    let withdrawal = crate::state::get_eth_withdrawal(withdrawal_id)
+        .ok_or_else(|| format!("Withdrawal {} not found", withdrawal_id))?;
+    if withdrawal.status != EthWithdrawalStatus::RefundPending {
+        return Err(format!("Withdrawal {} is not awaiting a refund", withdrawal_id));
+    }
+    
+    let total_e6 = withdrawal.reimbursed_e6
+        .unwrap_or(withdrawal.withdrawal_amount_e6 + withdrawal.gas_fee_e6 + withdrawal.treasury_fee_e6);
+    let refund_e6 = total_e6.saturating_sub(crate::config::CKUSDC_TRANSFER_FEE as u64);
+    
+    let ledger = route_of(&withdrawal).ckusdc_ledger;
+    let memo = format!("Refund W{}", withdrawal_id).into_bytes();
+    let transfer_args = TransferArg {
+        from_subaccount: None,
+        to: icrc_ledger_types::icrc1::account::Account {
+            owner: withdrawal.user,
+            subaccount: None,
+        },
+        amount: Nat::from(refund_e6),
+        fee: None,
+        created_at_time: Some(crate::transfer_dedup::created_at_time(&memo)),
+        memo: Some(Memo::from(memo.clone())),
+    };
+    
+    let transfer_result = crate::runtime::ledger().icrc1_transfer(ledger, transfer_args).await;
+    
+    if transfer_result.is_ok() {
+        crate::transfer_dedup::settle(&memo);
+    }
     
-    let minter = Principal::from_text(CK_USDC_MINTER)
-        .map_err(|e| format!("Invalid minter principal: {}", e))?;
+    let outcome = match transfer_result {
+        // Duplicate = an earlier refund attempt whose reply was lost went through
+        Ok((Ok(block_index),)) | Ok((Err(IcrcTransferError::Duplicate { duplicate_of: block_index }),)) => nat_to_u64(&block_index),
+        Ok((Err(transfer_error),)) => Err(format!("Refund transfer failed: {:?}", transfer_error)),
+        Err((code, msg)) => Err(format!("Failed to call transfer: {:?}: {}", code, msg)),
+    };
+    
+    crate::state::update_eth_withdrawal(withdrawal_id, |w| {
+        w.refund_attempts += 1;
+        w.updated_at = crate::state::get_time();
+        if let Ok(block_index) = outcome {
+            w.status = EthWithdrawalStatus::Refunded;
+            w.refund_block_index = Some(block_index);
+        }
+    });
+    
+    match &outcome {
+        Ok(block_index) => ic_cdk::println!("✅ Refunded withdrawal {} to {} (block {})", withdrawal_id, withdrawal.user, block_index),
+        Err(e) => ic_cdk::println!("❌ Refund of withdrawal {} failed: {}", withdrawal_id, e),
+    }
+    outcome
+}
+
+/// Timer task: retry refunds of failed withdrawals
+pub async fn retry_withdrawal_refunds() {
+    for withdrawal in crate::state::get_eth_withdrawals_by_status(EthWithdrawalStatus::RefundPending) {
+        let _ = refund_withdrawal(withdrawal.id).await;
+    }
+}
+
+/// Timer task: settle withdrawals whose withdraw_erc20 call was rejected
+/// By the time the reject arrives the minter has either executed the call or not, so one
+/// successful look at its withdrawals from this canister decides: a matching withdrawal (same
+/// recipient and amount, not yet linked to another) is tracked as Submitted and polled through
+/// retrieve_eth_status like any other, and no match means the ckUSDC never left - refund it
+pub async fn reconcile_unconfirmed_withdrawals() {
+    let unconfirmed = crate::state::get_eth_withdrawals_by_status(EthWithdrawalStatus::Unconfirmed);
+    let sender = Account {
+        owner: crate::runtime::canister_id(),
+        subaccount: None,
+    };
+    
+    for withdrawal in unconfirmed {
+        let minter = route_of(&withdrawal).minter;
+        let listed = match crate::runtime::minter().withdrawal_status(minter, WithdrawalSearchParameter::BySenderAccount(sender.clone())).await {
+            Ok((listed,)) => listed,
+            Err((code, msg)) => {
+                ic_cdk::println!("⚠️ Failed to look up withdrawal {} at the minter: {:?}: {}", withdrawal.id, code, msg);
+                continue;
+            }
+        };
+        
+        let linked: Vec<Nat> = crate::state::ETH_WITHDRAWALS.with(|map| {
+            map.borrow().iter().filter_map(|(_, w)| w.cketh_block_index).collect()
+        });
+        let found = listed.into_iter().find(|detail| {
+            detail.recipient_address.eq_ignore_ascii_case(&withdrawal.recipient_address)
+                && detail.withdrawal_amount == Nat::from(withdrawal.withdrawal_amount_e6)
+                && !linked.contains(&Nat::from(detail.withdrawal_id))
+        });
+        
+        match found {
+            Some(detail) => {
+                ic_cdk::println!("✅ Withdrawal {} reached the minter as {}", withdrawal.id, detail.withdrawal_id);
+                crate::state::update_eth_withdrawal(withdrawal.id, |w| {
+                    w.status = EthWithdrawalStatus::Submitted;
+                    w.cketh_block_index = Some(Nat::from(detail.withdrawal_id));
+                    w.error = None;
+                    w.updated_at = crate::state::get_time();
+                });
+                crate::treasury::record_inflow(crate::types::TreasuryInflowKind::WithdrawalFee, withdrawal.treasury_fee_e6);
+            }
+            None => {
+                ic_cdk::println!("↩️ Withdrawal {} never reached the minter - refunding user", withdrawal.id);
+                crate::state::update_eth_withdrawal(withdrawal.id, |w| {
+                    w.status = EthWithdrawalStatus::RefundPending;
+                    w.updated_at = crate::state::get_time();
+                });
+                let _ = refund_withdrawal(withdrawal.id).await;
+            }
+        }
+    }
+}
+
+/// Timer task: ask the minter how submitted withdrawals are doing
+/// The ckETH burn block index is the minter's ID for the withdrawal
+pub async fn poll_withdrawal_statuses() {
+    use crate::types::MinterWithdrawalStatus;
+    use crate::config::MAX_WITHDRAWAL_STATUS_POLLS_PER_RUN;
+    
+    // Least recently updated first, so every withdrawal gets polled eventually
+    let mut submitted = crate::state::get_eth_withdrawals_by_status(EthWithdrawalStatus::Submitted);
+    submitted.sort_by_key(|w| w.updated_at);
+    
+    for withdrawal in submitted.into_iter().take(MAX_WITHDRAWAL_STATUS_POLLS_PER_RUN) {
+        let block_index = match withdrawal.cketh_block_index.as_ref().and_then(|b| nat_to_u64(b).ok()) {
+            Some(block_index) => block_index,
+            None => continue,
+        };
+        
+        let result = crate::runtime::minter().retrieve_eth_status(route_of(&withdrawal).minter, block_index).await;
+        let status = match result {
+            Ok((status,)) => status,
+            Err((code, msg)) => {
+                ic_cdk::println!("⚠️ Failed to poll withdrawal {}: {:?}: {}", withdrawal.id, code, msg);
+                continue;
+            }
+        };
+        
+        let mut reimbursed_e6 = None;
+        let (minter_status, tx_hash) = match status {
+            RetrieveEthStatus::NotFound => (MinterWithdrawalStatus::NotFound, None),
+            RetrieveEthStatus::Pending => (MinterWithdrawalStatus::Pending, None),
+            RetrieveEthStatus::TxCreated => (MinterWithdrawalStatus::TxCreated, None),
+            RetrieveEthStatus::TxSigned(tx) => (MinterWithdrawalStatus::TxSigned, Some(tx.transaction_hash)),
+            RetrieveEthStatus::TxSent(tx) => (MinterWithdrawalStatus::TxSent, Some(tx.transaction_hash)),
+            RetrieveEthStatus::TxFinalized(TxFinalizedStatus::Success(tx)) => 
+                (MinterWithdrawalStatus::Finalized, Some(tx.transaction_hash)),
+            RetrieveEthStatus::TxFinalized(TxFinalizedStatus::PendingReimbursement(tx)) => 
+                (MinterWithdrawalStatus::PendingReimbursement, Some(tx.transaction_hash)),
+            RetrieveEthStatus::TxFinalized(TxFinalizedStatus::Reimbursed(tx)) => {
+                reimbursed_e6 = Some(nat_to_u64(&tx.reimbursed_amount).unwrap_or(0).min(withdrawal.withdrawal_amount_e6));
+                (MinterWithdrawalStatus::Reimbursed, Some(tx.transaction_hash))
+            }
+        };
+        
+        // A reimbursement puts the ckUSDC back in the canister's main account - it is the user's
+        let delivered = minter_status == MinterWithdrawalStatus::Finalized;
+        if delivered {
+            ic_cdk::println!("🏁 Withdrawal {} delivered on Ethereum", withdrawal.id);
+        } else if reimbursed_e6.is_some() {
+            ic_cdk::println!("↩️ Withdrawal {} failed on Ethereum and was reimbursed - refunding user", withdrawal.id);
+        }
+        
+        let now = crate::state::get_time();
+        crate::state::update_eth_withdrawal(withdrawal.id, |w| {
+            w.minter_status = Some(minter_status);
+            if tx_hash.is_some() {
+                w.eth_tx_hash = tx_hash;
+            }
+            if delivered {
+                w.status = EthWithdrawalStatus::Completed;
+                w.finalized_at = Some(now);
+            }
+            if reimbursed_e6.is_some() {
+                w.status = EthWithdrawalStatus::RefundPending;
+                w.reimbursed_e6 = reimbursed_e6;
+            }
+            w.updated_at = now;
+        });
+    }
+}
+
+/// Approve the route's minter to spend canister's ckUSDC (user deposited)
+pub async fn approve_ckusdc_for_withdrawal(route: &WithdrawalRoute, amount: Nat) -> Result<Nat, String> {
+    let ledger = route.ckusdc_ledger;
+    let minter = route.minter;
 
     let args = ApproveArgs {
         spender: Account {
@@ -381,11 +801,21 @@ pub async fn approve_ckusdc_for_withdrawal(amount: Nat) -> Result<Nat, String> {
     }
 }
 
-/// Admin function to withdraw ckUSDC from treasury to admin principal
-/// Transfers all ckUSDC balance minus the ICRC-2 transfer fee (10,000 e6)
+/// Admin function to withdraw ckUSDC from treasury
+/// Sends `amount_e6` (None = everything withdrawable) to `to` (None = the admin's default account)
+/// Only held revenue can leave, and never what in-flight trades have earmarked - see treasury::withdrawable_e6
 /// Note: Admin check is enforced in lib.rs before calling this function
-pub async fn admin_withdraw_ckusdc_treasury() -> Result<Nat, String> {
-    let stored_admin = crate::state::get_admin();
+pub async fn admin_withdraw_ckusdc_treasury(amount_e6: Option<u64>, to: Option<Principal>, to_subaccount: Option<Vec<u8>>) -> Result<Nat, EasySwapError> {
+    if to_subaccount.as_ref().is_some_and(|subaccount| subaccount.len() != 32) {
+        return Err(EasySwapError::invalid_input("Destination subaccount must be 32 bytes"));
+    }
+    if amount_e6 == Some(0) {
+        return Err(EasySwapError::invalid_input("Withdrawal amount must be positive"));
+    }
+    let destination = Account {
+        owner: to.unwrap_or_else(crate::state::get_admin),
+        subaccount: to_subaccount,
+    };
     
     let ledger = Principal::from_text(CK_USDC_LEDGER)
         .map_err(|e| format!("Invalid ckUSDC ledger principal: {}", e))?;
@@ -400,26 +830,22 @@ pub async fn admin_withdraw_ckusdc_treasury() -> Result<Nat, String> {
     let balance_result: CallResult<(Nat,)> = ic_cdk::call(ledger, "icrc1_balance_of", (account,)).await;
     let balance = match balance_result {
         Ok((balance,)) => balance,
-        Err((code, msg)) => return Err(format!("Failed to get ckUSDC balance: {:?}: {}", code, msg)),
+        Err((code, msg)) => return Err(EasySwapError::ledger(format!("Failed to get ckUSDC balance: {:?}: {}", code, msg))),
     };
     
-    // ICRC-2 transfer fee is 10,000 e6 (0.01 USDC)
-    let fee = Nat::from(10_000u64);
-    
-    if balance <= fee {
-        return Err(format!("Insufficient balance. Have: {}, Need more than fee: {}", balance, fee));
+    let withdrawable_e6 = crate::treasury::withdrawable_e6(nat_to_u64(&balance).unwrap_or(u64::MAX));
+    let transfer_amount = amount_e6.unwrap_or(withdrawable_e6);
+    if transfer_amount == 0 || transfer_amount > withdrawable_e6 {
+        return Err(EasySwapError::insufficient_funds(format!(
+            "Can withdraw up to {} e6 (balance {}, {} e6 earmarked for in-flight trades), requested {}",
+            withdrawable_e6, balance, crate::treasury::earmarked_for_trades_e6(), transfer_amount
+        )));
     }
     
-    // Transfer amount = balance - fee
-    let transfer_amount = balance - fee;
-    
     let transfer_args = TransferArgs {
         from_subaccount: None,
-        to: Account {
-            owner: stored_admin,
-            subaccount: None,
-        },
-        amount: transfer_amount.clone(),
+        to: destination.clone(),
+        amount: Nat::from(transfer_amount),
         fee: None, // Use default fee
         memo: None,
         created_at_time: None,
@@ -429,8 +855,423 @@ pub async fn admin_withdraw_ckusdc_treasury() -> Result<Nat, String> {
         ic_cdk::call(ledger, "icrc1_transfer", (transfer_args,)).await;
     
     match transfer_result {
-        Ok((Ok(block_index),)) => Ok(block_index),
-        Ok((Err(transfer_error),)) => Err(format!("Transfer failed: {:?}", transfer_error)),
-        Err((code, msg)) => Err(format!("Failed to call transfer: {:?}: {}", code, msg)),
+        Ok((Ok(block_index),)) => {
+            crate::treasury::record_admin_withdrawal(transfer_amount);
+            crate::state::create_admin_event(crate::types::AdminEventType::TreasuryWithdrawn {
+                amount_withdrawn: crate::types::e6_to_usd(transfer_amount),
+                to: destination.owner,
+                to_subaccount: destination.subaccount,
+                block_index: nat_to_u64(&block_index).unwrap_or(u64::MAX),
+            });
+            Ok(block_index)
+        }
+        Ok((Err(transfer_error),)) => Err(EasySwapError::ledger(format!("Transfer failed: {:?}", transfer_error))),
+        Err((code, msg)) => Err(EasySwapError::ledger(format!("Failed to call transfer: {:?}: {}", code, msg))),
+    }
+}
+
+/// Sweep residual dust from subaccounts of completed/cancelled orders to treasury
+/// Checks up to MAX_DUST_SWEEP_ORDERS_PER_CALL final-state orders starting at from_order_id
+/// With dry_run = true, only reports what would be swept
+/// Note: Admin check is enforced in lib.rs before calling this function
+pub async fn admin_sweep_order_dust(from_order_id: u64, dry_run: bool) -> Result<crate::types::DustSweepReport, EasySwapError> {
+    use crate::types::{ChunkStatus, DustSweepEntry, DustSweepReport, Order, OrderStatus, RefundStatus};
+    use crate::config::{CKUSDC_TRANSFER_FEE, MAX_DUST_SWEEP_USD, MAX_DUST_SWEEP_ORDERS_PER_CALL};
+    use crate::ckusdc_integration::{ckusdc_e6_to_usd, get_order_ckusdc_balance, transfer_ckusdc_from_order};
+    
+    // Collect one page of final-state orders (plus one extra to know if more remain)
+    let mut candidates: Vec<Order> = crate::state::ORDERS.with(|orders| {
+        orders.borrow()
+            .range(from_order_id..)
+            .map(|(_, order)| order)
+            .filter(|order| matches!(
+                order.status,
+                OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Refunded
+            ))
+            .take(MAX_DUST_SWEEP_ORDERS_PER_CALL + 1)
+            .collect()
+    });
+    
+    let next_order_id = if candidates.len() > MAX_DUST_SWEEP_ORDERS_PER_CALL {
+        candidates.pop().map(|order| order.id)
+    } else {
+        None
+    };
+    
+    ic_cdk::println!("🧹 Dust sweep{}: checking {} orders from #{}", 
+        if dry_run { " (dry run)" } else { "" }, candidates.len(), from_order_id);
+    
+    let treasury = crate::state::get_treasury_principal();
+    let mut entries = Vec::new();
+    let mut total_swept_usd = 0.0;
+    
+    for order in candidates {
+        // Never touch orders that still have locked chunks - funds are owed to a filler
+        let has_locked_chunks = order.chunks.iter().any(|chunk_id| {
+            crate::state::get_chunk(*chunk_id).is_some_and(|c| c.status == ChunkStatus::Locked)
+        });
+        if has_locked_chunks {
+            continue;
+        }
+        
+        // Nor orders with a refund still in flight - the balance is the maker's until it lands
+        let refund_outstanding = order.refund_attempts.iter().any(|r| r.status != RefundStatus::Confirmed)
+            || crate::pending_transfers::has_outstanding_order_refund(order.id);
+        if refund_outstanding {
+            continue;
+        }
+        
+        let balance_e6 = match get_order_ckusdc_balance(order.maker, order.id).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                entries.push(DustSweepEntry {
+                    order_id: order.id,
+                    maker: order.maker,
+                    order_status: order.status,
+                    balance_usd: 0.0,
+                    block_index: None,
+                    note: Some(format!("Balance check failed: {}", e)),
+                });
+                continue;
+            }
+        };
+        
+        if balance_e6 == 0 {
+            continue;
+        }
+        
+        let balance_usd = ckusdc_e6_to_usd(balance_e6);
+        let mut entry = DustSweepEntry {
+            order_id: order.id,
+            maker: order.maker,
+            order_status: order.status.clone(),
+            balance_usd,
+            block_index: None,
+            note: None,
+        };
+        
+        if balance_e6 <= CKUSDC_TRANSFER_FEE {
+            entry.note = Some("Balance does not cover transfer fee".to_string());
+        } else if balance_usd > MAX_DUST_SWEEP_USD {
+            entry.note = Some(format!("Balance exceeds dust threshold ${:.2} - needs manual review", MAX_DUST_SWEEP_USD));
+        } else if dry_run {
+            total_swept_usd += balance_usd;
+        } else {
+            match transfer_ckusdc_from_order(
+                order.maker,
+                order.id,
+                treasury,
+                None,
+                balance_e6,
+                Some(format!("Dust O{}", order.id).into_bytes()),
+            ).await {
+                Ok(block_index) => {
+                    ic_cdk::println!("✅ Swept ${:.6} dust from order {} (block: {})", balance_usd, order.id, block_index);
+                    total_swept_usd += balance_usd;
+                    entry.block_index = Some(block_index);
+                    crate::treasury::record_inflow(
+                        crate::types::TreasuryInflowKind::DustSweep,
+                        (balance_e6 - CKUSDC_TRANSFER_FEE) as u64,
+                    );
+                    
+                    crate::state::create_admin_event(crate::types::AdminEventType::OrderDustSwept {
+                        order_id: order.id,
+                        maker: order.maker,
+                        amount_swept: balance_usd,
+                        block_index,
+                    });
+                }
+                Err(e) => {
+                    ic_cdk::println!("❌ Failed to sweep dust from order {}: {}", order.id, e);
+                    entry.note = Some(format!("Transfer failed: {}", e));
+                }
+            }
+        }
+        
+        entries.push(entry);
+    }
+    
+    Ok(DustSweepReport {
+        dry_run,
+        scanned_from: from_order_id,
+        entries,
+        total_swept_usd,
+        next_order_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::mock::{self, block_on};
+    use crate::types::MinterWithdrawalStatus;
+
+    const AMOUNT_E6: u64 = 10_000_000;
+    // Withdrawal + gas fee + treasury fee
+    const PULLED_E6: u64 = AMOUNT_E6 + 500_000 + 100_000;
+
+    fn user() -> Principal {
+        Principal::from_slice(&[7, 7, 7])
+    }
+
+    fn main_account(owner: Principal) -> icrc_ledger_types::icrc1::account::Account {
+        icrc_ledger_types::icrc1::account::Account { owner, subaccount: None }
+    }
+
+    /// A withdrawal the minter accepted, burned at cketh_block_index
+    fn submitted_withdrawal(cketh_block_index: u64) -> EthWithdrawal {
+        let withdrawal = insert_withdrawal();
+        crate::state::update_eth_withdrawal(withdrawal.id, |w| {
+            w.status = EthWithdrawalStatus::Submitted;
+            w.cketh_block_index = Some(Nat::from(cketh_block_index));
+            w.ckerc20_block_index = Some(Nat::from(cketh_block_index + 1));
+        });
+        crate::state::get_eth_withdrawal(withdrawal.id).unwrap()
+    }
+
+    /// A withdrawal whose ckUSDC was pulled into the main account, minter call in progress
+    fn pending_withdrawal() -> EthWithdrawal {
+        mock::mint(main_account(crate::runtime::canister_id()), PULLED_E6 as u128);
+        insert_withdrawal()
+    }
+
+    fn insert_withdrawal() -> EthWithdrawal {
+        let now = crate::state::get_time();
+        crate::state::insert_eth_withdrawal(|id| EthWithdrawal {
+            id,
+            user: user(),
+            recipient_address: format!("0x{}", "ab".repeat(20)),
+            withdrawal_amount_e6: AMOUNT_E6,
+            gas_fee_e6: 500_000,
+            treasury_fee_e6: 100_000,
+            status: EthWithdrawalStatus::Pending,
+            pull_block_index: Some(1),
+            cketh_block_index: None,
+            ckerc20_block_index: None,
+            refund_block_index: None,
+            refund_attempts: 0,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            minter_status: None,
+            eth_tx_hash: None,
+            finalized_at: None,
+            route: None,
+            reimbursed_e6: None,
+        })
+    }
+
+    fn eth_tx(hash: &str) -> EthTransaction {
+        EthTransaction { transaction_hash: hash.to_string() }
+    }
+
+    #[test]
+    fn reimbursed_withdrawal_is_refunded_not_completed() {
+        let withdrawal = submitted_withdrawal(7);
+
+        mock::set_minter_status(7, RetrieveEthStatus::TxSent(eth_tx("0x01")));
+        block_on(poll_withdrawal_statuses());
+        let polled = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!(polled.status, EthWithdrawalStatus::Submitted);
+        assert_eq!(polled.minter_status, Some(MinterWithdrawalStatus::TxSent));
+
+        // The Ethereum transaction failed and the minter returned the ckUSDC to the main account
+        mock::set_minter_status(7, RetrieveEthStatus::TxFinalized(TxFinalizedStatus::Reimbursed(TxReimbursed {
+            transaction_hash: "0x01".to_string(),
+            reimbursed_amount: Nat::from(AMOUNT_E6),
+            reimbursed_in_block: Nat::from(9u64),
+        })));
+        mock::mint(main_account(crate::runtime::canister_id()), AMOUNT_E6 as u128);
+        block_on(poll_withdrawal_statuses());
+        let reimbursed = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!(reimbursed.status, EthWithdrawalStatus::RefundPending);
+        assert_eq!(reimbursed.minter_status, Some(MinterWithdrawalStatus::Reimbursed));
+        assert_eq!((reimbursed.reimbursed_e6, reimbursed.finalized_at), (Some(AMOUNT_E6), None));
+
+        // The refund timer pays back what was reimbursed - the gas went on the failed transaction
+        block_on(retry_withdrawal_refunds());
+        let refunded = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!(refunded.status, EthWithdrawalStatus::Refunded);
+        assert!(refunded.refund_block_index.is_some());
+        assert_eq!(mock::balance(main_account(user())), (AMOUNT_E6 - crate::config::CKUSDC_TRANSFER_FEE as u64) as u128);
+
+        // Nothing more to poll or refund
+        block_on(poll_withdrawal_statuses());
+        block_on(retry_withdrawal_refunds());
+        assert_eq!(crate::state::get_eth_withdrawal(withdrawal.id).unwrap().status, EthWithdrawalStatus::Refunded);
+        assert_eq!(mock::balance(main_account(user())), (AMOUNT_E6 - crate::config::CKUSDC_TRANSFER_FEE as u64) as u128);
+    }
+
+    #[test]
+    fn finalized_withdrawal_is_completed() {
+        let withdrawal = submitted_withdrawal(3);
+        mock::set_minter_status(3, RetrieveEthStatus::TxFinalized(TxFinalizedStatus::Success(TxFinalizedSuccess {
+            transaction_hash: "0x02".to_string(),
+            effective_transaction_fee: None,
+        })));
+        block_on(poll_withdrawal_statuses());
+
+        let completed = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!(completed.status, EthWithdrawalStatus::Completed);
+        assert_eq!(completed.eth_tx_hash.as_deref(), Some("0x02"));
+        assert!(completed.finalized_at.is_some() && completed.reimbursed_e6.is_none());
+    }
+
+    fn minter_withdrawal(withdrawal_id: u64, amount_e6: u64) -> WithdrawalDetail {
+        WithdrawalDetail {
+            withdrawal_id,
+            recipient_address: format!("0x{}", "AB".repeat(20)),
+            from: crate::runtime::canister_id(),
+            from_subaccount: None,
+            token_symbol: "ckUSDC".to_string(),
+            withdrawal_amount: Nat::from(amount_e6),
+        }
+    }
+
+    fn refunded_e6() -> u128 {
+        (PULLED_E6 - crate::config::CKUSDC_TRANSFER_FEE as u64) as u128
+    }
+
+    #[test]
+    fn only_a_rejected_withdraw_erc20_call_leaves_the_outcome_unknown() {
+        use ic_cdk::api::call::RejectionCode;
+        let definite = withdraw_erc20_outcome(Ok((Err(WithdrawalError::TemporarilyUnavailable("busy".to_string())),)));
+        assert!(matches!(definite, Err(SubmitError::Rejected(_))), "{:?}", definite.err());
+        let rejected = withdraw_erc20_outcome(Err((RejectionCode::SysTransient, "timeout".to_string())));
+        assert!(matches!(rejected, Err(SubmitError::Unconfirmed(_))), "{:?}", rejected.err());
+    }
+
+    #[test]
+    fn definite_minter_error_is_refunded_at_once() {
+        let withdrawal = pending_withdrawal();
+        block_on(handle_submit_failure(withdrawal.id, SubmitError::Rejected(EasySwapError::ledger("Withdrawal failed"))));
+        let refunded = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!(refunded.status, EthWithdrawalStatus::Refunded);
+        assert_eq!(mock::balance(main_account(user())), refunded_e6());
+    }
+
+    #[test]
+    fn unconfirmed_withdrawal_the_minter_took_is_polled_not_refunded() {
+        let withdrawal = pending_withdrawal();
+        block_on(handle_submit_failure(withdrawal.id, SubmitError::Unconfirmed(EasySwapError::ledger("Failed to call withdraw_erc20"))));
+        assert_eq!(crate::state::get_eth_withdrawal(withdrawal.id).unwrap().status, EthWithdrawalStatus::Unconfirmed);
+        block_on(retry_withdrawal_refunds());
+        assert_eq!(mock::balance(main_account(user())), 0);
+
+        // The minter burned it - an earlier withdrawal of another amount doesn't match
+        mock::add_minter_withdrawal(minter_withdrawal(10, AMOUNT_E6 / 2));
+        mock::add_minter_withdrawal(minter_withdrawal(11, AMOUNT_E6));
+        block_on(reconcile_unconfirmed_withdrawals());
+        let submitted = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!(submitted.status, EthWithdrawalStatus::Submitted);
+        assert_eq!(submitted.cketh_block_index, Some(Nat::from(11u64)));
+
+        mock::set_minter_status(11, RetrieveEthStatus::TxFinalized(TxFinalizedStatus::Success(TxFinalizedSuccess {
+            transaction_hash: "0x03".to_string(),
+            effective_transaction_fee: None,
+        })));
+        block_on(poll_withdrawal_statuses());
+        block_on(retry_withdrawal_refunds());
+        assert_eq!(crate::state::get_eth_withdrawal(withdrawal.id).unwrap().status, EthWithdrawalStatus::Completed);
+        assert_eq!(mock::balance(main_account(user())), 0);
+
+        // A second unknown outcome can't claim the same minter withdrawal
+        let second = pending_withdrawal();
+        block_on(handle_submit_failure(second.id, SubmitError::Unconfirmed(EasySwapError::ledger("Failed to call withdraw_erc20"))));
+        block_on(reconcile_unconfirmed_withdrawals());
+        assert_eq!(crate::state::get_eth_withdrawal(second.id).unwrap().status, EthWithdrawalStatus::Refunded);
+    }
+
+    #[test]
+    fn unconfirmed_withdrawal_the_minter_never_saw_is_refunded_once() {
+        let withdrawal = pending_withdrawal();
+        block_on(handle_submit_failure(withdrawal.id, SubmitError::Unconfirmed(EasySwapError::ledger("Failed to call withdraw_erc20"))));
+
+        // Not at the minter, and the ledger is down - the refund waits for the timer
+        mock::set_ledger_down(true);
+        block_on(reconcile_unconfirmed_withdrawals());
+        let pending = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!((pending.status, pending.refund_attempts), (EthWithdrawalStatus::RefundPending, 1));
+
+        mock::set_ledger_down(false);
+        block_on(retry_withdrawal_refunds());
+        block_on(retry_withdrawal_refunds());
+        block_on(reconcile_unconfirmed_withdrawals());
+        let refunded = crate::state::get_eth_withdrawal(withdrawal.id).unwrap();
+        assert_eq!((refunded.status, refunded.refund_attempts), (EthWithdrawalStatus::Refunded, 2));
+        assert_eq!(mock::balance(main_account(user())), refunded_e6());
+    }
+
+    #[test]
+    fn dust_sweep_leaves_orders_with_a_refund_in_flight() {
+        let maker = Principal::from_slice(&[1, 1, 1]);
+        mock::set_prices(&[50.0, 50.0, 50.0]);
+        mock::mint(crate::filler_accounts::get_deposit_account(maker), 10_000_000);
+        mock::set_caller(maker);
+        let order_id = block_on(crate::order_management::create_order(6.0, 100.0, crate::lifecycle_tests::p2pkh_address(&[0x11; 20]), None)).unwrap();
+        block_on(crate::order_management::cancel_order(order_id)).unwrap();
+        let order_account = crate::ckusdc_integration::get_order_deposit_account(maker, order_id);
+        mock::mint(order_account, 500_000);
+        let dust = mock::balance(order_account);
+
+        // A refund of the order is queued - the balance is the maker's
+        let transfer = crate::pending_transfers::queue_order_refund(maker, order_id, dust, b"Refund O1 #2".to_vec(), "rejected".to_string());
+        let report = block_on(admin_sweep_order_dust(0, false)).unwrap();
+        assert!(report.entries.is_empty(), "{:?}", report.entries);
+
+        // Given up and waiting for admin review - still the maker's
+        crate::state::update_pending_transfer(transfer.id, |t| t.status = crate::types::PendingTransferStatus::Failed);
+        assert!(block_on(admin_sweep_order_dust(0, false)).unwrap().entries.is_empty());
+
+        // The transfer is done but its refund attempt isn't final
+        crate::state::update_pending_transfer(transfer.id, |t| t.status = crate::types::PendingTransferStatus::Completed);
+        crate::state::update_order(order_id, |o| o.refund_attempts[0].status = crate::types::RefundStatus::Sent).unwrap();
+        assert!(block_on(admin_sweep_order_dust(0, false)).unwrap().entries.is_empty());
+        assert_eq!(mock::balance(order_account), dust);
+
+        // Everything settled - the leftover is dust
+        crate::state::update_order(order_id, |o| o.refund_attempts[0].status = crate::types::RefundStatus::Confirmed).unwrap();
+        let report = block_on(admin_sweep_order_dust(0, false)).unwrap();
+        assert_eq!(report.entries.len(), 1);
+        assert!(report.entries[0].block_index.is_some(), "{:?}", report.entries);
+        assert_eq!(mock::balance(order_account), 0);
+    }
+
+    fn arbitrum_route(enabled: bool) -> WithdrawalRoute {
+        WithdrawalRoute {
+            chain: "arbitrum".to_string(),
+            minter: Principal::from_slice(&[5, 0, 1]),
+            ckusdc_ledger: Principal::from_slice(&[5, 0, 2]),
+            gas_ledger: Principal::from_slice(&[5, 0, 3]),
+            gas_token_symbol: "ckETH-arb".to_string(),
+            enabled,
+        }
+    }
+
+    #[test]
+    fn withdrawals_resolve_to_enabled_routes_and_default_to_ethereum() {
+        assert_eq!(resolve_withdrawal_route(None).unwrap().minter, default_withdrawal_route().minter);
+        assert!(matches!(resolve_withdrawal_route(Some("arbitrum".to_string())), Err(EasySwapError::NotFound { .. })));
+
+        crate::state::insert_withdrawal_route(arbitrum_route(false));
+        assert!(matches!(resolve_withdrawal_route(Some(" Arbitrum ".to_string())), Err(EasySwapError::ServiceUnavailable { .. })));
+        crate::state::insert_withdrawal_route(arbitrum_route(true));
+        assert_eq!(resolve_withdrawal_route(Some(" Arbitrum ".to_string())).unwrap().minter, arbitrum_route(true).minter);
+
+        let chains: Vec<String> = list_withdrawal_routes().into_iter().map(|route| route.chain).collect();
+        assert_eq!(chains, vec![DEFAULT_WITHDRAWAL_CHAIN.to_string(), "arbitrum".to_string()]);
+    }
+
+    #[test]
+    fn failed_withdrawal_is_refunded_on_its_own_routes_ledger() {
+        let route = arbitrum_route(true);
+        mock::mint_token(route.ckusdc_ledger, main_account(crate::runtime::canister_id()), PULLED_E6 as u128);
+        let withdrawal = insert_withdrawal();
+        crate::state::update_eth_withdrawal(withdrawal.id, |w| w.route = Some(route.clone()));
+
+        block_on(handle_submit_failure(withdrawal.id, SubmitError::Rejected(EasySwapError::ledger("Withdrawal failed"))));
+        assert_eq!(crate::state::get_eth_withdrawal(withdrawal.id).unwrap().status, EthWithdrawalStatus::Refunded);
+        assert_eq!(mock::token_balance(route.ckusdc_ledger, main_account(user())), refunded_e6());
+        assert_eq!(mock::balance(main_account(user())), 0);
     }
 }
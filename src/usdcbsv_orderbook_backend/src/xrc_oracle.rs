@@ -68,12 +68,17 @@ pub enum ExchangeRateError {
 
 /// Get BSV/USD exchange rate from XRC
 pub async fn get_bsv_usd_rate() -> Result<f64, String> {
+    get_usd_rate("BSV").await
+}
+
+/// Get the USD exchange rate of any crypto asset XRC knows (BSV, BTC, USDT...)
+pub async fn get_usd_rate(symbol: &str) -> Result<f64, String> {
     let xrc = Principal::from_text(XRC_CANISTER_ID)
         .map_err(|e| format!("Invalid XRC principal: {}", e))?;
 
     let request = GetExchangeRateRequest {
         base_asset: Asset {
-            symbol: "BSV".to_string(),
+            symbol: symbol.to_string(),
             class: AssetClass::Cryptocurrency,
         },
         quote_asset: Asset {
@@ -98,7 +103,8 @@ pub async fn get_bsv_usd_rate() -> Result<f64, String> {
             let decimals = rate.metadata.decimals;
             let rate_value = rate.rate as f64 / 10f64.powi(decimals as i32);
             
-            ic_cdk::println!("BSV/USD rate from XRC: ${} (sources: {})", 
+            ic_cdk::println!("{}/USD rate from XRC: ${} (sources: {})", 
+                symbol,
                 rate_value, 
                 rate.metadata.base_asset_num_received_rates
             );
@@ -0,0 +1,91 @@
+//! Helpers for the PocketIC suite - installs the backend wasm and wraps candid calls
+use candid::{decode_one, encode_args, CandidType, Deserialize, Principal};
+use candid::utils::ArgumentEncoder;
+use pocket_ic::{PocketIc, WasmResult};
+
+/// config::ADMIN_PRINCIPAL
+pub const ADMIN_PRINCIPAL: &str = "dow63-puub5-ne7wq-knc6a-i3tqs-ur75n-ozxkz-ad22e-frcrk-vq5jo-jae";
+
+const DEFAULT_WASM_PATH: &str = "../../target/wasm32-unknown-unknown/release/usdcbsv_orderbook_backend.wasm";
+
+/// Mirror of the candid BackgroundTask variant (the backend keeps its types crate-private)
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackgroundTask {
+    Confirmations,
+    IdleReactivation,
+    CleanupTasks,
+    BlockSync,
+    DataCleanup,
+    SettlementReport,
+    Reconciliation,
+    CkEthBalanceCheck,
+    WithdrawalFollowUp,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ScheduledTask {
+    pub task: BackgroundTask,
+    pub interval_seconds: u64,
+    pub enabled: bool,
+    pub next_run_at: u64,
+    pub last_started_at: Option<u64>,
+    pub last_finished_at: Option<u64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub total_runs: u64,
+}
+
+/// EasySwapError payloads are only printed, never inspected
+pub type ApiResult<T> = Result<T, candid::Reserved>;
+
+pub fn backend_wasm() -> Vec<u8> {
+    let path = std::env::var("BACKEND_WASM").unwrap_or_else(|_| DEFAULT_WASM_PATH.to_string());
+    std::fs::read(&path).unwrap_or_else(|e| panic!("Cannot read backend wasm at {}: {} - build it first", path, e))
+}
+
+pub fn admin() -> Principal {
+    Principal::from_text(ADMIN_PRINCIPAL).expect("valid admin principal")
+}
+
+pub struct Backend {
+    pub pic: PocketIc,
+    pub canister_id: Principal,
+}
+
+impl Backend {
+    pub fn install() -> Self {
+        let pic = PocketIc::new();
+        let canister_id = pic.create_canister();
+        pic.add_cycles(canister_id, 100_000_000_000_000);
+        pic.install_canister(canister_id, backend_wasm(), encode_args(()).unwrap(), None);
+        Backend { pic, canister_id }
+    }
+
+    /// Reinstall the same wasm through the upgrade path (pre_upgrade + post_upgrade)
+    pub fn upgrade(&self) {
+        self.pic
+            .upgrade_canister(self.canister_id, backend_wasm(), encode_args(()).unwrap(), None)
+            .expect("upgrade succeeds");
+    }
+
+    pub fn update<T: for<'de> Deserialize<'de> + CandidType>(&self, sender: Principal, method: &str, args: impl ArgumentEncoder) -> T {
+        let result = self.pic
+            .update_call(self.canister_id, sender, method, encode_args(args).unwrap())
+            .unwrap_or_else(|e| panic!("{} failed: {:?}", method, e));
+        decode_reply(method, result)
+    }
+
+    pub fn query<T: for<'de> Deserialize<'de> + CandidType>(&self, sender: Principal, method: &str, args: impl ArgumentEncoder) -> T {
+        let result = self.pic
+            .query_call(self.canister_id, sender, method, encode_args(args).unwrap())
+            .unwrap_or_else(|e| panic!("{} failed: {:?}", method, e));
+        decode_reply(method, result)
+    }
+}
+
+fn decode_reply<T: for<'de> Deserialize<'de> + CandidType>(method: &str, result: WasmResult) -> T {
+    match result {
+        WasmResult::Reply(bytes) => decode_one(&bytes).unwrap_or_else(|e| panic!("{} reply didn't decode: {}", method, e)),
+        WasmResult::Reject(message) => panic!("{} rejected: {}", method, message),
+    }
+}
@@ -0,0 +1,46 @@
+//! Admin settings must survive an upgrade - both live in stable memory and post_upgrade must not
+//! reset them to defaults
+use candid::Principal;
+use usdcbsv_orderbook_e2e::*;
+
+#[test]
+fn filler_allowlist_flag_survives_upgrade() {
+    let backend = Backend::install();
+    assert!(!backend.query::<bool>(Principal::anonymous(), "is_filler_allowlist_enabled", ()));
+
+    let result: ApiResult<()> = backend.update(admin(), "admin_set_filler_allowlist_enabled", (true,));
+    assert!(result.is_ok(), "admin_set_filler_allowlist_enabled failed: {:?}", result);
+
+    backend.upgrade();
+    assert!(backend.query::<bool>(Principal::anonymous(), "is_filler_allowlist_enabled", ()));
+}
+
+#[test]
+fn scheduled_task_settings_survive_upgrade() {
+    let backend = Backend::install();
+
+    let result: ApiResult<ScheduledTask> = backend.update(
+        admin(),
+        "admin_update_scheduled_task",
+        (BackgroundTask::Reconciliation, Some(7_200u64), Some(false)),
+    );
+    assert!(result.is_ok(), "admin_update_scheduled_task failed: {:?}", result);
+
+    backend.upgrade();
+
+    let tasks: ApiResult<Vec<ScheduledTask>> = backend.query(admin(), "admin_get_scheduled_tasks", ());
+    let reconciliation = tasks.expect("admin can list tasks")
+        .into_iter()
+        .find(|task| task.task == BackgroundTask::Reconciliation)
+        .expect("reconciliation task registered");
+    assert_eq!(reconciliation.interval_seconds, 7_200);
+    assert!(!reconciliation.enabled);
+}
+
+#[test]
+fn non_admin_cannot_change_settings() {
+    let backend = Backend::install();
+    let result: ApiResult<()> = backend.update(Principal::anonymous(), "admin_set_filler_allowlist_enabled", (true,));
+    assert!(result.is_err());
+    assert!(!backend.query::<bool>(Principal::anonymous(), "is_filler_allowlist_enabled", ()));
+}
@@ -16,6 +16,7 @@ import {
   USDC_RELEASE_WAIT_HOURS,
   TRADE_CLAIM_EXPIRY_HOURS
 } from '../../config';
+import { formatCanisterError } from '../../utils/canisterError';
 
 const CreateTradeForm = ({ isOpen, onClose, onTradeCreated }) => {
   const { t } = useTranslation(['filler', 'common']);
@@ -63,7 +64,7 @@ const CreateTradeForm = ({ isOpen, onClose, onTradeCreated }) => {
         }
         
         // Fetch available orderbook balance
-        const orderbookBalance = await actor.get_available_orderbook();
+        const orderbookBalance = await actor.get_available_orderbook([]);
         setAvailableOrderbook(Number(orderbookBalance));
       } catch (error) {
         console.error('Error fetching data:', error);
@@ -131,7 +132,7 @@ const CreateTradeForm = ({ isOpen, onClose, onTradeCreated }) => {
         onTradeCreated?.(tradeIds);
         onClose();
       } else {
-        toast.error(result.Err || t('createTrade.createFailed'));
+        toast.error(formatCanisterError(result.Err) || t('createTrade.createFailed'));
       }
     } catch (error) {
       console.error('Error creating trades:', error);
@@ -14,6 +14,7 @@ import {
   TRADE_CLAIM_EXPIRY_HOURS,
   RESUBMISSION_WINDOW_HOURS
 } from '../../config';
+import { formatCanisterError } from '../../utils/canisterError';
 
 const TradeDetails = ({ tradeId, onClose }) => {
   const { t } = useTranslation(['filler', 'common']);
@@ -133,7 +134,8 @@ const TradeDetails = ({ tradeId, onClose }) => {
     try {
       const result = await actor.get_trade(tradeId);
       if (result.length > 0) {
-        const tradeData = result[0];
+        // The submitted tx hex is stored apart from the trade - fetch it alongside
+        const tradeData = { ...result[0], bsv_tx_hex: await actor.get_trade_tx_hex(tradeId) };
         setTrade(tradeData);
         
         // Log trade details for debugging
@@ -218,7 +220,7 @@ const TradeDetails = ({ tradeId, onClose }) => {
         setPaymentRef(null);
         fetchTradeDetails();
       } else {
-        toast.error(result.Err || t('errors.txFailed'));
+        toast.error(formatCanisterError(result.Err) || t('errors.txFailed'));
       }
       
       console.log('===========================');
@@ -274,7 +276,7 @@ const TradeDetails = ({ tradeId, onClose }) => {
         setTxHex('');
         fetchTradeDetails();
       } else {
-        toast.error(result.Err || t('errors.txFailed'));
+        toast.error(formatCanisterError(result.Err) || t('errors.txFailed'));
       }
       
       console.log('===========================');
@@ -303,7 +305,7 @@ const TradeDetails = ({ tradeId, onClose }) => {
         setTxHex('');
         fetchTradeDetails();
       } else {
-        toast.error(result.Err || t('errors.txFailed'));
+        toast.error(formatCanisterError(result.Err) || t('errors.txFailed'));
       }
       
       console.log('===========================');
@@ -437,7 +439,7 @@ const TradeDetails = ({ tradeId, onClose }) => {
         setClaimRef(null);
         fetchTradeDetails();
       } else {
-        toast.error(result.Err || t('errors.claimFailed'));
+        toast.error(formatCanisterError(result.Err) || t('errors.claimFailed'));
       }
     } catch (error) {
       console.error('Error claiming USDC:', error);
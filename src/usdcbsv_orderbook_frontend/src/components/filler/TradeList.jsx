@@ -6,6 +6,7 @@ import { useSDK } from '../../contexts/SDKProvider';
 import { useTheme } from '../../contexts/ThemeContext';
 import { Button, Card, Loader, StatusBadge } from '../common';
 import { toast } from 'react-hot-toast';
+import { formatCanisterError } from '../../utils/canisterError';
 
 const ITEMS_PER_PAGE = 20;
 
@@ -27,7 +28,9 @@ const TradeList = ({ onTradeSelect }) => {
       const limit = ITEMS_PER_PAGE;
       // Exclude concluded trades - only show trades that need action
       // Exclude: WithdrawalConfirmed, Cancelled, PenaltyApplied
-      const result = await actor.get_my_trades_paginated(BigInt(offset), BigInt(limit), []);
+      const response = await actor.get_my_trades_paginated(BigInt(offset), BigInt(limit), []);
+      if ('Err' in response) throw new Error(formatCanisterError(response.Err));
+      const result = response.Ok;
       
       // Filter out concluded trades on the frontend
       const activeTrades = result.trades.filter(trade => {
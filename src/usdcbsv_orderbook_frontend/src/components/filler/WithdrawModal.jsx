@@ -5,6 +5,7 @@ import { Loader2 } from 'lucide-react';
 import { useSDK } from '../../contexts/SDKProvider';
 import { useTheme } from '../../contexts/ThemeContext';
 import { Button, Modal, Input } from '../common';
+import { formatCanisterError } from '../../utils/canisterError';
 
 const CKUSDC_FEE = 10_000; // ckUSDC transfer fee is 10,000 e6s (0.01 ckUSDC)
 
@@ -52,7 +53,7 @@ const WithdrawModal = ({ isOpen, onClose, onWithdrawComplete, subaccountAddress,
         onWithdrawComplete?.();
         onClose();
       } else {
-        toast.error(result.Err || t('errors.withdrawFailed'));
+        toast.error(formatCanisterError(result.Err) || t('errors.withdrawFailed'));
       }
     } catch (error) {
       console.error('Error withdrawing:', error);
@@ -7,6 +7,7 @@ import { useSDK } from '../../contexts/SDKProvider';
 import { useTheme } from '../../contexts/ThemeContext';
 import { Button, Input, Select, Card } from '../common';
 import { MIN_CHUNK_SIZE_USD, MAX_CHUNKS_ALLOWED, MAX_ORDER_SIZE_USD, MAX_MAKER_TOTAL_ORDERS_USD, MAKER_FEE_PERCENT, ACTIVATION_FEE_PERCENT, FILLER_INCENTIVE_PERCENT, BSV_PRICE_BUFFER_PERCENT, CKUSDC_TRANSFER_FEE_USD } from '../../config';
+import { formatCanisterError } from '../../utils/canisterError';
 
 const CreateOrderForm = ({ onOrderCreated }) => {
   const { t } = useTranslation(['topup', 'common', 'wallet']);
@@ -243,7 +244,9 @@ const CreateOrderForm = ({ onOrderCreated }) => {
       const result = await actor.create_order(
         amountUsd,
         maxPrice,
-        formData.bsvAddress
+        formData.bsvAddress,
+        [], // post_only
+        [] // book_id - the default ckUSDC book
       );
       toast.dismiss();
       
@@ -278,7 +281,7 @@ const CreateOrderForm = ({ onOrderCreated }) => {
         // Order creation failed after transfer
         // Funds are safe in user's canister subaccount
         toast.error(
-          `${t('topup:form.createFailed')}: ${result.Err}\n\n` +
+          `${t('topup:form.createFailed')}: ${formatCanisterError(result.Err)}\n\n` +
           `Your ${totalRequired.toFixed(6)} ckUSDC has been transferred to your canister account and is safe. ` +
           `You can see it in the Trader page balance.`,
           { duration: 8000 }
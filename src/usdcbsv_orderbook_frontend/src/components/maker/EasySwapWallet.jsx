@@ -20,6 +20,7 @@ import {
   withdrawCkUSDCToEth
 } from '../../utils/wallet';
 import { CK_ETH_LEDGER, CK_USDC_LEDGER, CK_USDC_MINTER, MIN_CHUNK_SIZE_USD, MAX_CHUNKS_ALLOWED, MAX_ORDER_SIZE_USD, BSV_PRICE_BUFFER_PERCENT, ACTIVATION_FEE_PERCENT, FILLER_INCENTIVE_PERCENT, MAKER_FEE_PERCENT } from '../../config';
+import { formatCanisterError } from '../../utils/canisterError';
 
 // Contract addresses
 const USDC_CONTRACT = '0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48'; // Mainnet USDC
@@ -582,7 +583,8 @@ const EasySwapWallet = ({ prefilledAmount = null, onConversionComplete = null, s
         gasFeeUsdcE6,
         treasuryFeeE6,
         gasAmountWei,
-        withdrawAddress
+        withdrawAddress,
+        [] // Default route (Ethereum)
       );
       
       console.log('Withdrawal result:', result);
@@ -595,7 +597,7 @@ const EasySwapWallet = ({ prefilledAmount = null, onConversionComplete = null, s
         setSwapAmount('');
         setTimeout(fetchBalances, 5000);
       } else {
-        const errorMsg = result.Err || 'Unknown error';
+        const errorMsg = formatCanisterError(result.Err) || 'Unknown error';
         setWithdrawStatus({ type: 'error', message: `Withdrawal failed: ${errorMsg}` });
       }
     } catch (error) {
@@ -903,14 +905,16 @@ const EasySwapWallet = ({ prefilledAmount = null, onConversionComplete = null, s
       const createResult = await actor.create_order(
         amount,
         maxPrice,
-        initiatorAddress // BSV address from Metanet wallet
+        initiatorAddress, // BSV address from Metanet wallet
+        [], // post_only
+        [] // book_id - the default ckUSDC book
       );
       
       if (!('Ok' in createResult)) {
         // Order creation failed after transfer
         // Funds are safe in user's canister subaccount
         throw new Error(
-          `Order creation failed: ${createResult.Err}\n\n` +
+          `Order creation failed: ${formatCanisterError(createResult.Err)}\n\n` +
           `Your ${totalRequired.toFixed(6)} ckUSDC has been transferred to your canister account and is safe. ` +
           `You can see it in the Trader page balance.`
         );
@@ -25,6 +25,7 @@ import { toast } from 'react-hot-toast';
 import RefundModal from './RefundModal';
 import OrderFundingStepper from './OrderFundingStepper';
 import { MAKER_FEE_PERCENT, ACTIVATION_FEE_PERCENT, FILLER_INCENTIVE_PERCENT } from '../../config';
+import { formatCanisterError } from '../../utils/canisterError';
 
 /**
  * CRITICAL: TWO SEPARATE BALANCES
@@ -195,7 +196,7 @@ const OrderDetails = ({ orderId, onBack, onClose }) => {
         // Refresh to get updated order status
         await fetchOrderData(false);
       } else {
-        const error = result.Err;
+        const error = formatCanisterError(result.Err);
         console.error('Auto-activation failed:', error);
         toast.error(error);
       }
@@ -227,7 +228,7 @@ const OrderDetails = ({ orderId, onBack, onClose }) => {
         toast.success('Order activated successfully!');
         fetchOrderData(false);
       } else {
-        const errorMsg = result.Err || 'Activation failed';
+        const errorMsg = formatCanisterError(result.Err) || 'Activation failed';
         console.error('❌ Activation error:', errorMsg);
         toast.error(errorMsg);
       }
@@ -249,7 +250,7 @@ const OrderDetails = ({ orderId, onBack, onClose }) => {
         // Navigate to top-up page after cancellation
         navigate('/top-up');
       } else {
-        toast.error(result.Err || t('errors.cancelFailed'));
+        toast.error(formatCanisterError(result.Err) || t('errors.cancelFailed'));
       }
     } catch (error) {
       console.error('Error cancelling order:', error);
@@ -276,7 +277,7 @@ const OrderDetails = ({ orderId, onBack, onClose }) => {
         setNewMaxPrice('');
         fetchOrderData(false);
       } else {
-        toast.error(result.Err || t('details.updateMaxPriceFailed'));
+        toast.error(formatCanisterError(result.Err) || t('details.updateMaxPriceFailed'));
       }
     } catch (error) {
       console.error('Error updating max price:', error);
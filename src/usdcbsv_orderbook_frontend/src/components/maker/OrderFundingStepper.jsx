@@ -28,6 +28,7 @@ import {
 } from '../../utils/wallet';
 import { CK_USDC_LEDGER, MAKER_FEE_PERCENT, ACTIVATION_FEE_PERCENT, FILLER_INCENTIVE_PERCENT } from '../../config';
 import { IcrcLedgerCanister } from '@dfinity/ledger-icrc';
+import { formatCanisterError } from '../../utils/canisterError';
 
 const OrderFundingStepper = ({ order, onFundingComplete, onRefresh, orderDepositBalance }) => {
   const { t } = useTranslation(['maker', 'common']);
@@ -688,7 +689,7 @@ const OrderFundingStepper = ({ order, onFundingComplete, onRefresh, orderDeposit
           onFundingComplete();
         }
       } else {
-        const errorMsg = result.Err || 'Failed to confirm deposit';
+        const errorMsg = formatCanisterError(result.Err) || 'Failed to confirm deposit';
         console.error('❌ Confirmation error:', errorMsg);
         toast.error(errorMsg, { id: 'confirm' });
         throw new Error(errorMsg);
@@ -7,6 +7,7 @@ import { useTheme } from '../../contexts/ThemeContext';
 import { Button, Card, Loader, StatusBadge } from '../common';
 import { toast } from 'react-hot-toast';
 import { MIN_CHUNK_SIZE_CENTS } from '../../config';
+import { formatCanisterError } from '../../utils/canisterError';
 
 const ITEMS_PER_PAGE = 20;
 
@@ -28,7 +29,9 @@ const OrderList = ({ onOrderSelect }) => {
       const offset = BigInt((page - 1) * ITEMS_PER_PAGE);
       const limit = BigInt(ITEMS_PER_PAGE);
       // Use get_my_active_orders_paginated - shows orders with Available, Idle, or Locked chunks
-      const result = await actor.get_my_active_orders_paginated(offset, limit);
+      const response = await actor.get_my_active_orders_paginated(offset, limit);
+      if ('Err' in response) throw new Error(formatCanisterError(response.Err));
+      const result = response.Ok;
       
       setCurrentOrders(result.orders);
       setTotalOrders(Number(result.total));
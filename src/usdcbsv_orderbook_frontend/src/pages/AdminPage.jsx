@@ -7,6 +7,7 @@ import { useTheme } from '../contexts/ThemeContext';
 import { Card, Button } from '../components/common';
 import TreasuryCard from '../components/TreasuryCard';
 import { ADMIN_PRINCIPAL } from '../config';
+import { formatCanisterError } from '../utils/canisterError';
 
 const AdminPage = () => {
   const { t } = useTranslation(['common']);
@@ -65,7 +66,8 @@ const AdminPage = () => {
         const offset = BigInt(page * PAGE_SIZE);
         const limit = BigInt(PAGE_SIZE);
         const events = await actor.get_admin_events_paginated(offset, limit);
-        setAdminEvents(events);
+        if ('Err' in events) throw new Error(formatCanisterError(events.Err));
+        setAdminEvents(events.Ok);
       } catch (error) {
         console.error('Error fetching admin events:', error);
       } finally {
@@ -88,7 +90,8 @@ const AdminPage = () => {
         const offset = BigInt(page * PAGE_SIZE);
         const limit = BigInt(PAGE_SIZE);
         const events = await actor.get_admin_events_paginated(offset, limit);
-        setAdminEvents(events);
+        if ('Err' in events) throw new Error(formatCanisterError(events.Err));
+        setAdminEvents(events.Ok);
       } catch (e) {
         console.error('Failed to fetch page', e);
       } finally {
@@ -122,12 +125,12 @@ const AdminPage = () => {
 
     setWithdrawing(true);
     try {
-      const result = await actor.admin_withdraw_ckusdc_treasury();
+      const result = await actor.admin_withdraw_ckusdc_treasury([], [], []); // Everything withdrawable, to the admin
       
       if ('Ok' in result) {
         toast.success(`Treasury withdrawn! Block index: ${result.Ok.toString()}`);
       } else {
-        toast.error(result.Err || 'Withdrawal failed');
+        toast.error(formatCanisterError(result.Err) || 'Withdrawal failed');
       }
     } catch (error) {
       console.error('Withdraw error:', error);
@@ -153,7 +156,7 @@ const AdminPage = () => {
         setShowConfirmDialog(false);
         setConfirmAction(null);
       } else {
-        toast.error(result.Err || 'Toggle failed');
+        toast.error(formatCanisterError(result.Err) || 'Toggle failed');
       }
     } catch (error) {
       console.error('Toggle error:', error);
@@ -182,8 +185,8 @@ const AdminPage = () => {
         setResyncResult({ success: true, message: result.Ok });
         toast.success('Block resync completed');
       } else {
-        setResyncResult({ success: false, message: result.Err });
-        toast.error(result.Err || 'Resync failed');
+        setResyncResult({ success: false, message: formatCanisterError(result.Err) });
+        toast.error(formatCanisterError(result.Err) || 'Resync failed');
       }
     } catch (error) {
       console.error('Force resync error:', error);
@@ -237,7 +240,7 @@ const AdminPage = () => {
 
   const formatEventType = (eventType) => {
     if ('PenaltyApplied' in eventType) {
-      const { trade_id, order_id, filler, order_maker, penalty_amount, bsv_tx_hex, reason } = eventType.PenaltyApplied;
+      const { trade_id, order_id, filler, order_maker, penalty_amount, bsv_txid, reason } = eventType.PenaltyApplied;
       const details = [
         `Trade #${trade_id.toString()}`,
       ];
@@ -248,8 +251,8 @@ const AdminPage = () => {
       if (order_maker && order_maker.length > 0) {
         details.push(`Maker: ${order_maker[0].toString()}`);
       }
-      if (bsv_tx_hex && bsv_tx_hex.length > 0) {
-        details.push(`BSV TX: ${bsv_tx_hex[0].substring(0, 16)}...`);
+      if (bsv_txid && bsv_txid.length > 0) {
+        details.push(`BSV TX: ${bsv_txid[0].substring(0, 16)}...`);
       }
       details.push(`Amount: $${penalty_amount.toFixed(2)}`);
       details.push(`Reason: ${reason}`);
@@ -299,6 +302,54 @@ const AdminPage = () => {
         title: '🚨 New Orders Disabled',
         details: ['System is NOT accepting new orders', 'Existing orders and trades continue normally']
       };
+    } else if ('CircuitBreakerTripped' in eventType) {
+      const { low_price, high_price, move_bps } = eventType.CircuitBreakerTripped;
+      return {
+        title: '🛑 Circuit Breaker Tripped',
+        details: [
+          `BSV range: $${low_price.toFixed(2)} - $${high_price.toFixed(2)}`,
+          `Move: ${(Number(move_bps) / 100).toFixed(2)}%`,
+          'New trades and reactivations paused'
+        ]
+      };
+    } else if ('CircuitBreakerReset' in eventType) {
+      const { tripped_at } = eventType.CircuitBreakerReset;
+      return {
+        title: '🟢 Circuit Breaker Reset',
+        details: [
+          `Tripped at: ${new Date(Number(tripped_at) / 1_000_000).toLocaleString()}`,
+          'Trading resumed'
+        ]
+      };
+    } else if ('CkEthBalanceLow' in eventType) {
+      const { balance_wei, low_watermark_wei } = eventType.CkEthBalanceLow;
+      return {
+        title: '⛽ ckETH Gas Treasury Low',
+        details: [
+          `Balance: ${(Number(balance_wei) / 1e18).toFixed(6)} ETH`,
+          `Watermark: ${(Number(low_watermark_wei) / 1e18).toFixed(6)} ETH`
+        ]
+      };
+    } else if ('CkEthBalanceRestored' in eventType) {
+      const { balance_wei } = eventType.CkEthBalanceRestored;
+      return {
+        title: '⛽ ckETH Gas Treasury Refilled',
+        details: [
+          `Balance: ${(Number(balance_wei) / 1e18).toFixed(6)} ETH`
+        ]
+      };
+    } else if ('OrderBsvAddressUpdated' in eventType) {
+      const { order_id, maker, old_address, new_address, chunks_updated } = eventType.OrderBsvAddressUpdated;
+      return {
+        title: '📝 Order BSV Address Changed',
+        details: [
+          `Order: #${order_id}`,
+          `Maker: ${maker.toString()}`,
+          `From: ${old_address}`,
+          `To: ${new_address}`,
+          `Chunks updated: ${chunks_updated}`
+        ]
+      };
     }
     return { title: 'Unknown Event', details: [] };
   };
@@ -1,6 +1,7 @@
 import React, { useState, useEffect } from 'react';
 import { useSDK } from '../contexts/SDKProvider';
 import { toast } from 'react-hot-toast';
+import { formatCanisterError } from '../utils/canisterError';
 
 const AuditReportPage = () => {
   const { actor, isAuthenticated, sendCommand } = useSDK();
@@ -58,7 +59,7 @@ const AuditReportPage = () => {
       if ('Ok' in result) {
         setOrdersData(result.Ok);
       } else {
-        setError(result.Err);
+        setError(formatCanisterError(result.Err));
       }
     } catch (err) {
       setError(err.message || 'Failed to fetch orders audit data');
@@ -89,7 +90,7 @@ const AuditReportPage = () => {
       if ('Ok' in result) {
         setTradesData(result.Ok);
       } else {
-        setError(result.Err);
+        setError(formatCanisterError(result.Err));
       }
     } catch (err) {
       setError(err.message || 'Failed to fetch trades audit data');
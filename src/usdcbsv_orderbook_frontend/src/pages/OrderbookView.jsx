@@ -6,6 +6,7 @@ import { useTheme } from '../contexts/ThemeContext';
 import { Button, Loader } from '../components/common';
 import { toast } from 'react-hot-toast';
 import { MIN_CHUNK_SIZE_USD, MAX_ORDERBOOK_USD_LIMIT } from '../config';
+import { formatCanisterError } from '../utils/canisterError';
 
 const CHUNKS_PER_PAGE = 100;
 
@@ -34,13 +35,14 @@ const OrderbookView = () => {
       const limit = CHUNKS_PER_PAGE;
       
       const [chunksResult, statsResult, priceResult] = await Promise.all([
-        actor.get_active_chunks_paginated(BigInt(offset), BigInt(limit)),
+        actor.get_active_chunks_paginated(BigInt(offset), BigInt(limit), []),
         actor.get_orderbook_stats(),
         actor.get_bsv_price()
       ]);
       
-      setChunks(chunksResult.chunks);
-      setTotalChunks(Number(chunksResult.total));
+      if ('Err' in chunksResult) throw new Error(formatCanisterError(chunksResult.Err));
+      setChunks(chunksResult.Ok.chunks);
+      setTotalChunks(Number(chunksResult.Ok.total));
       setCurrentPage(page);
       setStats(statsResult);
       
@@ -7,6 +7,7 @@ import { useTheme } from '../contexts/ThemeContext';
 import { Button, Card, Loader, StatusBadge } from '../components/common';
 import { toast } from 'react-hot-toast';
 import { MIN_CHUNK_SIZE_CENTS } from '../config';
+import { formatCanisterError } from '../utils/canisterError';
 
 const ITEMS_PER_PAGE = 20;
 const STORAGE_KEY = 'pastOrdersState';
@@ -94,7 +95,9 @@ const PastOrdersPage = () => {
         ]];
       }
       
-      const result = await actor.get_my_orders_paginated(offset, limit, statusFilter);
+      const response = await actor.get_my_orders_paginated(offset, limit, statusFilter);
+      if ('Err' in response) throw new Error(formatCanisterError(response.Err));
+      const result = response.Ok;
       setCurrentOrders(result.orders);
       setTotalCount(Number(result.total));
     } catch (error) {
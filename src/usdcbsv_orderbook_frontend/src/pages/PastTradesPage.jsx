@@ -7,6 +7,7 @@ import { useTheme } from '../contexts/ThemeContext';
 import { Button, Card, Loader, StatusBadge } from '../components/common';
 import { TradeDetails } from '../components/filler';
 import { toast } from 'react-hot-toast';
+import { formatCanisterError } from '../utils/canisterError';
 
 const ITEMS_PER_PAGE = 20;
 const STORAGE_KEY = 'pastTradesState';
@@ -98,7 +99,9 @@ const PastTradesPage = () => {
         ]];
       }
       
-      const result = await actor.get_my_trades_paginated(BigInt(offset), BigInt(limit), statusFilter);
+      const response = await actor.get_my_trades_paginated(BigInt(offset), BigInt(limit), statusFilter);
+      if ('Err' in response) throw new Error(formatCanisterError(response.Err));
+      const result = response.Ok;
       setTrades(result.trades);
       setTotalTrades(Number(result.total));
       setCurrentPage(page);
@@ -0,0 +1,14 @@
+/**
+ * Convert an EasySwapError returned by the backend canister to a readable message
+ * Errors are candid variants like { InsufficientFunds: { reason: '...' } }
+ */
+export function formatCanisterError(error) {
+  if (!error) {
+    return '';
+  }
+  if (typeof error === 'string') {
+    return error;
+  }
+  const kind = Object.keys(error)[0];
+  return error[kind]?.reason || kind;
+}